@@ -4,11 +4,12 @@
 //! language with C FFI support. No JSON serialization is used for search or grep
 //! results — callers read struct fields directly.
 
-use std::ffi::{CString, c_char, c_void};
+use std::borrow::Cow;
+use std::ffi::{CStr, CString, c_char, c_void};
 use std::ptr;
 
-use fff::git::format_git_status;
-use fff::{FileItem, GrepMatch, GrepResult, Location, Score, SearchResult};
+use fff::git::{format_git_status, format_git_status_short};
+use fff::{ExportedMatch, FileItem, GrepMatch, GrepResult, Location, Score, SearchResult};
 
 // ---------------------------------------------------------------------------
 // Helpers
@@ -74,14 +75,16 @@ pub struct FffFileItem {
     pub modification_frecency_score: i64,
     pub total_frecency_score: i64,
     pub is_binary: bool,
+    /// `git status --short` style indicator (`M`, `A`, `??`, `R`, ...), empty when clean.
+    pub git_status_short: *mut c_char,
 }
 
 impl From<&FileItem> for FffFileItem {
     fn from(item: &FileItem) -> Self {
         FffFileItem {
-            path: cstring_new(&item.path.to_string_lossy()),
-            relative_path: cstring_new(&item.relative_path),
-            file_name: cstring_new(&item.file_name),
+            path: cstring_new(&item.path().to_string_lossy()),
+            relative_path: cstring_new(item.relative_path()),
+            file_name: cstring_new(item.file_name()),
             git_status: cstring_new(format_git_status(item.git_status)),
             size: item.size,
             modified: item.modified,
@@ -89,6 +92,7 @@ impl From<&FileItem> for FffFileItem {
             modification_frecency_score: item.modification_frecency_score as i64,
             total_frecency_score: item.total_frecency_score as i64,
             is_binary: item.is_binary,
+            git_status_short: cstring_new(format_git_status_short(item.git_status)),
         }
     }
 }
@@ -110,6 +114,9 @@ impl FffFileItem {
             if !self.git_status.is_null() {
                 drop(CString::from_raw(self.git_status));
             }
+            if !self.git_status_short.is_null() {
+                drop(CString::from_raw(self.git_status_short));
+            }
         }
     }
 }
@@ -226,6 +233,14 @@ pub struct FffSearchResult {
     pub total_files: u32,
     /// Location parsed from the query string.
     pub location: FffLocation,
+    /// Number of matches containing every query character in order.
+    pub exact_matched: u32,
+    /// Number of matches that needed typo tolerance.
+    pub typo_matched: u32,
+    /// Typo budget the matcher ran with (0 when no fuzzy matching happened).
+    pub max_typos: u32,
+    /// "Did you mean" query when nothing matched, null otherwise.
+    pub suggestion: *mut c_char,
 }
 
 impl FffSearchResult {
@@ -245,6 +260,13 @@ impl FffSearchResult {
             total_matched: result.total_matched as u32,
             total_files: result.total_files as u32,
             location: FffLocation::from(result.location.as_ref()),
+            exact_matched: result.match_stats.exact_matched as u32,
+            typo_matched: result.match_stats.typo_matched as u32,
+            max_typos: result.match_stats.max_typos as u32,
+            suggestion: result
+                .suggestion
+                .as_deref()
+                .map_or(std::ptr::null_mut(), cstring_new),
         }))
     }
 }
@@ -260,6 +282,15 @@ pub struct FffMatchRange {
     pub end: u32,
 }
 
+/// The bytes of a matched line a regex capture group matched.
+#[repr(C)]
+pub struct FffCaptureRange {
+    /// 1-based index of the group in the pattern.
+    pub group: u32,
+    pub start: u32,
+    pub end: u32,
+}
+
 /// A single grep match with file and line information.
 ///
 /// All string fields and arrays are heap-allocated. Free the parent
@@ -273,6 +304,7 @@ pub struct FffGrepMatch {
     pub git_status: *mut c_char,
     pub line_content: *mut c_char,
     pub match_ranges: *mut FffMatchRange,
+    pub capture_ranges: *mut FffCaptureRange,
     pub context_before: *mut *mut c_char,
     pub context_after: *mut *mut c_char,
     // -- 8-byte numeric fields --
@@ -286,8 +318,11 @@ pub struct FffGrepMatch {
     // -- 4-byte fields --
     pub col: u32,
     pub match_ranges_count: u32,
+    pub capture_ranges_count: u32,
     pub context_before_count: u32,
     pub context_after_count: u32,
+    /// Matches with the same line folded into this one, see `dedupe_lines`.
+    pub duplicates: u32,
     // -- 2-byte fields --
     pub fuzzy_score: u16,
     // -- 1-byte fields --
@@ -304,6 +339,16 @@ impl FffGrepMatch {
             .map(|&(start, end)| FffMatchRange { start, end })
             .collect();
         let (match_ranges, match_ranges_count) = vec_to_raw(ranges);
+        let captures: Vec<FffCaptureRange> = m
+            .captures
+            .iter()
+            .map(|c| FffCaptureRange {
+                group: c.group,
+                start: c.start,
+                end: c.end,
+            })
+            .collect();
+        let (capture_ranges, capture_ranges_count) = vec_to_raw(captures);
         let (context_before, context_before_count) = strings_to_raw(&m.context_before);
         let (context_after, context_after_count) = strings_to_raw(&m.context_after);
         let (has_fuzzy_score, fuzzy_score) = match m.fuzzy_score {
@@ -312,12 +357,13 @@ impl FffGrepMatch {
         };
 
         FffGrepMatch {
-            path: cstring_new(&file.path.to_string_lossy()),
-            relative_path: cstring_new(&file.relative_path),
-            file_name: cstring_new(&file.file_name),
+            path: cstring_new(&file.path().to_string_lossy()),
+            relative_path: cstring_new(file.relative_path()),
+            file_name: cstring_new(file.file_name()),
             git_status: cstring_new(format_git_status(file.git_status)),
             line_content: cstring_new(&m.line_content),
             match_ranges,
+            capture_ranges,
             context_before,
             context_after,
             size: file.size,
@@ -329,8 +375,10 @@ impl FffGrepMatch {
             byte_offset: m.byte_offset,
             col: m.col as u32,
             match_ranges_count,
+            capture_ranges_count,
             context_before_count,
             context_after_count,
+            duplicates: m.duplicates,
             fuzzy_score,
             has_fuzzy_score,
             is_binary: file.is_binary,
@@ -338,6 +386,31 @@ impl FffGrepMatch {
         }
     }
 
+    /// The match as the core exporters take it.
+    ///
+    /// ## Safety
+    /// The string pointers must be null or valid null-terminated strings.
+    pub unsafe fn as_exported(&self) -> ExportedMatch<'_> {
+        let string = |s: *mut c_char| {
+            if s.is_null() {
+                Cow::Borrowed("")
+            } else {
+                unsafe { CStr::from_ptr(s) }.to_string_lossy()
+            }
+        };
+        let first_range = (!self.match_ranges.is_null() && self.match_ranges_count > 0)
+            .then(|| unsafe { &*self.match_ranges });
+
+        ExportedMatch {
+            path: string(self.path),
+            relative_path: string(self.relative_path),
+            line_number: self.line_number,
+            col: self.col as usize,
+            end_col: first_range.map(|range| range.end as usize),
+            line_content: string(self.line_content),
+        }
+    }
+
     /// ## Safety
     /// All pointers must have been allocated by the corresponding `from_core`.
     pub unsafe fn free_fields(&mut self) {
@@ -364,6 +437,13 @@ impl FffGrepMatch {
                     self.match_ranges_count as usize,
                 ));
             }
+            if !self.capture_ranges.is_null() {
+                drop(Vec::from_raw_parts(
+                    self.capture_ranges,
+                    self.capture_ranges_count as usize,
+                    self.capture_ranges_count as usize,
+                ));
+            }
             free_cstring_array(self.context_before, self.context_before_count);
             free_cstring_array(self.context_after, self.context_after_count);
         }
@@ -391,6 +471,14 @@ pub struct FffGrepResult {
     pub next_file_offset: u32,
     /// Regex compilation error when falling back to literal matching. Null if none.
     pub regex_fallback_error: *mut c_char,
+    /// Token to pass as `page_token` for the next page. Null on the last page.
+    pub next_page: *mut c_char,
+    /// Pointer to a heap-allocated array of `FffFileItem` (length =
+    /// `file_entries_count`): the files an empty query lists, which has no
+    /// matches.
+    pub file_entries: *mut FffFileItem,
+    /// Number of files in the `file_entries` array.
+    pub file_entries_count: u32,
 }
 
 impl FffGrepResult {
@@ -405,6 +493,12 @@ impl FffGrepResult {
             })
             .collect();
         let (items_ptr, count) = vec_to_raw(items);
+        let file_entries: Vec<FffFileItem> = result
+            .file_entries
+            .iter()
+            .map(|&file| FffFileItem::from(file))
+            .collect();
+        let (file_entries, file_entries_count) = vec_to_raw(file_entries);
 
         Box::into_raw(Box::new(FffGrepResult {
             items: items_ptr,
@@ -418,6 +512,12 @@ impl FffGrepResult {
                 Some(e) => cstring_new(e),
                 None => ptr::null_mut(),
             },
+            next_page: match result.next_page {
+                Some(token) => cstring_new(&token.to_string()),
+                None => ptr::null_mut(),
+            },
+            file_entries,
+            file_entries_count,
         }))
     }
 }
@@ -432,16 +532,22 @@ impl FffGrepResult {
 /// |----------------------------|---------------|-------------------------------|
 /// | `fff_create_instance`      | `handle`      | opaque instance pointer       |
 /// | `fff_search`               | `handle`      | `*mut FffSearchResult`        |
+/// | `fff_search_json`          | `handle`      | `*mut FffSearchResult`        |
 /// | `fff_live_grep`            | `handle`      | `*mut FffGrepResult`          |
+/// | `fff_live_grep_json`       | `handle`      | `*mut FffGrepResult`          |
 /// | `fff_multi_grep`           | `handle`      | `*mut FffGrepResult`          |
 /// | `fff_get_scan_progress`    | `handle`      | `*mut FffScanProgress`        |
 /// | `fff_health_check`         | `handle`      | `*mut c_char` (JSON string)   |
 /// | `fff_get_historical_query` | `handle`      | `*mut c_char` (string or null)|
+/// | `fff_apply_batch`          | `handle`      | `*mut c_char` (JSON string)   |
+/// | `fff_undo_last_file_op`    | `handle`      | `*mut c_char` (JSON or null)  |
 /// | `fff_wait_for_scan`        | `int_value`   | 1 = completed, 0 = timed out  |
 /// | `fff_track_query`          | `int_value`   | 1 = success, 0 = failure      |
 /// | `fff_refresh_git_status`   | `int_value`   | number of files updated       |
+/// | `fff_set_external_scores`  | `int_value`   | number of files with a score  |
 /// | `fff_scan_files`           | (none)        | success flag only             |
 /// | `fff_restart_index`        | (none)        | success flag only             |
+/// | `fff_set_use_os_trash`     | (none)        | success flag only             |
 ///
 /// On failure, `success` is false and `error` contains the message.
 ///
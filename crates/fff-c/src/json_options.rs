@@ -0,0 +1,79 @@
+//! Option structs accepted as JSON by the `*_json` entry points.
+//!
+//! Every field is optional and unknown fields are ignored, so a binding
+//! written against an older or newer version of this library keeps working:
+//! options it doesn't know about get their defaults, options this library
+//! doesn't know about are dropped.
+
+use serde::Deserialize;
+
+/// Options of `fff_search_json`. Zero values mean "use default" like the
+/// positional arguments of `fff_search`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub current_file: Option<String>,
+    pub max_threads: u32,
+    pub page_index: u32,
+    pub page_size: u32,
+    pub combo_boost_multiplier: i32,
+    pub min_combo_count: u32,
+    /// Query length from which a prefix of a historical query gets its
+    /// combo boost, 0 keeps combos to exact queries.
+    pub min_combo_prefix_len: u32,
+    pub max_typos: Option<u16>,
+    pub adaptive_typos_threshold: u32,
+    pub search_root: Option<String>,
+}
+
+/// Options of `fff_live_grep_json`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct GrepOptions {
+    pub mode: GrepMode,
+    pub max_file_size: u64,
+    pub max_matches_per_file: u32,
+    pub smart_case: Option<bool>,
+    pub file_offset: u32,
+    pub page_limit: u32,
+    pub time_budget_ms: u64,
+    pub before_context: u32,
+    pub after_context: u32,
+    pub classify_definitions: bool,
+    pub file_order: Option<String>,
+    pub scope: Option<String>,
+    pub changed_only: bool,
+    pub page_token: Option<String>,
+    pub exact_page_limit: bool,
+    pub dedupe_lines: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrepMode {
+    #[default]
+    Plain,
+    Regex,
+    Fuzzy,
+    /// Modes added after this version fall back to plain text.
+    #[serde(other)]
+    Unknown,
+}
+
+impl From<GrepMode> for fff::GrepMode {
+    fn from(mode: GrepMode) -> Self {
+        match mode {
+            GrepMode::Regex => fff::GrepMode::Regex,
+            GrepMode::Fuzzy => fff::GrepMode::Fuzzy,
+            GrepMode::Plain | GrepMode::Unknown => fff::GrepMode::PlainText,
+        }
+    }
+}
+
+/// Parse an options object. A missing or empty string gives the defaults.
+pub fn parse<T: Default + for<'de> Deserialize<'de>>(json: Option<&str>) -> Result<T, String> {
+    match json.map(str::trim).filter(|json| !json.is_empty()) {
+        Some(json) => serde_json::from_str(json).map_err(|e| format!("Invalid options JSON: {e}")),
+        None => Ok(T::default()),
+    }
+}
@@ -21,20 +21,41 @@
 //! * Numeric parameters: 0 means "use default" unless documented otherwise.
 //! * Grep mode (`u8`): 0 = plain text, 1 = regex, 2 = fuzzy.
 //! * Multi-grep patterns are passed as a single newline-separated (`\n`) string.
+//! * `*_json` functions take their options as a JSON object. Missing fields use
+//!   their defaults and unknown fields are ignored.
+//!
+//! # Versioning
+//!
+//! `fff_api_version` is bumped whenever an exported signature or `#[repr(C)]`
+//! layout changes incompatibly. Bindings should check it once after loading
+//! the library and use `fff_capabilities` to find out which optional parts of
+//! the API are available instead of comparing package versions.
+//!
+//! Version history:
+//!
+//! - 3: `FffGrepMatch` lost `is_file_entry`; an empty grep query lists its
+//!   files in the new `FffGrepResult.file_entries` instead of in `items`.
+//! - 4: `FffGrepResult` grew `next_page`, see [`FFF_CAP_GREP_PAGE_TOKEN`].
+//! - 5: `FffGrepMatch` grew `duplicates`, set by the `dedupe_lines` option.
+//! - 6: `FffGrepMatch` grew `capture_ranges` and `capture_ranges_count`.
+//! - 7: `fff_create_instance` takes `read_only` after `ai_mode`.
 
 use std::ffi::{CStr, CString, c_char, c_void};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
 use fff::shared::SharedQueryTracker;
 
 mod ffi_types;
+mod json_options;
 
 use fff::file_picker::FilePicker;
 use fff::frecency::FrecencyTracker;
 use fff::query_tracker::QueryTracker;
 use fff::{DbHealthChecker, FFFMode, FuzzySearchOptions, PaginationArgs, QueryParser};
-use fff::{SharedFrecency, SharedPicker};
+use fff::{FrecencyStats, GrepExportFormat, export_grep_matches};
+use fff::{SearchScratch, SharedFrecency, SharedPicker};
 use ffi_types::{
     FffFileItem, FffGrepMatch, FffGrepResult, FffResult, FffScanProgress, FffScore, FffSearchResult,
 };
@@ -47,6 +68,8 @@ struct FffInstance {
     picker: SharedPicker,
     frecency: SharedFrecency,
     query_tracker: SharedQueryTracker,
+    /// Vectors reused by the fuzzy searches of this instance
+    scratch: SearchScratch,
 }
 
 /// Helper to convert C string to Rust &str.
@@ -80,12 +103,12 @@ unsafe fn instance_ref<'a>(fff_handle: *mut c_void) -> Result<&'a FffInstance, *
     }
 }
 
-/// Decode a `u8` grep mode into the core enum.
-fn grep_mode_from_u8(mode: u8) -> fff::GrepMode {
+/// Decode a `u8` grep mode.
+fn grep_mode_from_u8(mode: u8) -> json_options::GrepMode {
     match mode {
-        1 => fff::GrepMode::Regex,
-        2 => fff::GrepMode::Fuzzy,
-        _ => fff::GrepMode::PlainText,
+        1 => json_options::GrepMode::Regex,
+        2 => json_options::GrepMode::Fuzzy,
+        _ => json_options::GrepMode::Plain,
     }
 }
 
@@ -102,6 +125,75 @@ fn default_i32(val: i32, default: i32) -> i32 {
     if val == 0 { default } else { val }
 }
 
+/// Version of the C API, see the crate docs.
+pub const FFF_API_VERSION: u32 = 7;
+
+/// `fff_multi_grep` is available.
+pub const FFF_CAP_MULTI_GREP: u64 = 1 << 0;
+/// `fff_search_json` and `fff_live_grep_json` are available.
+pub const FFF_CAP_JSON_OPTIONS: u64 = 1 << 1;
+/// `fff_apply_batch` and `fff_undo_last_file_op` are available.
+pub const FFF_CAP_FILE_OPS: u64 = 1 << 2;
+/// Deletes move files to the OS trash when enabled with `fff_set_use_os_trash`.
+pub const FFF_CAP_OS_TRASH: u64 = 1 << 3;
+/// Glob constraints are matched with zlob.
+pub const FFF_CAP_ZLOB: u64 = 1 << 4;
+/// `fff_export_grep_results` is available.
+pub const FFF_CAP_GREP_EXPORT: u64 = 1 << 5;
+/// `fff_get_frecency_stats` is available.
+pub const FFF_CAP_FRECENCY_STATS: u64 = 1 << 6;
+/// `fff_set_external_scores` is available.
+pub const FFF_CAP_EXTERNAL_SCORES: u64 = 1 << 7;
+/// `fff_default_db_paths` is available.
+pub const FFF_CAP_DEFAULT_DB_PATHS: u64 = 1 << 8;
+/// Grep results carry `next_page` and the JSON grep options take it back as
+/// `page_token`.
+pub const FFF_CAP_GREP_PAGE_TOKEN: u64 = 1 << 9;
+
+/// Return [`FFF_API_VERSION`].
+#[unsafe(no_mangle)]
+pub extern "C" fn fff_api_version() -> u32 {
+    FFF_API_VERSION
+}
+
+/// Return the `FFF_CAP_*` bits supported by this build of the library.
+#[unsafe(no_mangle)]
+pub extern "C" fn fff_capabilities() -> u64 {
+    let mut capabilities = FFF_CAP_MULTI_GREP
+        | FFF_CAP_JSON_OPTIONS
+        | FFF_CAP_FILE_OPS
+        | FFF_CAP_GREP_EXPORT
+        | FFF_CAP_FRECENCY_STATS
+        | FFF_CAP_EXTERNAL_SCORES
+        | FFF_CAP_DEFAULT_DB_PATHS
+        | FFF_CAP_GREP_PAGE_TOKEN;
+    if cfg!(feature = "os-trash") {
+        capabilities |= FFF_CAP_OS_TRASH;
+    }
+    if cfg!(feature = "zlob") {
+        capabilities |= FFF_CAP_ZLOB;
+    }
+    capabilities
+}
+
+/// Default database paths to pass to `fff_create_instance`, the ones fff.nvim
+/// uses when Neovim is installed. Their parent directories are created.
+///
+/// Returns a `{"frecency","history"}` JSON string (free with `fff_free_string`).
+#[unsafe(no_mangle)]
+pub extern "C" fn fff_default_db_paths() -> *mut FffResult {
+    match fff::default_db_paths() {
+        Ok(paths) => FffResult::ok_string(
+            &serde_json::json!({
+                "frecency": paths.frecency,
+                "history": paths.history,
+            })
+            .to_string(),
+        ),
+        Err(e) => FffResult::err(&format!("Failed to resolve default database paths: {}", e)),
+    }
+}
+
 /// Create a new file finder instance.
 ///
 /// Returns an opaque pointer that must be passed to all other `fff_*` calls
@@ -115,6 +207,8 @@ fn default_i32(val: i32, default: i32) -> i32 {
 /// * `use_unsafe_no_lock` – use MDB_NOLOCK for LMDB (useful in single-process setups)
 /// * `warmup_mmap_cache`  – pre-populate mmap caches after the initial scan
 /// * `ai_mode`            – enable AI-agent optimizations (auto-track frecency on modifications)
+/// * `read_only`          – never write: both databases are skipped, the file
+///   operations fail (for shared checkouts and CI)
 ///
 /// ## Safety
 /// String parameters must be valid null-terminated UTF-8 or NULL.
@@ -126,14 +220,19 @@ pub unsafe extern "C" fn fff_create_instance(
     use_unsafe_no_lock: bool,
     warmup_mmap_cache: bool,
     ai_mode: bool,
+    read_only: bool,
 ) -> *mut FffResult {
     let base_path_str = match unsafe { cstr_to_str(base_path) } {
         Some(s) if !s.is_empty() => s.to_string(),
         _ => return FffResult::err("base_path is null or empty"),
     };
 
-    let frecency_path = unsafe { optional_cstr(frecency_db_path) }.map(|s| s.to_string());
-    let history_path = unsafe { optional_cstr(history_db_path) }.map(|s| s.to_string());
+    let frecency_path = unsafe { optional_cstr(frecency_db_path) }
+        .filter(|_| !read_only)
+        .map(|s| s.to_string());
+    let history_path = unsafe { optional_cstr(history_db_path) }
+        .filter(|_| !read_only)
+        .map(|s| s.to_string());
 
     // Create shared state that background threads will write into.
     let shared_picker = SharedPicker::default();
@@ -151,7 +250,7 @@ pub unsafe extern "C" fn fff_create_instance(
                 if let Err(e) = shared_frecency.init(tracker) {
                     return FffResult::err(&format!("Failed to acquire frecency lock: {}", e));
                 }
-                let _ = shared_frecency.spawn_gc(frecency_path.clone(), use_unsafe_no_lock);
+                let _ = shared_frecency.spawn_gc(frecency_path.clone());
             }
             Err(e) => return FffResult::err(&format!("Failed to init frecency db: {}", e)),
         }
@@ -168,6 +267,7 @@ pub unsafe extern "C" fn fff_create_instance(
                 if let Err(e) = query_tracker.init(tracker) {
                     return FffResult::err(&format!("Failed to acquire query tracker lock: {}", e));
                 }
+                let _ = query_tracker.spawn_gc();
             }
             Err(e) => return FffResult::err(&format!("Failed to init query tracker db: {}", e)),
         }
@@ -188,6 +288,7 @@ pub unsafe extern "C" fn fff_create_instance(
             warmup_mmap_cache,
             mode,
             cache_budget: None,
+            read_only,
             ..Default::default()
         },
     ) {
@@ -198,6 +299,7 @@ pub unsafe extern "C" fn fff_create_instance(
         picker: shared_picker,
         frecency: shared_frecency,
         query_tracker,
+        scratch: SearchScratch::new(),
     });
 
     let fff_handle = Box::into_raw(instance) as *mut c_void;
@@ -242,10 +344,12 @@ pub unsafe extern "C" fn fff_destroy(fff_handle: *mut c_void) {
 /// * `page_size`               – results per page (0 = default 100)
 /// * `combo_boost_multiplier`  – score multiplier for combo matches (0 = default 100)
 /// * `min_combo_count`         – minimum combo count before boost applies (0 = default 3)
+/// * `search_root`             – only return files under this directory, absolute or relative
+///   to the indexed directory (NULL/empty for the whole index)
 ///
 /// ## Safety
 /// * `fff_handle` must be a valid instance pointer from `fff_create_instance`.
-/// * `query` and `current_file` must be valid null-terminated UTF-8 strings or NULL.
+/// * `query`, `current_file` and `search_root` must be valid null-terminated UTF-8 strings or NULL.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn fff_search(
     fff_handle: *mut c_void,
@@ -256,6 +360,7 @@ pub unsafe extern "C" fn fff_search(
     page_size: u32,
     combo_boost_multiplier: i32,
     min_combo_count: u32,
+    search_root: *const c_char,
 ) -> *mut FffResult {
     let inst = match unsafe { instance_ref(fff_handle) } {
         Ok(i) => i,
@@ -267,17 +372,63 @@ pub unsafe extern "C" fn fff_search(
         None => return FffResult::err("Query is null or invalid UTF-8"),
     };
 
-    let current_file_str = unsafe { optional_cstr(current_file) };
-    let page_size = default_u32(page_size, 100) as usize;
-    let min_combo_count = default_u32(min_combo_count, 3);
-    let combo_boost_multiplier = default_i32(combo_boost_multiplier, 100);
+    let options = json_options::SearchOptions {
+        current_file: unsafe { optional_cstr(current_file) }.map(str::to_string),
+        max_threads,
+        page_index,
+        page_size,
+        combo_boost_multiplier,
+        min_combo_count,
+        min_combo_prefix_len: 0,
+        max_typos: None,
+        adaptive_typos_threshold: 0,
+        search_root: unsafe { optional_cstr(search_root) }.map(str::to_string),
+    };
+    search(inst, query_str, &options)
+}
 
-    let picker_guard = match inst.picker.read() {
-        Ok(g) => g,
-        Err(e) => return FffResult::err(&format!("Failed to acquire file picker lock: {}", e)),
+/// Perform fuzzy search with options passed as a JSON object.
+///
+/// `options_json` takes the same settings as the arguments of `fff_search`
+/// plus `max_typos`, `adaptive_typos_threshold` and `min_combo_prefix_len`, e.g.
+/// `{"page_size": 50, "search_root": "src"}`. Every field is optional and
+/// unknown fields are ignored. NULL or an empty string uses the defaults.
+///
+/// ## Safety
+/// * `fff_handle` must be a valid instance pointer from `fff_create_instance`.
+/// * `query` and `options_json` must be valid null-terminated UTF-8 strings or NULL.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fff_search_json(
+    fff_handle: *mut c_void,
+    query: *const c_char,
+    options_json: *const c_char,
+) -> *mut FffResult {
+    let inst = match unsafe { instance_ref(fff_handle) } {
+        Ok(i) => i,
+        Err(e) => return e,
+    };
+
+    let query_str = match unsafe { cstr_to_str(query) } {
+        Some(s) => s,
+        None => return FffResult::err("Query is null or invalid UTF-8"),
     };
 
-    let picker = match picker_guard.as_ref() {
+    match json_options::parse(unsafe { cstr_to_str(options_json) }) {
+        Ok(options) => search(inst, query_str, &options),
+        Err(e) => FffResult::err(&e),
+    }
+}
+
+fn search(
+    inst: &FffInstance,
+    query: &str,
+    options: &json_options::SearchOptions,
+) -> *mut FffResult {
+    let page_size = default_u32(options.page_size, 100) as usize;
+    let min_combo_count = default_u32(options.min_combo_count, 3);
+    let combo_boost_multiplier = default_i32(options.combo_boost_multiplier, 100);
+
+    let picker = match inst.picker.snapshot() {
         Some(p) => p,
         None => {
             return FffResult::err("File picker not initialized. Call fff_create_instance first.");
@@ -292,25 +443,48 @@ pub unsafe extern "C" fn fff_search(
     let query_tracker_ref = qt_guard.as_ref();
 
     let parser = QueryParser::default();
-    let parsed = parser.parse(query_str);
+    let parsed = parser.parse(query);
 
-    let results = FilePicker::fuzzy_search(
-        picker.get_files(),
+    let mut results = FilePicker::fuzzy_search(
+        picker.files_for_query(&parsed),
         &parsed,
         query_tracker_ref,
         FuzzySearchOptions {
-            max_threads: max_threads as usize,
-            current_file: current_file_str,
+            max_threads: options.max_threads as usize,
+            current_file: options.current_file.as_deref().filter(|s| !s.is_empty()),
             project_path: Some(picker.base_path()),
             combo_boost_score_multiplier: combo_boost_multiplier,
             min_combo_count,
+            min_combo_prefix_len: options.min_combo_prefix_len as usize,
             pagination: PaginationArgs {
-                offset: page_index as usize,
+                offset: options.page_index as usize,
                 limit: page_size,
             },
+            max_typos: options.max_typos,
+            adaptive_typos_threshold: options.adaptive_typos_threshold as usize,
+            scoring_weights: Default::default(),
+            extension_multipliers: None,
+            project_profile: picker.project_profile(),
+            search_root: options
+                .search_root
+                .as_deref()
+                .filter(|s| !s.is_empty())
+                .map(Path::new),
+            facets: false,
+            min_score: None,
+            min_score_ratio: 0.0,
+            with_cursor: false,
+            cursor: None,
+            index_generation: picker.index_generation(),
+            working_set: None,
+            scratch: Some(&inst.scratch),
         },
     );
 
+    if results.total_matched == 0 {
+        results.suggestion = picker.suggest_query(query);
+    }
+
     let search_result = FffSearchResult::from_core(&results);
     FffResult::ok_handle(search_result as *mut c_void)
 }
@@ -360,12 +534,71 @@ pub unsafe extern "C" fn fff_live_grep(
         None => return FffResult::err("Query is null or invalid UTF-8"),
     };
 
-    let picker_guard = match inst.picker.read() {
-        Ok(g) => g,
-        Err(e) => return FffResult::err(&format!("Failed to acquire file picker lock: {}", e)),
+    let options = json_options::GrepOptions {
+        mode: grep_mode_from_u8(mode),
+        max_file_size,
+        max_matches_per_file,
+        smart_case: Some(smart_case),
+        file_offset,
+        page_limit,
+        time_budget_ms,
+        before_context,
+        after_context,
+        classify_definitions,
+        file_order: None,
+        scope: None,
+        changed_only: false,
+        page_token: None,
+        exact_page_limit: false,
+        dedupe_lines: false,
     };
+    live_grep(inst, query_str, &options)
+}
 
-    let picker = match picker_guard.as_ref() {
+/// Perform content search with options passed as a JSON object.
+///
+/// `options_json` takes the same settings as the arguments of `fff_live_grep`
+/// plus `file_order` (`"frecency"`, `"mtime"`, `"path"` or
+/// `"git_changed_first"`), `scope` (`"all"`, `"code"`, `"comments"` or
+/// `"strings"`), `changed_only`, which only greps the lines of uncommitted
+/// git hunks, `exact_page_limit`, which ends the page at `page_limit` matches
+/// even inside a file, `page_token`, the `next_page` of the previous page
+/// which replaces `file_offset`, and `dedupe_lines`, which keeps one match of
+/// the lines that read the same. `mode` is `"plain"`, `"regex"` or `"fuzzy"`, and
+/// `smart_case` defaults to true. Every field is optional and unknown fields
+/// or values are ignored. NULL or an empty string uses the defaults.
+///
+/// ## Safety
+/// * `fff_handle` must be a valid instance pointer from `fff_create_instance`.
+/// * `query` and `options_json` must be valid null-terminated UTF-8 strings or NULL.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fff_live_grep_json(
+    fff_handle: *mut c_void,
+    query: *const c_char,
+    options_json: *const c_char,
+) -> *mut FffResult {
+    let inst = match unsafe { instance_ref(fff_handle) } {
+        Ok(i) => i,
+        Err(e) => return e,
+    };
+
+    let query_str = match unsafe { cstr_to_str(query) } {
+        Some(s) => s,
+        None => return FffResult::err("Query is null or invalid UTF-8"),
+    };
+
+    match json_options::parse(unsafe { cstr_to_str(options_json) }) {
+        Ok(options) => live_grep(inst, query_str, &options),
+        Err(e) => FffResult::err(&e),
+    }
+}
+
+fn live_grep(
+    inst: &FffInstance,
+    query: &str,
+    options: &json_options::GrepOptions,
+) -> *mut FffResult {
+    let picker = match inst.picker.snapshot() {
         Some(p) => p,
         None => {
             return FffResult::err("File picker not initialized. Call fff_create_instance first.");
@@ -374,25 +607,62 @@ pub unsafe extern "C" fn fff_live_grep(
 
     let is_ai = picker.mode().is_ai();
     let parsed = if is_ai {
-        fff::QueryParser::new(fff_query_parser::AiGrepConfig).parse(query_str)
+        fff::QueryParser::new(fff_query_parser::AiGrepConfig).parse(query)
     } else {
-        fff::grep::parse_grep_query(query_str)
+        fff::grep::parse_grep_query(query)
     };
 
-    let options = fff::GrepSearchOptions {
-        max_file_size: default_u64(max_file_size, 10 * 1024 * 1024),
-        max_matches_per_file: max_matches_per_file as usize,
-        smart_case,
-        file_offset: file_offset as usize,
-        page_limit: default_u32(page_limit, 50) as usize,
-        mode: grep_mode_from_u8(mode),
-        time_budget_ms,
-        before_context: before_context as usize,
-        after_context: after_context as usize,
-        classify_definitions,
+    let changed_lines = if options.changed_only {
+        match picker.changed_lines() {
+            Ok(changed) => changed.map(Arc::new),
+            Err(e) => return FffResult::err(&format!("Failed to diff the work tree: {e}")),
+        }
+    } else {
+        None
     };
 
-    let result = picker.grep(&parsed, &options);
+    let page_token = match options
+        .page_token
+        .as_deref()
+        .map(str::parse::<fff::GrepPageToken>)
+    {
+        Some(Ok(token)) => Some(token),
+        Some(Err(e)) => return FffResult::err(&e),
+        None => None,
+    };
+
+    let mut grep_options = fff::GrepSearchOptions {
+        max_file_size: default_u64(options.max_file_size, 10 * 1024 * 1024),
+        max_matches_per_file: options.max_matches_per_file as usize,
+        smart_case: options.smart_case.unwrap_or(true),
+        file_offset: options.file_offset as usize,
+        page_limit: default_u32(options.page_limit, 50) as usize,
+        mode: options.mode.into(),
+        time_budget_ms: options.time_budget_ms,
+        before_context: options.before_context as usize,
+        after_context: options.after_context as usize,
+        classify_definitions: options.classify_definitions,
+        file_order: options
+            .file_order
+            .as_deref()
+            .and_then(fff::GrepFileOrder::from_name)
+            .unwrap_or_default(),
+        scope: options
+            .scope
+            .as_deref()
+            .and_then(fff::GrepScope::from_name)
+            .unwrap_or_default(),
+        changed_lines,
+        resume_after_line: 0,
+        exact_page_limit: options.exact_page_limit,
+        dedupe_lines: options.dedupe_lines,
+        history_boost: None,
+    };
+    if let Some(token) = page_token {
+        grep_options.resume_from(token);
+    }
+
+    let result = picker.grep(&parsed, &grep_options);
     let grep_result = FffGrepResult::from_core(&result);
     FffResult::ok_handle(grep_result as *mut c_void)
 }
@@ -452,12 +722,7 @@ pub unsafe extern "C" fn fff_multi_grep(
 
     let constraints_str = unsafe { optional_cstr(constraints) };
 
-    let picker_guard = match inst.picker.read() {
-        Ok(g) => g,
-        Err(e) => return FffResult::err(&format!("Failed to acquire file picker lock: {}", e)),
-    };
-
-    let picker = match picker_guard.as_ref() {
+    let picker = match inst.picker.snapshot() {
         Some(p) => p,
         None => {
             return FffResult::err("File picker not initialized. Call fff_create_instance first.");
@@ -491,6 +756,13 @@ pub unsafe extern "C" fn fff_multi_grep(
         before_context: before_context as usize,
         after_context: after_context as usize,
         classify_definitions,
+        file_order: Default::default(),
+        scope: Default::default(),
+        changed_lines: None,
+        resume_after_line: 0,
+        exact_page_limit: false,
+        dedupe_lines: false,
+        history_boost: None,
     };
 
     let result = fff::multi_grep_search(
@@ -516,17 +788,7 @@ pub unsafe extern "C" fn fff_scan_files(fff_handle: *mut c_void) -> *mut FffResu
         Err(e) => return e,
     };
 
-    let mut guard = match inst.picker.write() {
-        Ok(g) => g,
-        Err(e) => return FffResult::err(&format!("Failed to acquire file picker lock: {}", e)),
-    };
-
-    let picker = match guard.as_mut() {
-        Some(p) => p,
-        None => return FffResult::err("File picker not initialized"),
-    };
-
-    match picker.trigger_rescan(&inst.frecency) {
+    match inst.picker.trigger_rescan(&inst.frecency) {
         Ok(_) => FffResult::ok_empty(),
         Err(e) => FffResult::err(&format!("Failed to trigger rescan: {}", e)),
     }
@@ -648,13 +910,47 @@ pub unsafe extern "C" fn fff_restart_index(
         Err(e) => return FffResult::err(&format!("Failed to acquire file picker lock: {}", e)),
     };
 
-    let (warmup_caches, mode) = if let Some(mut picker) = guard.take() {
+    let defaults = fff::FilePickerOptions::default();
+    let (
+        warmup_caches,
+        mode,
+        use_os_trash,
+        respect_ignore_files,
+        same_file_system,
+        git_enabled,
+        git_status_provider,
+        read_only,
+    ) = if let Some(mut picker) = guard.take() {
         let warmup = picker.need_warmup_mmap_cache();
         let mode = picker.mode();
+        let use_os_trash = picker.use_os_trash();
+        let respect_ignore_files = picker.respect_ignore_files();
+        let same_file_system = picker.same_file_system();
+        let git_enabled = picker.git_enabled();
+        let git_status_provider = picker.git_status_provider();
+        let read_only = picker.read_only();
         picker.stop_background_monitor();
-        (warmup, mode)
+        (
+            warmup,
+            mode,
+            use_os_trash,
+            respect_ignore_files,
+            same_file_system,
+            git_enabled,
+            git_status_provider,
+            read_only,
+        )
     } else {
-        (false, FFFMode::default())
+        (
+            false,
+            FFFMode::default(),
+            defaults.use_os_trash,
+            defaults.respect_ignore_files,
+            defaults.same_file_system,
+            defaults.git_enabled,
+            defaults.git_status_provider,
+            defaults.read_only,
+        )
     };
 
     drop(guard);
@@ -667,6 +963,12 @@ pub unsafe extern "C" fn fff_restart_index(
             warmup_mmap_cache: warmup_caches,
             mode,
             cache_budget: None,
+            use_os_trash,
+            respect_ignore_files,
+            same_file_system,
+            git_enabled,
+            git_status_provider,
+            read_only,
             ..Default::default()
         },
     ) {
@@ -692,6 +994,165 @@ pub unsafe extern "C" fn fff_refresh_git_status(fff_handle: *mut c_void) -> *mut
     }
 }
 
+/// Send files deleted by `fff_apply_batch` to the OS trash instead of unlinking them.
+///
+/// Only effective when the library is built with the `os-trash` feature;
+/// otherwise deletes fail with an error while this is enabled.
+/// The setting survives `fff_restart_index`.
+///
+/// ## Safety
+/// * `fff_handle` must be a valid instance pointer from `fff_create_instance`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fff_set_use_os_trash(
+    fff_handle: *mut c_void,
+    use_os_trash: bool,
+) -> *mut FffResult {
+    let inst = match unsafe { instance_ref(fff_handle) } {
+        Ok(i) => i,
+        Err(e) => return e,
+    };
+
+    let mut guard = match inst.picker.write() {
+        Ok(g) => g,
+        Err(e) => return FffResult::err(&format!("Failed to acquire file picker lock: {}", e)),
+    };
+
+    match guard.as_mut() {
+        Some(picker) => {
+            picker.set_use_os_trash(use_os_trash);
+            FffResult::ok_empty()
+        }
+        None => FffResult::err("File picker not initialized. Call fff_create_instance first."),
+    }
+}
+
+/// Add a score from another source to files, e.g. the files referencing a
+/// symbol or the ones with failing tests.
+///
+/// `scores_json` is an object of scores by path, e.g. `{"src/main.rs": 200}`.
+/// Paths are resolved against the indexed directory. Replaces the scores of
+/// the previous call, `{}` clears them; a rescan clears them too.
+///
+/// Returns the number of indexed files that got a score in `int_value`.
+///
+/// ## Safety
+/// * `fff_handle` must be a valid instance pointer from `fff_create_instance`.
+/// * `scores_json` must be a valid null-terminated UTF-8 string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fff_set_external_scores(
+    fff_handle: *mut c_void,
+    scores_json: *const c_char,
+) -> *mut FffResult {
+    let inst = match unsafe { instance_ref(fff_handle) } {
+        Ok(i) => i,
+        Err(e) => return e,
+    };
+
+    let scores_str = match unsafe { cstr_to_str(scores_json) } {
+        Some(s) => s,
+        None => return FffResult::err("scores_json is null or invalid UTF-8"),
+    };
+
+    let scores: std::collections::HashMap<String, i32> = match serde_json::from_str(scores_str) {
+        Ok(scores) => scores,
+        Err(e) => return FffResult::err(&format!("Failed to parse external scores: {}", e)),
+    };
+
+    let mut guard = match inst.picker.write() {
+        Ok(g) => g,
+        Err(e) => return FffResult::err(&format!("Failed to acquire file picker lock: {}", e)),
+    };
+
+    match guard.as_mut() {
+        Some(picker) => FffResult::ok_int(picker.set_external_scores(scores) as i64),
+        None => FffResult::err("File picker not initialized. Call fff_create_instance first."),
+    }
+}
+
+/// Apply a batch of rename / move / delete operations under a single index lock.
+///
+/// # Parameters
+///
+/// * `fff_handle` – instance from `fff_create_instance`
+/// * `ops_json`   – JSON array of operations, e.g.
+///   `[{"op":"rename","from":"a.rs","to":"b.rs"},{"op":"move","from":"c.rs","to_dir":"lib"},{"op":"delete","path":"d.rs"}]`.
+///   Relative paths are resolved against the indexed directory.
+///
+/// Returns a JSON array string (free with `fff_free_string`) with one
+/// `{"success","path","error","trash_location"}` object per operation, in
+/// input order. `trash_location` is set for deletes sent to the OS trash
+/// (see `fff_set_use_os_trash`).
+///
+/// ## Safety
+/// * `fff_handle` must be a valid instance pointer from `fff_create_instance`.
+/// * `ops_json` must be a valid null-terminated UTF-8 string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fff_apply_batch(
+    fff_handle: *mut c_void,
+    ops_json: *const c_char,
+) -> *mut FffResult {
+    let inst = match unsafe { instance_ref(fff_handle) } {
+        Ok(i) => i,
+        Err(e) => return e,
+    };
+
+    let ops_str = match unsafe { cstr_to_str(ops_json) } {
+        Some(s) => s,
+        None => return FffResult::err("ops_json is null or invalid UTF-8"),
+    };
+
+    let ops: Vec<fff::FileOp> = match serde_json::from_str(ops_str) {
+        Ok(ops) => ops,
+        Err(e) => return FffResult::err(&format!("Failed to parse file operations: {}", e)),
+    };
+
+    let results = match inst
+        .picker
+        .apply_batch(&ops, &inst.frecency, &inst.query_tracker)
+    {
+        Ok(results) => results,
+        Err(e) => return FffResult::err(&format!("Failed to apply file operations: {}", e)),
+    };
+
+    match serde_json::to_string(&results) {
+        Ok(json) => FffResult::ok_string(&json),
+        Err(e) => FffResult::err(&format!(
+            "Failed to serialize file operation results: {}",
+            e
+        )),
+    }
+}
+
+/// Undo the most recent file operation applied through `fff_apply_batch`.
+///
+/// Renames and moves are reverted, deleted files are restored together with
+/// their frecency history. Requires the query tracker (history database).
+///
+/// Returns a `{"success","path","error","trash_location"}` JSON string (free with
+/// `fff_free_string`), or a null `handle` when there is nothing to undo.
+///
+/// ## Safety
+/// * `fff_handle` must be a valid instance pointer from `fff_create_instance`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fff_undo_last_file_op(fff_handle: *mut c_void) -> *mut FffResult {
+    let inst = match unsafe { instance_ref(fff_handle) } {
+        Ok(i) => i,
+        Err(e) => return e,
+    };
+
+    match inst
+        .picker
+        .undo_last_file_op(&inst.frecency, &inst.query_tracker)
+    {
+        Ok(Some(result)) => match serde_json::to_string(&result) {
+            Ok(json) => FffResult::ok_string(&json),
+            Err(e) => FffResult::err(&format!("Failed to serialize file operation result: {}", e)),
+        },
+        Ok(None) => FffResult::ok_empty(),
+        Err(e) => FffResult::err(&format!("Failed to undo file operation: {}", e)),
+    }
+}
+
 /// Track query completion for smart suggestions.
 ///
 /// ## Safety
@@ -718,10 +1179,7 @@ pub unsafe extern "C" fn fff_track_query(
         None => return FffResult::err("File path is null or invalid UTF-8"),
     };
 
-    let file_path = match fff::path_utils::canonicalize(path_str) {
-        Ok(p) => p,
-        Err(e) => return FffResult::err(&format!("Failed to canonicalize path: {}", e)),
-    };
+    let file_path = Path::new(path_str);
 
     let project_path = {
         let guard = match inst.picker.read() {
@@ -740,7 +1198,7 @@ pub unsafe extern "C" fn fff_track_query(
     };
 
     if let Some(ref mut tracker) = *qt_guard
-        && let Err(e) = tracker.track_query_completion(query_str, &project_path, &file_path)
+        && let Err(e) = tracker.track_query_completion(query_str, &project_path, file_path)
     {
         return FffResult::err(&format!("Failed to track query: {}", e));
     }
@@ -790,6 +1248,53 @@ pub unsafe extern "C" fn fff_get_historical_query(
     }
 }
 
+/// Get the most opened files and most used queries of the indexed project.
+///
+/// On success `FffResult.handle` is a JSON string of
+/// `{ "files": [...], "queries": [...], "grep_queries": [...] }` with up to
+/// `top_n` entries each, most used first. Free it with `fff_free_string`.
+///
+/// ## Safety
+/// `fff_handle` must be a valid instance pointer from `fff_create_instance`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fff_get_frecency_stats(
+    fff_handle: *mut c_void,
+    top_n: u32,
+) -> *mut FffResult {
+    let inst = match unsafe { instance_ref(fff_handle) } {
+        Ok(i) => i,
+        Err(e) => return e,
+    };
+
+    let picker = match inst.picker.snapshot() {
+        Some(p) => p,
+        None => {
+            return FffResult::err("File picker not initialized. Call fff_create_instance first.");
+        }
+    };
+    let frecency = match inst.frecency.read() {
+        Ok(f) => f,
+        Err(_) => return FffResult::err("Failed to acquire frecency lock"),
+    };
+    let query_tracker = match inst.query_tracker.read() {
+        Ok(q) => q,
+        Err(_) => return FffResult::err("Failed to acquire query tracker lock"),
+    };
+
+    let stats = FrecencyStats::collect(
+        picker.get_files(),
+        frecency.as_ref(),
+        query_tracker.as_ref(),
+        picker.base_path(),
+        top_n as usize,
+    );
+    match stats.map(|stats| serde_json::to_string(&stats)) {
+        Ok(Ok(json)) => FffResult::ok_string(&json),
+        Ok(Err(e)) => FffResult::err(&format!("Failed to serialize frecency stats: {}", e)),
+        Err(e) => FffResult::err(&format!("Failed to read frecency stats: {}", e)),
+    }
+}
+
 /// Get health check information.
 ///
 /// ## Safety
@@ -820,7 +1325,7 @@ pub unsafe extern "C" fn fff_health_check(
         serde_json::Value::String(format!("{}.{}.{}", major, minor, rev)),
     );
 
-    match git2::Repository::discover(&test_path) {
+    match fff::git::open_repository(&test_path) {
         Ok(repo) => {
             git_info.insert("available".to_string(), serde_json::Value::Bool(true));
             git_info.insert(
@@ -833,6 +1338,10 @@ pub unsafe extern "C" fn fff_health_check(
                     serde_json::Value::String(workdir.to_string_lossy().to_string()),
                 );
             }
+            git_info.insert(
+                "is_worktree".to_string(),
+                serde_json::Value::Bool(repo.is_worktree()),
+            );
         }
         Err(e) => {
             git_info.insert("available".to_string(), serde_json::Value::Bool(true));
@@ -911,6 +1420,10 @@ pub unsafe extern "C" fn fff_health_check(
                         "path".to_string(),
                         serde_json::Value::String(health_data.path),
                     );
+                    db_health.insert(
+                        "backend".to_string(),
+                        serde_json::Value::String(health_data.backend.to_string()),
+                    );
                     db_health.insert(
                         "disk_size".to_string(),
                         serde_json::Value::Number(health_data.disk_size.into()),
@@ -950,6 +1463,10 @@ pub unsafe extern "C" fn fff_health_check(
                         "path".to_string(),
                         serde_json::Value::String(health_data.path),
                     );
+                    db_health.insert(
+                        "backend".to_string(),
+                        serde_json::Value::String(health_data.backend.to_string()),
+                    );
                     db_health.insert(
                         "disk_size".to_string(),
                         serde_json::Value::Number(health_data.disk_size.into()),
@@ -978,6 +1495,47 @@ pub unsafe extern "C" fn fff_health_check(
     }
 }
 
+/// Write logs of every instance in this process to `log_file_path`.
+///
+/// `log_level` is one of trace, debug, info, warn, error (NULL/empty = info).
+/// Only the first call installs the logger; later calls return without effect.
+///
+/// ## Safety
+/// String parameters must be valid null-terminated UTF-8 or NULL.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fff_init_tracing(
+    log_file_path: *const c_char,
+    log_level: *const c_char,
+) -> *mut FffResult {
+    let log_file_path = match unsafe { optional_cstr(log_file_path) } {
+        Some(s) => s,
+        None => return FffResult::err("log_file_path is null or empty"),
+    };
+    let log_level = unsafe { optional_cstr(log_level) };
+
+    match fff::log::init_tracing(log_file_path, log_level) {
+        Ok(path) => FffResult::ok_string(&path),
+        Err(e) => FffResult::err(&format!("Failed to initialize tracing: {}", e)),
+    }
+}
+
+/// Change the log level set by `fff_init_tracing` without restarting.
+///
+/// ## Safety
+/// `log_level` must be a valid null-terminated UTF-8 string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fff_set_log_level(log_level: *const c_char) -> *mut FffResult {
+    let log_level = match unsafe { cstr_to_str(log_level) } {
+        Some(s) => s,
+        None => return FffResult::err("log_level is null or not valid UTF-8"),
+    };
+
+    match fff::log::set_log_level(log_level) {
+        Ok(_) => FffResult::ok_empty(),
+        Err(e) => FffResult::err(&format!("Failed to set log level: {}", e)),
+    }
+}
+
 /// Free a search result returned by `fff_search`.
 ///
 /// This frees the `FffSearchResult` struct, its `items` and `scores` arrays,
@@ -1008,6 +1566,9 @@ pub unsafe extern "C" fn fff_free_search_result(result: *mut FffSearchResult) {
                 score.free_strings();
             }
         }
+        if !result.suggestion.is_null() {
+            drop(CString::from_raw(result.suggestion));
+        }
     }
 }
 
@@ -1082,6 +1643,16 @@ pub unsafe extern "C" fn fff_free_grep_result(result: *mut FffGrepResult) {
         if !result.regex_fallback_error.is_null() {
             drop(CString::from_raw(result.regex_fallback_error));
         }
+        if !result.next_page.is_null() {
+            drop(CString::from_raw(result.next_page));
+        }
+        if !result.file_entries.is_null() {
+            let count = result.file_entries_count as usize;
+            let mut file_entries = Vec::from_raw_parts(result.file_entries, count, count);
+            for item in &mut file_entries {
+                item.free_strings();
+            }
+        }
     }
 }
 
@@ -1107,6 +1678,62 @@ pub unsafe extern "C" fn fff_grep_result_get_match(
     unsafe { result.items.add(index as usize) }
 }
 
+/// Get a pointer to the `index`-th `FffFileItem` an empty query listed in a
+/// grep result.
+///
+/// Returns null if `result` is null or `index >= result->file_entries_count`.
+/// The returned pointer is valid until the grep result is freed.
+///
+/// ## Safety
+/// `result` must be a valid `FffGrepResult` pointer from `fff_live_grep` or `fff_multi_grep`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fff_grep_result_get_file_entry(
+    result: *const FffGrepResult,
+    index: u32,
+) -> *const FffFileItem {
+    if result.is_null() {
+        return std::ptr::null();
+    }
+    let result = unsafe { &*result };
+    if index >= result.file_entries_count || result.file_entries.is_null() {
+        return std::ptr::null();
+    }
+    unsafe { result.file_entries.add(index as usize) }
+}
+
+/// Export a grep result as `"quickfix"` JSON, a `"sarif"` log or `"text"`
+/// lines of `path:line:col:content`.
+///
+/// On success `FffResult.handle` is a null-terminated string that must be
+/// freed with `fff_free_string`.
+///
+/// ## Safety
+/// * `result` must be a valid `FffGrepResult` pointer from `fff_live_grep` or `fff_multi_grep`.
+/// * `format` must be a valid null-terminated UTF-8 string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fff_export_grep_results(
+    result: *const FffGrepResult,
+    format: *const c_char,
+) -> *mut FffResult {
+    if result.is_null() {
+        return FffResult::err("Grep result is null");
+    }
+    let format = match unsafe { cstr_to_str(format) }.map(str::parse::<GrepExportFormat>) {
+        Some(Ok(format)) => format,
+        Some(Err(e)) => return FffResult::err(&e.to_string()),
+        None => return FffResult::err("Export format is null or not valid UTF-8"),
+    };
+
+    let result = unsafe { &*result };
+    let items = if result.items.is_null() {
+        &[][..]
+    } else {
+        unsafe { std::slice::from_raw_parts(result.items, result.count as usize) }
+    };
+    let matches = items.iter().map(|item| unsafe { item.as_exported() });
+    FffResult::ok_string(&export_grep_matches(matches, format))
+}
+
 /// Free a scan progress result returned by `fff_get_scan_progress`.
 ///
 /// ## Safety
@@ -0,0 +1,63 @@
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use fff_search::file_picker::FilePicker;
+use fff_search::{FileItem, FileList, FuzzySearchOptions, PaginationArgs, QueryParser};
+use std::path::{Path, PathBuf};
+
+/// Files whose paths all match the short queries below, so the first page
+/// is cut out of every one of them like on the first keystroke in a big repo.
+fn make_files(count: usize) -> FileList {
+    let base = Path::new("/repo");
+    let mut files: Vec<FileItem> = (0..count)
+        .map(|i| {
+            let path = PathBuf::from(format!(
+                "/repo/src/module_{}/component_{}/file_{i}.rs",
+                i % 97,
+                i % 13
+            ));
+            FileItem::new_with_metadata(path, base, None, None)
+        })
+        .collect();
+    files.sort_unstable_by(|a, b| a.path().cmp(b.path()));
+    files.into()
+}
+
+fn search(files: &FileList, query: &str, limit: usize) -> usize {
+    let parsed = QueryParser::default().parse(query);
+    let result = FilePicker::fuzzy_search(
+        black_box(files),
+        &parsed,
+        None,
+        FuzzySearchOptions {
+            max_threads: 4,
+            pagination: PaginationArgs { offset: 0, limit },
+            ..Default::default()
+        },
+    );
+    result.items.len()
+}
+
+fn bench_first_page(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fuzzy_first_page");
+    group.sample_size(20);
+
+    // a page of 50 out of every file
+    let files = make_files(100_000);
+    for query in ["fi", "mod", "comp"] {
+        group.bench_with_input(BenchmarkId::new("100k", query), query, |b, query| {
+            b.iter(|| search(&files, query, 50));
+        });
+    }
+
+    // the 20k matches the neovim picker keeps for scrolling, out of 30k
+    let files = make_files(30_000);
+    for query in ["fi", "mod"] {
+        group.bench_with_input(BenchmarkId::new("30k_window", query), query, |b, query| {
+            b.iter(|| search(&files, query, 20_000));
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_first_page);
+criterion_main!(benches);
@@ -0,0 +1,302 @@
+//! Read-only directories searched next to the project without being part of
+//! its index, like `$VIMRUNTIME` or the directory of a plugin. A root is
+//! walked the first time it is searched and ranked below the project by its
+//! weight, `root:<name>` searches it alone. A label, e.g. `deps` or `docs`,
+//! tells its files apart from the project's in the results.
+
+use crate::file_list::FileList;
+use crate::file_picker::ScanLimits;
+use crate::grep::{GrepResult, GrepSearchOptions, grep_search};
+use crate::types::{ContentCacheBudget, FileItem, SearchResult};
+use fff_query_parser::FFFQuery;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, OnceLock};
+
+/// Percentage of their score that the files of a root keep in searches
+/// without `root:`, so the project ranks first.
+pub const DEFAULT_ROOT_WEIGHT: u32 = 50;
+
+#[derive(Debug)]
+pub struct AuxiliaryRoot {
+    name: String,
+    path: PathBuf,
+    weight: u32,
+    label: Option<String>,
+    limits: ScanLimits,
+    files: OnceLock<FileList>,
+}
+
+impl AuxiliaryRoot {
+    /// `weight` is the percentage of their score the files keep next to the
+    /// project, `0` leaves them to `root:` queries only.
+    pub fn new(name: String, path: PathBuf, weight: u32, limits: ScanLimits) -> Self {
+        Self {
+            name,
+            path,
+            weight,
+            label: None,
+            limits,
+            files: OnceLock::new(),
+        }
+    }
+
+    /// Shown next to the files of the root instead of its name.
+    pub fn with_label(mut self, label: String) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    /// The label given with [`Self::with_label`], the name otherwise.
+    pub fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Whether `file` is one of [`Self::files`]. Compares addresses, so a
+    /// project file under the root directory is not one of them.
+    pub fn contains(&self, file: &FileItem) -> bool {
+        self.files
+            .get()
+            .is_some_and(|files| files.index_of(file).is_some())
+    }
+
+    /// The files under the root sorted by path, walking it on the first call.
+    /// Their relative paths start at the root.
+    pub fn files(&self) -> &FileList {
+        self.files.get_or_init(|| {
+            let start = std::time::Instant::now();
+            let files = scan_root(&self.path, self.limits);
+            tracing::info!(
+                "Scanned {} files of the {} root in {:?}",
+                files.len(),
+                self.name,
+                start.elapsed()
+            );
+            files.into()
+        })
+    }
+
+    /// Scale the scores of a ranking of [`Self::files`] by the weight.
+    pub fn weigh(&self, ranking: &mut SearchResult<'_>) {
+        for score in &mut ranking.scores {
+            score.total = (score.total as i64 * self.weight as i64 / 100) as i32;
+        }
+    }
+
+    pub fn grep(
+        &self,
+        query: &FFFQuery<'_>,
+        options: &GrepSearchOptions,
+        budget: &ContentCacheBudget,
+    ) -> GrepResult<'_> {
+        grep_search(self.files(), query, options, budget, None, None, None)
+    }
+}
+
+/// The registered roots, shared by the pickers and snapshots cloned from
+/// the same registry so a root is only walked once.
+#[derive(Debug, Clone, Default)]
+pub struct AuxiliaryRoots {
+    roots: Vec<Arc<AuxiliaryRoot>>,
+}
+
+impl AuxiliaryRoots {
+    /// Replaces a root registered under the same name.
+    pub fn register(&mut self, root: AuxiliaryRoot) {
+        self.roots.retain(|r| r.name != root.name);
+        self.roots.push(Arc::new(root));
+    }
+
+    /// Whether a root with this name was registered.
+    pub fn unregister(&mut self, name: &str) -> bool {
+        let before = self.roots.len();
+        self.roots.retain(|r| r.name != name);
+        self.roots.len() != before
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AuxiliaryRoot> {
+        self.roots.iter().find(|r| r.name == name).map(Arc::as_ref)
+    }
+
+    /// The root `file` of a search result comes from, `None` for the files
+    /// of the project.
+    pub fn root_of(&self, file: &FileItem) -> Option<&AuxiliaryRoot> {
+        self.iter().find(|root| root.contains(file))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &AuxiliaryRoot> {
+        self.roots.iter().map(Arc::as_ref)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// The roots searched along with the project, i.e. with a weight.
+    pub fn weighted(&self) -> impl Iterator<Item = &AuxiliaryRoot> {
+        self.iter().filter(|r| r.weight > 0)
+    }
+
+    /// The files of the root a `root:` query targets, none for an unknown
+    /// root. `None` when the query has no `root:`.
+    pub fn files_for_query(&self, query: &FFFQuery<'_>) -> Option<&FileList> {
+        let name = query.constraints.iter().find_map(|c| c.root())?;
+        static EMPTY: LazyLock<FileList> = LazyLock::new(FileList::new);
+        Some(self.get(name).map_or(&EMPTY, AuxiliaryRoot::files))
+    }
+}
+
+fn scan_root(path: &Path, limits: ScanLimits) -> Vec<FileItem> {
+    use ignore::{WalkBuilder, WalkState};
+
+    let mut walk_builder = WalkBuilder::new(path);
+    walk_builder.follow_links(false).max_depth(limits.max_depth);
+
+    let files = parking_lot::Mutex::new(Vec::new());
+    walk_builder.build_parallel().run(|| {
+        let files = &files;
+        Box::new(move |result| {
+            let Ok(entry) = result else {
+                return WalkState::Continue;
+            };
+            if !entry.file_type().is_some_and(|ft| ft.is_file()) {
+                return WalkState::Continue;
+            }
+
+            let metadata = entry.metadata().ok();
+            let file = FileItem::new_with_metadata(
+                entry.path().to_path_buf(),
+                path,
+                None,
+                metadata.as_ref(),
+            );
+            let mut files = files.lock();
+            if limits.max_files.is_some_and(|max| files.len() >= max) {
+                return WalkState::Quit;
+            }
+            files.push(file);
+            WalkState::Continue
+        })
+    });
+
+    let mut files = files.into_inner();
+    files.sort_unstable_by(|a, b| a.path().cmp(b.path()));
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_picker::{FilePicker, FuzzySearchOptions};
+    use crate::grep::{GrepMode, parse_grep_query};
+    use crate::test_fixtures::{grep_options, picker_options, scan, write_files};
+    use crate::types::PaginationArgs;
+    use fff_query_parser::QueryParser;
+    use tempfile::TempDir;
+
+    fn picker_with_runtime(project: &TempDir, runtime: &TempDir) -> FilePicker {
+        write_files(project.path(), &[("options.rs", "pub struct Options;")]);
+        write_files(
+            runtime.path(),
+            &[("doc/options.txt", "'tabstop' number of spaces")],
+        );
+
+        let mut picker = scan(picker_options(project.path()));
+        picker.register_auxiliary_root(
+            "runtime".to_string(),
+            runtime.path().to_path_buf(),
+            DEFAULT_ROOT_WEIGHT,
+            Some("docs".to_string()),
+        );
+        picker
+    }
+
+    fn search<'a>(picker: &'a FilePicker, query: &'a str) -> Vec<(&'a str, i32)> {
+        let parsed = QueryParser::default().parse(query);
+        let rank = |files| {
+            FilePicker::fuzzy_search(
+                files,
+                &parsed,
+                None,
+                FuzzySearchOptions {
+                    project_path: Some(picker.base_path()),
+                    pagination: PaginationArgs {
+                        offset: 0,
+                        limit: 100,
+                    },
+                    ..Default::default()
+                },
+            )
+        };
+
+        let mut ranking = rank(picker.files_for_query(&parsed));
+        if parsed.constraints.iter().all(|c| c.root().is_none()) {
+            for root in picker.auxiliary_roots().weighted() {
+                let mut root_ranking = rank(root.files());
+                root.weigh(&mut root_ranking);
+                ranking = crate::virtual_files::merge_rankings(ranking, root_ranking, 100);
+            }
+        }
+        ranking
+            .items
+            .iter()
+            .zip(&ranking.scores)
+            .map(|(file, score)| (file.relative_path(), score.total))
+            .collect()
+    }
+
+    #[test]
+    fn root_ranks_below_the_project_and_alone_with_root_token() {
+        let project = TempDir::new().unwrap();
+        let runtime = TempDir::new().unwrap();
+        let picker = picker_with_runtime(&project, &runtime);
+
+        let results = search(&picker, "options");
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, "options.rs");
+        assert!(results[1].0.ends_with("options.txt"));
+        assert!(results[1].1 < results[0].1);
+
+        let results = search(&picker, "root:runtime options");
+        assert_eq!(results.len(), 1);
+        assert!(results[0].0.ends_with("options.txt"));
+
+        let roots = picker.auxiliary_roots();
+        let root_file = &roots.get("runtime").unwrap().files()[0];
+        assert_eq!(roots.root_of(root_file).map(|r| r.label()), Some("docs"));
+        assert!(roots.root_of(&picker.get_files()[0]).is_none());
+
+        assert!(search(&picker, "root:plugins options").is_empty());
+    }
+
+    #[test]
+    fn grep_searches_the_files_of_a_root() {
+        let project = TempDir::new().unwrap();
+        let runtime = TempDir::new().unwrap();
+        let mut picker = picker_with_runtime(&project, &runtime);
+
+        let query = parse_grep_query("root:runtime tabstop");
+        let root = picker.auxiliary_roots().get("runtime").unwrap();
+        let result = root.grep(
+            &query,
+            &grep_options(GrepMode::PlainText),
+            picker.cache_budget(),
+        );
+        assert_eq!(result.matches.len(), 1);
+        assert!(result.files[0].path().ends_with("doc/options.txt"));
+
+        assert!(picker.unregister_auxiliary_root("runtime"));
+        assert!(picker.auxiliary_roots().is_empty());
+    }
+}
@@ -1,7 +1,8 @@
 use crate::error::Error;
 use crate::file_picker::{FFFMode, FilePicker};
-use crate::git::GitStatusCache;
-use crate::shared::{SharedFrecency, SharedPicker};
+use crate::git::{GitRepoLocation, GitStatusCache};
+use crate::path_utils;
+use crate::shared::{IndexEvent, SharedFrecency, SharedPicker};
 use crate::sort_buffer::sort_with_buffer;
 use git2::Repository;
 use notify::event::{AccessKind, AccessMode};
@@ -48,6 +49,8 @@ impl BackgroundWatcher {
         shared_picker: SharedPicker,
         shared_frecency: SharedFrecency,
         mode: FFFMode,
+        respect_ignore_files: bool,
+        same_file_system: bool,
     ) -> Result<Self, Error> {
         info!(
             "Initializing background watcher for path: {}, mode: {:?}",
@@ -55,8 +58,15 @@ impl BackgroundWatcher {
             mode,
         );
 
-        let debouncer =
-            Self::create_debouncer(base_path, git_workdir, shared_picker, shared_frecency, mode)?;
+        let debouncer = Self::create_debouncer(
+            base_path,
+            git_workdir,
+            shared_picker.clone(),
+            shared_frecency.clone(),
+            mode,
+            respect_ignore_files,
+            same_file_system,
+        )?;
         info!("Background file watcher initialized successfully");
 
         let stop_signal = Arc::new(AtomicBool::new(false));
@@ -70,6 +80,7 @@ impl BackgroundWatcher {
             .spawn(move || {
                 while !stop_clone.load(Ordering::Acquire) {
                     std::thread::park_timeout(Duration::from_secs(1));
+                    refresh_external_frecency(&shared_picker, &shared_frecency);
                 }
                 // Debouncer::stop() joins the debouncer's event thread, then
                 // drops the watcher (whose Drop signals the I/O thread).
@@ -95,6 +106,8 @@ impl BackgroundWatcher {
         shared_picker: SharedPicker,
         shared_frecency: SharedFrecency,
         mode: FFFMode,
+        respect_ignore_files: bool,
+        same_file_system: bool,
     ) -> Result<Debouncer, Error> {
         // do not follow symlinks as then notifiers spawns a bunch of events for symlinked
         // files that could be git ignored, we have to property differentiate those and if
@@ -102,6 +115,9 @@ impl BackgroundWatcher {
         let config = Config::default().with_follow_symlinks(false);
 
         let git_workdir_for_handler = git_workdir.clone();
+        let root_device = same_file_system
+            .then(|| path_utils::device_id(&base_path))
+            .flatten();
         let mut debouncer = new_debouncer_opt(
             DEBOUNCE_TIMEOUT,
             Some(DEBOUNCE_TIMEOUT / 2), // tick rate for the event span
@@ -114,6 +130,7 @@ impl BackgroundWatcher {
                             &shared_picker,
                             &shared_frecency,
                             mode,
+                            root_device,
                         );
                     }
                     Err(errors) => {
@@ -134,7 +151,12 @@ impl BackgroundWatcher {
         // directories like `target/` in rust causes buffer overflow, which drops real source file
         // events. Instead we watch the root non-recursively (for top-level file changes
         // and new directory detection) and each non-ignored subdirectory recursively.
-        let watch_dirs = collect_non_ignored_dirs(&base_path, git_workdir.is_some());
+        let watch_dirs = collect_non_ignored_dirs(
+            &base_path,
+            git_workdir.is_some(),
+            respect_ignore_files,
+            same_file_system,
+        );
 
         if watch_dirs.len() > MAX_SELECTIVE_WATCH_DIRS {
             tracing::warn!(
@@ -191,6 +213,34 @@ impl Drop for BackgroundWatcher {
     }
 }
 
+/// Rescore the files when another process wrote to the shared frecency
+/// database, e.g. a second editor open in the same project.
+fn refresh_external_frecency(shared_picker: &SharedPicker, shared_frecency: &SharedFrecency) {
+    let changes = match shared_frecency.read() {
+        Ok(guard) => match guard.as_ref() {
+            Some(frecency) => frecency.take_external_changes(),
+            None => return,
+        },
+        Err(_) => return,
+    };
+    if changes.is_empty() {
+        return;
+    }
+
+    // same lock order as the event handler: picker first, then frecency
+    if let Ok(mut picker_guard) = shared_picker.write()
+        && let Some(ref mut picker) = *picker_guard
+        && let Ok(frecency_guard) = shared_frecency.read()
+        && let Some(ref frecency) = *frecency_guard
+    {
+        debug!(
+            ?changes,
+            "Frecency database changed in another process, refreshing scores"
+        );
+        picker.apply_external_frecency_changes(&changes, frecency);
+    }
+}
+
 #[tracing::instrument(name = "fs_events", skip(events, shared_picker, shared_frecency), level = Level::DEBUG)]
 fn handle_debounced_events(
     events: Vec<DebouncedEvent>,
@@ -198,9 +248,12 @@ fn handle_debounced_events(
     shared_picker: &SharedPicker,
     shared_frecency: &SharedFrecency,
     mode: FFFMode,
+    root_device: Option<u64>,
 ) {
     // this will be called very often, we have to minimiy the lock time for file picker
-    let repo = git_workdir.as_ref().and_then(|p| Repository::open(p).ok());
+    let repo = git_workdir
+        .as_ref()
+        .and_then(|p| crate::git::open_repository(p).ok());
     let mut need_full_rescan = false;
     let mut need_full_git_rescan = false;
     let mut paths_to_remove = Vec::new();
@@ -268,8 +321,11 @@ fn handle_debounced_events(
             if is_removal || !path.exists() {
                 paths_to_remove.push(path.as_path());
             } else {
-                // For additions/modifications, still filter gitignored files.
-                if should_include_file(path, &repo) {
+                // For additions/modifications, still filter gitignored files
+                // and, with `same_file_system`, files under nested mounts.
+                if should_include_file(path, &repo)
+                    && (root_device.is_none() || path_utils::device_id(path) == root_device)
+                {
                     paths_to_add_or_modify.push(path.as_path());
                 }
             }
@@ -333,9 +389,9 @@ fn handle_debounced_events(
                             debug!(
                                 "on_create_or_modify({:?}) -> Some({})",
                                 path,
-                                file.path.display()
+                                file.path().display()
                             );
-                            files_to_update.push(file.path.clone());
+                            files_to_update.push(file.path().to_path_buf());
                         }
                         None => {
                             error!("on_create_or_modify({:?}) -> None (file not added!)", path);
@@ -362,6 +418,9 @@ fn handle_debounced_events(
             debug!("No file index changes to apply");
             Vec::new()
         };
+    if !paths_to_remove.is_empty() || !paths_to_add_or_modify.is_empty() {
+        shared_picker.emit(IndexEvent::FilesChanged);
+    }
 
     // AI mode: auto-track frecency for all modified/created files.
     // Uses a 5-minute cooldown per file to prevent score inflation from rapid
@@ -437,16 +496,25 @@ fn handle_debounced_events(
             }
         };
 
-        if let Ok(mut guard) = shared_picker.write()
+        let updated = if let Ok(mut guard) = shared_picker.write()
             && let Some(ref mut picker) = *guard
         {
-            if let Err(e) = picker.update_git_statuses(status, shared_frecency) {
-                error!("Failed to update git statuses: {:?}", e);
-            } else {
-                info!("Successfully updated git statuses in picker");
+            match picker.update_git_statuses(status, shared_frecency) {
+                Ok(_) => {
+                    info!("Successfully updated git statuses in picker");
+                    true
+                }
+                Err(e) => {
+                    error!("Failed to update git statuses: {:?}", e);
+                    false
+                }
             }
         } else {
             error!("Failed to acquire picker lock for git status update");
+            false
+        };
+        if updated {
+            shared_picker.emit(IndexEvent::GitStatusRefreshed);
         }
     }
 }
@@ -458,15 +526,7 @@ fn trigger_full_rescan(shared_picker: &SharedPicker, shared_frecency: &SharedFre
     // and automatically reflect file changes. Old FileItems (and their mmaps)
     // are dropped when the picker rebuilds its file list.
 
-    let Ok(mut guard) = shared_picker.write() else {
-        error!("Failed to acquire file picker write lock for full rescan");
-        return;
-    };
-    let Some(ref mut picker) = *guard else {
-        error!("File picker not initialized, cannot trigger rescan");
-        return;
-    };
-    if let Err(e) = picker.trigger_rescan(shared_frecency) {
+    if let Err(e) = shared_picker.trigger_rescan(shared_frecency) {
         error!("Failed to trigger full rescan: {:?}", e);
     } else {
         info!("Full filesystem rescan completed successfully");
@@ -505,7 +565,10 @@ pub fn is_dotgit_change_affecting_status(changed: &Path, repo: &Option<Repositor
         return false;
     };
 
+    // For linked worktrees HEAD and index live in the worktree's own git dir
+    // while refs and info/ are shared through the common dir.
     let git_dir = repo.path();
+    let common_dir = repo.commondir();
 
     if let Ok(rel) = changed.strip_prefix(git_dir) {
         if rel.starts_with("objects") || rel.starts_with("logs") || rel.starts_with("hooks") {
@@ -517,10 +580,7 @@ pub fn is_dotgit_change_affecting_status(changed: &Path, repo: &Option<Repositor
         if rel == Path::new("HEAD") {
             return true;
         }
-        if rel.starts_with("refs") || rel == Path::new("packed-refs") {
-            return true;
-        }
-        if rel == Path::new("info/exclude") || rel == Path::new("info/sparse-checkout") {
+        if is_shared_git_path_affecting_status(rel) {
             return true;
         }
 
@@ -529,35 +589,47 @@ pub fn is_dotgit_change_affecting_status(changed: &Path, repo: &Option<Repositor
         {
             return true;
         }
+    } else if let Ok(rel) = changed.strip_prefix(common_dir) {
+        return is_shared_git_path_affecting_status(rel);
     }
 
     false
 }
 
+fn is_shared_git_path_affecting_status(rel: &Path) -> bool {
+    rel.starts_with("refs")
+        || rel == Path::new("packed-refs")
+        || rel == Path::new("info/exclude")
+        || rel == Path::new("info/sparse-checkout")
+}
+
 fn is_ignore_definition_path(path: &Path) -> bool {
     matches!(
         path.file_name().and_then(|f| f.to_str()),
-        Some(".ignore") | Some(".gitignore")
+        Some(".ignore") | Some(".rgignore") | Some(".gitignore")
     )
 }
 
 fn watch_git_status_paths(debouncer: &mut Debouncer, git_workdir: Option<&PathBuf>) {
-    let Some(workdir) = git_workdir else {
+    let Some(repo) = git_workdir.and_then(|workdir| GitRepoLocation::discover(workdir)) else {
         return;
     };
 
-    let git_dir = workdir.join(".git");
-    if !git_dir.is_dir() {
-        return;
-    }
-
-    // Watch .git/ non-recursively to catch top-level files:
+    // Watch the git dir non-recursively to catch top-level files:
     // index, index.lock, HEAD, packed-refs, MERGE_HEAD, CHERRY_PICK_HEAD, REVERT_HEAD
-    if let Err(e) = debouncer.watch(&git_dir, RecursiveMode::NonRecursive) {
+    if let Err(e) = debouncer.watch(&repo.git_dir, RecursiveMode::NonRecursive) {
         warn!("Failed to watch .git directory: {}", e);
         return;
     }
 
+    // Linked worktrees keep packed-refs in the shared git dir
+    let git_dir = repo.common_dir;
+    if repo.is_worktree
+        && let Err(e) = debouncer.watch(&git_dir, RecursiveMode::NonRecursive)
+    {
+        warn!("Failed to watch common git directory: {}", e);
+    }
+
     // Watch refs/ recursively to catch branch/tag changes
     let refs_dir = git_dir.join("refs");
     if refs_dir.is_dir()
@@ -576,11 +648,16 @@ fn watch_git_status_paths(debouncer: &mut Debouncer, git_workdir: Option<&PathBu
 }
 
 /// Collects immediate non-ignored subdirectories of `base_path` using the `ignore` crate
-/// to respect .gitignore, .ignore, and global gitignore rules. This is used to set up
+/// to respect .gitignore, .ignore, .rgignore, and global gitignore rules. This is used to set up
 /// selective file watching — only non-ignored directories get a recursive watcher,
 /// preventing gitignored directories like `target/` from flooding the OS event buffer.
-fn collect_non_ignored_dirs(base_path: &Path, has_git_repo: bool) -> Vec<PathBuf> {
-    use crate::ignore::non_git_repo_overrides;
+fn collect_non_ignored_dirs(
+    base_path: &Path,
+    has_git_repo: bool,
+    respect_ignore_files: bool,
+    same_file_system: bool,
+) -> Vec<PathBuf> {
+    use crate::ignore::{apply_ignore_files, non_git_repo_overrides};
     use ignore::WalkBuilder;
 
     let mut walk_builder = WalkBuilder::new(base_path);
@@ -589,9 +666,10 @@ fn collect_non_ignored_dirs(base_path: &Path, has_git_repo: bool) -> Vec<PathBuf
         .git_ignore(true)
         .git_exclude(true)
         .git_global(true)
-        .ignore(true)
         .follow_links(false)
+        .same_file_system(same_file_system)
         .max_depth(Some(1));
+    apply_ignore_files(&mut walk_builder, respect_ignore_files);
 
     if !has_git_repo && let Some(overrides) = non_git_repo_overrides(base_path) {
         walk_builder.overrides(overrides);
@@ -0,0 +1,378 @@
+//! Comment and string literal regions of source files for grep scopes.
+//!
+//! A small lexer per language family finds the comments and string literals
+//! of a file so [`GrepScope`] can keep only the matches inside, or outside,
+//! of them. It knows nothing beyond the delimiters: no nesting, no string
+//! interpolation, no heredocs. Files of a language it does not know are not
+//! filtered at all.
+
+use std::path::Path;
+
+use crate::grep::{GrepMatch, GrepScope};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RegionKind {
+    Comment,
+    String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Region {
+    start: usize,
+    end: usize,
+    kind: RegionKind,
+}
+
+struct StringDelimiter {
+    open: &'static [u8],
+    close: &'static [u8],
+    escapes: bool,
+    multiline: bool,
+}
+
+/// A single line string with backslash escapes: "..." or '...'.
+const fn quoted(quote: &'static [u8]) -> StringDelimiter {
+    StringDelimiter {
+        open: quote,
+        close: quote,
+        escapes: true,
+        multiline: false,
+    }
+}
+
+/// A string that can span lines: """...""", `...`.
+const fn multiline(open: &'static [u8], close: &'static [u8], escapes: bool) -> StringDelimiter {
+    StringDelimiter {
+        open,
+        close,
+        escapes,
+        multiline: true,
+    }
+}
+
+struct Syntax {
+    line_comments: &'static [&'static [u8]],
+    block_comments: &'static [(&'static [u8], &'static [u8])],
+    /// Checked in order, so a delimiter must come before its prefixes.
+    strings: &'static [StringDelimiter],
+    /// Rust char literals, a plain `'` also starts a lifetime.
+    char_literals: bool,
+}
+
+const RUST: Syntax = Syntax {
+    line_comments: &[b"//"],
+    block_comments: &[(b"/*", b"*/")],
+    strings: &[multiline(b"\"", b"\"", true)],
+    char_literals: true,
+};
+
+const C_LIKE: Syntax = Syntax {
+    line_comments: &[b"//"],
+    block_comments: &[(b"/*", b"*/")],
+    strings: &[
+        multiline(b"\"\"\"", b"\"\"\"", true),
+        quoted(b"\""),
+        quoted(b"'"),
+    ],
+    char_literals: false,
+};
+
+const JS_LIKE: Syntax = Syntax {
+    line_comments: &[b"//"],
+    block_comments: &[(b"/*", b"*/")],
+    strings: &[quoted(b"\""), quoted(b"'"), multiline(b"`", b"`", true)],
+    char_literals: false,
+};
+
+const GO: Syntax = Syntax {
+    line_comments: &[b"//"],
+    block_comments: &[(b"/*", b"*/")],
+    strings: &[quoted(b"\""), quoted(b"'"), multiline(b"`", b"`", false)],
+    char_literals: false,
+};
+
+const PYTHON: Syntax = Syntax {
+    line_comments: &[b"#"],
+    block_comments: &[],
+    strings: &[
+        multiline(b"\"\"\"", b"\"\"\"", true),
+        multiline(b"'''", b"'''", true),
+        quoted(b"\""),
+        quoted(b"'"),
+    ],
+    char_literals: false,
+};
+
+const HASH_COMMENTS: Syntax = Syntax {
+    line_comments: &[b"#"],
+    block_comments: &[],
+    strings: &[quoted(b"\""), quoted(b"'")],
+    char_literals: false,
+};
+
+const LUA: Syntax = Syntax {
+    line_comments: &[b"--"],
+    block_comments: &[(b"--[[", b"]]")],
+    strings: &[multiline(b"[[", b"]]", false), quoted(b"\""), quoted(b"'")],
+    char_literals: false,
+};
+
+const DASH_COMMENTS: Syntax = Syntax {
+    line_comments: &[b"--"],
+    block_comments: &[(b"{-", b"-}"), (b"/*", b"*/")],
+    strings: &[quoted(b"\""), quoted(b"'")],
+    char_literals: false,
+};
+
+const MARKUP: Syntax = Syntax {
+    line_comments: &[],
+    block_comments: &[(b"<!--", b"-->")],
+    strings: &[],
+    char_literals: false,
+};
+
+fn syntax_for(path: &Path) -> Option<&'static Syntax> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let syntax = match extension.as_str() {
+        "rs" => &RUST,
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" | "m" | "mm" | "cs" | "java" | "kt"
+        | "kts" | "scala" | "swift" | "dart" | "zig" | "css" | "scss" | "proto" => &C_LIKE,
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => &JS_LIKE,
+        "go" => &GO,
+        "py" | "pyi" => &PYTHON,
+        "sh" | "bash" | "zsh" | "fish" | "rb" | "pl" | "r" | "yaml" | "yml" | "toml" | "nix"
+        | "ex" | "exs" | "cmake" => &HASH_COMMENTS,
+        "lua" => &LUA,
+        "sql" | "hs" | "elm" => &DASH_COMMENTS,
+        "html" | "htm" | "xml" | "svg" => &MARKUP,
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// Comments and string literals of `content` in order.
+fn regions(content: &[u8], syntax: &Syntax) -> Vec<Region> {
+    let mut regions = Vec::new();
+    let mut i = 0;
+
+    while i < content.len() {
+        let rest = &content[i..];
+
+        if let Some((open, close)) = syntax
+            .block_comments
+            .iter()
+            .find(|(open, _)| rest.starts_with(open))
+        {
+            let end =
+                find(content, i + open.len(), close).map_or(content.len(), |at| at + close.len());
+            regions.push(Region {
+                start: i,
+                end,
+                kind: RegionKind::Comment,
+            });
+            i = end;
+            continue;
+        }
+
+        if syntax
+            .line_comments
+            .iter()
+            .any(|open| rest.starts_with(open))
+        {
+            let end = memchr::memchr(b'\n', rest).map_or(content.len(), |at| i + at);
+            regions.push(Region {
+                start: i,
+                end,
+                kind: RegionKind::Comment,
+            });
+            i = end;
+            continue;
+        }
+
+        if syntax.char_literals
+            && let Some(len) = char_literal_len(rest)
+        {
+            regions.push(Region {
+                start: i,
+                end: i + len,
+                kind: RegionKind::String,
+            });
+            i += len;
+            continue;
+        }
+
+        if let Some(delimiter) = syntax.strings.iter().find(|d| rest.starts_with(d.open)) {
+            let end = string_end(content, i + delimiter.open.len(), delimiter);
+            regions.push(Region {
+                start: i,
+                end,
+                kind: RegionKind::String,
+            });
+            i = end;
+            continue;
+        }
+
+        i += 1;
+    }
+
+    regions
+}
+
+fn find(content: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    memchr::memmem::find(&content[from..], needle).map(|at| from + at)
+}
+
+/// End of the string whose content starts at `from`, unterminated strings
+/// end with the line, or with the file when they can span lines.
+fn string_end(content: &[u8], from: usize, delimiter: &StringDelimiter) -> usize {
+    let mut j = from;
+    while j < content.len() {
+        if delimiter.escapes && content[j] == b'\\' {
+            j += 2;
+            continue;
+        }
+        if content[j..].starts_with(delimiter.close) {
+            return j + delimiter.close.len();
+        }
+        if !delimiter.multiline && content[j] == b'\n' {
+            return j;
+        }
+        j += 1;
+    }
+    content.len()
+}
+
+/// Length of a char literal at the start of `rest`: 'a', '\n', '\'', 'é'.
+/// `None` for a lifetime like 'a or 'static.
+fn char_literal_len(rest: &[u8]) -> Option<usize> {
+    if rest.first() != Some(&b'\'') {
+        return None;
+    }
+    let body = rest.get(1..)?;
+    let len = match body.first()? {
+        b'\\' => body.iter().skip(2).position(|&b| b == b'\'')? + 2,
+        &b => utf8_len(b),
+    };
+    (body.get(len) == Some(&b'\'')).then_some(len + 2)
+}
+
+fn utf8_len(first: u8) -> usize {
+    match first {
+        0xF0.. => 4,
+        0xE0.. => 3,
+        0xC0.. => 2,
+        _ => 1,
+    }
+}
+
+fn kind_at(regions: &[Region], offset: usize) -> Option<RegionKind> {
+    let after = regions.partition_point(|region| region.start <= offset);
+    let region = regions[..after].last()?;
+    (offset < region.end).then_some(region.kind)
+}
+
+fn in_scope(scope: GrepScope, kind: Option<RegionKind>) -> bool {
+    match scope {
+        GrepScope::All => true,
+        GrepScope::Code => kind.is_none(),
+        GrepScope::Comments => kind == Some(RegionKind::Comment),
+        GrepScope::Strings => kind == Some(RegionKind::String),
+    }
+}
+
+/// Drops the matches of a file outside of `scope`, and the highlights of the
+/// kept lines that are outside of it.
+pub(crate) fn retain_in_scope(
+    scope: GrepScope,
+    path: &Path,
+    content: &[u8],
+    matches: &mut Vec<GrepMatch>,
+) {
+    if scope == GrepScope::All || matches.is_empty() {
+        return;
+    }
+    let Some(syntax) = syntax_for(path) else {
+        return;
+    };
+
+    let regions = regions(content, syntax);
+    matches.retain_mut(|m| {
+        let line_start = m.byte_offset as usize;
+        if m.match_byte_offsets.is_empty() {
+            return in_scope(scope, kind_at(&regions, line_start + m.col));
+        }
+
+        m.match_byte_offsets
+            .retain(|(start, _)| in_scope(scope, kind_at(&regions, line_start + *start as usize)));
+        let kept = &m.match_byte_offsets;
+        m.captures.retain(|capture| {
+            kept.iter()
+                .any(|&(start, end)| start <= capture.start && capture.end <= end)
+        });
+        match m.match_byte_offsets.first() {
+            Some(&(start, _)) => {
+                m.col = start as usize;
+                true
+            }
+            None => false,
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds<'a>(content: &'a str, syntax: &Syntax) -> Vec<(&'a str, RegionKind)> {
+        regions(content.as_bytes(), syntax)
+            .into_iter()
+            .map(|region| (&content[region.start..region.end], region.kind))
+            .collect()
+    }
+
+    #[test]
+    fn finds_comments_and_strings() {
+        let source = "let s = \"a // b\"; // note \"x\"\n/* multi\nline */ f('\\'', 'a', &'a str)";
+        assert_eq!(
+            kinds(source, &RUST),
+            [
+                ("\"a // b\"", RegionKind::String),
+                ("// note \"x\"", RegionKind::Comment),
+                ("/* multi\nline */", RegionKind::Comment),
+                ("'\\''", RegionKind::String),
+                ("'a'", RegionKind::String),
+            ]
+        );
+
+        let source = "x = '''doc\n# not a comment''' # it's a comment\ny = 'it\\'s'";
+        assert_eq!(
+            kinds(source, &PYTHON),
+            [
+                ("'''doc\n# not a comment'''", RegionKind::String),
+                ("# it's a comment", RegionKind::Comment),
+                ("'it\\'s'", RegionKind::String),
+            ]
+        );
+
+        // an unterminated quote ends with the line
+        let source = "--[[ block ]] print(\"open\nx -- done";
+        assert_eq!(
+            kinds(source, &LUA),
+            [
+                ("--[[ block ]]", RegionKind::Comment),
+                ("\"open", RegionKind::String),
+                ("-- done", RegionKind::Comment),
+            ]
+        );
+    }
+
+    #[test]
+    fn kind_at_looks_up_the_enclosing_region() {
+        let source = b"a \"b\" c // d";
+        let regions = regions(source, &C_LIKE);
+        assert_eq!(kind_at(&regions, 0), None);
+        assert_eq!(kind_at(&regions, 3), Some(RegionKind::String));
+        assert_eq!(kind_at(&regions, 5), None);
+        assert_eq!(kind_at(&regions, 10), Some(RegionKind::Comment));
+    }
+}
@@ -0,0 +1,241 @@
+//! Tab completion of the constraint token under the cursor.
+
+use ahash::AHashMap;
+
+use crate::types::FileItem;
+
+/// Completions returned for one prefix.
+const MAX_COMPLETIONS: usize = 50;
+
+const GIT_STATUS_KEYS: &[&str] = &["status", "st", "g", "git"];
+const GIT_STATUSES: &[&str] = &["modified", "untracked", "staged", "clean", "tracked"];
+const KEYS: &[&str] = &["type:", "status:", "tracked:", "ignored:", "root:"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// A constraint key such as `status:`.
+    Key,
+    /// `*.rs`
+    Extension,
+    /// `status:modified`
+    GitStatus,
+    /// `src/`
+    PathSegment,
+}
+
+impl CompletionKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Key => "key",
+            Self::Extension => "extension",
+            Self::GitStatus => "git_status",
+            Self::PathSegment => "path_segment",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintCompletion {
+    /// The whole token that replaces the prefix, e.g. `*.rs` for `*.r`.
+    pub text: String,
+    pub kind: CompletionKind,
+    /// Indexed files the constraint keeps, 0 for keys and git statuses.
+    pub count: usize,
+}
+
+impl ConstraintCompletion {
+    fn new(text: String, kind: CompletionKind, count: usize) -> Self {
+        Self { text, kind, count }
+    }
+}
+
+/// Completions of the constraint token `prefix`: extensions for `*.`,
+/// statuses for `status:`, directory names for anything else, each with the
+/// number of indexed files it keeps. A leading `!` is kept on every
+/// completion. Extensions and directories are ordered by file count.
+pub(crate) fn complete_constraint<'a>(
+    files: impl Iterator<Item = &'a FileItem>,
+    prefix: &str,
+) -> Vec<ConstraintCompletion> {
+    if let Some(inner) = prefix.strip_prefix('!') {
+        let mut completions = complete_constraint(files, inner);
+        for completion in &mut completions {
+            completion.text.insert(0, '!');
+        }
+        return completions;
+    }
+
+    if let Some(ext_prefix) = prefix.strip_prefix("*.") {
+        return complete_extension(files, ext_prefix);
+    }
+
+    if let Some((key, value)) = prefix.split_once(':') {
+        if !GIT_STATUS_KEYS.contains(&key) {
+            // `type:` values are not indexed, anything else is not a constraint.
+            return Vec::new();
+        }
+        return GIT_STATUSES
+            .iter()
+            .filter(|status| status.starts_with(value))
+            .map(|status| {
+                ConstraintCompletion::new(format!("{key}:{status}"), CompletionKind::GitStatus, 0)
+            })
+            .collect();
+    }
+
+    let mut completions: Vec<ConstraintCompletion> = KEYS
+        .iter()
+        .filter(|key| !prefix.is_empty() && key.starts_with(prefix))
+        .map(|key| ConstraintCompletion::new(key.to_string(), CompletionKind::Key, 0))
+        .collect();
+    completions.extend(complete_path_segment(files, prefix));
+    completions.truncate(MAX_COMPLETIONS);
+    completions
+}
+
+fn complete_extension<'a>(
+    files: impl Iterator<Item = &'a FileItem>,
+    ext_prefix: &str,
+) -> Vec<ConstraintCompletion> {
+    let mut counts: AHashMap<String, usize> = AHashMap::new();
+    for file in files {
+        let Some((_, ext)) = file.file_name().rsplit_once('.') else {
+            continue;
+        };
+        // Extension constraints ignore case, so do the counts.
+        if !ext.is_empty() && starts_with_ignore_case(ext, ext_prefix) {
+            *counts.entry(ext.to_ascii_lowercase()).or_default() += 1;
+        }
+    }
+
+    ranked(counts, |ext| format!("*.{ext}"), CompletionKind::Extension)
+}
+
+fn complete_path_segment<'a>(
+    files: impl Iterator<Item = &'a FileItem>,
+    prefix: &str,
+) -> Vec<ConstraintCompletion> {
+    // `/src/` and `src/` are the same constraint, keep the form the user typed.
+    let (leading_slash, name_prefix) = match prefix.strip_prefix('/') {
+        Some(rest) => ("/", rest),
+        None => ("", prefix),
+    };
+    let name_prefix = name_prefix.trim_end_matches('/');
+
+    let mut counts: AHashMap<&str, usize> = AHashMap::new();
+    let mut seen_in_file: Vec<&str> = Vec::new();
+    for file in files {
+        let Some((dirs, _)) = file.relative_path().rsplit_once('/') else {
+            continue;
+        };
+        // A file under `a/src/b/src/` counts once for `src`.
+        seen_in_file.clear();
+        for dir in dirs.split('/') {
+            if starts_with_ignore_case(dir, name_prefix) && !seen_in_file.contains(&dir) {
+                seen_in_file.push(dir);
+                *counts.entry(dir).or_default() += 1;
+            }
+        }
+    }
+
+    ranked(
+        counts,
+        |dir| format!("{leading_slash}{dir}/"),
+        CompletionKind::PathSegment,
+    )
+}
+
+fn ranked<K: AsRef<str>>(
+    counts: AHashMap<K, usize>,
+    text: impl Fn(&str) -> String,
+    kind: CompletionKind,
+) -> Vec<ConstraintCompletion> {
+    let mut counts: Vec<(K, usize)> = counts.into_iter().collect();
+    counts.sort_unstable_by(|(a, a_count), (b, b_count)| {
+        b_count
+            .cmp(a_count)
+            .then_with(|| a.as_ref().cmp(b.as_ref()))
+    });
+    counts
+        .into_iter()
+        .take(MAX_COMPLETIONS)
+        .map(|(name, count)| ConstraintCompletion::new(text(name.as_ref()), kind, count))
+        .collect()
+}
+
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    text.len() >= prefix.len()
+        && text.is_char_boundary(prefix.len())
+        && text[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    fn files(paths: &[&str]) -> Vec<FileItem> {
+        let base = Path::new("/repo");
+        paths
+            .iter()
+            .map(|path| FileItem::new(PathBuf::from("/repo").join(path), base, None))
+            .collect()
+    }
+
+    fn texts(completions: &[ConstraintCompletion]) -> Vec<(&str, usize)> {
+        completions
+            .iter()
+            .map(|c| (c.text.as_str(), c.count))
+            .collect()
+    }
+
+    #[test]
+    fn completes_extensions_by_file_count() {
+        let files = files(&["src/a.rs", "src/b.rs", "lua/c.lua", "README.md", "src/D.RS"]);
+        assert_eq!(
+            texts(&complete_constraint(files.iter(), "*.")),
+            vec![("*.rs", 3), ("*.lua", 1), ("*.md", 1)]
+        );
+        assert_eq!(
+            texts(&complete_constraint(files.iter(), "!*.l")),
+            vec![("!*.lua", 1)]
+        );
+    }
+
+    #[test]
+    fn completes_path_segments_and_keys() {
+        let files = files(&[
+            "src/a.rs",
+            "src/stats/b.rs",
+            "crates/x/src/c.rs",
+            "lua/d.lua",
+        ]);
+        assert_eq!(
+            texts(&complete_constraint(files.iter(), "s")),
+            vec![("status:", 0), ("src/", 3), ("stats/", 1)]
+        );
+        assert_eq!(
+            texts(&complete_constraint(files.iter(), "/sr")),
+            vec![("/src/", 3)]
+        );
+    }
+
+    #[test]
+    fn completes_git_statuses() {
+        assert_eq!(
+            texts(&complete_constraint([].iter(), "st:")),
+            vec![
+                ("st:modified", 0),
+                ("st:untracked", 0),
+                ("st:staged", 0),
+                ("st:clean", 0),
+                ("st:tracked", 0)
+            ]
+        );
+        assert_eq!(
+            texts(&complete_constraint([].iter(), "status:u")),
+            vec![("status:untracked", 0)]
+        );
+        assert!(complete_constraint([].iter(), "type:r").is_empty());
+    }
+}
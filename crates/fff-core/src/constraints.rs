@@ -7,10 +7,22 @@
 //! different search modes (file picker, live grep, etc.).
 
 use ahash::AHashSet;
-use fff_query_parser::{Constraint, GitStatusFilter};
+use fff_query_parser::{Constraint, FFFQuery, GitStatusFilter};
 use smallvec::SmallVec;
 
+use crate::file_list::FileList;
 use crate::git::is_modified_status;
+use crate::types::FileItem;
+
+/// Hide the files matching any of `globs` from `query`, as if each was typed
+/// as `!glob`.
+pub fn exclude_globs<'a>(query: &mut FFFQuery<'a>, globs: &'a [String]) {
+    query.constraints.extend(
+        globs
+            .iter()
+            .map(|glob| Constraint::Not(Box::new(Constraint::Glob(glob)))),
+    );
+}
 
 /// Case-insensitive ASCII substring search without allocation.
 /// `needle` must already be lowercase.
@@ -130,20 +142,17 @@ fn item_matches_constraint_at_index<T: Constrainable>(
     item: &T,
     item_index: usize,
     constraint: &Constraint<'_>,
-    glob_results: &[(bool, AHashSet<usize>)],
+    glob_results: &[AHashSet<usize>],
     glob_idx: &mut usize,
     negate: bool,
 ) -> bool {
     let matches = match constraint {
         Constraint::Extension(ext) => file_has_extension(item.file_name(), ext),
         Constraint::Glob(_) => {
+            // a negated glob is flipped by `negate`, the set only has its matches
             let result = glob_results
                 .get(*glob_idx)
-                .map(|(is_neg, set)| {
-                    let matched = set.contains(&item_index);
-                    if *is_neg { !matched } else { matched }
-                })
-                .unwrap_or(true);
+                .is_none_or(|set| set.contains(&item_index));
             *glob_idx += 1;
             return if negate { !result } else { result };
         }
@@ -161,6 +170,10 @@ fn item_matches_constraint_at_index<T: Constrainable>(
             ),
             (Some(status), GitStatusFilter::Unmodified) => status.is_empty(),
             (None, GitStatusFilter::Unmodified) => true,
+            (Some(status), GitStatusFilter::Tracked) => {
+                !status.intersects(git2::Status::WT_NEW | git2::Status::IGNORED)
+            }
+            (None, GitStatusFilter::Tracked) => true,
             (None, _) => false,
         },
         Constraint::Not(inner) => {
@@ -177,8 +190,11 @@ fn item_matches_constraint_at_index<T: Constrainable>(
         // only works with negation
         Constraint::Text(text) => contains_ascii_ci(item.relative_path(), text),
 
-        // Parts and Exclude are handled at a higher level
+        // Parts and Exclude are handled at a higher level, `ignored:` and
+        // `root:` pick the files that are searched and `line:` filters the
+        // grep matches
         Constraint::Parts(_) | Constraint::Exclude(_) | Constraint::FileType(_) => true,
+        Constraint::Ignored | Constraint::Root(_) | Constraint::Line(_) => return true,
     };
 
     if negate { !matches } else { matches }
@@ -190,10 +206,10 @@ fn item_matches_constraint_at_index<T: Constrainable>(
 /// All other constraints are combined with AND logic.
 ///
 /// Uses parallel iteration via rayon when the item count exceeds [`PAR_THRESHOLD`].
-pub fn apply_constraints<'a, T: Constrainable + Sync>(
-    items: &'a [T],
+pub fn apply_constraints<'a>(
+    items: &'a FileList,
     constraints: &[Constraint<'_>],
-) -> Option<Vec<&'a T>> {
+) -> Option<Vec<&'a FileItem>> {
     if constraints.is_empty() {
         return None;
     }
@@ -221,7 +237,7 @@ pub fn apply_constraints<'a, T: Constrainable + Sync>(
         Vec::new()
     };
 
-    let matches_constraints = |i: usize, item: &T| -> bool {
+    let matches_constraints = |i: usize, item: &FileItem| -> bool {
         if !extensions.is_empty()
             && !extensions
                 .iter()
@@ -243,7 +259,7 @@ pub fn apply_constraints<'a, T: Constrainable + Sync>(
         })
     };
 
-    let filtered: Vec<&T> = if items.len() >= PAR_THRESHOLD {
+    let filtered: Vec<&FileItem> = if items.len() >= PAR_THRESHOLD {
         use rayon::prelude::*;
         items
             .par_iter()
@@ -266,10 +282,10 @@ pub fn apply_constraints<'a, T: Constrainable + Sync>(
 fn precompute_glob_matches<'a>(
     constraints: &[&Constraint<'a>],
     paths: &[&str],
-) -> Vec<(bool, AHashSet<usize>)> {
+) -> Vec<AHashSet<usize>> {
     let mut results = Vec::new();
     for constraint in constraints {
-        collect_glob_indices(constraint, paths, &mut results, false);
+        collect_glob_indices(constraint, paths, &mut results);
     }
     results
 }
@@ -277,17 +293,11 @@ fn precompute_glob_matches<'a>(
 fn collect_glob_indices<'a>(
     constraint: &Constraint<'a>,
     paths: &[&str],
-    results: &mut Vec<(bool, AHashSet<usize>)>,
-    is_negated: bool,
+    results: &mut Vec<AHashSet<usize>>,
 ) {
     match constraint {
-        Constraint::Glob(pattern) => {
-            let indices = match_glob_pattern(pattern, paths);
-            results.push((is_negated, indices));
-        }
-        Constraint::Not(inner) => {
-            collect_glob_indices(inner, paths, results, !is_negated);
-        }
+        Constraint::Glob(pattern) => results.push(match_glob_pattern(pattern, paths)),
+        Constraint::Not(inner) => collect_glob_indices(inner, paths, results),
         _ => {}
     }
 }
@@ -0,0 +1,144 @@
+//! Structured crash reports written by the panic hook.
+//!
+//! Searches register what they are working on while they run, so a report
+//! says which query and how large an index was involved instead of only
+//! where the panic happened. The latest report is kept in the cache dir
+//! and can be read back with [`last_crash_report`] for bug reports.
+
+use crate::metrics::SearchKind;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const CRASH_REPORT_FILE: &str = "fff_crash_report.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrashQuery {
+    pub kind: String,
+    pub query: String,
+    /// Whether the search was still running when the panic happened.
+    pub in_progress: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrashReport {
+    /// Seconds since the unix epoch.
+    pub timestamp: u64,
+    pub fff_version: String,
+    pub os: String,
+    pub arch: String,
+    pub thread: String,
+    pub message: String,
+    pub location: String,
+    /// The most recent search, which is usually the one that crashed.
+    pub last_query: Option<CrashQuery>,
+    pub indexed_files: usize,
+    pub backtrace: String,
+}
+
+static LAST_QUERY: Mutex<Option<CrashQuery>> = Mutex::new(None);
+static INDEXED_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Marks the search registered by [`enter_search`] as finished when dropped.
+pub(crate) struct SearchGuard;
+
+impl Drop for SearchGuard {
+    fn drop(&mut self) {
+        if let Some(last) = LAST_QUERY.lock().as_mut() {
+            last.in_progress = false;
+        }
+    }
+}
+
+/// Remember the search about to run so it ends up in a crash report.
+pub(crate) fn enter_search(kind: SearchKind, query: &str, indexed_files: usize) -> SearchGuard {
+    INDEXED_FILES.store(indexed_files, Ordering::Relaxed);
+    *LAST_QUERY.lock() = Some(CrashQuery {
+        kind: kind.name().to_string(),
+        query: query.to_string(),
+        in_progress: true,
+    });
+    SearchGuard
+}
+
+/// Where the latest crash report is stored.
+pub fn crash_report_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(CRASH_REPORT_FILE))
+}
+
+pub(crate) fn build_report(message: &str, location: &str) -> CrashReport {
+    // never block inside the panic hook
+    let last_query = LAST_QUERY.try_lock().and_then(|q| q.clone());
+    CrashReport::new(
+        message,
+        location,
+        last_query,
+        INDEXED_FILES.load(Ordering::Relaxed),
+    )
+}
+
+impl CrashReport {
+    fn new(
+        message: &str,
+        location: &str,
+        last_query: Option<CrashQuery>,
+        indexed_files: usize,
+    ) -> Self {
+        Self {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            fff_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            thread: std::thread::current()
+                .name()
+                .unwrap_or("<unnamed>")
+                .to_string(),
+            message: message.to_string(),
+            location: location.to_string(),
+            last_query,
+            indexed_files,
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+        }
+    }
+}
+
+/// Overwrite the crash report file with `report`.
+pub(crate) fn write_report(report: &CrashReport) -> Option<PathBuf> {
+    let path = crash_report_path()?;
+    let json = serde_json::to_vec_pretty(report).ok()?;
+    std::fs::write(&path, json).ok()?;
+    Some(path)
+}
+
+/// The report of the latest panic, if one was ever written.
+pub fn last_crash_report() -> Option<CrashReport> {
+    let content = std::fs::read(crash_report_path()?).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_survives_a_roundtrip_through_the_file_format() {
+        let query = CrashQuery {
+            kind: SearchKind::Grep.name().into(),
+            query: "needle".into(),
+            in_progress: true,
+        };
+        let report = CrashReport::new("boom", "src/grep.rs:1:1", Some(query.clone()), 42);
+
+        assert_eq!(report.message, "boom");
+        assert_eq!(report.last_query, Some(query));
+        assert_eq!(report.fff_version, env!("CARGO_PKG_VERSION"));
+        assert!(!report.backtrace.is_empty());
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<CrashReport>(&json).unwrap(), report);
+    }
+}
@@ -1,10 +1,13 @@
 use crate::error::Result;
+use crate::storage::{Storage, StorageBackend};
 
 /// Health information about a database
 #[derive(Debug, Clone)]
 pub struct DbHealth {
     /// Path to the database file
     pub path: String,
+    /// Engine the database is stored in
+    pub backend: StorageBackend,
     /// Size on disk in bytes
     pub disk_size: u64,
     /// Entry counts by table name
@@ -12,20 +15,31 @@ pub struct DbHealth {
 }
 
 pub trait DbHealthChecker {
-    fn get_env(&self) -> &heed::Env;
     fn count_entries(&self) -> Result<Vec<(&'static str, u64)>>;
+    fn get_health(&self) -> Result<DbHealth>;
+}
 
-    fn get_health(&self) -> Result<DbHealth> {
-        let env = self.get_env();
-
-        let size = env.real_disk_size().map_err(crate::error::Error::EnvOpen)?;
-        let path = env.path().to_string_lossy().to_string();
-        let entry_counts = self.count_entries()?;
+/// [`DbHealthChecker::get_health`] of a tracker stored in `storage`.
+pub(crate) fn storage_health(
+    storage: &dyn Storage,
+    entry_counts: Vec<(&'static str, u64)>,
+) -> Result<DbHealth> {
+    Ok(DbHealth {
+        path: storage.path().to_string_lossy().to_string(),
+        backend: storage.backend(),
+        disk_size: storage.disk_size()?,
+        entry_counts,
+    })
+}
 
-        Ok(DbHealth {
-            path,
-            disk_size: size,
-            entry_counts,
-        })
-    }
+/// Outcome of rebuilding a database from the entries that could still be read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DbRepair {
+    /// Entries copied into the rebuilt database.
+    pub kept: usize,
+    /// Entries that failed to read or decode and were left out.
+    pub dropped: usize,
+    /// The old database could not be opened at all. Its data file was moved
+    /// aside with a `.corrupt` suffix and an empty one was created.
+    pub reset: bool,
 }
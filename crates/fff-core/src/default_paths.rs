@@ -0,0 +1,166 @@
+//! Default locations of the frecency and query history databases.
+//!
+//! fff.nvim keeps its databases in Neovim's `stdpath('cache')` and
+//! `stdpath('data')`. Other frontends on a machine with Neovim open the same
+//! files, so a file opened in the MCP server or a script ranks higher in the
+//! editor too. Without Neovim they go to the platform data directory.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+
+/// Where an older MCP server kept its databases when Neovim was missing.
+const LEGACY_DIR: &str = ".fff";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefaultDbPaths {
+    pub frecency: PathBuf,
+    pub history: PathBuf,
+}
+
+/// `stdpath('cache')` and `stdpath('data')` of Neovim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NeovimDirs {
+    cache: PathBuf,
+    data: PathBuf,
+}
+
+/// Neovim's directories from the environment and the home and local data
+/// directories, following `:h base-directories`: `XDG_CACHE_HOME` and
+/// `XDG_DATA_HOME` on every platform, `NVIM_APPNAME` for the subdirectory,
+/// and `-data` appended to the data directory on Windows.
+fn neovim_dirs(
+    env: impl Fn(&str) -> Option<OsString>,
+    home: &Path,
+    local_data: Option<&Path>,
+    windows: bool,
+) -> Option<NeovimDirs> {
+    let app_name = env("NVIM_APPNAME")
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "nvim".into());
+    let xdg = |name: &str| env(name).filter(|dir| !dir.is_empty()).map(PathBuf::from);
+
+    let (cache_home, data_home) = if windows {
+        let local_data = local_data?;
+        (local_data.join("Temp"), local_data.to_path_buf())
+    } else {
+        (home.join(".cache"), home.join(".local").join("share"))
+    };
+    let cache_home = xdg("XDG_CACHE_HOME").unwrap_or(cache_home);
+    let data_home = xdg("XDG_DATA_HOME").unwrap_or(data_home);
+
+    let mut data_name = app_name.clone();
+    if windows {
+        data_name.push("-data");
+    }
+    Some(NeovimDirs {
+        cache: cache_home.join(app_name),
+        data: data_home.join(data_name),
+    })
+}
+
+/// fff's own data directory, `$XDG_DATA_HOME/fff` on Linux and BSD,
+/// `~/Library/Application Support/fff` on macOS and `%LOCALAPPDATA%\fff` on
+/// Windows. Created when missing.
+pub fn default_data_dir() -> Result<PathBuf, Error> {
+    let dir = dirs::data_local_dir().ok_or(Error::NoDataDir)?.join("fff");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// The databases every frontend opens unless told otherwise: the ones of
+/// fff.nvim when Neovim has been used on this machine, then the `~/.fff`
+/// directory of older MCP servers, then [`default_data_dir`]. The parent
+/// directories are created.
+pub fn default_db_paths() -> Result<DefaultDbPaths, Error> {
+    let home = dirs::home_dir().ok_or(Error::NoDataDir)?;
+    let neovim = neovim_dirs(
+        |name| std::env::var_os(name),
+        &home,
+        dirs::data_local_dir().as_deref(),
+        cfg!(windows),
+    )
+    .filter(|dirs| dirs.cache.exists() || dirs.data.exists());
+    let legacy = home.join(LEGACY_DIR);
+
+    let paths = if let Some(neovim) = neovim {
+        DefaultDbPaths {
+            frecency: neovim.cache.join("fff_nvim"),
+            history: neovim.data.join("fff_queries"),
+        }
+    } else if legacy.is_dir() {
+        DefaultDbPaths {
+            frecency: legacy.join("frecency.mdb"),
+            history: legacy.join("history.mdb"),
+        }
+    } else {
+        let dir = default_data_dir()?;
+        DefaultDbPaths {
+            frecency: dir.join("frecency"),
+            history: dir.join("history"),
+        }
+    };
+
+    for path in [&paths.frecency, &paths.history] {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<OsString> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.into())
+        }
+    }
+
+    #[test]
+    fn neovim_dirs_follow_xdg_and_appname() {
+        let home = Path::new("/home/u");
+        assert_eq!(
+            neovim_dirs(env(&[]), home, None, false),
+            Some(NeovimDirs {
+                cache: "/home/u/.cache/nvim".into(),
+                data: "/home/u/.local/share/nvim".into(),
+            })
+        );
+
+        let vars = [
+            ("XDG_CACHE_HOME", "/xdg/cache"),
+            ("XDG_DATA_HOME", ""),
+            ("NVIM_APPNAME", "lazyvim"),
+        ];
+        assert_eq!(
+            neovim_dirs(env(&vars), home, None, false),
+            Some(NeovimDirs {
+                cache: "/xdg/cache/lazyvim".into(),
+                data: "/home/u/.local/share/lazyvim".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn neovim_data_dir_has_a_suffix_on_windows() {
+        let local = Path::new("/appdata/local");
+        assert_eq!(
+            neovim_dirs(env(&[]), Path::new("/users/u"), Some(local), true),
+            Some(NeovimDirs {
+                cache: local.join("Temp").join("nvim"),
+                data: local.join("nvim-data"),
+            })
+        );
+        assert_eq!(
+            neovim_dirs(env(&[]), Path::new("/users/u"), None, true),
+            None
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Finding indexed files with identical content.
+
+use ahash::AHashMap;
+use rayon::prelude::*;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::types::{FileItem, load_file_content};
+
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup<'a> {
+    /// Size of each file in bytes.
+    pub size: u64,
+    /// xxh3 hash of the shared content.
+    pub hash: u64,
+    /// At least two files, sorted by relative path.
+    pub files: Vec<&'a FileItem>,
+}
+
+impl DuplicateGroup<'_> {
+    /// Bytes freed by keeping a single copy.
+    pub fn wasted_bytes(&self) -> u64 {
+        self.size * (self.files.len() as u64 - 1)
+    }
+}
+
+/// Groups the files of at least `min_size` bytes by content. Only files that
+/// share their size with another file are read, so most of the index is never
+/// touched. Groups are sorted by [`DuplicateGroup::wasted_bytes`], largest
+/// first.
+pub(crate) fn find_duplicates<'a>(
+    files: impl Iterator<Item = &'a FileItem>,
+    min_size: u64,
+) -> Vec<DuplicateGroup<'a>> {
+    let mut by_size: AHashMap<u64, Vec<&'a FileItem>> = AHashMap::new();
+    for file in files.filter(|f| !f.is_deleted && f.size > 0 && f.size >= min_size) {
+        by_size.entry(file.size).or_default().push(file);
+    }
+
+    // Read the candidates directly: hashing every asset must not evict the
+    // grep content cache.
+    let hashed: Vec<(u64, u64, &'a FileItem)> = by_size
+        .into_values()
+        .filter(|same_size| same_size.len() > 1)
+        .flatten()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .filter_map(|file| {
+            let content = load_file_content(file.path(), file.size)?;
+            // The file changed since it was indexed.
+            if content.len() as u64 != file.size {
+                return None;
+            }
+            Some((file.size, xxh3_64(&content), file))
+        })
+        .collect();
+
+    let mut by_content: AHashMap<(u64, u64), Vec<&'a FileItem>> = AHashMap::new();
+    for (size, hash, file) in hashed {
+        by_content.entry((size, hash)).or_default().push(file);
+    }
+
+    let mut groups: Vec<DuplicateGroup<'a>> = by_content
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .map(|((size, hash), mut files)| {
+            files.sort_unstable_by(|a, b| a.relative_path().cmp(b.relative_path()));
+            DuplicateGroup { size, hash, files }
+        })
+        .collect();
+    groups.sort_unstable_by(|a, b| {
+        b.wasted_bytes()
+            .cmp(&a.wasted_bytes())
+            .then_with(|| a.files[0].relative_path().cmp(b.files[0].relative_path()))
+    });
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write(tmp: &TempDir, name: &str, contents: &str) -> FileItem {
+        let path = tmp.path().join(name);
+        fs::write(&path, contents).unwrap();
+        FileItem::new(path, tmp.path(), None)
+    }
+
+    #[test]
+    fn groups_files_with_equal_content() {
+        let tmp = TempDir::new().unwrap();
+        let files = vec![
+            write(&tmp, "b.txt", "same content"),
+            write(&tmp, "a.txt", "same content"),
+            write(&tmp, "c.txt", "diff content"),
+            write(&tmp, "d.txt", "tiny"),
+            write(&tmp, "e.txt", "tiny"),
+        ];
+
+        let groups = find_duplicates(files.iter(), 5);
+        assert_eq!(groups.len(), 1);
+        let paths: Vec<&str> = groups[0].files.iter().map(|f| f.relative_path()).collect();
+        assert_eq!(paths, vec!["a.txt", "b.txt"]);
+        assert_eq!(groups[0].wasted_bytes(), 12);
+
+        assert_eq!(find_duplicates(files.iter(), 0).len(), 2);
+    }
+}
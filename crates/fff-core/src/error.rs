@@ -19,8 +19,12 @@ pub enum Error {
     AcquireItemLock,
     #[error("Failed to acquire lock for path cache")]
     AcquirePathCacheLock,
+    #[error("No home or data directory found for the current user")]
+    NoDataDir,
     #[error("Failed to create directory: {0}")]
     CreateDir(#[from] std::io::Error),
+    #[error("I/O error on {0}: {1}")]
+    Io(std::path::PathBuf, #[source] std::io::Error),
     #[error("Failed to open frecency database env: {0}")]
     EnvOpen(#[source] heed::Error),
     #[error("Failed to create frecency database: {0}")]
@@ -39,6 +43,26 @@ pub enum Error {
     DbWrite(#[source] heed::Error),
     #[error("Failed to commit write transaction to frecency database: {0}")]
     DbCommit(#[source] heed::Error),
+    #[error("Failed to grow the database map: {0}")]
+    DbResize(#[source] heed::Error),
+    #[error("Failed to decode a database value: {0}")]
+    DbDecode(#[source] bincode::Error),
+    #[error("Failed to encode a database value: {0}")]
+    DbEncode(#[source] bincode::Error),
+    #[error("redb database error: {0}")]
+    Redb(#[source] Box<redb::Error>),
+    #[error("Database {0:?} is open in another process, and redb databases can't be shared")]
+    DatabaseInUse(std::path::PathBuf),
+    #[error("Unknown storage backend {0:?}, expected \"lmdb\" or \"redb\"")]
+    UnknownStorageBackend(String),
+    #[error("Unknown export format {0:?}, expected \"quickfix\", \"sarif\" or \"text\"")]
+    UnknownExportFormat(String),
+    #[error("Invalid keyword pattern {0:?}: {1}")]
+    InvalidKeywordPattern(String, #[source] regex::Error),
+    #[error("Failed to create thread pool: {0}")]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+    #[error("File picker is read-only")]
+    ReadOnly,
     #[error("Failed to start file system watcher: {0}")]
     FileSystemWatch(#[from] notify::Error),
 
@@ -47,6 +71,49 @@ pub enum Error {
 
     #[error("libgit2 error occurred: {0}")]
     Git(#[from] git2::Error),
+    #[error("git status failed: {0}")]
+    GitCli(String),
+    #[error("Unknown git status provider {0:?}, expected \"library\", \"cli\" or \"auto\"")]
+    UnknownGitStatusProvider(String),
+
+    #[cfg(feature = "gix")]
+    #[error("gitoxide error occurred: {0}")]
+    Gix(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Error {
+    /// Whether the error means the database file is damaged, so rebuilding it
+    /// can help. Errors like another process holding the database are not.
+    pub fn is_corruption(&self) -> bool {
+        use heed::MdbError;
+
+        match self {
+            Error::EnvOpen(e)
+            | Error::DbCreate(e)
+            | Error::DbStartReadTxn(e)
+            | Error::DbStartWriteTxn(e)
+            | Error::DbRead(e)
+            | Error::DbWrite(e)
+            | Error::DbCommit(e) => matches!(
+                e,
+                heed::Error::Mdb(
+                    MdbError::Corrupted
+                        | MdbError::PageNotFound
+                        | MdbError::Panic
+                        | MdbError::Invalid
+                        | MdbError::VersionMismatch
+                ) | heed::Error::Decoding(_)
+            ),
+            Error::DbDecode(_) => true,
+            Error::Redb(e) => match &**e {
+                redb::Error::Corrupted(_) | redb::Error::UpgradeRequired(_) => true,
+                // the file doesn't start with redb's header
+                redb::Error::Io(io) => io.kind() == std::io::ErrorKind::InvalidData,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
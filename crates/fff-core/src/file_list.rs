@@ -0,0 +1,456 @@
+//! The indexed files, stored in fixed-size chunks shared between snapshots.
+//!
+//! Every [`PickerSnapshot`](crate::PickerSnapshot) holds the file list of the
+//! picker at the time it was taken. Keeping the files in one `Vec` would copy
+//! all of them on the first write after a snapshot; with chunks a watcher
+//! event copies only the chunk of the file it touches, and every other chunk
+//! stays shared with the snapshots.
+
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::{Index, IndexMut, Range};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use rayon::prelude::*;
+
+use crate::types::FileItem;
+
+/// Files per chunk. Every chunk but the last is full, so the chunk of an
+/// index is a shift away.
+const CHUNK_LEN: usize = 1024;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+type Chunk = Arc<Vec<FileItem>>;
+
+/// An indexed list of [`FileItem`]s with cheap clones: cloning shares the
+/// chunks, and mutating a file copies only its chunk when it is shared.
+#[derive(Clone)]
+pub struct FileList {
+    chunks: Vec<Chunk>,
+    len: usize,
+    id: u64,
+    /// `(address, chunk)` sorted by the address of the chunk's first file,
+    /// for [`Self::index_of`]. Built on first use after a mutation.
+    by_address: OnceLock<Vec<(usize, usize)>>,
+}
+
+impl Default for FileList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for FileList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl FileList {
+    pub fn new() -> Self {
+        Self {
+            chunks: Vec::new(),
+            len: 0,
+            id: next_id(),
+            by_address: OnceLock::new(),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Identifies the contents of the list: clones share it and every
+    /// mutation changes it.
+    #[inline]
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&FileItem> {
+        self.chunks
+            .get(index / CHUNK_LEN)
+            .and_then(|chunk| chunk.get(index % CHUNK_LEN))
+    }
+
+    pub fn first(&self) -> Option<&FileItem> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&FileItem> {
+        self.chunks.last().and_then(|chunk| chunk.last())
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        self.iter_range(0..self.len)
+    }
+
+    /// The files at `range`, which must be within the list.
+    pub fn iter_range(&self, range: Range<usize>) -> Iter<'_> {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "range {range:?} out of bounds for {} files",
+            self.len
+        );
+        if range.is_empty() {
+            return Iter::default();
+        }
+        let (first, last) = (range.start / CHUNK_LEN, (range.end - 1) / CHUNK_LEN);
+        let (start, end) = (range.start % CHUNK_LEN, (range.end - 1) % CHUNK_LEN + 1);
+        if first == last {
+            return Iter {
+                front: self.chunks[first][start..end].iter(),
+                middle: [].iter(),
+                back: [].iter(),
+                remaining: range.len(),
+            };
+        }
+        Iter {
+            front: self.chunks[first][start..].iter(),
+            middle: self.chunks[first + 1..last].iter(),
+            back: self.chunks[last][..end].iter(),
+            remaining: range.len(),
+        }
+    }
+
+    pub fn par_iter(&self) -> impl IndexedParallelIterator<Item = &FileItem> {
+        (0..self.len).into_par_iter().map(move |index| &self[index])
+    }
+
+    /// Binary search among the first `end` files, which must be sorted by `f`.
+    pub fn binary_search_by<F>(&self, end: usize, mut f: F) -> Result<usize, usize>
+    where
+        F: FnMut(&FileItem) -> std::cmp::Ordering,
+    {
+        let (mut low, mut high) = (0, end.min(self.len));
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match f(&self[mid]) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(low)
+    }
+
+    /// Index of `file`, which must be borrowed from this list rather than
+    /// equal to one of its files. `None` for any other reference.
+    pub fn index_of(&self, file: &FileItem) -> Option<usize> {
+        let address = std::ptr::from_ref(file).addr();
+        let by_address = self.by_address.get_or_init(|| {
+            let mut by_address: Vec<(usize, usize)> = self
+                .chunks
+                .iter()
+                .enumerate()
+                .map(|(index, chunk)| (chunk.as_ptr().addr(), index))
+                .collect();
+            by_address.sort_unstable();
+            by_address
+        });
+        let after = by_address.partition_point(|&(start, _)| start <= address);
+        let &(start, chunk) = by_address.get(after.checked_sub(1)?)?;
+        let offset = address - start;
+        let position = offset / size_of::<FileItem>();
+        (offset.is_multiple_of(size_of::<FileItem>()) && position < self.chunks[chunk].len())
+            .then_some(chunk * CHUNK_LEN + position)
+    }
+
+    pub fn to_vec(&self) -> Vec<FileItem> {
+        self.iter().cloned().collect()
+    }
+
+    /// Number of chunks `self` and `other` share rather than hold copies of.
+    pub fn shared_chunks(&self, other: &FileList) -> usize {
+        self.chunks
+            .iter()
+            .zip(&other.chunks)
+            .filter(|(a, b)| Arc::ptr_eq(a, b))
+            .count()
+    }
+
+    /// Called before every mutation.
+    fn touch(&mut self) {
+        self.id = next_id();
+        self.by_address.take();
+    }
+
+    /// Mutable access to a file, copying its chunk if a clone shares it.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut FileItem> {
+        if index >= self.len {
+            return None;
+        }
+        self.touch();
+        Arc::make_mut(&mut self.chunks[index / CHUNK_LEN]).get_mut(index % CHUNK_LEN)
+    }
+
+    /// Every file, mutably. Copies all shared chunks.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut FileItem> {
+        self.touch();
+        self.chunks
+            .iter_mut()
+            .flat_map(|chunk| Arc::make_mut(chunk).iter_mut())
+    }
+
+    /// Every file, mutably and in parallel. Copies all shared chunks.
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = &mut FileItem> {
+        self.touch();
+        self.chunks
+            .par_iter_mut()
+            .flat_map_iter(|chunk| Arc::make_mut(chunk).iter_mut())
+    }
+
+    pub fn push(&mut self, file: FileItem) {
+        self.touch();
+        match self.chunks.last_mut() {
+            Some(chunk) if chunk.len() < CHUNK_LEN => Arc::make_mut(chunk).push(file),
+            _ => {
+                let mut chunk = Vec::with_capacity(CHUNK_LEN);
+                chunk.push(file);
+                self.chunks.push(Arc::new(chunk));
+            }
+        }
+        self.len += 1;
+    }
+
+    /// Insert `file` at `index`, shifting the files after it. Copies the
+    /// chunks from the one of `index` on.
+    pub fn insert(&mut self, index: usize, file: FileItem) {
+        assert!(index <= self.len, "insert index {index} out of bounds");
+        let first = index / CHUNK_LEN;
+        let mut tail = self.split_off_chunks(first);
+        tail.insert(index - first * CHUNK_LEN, file);
+        self.extend(tail);
+    }
+
+    /// Remove the file at `index`, shifting the files after it. Copies the
+    /// chunks from the one of `index` on.
+    pub fn remove(&mut self, index: usize) -> FileItem {
+        assert!(index < self.len, "remove index {index} out of bounds");
+        let first = index / CHUNK_LEN;
+        let mut tail = self.split_off_chunks(first);
+        let file = tail.remove(index - first * CHUNK_LEN);
+        self.extend(tail);
+        file
+    }
+
+    /// Keep the files for which `keep` returns true. The chunks before the
+    /// first removed file stay shared.
+    pub fn retain(&mut self, mut keep: impl FnMut(&FileItem) -> bool) {
+        let kept: Vec<bool> = self.iter().map(&mut keep).collect();
+        let Some(first_removed) = kept.iter().position(|&kept| !kept) else {
+            return;
+        };
+        let first = first_removed / CHUNK_LEN;
+        let tail = self.split_off_chunks(first);
+        let mut kept = kept[first * CHUNK_LEN..].iter();
+        self.extend(tail.into_iter().filter(|_| *kept.next().unwrap_or(&true)));
+    }
+
+    /// Take the files of the chunks from `first` on out of the list.
+    fn split_off_chunks(&mut self, first: usize) -> Vec<FileItem> {
+        self.touch();
+        let tail: Vec<FileItem> = self
+            .chunks
+            .drain(first..)
+            .flat_map(Arc::unwrap_or_clone)
+            .collect();
+        self.len -= tail.len();
+        tail
+    }
+}
+
+impl Extend<FileItem> for FileList {
+    fn extend<I: IntoIterator<Item = FileItem>>(&mut self, files: I) {
+        for file in files {
+            self.push(file);
+        }
+    }
+}
+
+impl FromIterator<FileItem> for FileList {
+    fn from_iter<I: IntoIterator<Item = FileItem>>(files: I) -> Self {
+        let mut list = Self::new();
+        list.extend(files);
+        list
+    }
+}
+
+impl From<Vec<FileItem>> for FileList {
+    fn from(files: Vec<FileItem>) -> Self {
+        files.into_iter().collect()
+    }
+}
+
+impl Index<usize> for FileList {
+    type Output = FileItem;
+
+    #[inline]
+    fn index(&self, index: usize) -> &FileItem {
+        match self.get(index) {
+            Some(file) => file,
+            None => panic!("index {index} out of bounds for {} files", self.len),
+        }
+    }
+}
+
+impl IndexMut<usize> for FileList {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut FileItem {
+        let len = self.len;
+        match self.get_mut(index) {
+            Some(file) => file,
+            None => panic!("index {index} out of bounds for {len} files"),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a FileList {
+    type Item = &'a FileItem;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// Iterator over the files of a [`FileList`].
+#[derive(Clone, Default)]
+pub struct Iter<'a> {
+    front: std::slice::Iter<'a, FileItem>,
+    middle: std::slice::Iter<'a, Chunk>,
+    back: std::slice::Iter<'a, FileItem>,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a FileItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a FileItem> {
+        loop {
+            if let Some(file) = self.front.next() {
+                self.remaining -= 1;
+                return Some(file);
+            }
+            match self.middle.next() {
+                Some(chunk) => self.front = chunk.iter(),
+                None => {
+                    let file = self.back.next()?;
+                    self.remaining -= 1;
+                    return Some(file);
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(file) = self.back.next_back() {
+                self.remaining -= 1;
+                return Some(file);
+            }
+            match self.middle.next_back() {
+                Some(chunk) => self.back = chunk.iter(),
+                None => {
+                    let file = self.front.next_back()?;
+                    self.remaining -= 1;
+                    return Some(file);
+                }
+            }
+        }
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl FusedIterator for Iter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    fn file(index: usize) -> FileItem {
+        FileItem::new_with_metadata(
+            PathBuf::from(format!("/repo/{index:05}.rs")),
+            Path::new("/repo"),
+            None,
+            None,
+        )
+    }
+
+    fn names(files: impl IntoIterator<Item = FileItem>) -> Vec<String> {
+        files
+            .into_iter()
+            .map(|file| file.relative_path().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn behaves_like_a_vec() {
+        let mut expected: Vec<FileItem> = (0..3000).map(file).collect();
+        let mut list = FileList::from(expected.clone());
+
+        list.insert(5, file(9001));
+        expected.insert(5, file(9001));
+        list.remove(2500);
+        expected.remove(2500);
+        list.push(file(9002));
+        expected.push(file(9002));
+        list.retain(|file| !file.relative_path().ends_with("7.rs"));
+        expected.retain(|file| !file.relative_path().ends_with("7.rs"));
+
+        assert_eq!(list.len(), expected.len());
+        assert_eq!(names(list.to_vec()), names(expected.clone()));
+        assert_eq!(
+            names(list.iter().rev().cloned()),
+            names(expected.iter().rev().cloned())
+        );
+        assert_eq!(
+            names(list.iter_range(1000..2050).cloned()),
+            names(expected[1000..2050].iter().cloned())
+        );
+        assert_eq!(list.iter_range(1000..2050).len(), 1050);
+        for index in [0, 1023, 1024, 2047, expected.len() - 1] {
+            assert_eq!(list.index_of(&list[index]), Some(index));
+        }
+        assert_eq!(list.index_of(&expected[0]), None);
+    }
+
+    #[test]
+    fn mutating_a_clone_copies_one_chunk() {
+        let list: FileList = (0..3000).map(file).collect();
+        let mut changed = list.clone();
+
+        changed.get_mut(1500).unwrap().size = 42;
+        assert_eq!(changed.shared_chunks(&list), 2);
+        assert_eq!(list[1500].size, 0);
+        assert_ne!(changed.id(), list.id());
+
+        changed.push(file(9000));
+        assert_eq!(changed.shared_chunks(&list), 1);
+        assert_eq!(list.len(), 3000);
+    }
+}
@@ -0,0 +1,695 @@
+//! Batch file operations (rename / move / delete) for multi-select picker actions.
+//!
+//! Every operation in a batch touches the disk first and then the in-memory
+//! index. [`SharedPicker::apply_batch`](crate::SharedPicker::apply_batch) runs
+//! the whole batch under a single picker write lock, so concurrent searches
+//! observe either none or all of the index changes — never a half-applied batch.
+//! The background watcher will later see the same events; those are no-ops
+//! because the index is already up to date.
+//!
+//! When a [`QueryTracker`] is available every successful operation is recorded
+//! in a per-project undo journal. Deleted files are then stashed next to the
+//! database instead of being unlinked, so [`FilePicker::undo_last_file_op`]
+//! can bring them back together with their frecency history.
+//!
+//! With [`FilePickerOptions::use_os_trash`](crate::FilePickerOptions::use_os_trash)
+//! deleted files go to the OS trash instead (see the `os-trash` feature) and
+//! remain undoable from there.
+//!
+//! A [`read_only`](crate::FilePickerOptions::read_only) picker fails every
+//! operation and the undo.
+
+use crate::error::Error;
+use crate::file_picker::FilePicker;
+use crate::frecency::FrecencyTracker;
+use crate::query_tracker::QueryTracker;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// A single file operation requested by the UI.
+///
+/// Relative paths are resolved against the picker's base path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FileOp {
+    /// Rename `from` to `to`. Fails if `to` already exists.
+    Rename { from: PathBuf, to: PathBuf },
+    /// Move `from` into the directory `to_dir`, keeping its file name.
+    /// The directory is created if missing.
+    Move { from: PathBuf, to_dir: PathBuf },
+    /// Delete the file at `path`.
+    Delete { path: PathBuf },
+}
+
+/// Outcome of a single [`FileOp`], in the same order as the input batch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileOpResult {
+    pub success: bool,
+    /// Resulting path: the destination for rename/move, the removed path for delete.
+    pub path: PathBuf,
+    /// Human readable error when `success` is false.
+    pub error: Option<String>,
+    /// Location inside the OS trash for deletes with `use_os_trash` enabled.
+    pub trash_location: Option<PathBuf>,
+}
+
+impl FileOpResult {
+    fn ok(path: PathBuf) -> Self {
+        Self {
+            success: true,
+            path,
+            error: None,
+            trash_location: None,
+        }
+    }
+
+    fn err(path: PathBuf, error: impl ToString) -> Self {
+        Self {
+            success: false,
+            path,
+            error: Some(error.to_string()),
+            trash_location: None,
+        }
+    }
+}
+
+/// Undo journal record of an applied [`FileOp`], persisted in the query tracker DB.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileOpJournalEntry {
+    /// Original location of the file.
+    pub from: PathBuf,
+    /// Location after a rename or move. `None` for deletes.
+    pub to: Option<PathBuf>,
+    /// Where a deleted file was kept so that it can be restored.
+    pub trash_location: Option<PathBuf>,
+    /// Frecency access history of a deleted file.
+    pub frecency: Option<VecDeque<u64>>,
+    /// Unix timestamp of the operation.
+    pub timestamp: u64,
+}
+
+impl FilePicker {
+    /// Apply a batch of file operations to disk and to the index.
+    ///
+    /// Operations are executed in order and a failing operation does not abort
+    /// the rest of the batch. Frecency history follows renamed files, and with
+    /// a `journal` every successful operation becomes undoable. Callers that
+    /// share the picker should go through
+    /// [`SharedPicker::apply_batch`](crate::SharedPicker::apply_batch) which
+    /// holds the write lock for the whole batch.
+    pub fn apply_batch(
+        &mut self,
+        ops: &[FileOp],
+        frecency: Option<&FrecencyTracker>,
+        mut journal: Option<&mut QueryTracker>,
+    ) -> Vec<FileOpResult> {
+        ops.iter()
+            .map(|op| {
+                let stash_dir = journal.as_deref().map(QueryTracker::file_op_stash_dir);
+                let (result, entry) = self.apply_file_op(op, frecency, stash_dir.as_deref());
+
+                if let Some(entry) = entry
+                    && let Some(journal) = journal.as_deref_mut()
+                {
+                    self.record_file_op(journal, entry);
+                }
+
+                result
+            })
+            .collect()
+    }
+
+    /// Revert the most recent journaled file operation of this project.
+    ///
+    /// Renames and moves are moved back, deleted files are restored from the
+    /// stash together with their frecency history. Returns `None` when the
+    /// journal is empty.
+    pub fn undo_last_file_op(
+        &mut self,
+        frecency: Option<&FrecencyTracker>,
+        journal: &mut QueryTracker,
+    ) -> Result<Option<FileOpResult>, Error> {
+        if self.read_only() {
+            return Err(Error::ReadOnly);
+        }
+        let Some(entry) = journal.pop_file_op(&self.base_path)? else {
+            return Ok(None);
+        };
+
+        debug!(?entry, "Undoing file operation");
+        let stash_dir = journal.file_op_stash_dir();
+        let result = match (&entry.to, &entry.trash_location) {
+            (Some(to), _) => self.rename_indexed_file(to.clone(), entry.from.clone(), frecency),
+            (None, Some(trash_location)) => self.restore_deleted_file(
+                trash_location,
+                &entry.from,
+                trash_location.starts_with(&stash_dir),
+                entry.frecency.as_ref(),
+                frecency,
+            ),
+            (None, None) => FileOpResult::err(
+                entry.from.clone(),
+                "deleted file was not kept, nothing to restore",
+            ),
+        };
+
+        // Keep the entry if it can still be undone once the conflict is resolved.
+        let restorable = entry.to.as_ref().or(entry.trash_location.as_ref());
+        if !result.success && restorable.is_some_and(|p| p.exists()) {
+            journal.push_file_op(&self.base_path, entry)?;
+        }
+
+        Ok(Some(result))
+    }
+
+    fn apply_file_op(
+        &mut self,
+        op: &FileOp,
+        frecency: Option<&FrecencyTracker>,
+        stash_dir: Option<&Path>,
+    ) -> (FileOpResult, Option<FileOpJournalEntry>) {
+        debug!(?op, "Applying file operation");
+
+        if self.read_only() {
+            let path = match op {
+                FileOp::Rename { from, .. } | FileOp::Move { from, .. } => from,
+                FileOp::Delete { path } => path,
+            };
+            return (
+                FileOpResult::err(self.resolve_op_path(path), Error::ReadOnly),
+                None,
+            );
+        }
+
+        let (from, to) = match op {
+            FileOp::Rename { from, to } => (self.resolve_op_path(from), self.resolve_op_path(to)),
+            FileOp::Move { from, to_dir } => {
+                let from = self.resolve_op_path(from);
+                let to_dir = self.resolve_op_path(to_dir);
+                let Some(file_name) = from.file_name() else {
+                    return (
+                        FileOpResult::err(from, "source path has no file name"),
+                        None,
+                    );
+                };
+
+                let to = to_dir.join(file_name);
+                if let Err(e) = std::fs::create_dir_all(&to_dir) {
+                    return (FileOpResult::err(to, e), None);
+                }
+
+                (from, to)
+            }
+            FileOp::Delete { path } => {
+                let path = self.resolve_op_path(path);
+                return self.delete_indexed_file(path, frecency, stash_dir);
+            }
+        };
+
+        let result = self.rename_indexed_file(from.clone(), to.clone(), frecency);
+        let entry = result.success.then(|| FileOpJournalEntry {
+            from,
+            to: Some(to),
+            trash_location: None,
+            frecency: None,
+            timestamp: unix_now(),
+        });
+
+        (result, entry)
+    }
+
+    fn delete_indexed_file(
+        &mut self,
+        path: PathBuf,
+        frecency: Option<&FrecencyTracker>,
+        stash_dir: Option<&Path>,
+    ) -> (FileOpResult, Option<FileOpJournalEntry>) {
+        if !path.is_file() {
+            let error = format!("{} is not a file", path.display());
+            return (FileOpResult::err(path, error), None);
+        }
+
+        let trash_location = match stash_dir {
+            _ if self.use_os_trash() => match crate::trash::move_to_trash(&path) {
+                Ok(trashed) => Some(trashed),
+                Err(e) => {
+                    let error = format!("failed to move to trash: {e}");
+                    return (FileOpResult::err(path, error), None);
+                }
+            },
+            Some(stash_dir) => {
+                let stash_path = stash_path_for(stash_dir, &path);
+                if let Err(e) =
+                    std::fs::create_dir_all(stash_dir).and_then(|_| move_file(&path, &stash_path))
+                {
+                    return (FileOpResult::err(path, e), None);
+                }
+                Some(stash_path)
+            }
+            None => {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    return (FileOpResult::err(path, e), None);
+                }
+                None
+            }
+        };
+
+        self.remove_file_by_path(&path);
+
+        // Only drop the frecency record when it can be brought back by undo.
+        let frecency_history = match (frecency, &trash_location) {
+            (Some(frecency), Some(_)) if stash_dir.is_some() => {
+                frecency.take_access_history(&path).unwrap_or_else(|e| {
+                    warn!(?path, error = ?e, "Failed to read frecency of deleted file");
+                    None
+                })
+            }
+            _ => None,
+        };
+
+        let result = FileOpResult {
+            trash_location: trash_location.clone().filter(|_| self.use_os_trash()),
+            ..FileOpResult::ok(path.clone())
+        };
+        let entry = FileOpJournalEntry {
+            from: path,
+            to: None,
+            trash_location,
+            frecency: frecency_history,
+            timestamp: unix_now(),
+        };
+
+        (result, Some(entry))
+    }
+
+    fn rename_indexed_file(
+        &mut self,
+        from: PathBuf,
+        to: PathBuf,
+        frecency: Option<&FrecencyTracker>,
+    ) -> FileOpResult {
+        if !from.is_file() {
+            return FileOpResult::err(to, format!("{} is not a file", from.display()));
+        }
+
+        if to.exists() {
+            return FileOpResult::err(to, "destination already exists");
+        }
+
+        if let Err(e) = std::fs::rename(&from, &to) {
+            return FileOpResult::err(to, e);
+        }
+
+        self.remove_file_by_path(&from);
+        if to.starts_with(&self.base_path) {
+            self.on_create_or_modify(&to);
+        } else {
+            warn!(?to, "File moved outside of the indexed directory");
+        }
+
+        if let Some(frecency) = frecency {
+            self.move_frecency_history(frecency, &from, &to);
+        }
+
+        FileOpResult::ok(to)
+    }
+
+    fn restore_deleted_file(
+        &mut self,
+        trash_location: &Path,
+        original_path: &Path,
+        is_stashed: bool,
+        history: Option<&VecDeque<u64>>,
+        frecency: Option<&FrecencyTracker>,
+    ) -> FileOpResult {
+        if original_path.exists() {
+            return FileOpResult::err(original_path.to_path_buf(), "destination already exists");
+        }
+
+        let restored = original_path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| {
+                if is_stashed {
+                    move_file(trash_location, original_path)
+                } else {
+                    crate::trash::restore_from_trash(trash_location, original_path)
+                }
+            });
+        if let Err(e) = restored {
+            return FileOpResult::err(original_path.to_path_buf(), e);
+        }
+
+        if original_path.starts_with(&self.base_path) {
+            self.on_create_or_modify(original_path);
+        }
+
+        if let Some(frecency) = frecency
+            && let Some(history) = history
+        {
+            if let Err(e) = frecency.restore_access_history(original_path, history) {
+                warn!(?original_path, error = ?e, "Failed to restore frecency of deleted file");
+            }
+            let _ = self.update_single_file_frecency(original_path, frecency);
+        }
+
+        FileOpResult::ok(original_path.to_path_buf())
+    }
+
+    fn move_frecency_history(&mut self, frecency: &FrecencyTracker, from: &Path, to: &Path) {
+        let moved = frecency
+            .take_access_history(from)
+            .and_then(|history| match history {
+                Some(history) => frecency.restore_access_history(to, &history),
+                None => Ok(()),
+            });
+
+        if let Err(e) = moved {
+            warn!(?from, ?to, error = ?e, "Failed to move frecency history");
+        }
+        let _ = self.update_single_file_frecency(to, frecency);
+    }
+
+    fn record_file_op(&self, journal: &mut QueryTracker, entry: FileOpJournalEntry) {
+        let stash_dir = journal.file_op_stash_dir();
+        match journal.push_file_op(&self.base_path, entry) {
+            Ok(evicted) => {
+                // Stashed files of entries that fell out of the journal can never be restored.
+                for trash_location in evicted.into_iter().filter_map(|e| e.trash_location) {
+                    if trash_location.starts_with(&stash_dir) {
+                        let _ = std::fs::remove_file(trash_location);
+                    }
+                }
+            }
+            Err(e) => warn!(error = ?e, "Failed to record file operation in the undo journal"),
+        }
+    }
+
+    fn resolve_op_path(&self, path: &Path) -> PathBuf {
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.base_path.join(path)
+        }
+    }
+}
+
+/// Unique location inside the stash directory for a deleted file.
+fn stash_path_for(stash_dir: &Path, path: &Path) -> PathBuf {
+    let hash = blake3::hash(path.to_string_lossy().as_bytes()).to_hex();
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    stash_dir.join(format!("{}-{}-{}", unix_now(), &hash[..16], file_name))
+}
+
+/// `rename` with a copy + remove fallback for moves across file systems.
+pub(crate) fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    std::fs::copy(from, to)?;
+    std::fs::remove_file(from)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::{SharedFrecency, SharedQueryTracker};
+    use crate::test_fixtures::{indexed_paths, picker_options, scan, scan_shared, write_files};
+    use crate::{FilePickerOptions, SharedPicker};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn live_paths(shared_picker: &SharedPicker) -> Vec<String> {
+        indexed_paths(shared_picker.snapshot().unwrap().get_files())
+    }
+
+    #[test]
+    fn batch_applies_rename_move_and_delete() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_files(
+            base,
+            &[
+                ("alpha.txt", "alpha"),
+                ("beta.txt", "beta"),
+                ("gamma.txt", "gamma"),
+            ],
+        );
+
+        let shared_picker = scan_shared(picker_options(base));
+        let results = shared_picker
+            .apply_batch(
+                &[
+                    FileOp::Rename {
+                        from: "alpha.txt".into(),
+                        to: "renamed.txt".into(),
+                    },
+                    FileOp::Move {
+                        from: base.join("beta.txt"),
+                        to_dir: "nested".into(),
+                    },
+                    FileOp::Delete {
+                        path: "gamma.txt".into(),
+                    },
+                ],
+                &SharedFrecency::default(),
+                &SharedQueryTracker::default(),
+            )
+            .unwrap();
+
+        assert!(results.iter().all(|r| r.success), "{results:?}");
+        assert_eq!(results[1].path, base.join("nested").join("beta.txt"));
+
+        assert!(base.join("renamed.txt").is_file());
+        assert!(base.join("nested/beta.txt").is_file());
+        assert!(!base.join("gamma.txt").exists());
+
+        assert_eq!(
+            live_paths(&shared_picker),
+            vec!["nested/beta.txt".to_string(), "renamed.txt".to_string()]
+        );
+    }
+
+    #[test]
+    fn failed_op_does_not_abort_batch() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_files(base, &[("alpha.txt", "alpha"), ("beta.txt", "beta")]);
+
+        let shared_picker = scan_shared(picker_options(base));
+        let results = shared_picker
+            .apply_batch(
+                &[
+                    FileOp::Rename {
+                        from: "alpha.txt".into(),
+                        to: "beta.txt".into(),
+                    },
+                    FileOp::Delete {
+                        path: "missing.txt".into(),
+                    },
+                    FileOp::Delete {
+                        path: "alpha.txt".into(),
+                    },
+                ],
+                &SharedFrecency::default(),
+                &SharedQueryTracker::default(),
+            )
+            .unwrap();
+
+        assert!(!results[0].success);
+        assert!(results[0].error.is_some());
+        assert!(!results[1].success);
+        assert!(results[2].success);
+
+        assert_eq!(fs::read_to_string(base.join("beta.txt")).unwrap(), "beta");
+        assert_eq!(live_paths(&shared_picker), vec!["beta.txt".to_string()]);
+    }
+
+    #[test]
+    fn undo_restores_deleted_file_and_frecency() {
+        let tmp = TempDir::new().unwrap();
+        let db_dir = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_files(base, &[("alpha.txt", "alpha"), ("beta.txt", "beta")]);
+
+        let shared_picker = scan_shared(picker_options(base));
+        let shared_frecency = SharedFrecency::default();
+        shared_frecency
+            .init(FrecencyTracker::new(db_dir.path().join("frecency"), true).unwrap())
+            .unwrap();
+        let shared_query_tracker = SharedQueryTracker::default();
+        shared_query_tracker
+            .init(QueryTracker::new(db_dir.path().join("history"), true).unwrap())
+            .unwrap();
+
+        let alpha = base.join("alpha.txt");
+        {
+            let frecency = shared_frecency.read().unwrap();
+            let frecency = frecency.as_ref().unwrap();
+            frecency.track_access(&alpha).unwrap();
+            frecency.track_access(&alpha).unwrap();
+        }
+
+        let results = shared_picker
+            .apply_batch(
+                &[
+                    FileOp::Delete {
+                        path: "alpha.txt".into(),
+                    },
+                    FileOp::Rename {
+                        from: "beta.txt".into(),
+                        to: "gamma.txt".into(),
+                    },
+                ],
+                &shared_frecency,
+                &shared_query_tracker,
+            )
+            .unwrap();
+        assert!(results.iter().all(|r| r.success), "{results:?}");
+        assert!(!alpha.exists());
+        assert_eq!(live_paths(&shared_picker), vec!["gamma.txt".to_string()]);
+
+        // Undo is LIFO: the rename goes first, then the delete.
+        let undone = shared_picker
+            .undo_last_file_op(&shared_frecency, &shared_query_tracker)
+            .unwrap()
+            .unwrap();
+        assert!(undone.success, "{undone:?}");
+        assert_eq!(undone.path, base.join("beta.txt"));
+
+        let undone = shared_picker
+            .undo_last_file_op(&shared_frecency, &shared_query_tracker)
+            .unwrap()
+            .unwrap();
+        assert!(undone.success, "{undone:?}");
+        assert_eq!(fs::read_to_string(&alpha).unwrap(), "alpha");
+        assert_eq!(
+            live_paths(&shared_picker),
+            vec!["alpha.txt".to_string(), "beta.txt".to_string()]
+        );
+
+        let guard = shared_picker.read().unwrap();
+        let restored = guard.as_ref().unwrap().get_file_by_path(&alpha).unwrap();
+        assert!(restored.access_frecency_score > 0);
+        drop(guard);
+
+        assert!(
+            shared_picker
+                .undo_last_file_op(&shared_frecency, &shared_query_tracker)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[cfg(not(feature = "os-trash"))]
+    #[test]
+    fn os_trash_without_feature_keeps_the_file() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_files(base, &[("alpha.txt", "alpha")]);
+
+        let shared_picker = scan_shared(picker_options(base));
+        shared_picker
+            .write()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .set_use_os_trash(true);
+
+        let results = shared_picker
+            .apply_batch(
+                &[FileOp::Delete {
+                    path: "alpha.txt".into(),
+                }],
+                &SharedFrecency::default(),
+                &SharedQueryTracker::default(),
+            )
+            .unwrap();
+
+        assert!(!results[0].success);
+        assert!(results[0].error.as_deref().unwrap().contains("trash"));
+        assert!(results[0].trash_location.is_none());
+        assert!(base.join("alpha.txt").is_file());
+        assert_eq!(live_paths(&shared_picker), vec!["alpha.txt".to_string()]);
+    }
+
+    #[test]
+    fn read_only_picker_refuses_file_ops() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path();
+        write_files(base, &[("alpha.txt", "alpha")]);
+        let db_dir = TempDir::new().unwrap();
+
+        let picker = scan(FilePickerOptions {
+            read_only: true,
+            trigram_index_dir: Some(db_dir.path().join("trigrams")),
+            ..picker_options(base)
+        });
+        assert!(picker.trigram_index_dir().is_none());
+        let shared_picker = SharedPicker::default();
+        *shared_picker.write().unwrap() = Some(picker);
+
+        let results = shared_picker
+            .apply_batch(
+                &[
+                    FileOp::Rename {
+                        from: "alpha.txt".into(),
+                        to: "beta.txt".into(),
+                    },
+                    FileOp::Delete {
+                        path: "alpha.txt".into(),
+                    },
+                ],
+                &SharedFrecency::default(),
+                &SharedQueryTracker::default(),
+            )
+            .unwrap();
+        assert!(results.iter().all(|r| !r.success));
+        assert!(results[0].error.as_deref().unwrap().contains("read-only"));
+        assert_eq!(results[1].path, base.join("alpha.txt"));
+        assert!(base.join("alpha.txt").is_file());
+        assert_eq!(live_paths(&shared_picker), vec!["alpha.txt".to_string()]);
+
+        let shared_query_tracker = SharedQueryTracker::default();
+        shared_query_tracker
+            .init(QueryTracker::new(db_dir.path().join("history"), true).unwrap())
+            .unwrap();
+        assert!(matches!(
+            shared_picker.undo_last_file_op(&SharedFrecency::default(), &shared_query_tracker),
+            Err(Error::ReadOnly)
+        ));
+    }
+
+    #[test]
+    fn file_op_deserializes_from_tagged_json() {
+        let ops: Vec<FileOp> = serde_json::from_str(
+            r#"[{"op":"rename","from":"a","to":"b"},{"op":"move","from":"c","to_dir":"d"},{"op":"delete","path":"e"}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            ops,
+            vec![
+                FileOp::Rename {
+                    from: "a".into(),
+                    to: "b".into()
+                },
+                FileOp::Move {
+                    from: "c".into(),
+                    to_dir: "d".into()
+                },
+                FileOp::Delete { path: "e".into() },
+            ]
+        );
+    }
+}
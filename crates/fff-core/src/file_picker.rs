@@ -3,7 +3,7 @@
 //! [`FilePicker`] is the central component of fff-search. It:
 //!
 //! 1. **Indexes** a directory tree in a background thread, collecting every
-//!    non-ignored file into a path-sorted `Vec<FileItem>`.
+//!    non-ignored file into a path-sorted [`FileList`](crate::FileList).
 //! 2. **Watches** the filesystem via the `notify` crate, applying
 //!    create/modify/delete events to the index in real time.
 //! 3. **Owns files**: Provides a values for search and provides a good entry point for
@@ -17,8 +17,8 @@
 //!     ├─> background scan thread ──> populates SharedPicker
 //!     └─> file-system watcher    ──> live updates SharedPicker
 //!
-//!   fuzzy_search()   <── static, borrows &[FileItem]
-//!   grep()           <── static, borrows &[FileItem] (live content search)
+//!   fuzzy_search()   <── static, borrows &FileList
+//!   grep()           <── static, borrows &FileList (live content search)
 //!   trigger_rescan() <── synchronous re-index
 //!   cancel()         <── shuts down background work
 //! ```
@@ -28,21 +28,51 @@
 //! `FilePicker` itself is **not** `Sync`!
 //! all concurrent access goes through [`SharedPicker`](crate::SharedPicker) .
 //! The background scanner and watcher acquire write locks only when mutating
-//! the file index, so read-heavy search workloads rarely contend.
+//! the file index. Searches don't lock at all: they run on the
+//! [`PickerSnapshot`] published when a write lock is released, whose file
+//! list is shared with the picker until the next mutation copies it.
 
+use crate::auxiliary_roots::{AuxiliaryRoot, AuxiliaryRoots};
 use crate::background_watcher::BackgroundWatcher;
 use crate::bigram_filter::{BigramFilter, BigramIndexBuilder, BigramOverlay};
+use crate::completion::{ConstraintCompletion, complete_constraint};
+use crate::constraints::exclude_globs;
+use crate::duplicates::{DuplicateGroup, find_duplicates};
 use crate::error::Error;
-use crate::frecency::FrecencyTracker;
-use crate::git::GitStatusCache;
-use crate::grep::{GrepResult, GrepSearchOptions, grep_search};
-use crate::ignore::non_git_repo_overrides;
+use crate::file_list::FileList;
+use crate::frecency::{ExternalChanges, FrecencyTracker};
+use crate::git::{
+    ChangedLines, GitRepoLocation, GitStatusCache, GitStatusProvider, StatusQuery,
+    is_modified_status,
+};
+use crate::grep::{
+    ContentIndexes, FileSubset, GrepResult, GrepSearchOptions, grep_search, grep_search_indexed,
+};
+use crate::ignore::{IgnoredFiles, apply_ignore_files, non_git_repo_overrides};
+use crate::keyword_index::{KeywordIndex, KeywordMatch, KeywordMatcher};
+use crate::metrics::SearchKind;
+use crate::path_utils::normalize_key;
+use crate::preview_hunk::{self, PreviewHunk};
+use crate::project_profile::{ProjectProfile, ProjectProfileOptions};
 use crate::query_tracker::QueryTracker;
-use crate::score::match_and_score_files;
-use crate::shared::{SharedFrecency, SharedPicker};
-use crate::types::{ContentCacheBudget, FileItem, PaginationArgs, ScoringContext, SearchResult};
+use crate::replace_diff::{self, Replacement};
+use crate::score::{AFFINITY_MAX_QUERY_LEN, match_and_score_files};
+use crate::search_cursor::SearchCursor;
+use crate::search_scratch::SearchScratch;
+use crate::shared::{IndexEvent, SharedFrecency, SharedPicker};
+use crate::suggestion::PathTokenIndex;
+use crate::test_index::{TestIndex, TestMatch, enclosing_test, scan_tests};
+use crate::todo_index::{TodoIndex, TodoMatch, scan_todos};
+use crate::trigram_index::{self, TrigramIndex, TrigramIndexHealth};
+use crate::types::{
+    ContentCacheBudget, ExtensionMultipliers, FileItem, PaginationArgs, ScoringContext,
+    ScoringWeights, SearchResult,
+};
+use crate::virtual_files::{VirtualFileProvider, VirtualFiles};
+use crate::working_set::WorkingSetCache;
+use ahash::AHashSet;
 use fff_query_parser::FFFQuery;
-use git2::{Repository, Status, StatusOptions};
+use git2::Status;
 use rayon::prelude::*;
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
@@ -50,7 +80,7 @@ use std::sync::{
     Arc, LazyLock,
     atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
 };
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use tracing::{Level, debug, error, info, warn};
 
 /// Dedicated thread pool for background work (scan, warmup, bigram build).
@@ -72,6 +102,12 @@ static BACKGROUND_THREAD_POOL: LazyLock<rayon::ThreadPool> = LazyLock::new(|| {
         .expect("failed to create background rayon pool")
 });
 
+/// The pool background indexing runs on: the picker's own pool when it has
+/// one, the shared background pool otherwise.
+fn background_pool(thread_pool: Option<&Arc<rayon::ThreadPool>>) -> &rayon::ThreadPool {
+    thread_pool.map_or(&BACKGROUND_THREAD_POOL, |pool| pool)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum FFFMode {
     #[default]
@@ -96,41 +132,143 @@ pub struct FuzzySearchOptions<'a> {
     pub project_path: Option<&'a Path>,
     pub combo_boost_score_multiplier: i32,
     pub min_combo_count: u32,
+    /// Also boost the files of a longer query from the history this one is
+    /// a prefix of, once the query has this many characters. `0` disables it.
+    pub min_combo_prefix_len: usize,
     pub pagination: PaginationArgs,
+    /// Overrides the typo budget derived from the query length.
+    pub max_typos: Option<u16>,
+    /// Skip typo-tolerant matching when at least this many files match the
+    /// query without typos. `0` disables the adaptive mode.
+    pub adaptive_typos_threshold: usize,
+    pub scoring_weights: ScoringWeights,
+    /// Score multipliers applied by file extension, see [`ExtensionMultipliers`].
+    pub extension_multipliers: Option<&'a ExtensionMultipliers>,
+    /// Directory adjustments of the picker's project, see [`FilePicker::project_profile`].
+    pub project_profile: Option<&'a ProjectProfile>,
+    /// Only return files under this directory, absolute or relative to
+    /// `project_path`. A directory outside of the project matches nothing.
+    pub search_root: Option<&'a Path>,
+    /// Count the matches per extension and top-level directory into
+    /// [`SearchResult::facets`]. Costs one more pass over all matches.
+    pub facets: bool,
+    /// Drop matches scoring below this before paginating.
+    pub min_score: Option<i32>,
+    /// Drop matches scoring below this fraction of the best match before
+    /// paginating, e.g. `0.3`, so `total_matched` of a short query counts the
+    /// relevant files rather than everything with its letters in order.
+    /// `0.0` keeps every match. [`SearchResult::match_stats`] still counts
+    /// the dropped ones.
+    pub min_score_ratio: f32,
+    /// Return a [`SearchResult::cursor`] when matches are left past the
+    /// page, so the next page doesn't rank every match again. Keeps the rest
+    /// of the matches in memory until the cursor is dropped.
+    pub with_cursor: bool,
+    /// Continue the search of a previous page at `pagination.offset`. A
+    /// cursor of another query, file list or offset is ignored.
+    pub cursor: Option<&'a SearchCursor>,
+    /// The [`FilePicker::index_generation`] of the searched files, returned
+    /// in [`SearchResult::index_generation`].
+    pub index_generation: u64,
+    /// Reuse the files that passed the constraints in the previous search
+    /// with the same constraints and `index_generation`.
+    pub working_set: Option<&'a WorkingSetCache>,
+    /// Reuse the vectors of the previous searches of the session for
+    /// matching instead of allocating them on every keystroke.
+    pub scratch: Option<&'a SearchScratch>,
 }
 
 #[derive(Debug, Clone)]
-struct FileSync {
+pub(crate) struct FileSync {
     /// All files: `files[..base_count]` are sorted by path (base index, used
     /// for binary search and bigram); `files[base_count..]` are overflow files
     /// added since the last full reindex. Deletions in the base use tombstones
     /// (`is_deleted = true`) to keep bigram indices stable.
-    files: Vec<FileItem>,
+    ///
+    /// Shared with the published [`PickerSnapshot`]s; mutations go through
+    /// [`Self::files_mut`], which copies only the chunks a snapshot still holds.
+    files: FileList,
     /// Number of base files (the sorted prefix used for binary search / bigram).
     base_count: usize,
     pub git_workdir: Option<PathBuf>,
+    /// The walk stopped at [`ScanLimits::max_files`].
+    truncated: bool,
+    /// Scanned by the first `ignored:` query, replaced with the files.
+    ignored: Arc<IgnoredFiles>,
+    /// Changes on every write to `files`, see [`FilePicker::index_generation`].
+    generation: u64,
+}
+
+/// Generations are unique across file lists, so a rescan replacing the list
+/// never reuses the generation of the old one.
+static NEXT_INDEX_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+fn next_index_generation() -> u64 {
+    NEXT_INDEX_GENERATION.fetch_add(1, Ordering::Relaxed)
 }
 
 impl FileSync {
     fn new() -> Self {
         Self {
-            files: Vec::new(),
+            files: FileList::new(),
             base_count: 0,
             git_workdir: None,
+            truncated: false,
+            ignored: Arc::default(),
+            generation: next_index_generation(),
+        }
+    }
+
+    /// The files hidden by the ignore rules, see [`IgnoredFiles`].
+    fn ignored_files(&self, base_path: &Path) -> &FileList {
+        self.ignored
+            .get_or_scan(base_path, self.git_workdir.as_deref(), |path| {
+                self.find_file_index(path).is_ok() || self.find_overflow_index(path).is_some()
+            })
+    }
+
+    /// See [`FilePicker::changed_lines`].
+    fn changed_lines(&self) -> Result<Option<ChangedLines>, Error> {
+        let Some(workdir) = self.git_workdir.as_deref() else {
+            return Ok(None);
+        };
+        let modified: Vec<&Path> = self
+            .files()
+            .iter()
+            .filter(|file| !file.is_deleted && file.git_status.is_some_and(is_modified_status))
+            .map(|file| file.path())
+            .collect();
+        ChangedLines::read(workdir, &modified).map(Some)
+    }
+
+    /// The ignored files for an `ignored:` query, the index otherwise.
+    fn files_for_query(&self, base_path: &Path, query: &FFFQuery<'_>) -> &FileList {
+        if query.constraints.iter().any(|c| c.is_ignored()) {
+            self.ignored_files(base_path)
+        } else {
+            self.files()
         }
     }
 
     /// Get all files (base + overflow). The base portion `[..base_count]` is
     /// sorted by path; the overflow tail is unsorted.
     #[inline]
-    fn files(&self) -> &[FileItem] {
+    fn files(&self) -> &FileList {
         &self.files
     }
 
+    /// Mutable access to the files, copy-on-write per chunk if a snapshot
+    /// shares them.
+    #[inline]
+    fn files_mut(&mut self) -> &mut FileList {
+        self.generation = next_index_generation();
+        &mut self.files
+    }
+
     /// Get the overflow portion (files added since last full reindex).
     #[inline]
-    fn overflow_files(&self) -> &[FileItem] {
-        &self.files[self.base_count..]
+    fn overflow_files(&self) -> impl ExactSizeIterator<Item = &FileItem> {
+        self.files.iter_range(self.base_count..self.files.len())
     }
 
     #[allow(dead_code)]
@@ -141,24 +279,92 @@ impl FileSync {
     /// Get mutable file at index (works for both base and overflow)
     #[inline]
     fn get_file_mut(&mut self, index: usize) -> Option<&mut FileItem> {
-        self.files.get_mut(index)
+        self.files_mut().get_mut(index)
     }
 
     /// Find file index by path using binary search on the sorted base portion.
     #[inline]
     fn find_file_index(&self, path: &Path) -> Result<usize, usize> {
-        self.files[..self.base_count].binary_search_by(|f| f.path.as_path().cmp(path))
+        self.files
+            .binary_search_by(self.base_count, |f| f.path().cmp(path))
     }
 
     /// Find a file in the overflow portion by path (linear scan).
     /// Returns the absolute index into `files`.
     fn find_overflow_index(&self, path: &Path) -> Option<usize> {
-        self.files[self.base_count..]
-            .iter()
-            .position(|f| f.path == path)
+        self.overflow_files()
+            .position(|f| f.path() == path)
             .map(|pos| self.base_count + pos)
     }
 
+    /// Find a file in the sorted base (O(log n)), then in the overflow tail (O(k)).
+    fn find_any_index(&self, path: &Path) -> Option<usize> {
+        self.find_file_index(path)
+            .ok()
+            .or_else(|| self.find_overflow_index(path))
+    }
+
+    /// Lines around `byte_offset` of the file at `path`, from its cached content.
+    fn read_context_at(
+        &self,
+        budget: &ContentCacheBudget,
+        path: &Path,
+        byte_offset: u64,
+        lines_before: usize,
+        lines_after: usize,
+    ) -> Option<PreviewHunk> {
+        let file = self.files.get(self.find_any_index(path)?)?;
+        let content = file.get_content_for_search(budget)?;
+        preview_hunk::hunk_at(
+            &content,
+            usize::try_from(byte_offset).ok()?,
+            lines_before,
+            lines_after,
+        )
+    }
+
+    fn replacement_diff(
+        &self,
+        budget: &ContentCacheBudget,
+        path: &Path,
+        replacements: &[Replacement],
+    ) -> Option<String> {
+        let file = self.files.get(self.find_any_index(path)?)?;
+        let content = file.get_content_for_search(budget)?;
+        Some(replace_diff::unified_diff(
+            &content,
+            file.relative_path(),
+            replacements,
+        ))
+    }
+
+    /// Indices of the files below the directory `dir`, which directly follow
+    /// its position in the sorted base.
+    fn indices_under(&self, dir: &Path) -> Vec<usize> {
+        let start = self.find_file_index(dir).unwrap_or_else(|idx| idx);
+        let base = self
+            .files
+            .iter_range(start..self.base_count)
+            .take_while(|f| f.path().starts_with(dir))
+            .count();
+        let overflow = self
+            .overflow_files()
+            .enumerate()
+            .filter(|(_, f)| f.path().starts_with(dir))
+            .map(|(pos, _)| self.base_count + pos);
+
+        (start..start + base).chain(overflow).collect()
+    }
+
+    /// Indices of the indexed files among `paths`, ignoring unknown paths.
+    fn subset_of<P: AsRef<Path>>(&self, paths: impl IntoIterator<Item = P>) -> FileSubset {
+        FileSubset::from_indices(
+            paths
+                .into_iter()
+                .filter_map(|path| self.find_any_index(path.as_ref())),
+        )
+    }
+
     /// Get file count
     #[inline]
     #[allow(dead_code)]
@@ -168,14 +374,14 @@ impl FileSync {
 
     /// Insert a file at position. Simple - no HashMap to maintain!
     fn insert_file(&mut self, position: usize, file: FileItem) {
-        self.files.insert(position, file);
+        self.files_mut().insert(position, file);
     }
 
     /// Remove file at index. Simple - no HashMap to maintain!
     #[allow(dead_code)]
     fn remove_file(&mut self, index: usize) {
         if index < self.files.len() {
-            self.files.remove(index);
+            self.files_mut().remove(index);
         }
     }
 
@@ -187,11 +393,12 @@ impl FileSync {
     {
         let initial_len = self.files.len();
         // Count how many base files survive.
-        let base_retained = self.files[..self.base_count]
-            .iter()
+        let base_retained = self
+            .files
+            .iter_range(0..self.base_count)
             .filter(|f| predicate(f))
             .count();
-        self.files.retain(predicate);
+        self.files_mut().retain(predicate);
         self.base_count = base_retained;
         initial_len - self.files.len()
     }
@@ -199,7 +406,7 @@ impl FileSync {
     /// Insert a file in sorted order (by path).
     /// Returns true if inserted, false if file already exists.
     fn insert_file_sorted(&mut self, file: FileItem) -> bool {
-        match self.find_file_index(&file.path) {
+        match self.find_file_index(file.path()) {
             Ok(_) => false, // File already exists
             Err(position) => {
                 self.insert_file(position, file);
@@ -267,7 +474,7 @@ impl FileItem {
         tracker: &FrecencyTracker,
         mode: FFFMode,
     ) -> Result<(), Error> {
-        self.access_frecency_score = tracker.get_access_score(&self.path, mode) as i32;
+        self.access_frecency_score = tracker.get_access_score(self.path(), mode) as i32;
         self.modification_frecency_score =
             tracker.get_modification_score(self.modified, self.git_status, mode) as i32;
         self.total_frecency_score = self.access_frecency_score + self.modification_frecency_score;
@@ -276,6 +483,48 @@ impl FileItem {
     }
 }
 
+/// Limits on the background scan, content warmup and git status work, for
+/// machines where an initial scan at full speed gets in the way.
+#[derive(Clone, Default)]
+pub struct BackgroundPriority {
+    /// Threads used by the scan, the warmup and the git status refresh.
+    /// `None` uses all but two cores.
+    pub max_parallelism: Option<usize>,
+    /// Rest between batches of warmed up and bigram-indexed files.
+    pub batch_pause: Duration,
+    /// Asked before every content warmup, returning `true` skips it as if
+    /// [`FilePickerOptions::warmup_mmap_cache`] was off, e.g. when
+    /// [`on_battery_power`](crate::on_battery_power).
+    pub skip_warmup: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
+}
+
+impl BackgroundPriority {
+    fn skips_warmup(&self) -> bool {
+        self.skip_warmup.as_ref().is_some_and(|skip| skip())
+    }
+}
+
+impl std::fmt::Debug for BackgroundPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackgroundPriority")
+            .field("max_parallelism", &self.max_parallelism)
+            .field("batch_pause", &self.batch_pause)
+            .field("skip_warmup", &self.skip_warmup.is_some())
+            .finish()
+    }
+}
+
+/// Bounds on how much of the tree under the base path gets indexed, e.g. for
+/// a restricted first look at a home directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScanLimits {
+    /// Only index files this many directories below the base path.
+    pub max_depth: Option<usize>,
+    /// Stop walking after this many files, see
+    /// [`FilePicker::is_scan_truncated`].
+    pub max_files: Option<usize>,
+}
+
 /// Options for creating a [`FilePicker`].
 pub struct FilePickerOptions {
     pub base_path: String,
@@ -287,6 +536,52 @@ pub struct FilePickerOptions {
     /// When `false`, `new_with_shared_state` skips the background file watcher.
     /// Files are still scanned, warmed up, and bigram-indexed.
     pub watch: bool,
+    /// Send files deleted through [`FilePicker::apply_batch`] to the OS trash
+    /// instead of unlinking them. Requires the `os-trash` feature.
+    pub use_os_trash: bool,
+    /// Honor per-directory `.ignore` and `.rgignore` files (the ripgrep / fd
+    /// convention) in addition to git ignore rules.
+    pub respect_ignore_files: bool,
+    /// Don't descend into other mounted filesystems, like fd and ripgrep's
+    /// `--one-file-system`, so a picker on a home directory skips network
+    /// drives and snapshot mounts below it.
+    pub same_file_system: bool,
+    /// Discover the git repository and read file statuses. When `false` the
+    /// tree is scanned like a directory outside of git, which skips the
+    /// repository discovery walk on huge non-git trees.
+    pub git_enabled: bool,
+    /// How full git statuses are read, see [`GitStatusProvider`].
+    pub git_status_provider: GitStatusProvider,
+    pub scan_limits: ScanLimits,
+    /// Detect the project type and adjust scores of its build output and
+    /// source directories, see [`ProjectProfile`].
+    pub project_profile: ProjectProfileOptions,
+    /// Named regexes whose matching lines are indexed like TODO comments,
+    /// as `(name, pattern)`. See [`FilePicker::search_keyword`].
+    pub keyword_patterns: Vec<(String, String)>,
+    /// Directory the trigram index is persisted to, usually next to the
+    /// frecency database. `None` disables the index. See [`TrigramIndex`].
+    pub trigram_index_dir: Option<PathBuf>,
+    /// Run searches and content indexing on a rayon pool of this many
+    /// threads owned by the picker (`0` for one per CPU), so embedders with
+    /// their own rayon work or several pickers do not contend on the global
+    /// pool. `None` searches on the global pool and indexes on a background
+    /// pool shared by all pickers.
+    pub thread_pool_size: Option<usize>,
+    /// Throttling of the background work. Its `max_parallelism` takes
+    /// precedence over `thread_pool_size` for indexing.
+    pub background_priority: BackgroundPriority,
+    /// Files outside of the local disk searched next to the index, usually
+    /// carried over from the previous picker.
+    pub virtual_files: VirtualFiles,
+    /// Read-only directories searched next to the index, carried over like
+    /// the virtual files.
+    pub auxiliary_roots: AuxiliaryRoots,
+    /// Never write anything: [`FilePicker::apply_batch`] and
+    /// [`FilePicker::undo_last_file_op`] fail and the trigram index is not
+    /// persisted. For shared checkouts and CI, where the caller should not
+    /// open the frecency and history databases either.
+    pub read_only: bool,
 }
 
 impl Default for FilePickerOptions {
@@ -297,6 +592,20 @@ impl Default for FilePickerOptions {
             mode: FFFMode::default(),
             cache_budget: None,
             watch: true,
+            use_os_trash: false,
+            respect_ignore_files: true,
+            same_file_system: false,
+            git_enabled: true,
+            git_status_provider: GitStatusProvider::default(),
+            scan_limits: ScanLimits::default(),
+            project_profile: ProjectProfileOptions::default(),
+            keyword_patterns: Vec::new(),
+            trigram_index_dir: None,
+            thread_pool_size: None,
+            background_priority: BackgroundPriority::default(),
+            virtual_files: VirtualFiles::default(),
+            auxiliary_roots: AuxiliaryRoots::default(),
+            read_only: false,
         }
     }
 }
@@ -313,9 +622,33 @@ pub struct FilePicker {
     background_watcher: Option<BackgroundWatcher>,
     warmup_mmap_cache: bool,
     watch: bool,
+    use_os_trash: bool,
+    respect_ignore_files: bool,
+    same_file_system: bool,
+    git_enabled: bool,
+    git_status_provider: GitStatusProvider,
+    scan_limits: ScanLimits,
     cancelled: Arc<AtomicBool>,
     bigram_index: Option<Arc<BigramFilter>>,
     bigram_overlay: Option<Arc<parking_lot::RwLock<BigramOverlay>>>,
+    path_tokens: Option<Arc<PathTokenIndex>>,
+    /// Built after the bigram index, or by the first search when the content
+    /// warmup is disabled.
+    line_indexes: parking_lot::RwLock<LineIndexes>,
+    keyword_patterns: Vec<(String, String)>,
+    keyword_matcher: Option<Arc<KeywordMatcher>>,
+    trigram_index_dir: Option<PathBuf>,
+    trigram_index: Option<Arc<TrigramIndex>>,
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+    /// Runs scans, warmup and indexing, `None` for the shared background pool.
+    background_pool: Option<Arc<rayon::ThreadPool>>,
+    background_priority: BackgroundPriority,
+    project_profile: Option<ProjectProfile>,
+    project_profile_options: ProjectProfileOptions,
+    virtual_files: VirtualFiles,
+    auxiliary_roots: AuxiliaryRoots,
+    runtime_excludes: Arc<[String]>,
+    read_only: bool,
 }
 
 impl std::fmt::Debug for FilePicker {
@@ -332,7 +665,217 @@ impl std::fmt::Debug for FilePicker {
     }
 }
 
+/// Immutable view of the index taken after a change to the picker.
+///
+/// [`SharedPicker`] publishes a new snapshot every time a write lock is
+/// released, so searches can run on [`SharedPicker::snapshot`] without
+/// taking the picker lock and never wait for the watcher or a rescan.
+#[derive(Clone)]
+pub struct PickerSnapshot {
+    sync_data: FileSync,
+    base_path: PathBuf,
+    mode: FFFMode,
+    cache_budget: Arc<ContentCacheBudget>,
+    cancelled: Arc<AtomicBool>,
+    bigram_index: Option<Arc<BigramFilter>>,
+    bigram_overlay: Option<Arc<parking_lot::RwLock<BigramOverlay>>>,
+    trigram_index: Option<Arc<TrigramIndex>>,
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+    path_tokens: Option<Arc<PathTokenIndex>>,
+    project_profile: Option<ProjectProfile>,
+    git_enabled: bool,
+    virtual_files: VirtualFiles,
+    auxiliary_roots: AuxiliaryRoots,
+    runtime_excludes: Arc<[String]>,
+}
+
+impl std::fmt::Debug for PickerSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PickerSnapshot")
+            .field("base_path", &self.base_path)
+            .field("files", &self.sync_data.files.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl PickerSnapshot {
+    pub fn base_path(&self) -> &Path {
+        &self.base_path
+    }
+
+    /// See [`FilePickerOptions::git_enabled`].
+    pub fn git_enabled(&self) -> bool {
+        self.git_enabled
+    }
+
+    /// See [`FilePicker::virtual_files`].
+    pub fn virtual_files(&self) -> &VirtualFiles {
+        &self.virtual_files
+    }
+
+    /// See [`FilePicker::auxiliary_roots`].
+    pub fn auxiliary_roots(&self) -> &AuxiliaryRoots {
+        &self.auxiliary_roots
+    }
+
+    pub fn mode(&self) -> FFFMode {
+        self.mode
+    }
+
+    pub fn cache_budget(&self) -> &ContentCacheBudget {
+        &self.cache_budget
+    }
+
+    /// All indexed files at the time of the snapshot, see [`FilePicker::get_files`].
+    pub fn get_files(&self) -> &FileList {
+        self.sync_data.files()
+    }
+
+    /// See [`FilePicker::ignored_files`].
+    pub fn ignored_files(&self) -> &FileList {
+        self.sync_data.ignored_files(&self.base_path)
+    }
+
+    /// See [`FilePicker::files_for_query`].
+    pub fn files_for_query(&self, query: &FFFQuery<'_>) -> &FileList {
+        self.auxiliary_roots
+            .files_for_query(query)
+            .unwrap_or_else(|| self.sync_data.files_for_query(&self.base_path, query))
+    }
+
+    /// See [`FilePicker::exclude_runtime_globs`].
+    pub fn exclude_runtime_globs<'a>(&'a self, query: &mut FFFQuery<'a>) {
+        exclude_globs(query, &self.runtime_excludes);
+    }
+
+    /// See [`FilePicker::index_generation`].
+    pub fn index_generation(&self) -> u64 {
+        self.sync_data.generation
+    }
+
+    /// The file list itself, to keep it alive beyond the snapshot.
+    pub fn files(&self) -> FileList {
+        self.sync_data.files.clone()
+    }
+
+    pub fn project_profile(&self) -> Option<&ProjectProfile> {
+        self.project_profile.as_ref()
+    }
+
+    pub fn suggest_query(&self, query: &str) -> Option<String> {
+        self.path_tokens.as_ref()?.suggest(query)
+    }
+
+    /// See [`FilePicker::install`].
+    pub fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.thread_pool {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+
+    /// See [`FilePicker::grep`].
+    pub fn grep(&self, query: &FFFQuery<'_>, options: &GrepSearchOptions) -> GrepResult<'_> {
+        let overlay_guard = self.bigram_overlay.as_ref().map(|o| o.read());
+        self.install(|| {
+            grep_search_indexed(
+                self.get_files(),
+                query,
+                options,
+                &self.cache_budget,
+                ContentIndexes {
+                    bigram: self.bigram_index.as_deref(),
+                    bigram_overlay: overlay_guard.as_deref(),
+                    trigram: self.trigram_index.as_deref(),
+                },
+                Some(&self.cancelled),
+                None,
+            )
+        })
+    }
+
+    /// See [`FilePicker::changed_lines`].
+    pub fn changed_lines(&self) -> Result<Option<ChangedLines>, Error> {
+        self.sync_data.changed_lines()
+    }
+
+    /// See [`FilePicker::read_context_at`].
+    pub fn read_context_at(
+        &self,
+        path: impl AsRef<Path>,
+        byte_offset: u64,
+        lines_before: usize,
+        lines_after: usize,
+    ) -> Option<PreviewHunk> {
+        self.sync_data.read_context_at(
+            &self.cache_budget,
+            path.as_ref(),
+            byte_offset,
+            lines_before,
+            lines_after,
+        )
+    }
+
+    /// See [`FilePicker::generate_diff`].
+    pub fn generate_diff(
+        &self,
+        path: impl AsRef<Path>,
+        replacements: &[Replacement],
+    ) -> Option<String> {
+        self.sync_data
+            .replacement_diff(&self.cache_budget, path.as_ref(), replacements)
+    }
+
+    /// See [`FilePicker::grep_in_files`].
+    pub fn grep_in_files<P: AsRef<Path>>(
+        &self,
+        query: &FFFQuery<'_>,
+        options: &GrepSearchOptions,
+        paths: impl IntoIterator<Item = P>,
+    ) -> GrepResult<'_> {
+        let subset = self.sync_data.subset_of(paths);
+        let overlay_guard = self.bigram_overlay.as_ref().map(|o| o.read());
+        self.install(|| {
+            grep_search_indexed(
+                self.get_files(),
+                query,
+                options,
+                &self.cache_budget,
+                ContentIndexes {
+                    bigram: self.bigram_index.as_deref(),
+                    bigram_overlay: overlay_guard.as_deref(),
+                    trigram: self.trigram_index.as_deref(),
+                },
+                Some(&self.cancelled),
+                Some(&subset),
+            )
+        })
+    }
+}
+
 impl FilePicker {
+    /// Take a [`PickerSnapshot`] of the current index. Cheap: the file list
+    /// and indexes are shared, not copied.
+    pub fn snapshot(&self) -> PickerSnapshot {
+        PickerSnapshot {
+            sync_data: self.sync_data.clone(),
+            base_path: self.base_path.clone(),
+            mode: self.mode,
+            cache_budget: Arc::clone(&self.cache_budget),
+            cancelled: Arc::clone(&self.cancelled),
+            bigram_index: self.bigram_index.clone(),
+            bigram_overlay: self.bigram_overlay.clone(),
+            trigram_index: self.trigram_index.clone(),
+            thread_pool: self.thread_pool.clone(),
+            path_tokens: self.path_tokens.clone(),
+            project_profile: self.project_profile.clone(),
+            git_enabled: self.git_enabled,
+            virtual_files: self.virtual_files.clone(),
+            auxiliary_roots: self.auxiliary_roots.clone(),
+            runtime_excludes: Arc::clone(&self.runtime_excludes),
+        }
+    }
+
     pub fn base_path(&self) -> &Path {
         &self.base_path
     }
@@ -345,6 +888,90 @@ impl FilePicker {
         self.mode
     }
 
+    pub fn use_os_trash(&self) -> bool {
+        self.use_os_trash
+    }
+
+    pub fn set_use_os_trash(&mut self, use_os_trash: bool) {
+        self.use_os_trash = use_os_trash;
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn respect_ignore_files(&self) -> bool {
+        self.respect_ignore_files
+    }
+
+    pub fn same_file_system(&self) -> bool {
+        self.same_file_system
+    }
+
+    pub fn git_enabled(&self) -> bool {
+        self.git_enabled
+    }
+
+    pub fn git_status_provider(&self) -> GitStatusProvider {
+        self.git_status_provider
+    }
+
+    /// Files of the registered [`VirtualFileProvider`]s. They are not part
+    /// of [`Self::get_files`], callers rank them separately and merge the
+    /// results with [`crate::virtual_files::merge_rankings`].
+    pub fn virtual_files(&self) -> &VirtualFiles {
+        &self.virtual_files
+    }
+
+    /// Make the files of `provider` searchable, replacing a provider
+    /// registered under the same name.
+    pub fn register_virtual_provider(&mut self, provider: Arc<dyn VirtualFileProvider>) {
+        self.virtual_files.register(provider);
+    }
+
+    pub fn unregister_virtual_provider(&mut self, name: &str) -> bool {
+        self.virtual_files.unregister(name)
+    }
+
+    /// Directories searched next to the index, ranked below it by their
+    /// weight. Like the virtual files, their rankings are merged by the
+    /// caller, while [`Self::files_for_query`] gives the files of the root
+    /// a `root:` query targets.
+    pub fn auxiliary_roots(&self) -> &AuxiliaryRoots {
+        &self.auxiliary_roots
+    }
+
+    /// Search the files under `path` as the `name` root, replacing a root
+    /// registered under the same name. The directory is walked on the first
+    /// search, see [`AuxiliaryRoot::new`] for the weight.
+    pub fn register_auxiliary_root(
+        &mut self,
+        name: String,
+        path: PathBuf,
+        weight: u32,
+        label: Option<String>,
+    ) {
+        let root = AuxiliaryRoot::new(name, path, weight, ScanLimits::default());
+        self.auxiliary_roots.register(match label {
+            Some(label) => root.with_label(label),
+            None => root,
+        });
+    }
+
+    pub fn unregister_auxiliary_root(&mut self, name: &str) -> bool {
+        self.auxiliary_roots.unregister(name)
+    }
+
+    pub fn scan_limits(&self) -> ScanLimits {
+        self.scan_limits
+    }
+
+    /// Whether the last scan hit [`ScanLimits::max_files`] and left files
+    /// out of the index.
+    pub fn is_scan_truncated(&self) -> bool {
+        self.sync_data.truncated
+    }
+
     pub fn cache_budget(&self) -> &ContentCacheBudget {
         &self.cache_budget
     }
@@ -357,6 +984,51 @@ impl FilePicker {
         self.bigram_overlay.as_deref()
     }
 
+    pub fn trigram_index_dir(&self) -> Option<&Path> {
+        self.trigram_index_dir.as_deref()
+    }
+
+    pub fn trigram_index(&self) -> Option<&TrigramIndex> {
+        self.trigram_index.as_deref()
+    }
+
+    /// Staleness of the trigram index for the health check, `None` while
+    /// it is disabled or not built yet.
+    pub fn trigram_index_health(&self) -> Option<TrigramIndexHealth> {
+        let index = self.trigram_index.as_ref()?;
+        Some(index.health(self.sync_data.files()))
+    }
+
+    /// Load the trigram index saved for this project, or build and save it
+    /// when it is missing or outdated. Does nothing when
+    /// [`FilePickerOptions::trigram_index_dir`] is not set. The background
+    /// indexing calls this after the bigram index.
+    pub fn load_or_build_trigram_index(&mut self) {
+        if let Some(job) = self.trigram_index_job() {
+            let index = job.run();
+            job.install(self, index);
+        }
+    }
+
+    /// The picker's own pool, see [`FilePickerOptions::thread_pool_size`].
+    pub fn thread_pool(&self) -> Option<&rayon::ThreadPool> {
+        self.thread_pool.as_deref()
+    }
+
+    pub fn background_priority(&self) -> &BackgroundPriority {
+        &self.background_priority
+    }
+
+    /// Run `op` on the picker's own pool, or on the calling thread when it
+    /// has none. Wrap [`FilePicker::fuzzy_search`] in it to keep the scoring
+    /// off the global rayon pool, grep does it by itself.
+    pub fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.thread_pool {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+
     pub fn get_file_mut(&mut self, index: usize) -> Option<&mut FileItem> {
         self.sync_data.get_file_mut(index)
     }
@@ -370,14 +1042,189 @@ impl FilePicker {
         self.sync_data.git_workdir.as_deref()
     }
 
+    /// The lines of the uncommitted hunks of the indexed files, for
+    /// [`GrepSearchOptions::changed_lines`]. Only files with a modified git
+    /// status are diffed. `None` outside of a git repository.
+    pub fn changed_lines(&self) -> Result<Option<ChangedLines>, Error> {
+        self.sync_data.changed_lines()
+    }
+
+    /// Changes whenever the indexed files do: the watcher or a rescan added,
+    /// removed or updated one. Files with the same generation are the same.
+    pub fn index_generation(&self) -> u64 {
+        self.sync_data.generation
+    }
+
     /// Get all indexed files sorted by path.
     /// Note: Files are stored sorted by PATH for efficient insert/remove.
     /// For frecency-sorted results, use search() which sorts matched results.
-    pub fn get_files(&self) -> &[FileItem] {
+    pub fn get_files(&self) -> &FileList {
         self.sync_data.files()
     }
 
-    pub fn get_overflow_files(&self) -> &[FileItem] {
+    /// Files hidden by the ignore rules, sorted by path. Walked by the first
+    /// call after each full scan, which can take a while in `node_modules/`.
+    pub fn ignored_files(&self) -> &FileList {
+        self.sync_data.ignored_files(&self.base_path)
+    }
+
+    /// The files [`Self::fuzzy_search`] should rank for `query`: the index,
+    /// for an `ignored:` query the files the ignore rules hide and for a
+    /// `root:` query the files of that root, both walked on first use.
+    pub fn files_for_query(&self, query: &FFFQuery<'_>) -> &FileList {
+        self.auxiliary_roots
+            .files_for_query(query)
+            .unwrap_or_else(|| self.sync_data.files_for_query(&self.base_path, query))
+    }
+
+    /// Hide the files matching any of `globs` from the searches that go
+    /// through [`Self::exclude_runtime_globs`], e.g. tests or generated code
+    /// while a keymap is toggled. Filters the results, the index stays as is,
+    /// so this takes effect immediately. An empty list shows everything again.
+    pub fn set_runtime_excludes(&mut self, globs: Vec<String>) {
+        self.runtime_excludes = globs.into();
+    }
+
+    pub fn runtime_excludes(&self) -> &[String] {
+        &self.runtime_excludes
+    }
+
+    /// Add the [`Self::set_runtime_excludes`] globs to `query` as `!glob`
+    /// constraints, for the fuzzy search and grep alike.
+    pub fn exclude_runtime_globs<'a>(&'a self, query: &mut FFFQuery<'a>) {
+        exclude_globs(query, &self.runtime_excludes);
+    }
+
+    /// "Did you mean" correction of `query` built from the indexed path
+    /// tokens, meant for searches that matched nothing. `None` until the
+    /// token index is built or when no word of the query looks misspelled.
+    pub fn suggest_query(&self, query: &str) -> Option<String> {
+        self.path_tokens.as_ref()?.suggest(query)
+    }
+
+    /// Completions of the constraint token being typed: `*.r` gives the
+    /// indexed extensions starting with `r`, `status:` the git statuses and a
+    /// bare word the keys and directory names starting with it. Extensions
+    /// and directories come with their file count, most files first.
+    pub fn complete_constraint(&self, prefix: &str) -> Vec<ConstraintCompletion> {
+        let files = self.sync_data.files().iter().filter(|f| !f.is_deleted);
+        complete_constraint(files, prefix)
+    }
+
+    /// Indexed files of at least `min_size` bytes that have the same content
+    /// as another indexed file, grouped by content. Groups that waste the
+    /// most space come first.
+    pub fn find_duplicates(&self, min_size: u64) -> Vec<DuplicateGroup<'_>> {
+        find_duplicates(self.sync_data.files().iter(), min_size)
+    }
+
+    /// TODO, FIXME, HACK and XXX comments ranked against `query` by fuzzy
+    /// score and file frecency, best first. Builds the index on the first
+    /// call if the background build has not finished yet.
+    pub fn search_todos(&self, query: &str, max_results: usize) -> Vec<TodoMatch<'_>> {
+        if self.line_indexes.read().todos.is_none() {
+            let index = self.install(|| {
+                TodoIndex::build(self.sync_data.files(), &self.cache_budget, scan_todos)
+            });
+            self.line_indexes.write().todos.get_or_insert(index);
+        }
+
+        let indexes = self.line_indexes.read();
+        indexes.todos.as_ref().map_or_else(Vec::new, |index| {
+            index.search(
+                query,
+                max_results,
+                |path| self.get_file_by_path(path),
+                |_| true,
+            )
+        })
+    }
+
+    /// Lines matching the keyword pattern `name` ranked against `query` like
+    /// [`Self::search_todos`]. `None` when no pattern is named `name`.
+    pub fn search_keyword(
+        &self,
+        name: &str,
+        query: &str,
+        max_results: usize,
+    ) -> Option<Vec<KeywordMatch<'_>>> {
+        let matcher = self.keyword_matcher.as_deref()?;
+        let kind = matcher.position(name)?;
+        if self.line_indexes.read().keywords.is_none() {
+            let index = self.install(|| {
+                KeywordIndex::build(self.sync_data.files(), &self.cache_budget, |c| {
+                    matcher.scan(c)
+                })
+            });
+            self.line_indexes.write().keywords.get_or_insert(index);
+        }
+
+        let indexes = self.line_indexes.read();
+        let index = indexes.keywords.as_ref()?;
+        Some(index.search(
+            query,
+            max_results,
+            |path| self.get_file_by_path(path),
+            |k| k == kind,
+        ))
+    }
+
+    /// Tests ranked against `query` by fuzzy score of their path and name
+    /// and file frecency, like [`Self::search_todos`].
+    pub fn search_tests(&self, query: &str, max_results: usize) -> Vec<TestMatch<'_>> {
+        self.ensure_test_index();
+        let indexes = self.line_indexes.read();
+        indexes.tests.as_ref().map_or_else(Vec::new, |index| {
+            index.search(
+                query,
+                max_results,
+                |path| self.get_file_by_path(path),
+                |_| true,
+            )
+        })
+    }
+
+    /// The test `line_number` of `path` is in: the last one declared at or
+    /// before it. `None` when the file declares no test before that line.
+    pub fn test_at(&self, path: &Path, line_number: u64) -> Option<TestMatch<'_>> {
+        let file = self.get_file_by_path(path)?;
+        self.ensure_test_index();
+        let indexes = self.line_indexes.read();
+        let entries = indexes.tests.as_ref()?.file_entries(file.path());
+        let test = enclosing_test(entries, line_number)?;
+        Some(TestMatch {
+            file,
+            line_number: test.line_number,
+            kind: test.kind,
+            text: test.text.clone(),
+            score: 0,
+        })
+    }
+
+    fn ensure_test_index(&self) {
+        if self.line_indexes.read().tests.is_none() {
+            let index = self.install(|| {
+                TestIndex::build(self.sync_data.files(), &self.cache_budget, scan_tests)
+            });
+            self.line_indexes.write().tests.get_or_insert(index);
+        }
+    }
+
+    pub fn keyword_patterns(&self) -> &[(String, String)] {
+        &self.keyword_patterns
+    }
+
+    /// Project profile detected when the picker was created, `None` when
+    /// disabled or when no known project type was found.
+    pub fn project_profile(&self) -> Option<&ProjectProfile> {
+        self.project_profile.as_ref()
+    }
+
+    pub fn project_profile_options(&self) -> &ProjectProfileOptions {
+        &self.project_profile_options
+    }
+
+    pub fn get_overflow_files(&self) -> impl ExactSizeIterator<Item = &FileItem> {
         self.sync_data.overflow_files()
     }
 
@@ -390,6 +1237,8 @@ impl FilePicker {
             error!("Base path does not exist: {}", options.base_path);
             return Err(Error::InvalidPath(path));
         }
+        // index files by the paths the frecency and query databases use
+        let path = normalize_key(&path);
         if path.parent().is_none() {
             error!("Refusing to index filesystem root: {}", path.display());
             return Err(Error::FilesystemRoot(path));
@@ -397,6 +1246,29 @@ impl FilePicker {
 
         let has_explicit_budget = options.cache_budget.is_some();
         let initial_budget = options.cache_budget.unwrap_or_default();
+        let keyword_matcher = KeywordMatcher::new(&options.keyword_patterns)?.map(Arc::new);
+        let thread_pool = match options.thread_pool_size {
+            Some(size) => Some(Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(size)
+                    .thread_name(|i| format!("fff-{i}"))
+                    .build()?,
+            )),
+            None => None,
+        };
+        let background_pool = match options.background_priority.max_parallelism {
+            Some(threads) => Some(Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads.max(1))
+                    .thread_name(|i| format!("fff-bg-{i}"))
+                    .build()?,
+            )),
+            None => thread_pool.clone(),
+        };
+        let project_profile = ProjectProfile::detect(&path, &options.project_profile);
+        if let Some(profile) = &project_profile {
+            info!(kinds = ?profile.kinds, "Detected project profile");
+        }
 
         Ok(FilePicker {
             background_watcher: None,
@@ -408,11 +1280,32 @@ impl FilePicker {
             has_explicit_cache_budget: has_explicit_budget,
             is_scanning: Arc::new(AtomicBool::new(false)),
             mode: options.mode,
+            path_tokens: None,
+            project_profile,
+            project_profile_options: options.project_profile,
+            respect_ignore_files: options.respect_ignore_files,
+            same_file_system: options.same_file_system,
+            git_enabled: options.git_enabled,
+            git_status_provider: options.git_status_provider,
+            scan_limits: options.scan_limits,
             scanned_files_count: Arc::new(AtomicUsize::new(0)),
             sync_data: FileSync::new(),
+            line_indexes: Default::default(),
+            keyword_patterns: options.keyword_patterns,
+            keyword_matcher,
+            trigram_index_dir: options.trigram_index_dir.filter(|_| !options.read_only),
+            trigram_index: None,
+            thread_pool,
+            background_pool,
+            background_priority: options.background_priority,
+            use_os_trash: options.use_os_trash,
             warmup_mmap_cache: options.warmup_mmap_cache,
             watch: options.watch,
             watcher_ready: Arc::new(AtomicBool::new(false)),
+            virtual_files: options.virtual_files,
+            auxiliary_roots: options.auxiliary_roots,
+            runtime_excludes: Arc::default(),
+            read_only: options.read_only,
         })
     }
 
@@ -423,7 +1316,11 @@ impl FilePicker {
         shared_frecency: SharedFrecency,
         options: FilePickerOptions,
     ) -> Result<(), Error> {
+        let given_path = PathBuf::from(&options.base_path);
         let picker = Self::new(options)?;
+        // histories tracked under the base path as given are moved to the
+        // normalized one by the first scan
+        let key_alias = (given_path != picker.base_path).then_some(given_path);
 
         info!(
             "Spawning background threads: base_path={}, warmup={}, mode={:?}",
@@ -435,6 +1332,13 @@ impl FilePicker {
         let warmup = picker.warmup_mmap_cache;
         let watch = picker.watch;
         let mode = picker.mode;
+        let respect_ignore_files = picker.respect_ignore_files;
+        let same_file_system = picker.same_file_system;
+        let git_enabled = picker.git_enabled;
+        let git_status_provider = picker.git_status_provider;
+        let scan_limits = picker.scan_limits;
+        let background_pool = picker.background_pool.clone();
+        let background_priority = picker.background_priority.clone();
 
         picker.is_scanning.store(true, Ordering::Release);
 
@@ -451,15 +1355,23 @@ impl FilePicker {
 
         spawn_scan_and_watcher(
             path,
+            key_alias,
             scan_signal,
             watcher_ready,
             synced_files_count,
             warmup,
             watch,
             mode,
+            respect_ignore_files,
+            same_file_system,
+            git_enabled,
+            git_status_provider,
+            scan_limits,
             shared_picker,
             shared_frecency,
             cancelled,
+            background_pool,
+            background_priority,
         );
 
         Ok(())
@@ -480,12 +1392,20 @@ impl FilePicker {
         let empty_frecency = SharedFrecency::default();
         let walk = walk_filesystem(
             &self.base_path,
+            None,
             &self.scanned_files_count,
             &empty_frecency,
             self.mode,
+            self.respect_ignore_files,
+            self.same_file_system,
+            self.git_enabled,
+            self.git_status_provider,
+            self.scan_limits,
+            background_pool(self.background_pool.as_ref()),
         )?;
 
         self.sync_data = walk.sync;
+        self.path_tokens = Some(Arc::new(PathTokenIndex::build(self.sync_data.files())));
 
         // Recalculate cache budget based on actual file count (unless
         // the caller provided an explicit budget via FilePickerOptions).
@@ -498,8 +1418,8 @@ impl FilePicker {
 
         // Apply git status synchronously.
         if let Ok(Some(git_cache)) = walk.git_handle.join() {
-            for file in self.sync_data.files.iter_mut() {
-                file.git_status = git_cache.lookup_status(&file.path);
+            for file in self.sync_data.files_mut().iter_mut() {
+                file.git_status = git_cache.lookup_status(file.path());
             }
         }
 
@@ -524,6 +1444,8 @@ impl FilePicker {
             shared_picker.clone(),
             shared_frecency.clone(),
             self.mode,
+            self.respect_ignore_files,
+            self.same_file_system,
         )?;
         self.background_watcher = Some(watcher);
         self.watcher_ready.store(true, Ordering::Release);
@@ -534,11 +1456,11 @@ impl FilePicker {
     ///
     /// The query should be parsed using [`FFFQuery`]::parse() before calling
     /// this function. If a [`QueryTracker`] is provided, the search will
-    /// automatically look up the last selected file for this query and apply
-    /// combo-boost scoring.
+    /// automatically look up the files selected for this query before and
+    /// apply combo-boost scoring.
     ///
     pub fn fuzzy_search<'a, 'q>(
-        files: &'a [FileItem],
+        files: &'a FileList,
         query: &'q FFFQuery<'q>,
         query_tracker: Option<&QueryTracker>,
         options: FuzzySearchOptions<'q>,
@@ -559,6 +1481,21 @@ impl FilePicker {
             "Fuzzy search",
         );
 
+        if let Some(page) = options.cursor.and_then(|cursor| {
+            cursor.next_page(
+                files,
+                query.raw_query,
+                options.pagination.offset,
+                options.pagination.limit,
+            )
+        }) {
+            debug!(
+                returned_count = page.items.len(),
+                "Fuzzy search page from cursor"
+            );
+            return page;
+        }
+
         let total_files = files.len();
         let location = query.location;
 
@@ -570,21 +1507,40 @@ impl FilePicker {
         };
 
         // small queries with a large number of results can match absolutely everything
-        let max_typos = (effective_query.len() as u16 / 4).clamp(2, 6);
-        // Look up the last file selected for this query (combo-boost scoring)
-        let last_same_query_entry =
+        let max_typos = options
+            .max_typos
+            .unwrap_or_else(|| (effective_query.len() as u16 / 4).clamp(2, 6));
+        // Look up the files selected for this query before (combo-boost scoring)
+        let same_query_matches = query_tracker
+            .zip(options.project_path)
+            .and_then(|(tracker, project_path)| {
+                tracker
+                    .get_query_candidates(
+                        query.raw_query,
+                        project_path,
+                        options.min_combo_count,
+                        options.min_combo_prefix_len,
+                    )
+                    .ok()
+            })
+            .unwrap_or_default();
+        // and the files usually opened with the current one, which only matter
+        // while the query is too short to tell them apart
+        let co_opened = if effective_query.chars().count() <= AFFINITY_MAX_QUERY_LEN {
             query_tracker
                 .zip(options.project_path)
-                .and_then(|(tracker, project_path)| {
-                    tracker
-                        .get_last_query_entry(
-                            query.raw_query,
-                            project_path,
-                            options.min_combo_count,
-                        )
-                        .ok()
-                        .flatten()
-                });
+                .zip(options.current_file)
+                .and_then(|((tracker, project_path), current_file)| {
+                    tracker.get_co_opened(project_path, current_file).ok()
+                })
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        let search_root = options
+            .search_root
+            .and_then(|root| search_root_prefix(root, options.project_path));
 
         let context = ScoringContext {
             query,
@@ -592,45 +1548,106 @@ impl FilePicker {
             max_threads,
             project_path: options.project_path,
             current_file: options.current_file,
-            last_same_query_match: last_same_query_entry,
+            same_query_matches,
+            co_opened,
             combo_boost_score_multiplier: options.combo_boost_score_multiplier,
             min_combo_count: options.min_combo_count,
             pagination: options.pagination,
+            adaptive_typos_threshold: options.adaptive_typos_threshold,
+            scoring_weights: options.scoring_weights,
+            extension_multipliers: options.extension_multipliers,
+            project_profile: options.project_profile,
+            search_root: search_root.as_deref(),
+            facets: options.facets,
+            min_score: options.min_score,
+            min_score_ratio: options.min_score_ratio,
+            cursor: options.with_cursor,
+            working_set: options
+                .working_set
+                .map(|cache| (cache, options.index_generation)),
+            scratch: options.scratch,
         };
 
+        let _crash_guard =
+            crate::crash::enter_search(SearchKind::Fuzzy, query.raw_query, total_files);
         let time = std::time::Instant::now();
-        let (items, scores, total_matched) = match_and_score_files(files, &context);
+        let (items, scores, total_matched, match_stats, facets, rest) =
+            match_and_score_files(files, &context);
+        crate::metrics::record(SearchKind::Fuzzy, query.raw_query, time.elapsed());
 
         info!(
             ?query,
             completed_in = ?time.elapsed(),
             total_matched,
+            ?match_stats,
             returned_count = items.len(),
             pagination = ?options.pagination,
             "Fuzzy search completed",
         );
 
-        SearchResult {
+        let mut result = SearchResult {
             items,
             scores,
             total_matched,
             total_files,
             location,
-        }
+            match_stats,
+            suggestion: None,
+            facets,
+            cursor: None,
+            index_generation: options.index_generation,
+        };
+        let next_offset = options.pagination.offset + result.items.len();
+        result.cursor = SearchCursor::new(files, query.raw_query, next_offset, rest, &result);
+        result
     }
 
     /// Perform a live grep search across indexed files with a pre-parsed query.
     pub fn grep(&self, query: &FFFQuery<'_>, options: &GrepSearchOptions) -> GrepResult<'_> {
         let overlay_guard = self.bigram_overlay.as_ref().map(|o| o.read());
-        grep_search(
-            self.get_files(),
-            query,
-            options,
-            self.cache_budget(),
-            self.bigram_index.as_deref(),
-            overlay_guard.as_deref(),
-            Some(&self.cancelled),
-        )
+        self.install(|| {
+            grep_search_indexed(
+                self.get_files(),
+                query,
+                options,
+                self.cache_budget(),
+                ContentIndexes {
+                    bigram: self.bigram_index.as_deref(),
+                    bigram_overlay: overlay_guard.as_deref(),
+                    trigram: self.trigram_index.as_deref(),
+                },
+                Some(&self.cancelled),
+                None,
+            )
+        })
+    }
+
+    /// Like [`grep`](Self::grep) but only searches `paths`, typically the
+    /// files matched by a previous fuzzy search. Paths that are not indexed
+    /// are ignored.
+    pub fn grep_in_files<P: AsRef<Path>>(
+        &self,
+        query: &FFFQuery<'_>,
+        options: &GrepSearchOptions,
+        paths: impl IntoIterator<Item = P>,
+    ) -> GrepResult<'_> {
+        let subset = self.sync_data.subset_of(paths);
+        let overlay_guard = self.bigram_overlay.as_ref().map(|o| o.read());
+        self.install(|| {
+            grep_search_indexed(
+                self.get_files(),
+                query,
+                options,
+                self.cache_budget(),
+                ContentIndexes {
+                    bigram: self.bigram_index.as_deref(),
+                    bigram_overlay: overlay_guard.as_deref(),
+                    trigram: self.trigram_index.as_deref(),
+                },
+                Some(&self.cancelled),
+                Some(&subset),
+            )
+        })
     }
 
     /// Like [`grep`](Self::grep) but ignores the bigram overlay.
@@ -660,6 +1677,7 @@ impl FilePicker {
             is_scanning,
             is_watcher_ready: self.watcher_ready.load(Ordering::Relaxed),
             is_warmup_complete: self.bigram_index.is_some(),
+            is_truncated: self.sync_data.truncated,
         }
     }
 
@@ -684,26 +1702,54 @@ impl FilePicker {
                     if let Some(ref f) = *frecency {
                         file.update_frecency_scores(f, mode)?;
                     }
+                    return Ok(());
+                }
+
+                // an untracked directory reported as a whole
+                let indices = if status == Status::WT_NEW {
+                    self.sync_data.indices_under(&path)
                 } else {
+                    vec![]
+                };
+                if indices.is_empty() {
                     error!(?path, "Couldn't update the git status for path");
                 }
+                for index in indices {
+                    if let Some(file) = self.sync_data.get_file_mut(index) {
+                        file.git_status = Some(status);
+                        if let Some(ref f) = *frecency {
+                            file.update_frecency_scores(f, mode)?;
+                        }
+                    }
+                }
                 Ok(())
             })?;
 
         Ok(())
     }
 
+    /// Drop the statuses the files already have, so applying a full refresh
+    /// only touches what changed. The scan leaves clean files without a
+    /// status, which is the same as [`Status::CURRENT`]. Paths the picker
+    /// can't match directly, like untracked directories, are kept.
+    pub fn retain_changed_git_statuses(&self, status_cache: &mut GitStatusCache) {
+        status_cache.retain(|path, status| match self.get_file_by_path(path) {
+            Some(file) => file.git_status.unwrap_or(Status::CURRENT) != status,
+            None => true,
+        });
+    }
+
     pub fn update_single_file_frecency(
         &mut self,
         file_path: impl AsRef<Path>,
         frecency_tracker: &FrecencyTracker,
     ) -> Result<(), Error> {
-        let path = file_path.as_ref();
+        let path = normalize_key(file_path.as_ref());
         let index = self
             .sync_data
-            .find_file_index(path)
+            .find_file_index(&path)
             .ok()
-            .or_else(|| self.sync_data.find_overflow_index(path));
+            .or_else(|| self.sync_data.find_overflow_index(&path));
         if let Some(index) = index
             && let Some(file) = self.sync_data.get_file_mut(index)
         {
@@ -713,6 +1759,64 @@ impl FilePicker {
         Ok(())
     }
 
+    /// Replace the scores added by an integration, e.g. the references to the
+    /// symbol under the cursor or the files with failing tests. Paths are
+    /// absolute or relative to the base path, every other file goes back to 0.
+    /// They live on the indexed files, so a rescan clears them.
+    ///
+    /// Returns how many of the paths are indexed.
+    pub fn set_external_scores<P: AsRef<Path>>(
+        &mut self,
+        scores: impl IntoIterator<Item = (P, i32)>,
+    ) -> usize {
+        let files = self.sync_data.files_mut();
+        for file in files.iter_mut() {
+            file.external_score = 0;
+        }
+
+        let mut matched = 0;
+        for (path, score) in scores {
+            let path = normalize_key(&self.base_path.join(path));
+            if let Some(index) = self.sync_data.find_any_index(&path)
+                && let Some(file) = self.sync_data.get_file_mut(index)
+            {
+                file.external_score = score;
+                matched += 1;
+            }
+        }
+        matched
+    }
+
+    /// Read the frecency scores of every file again, e.g. after the
+    /// [`FrecencyContext`](crate::frecency::FrecencyContext) changed.
+    pub fn refresh_frecency_scores(&mut self, frecency_tracker: &FrecencyTracker) {
+        let mode = self.mode;
+        let pool = background_pool(self.background_pool.as_ref());
+        let files = self.sync_data.files_mut();
+        pool.install(|| {
+            files.par_iter_mut().for_each(|file| {
+                let _ = file.update_frecency_scores(frecency_tracker, mode);
+            });
+        });
+    }
+
+    /// Rescore the files another process changed the frecency of, see
+    /// [`FrecencyTracker::take_external_changes`].
+    pub fn apply_external_frecency_changes(
+        &mut self,
+        changes: &ExternalChanges,
+        frecency_tracker: &FrecencyTracker,
+    ) {
+        match changes {
+            ExternalChanges::All => self.refresh_frecency_scores(frecency_tracker),
+            ExternalChanges::Paths(paths) => {
+                for path in paths {
+                    let _ = self.update_single_file_frecency(path, frecency_tracker);
+                }
+            }
+        }
+    }
+
     pub fn get_file_by_path(&self, path: impl AsRef<Path>) -> Option<&FileItem> {
         self.sync_data
             .find_file_index(path.as_ref())
@@ -720,20 +1824,49 @@ impl FilePicker {
             .and_then(|index| self.sync_data.files().get(index))
     }
 
+    /// The line of the file at `path` containing `byte_offset`, e.g. the
+    /// [`GrepMatch::byte_offset`](crate::GrepMatch::byte_offset) of a match, with up to `lines_before` and
+    /// `lines_after` lines around it. Reads the content the grep search
+    /// cached instead of the file, and only counts the newlines before the
+    /// hunk, so previewing a match far down a large file stays cheap.
+    /// `None` for unknown, binary or too large files and offsets past the end.
+    pub fn read_context_at(
+        &self,
+        path: impl AsRef<Path>,
+        byte_offset: u64,
+        lines_before: usize,
+        lines_after: usize,
+    ) -> Option<PreviewHunk> {
+        self.sync_data.read_context_at(
+            &self.cache_budget,
+            path.as_ref(),
+            byte_offset,
+            lines_before,
+            lines_after,
+        )
+    }
+
+    /// The unified diff of the file at `path` with `replacements` applied,
+    /// for previewing a search and replace. Built from the content the grep
+    /// search cached, labelled with the relative path. Empty when nothing
+    /// changes, `None` for unknown, binary or too large files.
+    pub fn generate_diff(
+        &self,
+        path: impl AsRef<Path>,
+        replacements: &[Replacement],
+    ) -> Option<String> {
+        self.sync_data
+            .replacement_diff(&self.cache_budget, path.as_ref(), replacements)
+    }
+
     pub fn get_mut_file_by_path(&mut self, path: impl AsRef<Path>) -> Option<&mut FileItem> {
-        let path = path.as_ref();
-        // Check sorted base first (O(log n)), then overflow tail (O(k)).
-        let index = self
-            .sync_data
-            .find_file_index(path)
-            .ok()
-            .or_else(|| self.sync_data.find_overflow_index(path));
+        let index = self.sync_data.find_any_index(path.as_ref());
         index.and_then(|i| self.sync_data.get_file_mut(i))
     }
 
     /// Add a file to the picker's files in sorted order (used by background watcher)
     pub fn add_file_sorted(&mut self, file: FileItem) -> Option<&FileItem> {
-        let path = file.path.clone();
+        let path = file.path().to_path_buf();
 
         if self.sync_data.insert_file_sorted(file) {
             // File was inserted, look it up
@@ -759,6 +1892,9 @@ impl FilePicker {
     #[tracing::instrument(skip(self), name = "timing_update", level = Level::DEBUG)]
     pub fn on_create_or_modify(&mut self, path: impl AsRef<Path> + Debug) -> Option<&FileItem> {
         let path = path.as_ref();
+        if let Some(index) = &self.trigram_index {
+            index.update_file(path, std::fs::read(path).ok().as_deref());
+        }
 
         // Check if this is a tombstoned base file being re-created.
         if let Ok(pos) = self.sync_data.find_file_index(path) {
@@ -803,13 +1939,14 @@ impl FilePicker {
             {
                 overlay.write().modify_file(pos, &content);
             }
+            update_line_indexes(&self.line_indexes, self.keyword_matcher.as_deref(), path);
 
             return Some(&*file);
         }
 
         // Check overflow for existing added files.
         if let Some(abs_pos) = self.sync_data.find_overflow_index(path) {
-            let file = &mut self.sync_data.files[abs_pos];
+            let file = &mut self.sync_data.files_mut()[abs_pos];
             let modified = std::fs::metadata(path)
                 .ok()
                 .and_then(|m| m.modified().ok())
@@ -829,6 +1966,7 @@ impl FilePicker {
                 let bigrams = crate::bigram_filter::extract_bigrams(&content);
                 overlay.write().update_added(overflow_pos, bigrams);
             }
+            update_line_indexes(&self.line_indexes, self.keyword_matcher.as_deref(), path);
             return Some(&self.sync_data.files[abs_pos]);
         }
 
@@ -840,12 +1978,13 @@ impl FilePicker {
         );
 
         let file_item = FileItem::new(path.to_path_buf(), &self.base_path, None);
-        self.sync_data.files.push(file_item);
+        self.sync_data.files_mut().push(file_item);
 
         if let Some(ref overlay) = self.bigram_overlay {
             let content = std::fs::read(path).unwrap_or_default();
             overlay.write().add_file(&content);
         }
+        update_line_indexes(&self.line_indexes, self.keyword_matcher.as_deref(), path);
 
         self.sync_data.files.last()
     }
@@ -853,9 +1992,13 @@ impl FilePicker {
     /// Tombstone a file instead of removing it, keeping base indices stable.
     pub fn remove_file_by_path(&mut self, path: impl AsRef<Path>) -> bool {
         let path = path.as_ref();
+        self.line_indexes.get_mut().remove_file(path);
+        if let Some(index) = &self.trigram_index {
+            index.remove_file(path);
+        }
         match self.sync_data.find_file_index(path) {
             Ok(index) => {
-                let file = &mut self.sync_data.files[index];
+                let file = &mut self.sync_data.files_mut()[index];
                 file.is_deleted = true;
                 file.invalidate_mmap(&self.cache_budget);
                 if let Some(ref overlay) = self.bigram_overlay {
@@ -868,7 +2011,7 @@ impl FilePicker {
                 // since they aren't in the base bigram index.
                 if let Some(abs_pos) = self.sync_data.find_overflow_index(path) {
                     let overflow_pos = abs_pos - self.sync_data.base_count;
-                    self.sync_data.files.remove(abs_pos);
+                    self.sync_data.files_mut().remove(abs_pos);
                     if let Some(ref overlay) = self.bigram_overlay {
                         overlay.write().remove_added(overflow_pos);
                     }
@@ -883,9 +2026,13 @@ impl FilePicker {
     // TODO make this O(n)
     pub fn remove_all_files_in_dir(&mut self, dir: impl AsRef<Path>) -> usize {
         let dir_path = dir.as_ref();
+        self.line_indexes.get_mut().remove_dir(dir_path);
+        if let Some(index) = &self.trigram_index {
+            index.remove_dir(dir_path);
+        }
         // Use the safe retain_files method which maintains both indices
         self.sync_data
-            .retain_files(|file| !file.path.starts_with(dir_path))
+            .retain_files(|file| !file.path().starts_with(dir_path))
     }
 
     /// Use this to prevent any substantial background threads from acquiring the locks
@@ -906,48 +2053,9 @@ impl FilePicker {
         }
 
         self.is_scanning.store(true, Ordering::Relaxed);
-        self.scanned_files_count.store(0, Ordering::Relaxed);
-
-        let walk_result = walk_filesystem(
-            &self.base_path,
-            &self.scanned_files_count,
-            shared_frecency,
-            self.mode,
-        );
 
-        match walk_result {
-            Ok(walk) => {
-                info!(
-                    "Filesystem rescan completed: found {} files",
-                    walk.sync.files.len()
-                );
-
-                self.sync_data = walk.sync;
-                self.cache_budget.reset();
-
-                // Apply git status synchronously for rescan (typically fast).
-                if let Ok(Some(git_cache)) = walk.git_handle.join() {
-                    let frecency = shared_frecency.read().ok();
-                    let frecency_ref = frecency.as_ref().and_then(|f| f.as_ref());
-                    let mode = self.mode;
-                    BACKGROUND_THREAD_POOL.install(|| {
-                        self.sync_data.files.par_iter_mut().for_each(|file| {
-                            file.git_status = git_cache.lookup_status(&file.path);
-                            if let Some(frecency) = frecency_ref {
-                                let _ = file.update_frecency_scores(frecency, mode);
-                            }
-                        });
-                    });
-                }
-
-                if self.warmup_mmap_cache {
-                    let files = self.sync_data.files().to_vec();
-                    let budget = Arc::clone(&self.cache_budget);
-                    std::thread::spawn(move || {
-                        warmup_mmaps(&files, &budget);
-                    });
-                }
-            }
+        match self.rescan_job().run(shared_frecency) {
+            Ok(sync) => self.install_rescan(sync),
             Err(error) => error!(?error, "Failed to scan file system"),
         }
 
@@ -955,6 +2063,68 @@ impl FilePicker {
         Ok(())
     }
 
+    /// Everything a rescan needs from the picker, so the walk can run
+    /// without holding the picker lock.
+    /// `None` when the trigram index is disabled.
+    pub(crate) fn trigram_index_job(&self) -> Option<TrigramIndexJob> {
+        Some(TrigramIndexJob {
+            dir: self.trigram_index_dir.clone()?,
+            base_path: self.base_path.clone(),
+            files: self.sync_data.files.clone(),
+            budget: Arc::clone(&self.cache_budget),
+            scan_signal: Arc::clone(&self.is_scanning),
+            thread_pool: self.background_pool.clone(),
+        })
+    }
+
+    pub(crate) fn rescan_job(&self) -> RescanJob {
+        RescanJob {
+            base_path: self.base_path.clone(),
+            scanned_files_count: Arc::clone(&self.scanned_files_count),
+            mode: self.mode,
+            respect_ignore_files: self.respect_ignore_files,
+            same_file_system: self.same_file_system,
+            git_enabled: self.git_enabled,
+            git_status_provider: self.git_status_provider,
+            scan_limits: self.scan_limits,
+            thread_pool: self.background_pool.clone(),
+        }
+    }
+
+    /// Swap in the files found by a [`RescanJob`]. The new list replaces the
+    /// old one as a whole; snapshots still holding the old list keep it.
+    pub(crate) fn install_rescan(&mut self, sync: FileSync) {
+        info!(
+            "Filesystem rescan completed: found {} files",
+            sync.files.len()
+        );
+
+        self.sync_data = sync;
+        self.path_tokens = Some(Arc::new(PathTokenIndex::build(self.sync_data.files())));
+        // rebuilt by the next search_todos
+        *self.line_indexes.get_mut() = LineIndexes::default();
+        // rebuilt by the rescan job, see SharedPicker::trigger_rescan
+        if let Some(index) = &self.trigram_index {
+            index.invalidate();
+        }
+        self.cache_budget.reset();
+
+        if self.warmup_mmap_cache && !self.background_priority.skips_warmup() {
+            let files = self.sync_data.files.clone();
+            let budget = Arc::clone(&self.cache_budget);
+            let thread_pool = self.background_pool.clone();
+            let pause = self.background_priority.batch_pause;
+            std::thread::spawn(move || {
+                warmup_mmaps_in(
+                    background_pool(thread_pool.as_ref()),
+                    &files,
+                    &budget,
+                    pause,
+                );
+            });
+        }
+    }
+
     /// Quick way to check if scan is going without acquiring a lock for [Self::get_scan_progress]
     pub fn is_scan_active(&self) -> bool {
         self.is_scanning.load(Ordering::Relaxed)
@@ -986,29 +2156,52 @@ pub struct ScanProgress {
     pub is_scanning: bool,
     pub is_watcher_ready: bool,
     pub is_warmup_complete: bool,
+    /// The scan stopped at [`ScanLimits::max_files`].
+    pub is_truncated: bool,
 }
 
 #[allow(clippy::too_many_arguments)]
 fn spawn_scan_and_watcher(
     base_path: PathBuf,
+    key_alias: Option<PathBuf>,
     scan_signal: Arc<AtomicBool>,
     watcher_ready: Arc<AtomicBool>,
     synced_files_count: Arc<AtomicUsize>,
     warmup_mmap_cache: bool,
     watch: bool,
     mode: FFFMode,
+    respect_ignore_files: bool,
+    same_file_system: bool,
+    git_enabled: bool,
+    git_status_provider: GitStatusProvider,
+    scan_limits: ScanLimits,
     shared_picker: SharedPicker,
     shared_frecency: SharedFrecency,
     cancelled: Arc<AtomicBool>,
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+    priority: BackgroundPriority,
 ) {
     std::thread::spawn(move || {
+        let pool = background_pool(thread_pool.as_ref());
         // scan_signal is already `true` (set by the caller before spawning)
         // so waiters see "scanning" even before this thread is scheduled.
         info!("Starting initial file scan");
 
         let git_workdir;
 
-        match walk_filesystem(&base_path, &synced_files_count, &shared_frecency, mode) {
+        match walk_filesystem(
+            &base_path,
+            key_alias.as_deref(),
+            &synced_files_count,
+            &shared_frecency,
+            mode,
+            respect_ignore_files,
+            same_file_system,
+            git_enabled,
+            git_status_provider,
+            scan_limits,
+            pool,
+        ) {
             Ok(walk) => {
                 if cancelled.load(Ordering::Acquire) {
                     info!("Walk completed but picker was replaced, discarding results");
@@ -1039,16 +2232,22 @@ fn spawn_scan_and_watcher(
 
                 // Signal scan complete — files are searchable.
                 scan_signal.store(false, Ordering::Relaxed);
+                shared_picker.emit(IndexEvent::ScanCompleted);
                 info!("Files indexed and searchable");
 
                 // Apply git status (may still be running — this waits for it).
                 if !cancelled.load(Ordering::Acquire) {
-                    apply_git_status(&shared_picker, &shared_frecency, git_handle, mode);
+                    apply_git_status(&shared_picker, &shared_frecency, git_handle, mode, pool);
+                }
+
+                if !cancelled.load(Ordering::Acquire) {
+                    build_path_tokens(&shared_picker);
                 }
             }
             Err(e) => {
                 error!("Initial scan failed: {:?}", e);
                 scan_signal.store(false, Ordering::Relaxed);
+                shared_picker.emit(IndexEvent::ScanCompleted);
                 watcher_ready.store(true, Ordering::Release);
                 return;
             }
@@ -1061,6 +2260,8 @@ fn spawn_scan_and_watcher(
                 shared_picker.clone(),
                 shared_frecency.clone(),
                 mode,
+                respect_ignore_files,
+                same_file_system,
             ) {
                 Ok(watcher) => {
                     info!("Background file watcher initialized successfully");
@@ -1090,7 +2291,9 @@ fn spawn_scan_and_watcher(
 
         watcher_ready.store(true, Ordering::Release);
 
-        if warmup_mmap_cache && !cancelled.load(Ordering::Acquire) {
+        if warmup_mmap_cache && priority.skips_warmup() {
+            info!("Skipping content warmup as asked by the background priority");
+        } else if warmup_mmap_cache && !cancelled.load(Ordering::Acquire) {
             let phase_start = std::time::Instant::now();
 
             // Scale cache limits based on repo size (skip if caller provided an explicit budget).
@@ -1106,32 +2309,22 @@ fn spawn_scan_and_watcher(
                 );
             }
 
-            // SAFETY: The file index Vec is not resized between the initial scan
-            // completing and the warmup + bigram phase finishing.
-            let files_snapshot: Option<(&[FileItem], Arc<ContentCacheBudget>)> =
-                if !cancelled.load(Ordering::Acquire) {
-                    let guard = shared_picker.read().ok();
-                    guard.and_then(|guard| {
-                        guard.as_ref().map(|picker| {
-                            let files = picker.sync_data.files();
-                            let ptr = files.as_ptr();
-                            let len = files.len();
-                            let budget = Arc::clone(&picker.cache_budget);
-                            // SAFETY: see comment above — Vec is stable during this window.
-                            let static_files: &[FileItem] =
-                                unsafe { std::slice::from_raw_parts(ptr, len) };
-                            (static_files, budget)
-                        })
-                    })
-                } else {
-                    None
-                };
+            // The snapshot shares the file list with the picker, and content
+            // caches are shared by the chunk the watcher copies when it
+            // changes a file meanwhile, so the warmup benefits the live index.
+            let files_snapshot = if !cancelled.load(Ordering::Acquire) {
+                shared_picker
+                    .snapshot()
+                    .map(|snapshot| (snapshot.files(), Arc::clone(&snapshot.cache_budget)))
+            } else {
+                None
+            };
 
             if let Some((files, budget)) = files_snapshot {
                 // Warmup: populate mmap caches for top-frecency files.
                 if !cancelled.load(Ordering::Acquire) {
                     let warmup_start = std::time::Instant::now();
-                    warmup_mmaps(files, &budget);
+                    warmup_mmaps_in(pool, &files, &budget, priority.batch_pause);
                     info!(
                         "Warmup completed in {:.2}s (cached {} files, {} bytes)",
                         warmup_start.elapsed().as_secs_f64(),
@@ -1144,7 +2337,8 @@ fn spawn_scan_and_watcher(
                 if !cancelled.load(Ordering::Acquire) {
                     let bigram_start = std::time::Instant::now();
                     info!("Starting bigram index build for {} files...", files.len());
-                    let (index, content_binary) = build_bigram_index(files, &budget);
+                    let (index, content_binary) =
+                        build_bigram_index_in(pool, &files, &budget, priority.batch_pause);
                     info!(
                         "Bigram index ready in {:.2}s",
                         bigram_start.elapsed().as_secs_f64(),
@@ -1166,6 +2360,59 @@ fn spawn_scan_and_watcher(
                         )));
                     }
                 }
+
+                // TODO comments, tests and keywords are read from the content
+                // cache just warmed up.
+                if !cancelled.load(Ordering::Acquire) {
+                    let lines_start = std::time::Instant::now();
+                    let keyword_matcher = shared_picker
+                        .read()
+                        .ok()
+                        .and_then(|guard| guard.as_ref().and_then(|p| p.keyword_matcher.clone()));
+                    let (todos, tests, keywords) = pool.install(|| {
+                        let todos = TodoIndex::build(&files, &budget, scan_todos);
+                        let tests = TestIndex::build(&files, &budget, scan_tests);
+                        let keywords = keyword_matcher.map(|matcher| {
+                            KeywordIndex::build(&files, &budget, |c| matcher.scan(c))
+                        });
+                        (todos, tests, keywords)
+                    });
+                    info!(
+                        todos = todos.len(),
+                        tests = tests.len(),
+                        keywords = keywords.as_ref().map_or(0, |k| k.len()),
+                        "Line indexes ready in {:.2}s",
+                        lines_start.elapsed().as_secs_f64(),
+                    );
+
+                    // A search may have built them meanwhile, from newer files.
+                    if let Ok(guard) = shared_picker.read()
+                        && let Some(ref picker) = *guard
+                    {
+                        let mut indexes = picker.line_indexes.write();
+                        indexes.todos.get_or_insert(todos);
+                        indexes.tests.get_or_insert(tests);
+                        if let Some(keywords) = keywords {
+                            indexes.keywords.get_or_insert(keywords);
+                        }
+                    }
+                }
+
+                // Opt-in, persisted next to the frecency database.
+                let trigram_job = shared_picker
+                    .read()
+                    .ok()
+                    .and_then(|guard| guard.as_ref().and_then(FilePicker::trigram_index_job));
+                if let Some(job) = trigram_job
+                    && !cancelled.load(Ordering::Acquire)
+                {
+                    let index = job.run();
+                    if let Ok(mut guard) = shared_picker.write()
+                        && let Some(ref mut picker) = *guard
+                    {
+                        job.install(picker, index);
+                    }
+                }
             }
 
             info!(
@@ -1178,6 +2425,102 @@ fn spawn_scan_and_watcher(
     });
 }
 
+/// Index the path tokens used for "did you mean" suggestions. Built after the
+/// files are already searchable so the initial scan is not delayed by it.
+/// Relative path prefix, with a trailing separator, that files under
+/// `search_root` start with. `None` when the root is the project itself.
+fn search_root_prefix(search_root: &Path, project_path: Option<&Path>) -> Option<String> {
+    let relative = project_path
+        .and_then(|project| search_root.strip_prefix(project).ok())
+        .unwrap_or(search_root);
+    let relative = relative.to_string_lossy();
+    let relative = relative.trim_end_matches(std::path::MAIN_SEPARATOR);
+    (!relative.is_empty() && relative != ".")
+        .then(|| format!("{relative}{}", std::path::MAIN_SEPARATOR))
+}
+
+fn build_path_tokens(shared_picker: &SharedPicker) {
+    let started = std::time::Instant::now();
+    let index = match shared_picker.read() {
+        Ok(guard) => match guard.as_ref() {
+            Some(picker) => PathTokenIndex::build(picker.get_files()),
+            None => return,
+        },
+        Err(_) => return,
+    };
+    info!(
+        tokens = index.len(),
+        "Path token index built in {:?}",
+        started.elapsed()
+    );
+
+    if let Ok(mut guard) = shared_picker.write()
+        && let Some(ref mut picker) = *guard
+    {
+        picker.path_tokens = Some(Arc::new(index));
+    }
+}
+
+/// The TODO, test and keyword indexes, `None` until built.
+#[derive(Debug, Default)]
+struct LineIndexes {
+    todos: Option<TodoIndex>,
+    tests: Option<TestIndex>,
+    keywords: Option<KeywordIndex>,
+}
+
+impl LineIndexes {
+    fn remove_file(&mut self, path: &Path) {
+        if let Some(todos) = &mut self.todos {
+            todos.remove_file(path);
+        }
+        if let Some(tests) = &mut self.tests {
+            tests.remove_file(path);
+        }
+        if let Some(keywords) = &mut self.keywords {
+            keywords.remove_file(path);
+        }
+    }
+
+    fn remove_dir(&mut self, dir: &Path) {
+        if let Some(todos) = &mut self.todos {
+            todos.remove_dir(dir);
+        }
+        if let Some(tests) = &mut self.tests {
+            tests.remove_dir(dir);
+        }
+        if let Some(keywords) = &mut self.keywords {
+            keywords.remove_dir(dir);
+        }
+    }
+}
+
+/// Re-scan the lines of a file the watcher reported.
+fn update_line_indexes(
+    line_indexes: &parking_lot::RwLock<LineIndexes>,
+    keyword_matcher: Option<&KeywordMatcher>,
+    path: &Path,
+) {
+    let mut indexes = line_indexes.write();
+    if indexes.todos.is_none() && indexes.tests.is_none() && indexes.keywords.is_none() {
+        return;
+    }
+    let Ok(content) = std::fs::read(path) else {
+        indexes.remove_file(path);
+        return;
+    };
+
+    if let Some(todos) = &mut indexes.todos {
+        todos.update_file(path, &content, scan_todos);
+    }
+    if let Some(tests) = &mut indexes.tests {
+        tests.update_file(path, &content, scan_tests);
+    }
+    if let (Some(keywords), Some(matcher)) = (&mut indexes.keywords, keyword_matcher) {
+        keywords.update_file(path, &content, |c| matcher.scan(c));
+    }
+}
+
 /// Pre-populate mmap caches for the most valuable files so the first grep
 /// search doesn't pay the mmap creation + page fault cost.
 ///
@@ -1188,8 +2531,52 @@ fn spawn_scan_and_watcher(
 ///
 /// Files beyond the budget are still available via temporary mmaps on first
 /// grep access, so correctness is unaffected.
-#[tracing::instrument(skip(files), name = "warmup_mmaps", level = Level::DEBUG)]
-pub fn warmup_mmaps(files: &[FileItem], budget: &ContentCacheBudget) {
+pub fn warmup_mmaps(files: &FileList, budget: &ContentCacheBudget) {
+    warmup_mmaps_in(&BACKGROUND_THREAD_POOL, files, budget, Duration::ZERO);
+}
+
+/// Files handled between two rests of [`BackgroundPriority::batch_pause`].
+const BACKGROUND_BATCH_SIZE: usize = 1024;
+
+/// `items.par_iter().enumerate().for_each(op)` on `pool`, resting `pause`
+/// between batches of [`BACKGROUND_BATCH_SIZE`] when it is not zero.
+fn for_each_in_batches<T: Sync>(
+    pool: &rayon::ThreadPool,
+    items: &[T],
+    pause: Duration,
+    op: impl Fn(usize, &T) + Sync,
+) {
+    if pause.is_zero() {
+        pool.install(|| {
+            items
+                .par_iter()
+                .enumerate()
+                .for_each(|(i, item)| op(i, item))
+        });
+        return;
+    }
+
+    for (batch, chunk) in items.chunks(BACKGROUND_BATCH_SIZE).enumerate() {
+        if batch > 0 {
+            std::thread::sleep(pause);
+        }
+        let offset = batch * BACKGROUND_BATCH_SIZE;
+        pool.install(|| {
+            chunk
+                .par_iter()
+                .enumerate()
+                .for_each(|(i, item)| op(offset + i, item))
+        });
+    }
+}
+
+#[tracing::instrument(skip(pool, files), name = "warmup_mmaps", level = Level::DEBUG)]
+fn warmup_mmaps_in(
+    pool: &rayon::ThreadPool,
+    files: &FileList,
+    budget: &ContentCacheBudget,
+    pause: Duration,
+) {
     let max_files = budget.max_files;
     let max_bytes = budget.max_bytes;
     let max_file_size = budget.max_file_size;
@@ -1219,27 +2606,25 @@ pub fn warmup_mmaps(files: &[FileItem], budget: &ContentCacheBudget) {
     let warmed_bytes = AtomicU64::new(0);
     let budget_exhausted = AtomicBool::new(false);
 
-    BACKGROUND_THREAD_POOL.install(|| {
-        to_warm.par_iter().for_each(|file| {
-            if budget_exhausted.load(Ordering::Relaxed) {
-                return;
-            }
+    for_each_in_batches(pool, to_warm, pause, |_, file| {
+        if budget_exhausted.load(Ordering::Relaxed) {
+            return;
+        }
 
-            if file.is_binary || file.size == 0 || file.size > max_file_size {
-                return;
-            }
+        if file.is_binary || file.size == 0 || file.size > max_file_size {
+            return;
+        }
 
-            // Byte budget.
-            let prev_bytes = warmed_bytes.fetch_add(file.size, Ordering::Relaxed);
-            if prev_bytes + file.size > max_bytes {
-                budget_exhausted.store(true, Ordering::Relaxed);
-                return;
-            }
+        // Byte budget.
+        let prev_bytes = warmed_bytes.fetch_add(file.size, Ordering::Relaxed);
+        if prev_bytes + file.size > max_bytes {
+            budget_exhausted.store(true, Ordering::Relaxed);
+            return;
+        }
 
-            if let Some(content) = file.get_content(budget) {
-                let _ = std::hint::black_box(content.first());
-            }
-        });
+        if let Some(content) = file.get_content(budget) {
+            let _ = std::hint::black_box(content.first());
+        }
     });
 }
 
@@ -1249,8 +2634,17 @@ pub fn warmup_mmaps(files: &[FileItem], budget: &ContentCacheBudget) {
 pub const BIGRAM_CONTENT_CAP: usize = 64 * 1024;
 
 pub fn build_bigram_index(
-    files: &[FileItem],
+    files: &FileList,
+    budget: &ContentCacheBudget,
+) -> (BigramFilter, Vec<usize>) {
+    build_bigram_index_in(&BACKGROUND_THREAD_POOL, files, budget, Duration::ZERO)
+}
+
+fn build_bigram_index_in(
+    pool: &rayon::ThreadPool,
+    files: &FileList,
     budget: &ContentCacheBudget,
+    pause: Duration,
 ) -> (BigramFilter, Vec<usize>) {
     let start = std::time::Instant::now();
     info!("Building bigram index for {} files...", files.len());
@@ -1263,37 +2657,36 @@ pub fn build_bigram_index(
     // on the real file list after the build, so grep never has to re-check.
     let content_binary: std::sync::Mutex<Vec<usize>> = std::sync::Mutex::new(Vec::new());
 
-    BACKGROUND_THREAD_POOL.install(|| {
-        files.par_iter().enumerate().for_each(|(i, file)| {
-            if file.is_binary || file.size == 0 || file.size > max_file_size {
+    let files: Vec<&FileItem> = files.iter().collect();
+    for_each_in_batches(pool, &files, pause, |i, file| {
+        if file.is_binary || file.size == 0 || file.size > max_file_size {
+            return;
+        }
+        // Use cached content if available (no extra memory).
+        // For uncached files, read from disk — heap memory is freed on drop.
+        let data: Option<&[u8]>;
+        let owned;
+        if let Some(cached) = file.get_content(budget) {
+            if detect_binary_content(cached) {
+                content_binary.lock().unwrap().push(i);
                 return;
             }
-            // Use cached content if available (no extra memory).
-            // For uncached files, read from disk — heap memory is freed on drop.
-            let data: Option<&[u8]>;
-            let owned;
-            if let Some(cached) = file.get_content(budget) {
-                if detect_binary_content(cached) {
-                    content_binary.lock().unwrap().push(i);
-                    return;
-                }
-                data = Some(cached);
-                owned = None;
-            } else if let Ok(read_data) = std::fs::read(&file.path) {
-                if detect_binary_content(&read_data) {
-                    content_binary.lock().unwrap().push(i);
-                    return;
-                }
-                data = None;
-                owned = Some(read_data);
-            } else {
+            data = Some(cached);
+            owned = None;
+        } else if let Ok(read_data) = std::fs::read(file.path()) {
+            if detect_binary_content(&read_data) {
+                content_binary.lock().unwrap().push(i);
                 return;
             }
+            data = None;
+            owned = Some(read_data);
+        } else {
+            return;
+        }
 
-            let content = data.unwrap_or_else(|| owned.as_ref().unwrap());
-            let capped = &content[..content.len().min(BIGRAM_CONTENT_CAP)];
-            builder.add_file_content(&skip_builder, i, capped);
-        });
+        let content = data.unwrap_or_else(|| owned.as_ref().unwrap());
+        let capped = &content[..content.len().min(BIGRAM_CONTENT_CAP)];
+        builder.add_file_content(&skip_builder, i, capped);
     });
 
     let cols = builder.columns_used();
@@ -1333,9 +2726,7 @@ pub fn build_bigram_index(
 pub fn scan_files(base_path: &Path) -> Vec<FileItem> {
     use ignore::{WalkBuilder, WalkState};
 
-    let git_workdir = Repository::discover(base_path)
-        .ok()
-        .and_then(|repo| repo.workdir().map(Path::to_path_buf));
+    let git_workdir = GitRepoLocation::discover(base_path).map(|repo| repo.workdir);
     let is_git_repo = git_workdir.is_some();
 
     let mut walk_builder = WalkBuilder::new(base_path);
@@ -1344,8 +2735,8 @@ pub fn scan_files(base_path: &Path) -> Vec<FileItem> {
         .git_ignore(true)
         .git_exclude(true)
         .git_global(true)
-        .ignore(true)
         .follow_links(false);
+    apply_ignore_files(&mut walk_builder, true);
 
     if !is_git_repo && let Some(overrides) = non_git_repo_overrides(base_path) {
         walk_builder.overrides(overrides);
@@ -1389,10 +2780,96 @@ pub fn scan_files(base_path: &Path) -> Vec<FileItem> {
     });
 
     let mut files = files.into_inner();
-    files.sort_unstable_by(|a, b| a.path.as_os_str().cmp(b.path.as_os_str()));
+    files.sort_unstable_by(|a, b| a.path().as_os_str().cmp(b.path().as_os_str()));
     files
 }
 
+/// Parameters of a full rescan taken from the picker, see [`FilePicker::rescan_job`].
+pub(crate) struct RescanJob {
+    base_path: PathBuf,
+    scanned_files_count: Arc<AtomicUsize>,
+    mode: FFFMode,
+    respect_ignore_files: bool,
+    same_file_system: bool,
+    git_enabled: bool,
+    git_status_provider: GitStatusProvider,
+    scan_limits: ScanLimits,
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl RescanJob {
+    /// Walk the filesystem and apply git status and frecency to the found
+    /// files. Git status is applied synchronously for rescans (typically fast).
+    pub(crate) fn run(&self, shared_frecency: &SharedFrecency) -> Result<FileSync, Error> {
+        self.scanned_files_count.store(0, Ordering::Relaxed);
+        let pool = background_pool(self.thread_pool.as_ref());
+        let walk = walk_filesystem(
+            &self.base_path,
+            None,
+            &self.scanned_files_count,
+            shared_frecency,
+            self.mode,
+            self.respect_ignore_files,
+            self.same_file_system,
+            self.git_enabled,
+            self.git_status_provider,
+            self.scan_limits,
+            pool,
+        )?;
+
+        let mut sync = walk.sync;
+        if let Ok(Some(git_cache)) = walk.git_handle.join() {
+            let frecency = shared_frecency.read().ok();
+            let frecency_ref = frecency.as_ref().and_then(|f| f.as_ref());
+            let mode = self.mode;
+            pool.install(|| {
+                // not shared yet, so this does not copy the list
+                sync.files_mut().par_iter_mut().for_each(|file| {
+                    file.git_status = git_cache.lookup_status(file.path());
+                    if let Some(frecency) = frecency_ref {
+                        let _ = file.update_frecency_scores(frecency, mode);
+                    }
+                });
+            });
+        }
+
+        Ok(sync)
+    }
+}
+
+/// Parameters of a trigram index build taken from the picker, see
+/// [`FilePicker::trigram_index_job`]. Runs without holding the picker lock.
+pub(crate) struct TrigramIndexJob {
+    dir: PathBuf,
+    base_path: PathBuf,
+    files: FileList,
+    budget: Arc<ContentCacheBudget>,
+    scan_signal: Arc<AtomicBool>,
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl TrigramIndexJob {
+    pub(crate) fn run(&self) -> TrigramIndex {
+        let started = std::time::Instant::now();
+        let index = background_pool(self.thread_pool.as_ref()).install(|| {
+            trigram_index::load_or_build(&self.dir, &self.base_path, &self.files, &self.budget)
+        });
+        info!(
+            files = index.len(),
+            "Trigram index ready in {:.2}s",
+            started.elapsed().as_secs_f64(),
+        );
+        index
+    }
+
+    /// Install the index unless the picker was replaced meanwhile.
+    pub(crate) fn install(&self, picker: &mut FilePicker, index: TrigramIndex) {
+        if Arc::ptr_eq(&picker.is_scanning, &self.scan_signal) {
+            picker.trigram_index = Some(Arc::new(index));
+        }
+    }
+}
+
 /// Result of the fast walk phase — files are searchable immediately,
 /// git status arrives later via the join handle.
 struct WalkResult {
@@ -1404,11 +2881,20 @@ struct WalkResult {
 /// Returns files immediately (searchable) and a handle to the in-progress
 /// git status computation. This avoids blocking on `git status` which can
 /// take 10+ seconds on very large repos (e.g. chromium).
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(name = "scan", skip_all, fields(base_path = %base_path.display()))]
 fn walk_filesystem(
     base_path: &Path,
+    key_alias: Option<&Path>,
     synced_files_count: &Arc<AtomicUsize>,
     shared_frecency: &SharedFrecency,
     mode: FFFMode,
+    respect_ignore_files: bool,
+    same_file_system: bool,
+    git_enabled: bool,
+    git_status_provider: GitStatusProvider,
+    limits: ScanLimits,
+    pool: &rayon::ThreadPool,
 ) -> Result<WalkResult, Error> {
     use ignore::{WalkBuilder, WalkState};
 
@@ -1416,31 +2902,40 @@ fn walk_filesystem(
     info!("SCAN: Starting filesystem walk and git status (async)");
 
     // Discover git root (fast — just walks up looking for .git/)
-    let git_workdir = Repository::discover(base_path)
-        .ok()
-        .and_then(|repo| repo.workdir().map(Path::to_path_buf));
+    let git_repo = git_enabled
+        .then(|| GitRepoLocation::discover(base_path))
+        .flatten();
 
-    if let Some(ref git_dir) = git_workdir {
-        debug!("Git repository found at: {}", git_dir.display());
+    if !git_enabled {
+        debug!("Git integration disabled, scanning as a non-git directory");
+    } else if let Some(ref repo) = git_repo {
+        debug!(
+            "Git repository found at: {} (worktree: {})",
+            repo.workdir.display(),
+            repo.is_worktree
+        );
     } else {
         debug!("No git repository found for path: {}", base_path.display());
     }
+    let git_workdir = git_repo.map(|repo| repo.workdir);
 
     // Spawn git status on a detached thread — we won't wait for it here.
     let git_workdir_for_status = git_workdir.clone();
     let git_handle = std::thread::spawn(move || {
         GitStatusCache::read_git_status(
             git_workdir_for_status.as_deref(),
-            StatusOptions::new()
-                .include_untracked(true)
-                .recurse_untracked_dirs(true)
-                .exclude_submodules(true),
+            StatusQuery {
+                include_untracked: true,
+                exclude_submodules: true,
+                ..Default::default()
+            },
+            git_status_provider,
         )
     });
 
     // Walk files (the fast part, typically 2-3s even on huge repos).
     let is_git_repo = git_workdir.is_some();
-    let bg_threads = BACKGROUND_THREAD_POOL.current_num_threads();
+    let bg_threads = pool.current_num_threads();
     let mut walk_builder = WalkBuilder::new(base_path);
     walk_builder
         // this is a very important guard for the user opening ~/ or other root non-git dir
@@ -1448,9 +2943,11 @@ fn walk_filesystem(
         .git_ignore(true)
         .git_exclude(true)
         .git_global(true)
-        .ignore(true)
         .follow_links(false)
+        .same_file_system(same_file_system)
+        .max_depth(limits.max_depth)
         .threads(bg_threads);
+    apply_ignore_files(&mut walk_builder, respect_ignore_files);
 
     if !is_git_repo && let Some(overrides) = non_git_repo_overrides(base_path) {
         walk_builder.overrides(overrides);
@@ -1462,8 +2959,12 @@ fn walk_filesystem(
     debug!("SCAN: Starting file walker");
 
     let files = parking_lot::Mutex::new(Vec::new());
+    let hardlinks = parking_lot::Mutex::new(Vec::new());
+    let truncated = AtomicBool::new(false);
     walker.run(|| {
         let files = &files;
+        let hardlinks = &hardlinks;
+        let truncated = &truncated;
         let counter = Arc::clone(synced_files_count);
         let base_path = base_path.to_path_buf();
 
@@ -1487,6 +2988,9 @@ fn walk_filesystem(
                 }
 
                 let metadata = entry.metadata().ok();
+                if let Some(inode) = metadata.as_ref().and_then(hardlinked_inode) {
+                    hardlinks.lock().push((inode, path.to_path_buf()));
+                }
                 let file_item = FileItem::new_with_metadata(
                     path.to_path_buf(),
                     &base_path,
@@ -1494,7 +2998,12 @@ fn walk_filesystem(
                     metadata.as_ref(),
                 );
 
-                files.lock().push(file_item);
+                let mut files = files.lock();
+                if limits.max_files.is_some_and(|max| files.len() >= max) {
+                    truncated.store(true, Ordering::Relaxed);
+                    return WalkState::Quit;
+                }
+                files.push(file_item);
                 counter.fetch_add(1, Ordering::Relaxed);
             }
             WalkState::Continue
@@ -1507,13 +3016,22 @@ fn walk_filesystem(
         walker_start.elapsed(),
         files.len(),
     );
+    drop_hardlinked_duplicates(&mut files, hardlinks.into_inner());
 
     // Apply frecency scores (access-based only — git status not yet available).
     let frecency = shared_frecency
         .read()
         .map_err(|_| Error::AcquireFrecencyLock)?;
     if let Some(frecency) = frecency.as_ref() {
-        BACKGROUND_THREAD_POOL.install(|| {
+        if let Some(alias) = key_alias {
+            let keys = files
+                .iter()
+                .map(|file| (alias.join(file.relative_path()), file.path()));
+            if let Err(e) = frecency.migrate_keys(keys) {
+                warn!("Failed to migrate frecency keys: {e}");
+            }
+        }
+        pool.install(|| {
             files.par_iter_mut().for_each(|file| {
                 let _ = file.update_frecency_scores(frecency, mode);
             });
@@ -1521,8 +3039,8 @@ fn walk_filesystem(
     }
     drop(frecency);
 
-    BACKGROUND_THREAD_POOL.install(|| {
-        files.par_sort_unstable_by(|a, b| a.path.as_os_str().cmp(b.path.as_os_str()));
+    pool.install(|| {
+        files.par_sort_unstable_by(|a, b| a.path().as_os_str().cmp(b.path().as_os_str()));
     });
 
     let total_time = scan_start.elapsed();
@@ -1531,9 +3049,12 @@ fn walk_filesystem(
     let base_count = files.len();
     Ok(WalkResult {
         sync: FileSync {
-            files,
+            files: files.into(),
             base_count,
             git_workdir,
+            truncated: truncated.into_inner(),
+            ignored: Arc::new(IgnoredFiles::new(same_file_system, limits)),
+            generation: next_index_generation(),
         },
         git_handle,
     })
@@ -1546,6 +3067,7 @@ fn apply_git_status(
     shared_frecency: &SharedFrecency,
     git_handle: std::thread::JoinHandle<Option<GitStatusCache>>,
     mode: FFFMode,
+    pool: &rayon::ThreadPool,
 ) {
     let join_start = std::time::Instant::now();
     let git_cache = match git_handle.join() {
@@ -1559,19 +3081,23 @@ fn apply_git_status(
 
     let Some(git_cache) = git_cache else { return };
 
-    if let Ok(mut guard) = shared_picker.write()
+    let applied = if let Ok(mut guard) = shared_picker.write()
         && let Some(ref mut picker) = *guard
     {
         let frecency = shared_frecency.read().ok();
         let frecency_ref = frecency.as_ref().and_then(|f| f.as_ref());
 
-        BACKGROUND_THREAD_POOL.install(|| {
-            picker.sync_data.files.par_iter_mut().for_each(|file| {
-                file.git_status = git_cache.lookup_status(&file.path);
-                if let Some(frecency) = frecency_ref {
-                    let _ = file.update_frecency_scores(frecency, mode);
-                }
-            });
+        pool.install(|| {
+            picker
+                .sync_data
+                .files_mut()
+                .par_iter_mut()
+                .for_each(|file| {
+                    file.git_status = git_cache.lookup_status(file.path());
+                    if let Some(frecency) = frecency_ref {
+                        let _ = file.update_frecency_scores(frecency, mode);
+                    }
+                });
         });
 
         info!(
@@ -1579,7 +3105,55 @@ fn apply_git_status(
             picker.sync_data.files.len(),
             git_cache.statuses_len(),
         );
+        true
+    } else {
+        false
+    };
+    if applied {
+        shared_picker.emit(IndexEvent::GitStatusRefreshed);
+    }
+}
+
+/// `(device, inode)` of a file with more than one hard link. Symlinks are
+/// not followed by the walker, so hard links are how the same file shows up
+/// under two paths, e.g. in workspaces linked together by a package manager.
+#[cfg(unix)]
+fn hardlinked_inode(metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn hardlinked_inode(_: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Keep one entry per file linked under several paths, the one with the
+/// shortest relative path, so its frecency isn't split between them.
+fn drop_hardlinked_duplicates(
+    files: &mut Vec<FileItem>,
+    mut hardlinks: Vec<((u64, u64), PathBuf)>,
+) {
+    if hardlinks.len() < 2 {
+        return;
     }
+
+    hardlinks.sort_unstable_by(|(a_inode, a), (b_inode, b)| {
+        a_inode
+            .cmp(b_inode)
+            .then_with(|| a.as_os_str().len().cmp(&b.as_os_str().len()))
+            .then_with(|| a.cmp(b))
+    });
+    let duplicates: AHashSet<PathBuf> = hardlinks
+        .chunk_by(|(a, _), (b, _)| a == b)
+        .flat_map(|links| links[1..].iter().map(|(_, path)| path.clone()))
+        .collect();
+    if duplicates.is_empty() {
+        return;
+    }
+
+    files.retain(|file| !duplicates.contains(file.path()));
+    debug!("SCAN: Dropped {} hard linked duplicates", duplicates.len());
 }
 
 #[inline]
@@ -1657,3 +3231,496 @@ fn hint_allocator_collect() {
         unsafe { libmimalloc_sys::mi_collect(true) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{GitStatusProvider, StatusQuery};
+    use crate::grep::{GrepMode, parse_grep_query};
+    use crate::shared::{SharedFrecency, SharedPicker};
+    use crate::test_fixtures::{
+        grep_options, indexed_paths, initial_commit, picker_options, scan, wait_until, write_files,
+    };
+    use fff_query_parser::QueryParser;
+    use git2::Status;
+    use std::fs;
+    use std::sync::atomic::AtomicUsize;
+    use tempfile::TempDir;
+
+    /// A picker scanning and warming up `base` in the background.
+    fn start_picker(base: &Path, background_priority: BackgroundPriority) -> SharedPicker {
+        let shared_picker = SharedPicker::default();
+        FilePicker::new_with_shared_state(
+            shared_picker.clone(),
+            SharedFrecency::default(),
+            FilePickerOptions {
+                warmup_mmap_cache: true,
+                background_priority,
+                ..picker_options(base)
+            },
+        )
+        .expect("Failed to create FilePicker");
+        shared_picker
+    }
+
+    #[test]
+    fn searches_run_on_the_picker_pool() {
+        let tmp = TempDir::new().unwrap();
+        for i in 0..20 {
+            fs::write(
+                tmp.path().join(format!("file_{i}.rs")),
+                format!("fn item_{i}() {{}}\n"),
+            )
+            .unwrap();
+        }
+
+        let shared = scan(picker_options(tmp.path()));
+        assert!(shared.thread_pool().is_none());
+        assert_eq!(shared.install(rayon::current_thread_index), None);
+
+        let isolated = scan(FilePickerOptions {
+            thread_pool_size: Some(2),
+            ..picker_options(tmp.path())
+        });
+        assert_eq!(isolated.thread_pool().unwrap().current_num_threads(), 2);
+        assert_eq!(
+            isolated.install(rayon::current_num_threads),
+            2,
+            "closures run inside the picker pool"
+        );
+        assert!(isolated.install(rayon::current_thread_index).is_some());
+
+        let query = QueryParser::default().parse("file_7");
+        let results = isolated.install(|| {
+            FilePicker::fuzzy_search(
+                isolated.get_files(),
+                &query,
+                None,
+                FuzzySearchOptions {
+                    max_threads: 2,
+                    project_path: Some(isolated.base_path()),
+                    pagination: PaginationArgs {
+                        offset: 0,
+                        limit: 10,
+                    },
+                    ..Default::default()
+                },
+            )
+        });
+        assert_eq!(results.items[0].relative_path(), "file_7.rs");
+
+        let grep = isolated.grep(
+            &parse_grep_query("item_13"),
+            &grep_options(GrepMode::PlainText),
+        );
+        assert_eq!(grep.matches.len(), 1);
+    }
+
+    #[test]
+    fn throttled_warmup_indexes_every_batch() {
+        let tmp = TempDir::new().unwrap();
+        // more than one batch, so the index offsets of later batches are checked
+        for i in 0..1500 {
+            fs::write(
+                tmp.path().join(format!("file_{i:04}.txt")),
+                format!("content of file {i}\n"),
+            )
+            .unwrap();
+        }
+        fs::write(
+            tmp.path().join("zzz_last.txt"),
+            "needle_in_the_last_batch\n",
+        )
+        .unwrap();
+
+        let shared_picker = start_picker(
+            tmp.path(),
+            BackgroundPriority {
+                max_parallelism: Some(1),
+                batch_pause: Duration::from_millis(1),
+                skip_warmup: None,
+            },
+        );
+        wait_until("the bigram index", || {
+            shared_picker
+                .read()
+                .unwrap()
+                .as_ref()
+                .is_some_and(|picker| picker.bigram_index().is_some())
+        });
+
+        let guard = shared_picker.read().unwrap();
+        let picker = guard.as_ref().unwrap();
+        assert_eq!(picker.get_files().len(), 1501);
+        let result = picker.grep(
+            &parse_grep_query("needle_in_the_last_batch"),
+            &grep_options(GrepMode::PlainText),
+        );
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!(
+            result.files[result.matches[0].file_index].relative_path(),
+            "zzz_last.txt"
+        );
+    }
+
+    #[test]
+    fn skip_hook_disables_warmup() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(tmp.path().join("alpha.txt"), "hello\n").unwrap();
+
+        let asked = Arc::new(AtomicUsize::new(0));
+        let shared_picker = start_picker(
+            tmp.path(),
+            BackgroundPriority {
+                skip_warmup: Some(Arc::new({
+                    let asked = Arc::clone(&asked);
+                    move || {
+                        asked.fetch_add(1, Ordering::Relaxed);
+                        true
+                    }
+                })),
+                ..Default::default()
+            },
+        );
+        wait_until("the skip hook", || asked.load(Ordering::Relaxed) > 0);
+        std::thread::sleep(Duration::from_millis(200));
+
+        let guard = shared_picker.read().unwrap();
+        let picker = guard.as_ref().unwrap();
+        assert_eq!(picker.get_files().len(), 1);
+        assert!(picker.bigram_index().is_none());
+    }
+
+    fn scan_with_limits(tmp: &TempDir, scan_limits: ScanLimits) -> FilePicker {
+        scan(FilePickerOptions {
+            scan_limits,
+            ..picker_options(tmp.path())
+        })
+    }
+
+    fn nested_files() -> TempDir {
+        let tmp = TempDir::new().unwrap();
+        write_files(
+            tmp.path(),
+            &[
+                ("top.txt", ""),
+                ("a/one.txt", ""),
+                ("a/b/two.txt", ""),
+                ("a/b/c/three.txt", ""),
+            ],
+        );
+        tmp
+    }
+
+    #[test]
+    fn max_depth_skips_deeper_files() {
+        let tmp = nested_files();
+        let picker = scan_with_limits(
+            &tmp,
+            ScanLimits {
+                max_depth: Some(2),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            indexed_paths(picker.get_files()),
+            vec!["a/one.txt", "top.txt"]
+        );
+        assert!(!picker.is_scan_truncated());
+    }
+
+    #[test]
+    fn max_files_truncates_the_scan() {
+        let tmp = nested_files();
+        assert!(!scan_with_limits(&tmp, ScanLimits::default()).is_scan_truncated());
+
+        let picker = scan_with_limits(
+            &tmp,
+            ScanLimits {
+                max_files: Some(2),
+                ..Default::default()
+            },
+        );
+        assert_eq!(picker.get_files().len(), 2);
+        assert!(picker.is_scan_truncated());
+        assert!(picker.get_scan_progress().is_truncated);
+    }
+
+    #[test]
+    fn disabled_git_skips_discovery_and_status() {
+        let tmp = TempDir::new().unwrap();
+        let base = fs::canonicalize(tmp.path()).unwrap();
+        git2::Repository::init(&base).unwrap();
+        fs::write(base.join("untracked.txt"), "").unwrap();
+
+        let picker = scan(picker_options(&base));
+        assert!(picker.git_root().is_some());
+        assert!(picker.get_files()[0].git_status.is_some());
+
+        let picker = scan(FilePickerOptions {
+            git_enabled: false,
+            ..picker_options(&base)
+        });
+        assert!(!picker.git_enabled());
+        assert_eq!(picker.git_root(), None);
+        assert_eq!(picker.get_files().len(), 1);
+        assert_eq!(picker.get_files()[0].git_status, None);
+        assert!(!picker.snapshot().git_enabled());
+    }
+
+    #[test]
+    fn refresh_applies_only_changed_statuses() {
+        const FULL_REFRESH: StatusQuery = StatusQuery {
+            include_untracked: true,
+            include_unmodified: true,
+            exclude_submodules: true,
+        };
+        let full_status = |root: &Path| {
+            GitStatusCache::read_git_status(Some(root), FULL_REFRESH, GitStatusProvider::Library)
+                .unwrap()
+        };
+
+        let tmp = TempDir::new().unwrap();
+        let root = fs::canonicalize(tmp.path()).unwrap();
+        write_files(
+            &root,
+            &[("a.rs", "a.rs"), ("b.rs", "b.rs"), ("c.rs", "c.rs")],
+        );
+        initial_commit(&git2::Repository::init(&root).unwrap());
+
+        let picker = scan(picker_options(&root));
+
+        let mut unchanged = full_status(&root);
+        assert_eq!(unchanged.statuses_len(), 3);
+        picker.retain_changed_git_statuses(&mut unchanged);
+        assert_eq!(unchanged.statuses_len(), 0);
+
+        fs::write(root.join("b.rs"), "changed").unwrap();
+        let mut changed = full_status(&root);
+        picker.retain_changed_git_statuses(&mut changed);
+        assert_eq!(
+            changed.into_iter().collect::<Vec<_>>(),
+            vec![(root.join("b.rs"), Status::WT_MODIFIED)]
+        );
+
+        let shared_picker = SharedPicker::default();
+        *shared_picker.write().unwrap() = Some(picker);
+        let count = shared_picker
+            .refresh_git_status(&SharedFrecency::default())
+            .unwrap();
+        assert_eq!(count, 3);
+
+        let guard = shared_picker.read().unwrap();
+        let picker = guard.as_ref().unwrap();
+        let status = |name: &str| picker.get_file_by_path(root.join(name)).unwrap().git_status;
+        assert_eq!(status("a.rs"), None);
+        assert_eq!(status("b.rs"), Some(Status::WT_MODIFIED));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_linked_files_are_indexed_once() {
+        let tmp = TempDir::new().unwrap();
+        let root = fs::canonicalize(tmp.path()).unwrap();
+        write_files(
+            &root,
+            &[
+                ("shared/index.js", "export {}"),
+                ("packages/app/main.js", "import 'shared'"),
+            ],
+        );
+        fs::create_dir_all(root.join("packages/app/vendor/shared")).unwrap();
+        fs::hard_link(
+            root.join("shared/index.js"),
+            root.join("packages/app/vendor/shared/index.js"),
+        )
+        .unwrap();
+
+        let picker = scan(picker_options(&root));
+        assert_eq!(
+            indexed_paths(picker.get_files()),
+            ["packages/app/main.js", "shared/index.js"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn frecency_of_a_symlinked_project_is_migrated() {
+        let tmp = TempDir::new().unwrap();
+        let db_dir = TempDir::new().unwrap();
+        let root = fs::canonicalize(tmp.path()).unwrap();
+        write_files(
+            &root,
+            &[
+                ("project/src/main.rs", "fn main() {}"),
+                ("project/src/lib.rs", ""),
+            ],
+        );
+
+        let shared_frecency = SharedFrecency::default();
+        shared_frecency
+            .init(FrecencyTracker::new(db_dir.path().join("frecency"), true).unwrap())
+            .unwrap();
+        {
+            // Tracked before the symlink exists, so the key stays lexical, as it
+            // would for a database written before the project moved.
+            let frecency = shared_frecency.read().unwrap();
+            let frecency = frecency.as_ref().unwrap();
+            frecency
+                .track_access(&root.join("alias/src/main.rs"))
+                .unwrap();
+            frecency.flush().unwrap();
+        }
+        std::os::unix::fs::symlink(root.join("project"), root.join("alias")).unwrap();
+
+        let shared_picker = SharedPicker::default();
+        FilePicker::new_with_shared_state(
+            shared_picker.clone(),
+            shared_frecency.clone(),
+            picker_options(&root.join("alias")),
+        )
+        .unwrap();
+        wait_until("the scan", || {
+            shared_picker
+                .read()
+                .unwrap()
+                .as_ref()
+                .is_some_and(|picker| !picker.is_scan_active())
+        });
+
+        let guard = shared_picker.read().unwrap();
+        let picker = guard.as_ref().unwrap();
+        assert_eq!(picker.base_path(), root.join("project"));
+        let main = picker
+            .get_file_by_path(normalize_key(&root.join("alias/src/../src/main.rs")))
+            .expect("indexed under the normalized path");
+        assert!(main.access_frecency_score > 0);
+        drop(guard);
+
+        // Without the symlink the lookup can't resolve to the new key.
+        fs::remove_file(root.join("alias")).unwrap();
+        let frecency = shared_frecency.read().unwrap();
+        let frecency = frecency.as_ref().unwrap();
+        assert!(
+            frecency
+                .seconds_since_last_access(&root.join("alias/src/main.rs"))
+                .unwrap()
+                .is_none(),
+            "the old key is removed"
+        );
+    }
+
+    #[test]
+    fn external_scores_are_added_and_replaced() {
+        let tmp = TempDir::new().unwrap();
+        write_files(
+            tmp.path(),
+            &[
+                ("src/parser.rs", ""),
+                ("src/printer.rs", ""),
+                ("src/planner.rs", ""),
+            ],
+        );
+        let mut picker = scan(picker_options(tmp.path()));
+
+        let top = |picker: &FilePicker, query: &str| {
+            let parsed = QueryParser::default().parse(query);
+            let result = FilePicker::fuzzy_search(
+                picker.get_files(),
+                &parsed,
+                None,
+                FuzzySearchOptions {
+                    max_threads: 1,
+                    ..Default::default()
+                },
+            );
+            let score = result.scores[0].clone();
+            (result.items[0].relative_path().to_string(), score)
+        };
+
+        let printer = tmp.path().join("src/printer.rs");
+        let matched = picker
+            .set_external_scores([(printer.as_path(), 500), (Path::new("src/missing.rs"), 500)]);
+        assert_eq!(matched, 1);
+
+        for query in ["", "src/p"] {
+            let (path, score) = top(&picker, query);
+            assert_eq!(path, "src/printer.rs", "query {query:?}");
+            assert_eq!(score.external_boost, 500);
+        }
+
+        // relative paths work too, and the previous scores are dropped
+        assert_eq!(picker.set_external_scores([("src/planner.rs", 500)]), 1);
+        let (path, _) = top(&picker, "");
+        assert_eq!(path, "src/planner.rs");
+        let printer = picker
+            .get_files()
+            .iter()
+            .find(|file| file.relative_path() == "src/printer.rs")
+            .unwrap();
+        assert_eq!(printer.external_score, 0);
+    }
+
+    fn fuzzy_paths(picker: &FilePicker, query: &str) -> Vec<String> {
+        let mut parsed = QueryParser::default().parse(query);
+        picker.exclude_runtime_globs(&mut parsed);
+        let result = FilePicker::fuzzy_search(
+            picker.get_files(),
+            &parsed,
+            None,
+            FuzzySearchOptions {
+                max_threads: 1,
+                ..Default::default()
+            },
+        );
+        let mut paths: Vec<_> = result
+            .items
+            .iter()
+            .map(|file| file.relative_path().to_string())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    fn grep_paths(picker: &FilePicker, query: &str) -> Vec<String> {
+        let mut parsed = parse_grep_query(query);
+        picker.exclude_runtime_globs(&mut parsed);
+        let result = picker.grep(&parsed, &grep_options(GrepMode::PlainText));
+        let mut paths: Vec<_> = result
+            .files
+            .iter()
+            .map(|file| file.relative_path().to_string())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn runtime_excludes_hide_files_until_cleared() {
+        let tmp = TempDir::new().unwrap();
+        write_files(
+            tmp.path(),
+            &[
+                ("src/parser.rs", "fn parse() {}"),
+                ("src/parser_test.rs", "fn parse() {}"),
+                ("tests/parser.rs", "fn parse() {}"),
+            ],
+        );
+        let mut picker = scan(picker_options(tmp.path()));
+
+        let all = vec!["src/parser.rs", "src/parser_test.rs", "tests/parser.rs"];
+        assert_eq!(fuzzy_paths(&picker, "parser"), all);
+        assert_eq!(grep_paths(&picker, "fn parse"), all);
+
+        picker.set_runtime_excludes(vec!["tests/**".into(), "**/*_test.rs".into()]);
+        assert_eq!(fuzzy_paths(&picker, "parser"), vec!["src/parser.rs"]);
+        assert_eq!(fuzzy_paths(&picker, ""), vec!["src/parser.rs"]);
+        assert_eq!(grep_paths(&picker, "fn parse"), vec!["src/parser.rs"]);
+        // excludes stack with the constraints of the query
+        assert!(fuzzy_paths(&picker, "tests/ parser").is_empty());
+
+        picker.set_runtime_excludes(Vec::new());
+        assert_eq!(fuzzy_paths(&picker, "parser"), all);
+    }
+}
@@ -1,30 +1,415 @@
-use crate::db_healthcheck::DbHealthChecker;
+use crate::db_healthcheck::{self, DbHealth, DbHealthChecker, DbRepair};
 use crate::error::{Error, Result};
 use crate::file_picker::FFFMode;
 use crate::git::is_modified_status;
+use crate::path_utils::normalize_key;
 use crate::shared::SharedFrecency;
-use heed::{Database, Env, EnvOpenOptions};
-use heed::{
-    EnvFlags,
-    types::{Bytes, SerdeBincode},
-};
-use std::fs;
-use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH};
-use std::{collections::VecDeque, path::Path};
+use crate::storage::{self, Schema, Storage, StorageBackend, StorageOptions, StorageWrite, Table};
+use chrono::{Datelike, TimeZone, Timelike};
+use parking_lot::{Condvar, Mutex, RwLock};
+use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 const DECAY_CONSTANT: f64 = 0.0693; // ln(2)/10 for 10-day half-life
 const SECONDS_PER_DAY: f64 = 86400.0;
+const MIN_MAP_SIZE: usize = 24 * 1024 * 1024; // 24 MiB
 const MAX_HISTORY_DAYS: f64 = 30.0; // Only consider accesses within 30 days
 
 // AI mode: faster decay since AI sessions are shorter and more intense
 const AI_DECAY_CONSTANT: f64 = 0.231; // ln(2)/3 for 3-day half-life
 const AI_MAX_HISTORY_DAYS: f64 = 7.0; // Only consider accesses within 7 days
 
+/// Weight of an access made in the current [`FrecencyContext`], added on top
+/// of its regular weight.
+const CONTEXT_WEIGHT: f64 = 0.5;
+/// Hours an access may be away from the current hour of the day to count as
+/// made at the same time of day.
+const TIME_OF_DAY_WINDOW_HOURS: u32 = 1;
+
+/// How often accesses queued by [`FrecencyTracker::track_access`] are written
+/// to the database.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+// access timestamps by path hash, oldest first. Accesses made on a branch
+// (see `FrecencyContext::branch`) are stored a second time under the hash of
+// the branch and path, so they share the retention and the GC of the others.
+const ACCESSES: Table<VecDeque<u64>> = Table::new(None);
+const SCHEMA: Schema = Schema {
+    tables: &[ACCESSES.schema()],
+    min_map_size: MIN_MAP_SIZE,
+    max_dbs: 0,
+};
+
 #[derive(Debug)]
 pub struct FrecencyTracker {
-    env: Env,
-    db: Database<Bytes, SerdeBincode<VecDeque<u64>>>,
+    storage: Arc<dyn Storage>,
+    options: StorageOptions,
+    queue: Arc<WriteQueue>,
+    flusher: Option<JoinHandle<()>>,
+    context: RwLock<FrecencyContext>,
+    /// Whether files were scored by their branch accesses when the changes
+    /// of other processes were last taken.
+    branch_active: AtomicBool,
+}
+
+/// Where the files are being opened, so the ones opened in the same
+/// situation before rank higher: the notes opened every morning in the
+/// morning, the files of a feature on its branch. Every access made in the
+/// context adds [`CONTEXT_WEIGHT`] to the access score. Empty by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrecencyContext {
+    pub time_of_day: Option<TimeOfDay>,
+    /// Branch checked out in the project, see [`crate::git::current_branch`].
+    /// Accesses are recorded for the branch as well while it is set.
+    pub branch: Option<String>,
+    /// Repository `branch` is checked out in, so the same branch name in
+    /// two projects counts as two branches for [`Self::branch_scoped`].
+    pub repository: Option<PathBuf>,
+    /// Score files by the accesses made on `branch` only, so a long-lived
+    /// feature branch and main don't rank by each other's working set.
+    /// Until anything was opened on the branch the project-wide accesses
+    /// are used.
+    pub branch_scoped: bool,
+}
+
+/// Local hour of the day and whether it's on a weekend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeOfDay {
+    pub hour: u32,
+    pub weekend: bool,
+}
+
+impl TimeOfDay {
+    pub fn now() -> Self {
+        let now = chrono::Local::now();
+        TimeOfDay {
+            hour: now.hour(),
+            weekend: now.weekday().number_from_monday() > 5,
+        }
+    }
+
+    fn at(timestamp: u64) -> Option<Self> {
+        let time = chrono::Local
+            .timestamp_opt(timestamp as i64, 0)
+            .earliest()?;
+        Some(TimeOfDay {
+            hour: time.hour(),
+            weekend: time.weekday().number_from_monday() > 5,
+        })
+    }
+
+    /// Same kind of day and at most [`TIME_OF_DAY_WINDOW_HOURS`] apart,
+    /// across midnight too.
+    fn is_near(self, other: TimeOfDay) -> bool {
+        let distance = self.hour.abs_diff(other.hour);
+        self.weekend == other.weekend && distance.min(24 - distance) <= TIME_OF_DAY_WINDOW_HOURS
+    }
+}
+
+/// File next to the database that lists the paths written to it.
+const CHANGE_LOG_FILE: &str = "frecency.changes";
+/// The log starts with a generation number, bumped whenever it is cut short.
+const CHANGE_LOG_HEADER: u64 = 8;
+/// Size after which the next writer empties the log.
+const CHANGE_LOG_MAX_LEN: u64 = 1024 * 1024;
+
+/// Entries of the database written by other processes, see
+/// [`FrecencyTracker::take_external_changes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExternalChanges {
+    /// The accesses of these paths changed.
+    Paths(HashSet<PathBuf>),
+    /// Any score may have changed, e.g. because the log was cut short before
+    /// this process read it.
+    All,
+}
+
+impl Default for ExternalChanges {
+    fn default() -> Self {
+        ExternalChanges::Paths(HashSet::new())
+    }
+}
+
+impl ExternalChanges {
+    pub fn is_empty(&self) -> bool {
+        matches!(self, ExternalChanges::Paths(paths) if paths.is_empty())
+    }
+}
+
+/// Where this process is in the change log.
+#[derive(Debug)]
+struct ChangeLogState {
+    file: File,
+    generation: u64,
+    offset: u64,
+    unread: ExternalChanges,
+}
+
+/// Log of the paths written to the database, shared by every process that
+/// uses it. Each one appends the paths it committed, so the others can
+/// rescore just those files.
+#[derive(Debug)]
+struct ChangeLog {
+    state: Mutex<ChangeLogState>,
+}
+
+impl ChangeLog {
+    fn open(db_path: &Path) -> Result<Self> {
+        let path = db_path.join(CHANGE_LOG_FILE);
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| Error::Io(path.clone(), e))?;
+        let mut state = ChangeLogState {
+            file,
+            generation: 0,
+            offset: CHANGE_LOG_HEADER,
+            unread: ExternalChanges::default(),
+        };
+
+        state
+            .file
+            .lock_shared()
+            .map_err(|e| Error::Io(path.clone(), e))?;
+        let position = Self::position(&mut state.file);
+        state
+            .file
+            .unlock()
+            .map_err(|e| Error::Io(path.clone(), e))?;
+        (state.generation, state.offset) = position.map_err(|e| Error::Io(path, e))?;
+
+        Ok(ChangeLog {
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Generation and end of the log.
+    fn position(file: &mut File) -> io::Result<(u64, u64)> {
+        let len = file.metadata()?.len();
+        if len < CHANGE_LOG_HEADER {
+            return Ok((0, CHANGE_LOG_HEADER));
+        }
+
+        let mut generation = [0; 8];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut generation)?;
+        Ok((u64::from_le_bytes(generation), len))
+    }
+
+    /// Collect the paths other processes appended since this one last read
+    /// the log. The file must be locked.
+    fn read_unread(state: &mut ChangeLogState) -> io::Result<()> {
+        let (generation, len) = Self::position(&mut state.file)?;
+        if generation != state.generation || len < state.offset {
+            state.unread = ExternalChanges::All;
+        } else if len > state.offset
+            && let ExternalChanges::Paths(paths) = &mut state.unread
+        {
+            let mut appended = Vec::new();
+            state.file.seek(SeekFrom::Start(state.offset))?;
+            (&state.file)
+                .take(len - state.offset)
+                .read_to_end(&mut appended)?;
+            paths.extend(
+                appended
+                    .split(|&byte| byte == 0)
+                    .filter(|path| !path.is_empty())
+                    .filter_map(|path| std::str::from_utf8(path).ok())
+                    .map(PathBuf::from),
+            );
+        }
+
+        state.generation = generation;
+        state.offset = len;
+        Ok(())
+    }
+
+    /// Append the paths this process committed, each followed by a NUL byte.
+    fn append<'a>(&self, paths: impl IntoIterator<Item = &'a Path>) -> io::Result<()> {
+        let mut record = Vec::new();
+        for path in paths {
+            if let Some(path) = path.to_str() {
+                record.extend_from_slice(path.as_bytes());
+                record.push(0);
+            }
+        }
+        if record.is_empty() {
+            return Ok(());
+        }
+
+        let mut guard = self.state.lock();
+        let state = &mut *guard;
+        state.file.lock()?;
+        let result = (|| {
+            Self::read_unread(state)?;
+            let empty = state.file.metadata()?.len() < CHANGE_LOG_HEADER;
+            if empty || state.offset >= CHANGE_LOG_MAX_LEN {
+                let generation = state.generation + u64::from(!empty);
+                state.file.set_len(0)?;
+                state.file.seek(SeekFrom::Start(0))?;
+                state.file.write_all(&generation.to_le_bytes())?;
+                state.generation = generation;
+                state.offset = CHANGE_LOG_HEADER;
+            }
+            state.file.seek(SeekFrom::Start(state.offset))?;
+            state.file.write_all(&record)?;
+            state.offset += record.len() as u64;
+            Ok(())
+        })();
+        state.file.unlock()?;
+        result
+    }
+
+    /// Look for paths committed by other processes.
+    fn poll(&self) -> io::Result<()> {
+        let mut state = self.state.lock();
+        state.file.lock_shared()?;
+        let result = Self::read_unread(&mut state);
+        state.file.unlock()?;
+        result
+    }
+
+    fn take(&self) -> ExternalChanges {
+        std::mem::take(&mut self.state.lock().unread)
+    }
+}
+
+/// Accesses that were tracked but not yet written to the database, keyed by path hash.
+#[derive(Debug)]
+struct WriteQueue {
+    pending: Mutex<HashMap<[u8; 32], Vec<u64>>>,
+    /// Paths of the pending accesses, logged for other processes once written.
+    pending_paths: Mutex<HashSet<PathBuf>>,
+    /// Held for the whole drain and write so batches land in the order they were taken.
+    flush_lock: Mutex<()>,
+    changes: ChangeLog,
+    stopped: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl WriteQueue {
+    fn new(changes: ChangeLog) -> Self {
+        WriteQueue {
+            pending: Mutex::default(),
+            pending_paths: Mutex::default(),
+            flush_lock: Mutex::default(),
+            changes,
+            stopped: Mutex::default(),
+            wake: Condvar::new(),
+        }
+    }
+
+    /// Let other processes know this one changed the accesses of `paths`.
+    fn committed<'a>(&self, paths: impl IntoIterator<Item = &'a Path>) {
+        if let Err(e) = self.changes.append(paths) {
+            tracing::warn!("Failed to log a frecency database write: {e}");
+        }
+    }
+
+    /// Queue an access to `path` stored under each of `key_hashes`.
+    fn push(&self, path: &Path, key_hashes: &[[u8; 32]], timestamp: u64) {
+        let mut pending = self.pending.lock();
+        for key_hash in key_hashes {
+            pending.entry(*key_hash).or_default().push(timestamp);
+        }
+        self.pending_paths.lock().insert(path.to_path_buf());
+    }
+
+    fn pending_for(&self, key_hash: &[u8; 32]) -> Option<Vec<u64>> {
+        self.pending.lock().get(key_hash).cloned()
+    }
+
+    /// Write every queued access in a single transaction. Returns the number
+    /// of accesses written; on failure they are queued again.
+    fn flush(&self, storage: &dyn Storage) -> Result<usize> {
+        let _flushing = self.flush_lock.lock();
+        let (batch, paths) = {
+            let mut pending = self.pending.lock();
+            let paths = std::mem::take(&mut *self.pending_paths.lock());
+            (std::mem::take(&mut *pending), paths)
+        };
+        if batch.is_empty() {
+            return Ok(0);
+        }
+
+        match self.write_batch(storage, &batch) {
+            Ok(written) => {
+                self.committed(paths.iter().map(PathBuf::as_path));
+                Ok(written)
+            }
+            Err(e) => {
+                let mut pending = self.pending.lock();
+                for (key_hash, mut timestamps) in batch {
+                    let queued = pending.entry(key_hash).or_default();
+                    timestamps.append(queued);
+                    *queued = timestamps;
+                }
+                self.pending_paths.lock().extend(paths);
+                Err(e)
+            }
+        }
+    }
+
+    fn write_batch(
+        &self,
+        storage: &dyn Storage,
+        batch: &HashMap<[u8; 32], Vec<u64>>,
+    ) -> Result<usize> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let cutoff_time = now.saturating_sub((MAX_HISTORY_DAYS * SECONDS_PER_DAY) as u64);
+
+        let written = storage.write(|wtxn| {
+            let mut written = 0;
+            for (key_hash, timestamps) in batch {
+                let mut accesses = ACCESSES.get(wtxn, key_hash)?.unwrap_or_default();
+                while accesses.front().is_some_and(|&time| time < cutoff_time) {
+                    accesses.pop_front();
+                }
+
+                accesses.extend(timestamps);
+                written += timestamps.len();
+                ACCESSES.put(wtxn, key_hash, &accesses)?;
+            }
+            Ok(written)
+        })?;
+
+        tracing::debug!(written, files = batch.len(), "Flushed frecency accesses");
+        Ok(written)
+    }
+
+    fn run_flusher(queue: Arc<WriteQueue>, storage: Arc<dyn Storage>) {
+        loop {
+            {
+                let mut stopped = queue.stopped.lock();
+                if !*stopped {
+                    queue.wake.wait_for(&mut stopped, FLUSH_INTERVAL);
+                }
+                if *stopped {
+                    return;
+                }
+            }
+
+            if let Err(e) = queue.flush(&*storage) {
+                tracing::warn!("Failed to flush frecency accesses: {e}");
+            }
+            if let Err(e) = queue.changes.poll() {
+                tracing::debug!("Failed to check for frecency changes by other processes: {e}");
+            }
+        }
+    }
 }
 
 const MODIFICATION_THRESHOLDS: [(i64, u64); 5] = [
@@ -44,47 +429,106 @@ const AI_MODIFICATION_THRESHOLDS: [(i64, u64); 5] = [
     (1, 60 * 60 * 4), // 4 hours
 ];
 
-impl DbHealthChecker for FrecencyTracker {
-    fn get_env(&self) -> &heed::Env {
-        &self.env
+impl Drop for FrecencyTracker {
+    fn drop(&mut self) {
+        *self.queue.stopped.lock() = true;
+        self.queue.wake.notify_all();
+        if let Some(flusher) = self.flusher.take() {
+            let _ = flusher.join();
+        }
+
+        if let Err(e) = self.flush() {
+            tracing::error!("Failed to flush frecency accesses on shutdown: {e}");
+        }
     }
+}
 
+impl DbHealthChecker for FrecencyTracker {
     fn count_entries(&self) -> Result<Vec<(&'static str, u64)>> {
-        let rtxn = self.env.read_txn().map_err(Error::DbStartReadTxn)?;
-        let count = self.db.len(&rtxn).map_err(Error::DbRead)?;
+        let count = self.storage.read(|rtxn| ACCESSES.len(rtxn))?;
 
         Ok(vec![("absolute_frecency_entries", count)])
     }
+
+    fn get_health(&self) -> Result<DbHealth> {
+        db_healthcheck::storage_health(&*self.storage, self.count_entries()?)
+    }
 }
 
 impl FrecencyTracker {
+    /// Open the LMDB database at `db_path`.
     pub fn new(db_path: impl AsRef<Path>, use_unsafe_no_lock: bool) -> Result<Self> {
-        let db_path = db_path.as_ref();
-        fs::create_dir_all(db_path).map_err(Error::CreateDir)?;
+        Self::with_options(db_path, StorageOptions::lmdb(use_unsafe_no_lock))
+    }
 
-        let env = unsafe {
-            let mut opts = EnvOpenOptions::new();
-            opts.map_size(24 * 1024 * 1024); // 24 MiB
-            if use_unsafe_no_lock {
-                opts.flags(EnvFlags::NO_LOCK | EnvFlags::NO_SYNC | EnvFlags::NO_META_SYNC);
-            }
-            opts.open(db_path).map_err(Error::EnvOpen)?
-        };
-        env.clear_stale_readers()
-            .map_err(Error::DbClearStaleReaders)?;
+    /// Open the database at `db_path` with the storage backend from `options`.
+    pub fn with_options(db_path: impl AsRef<Path>, options: StorageOptions) -> Result<Self> {
+        let db_path = db_path.as_ref();
+        let storage = storage::open(db_path, options, &SCHEMA)?;
 
-        // we will open the default unnamed database
-        let mut wtxn = env.write_txn().map_err(Error::DbStartWriteTxn)?;
-        let db = env
-            .create_database(&mut wtxn, None)
-            .map_err(Error::DbCreate)?;
+        let queue = Arc::new(WriteQueue::new(ChangeLog::open(db_path)?));
+        let flusher = std::thread::Builder::new()
+            .name("fff-frecency-flush".into())
+            .spawn({
+                let queue = Arc::clone(&queue);
+                let storage = Arc::clone(&storage);
+                move || WriteQueue::run_flusher(queue, storage)
+            })?;
 
         Ok(FrecencyTracker {
-            db,
-            env: env.clone(),
+            storage,
+            options,
+            queue,
+            flusher: Some(flusher),
+            context: RwLock::default(),
+            branch_active: AtomicBool::new(false),
         })
     }
 
+    /// Rebuild the database at `db_path` from the entries that can still be
+    /// read, for when opening it fails with a corruption error
+    /// ([`Error::is_corruption`]). The database must not be open.
+    pub fn repair(db_path: impl AsRef<Path>, backend: StorageBackend) -> Result<DbRepair> {
+        storage::repair(db_path.as_ref(), backend, &SCHEMA)
+    }
+
+    /// Write all queued accesses to the database now. Returns how many were written.
+    ///
+    /// Queued accesses are otherwise written every [`FLUSH_INTERVAL`] and when
+    /// the tracker is dropped.
+    pub fn flush(&self) -> Result<usize> {
+        self.queue.flush(&*self.storage)
+    }
+
+    /// The paths other processes sharing the database changed the accesses
+    /// of since the last call, so the scores cached for them are stale.
+    /// Checked every [`FLUSH_INTERVAL`].
+    pub fn take_external_changes(&self) -> ExternalChanges {
+        let changes = self.queue.changes.take();
+        let context = self.context.read();
+        if changes.is_empty() || !context.branch_scoped {
+            return changes;
+        }
+
+        // the first access on the branch switches every file to branch scores
+        let active = self.branch_has_accesses(&context);
+        if self.branch_active.swap(active, Ordering::AcqRel) != active {
+            return ExternalChanges::All;
+        }
+        changes
+    }
+
+    /// Write to the database and let other processes know `paths` changed.
+    fn write<T>(
+        &self,
+        paths: &[&Path],
+        write: impl FnMut(&mut dyn StorageWrite) -> Result<T>,
+    ) -> Result<T> {
+        let value = self.storage.write(write)?;
+        self.queue.committed(paths.iter().copied());
+        Ok(value)
+    }
+
     /// Spawns a background thread to purge stale frecency entries and compact the database.
     /// Run it once in a while to purge old pages and keep DB file size reasonable.
     ///
@@ -94,27 +538,29 @@ impl FrecencyTracker {
     /// use fff_search::frecency::FrecencyTracker;
     /// use fff_search::SharedFrecency;
     /// let shared_frecency: SharedFrecency = Default::default();
-    /// let _ = FrecencyTracker::spawn_gc(shared_frecency, "/path/to/frecency_db".into(), true).ok();
+    /// let _ = FrecencyTracker::spawn_gc(shared_frecency, "/path/to/frecency_db".into()).ok();
     /// ```
+    ///
+    /// The compacted database is reopened with the same storage options as the
+    /// tracker it replaces.
     pub fn spawn_gc(
         shared: SharedFrecency,
         db_path: String,
-        use_unsafe_no_lock: bool,
     ) -> Result<std::thread::JoinHandle<()>> {
         Ok(std::thread::Builder::new()
             .name("fff-frecency-gc".into())
-            .spawn(move || Self::run_frecency_gc(shared, db_path, use_unsafe_no_lock))?)
+            .spawn(move || Self::run_frecency_gc(shared, db_path))?)
     }
 
     #[tracing::instrument(skip(shared), fields(db_path = %db_path))]
-    fn run_frecency_gc(shared: SharedFrecency, db_path: String, use_unsafe_no_lock: bool) {
+    fn run_frecency_gc(shared: SharedFrecency, db_path: String) {
         let start = std::time::Instant::now();
-        let data_path = PathBuf::from(&db_path).join("data.mdb");
+        let db_path = Path::new(&db_path);
 
         // Phase 1: Purge stale entries.
         // The RwLock protects the Option<FrecencyTracker> (not the DB itself),
-        // so a read lock is sufficient — LMDB handles its own write serialization.
-        let (deleted, pruned) = {
+        // so a read lock is sufficient — the storage serializes its own writes.
+        let (deleted, pruned, options) = {
             let guard = match shared.read() {
                 Ok(g) => g,
                 Err(e) => {
@@ -126,7 +572,7 @@ impl FrecencyTracker {
                 return;
             };
             match tracker.purge_stale_entries() {
-                Ok(result) => result,
+                Ok((deleted, pruned)) => (deleted, pruned, tracker.options),
                 Err(e) => {
                     tracing::debug!("Purge failed: {e}");
                     return;
@@ -139,7 +585,7 @@ impl FrecencyTracker {
         }
 
         // Compact if we purged entries OR the file has significant freelist bloat
-        let file_size = fs::metadata(&data_path).map(|m| m.len()).unwrap_or(0);
+        let file_size = storage::data_file_size(db_path, options.backend);
         if deleted == 0 && pruned == 0 && file_size <= 512 * 1024 {
             return;
         }
@@ -153,31 +599,32 @@ impl FrecencyTracker {
             }
         };
 
-        // Read all entries from current env
+        // Read all entries from current database
         let entries: Vec<(Vec<u8>, VecDeque<u64>)> = match guard.as_ref() {
             Some(tracker) => {
-                let rtxn = match tracker.env.read_txn() {
-                    Ok(t) => t,
-                    Err(e) => {
-                        tracing::debug!("Compaction read_txn failed: {e}");
-                        return;
-                    }
-                };
-                let iter = match tracker.db.iter(&rtxn) {
-                    Ok(i) => i,
+                if let Err(e) = tracker.flush() {
+                    tracing::debug!("Compaction flush failed: {e}");
+                    return;
+                }
+                let read = tracker.storage.read(|rtxn| {
+                    let mut entries = Vec::new();
+                    let mut read_errors = 0u32;
+                    rtxn.for_each(None, &mut |key, value| {
+                        match bincode::deserialize(value) {
+                            Ok(accesses) => entries.push((key.to_vec(), accesses)),
+                            Err(_) => read_errors += 1,
+                        }
+                        Ok(())
+                    })?;
+                    Ok((entries, read_errors))
+                });
+                let (entries, read_errors) = match read {
+                    Ok(read) => read,
                     Err(e) => {
-                        tracing::debug!("Compaction iter failed: {e}");
+                        tracing::debug!("Compaction read failed: {e}");
                         return;
                     }
                 };
-                let mut entries = Vec::new();
-                let mut read_errors = 0u32;
-                for result in iter {
-                    match result {
-                        Ok((key, value)) => entries.push((key.to_vec(), value)),
-                        Err(_) => read_errors += 1,
-                    }
-                }
                 if read_errors > 0 {
                     tracing::warn!(
                         read_errors,
@@ -189,14 +636,11 @@ impl FrecencyTracker {
             None => return,
         };
 
-        // Drop old tracker, delete files, create fresh env, write back
+        // Drop old tracker, delete files, create fresh database, write back
         *guard = None;
+        storage::remove(db_path, options.backend);
 
-        let lock_path = PathBuf::from(&db_path).join("lock.mdb");
-        let _ = fs::remove_file(&data_path);
-        let _ = fs::remove_file(&lock_path);
-
-        let tracker = match FrecencyTracker::new(&db_path, use_unsafe_no_lock) {
+        let tracker = match FrecencyTracker::with_options(db_path, options) {
             Ok(t) => t,
             Err(e) => {
                 tracing::error!("Compaction reopen failed, frecency disabled: {e}");
@@ -204,18 +648,16 @@ impl FrecencyTracker {
             }
         };
 
-        let write_result = (|| -> std::result::Result<(), heed::Error> {
-            let mut wtxn = tracker.env.write_txn()?;
+        let write_result = tracker.storage.write(|wtxn| {
             for (key, value) in &entries {
-                tracker.db.put(&mut wtxn, key.as_slice(), value)?;
+                ACCESSES.put(wtxn, key, value)?;
             }
-            wtxn.commit()?;
             Ok(())
-        })();
+        });
 
         match write_result {
             Ok(()) => {
-                let new_size = fs::metadata(&data_path).map(|m| m.len()).unwrap_or(0);
+                let new_size = storage::data_file_size(db_path, options.backend);
                 *guard = Some(tracker);
                 tracing::debug!(
                     entries = entries.len(),
@@ -236,62 +678,72 @@ impl FrecencyTracker {
     /// and prunes stale timestamps from entries that still have recent ones.
     /// Returns (deleted_count, pruned_count).
     fn purge_stale_entries(&self) -> Result<(usize, usize)> {
+        self.flush()?;
         let now = self.get_now();
         let cutoff_time = now.saturating_sub((MAX_HISTORY_DAYS * SECONDS_PER_DAY) as u64);
 
         // Collect entries to delete or update
-        let rtxn = self.env.read_txn().map_err(Error::DbStartReadTxn)?;
         let mut to_delete: Vec<Vec<u8>> = Vec::new();
         let mut to_update: Vec<(Vec<u8>, VecDeque<u64>)> = Vec::new();
 
-        let iter = self.db.iter(&rtxn).map_err(Error::DbRead)?;
-        for result in iter {
-            let (key, accesses) = result.map_err(Error::DbRead)?;
-
-            // Timestamps are chronologically ordered (oldest at front).
-            // Find the first timestamp that is still within the retention window.
-            let fresh_start = accesses.iter().position(|&ts| ts >= cutoff_time);
-            match fresh_start {
-                None => {
-                    // All timestamps are stale — delete the entire entry
-                    to_delete.push(key.to_vec());
-                }
-                Some(0) => {
-                    // All timestamps are fresh — nothing to do
-                }
-                Some(start) => {
-                    // Some timestamps are stale — keep only the fresh ones
-                    let pruned: VecDeque<u64> = accesses.iter().skip(start).copied().collect();
-                    to_update.push((key.to_vec(), pruned));
+        self.storage.read(|rtxn| {
+            to_delete.clear();
+            to_update.clear();
+            ACCESSES.for_each(rtxn, |key, accesses| {
+                // Timestamps are chronologically ordered (oldest at front).
+                // Find the first timestamp that is still within the retention window.
+                let fresh_start = accesses.iter().position(|&ts| ts >= cutoff_time);
+                match fresh_start {
+                    None => {
+                        // All timestamps are stale — delete the entire entry
+                        to_delete.push(key.to_vec());
+                    }
+                    Some(0) => {
+                        // All timestamps are fresh — nothing to do
+                    }
+                    Some(start) => {
+                        // Some timestamps are stale — keep only the fresh ones
+                        let pruned: VecDeque<u64> = accesses.iter().skip(start).copied().collect();
+                        to_update.push((key.to_vec(), pruned));
+                    }
                 }
-            }
-        }
-        drop(rtxn);
+            })
+        })?;
 
         if to_delete.is_empty() && to_update.is_empty() {
             return Ok((0, 0));
         }
 
-        // Apply all changes in a single write transaction
-        let mut wtxn = self.env.write_txn().map_err(Error::DbStartWriteTxn)?;
-        for key in &to_delete {
-            self.db.delete(&mut wtxn, key).map_err(Error::DbWrite)?;
-        }
-        for (key, accesses) in &to_update {
-            self.db
-                .put(&mut wtxn, key, accesses)
-                .map_err(Error::DbWrite)?;
-        }
-        wtxn.commit().map_err(Error::DbCommit)?;
+        // Apply all changes in a single write transaction. Only accesses
+        // outside of the scored history go, so other processes keep their
+        // scores.
+        self.storage.write(|wtxn| {
+            for key in &to_delete {
+                ACCESSES.delete(wtxn, key)?;
+            }
+            for (key, accesses) in &to_update {
+                ACCESSES.put(wtxn, key, accesses)?;
+            }
+            Ok(())
+        })?;
 
         Ok((to_delete.len(), to_update.len()))
     }
 
     fn get_accesses(&self, path: &Path) -> Result<Option<VecDeque<u64>>> {
-        let rtxn = self.env.read_txn().map_err(Error::DbStartReadTxn)?;
+        self.get_accesses_by_key(&Self::path_to_hash_bytes(path)?)
+    }
 
-        let key_hash = Self::path_to_hash_bytes(path)?;
-        self.db.get(&rtxn, &key_hash).map_err(Error::DbRead)
+    fn get_accesses_by_key(&self, key_hash: &[u8; 32]) -> Result<Option<VecDeque<u64>>> {
+        let stored = self.storage.read(|rtxn| ACCESSES.get(rtxn, key_hash))?;
+        match self.queue.pending_for(key_hash) {
+            Some(pending) => {
+                let mut accesses = stored.unwrap_or_default();
+                accesses.extend(pending);
+                Ok(Some(accesses))
+            }
+            None => Ok(stored),
+        }
     }
 
     fn get_now(&self) -> u64 {
@@ -309,48 +761,183 @@ impl FrecencyTracker {
         Ok(*blake3::hash(key.as_bytes()).as_bytes())
     }
 
+    /// Key of the accesses to `path` made on `branch`.
+    fn branch_hash_bytes(branch: &str, path: &Path) -> Result<[u8; 32]> {
+        let Some(key) = path.to_str() else {
+            return Err(Error::InvalidPath(path.to_path_buf()));
+        };
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(b"branch\0");
+        hasher.update(branch.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(key.as_bytes());
+        Ok(*hasher.finalize().as_bytes())
+    }
+
+    /// Replace the context accesses are scored and recorded in. Returns
+    /// whether it changed, in which case the cached scores of the files are
+    /// stale, see [`crate::file_picker::FilePicker::refresh_frecency_scores`].
+    pub fn set_context(&self, context: FrecencyContext) -> bool {
+        self.branch_active
+            .store(self.branch_has_accesses(&context), Ordering::Release);
+        let mut current = self.context.write();
+        let changed = *current != context;
+        *current = context;
+        changed
+    }
+
+    pub fn context(&self) -> FrecencyContext {
+        self.context.read().clone()
+    }
+
     /// Returns seconds since the most recent tracked access, or `None` if the
     /// file has never been tracked.
     pub fn seconds_since_last_access(&self, path: &Path) -> Result<Option<u64>> {
-        let accesses = self.get_accesses(path)?;
+        let accesses = self.get_accesses(&normalize_key(path))?;
         let last = accesses.and_then(|a| a.back().copied());
         Ok(last.map(|ts| self.get_now().saturating_sub(ts)))
     }
 
-    pub fn track_access(&self, path: &Path) -> Result<()> {
-        let mut wtxn = self.env.write_txn().map_err(Error::DbStartWriteTxn)?;
+    /// How many accesses to `path` are recorded and when the last one was,
+    /// or `None` if the file has never been tracked.
+    pub fn access_stats(&self, path: &Path) -> Result<Option<(usize, u64)>> {
+        let accesses = self.get_accesses(&normalize_key(path))?;
+        Ok(accesses.and_then(|a| Some((a.len(), *a.back()?))))
+    }
 
+    /// Record an access to `path`, under its [`normalize_key`] form. The
+    /// write is queued and reaches the database within [`FLUSH_INTERVAL`],
+    /// but scores include it right away.
+    pub fn track_access(&self, path: &Path) -> Result<()> {
+        let path = &normalize_key(path);
         let key_hash = Self::path_to_hash_bytes(path)?;
-        let mut accesses = self.get_accesses(path)?.unwrap_or_default();
-
+        tracing::debug!(?path, "Tracking access");
         let now = self.get_now();
-        let cutoff_time = now.saturating_sub((MAX_HISTORY_DAYS * SECONDS_PER_DAY) as u64);
-        while let Some(&front_time) = accesses.front() {
-            if front_time < cutoff_time {
-                accesses.pop_front();
-            } else {
-                break;
+        let mut key_hashes = vec![key_hash];
+        let context = self.context.read();
+        if let Some(ref branch) = context.branch {
+            key_hashes.push(Self::branch_hash_bytes(branch, path)?);
+            // the repository stands for every file opened on the branch
+            if let Some(ref repository) = context.repository {
+                key_hashes.push(Self::branch_hash_bytes(branch, repository)?);
             }
         }
+        self.queue.push(path, &key_hashes, now);
+        Ok(())
+    }
 
-        accesses.push_back(now);
-        tracing::debug!(?path, accesses = accesses.len(), "Tracking access");
+    /// Remove the access history of `path` and return it, e.g. before the file
+    /// is renamed or deleted so the record can follow it or be restored later.
+    pub fn take_access_history(&self, path: &Path) -> Result<Option<VecDeque<u64>>> {
+        self.flush()?;
+        let path = &normalize_key(path);
+        let key_hash = Self::path_to_hash_bytes(path)?;
 
-        self.db
-            .put(&mut wtxn, &key_hash, &accesses)
-            .map_err(Error::DbWrite)?;
+        self.write(&[path], |wtxn| {
+            let accesses = ACCESSES.get(wtxn, &key_hash)?;
+            if accesses.is_some() {
+                ACCESSES.delete(wtxn, &key_hash)?;
+            }
+            Ok(accesses)
+        })
+    }
 
-        wtxn.commit().map_err(Error::DbCommit)?;
+    /// Store a previously taken access history under `path`, replacing any existing record.
+    pub fn restore_access_history(&self, path: &Path, accesses: &VecDeque<u64>) -> Result<()> {
+        self.flush()?;
+        let path = &normalize_key(path);
+        let key_hash = Self::path_to_hash_bytes(path)?;
 
-        Ok(())
+        self.write(&[path], |wtxn| ACCESSES.put(wtxn, &key_hash, accesses))
     }
 
+    /// Move the access histories recorded under the old key of a file to its
+    /// current one, for histories tracked before the paths were normalized
+    /// with [`crate::path_utils::normalize_key`]. Takes `(old, new)` pairs
+    /// and returns how many histories were moved.
+    pub fn migrate_keys<'a>(
+        &self,
+        keys: impl IntoIterator<Item = (PathBuf, &'a Path)>,
+    ) -> Result<usize> {
+        let keys = keys
+            .into_iter()
+            .filter(|(old, new)| old.as_path() != *new)
+            .filter_map(|(old, new)| {
+                Some((
+                    Self::path_to_hash_bytes(&old).ok()?,
+                    Self::path_to_hash_bytes(new).ok()?,
+                    new,
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        let stale = self.storage.read(|rtxn| {
+            let mut stale = Vec::new();
+            for (old, new, _) in &keys {
+                if let Some(accesses) = ACCESSES.get(rtxn, old)? {
+                    stale.push((old, new, accesses));
+                }
+            }
+            Ok(stale)
+        })?;
+        if stale.is_empty() {
+            return Ok(0);
+        }
+
+        let paths = keys.iter().map(|&(_, _, new)| new).collect::<Vec<_>>();
+        self.flush()?;
+        self.write(&paths, |wtxn| {
+            for (old, new, accesses) in &stale {
+                let mut merged = ACCESSES.get(wtxn, *new)?.unwrap_or_default();
+                merged.extend(accesses);
+                merged.make_contiguous().sort_unstable();
+                ACCESSES.put(wtxn, *new, &merged)?;
+                ACCESSES.delete(wtxn, *old)?;
+            }
+            Ok(())
+        })?;
+
+        tracing::info!(migrated = stale.len(), "Migrated frecency keys");
+        Ok(stale.len())
+    }
+
+    /// Whether anything was opened on the branch of `context` in its
+    /// repository within the retained history.
+    fn branch_has_accesses(&self, context: &FrecencyContext) -> bool {
+        let (Some(branch), Some(repository)) = (&context.branch, &context.repository) else {
+            return false;
+        };
+        Self::branch_hash_bytes(branch, repository)
+            .and_then(|key| self.get_accesses_by_key(&key))
+            .is_ok_and(|accesses| accesses.is_some_and(|a| !a.is_empty()))
+    }
+
+    /// Score of the accesses to `file_path`, which must be a [`normalize_key`]
+    /// already. The picker scores every indexed file with it, and their paths
+    /// are keys because the base path is normalized, resolving each one again
+    /// would cost a syscall per file.
     pub fn get_access_score(&self, file_path: &Path, mode: FFFMode) -> i64 {
-        let accesses = self
-            .get_accesses(file_path)
-            .ok()
-            .flatten()
-            .unwrap_or_default();
+        let context = self.context.read().clone();
+        let branch_accesses = |branch: &str| {
+            Self::branch_hash_bytes(branch, file_path)
+                .and_then(|key| self.get_accesses_by_key(&key))
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        };
+        let scoped_branch = context
+            .branch
+            .as_deref()
+            .filter(|_| context.branch_scoped && self.branch_has_accesses(&context));
+        let accesses = match scoped_branch {
+            Some(branch) => branch_accesses(branch),
+            None => self
+                .get_accesses(file_path)
+                .ok()
+                .flatten()
+                .unwrap_or_default(),
+        };
 
         if accesses.is_empty() {
             return 0;
@@ -371,15 +958,35 @@ impl FrecencyTracker {
         let mut total_frecency = 0.0;
 
         let cutoff_time = now.saturating_sub((max_history_days * SECONDS_PER_DAY) as u64);
+        let decay = |access_time: u64| {
+            let days_ago = (now.saturating_sub(access_time) as f64) / SECONDS_PER_DAY;
+            (-decay_constant * days_ago).exp()
+        };
 
         for &access_time in accesses.iter().rev() {
             if access_time < cutoff_time {
                 break; // All remaining entries are older, stop processing
             }
 
-            let days_ago = (now.saturating_sub(access_time) as f64) / SECONDS_PER_DAY;
-            let decay_factor = (-decay_constant * days_ago).exp();
+            let decay_factor = decay(access_time);
             total_frecency += decay_factor;
+            if let Some(time_of_day) = context.time_of_day
+                && TimeOfDay::at(access_time).is_some_and(|at| at.is_near(time_of_day))
+            {
+                total_frecency += CONTEXT_WEIGHT * decay_factor;
+            }
+        }
+
+        // scored by the branch alone already
+        if scoped_branch.is_none()
+            && let Some(ref branch) = context.branch
+        {
+            for &access_time in branch_accesses(branch).iter().rev() {
+                if access_time < cutoff_time {
+                    break;
+                }
+                total_frecency += CONTEXT_WEIGHT * decay(access_time);
+            }
         }
 
         let normalized_frecency = if total_frecency <= 10.0 {
@@ -551,4 +1158,168 @@ mod tests {
 
         let _ = std::fs::remove_dir_all(&temp_dir);
     }
+
+    #[test]
+    fn queued_accesses_are_scored_before_and_persisted_after_flush() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("frecency");
+        let file = tmp.path().join("main.rs");
+
+        let tracker = FrecencyTracker::new(&db_path, true).unwrap();
+        tracker.track_access(&file).unwrap();
+        tracker.track_access(&file).unwrap();
+        assert_eq!(tracker.get_access_score(&file, FFFMode::Neovim), 2);
+
+        tracker.flush().unwrap();
+        assert_eq!(tracker.flush().unwrap(), 0);
+        assert_eq!(tracker.get_access_score(&file, FFFMode::Neovim), 2);
+
+        // dropping the tracker writes whatever is still queued
+        tracker.track_access(&file).unwrap();
+        drop(tracker);
+
+        let reopened = FrecencyTracker::new(&db_path, true).unwrap();
+        assert_eq!(reopened.get_accesses(&file).unwrap().unwrap().len(), 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn accesses_are_keyed_by_the_normalized_path() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = std::fs::canonicalize(tmp.path()).unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "").unwrap();
+        std::os::unix::fs::symlink(&root, root.join("alias")).unwrap();
+        let tracker = FrecencyTracker::new(root.join("frecency"), true).unwrap();
+
+        tracker
+            .track_access(&root.join("alias/src/main.rs"))
+            .unwrap();
+        tracker
+            .track_access(&root.join("src/../src/main.rs"))
+            .unwrap();
+
+        let key = root.join("src/main.rs");
+        assert_eq!(tracker.get_access_score(&key, FFFMode::Neovim), 2);
+        assert!(
+            tracker
+                .seconds_since_last_access(&root.join("alias/src/main.rs"))
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn accesses_in_the_current_context_score_higher() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tracker = FrecencyTracker::new(tmp.path().join("frecency"), true).unwrap();
+        let on_branch = tmp.path().join("feature.rs");
+        let elsewhere = tmp.path().join("main.rs");
+        let score = |path: &Path| tracker.get_access_score(path, FFFMode::Neovim);
+        let on = |branch: &str| FrecencyContext {
+            branch: Some(branch.to_string()),
+            ..Default::default()
+        };
+
+        assert!(tracker.set_context(on("feature")));
+        assert!(!tracker.set_context(on("feature")));
+        for _ in 0..4 {
+            tracker.track_access(&on_branch).unwrap();
+        }
+        tracker.set_context(FrecencyContext::default());
+        for _ in 0..4 {
+            tracker.track_access(&elsewhere).unwrap();
+        }
+        assert_eq!((score(&on_branch), score(&elsewhere)), (4, 4));
+
+        tracker.set_context(on("feature"));
+        assert_eq!((score(&on_branch), score(&elsewhere)), (6, 4));
+        tracker.set_context(on("main"));
+        assert_eq!(score(&on_branch), 4);
+
+        tracker.set_context(FrecencyContext {
+            time_of_day: Some(TimeOfDay::now()),
+            ..Default::default()
+        });
+        assert_eq!(
+            score(&elsewhere),
+            6,
+            "opened just now, so at this time of day"
+        );
+    }
+
+    #[test]
+    fn branch_scoped_scores_fall_back_until_the_branch_has_accesses() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tracker = FrecencyTracker::new(tmp.path().join("frecency"), true).unwrap();
+        let main_file = tmp.path().join("main.rs");
+        let feature_file = tmp.path().join("feature.rs");
+        let score = |path: &Path| tracker.get_access_score(path, FFFMode::Neovim);
+        let scoped_to = |branch: &str| FrecencyContext {
+            branch: Some(branch.to_string()),
+            repository: Some(tmp.path().to_path_buf()),
+            branch_scoped: true,
+            ..Default::default()
+        };
+
+        tracker.set_context(scoped_to("main"));
+        for _ in 0..4 {
+            tracker.track_access(&main_file).unwrap();
+        }
+
+        tracker.set_context(scoped_to("feature"));
+        assert_eq!(score(&main_file), 4, "nothing opened on the branch yet");
+
+        tracker.track_access(&feature_file).unwrap();
+        assert_eq!((score(&main_file), score(&feature_file)), (0, 1));
+
+        tracker.set_context(scoped_to("main"));
+        assert_eq!((score(&main_file), score(&feature_file)), (4, 0));
+    }
+
+    #[test]
+    fn time_of_day_wraps_around_midnight() {
+        let at = |hour| TimeOfDay {
+            hour,
+            weekend: false,
+        };
+        assert!(at(23).is_near(at(0)));
+        assert!(at(9).is_near(at(10)));
+        assert!(!at(9).is_near(at(11)));
+        assert!(!at(9).is_near(TimeOfDay {
+            hour: 9,
+            weekend: true
+        }));
+    }
+
+    #[test]
+    fn paths_written_by_another_process_are_noticed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ours = ChangeLog::open(tmp.path()).unwrap();
+        let theirs = ChangeLog::open(tmp.path()).unwrap();
+        let paths =
+            |names: &[&str]| ExternalChanges::Paths(names.iter().map(PathBuf::from).collect());
+
+        ours.append([Path::new("/a")]).unwrap();
+        ours.poll().unwrap();
+        assert!(ours.take().is_empty(), "own writes are not external");
+        theirs.poll().unwrap();
+        assert_eq!(theirs.take(), paths(&["/a"]));
+
+        // a write that lands between two of ours is noticed without polling
+        theirs.append([Path::new("/b"), Path::new("/c")]).unwrap();
+        ours.append([Path::new("/a")]).unwrap();
+        assert_eq!(ours.take(), paths(&["/b", "/c"]));
+        assert!(theirs.take().is_empty());
+
+        // a reader that missed the log before it was cut rescores everything
+        let long = "x".repeat(CHANGE_LOG_MAX_LEN as usize);
+        ours.append([Path::new(&long)]).unwrap();
+        ours.append([Path::new("/a")]).unwrap();
+        theirs.poll().unwrap();
+        assert_eq!(theirs.take(), ExternalChanges::All);
+        ours.append([Path::new("/a")]).unwrap();
+        theirs.poll().unwrap();
+        assert_eq!(theirs.take(), paths(&["/a"]));
+    }
 }
@@ -0,0 +1,157 @@
+//! What a project's frecency and query history databases say about it: the
+//! files opened most and the queries searched most.
+//!
+//! The frecency database is keyed by path hashes and can't be listed, so
+//! the opened files are looked up from the indexed ones. Only files with an
+//! access score are looked up, the others weren't opened within the scored
+//! history and a full index doesn't need to be read for a dashboard.
+
+use crate::error::Result;
+use crate::file_list::FileList;
+use crate::frecency::FrecencyTracker;
+use crate::query_tracker::{QueryTracker, QueryUsage};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// An indexed file and how often it was opened.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct FileUsage {
+    pub path: PathBuf,
+    pub relative_path: String,
+    pub open_count: usize,
+    pub last_opened: u64,
+}
+
+/// Most opened files and most used queries of a project, most used first.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FrecencyStats {
+    pub files: Vec<FileUsage>,
+    pub queries: Vec<QueryUsage>,
+    pub grep_queries: Vec<QueryUsage>,
+}
+
+impl FrecencyStats {
+    /// The `top_n` most opened of `files` and most used queries of the
+    /// project. A missing database leaves its lists empty.
+    pub fn collect(
+        files: &FileList,
+        frecency: Option<&FrecencyTracker>,
+        query_tracker: Option<&QueryTracker>,
+        project_path: &Path,
+        top_n: usize,
+    ) -> Result<Self> {
+        let mut stats = FrecencyStats::default();
+
+        if let Some(frecency) = frecency {
+            for file in files
+                .iter()
+                .filter(|f| f.access_frecency_score > 0 && !f.is_deleted)
+            {
+                if let Some((open_count, last_opened)) = frecency.access_stats(file.path())? {
+                    stats.files.push(FileUsage {
+                        path: file.path().to_path_buf(),
+                        relative_path: file.relative_path().to_string(),
+                        open_count,
+                        last_opened,
+                    });
+                }
+            }
+            stats.files.sort_by(|a, b| {
+                b.open_count
+                    .cmp(&a.open_count)
+                    .then_with(|| b.last_opened.cmp(&a.last_opened))
+            });
+            stats.files.truncate(top_n);
+        }
+
+        if let Some(tracker) = query_tracker {
+            stats.queries = tracker.most_used_queries(project_path, top_n)?;
+            stats.grep_queries = tracker.most_used_grep_queries(project_path, top_n)?;
+        }
+
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_picker::FilePicker;
+    use crate::shared::{SharedFrecency, SharedPicker};
+    use crate::test_fixtures::{picker_options, wait_until, write_files};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn most_opened_files_and_queries() {
+        let tmp = TempDir::new().unwrap();
+        let db_dir = TempDir::new().unwrap();
+        let root = fs::canonicalize(tmp.path()).unwrap();
+        write_files(
+            &root,
+            &[("src/main.rs", ""), ("src/lib.rs", ""), ("src/util.rs", "")],
+        );
+
+        let shared_frecency = SharedFrecency::default();
+        shared_frecency
+            .init(FrecencyTracker::new(db_dir.path().join("frecency"), true).unwrap())
+            .unwrap();
+        {
+            let frecency = shared_frecency.read().unwrap();
+            let frecency = frecency.as_ref().unwrap();
+            for name in ["lib.rs", "main.rs", "lib.rs", "lib.rs", "main.rs"] {
+                frecency.track_access(&root.join("src").join(name)).unwrap();
+            }
+            frecency.flush().unwrap();
+        }
+
+        let mut query_tracker = QueryTracker::new(db_dir.path().join("history"), true).unwrap();
+        for query in ["lib", "main", "lib"] {
+            query_tracker
+                .track_query_completion(query, &root, &root.join("src/lib.rs"))
+                .unwrap();
+        }
+
+        let shared_picker = SharedPicker::default();
+        FilePicker::new_with_shared_state(
+            shared_picker.clone(),
+            shared_frecency.clone(),
+            picker_options(&root),
+        )
+        .unwrap();
+        wait_until("the scan", || {
+            shared_picker
+                .read()
+                .unwrap()
+                .as_ref()
+                .is_some_and(|picker| !picker.is_scan_active())
+        });
+
+        let guard = shared_picker.read().unwrap();
+        let picker = guard.as_ref().unwrap();
+        let frecency = shared_frecency.read().unwrap();
+        let stats = FrecencyStats::collect(
+            picker.get_files(),
+            frecency.as_ref(),
+            Some(&query_tracker),
+            picker.base_path(),
+            10,
+        )
+        .unwrap();
+
+        let files: Vec<_> = stats
+            .files
+            .iter()
+            .map(|f| (f.relative_path.as_str(), f.open_count))
+            .collect();
+        assert_eq!(files, [("src/lib.rs", 3), ("src/main.rs", 2)]);
+
+        let queries: Vec<_> = stats
+            .queries
+            .iter()
+            .map(|q| (q.query.as_str(), q.count))
+            .collect();
+        assert_eq!(queries, [("lib", 2), ("main", 1)]);
+        assert!(stats.grep_queries.is_empty());
+    }
+}
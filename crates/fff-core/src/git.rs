@@ -1,11 +1,281 @@
+mod cli_backend;
+#[cfg(feature = "gix")]
+mod gix_backend;
+
+pub use cli_backend::GitCliBackend;
+
 use crate::error::Result;
-use git2::{Repository, Status, StatusOptions};
+use git2::{DiffOptions, Repository, Status, StatusOptions};
 use std::{
+    collections::HashMap,
     fmt::Debug,
     path::{Path, PathBuf},
 };
 use tracing::debug;
 
+/// Reads repositories for the scan and full status refreshes. libgit2 is
+/// the default, the `gix` feature switches to gitoxide for builds where
+/// libgit2 doesn't compile. Per file status updates of the watcher still go
+/// through libgit2.
+pub trait GitBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Find the repository containing `path`, see [`GitRepoLocation::discover`].
+    fn discover(&self, path: &Path) -> Option<GitRepoLocation>;
+
+    /// Statuses of the work tree at `workdir` as `(full path, status)`,
+    /// sorted by path. A [`Status::WT_NEW`] directory stands for every file
+    /// below it, backends may report untracked directories without listing
+    /// their contents.
+    fn read_status(&self, workdir: &Path, query: StatusQuery) -> Result<Vec<(PathBuf, Status)>>;
+}
+
+/// Which files a status read reports, independent of the backend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatusQuery {
+    /// Report untracked files, recursing into untracked directories.
+    pub include_untracked: bool,
+    /// Report clean tracked files as [`Status::CURRENT`].
+    pub include_unmodified: bool,
+    pub exclude_submodules: bool,
+}
+
+/// The backend the crate was built with.
+pub fn backend() -> &'static dyn GitBackend {
+    #[cfg(feature = "gix")]
+    {
+        &gix_backend::GixBackend
+    }
+    #[cfg(not(feature = "gix"))]
+    {
+        &Libgit2Backend
+    }
+}
+
+/// Where full status reads of a picker come from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum GitStatusProvider {
+    /// The [`backend`] the crate was built with.
+    Library,
+    /// [`GitCliBackend`], for repositories where `git status` is faster
+    /// thanks to `core.fsmonitor` or `core.untrackedCache`.
+    Cli,
+    /// [`GitCliBackend`] for repositories that enable `core.fsmonitor` or
+    /// `core.untrackedCache`, the library otherwise.
+    #[default]
+    Auto,
+}
+
+impl GitStatusProvider {
+    pub fn name(self) -> &'static str {
+        match self {
+            GitStatusProvider::Library => "library",
+            GitStatusProvider::Cli => "cli",
+            GitStatusProvider::Auto => "auto",
+        }
+    }
+
+    /// Whether the status of the repository at `workdir` is read with
+    /// [`GitCliBackend`].
+    pub fn uses_cli(self, workdir: &Path) -> bool {
+        match self {
+            GitStatusProvider::Library => false,
+            GitStatusProvider::Cli => true,
+            GitStatusProvider::Auto => uses_git_caches(workdir),
+        }
+    }
+}
+
+impl std::fmt::Display for GitStatusProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for GitStatusProvider {
+    type Err = crate::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "library" => Ok(GitStatusProvider::Library),
+            "cli" => Ok(GitStatusProvider::Cli),
+            "auto" => Ok(GitStatusProvider::Auto),
+            _ => Err(crate::Error::UnknownGitStatusProvider(name.to_string())),
+        }
+    }
+}
+
+/// Name of the branch checked out in the repository at `workdir`, `None` on a
+/// detached HEAD or outside of a repository.
+pub fn current_branch(workdir: &Path) -> Option<String> {
+    let repository = open_repository(workdir).ok()?;
+    if repository.head_detached().unwrap_or(true) {
+        return None;
+    }
+    repository.head().ok()?.shorthand().map(str::to_string)
+}
+
+/// Whether the repository enables the caches only the git CLI uses.
+/// `core.fsmonitor` is either a boolean or the path of a hook.
+fn uses_git_caches(workdir: &Path) -> bool {
+    open_repository(workdir)
+        .and_then(|repo| repo.config())
+        .is_ok_and(|config| config_uses_git_caches(&config))
+}
+
+fn config_uses_git_caches(config: &git2::Config) -> bool {
+    let fsmonitor = config
+        .get_bool("core.fsmonitor")
+        .or_else(|_| {
+            config
+                .get_string("core.fsmonitor")
+                .map(|hook| !hook.is_empty())
+        })
+        .unwrap_or(false);
+    fsmonitor || config.get_bool("core.untrackedCache").unwrap_or(false)
+}
+
+/// Bare dotfile repositories with $HOME as the work tree conventionally set
+/// `status.showUntrackedFiles=no`, listing untracked files there would walk
+/// the whole home directory.
+fn hides_untracked(show_untracked_files: Option<&str>) -> bool {
+    show_untracked_files == Some("no")
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Libgit2Backend;
+
+impl GitBackend for Libgit2Backend {
+    fn name(&self) -> &'static str {
+        "libgit2"
+    }
+
+    fn discover(&self, path: &Path) -> Option<GitRepoLocation> {
+        GitRepoLocation::from_repository(&open_repository(path).ok()?)
+    }
+
+    fn read_status(&self, workdir: &Path, query: StatusQuery) -> Result<Vec<(PathBuf, Status)>> {
+        let repository = open_repository(workdir)?;
+        let show_untracked_files = repository
+            .config()
+            .and_then(|config| config.get_string("status.showUntrackedFiles"))
+            .ok();
+
+        let mut status_options = StatusOptions::new();
+        status_options
+            .include_untracked(
+                query.include_untracked && !hides_untracked(show_untracked_files.as_deref()),
+            )
+            .recurse_untracked_dirs(true)
+            .include_unmodified(query.include_unmodified)
+            .exclude_submodules(query.exclude_submodules);
+
+        Ok(GitStatusCache::read_status_impl(&repository, &mut status_options)?.0)
+    }
+}
+
+/// Location of the repository that owns a directory.
+///
+/// Linked worktrees (`git worktree add`) are resolved explicitly: their `.git`
+/// is a file pointing at `<main>/.git/worktrees/<name>`, which holds the
+/// worktree's own `HEAD` and `index`, while refs and `info/` stay in the main
+/// repository's git dir.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitRepoLocation {
+    /// Checked out working tree. For linked worktrees this is the worktree itself.
+    pub workdir: PathBuf,
+    /// Per-worktree git dir containing `HEAD` and `index`.
+    pub git_dir: PathBuf,
+    /// Git dir shared by all worktrees, containing refs and `info/`.
+    pub common_dir: PathBuf,
+    pub is_worktree: bool,
+}
+
+impl GitRepoLocation {
+    /// Find the repository containing `path`. Returns `None` outside of a
+    /// repository and for bare repositories without a work tree.
+    pub fn discover(path: &Path) -> Option<Self> {
+        backend().discover(path)
+    }
+
+    pub fn from_repository(repo: &Repository) -> Option<Self> {
+        Some(Self {
+            workdir: repo.workdir()?.to_path_buf(),
+            git_dir: repo.path().to_path_buf(),
+            common_dir: repo.commondir().to_path_buf(),
+            is_worktree: repo.is_worktree(),
+        })
+    }
+}
+
+/// Open the repository containing `path`.
+///
+/// Honors `GIT_DIR` / `GIT_WORK_TREE` the way git does, which is how bare
+/// "dotfiles" repositories are usually used, as long as `path` lies inside
+/// that work tree. Otherwise the repository is discovered from `path`,
+/// resolving linked worktrees explicitly.
+pub fn open_repository(path: &Path) -> std::result::Result<Repository, git2::Error> {
+    if let Some(repo) = open_from_git_env(path) {
+        return Ok(repo);
+    }
+
+    match find_linked_worktree_git_dir(path) {
+        Some(git_dir) => Repository::open(&git_dir),
+        None => Repository::discover(path),
+    }
+}
+
+fn open_from_git_env(path: &Path) -> Option<Repository> {
+    let git_dir = std::env::var_os("GIT_DIR")?;
+    let repo = Repository::open(&git_dir)
+        .inspect_err(|e| tracing::warn!(?git_dir, ?e, "Failed to open GIT_DIR repository"))
+        .ok()?;
+
+    // Without GIT_WORK_TREE fall back to `core.worktree`. Git would use the
+    // current directory, which means nothing for a long running picker.
+    let work_tree = match std::env::var_os("GIT_WORK_TREE") {
+        Some(work_tree) => PathBuf::from(work_tree),
+        None => repo.workdir()?.to_path_buf(),
+    };
+    let work_tree = crate::path_utils::canonicalize(&work_tree).ok()?;
+
+    if !path.starts_with(&work_tree) {
+        debug!(
+            ?path,
+            ?work_tree,
+            "Path is outside of GIT_WORK_TREE, ignoring GIT_DIR"
+        );
+        return None;
+    }
+
+    if repo.workdir() != Some(work_tree.as_path()) {
+        repo.set_workdir(&work_tree, false).ok()?;
+    }
+
+    Some(repo)
+}
+
+/// Walks up from `path` to the nearest `.git`. When it is a gitlink file
+/// pointing at a worktree git dir (one that has a `commondir` file), returns
+/// that git dir. Submodules also use gitlink files but have no `commondir`.
+fn find_linked_worktree_git_dir(path: &Path) -> Option<PathBuf> {
+    for dir in path.ancestors() {
+        let dot_git = dir.join(".git");
+        if dot_git.is_dir() {
+            return None;
+        }
+
+        if dot_git.is_file() {
+            let content = std::fs::read_to_string(&dot_git).ok()?;
+            let target = content.trim().strip_prefix("gitdir:")?.trim();
+            let git_dir = dir.join(target);
+            return git_dir.join("commondir").is_file().then_some(git_dir);
+        }
+    }
+
+    None
+}
+
 /// Represents a cache of a single git status query, if there is no
 /// status aka file is clear but it was specifically requested to updated
 /// the status is `None` otherwise contains only actual file statuses.
@@ -26,11 +296,25 @@ impl GitStatusCache {
         self.0.len()
     }
 
+    /// Keep the statuses `keep` returns true for, preserving the order.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Path, Status) -> bool) {
+        self.0.retain(|(path, status)| keep(path, *status));
+    }
+
     pub fn lookup_status(&self, full_path: &Path) -> Option<Status> {
-        self.0
+        match self
+            .0
             .binary_search_by(|(path, _)| path.as_path().cmp(full_path))
-            .ok()
-            .and_then(|idx| self.0.get(idx).map(|(_, status)| *status))
+        {
+            Ok(idx) => self.0.get(idx).map(|(_, status)| *status),
+            // nothing is reported inside an untracked directory, so if one
+            // contains the file it is the closest preceding entry
+            Err(idx) => idx
+                .checked_sub(1)
+                .and_then(|idx| self.0.get(idx))
+                .filter(|(path, status)| *status == Status::WT_NEW && full_path.starts_with(path))
+                .map(|(_, status)| *status),
+        }
     }
 
     #[tracing::instrument(skip(repo, status_options))]
@@ -53,15 +337,26 @@ impl GitStatusCache {
 
     pub fn read_git_status(
         git_workdir: Option<&Path>,
-        status_options: &mut StatusOptions,
+        query: StatusQuery,
+        provider: GitStatusProvider,
     ) -> Option<Self> {
         let git_workdir = git_workdir.as_ref()?;
-        let repository = Repository::open(git_workdir).ok()?;
-
-        let status = Self::read_status_impl(&repository, status_options);
+        let status = if provider.uses_cli(git_workdir) {
+            GitCliBackend.read_status(git_workdir, query).or_else(|e| {
+                // e.g. git is not installed, the library still gives a status
+                tracing::warn!(
+                    ?e,
+                    "git status failed, falling back to {}",
+                    backend().name()
+                );
+                backend().read_status(git_workdir, query)
+            })
+        } else {
+            backend().read_status(git_workdir, query)
+        };
 
         match status {
-            Ok(status) => Some(status),
+            Ok(status) => Some(Self(status)),
             Err(e) => {
                 tracing::error!(?e, "Failed to read git status");
 
@@ -114,6 +409,87 @@ impl GitStatusCache {
     }
 }
 
+/// The lines of the work tree that differ from `HEAD`: the new side of every
+/// uncommitted hunk, staged or not. Untracked files are changed as a whole.
+#[derive(Debug, Clone, Default)]
+pub struct ChangedLines {
+    /// Full path to the 1-based, inclusive line ranges of its hunks, in order.
+    files: HashMap<PathBuf, Vec<(u64, u64)>>,
+}
+
+impl ChangedLines {
+    /// Diffs `paths`, full paths below `workdir`, against `HEAD`. Only the
+    /// given files are diffed, pass the ones with a modified git status
+    /// rather than the whole index.
+    pub fn read<TPath: AsRef<Path>>(workdir: &Path, paths: &[TPath]) -> Result<Self> {
+        let mut files: HashMap<PathBuf, Vec<(u64, u64)>> = HashMap::new();
+        if paths.is_empty() {
+            return Ok(Self { files });
+        }
+
+        let repo = open_repository(workdir)?;
+        // an unborn branch has no HEAD, every file is new
+        let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+
+        let mut options = DiffOptions::new();
+        options
+            .context_lines(0)
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true)
+            .disable_pathspec_match(true);
+        for path in paths {
+            options.pathspec(path.as_ref().strip_prefix(workdir)?);
+        }
+
+        let diff = repo.diff_tree_to_workdir_with_index(head.as_ref(), Some(&mut options))?;
+        diff.foreach(
+            &mut |_, _| true,
+            None,
+            Some(&mut |delta, hunk| {
+                if hunk.new_lines() > 0
+                    && let Some(path) = delta.new_file().path()
+                {
+                    let start = u64::from(hunk.new_start());
+                    files
+                        .entry(workdir.join(path))
+                        .or_default()
+                        .push((start, start + u64::from(hunk.new_lines()) - 1));
+                }
+                true
+            }),
+            None,
+        )?;
+
+        debug!(files = files.len(), "Read changed lines");
+        Ok(Self { files })
+    }
+
+    /// Number of files with changed lines.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn contains_file(&self, full_path: &Path) -> bool {
+        self.files.contains_key(full_path)
+    }
+
+    /// Whether the 1-based `line_number` of the file is part of a hunk.
+    pub fn contains(&self, full_path: &Path, line_number: u64) -> bool {
+        let Some(ranges) = self.files.get(full_path) else {
+            return false;
+        };
+        let after = ranges.partition_point(|&(_, end)| end < line_number);
+        ranges
+            .get(after)
+            .is_some_and(|&(start, _)| start <= line_number)
+    }
+}
+
 #[inline]
 pub fn is_modified_status(status: Status) -> bool {
     status.intersects(
@@ -157,3 +533,330 @@ pub fn format_git_status_opt(status: Option<Status>) -> Option<&'static str> {
 pub fn format_git_status(status: Option<Status>) -> &'static str {
     format_git_status_opt(status).unwrap_or("unknown")
 }
+
+/// Compact `git status --short` style indicator: `M`, `A`, `D`, `R`, `U`
+/// (conflicted), `??` (untracked), `!!` (ignored), or an empty string for
+/// clean files.
+pub fn format_git_status_short(status: Option<Status>) -> &'static str {
+    let Some(status) = status else {
+        return "";
+    };
+
+    if status.contains(Status::CONFLICTED) {
+        "U"
+    } else if status.contains(Status::WT_NEW) {
+        "??"
+    } else if status.contains(Status::INDEX_NEW) {
+        "A"
+    } else if status.intersects(Status::WT_RENAMED | Status::INDEX_RENAMED) {
+        "R"
+    } else if status.intersects(Status::WT_DELETED | Status::INDEX_DELETED) {
+        "D"
+    } else if status.intersects(
+        Status::WT_MODIFIED
+            | Status::INDEX_MODIFIED
+            | Status::WT_TYPECHANGE
+            | Status::INDEX_TYPECHANGE,
+    ) {
+        "M"
+    } else if status.contains(Status::IGNORED) {
+        "!!"
+    } else {
+        ""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_picker::{FilePicker, FilePickerOptions};
+    use crate::grep::{GrepMode, parse_grep_query};
+    use crate::test_fixtures::{grep_options, initial_commit, picker_options, scan, write_files};
+    use git2::Repository;
+    use std::fs;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn short_status_matches_porcelain_letters() {
+        assert_eq!(format_git_status_short(None), "");
+        assert_eq!(format_git_status_short(Some(Status::CURRENT)), "");
+        assert_eq!(format_git_status_short(Some(Status::WT_NEW)), "??");
+        assert_eq!(format_git_status_short(Some(Status::INDEX_NEW)), "A");
+        assert_eq!(
+            format_git_status_short(Some(Status::INDEX_NEW | Status::WT_MODIFIED)),
+            "A"
+        );
+        assert_eq!(format_git_status_short(Some(Status::INDEX_RENAMED)), "R");
+        assert_eq!(format_git_status_short(Some(Status::WT_DELETED)), "D");
+        assert_eq!(format_git_status_short(Some(Status::WT_MODIFIED)), "M");
+        assert_eq!(
+            format_git_status_short(Some(Status::CONFLICTED | Status::WT_MODIFIED)),
+            "U"
+        );
+        assert_eq!(format_git_status_short(Some(Status::IGNORED)), "!!");
+    }
+
+    #[test]
+    fn lookup_covers_files_of_untracked_directories() {
+        let cache = GitStatusCache(vec![
+            (PathBuf::from("/repo/a.rs"), Status::WT_MODIFIED),
+            (PathBuf::from("/repo/new"), Status::WT_NEW),
+            (PathBuf::from("/repo/new.rs"), Status::INDEX_NEW),
+            (PathBuf::from("/repo/src/lib.rs"), Status::WT_MODIFIED),
+        ]);
+
+        let lookup = |path: &str| cache.lookup_status(Path::new(path));
+        assert_eq!(lookup("/repo/new/deep/file.rs"), Some(Status::WT_NEW));
+        assert_eq!(lookup("/repo/new.rs"), Some(Status::INDEX_NEW));
+        assert_eq!(lookup("/repo/a.rs/x"), None);
+        assert_eq!(lookup("/repo/src/main.rs"), None);
+        assert_eq!(lookup("/repo/newer.rs"), None);
+    }
+
+    /// Creates `<tmp>/main` with one commit and a linked worktree at `<tmp>/wt`.
+    fn worktree_setup() -> (TempDir, PathBuf, PathBuf) {
+        let tmp = TempDir::new().unwrap();
+        let root = fs::canonicalize(tmp.path()).unwrap();
+        let main = root.join("main");
+        let worktree = root.join("wt");
+        write_files(
+            &main,
+            &[("src/lib.rs", "pub fn lib() {}"), ("README.md", "# readme")],
+        );
+
+        let repo = Repository::init(&main).unwrap();
+        initial_commit(&repo);
+        repo.worktree("wt", &worktree, None).unwrap();
+        (tmp, main, worktree)
+    }
+
+    fn git_status_of(picker: &FilePicker, path: &Path) -> Option<Status> {
+        picker
+            .get_files()
+            .iter()
+            .find(|f| f.path() == path)
+            .unwrap_or_else(|| panic!("{} is not indexed", path.display()))
+            .git_status
+    }
+
+    #[test]
+    fn discover_resolves_linked_worktree() {
+        let (_tmp, main, worktree) = worktree_setup();
+
+        let location = GitRepoLocation::discover(&worktree.join("src")).unwrap();
+        assert!(location.is_worktree);
+        assert_eq!(location.workdir, worktree.join(""));
+        assert!(location.git_dir.starts_with(main.join(".git/worktrees")));
+        assert_eq!(location.common_dir, main.join(".git/"));
+
+        let location = GitRepoLocation::discover(&main).unwrap();
+        assert!(!location.is_worktree);
+        assert_eq!(location.workdir, main.join(""));
+        assert_eq!(location.git_dir, location.common_dir);
+    }
+
+    #[test]
+    fn worktree_status_is_read_from_its_own_index() {
+        let (_tmp, main, worktree) = worktree_setup();
+
+        fs::write(worktree.join("src/lib.rs"), "pub fn changed() {}").unwrap();
+        fs::write(main.join("README.md"), "# changed in main only").unwrap();
+
+        let picker = scan(picker_options(&worktree));
+
+        assert_eq!(picker.git_root(), Some(worktree.join("").as_path()));
+        assert!(
+            git_status_of(&picker, &worktree.join("src/lib.rs"))
+                .is_some_and(|status| status.contains(Status::WT_MODIFIED))
+        );
+        assert_eq!(git_status_of(&picker, &worktree.join("README.md")), None);
+    }
+
+    /// A repository with modified, deleted, staged and untracked files.
+    fn changed_repo() -> (TempDir, PathBuf) {
+        let tmp = TempDir::new().unwrap();
+        let root = fs::canonicalize(tmp.path()).unwrap();
+        let tracked = [
+            "clean.rs",
+            "modified.rs",
+            "deleted.rs",
+            "staged.rs",
+            "with space.rs",
+        ];
+        for name in tracked {
+            write_files(&root, &[(&format!("src/{name}"), name)]);
+        }
+        let repo = Repository::init(&root).unwrap();
+        initial_commit(&repo);
+
+        write_files(
+            &root,
+            &[
+                ("src/modified.rs", "changed"),
+                ("src/with space.rs", "changed"),
+                ("src/staged.rs", "staged change"),
+                ("untracked/dir/file.rs", ""),
+            ],
+        );
+        fs::remove_file(root.join("src/deleted.rs")).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("src/staged.rs")).unwrap();
+        index.write().unwrap();
+
+        (tmp, root)
+    }
+
+    #[test]
+    fn cli_statuses_match_libgit2() {
+        let (_tmp, root) = changed_repo();
+
+        for query in [
+            StatusQuery {
+                include_untracked: true,
+                exclude_submodules: true,
+                ..Default::default()
+            },
+            StatusQuery {
+                include_untracked: true,
+                include_unmodified: true,
+                exclude_submodules: true,
+            },
+            StatusQuery::default(),
+        ] {
+            assert_eq!(
+                GitCliBackend.read_status(&root, query).unwrap(),
+                Libgit2Backend.read_status(&root, query).unwrap(),
+                "{query:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn auto_provider_follows_the_repository_caches() {
+        let (_tmp, root) = changed_repo();
+        assert!(!GitStatusProvider::Auto.uses_cli(&root));
+        assert!(GitStatusProvider::Cli.uses_cli(&root));
+
+        Repository::open(&root)
+            .unwrap()
+            .config()
+            .unwrap()
+            .set_bool("core.untrackedCache", true)
+            .unwrap();
+        assert!(GitStatusProvider::Auto.uses_cli(&root));
+        assert!(!GitStatusProvider::Library.uses_cli(&root));
+
+        assert_eq!(
+            "auto".parse::<GitStatusProvider>().unwrap(),
+            GitStatusProvider::Auto
+        );
+        assert!("svn".parse::<GitStatusProvider>().is_err());
+    }
+
+    #[test]
+    fn untracked_cache_reports_untracked_directories_whole() {
+        let (_tmp, root) = changed_repo();
+        Repository::open(&root)
+            .unwrap()
+            .config()
+            .unwrap()
+            .set_bool("core.untrackedCache", true)
+            .unwrap();
+
+        let query = StatusQuery {
+            include_untracked: true,
+            exclude_submodules: true,
+            ..Default::default()
+        };
+        let statuses = GitCliBackend.read_status(&root, query).unwrap();
+        assert!(statuses.contains(&(root.join("untracked"), Status::WT_NEW)));
+
+        let cache =
+            GitStatusCache::read_git_status(Some(&root), query, GitStatusProvider::Cli).unwrap();
+        assert_eq!(
+            cache.lookup_status(&root.join("untracked/dir/file.rs")),
+            Some(Status::WT_NEW)
+        );
+
+        let picker = scan(FilePickerOptions {
+            git_status_provider: GitStatusProvider::Auto,
+            ..picker_options(&root)
+        });
+        let file = picker
+            .get_files()
+            .iter()
+            .find(|file| file.path().ends_with("untracked/dir/file.rs"))
+            .unwrap();
+        assert_eq!(file.git_status, Some(Status::WT_NEW));
+    }
+
+    #[test]
+    fn grep_keeps_only_the_lines_of_uncommitted_hunks() {
+        let tmp = TempDir::new().unwrap();
+        let root = fs::canonicalize(tmp.path()).unwrap();
+        write_files(
+            &root,
+            &[
+                ("lib.rs", "fn a() {\n    dbg!(1);\n}\n\nfn b() {}\n"),
+                ("clean.rs", "fn c() {\n    dbg!(2);\n}\n"),
+                ("staged.rs", "fn d() {}\n"),
+            ],
+        );
+        let repo = Repository::init(&root).unwrap();
+        initial_commit(&repo);
+
+        write_files(
+            &root,
+            &[
+                (
+                    "lib.rs",
+                    "fn a() {\n    dbg!(1);\n}\n\nfn b() {\n    dbg!(3);\n}\n",
+                ),
+                ("staged.rs", "fn d() {\n    dbg!(4);\n}\n"),
+            ],
+        );
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("staged.rs")).unwrap();
+        index.write().unwrap();
+        write_files(&root, &[("new.rs", "fn e() {\n    dbg!(5);\n}\n")]);
+
+        let picker = scan(picker_options(&root));
+        let changed = picker.changed_lines().unwrap().unwrap();
+        assert_eq!(changed.len(), 3);
+        assert!(!changed.contains_file(&root.join("clean.rs")));
+        assert!(changed.contains(&root.join("lib.rs"), 6));
+        assert!(!changed.contains(&root.join("lib.rs"), 2));
+        assert!(changed.contains(&root.join("new.rs"), 1));
+
+        let query = parse_grep_query("dbg!");
+        for mode in [GrepMode::PlainText, GrepMode::Fuzzy] {
+            let mut options = grep_options(mode);
+            assert!(picker.grep(&query, &options).matches.len() >= 5);
+
+            options.changed_lines = Some(Arc::new(changed.clone()));
+            let result = picker.grep(&query, &options);
+            let mut found: Vec<(String, u64)> = result
+                .matches
+                .iter()
+                .map(|m| {
+                    (
+                        result.files[m.file_index].relative_path().to_string(),
+                        m.line_number,
+                    )
+                })
+                .collect();
+            found.sort();
+            assert_eq!(
+                found,
+                [
+                    ("lib.rs".to_string(), 6),
+                    ("new.rs".to_string(), 2),
+                    ("staged.rs".to_string(), 2),
+                ],
+                "{mode:?}"
+            );
+            assert_eq!(result.filtered_file_count, 3);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! [`GitBackend`] reading statuses from `git status --porcelain=v2`. Unlike
+//! libgit2 the git CLI uses `core.fsmonitor` and the untracked cache, which
+//! makes it much faster on large repositories that enable them.
+
+use super::{
+    GitBackend, GitRepoLocation, StatusQuery, backend, config_uses_git_caches, hides_untracked,
+    open_repository,
+};
+use crate::error::{Error, Result};
+use git2::Status;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GitCliBackend;
+
+impl GitBackend for GitCliBackend {
+    fn name(&self) -> &'static str {
+        "git-cli"
+    }
+
+    /// Discovery doesn't benefit from the CLI, it uses the library backend.
+    fn discover(&self, path: &Path) -> Option<GitRepoLocation> {
+        backend().discover(path)
+    }
+
+    fn read_status(&self, workdir: &Path, query: StatusQuery) -> Result<Vec<(PathBuf, Status)>> {
+        let repository = open_repository(workdir)?;
+        let Some(repo_path) = repository.workdir().map(Path::to_path_buf) else {
+            return Ok(vec![]); // repo is bare
+        };
+        let config = repository.config()?;
+        let show_untracked_files = config.get_string("status.showUntrackedFiles").ok();
+        let include_untracked =
+            query.include_untracked && !hides_untracked(show_untracked_files.as_deref());
+        // git only consults the untracked cache when it is allowed to report
+        // untracked directories as a whole, unless configured to list all
+        let untracked_files = match include_untracked {
+            false => "--untracked-files=no",
+            true if config_uses_git_caches(&config)
+                && show_untracked_files.as_deref() != Some("all") =>
+            {
+                "--untracked-files=normal"
+            }
+            true => "--untracked-files=all",
+        };
+
+        let mut command = Command::new("git");
+        // a background status must not take index.lock from under the user's
+        // own git commands
+        command
+            .arg("--no-optional-locks")
+            .arg("-C")
+            .arg(&repo_path)
+            .args(["status", "--porcelain=v2", "-z", "--no-renames"])
+            .arg(untracked_files);
+        if query.exclude_submodules {
+            command.arg("--ignore-submodules=all");
+        }
+
+        let output = command
+            .output()
+            .map_err(|e| Error::GitCli(format!("failed to run git: {e}")))?;
+        if !output.status.success() {
+            return Err(Error::GitCli(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        let mut entries: Vec<(PathBuf, Status)> = parse_porcelain_v2(&output.stdout)
+            .map(|(path, status)| (repo_path.join(path), status))
+            .collect();
+
+        if query.include_unmodified {
+            let index = repository.index()?;
+            for entry in index.iter() {
+                entries.push((repo_path.join(bytes_to_path(&entry.path)), Status::CURRENT));
+            }
+        }
+
+        // `CURRENT` is the empty set, merging keeps only the real changes
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        entries.dedup_by(|(path, status), (kept_path, kept_status)| {
+            let same = path == kept_path;
+            if same {
+                *kept_status |= *status;
+            }
+            same
+        });
+
+        Ok(entries)
+    }
+}
+
+#[cfg(unix)]
+fn bytes_to_path(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn bytes_to_path(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).as_ref())
+}
+
+/// Flags of the `XY` field, `X` for the index and `Y` for the work tree.
+fn xy_status(xy: &[u8]) -> Status {
+    let mut status = Status::CURRENT;
+    status |= match xy.first() {
+        Some(b'M') => Status::INDEX_MODIFIED,
+        Some(b'T') => Status::INDEX_TYPECHANGE,
+        Some(b'A') | Some(b'C') => Status::INDEX_NEW,
+        Some(b'D') => Status::INDEX_DELETED,
+        Some(b'R') => Status::INDEX_RENAMED,
+        _ => Status::CURRENT,
+    };
+    status |= match xy.get(1) {
+        Some(b'M') => Status::WT_MODIFIED,
+        Some(b'T') => Status::WT_TYPECHANGE,
+        Some(b'A') => Status::WT_NEW,
+        Some(b'D') => Status::WT_DELETED,
+        Some(b'R') => Status::WT_RENAMED,
+        _ => Status::CURRENT,
+    };
+    status
+}
+
+/// Repository relative paths and statuses of `git status --porcelain=v2 -z`.
+/// Collapsed untracked directories are reported as `dir/`.
+fn parse_porcelain_v2(output: &[u8]) -> impl Iterator<Item = (PathBuf, Status)> + '_ {
+    let mut records = output.split(|&b| b == 0).filter(|r| !r.is_empty());
+    std::iter::from_fn(move || {
+        loop {
+            let record = records.next()?;
+            // fields before the path, which may itself contain spaces
+            let fields = match record[0] {
+                b'?' => return Some((bytes_to_path(&record[2..]), Status::WT_NEW)),
+                b'!' => return Some((bytes_to_path(&record[2..]), Status::IGNORED)),
+                b'1' => 8,
+                b'2' => 9,
+                b'u' => 10,
+                _ => continue, // `#` headers
+            };
+
+            let mut parts = record.splitn(fields + 1, |&b| b == b' ');
+            let xy = parts.nth(1).unwrap_or_default();
+            let Some(path) = parts.nth(fields - 2) else {
+                continue;
+            };
+            if record[0] == b'2' {
+                records.next(); // the rename source
+            }
+
+            let status = match record[0] {
+                b'u' => Status::CONFLICTED,
+                _ => xy_status(xy),
+            };
+            return Some((bytes_to_path(path), status));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_porcelain_v2_records() {
+        let output = b"# branch.oid abc\0\
+            1 .M N... 100644 100644 100644 aaa aaa src/main.rs\0\
+            1 A. N... 000000 100644 100644 000 bbb new file.rs\0\
+            1 MD N... 100644 100644 000000 ccc ddd gone.rs\0\
+            2 R. N... 100644 100644 100644 eee eee R100 to.rs\0from.rs\0\
+            u UU N... 100644 100644 100644 100644 f1 f2 f3 conflict.rs\0\
+            ? untracked/dir/file.rs\0\
+            ? collapsed/\0";
+
+        let parsed: Vec<(PathBuf, Status)> = parse_porcelain_v2(output).collect();
+        assert_eq!(
+            parsed,
+            vec![
+                (PathBuf::from("src/main.rs"), Status::WT_MODIFIED),
+                (PathBuf::from("new file.rs"), Status::INDEX_NEW),
+                (
+                    PathBuf::from("gone.rs"),
+                    Status::INDEX_MODIFIED | Status::WT_DELETED
+                ),
+                (PathBuf::from("to.rs"), Status::INDEX_RENAMED),
+                (PathBuf::from("conflict.rs"), Status::CONFLICTED),
+                (PathBuf::from("untracked/dir/file.rs"), Status::WT_NEW),
+                (PathBuf::from("collapsed"), Status::WT_NEW),
+            ]
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! gitoxide implementation of [`GitBackend`], for platforms where libgit2
+//! fails to build.
+
+use super::{GitBackend, GitRepoLocation, StatusQuery, hides_untracked};
+use crate::error::{Error, Result};
+use git2::Status;
+use gix::diff::index::ChangeRef;
+use gix::status::index_worktree::Item as WorktreeItem;
+use gix::status::plumbing::index_as_worktree::{Change, EntryStatus};
+use gix::status::tree_index::TrackRenames;
+use gix::status::{Submodule, UntrackedFiles};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GixBackend;
+
+fn gix_error(e: impl std::error::Error + Send + Sync + 'static) -> Error {
+    Error::Gix(Box::new(e))
+}
+
+/// Honors `GIT_DIR` / `GIT_WORK_TREE` while `path` lies inside that work
+/// tree, like [`super::open_repository`], otherwise discovers from `path`.
+fn open(path: &Path) -> Option<gix::Repository> {
+    if std::env::var_os("GIT_DIR").is_some()
+        && let Ok(repo) = gix::discover_with_environment_overrides(path)
+        && repo.workdir().is_some_and(|workdir| {
+            crate::path_utils::canonicalize(workdir).is_ok_and(|workdir| path.starts_with(workdir))
+        })
+    {
+        return Some(repo);
+    }
+
+    gix::discover(path).ok()
+}
+
+/// The libgit2 flag of a status item, `None` for items libgit2 doesn't report.
+fn item_status(item: &gix::status::Item) -> Option<Status> {
+    let status = match item {
+        gix::status::Item::TreeIndex(change) => match change {
+            ChangeRef::Addition { .. } => Status::INDEX_NEW,
+            ChangeRef::Deletion { .. } => Status::INDEX_DELETED,
+            ChangeRef::Modification { .. } => Status::INDEX_MODIFIED,
+            ChangeRef::Rewrite { .. } => Status::INDEX_RENAMED,
+        },
+        gix::status::Item::IndexWorktree(item) => match item {
+            WorktreeItem::Modification { status, .. } => match status {
+                EntryStatus::Conflict { .. } => Status::CONFLICTED,
+                EntryStatus::IntentToAdd => Status::WT_NEW,
+                EntryStatus::NeedsUpdate(_) => return None,
+                EntryStatus::Change(Change::Removed) => Status::WT_DELETED,
+                EntryStatus::Change(Change::Type { .. }) => Status::WT_TYPECHANGE,
+                EntryStatus::Change(Change::Modification { .. })
+                | EntryStatus::Change(Change::SubmoduleModification(_)) => Status::WT_MODIFIED,
+            },
+            WorktreeItem::DirectoryContents { entry, .. } => match entry.status {
+                gix::dir::entry::Status::Untracked => Status::WT_NEW,
+                _ => return None,
+            },
+            WorktreeItem::Rewrite { .. } => Status::WT_RENAMED,
+        },
+    };
+
+    Some(status)
+}
+
+impl GitBackend for GixBackend {
+    fn name(&self) -> &'static str {
+        "gitoxide"
+    }
+
+    fn discover(&self, path: &Path) -> Option<GitRepoLocation> {
+        let repo = open(path)?;
+        Some(GitRepoLocation {
+            workdir: repo.workdir()?.to_path_buf(),
+            git_dir: repo.git_dir().to_path_buf(),
+            common_dir: repo.common_dir().to_path_buf(),
+            is_worktree: matches!(
+                repo.kind(),
+                gix::repository::Kind::WorkTree { is_linked: true }
+            ),
+        })
+    }
+
+    fn read_status(&self, workdir: &Path, query: StatusQuery) -> Result<Vec<(PathBuf, Status)>> {
+        let repo = open(workdir).ok_or_else(|| Error::InvalidPath(workdir.to_path_buf()))?;
+        let Some(repo_path) = repo.workdir().map(Path::to_path_buf) else {
+            return Ok(vec![]); // repo is bare
+        };
+
+        let show_untracked_files = repo
+            .config_snapshot()
+            .string("status.showUntrackedFiles")
+            .map(|value| value.to_string());
+        let untracked_files =
+            if query.include_untracked && !hides_untracked(show_untracked_files.as_deref()) {
+                UntrackedFiles::Files
+            } else {
+                UntrackedFiles::None
+            };
+
+        // libgit2 reports renames as a deletion and an addition unless asked
+        let mut platform = repo
+            .status(gix::progress::Discard)
+            .map_err(gix_error)?
+            .untracked_files(untracked_files)
+            .index_worktree_rewrites(None)
+            .tree_index_track_renames(TrackRenames::Disabled);
+        if query.exclude_submodules {
+            platform = platform.index_worktree_submodules(Submodule::Given {
+                ignore: gix::submodule::config::Ignore::All,
+                check_dirty: false,
+            });
+        }
+
+        let mut entries = Vec::new();
+        for item in platform.into_iter(None).map_err(gix_error)? {
+            let item = item.map_err(gix_error)?;
+            if let Some(status) = item_status(&item) {
+                let path = gix::path::from_bstr(item.location());
+                entries.push((repo_path.join(path), status));
+            }
+        }
+
+        if query.include_unmodified {
+            let index = repo.index_or_empty().map_err(gix_error)?;
+            for entry in index.entries() {
+                let path = gix::path::from_bstr(entry.path(&index));
+                entries.push((repo_path.join(path), Status::CURRENT));
+            }
+        }
+
+        // index and work tree changes of a file arrive as separate items,
+        // `CURRENT` is the empty set so merging keeps only real changes
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        entries.dedup_by(|(path, status), (kept_path, kept_status)| {
+            let same = path == kept_path;
+            if same {
+                *kept_status |= *status;
+            }
+            same
+        });
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::Libgit2Backend;
+    use git2::{Repository, Signature};
+    use std::fs;
+
+    #[test]
+    fn statuses_match_libgit2() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let root = fs::canonicalize(tmp.path()).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        for name in ["clean.rs", "modified.rs", "deleted.rs", "staged.rs"] {
+            fs::write(root.join("src").join(name), name).unwrap();
+        }
+
+        let repo = Repository::init(&root).unwrap();
+        let mut index = repo.index().unwrap();
+        for name in ["clean.rs", "modified.rs", "deleted.rs", "staged.rs"] {
+            index.add_path(&Path::new("src").join(name)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("fff", "fff@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+
+        fs::write(root.join("src/modified.rs"), "changed").unwrap();
+        fs::remove_file(root.join("src/deleted.rs")).unwrap();
+        fs::write(root.join("src/staged.rs"), "staged change").unwrap();
+        fs::write(root.join("src/new.rs"), "new").unwrap();
+        fs::create_dir_all(root.join("untracked/dir")).unwrap();
+        fs::write(root.join("untracked/dir/file.rs"), "").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("src/staged.rs")).unwrap();
+        index.write().unwrap();
+
+        for query in [
+            StatusQuery {
+                include_untracked: true,
+                exclude_submodules: true,
+                ..Default::default()
+            },
+            StatusQuery {
+                include_untracked: true,
+                include_unmodified: true,
+                exclude_submodules: true,
+            },
+            StatusQuery::default(),
+        ] {
+            assert_eq!(
+                GixBackend.read_status(&root, query).unwrap(),
+                Libgit2Backend.read_status(&root, query).unwrap(),
+                "{query:?}"
+            );
+        }
+
+        let location = GixBackend.discover(&root.join("src")).unwrap();
+        assert_eq!(Some(location), Libgit2Backend.discover(&root.join("src")));
+    }
+}
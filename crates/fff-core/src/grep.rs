@@ -1,15 +1,20 @@
 //! High-performance grep engine for live content search.
 //!
 //! Searches file contents using the `grep-searcher` crate with mmap-backed
-//! file access. Files are searched in frecency order for optimal pagination
-//! performance — the most relevant files are searched first, enabling early
-//! termination once enough results are collected.
+//! file access. Files are searched in frecency order by default (see
+//! [`GrepFileOrder`]) for optimal pagination performance — the most relevant
+//! files are searched first, enabling early termination once enough results
+//! are collected.
 
 use crate::{
-    BigramFilter, BigramOverlay,
+    BigramFilter, BigramOverlay, code_scope,
     constraints::apply_constraints,
-    extract_bigrams,
+    crash, extract_bigrams,
+    file_list::FileList,
+    git::{ChangedLines, is_modified_status},
+    metrics::{self, SearchKind},
     sort_buffer::sort_with_buffer,
+    trigram_index::TrigramIndex,
     types::{ContentCacheBudget, FileItem},
 };
 use aho_corasick::AhoCorasick;
@@ -21,6 +26,10 @@ pub use fff_grep::{
 use fff_query_parser::{Constraint, FFFQuery, GrepConfig, QueryParser};
 use rayon::prelude::*;
 use smallvec::SmallVec;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tracing::Level;
 
@@ -251,6 +260,76 @@ pub enum GrepMode {
     Fuzzy,
 }
 
+/// Order in which files are searched, and therefore the order of results
+/// across pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GrepFileOrder {
+    /// Highest frecency first, most recently modified as a tie breaker.
+    #[default]
+    Frecency,
+    /// Most recently modified first.
+    Mtime,
+    /// Alphabetical by relative path.
+    Path,
+    /// Files with uncommitted changes first, each group in frecency order.
+    GitChangedFirst,
+}
+
+impl GrepFileOrder {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "frecency" => Some(Self::Frecency),
+            "mtime" => Some(Self::Mtime),
+            "path" => Some(Self::Path),
+            "git_changed_first" => Some(Self::GitChangedFirst),
+            _ => None,
+        }
+    }
+}
+
+/// Which part of the source a grep match has to be in. Comments and string
+/// literals are found by a lexer per language family, files of other
+/// languages are not filtered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GrepScope {
+    #[default]
+    All,
+    /// Outside of comments and string literals.
+    Code,
+    Comments,
+    Strings,
+}
+
+impl GrepScope {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "all" => Some(Self::All),
+            "code" => Some(Self::Code),
+            "comments" => Some(Self::Comments),
+            "strings" => Some(Self::Strings),
+            _ => None,
+        }
+    }
+}
+
+/// One row of a [`GrepResult`], see [`GrepResult::rows`].
+#[derive(Debug, Clone, Copy)]
+pub enum GrepRow<'r, 'a> {
+    /// A whole file listed by an empty query. It has no line.
+    FileEntry(&'a FileItem),
+    /// A line that matched the query, with the file it is in.
+    LineMatch(&'r GrepMatch, &'a FileItem),
+}
+
+impl GrepRow<'_, '_> {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GrepRow::FileEntry(_) => "file",
+            GrepRow::LineMatch(..) => "line",
+        }
+    }
+}
+
 /// A single content match within a file.
 #[derive(Debug, Clone)]
 pub struct GrepMatch {
@@ -268,6 +347,9 @@ pub struct GrepMatch {
     /// Byte offsets `(start, end)` within `line_content` for each match.
     /// Stack-allocated for the common case of ≤4 spans per line.
     pub match_byte_offsets: SmallVec<[(u32, u32); 4]>,
+    /// Spans of the capture groups of a regex pattern within `line_content`,
+    /// for every match of the line. Empty in the other modes.
+    pub captures: Vec<CaptureSpan>,
     /// Fuzzy match score from neo_frizbee (only set in Fuzzy grep mode).
     pub fuzzy_score: Option<u16>,
     /// Whether the matched line looks like a definition (struct, fn, class, etc.).
@@ -277,6 +359,18 @@ pub struct GrepMatch {
     pub context_before: Vec<String>,
     /// Lines after the match (for context display). Empty when context is 0.
     pub context_after: Vec<String>,
+    /// Matches of the page with the same line folded into this one by
+    /// [`GrepSearchOptions::dedupe_lines`].
+    pub duplicates: u32,
+}
+
+/// The bytes of `line_content` a regex capture group matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureSpan {
+    /// 1-based index of the group in the pattern.
+    pub group: u32,
+    pub start: u32,
+    pub end: u32,
 }
 
 /// Result of a grep search.
@@ -285,6 +379,9 @@ pub struct GrepResult<'a> {
     pub matches: Vec<GrepMatch>,
     /// Deduplicated file references for the returned matches.
     pub files: Vec<&'a FileItem>,
+    /// Files listed by an empty query, highest frecency first (see
+    /// [`grep_search`]). Such a result has no `matches`.
+    pub file_entries: Vec<&'a FileItem>,
     /// Number of files actually searched in this call.
     pub total_files_searched: usize,
     /// Total number of indexed files (before filtering).
@@ -296,12 +393,79 @@ pub struct GrepResult<'a> {
     /// The file offset to pass for the next page. `0` if there are no more files.
     /// Callers should store this and pass it as `file_offset` in the next call.
     pub next_file_offset: usize,
+    /// Where the next page resumes, `None` on the last page. Unlike
+    /// `next_file_offset` it can point inside a file, see
+    /// [`GrepSearchOptions::exact_page_limit`].
+    pub next_page: Option<GrepPageToken>,
     /// When regex mode fails to compile the pattern, the search falls back to
     /// literal matching and this field contains the compilation error message.
     /// The UI can display this to inform the user their regex was invalid.
     pub regex_fallback_error: Option<String>,
 }
 
+impl<'a> GrepResult<'a> {
+    /// The file entries, then the line matches with their files.
+    pub fn rows(&self) -> impl Iterator<Item = GrepRow<'_, 'a>> {
+        let entries = self
+            .file_entries
+            .iter()
+            .map(|&file| GrepRow::FileEntry(file));
+        let matches = self
+            .matches
+            .iter()
+            .map(|m| GrepRow::LineMatch(m, self.files[m.file_index]));
+        entries.chain(matches)
+    }
+}
+
+/// A set of file indices (into the picker's file list) that a grep is
+/// restricted to, e.g. the files matched by a previous fuzzy search.
+#[derive(Debug, Clone, Default)]
+pub struct FileSubset {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl FileSubset {
+    pub fn from_indices(indices: impl IntoIterator<Item = usize>) -> Self {
+        let mut subset = Self::default();
+        for idx in indices {
+            let word = idx / 64;
+            if word >= subset.words.len() {
+                subset.words.resize(word + 1, 0);
+            }
+            let bit = 1u64 << (idx % 64);
+            if subset.words[word] & bit == 0 {
+                subset.words[word] |= bit;
+                subset.len += 1;
+            }
+        }
+        subset
+    }
+
+    #[inline]
+    pub fn contains(&self, idx: usize) -> bool {
+        self.words
+            .get(idx / 64)
+            .is_some_and(|word| word & (1u64 << (idx % 64)) != 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Clears every candidate bit that is not part of the subset.
+    fn intersect_candidates(&self, candidates: &mut [u64]) {
+        for (i, word) in candidates.iter_mut().enumerate() {
+            *word &= self.words.get(i).copied().unwrap_or(0);
+        }
+    }
+}
+
 /// Options for grep search.
 #[derive(Debug, Clone)]
 pub struct GrepSearchOptions {
@@ -326,6 +490,74 @@ pub struct GrepSearchOptions {
     /// Whether to classify each match as a definition line. Adds ~2% overhead
     /// on large repos; disable for interactive grep where it is not needed.
     pub classify_definitions: bool,
+    /// Order in which files are searched. Defaults to `Frecency`.
+    pub file_order: GrepFileOrder,
+    /// Only keep the matches in code, comments or strings. Defaults to `All`.
+    pub scope: GrepScope,
+    /// Only search these lines, usually the uncommitted hunks from
+    /// [`crate::FilePicker::changed_lines`]. Files without one are skipped.
+    pub changed_lines: Option<Arc<ChangedLines>>,
+    /// Skip the matches of the file at `file_offset` up to this line, set
+    /// with [`Self::resume_from`]. 0 = none.
+    pub resume_after_line: u64,
+    /// End the page at exactly `page_limit` matches, inside a file if needed,
+    /// instead of with the file that filled it. The next page has to resume
+    /// from [`GrepResult::next_page`] then.
+    pub exact_page_limit: bool,
+    /// Keep only the first of the matches of a page whose lines read the
+    /// same, e.g. license headers, counting the others in
+    /// [`GrepMatch::duplicates`]. Folds within one page only: a line shown
+    /// on a page comes back on the following ones when it matches there
+    /// too, the page token carries no record of the lines already seen.
+    pub dedupe_lines: bool,
+    /// A result previously opened for the same query, pinned to the top.
+    pub history_boost: Option<GrepHistoryBoost>,
+}
+
+impl GrepSearchOptions {
+    /// Continue a search at the page `token` points to.
+    pub fn resume_from(&mut self, token: GrepPageToken) {
+        self.file_offset = token.file_offset;
+        self.resume_after_line = token.after_line;
+    }
+}
+
+/// Where a page of grep results resumes: a file offset and the last line of
+/// that file already returned. Round-trips through a string as
+/// `"<file_offset>:<after_line>"` for the bindings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GrepPageToken {
+    pub file_offset: usize,
+    /// 0 when the page ended with the previous file.
+    pub after_line: u64,
+}
+
+impl std::fmt::Display for GrepPageToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file_offset, self.after_line)
+    }
+}
+
+impl std::str::FromStr for GrepPageToken {
+    type Err = String;
+
+    fn from_str(token: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid grep page token: {token}");
+        let (file_offset, after_line) = token.split_once(':').ok_or_else(invalid)?;
+        Ok(Self {
+            file_offset: file_offset.parse().map_err(|_| invalid())?,
+            after_line: after_line.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// A grep result the user opened before for the same query, usually from
+/// [`crate::QueryTracker::get_last_grep_entry`]. Its file is searched first
+/// and the remembered line is listed before the other matches of the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepHistoryBoost {
+    pub file_path: PathBuf,
+    pub line_number: u64,
 }
 
 #[derive(Clone, Copy)]
@@ -559,10 +791,12 @@ impl SinkState {
             byte_offset,
             line_content,
             match_byte_offsets,
+            captures: Vec::new(),
             fuzzy_score: None,
             is_definition,
             context_before,
             context_after,
+            duplicates: 0,
         });
     }
 
@@ -758,18 +992,31 @@ impl Sink for RegexSink<'_> {
 
         let line_content = String::from_utf8_lossy(display_bytes).into_owned();
         let mut match_byte_offsets: SmallVec<[(u32, u32); 4]> = SmallVec::new();
-        let mut col = 0usize;
-        let mut first = true;
+        let mut captures = Vec::new();
 
-        for m in self.re.find_iter(display_bytes) {
-            let abs_start = m.start() as u32;
-            let abs_end = (m.end() as u32).min(display_len);
-            if first {
-                col = abs_start as usize;
-                first = false;
+        if self.re.captures_len() > 1 {
+            for groups in self.re.captures_iter(display_bytes) {
+                let mut spans = groups.iter().enumerate().filter_map(|(group, span)| {
+                    let span = span?;
+                    Some((group as u32, span.start() as u32, span.end() as u32))
+                });
+                if let Some((_, start, end)) = spans.next() {
+                    match_byte_offsets.push((start, end.min(display_len)));
+                }
+                captures.extend(spans.map(|(group, start, end)| CaptureSpan {
+                    group,
+                    start,
+                    end: end.min(display_len),
+                }));
+            }
+        } else {
+            for m in self.re.find_iter(display_bytes) {
+                match_byte_offsets.push((m.start() as u32, (m.end() as u32).min(display_len)));
             }
-            match_byte_offsets.push((abs_start, abs_end));
         }
+        let col = match_byte_offsets
+            .first()
+            .map_or(0, |&(start, _)| start as usize);
 
         let (context_before, context_after) = self.state.extract_context(sink_match);
         self.state.push_match(
@@ -781,6 +1028,9 @@ impl Sink for RegexSink<'_> {
             context_before,
             context_after,
         );
+        if let Some(pushed) = self.state.matches.last_mut() {
+            pushed.captures = captures;
+        }
         Ok(true)
     }
 
@@ -874,7 +1124,7 @@ impl Sink for AhoCorasickSink<'_> {
 ///
 /// Returns the same `GrepResult` type as `grep_search`.
 pub fn multi_grep_search<'a>(
-    files: &'a [FileItem],
+    files: &'a FileList,
     patterns: &[&str],
     constraints: &[fff_query_parser::Constraint<'_>],
     options: &GrepSearchOptions,
@@ -892,14 +1142,14 @@ pub fn multi_grep_search<'a>(
     }
 
     let (mut files_to_search, mut filtered_file_count) =
-        prepare_files_to_search(files, constraints, options);
+        prepare_files_to_search(files, constraints, options, None);
 
     // If constraints yielded 0 files and we had FilePath constraints,
     // retry without them (the path token was likely part of the search text).
     if files_to_search.is_empty()
         && let Some(stripped) = strip_file_path_constraints(constraints)
     {
-        let (retry_files, retry_count) = prepare_files_to_search(files, &stripped, options);
+        let (retry_files, retry_count) = prepare_files_to_search(files, &stripped, options, None);
         files_to_search = retry_files;
         filtered_file_count = retry_count;
     }
@@ -981,7 +1231,7 @@ const fn is_utf8_char_boundary(b: u8) -> bool {
 /// - The input is passed directly to the regex engine without escaping
 /// - Smart case still applies
 /// - Returns `None` for invalid regex patterns — the caller falls back to literal mode
-fn build_regex(pattern: &str, smart_case: bool) -> Result<regex::bytes::Regex, String> {
+pub(crate) fn build_regex(pattern: &str, smart_case: bool) -> Result<regex::bytes::Regex, String> {
     if pattern.is_empty() {
         return Err("empty pattern".to_string());
     }
@@ -1006,6 +1256,55 @@ fn build_regex(pattern: &str, smart_case: bool) -> Result<regex::bytes::Regex, S
         .map_err(|e| e.to_string())
 }
 
+/// The `line:` constraints of a grep query. They are checked against the
+/// collected matches of each file, so dropped lines never count towards the
+/// page limit.
+struct LineFilter {
+    /// Each pattern with whether the matching lines are kept or dropped.
+    rules: Vec<(regex::bytes::Regex, bool)>,
+}
+
+impl LineFilter {
+    fn from_constraints(constraints: &[Constraint<'_>], smart_case: bool) -> Option<Self> {
+        let rules: Vec<_> = constraints
+            .iter()
+            .filter_map(|constraint| match constraint {
+                Constraint::Line(pattern) => Some((*pattern, true)),
+                Constraint::Not(inner) => match inner.as_ref() {
+                    Constraint::Line(pattern) => Some((*pattern, false)),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .map(|(pattern, keep)| (Self::line_regex(pattern, smart_case), keep))
+            .collect();
+
+        (!rules.is_empty()).then_some(Self { rules })
+    }
+
+    /// Patterns that are not a valid regex, like `line:foo(`, match literally.
+    fn line_regex(pattern: &str, smart_case: bool) -> regex::bytes::Regex {
+        let case_insensitive = smart_case && !pattern.chars().any(|c| c.is_uppercase());
+        let build = |pattern: &str| {
+            regex::bytes::RegexBuilder::new(pattern)
+                .case_insensitive(case_insensitive)
+                .unicode(false)
+                .build()
+        };
+        build(pattern)
+            .or_else(|_| build(&regex::escape(pattern)))
+            .expect("escaped pattern is a valid regex")
+    }
+
+    fn retain(&self, matches: &mut Vec<GrepMatch>) {
+        matches.retain(|m| {
+            self.rules
+                .iter()
+                .all(|(re, keep)| re.is_match(m.line_content.as_bytes()) == *keep)
+        });
+    }
+}
+
 /// Convert character-position indices from neo_frizbee into byte-offset
 /// pairs (start, end) suitable for `match_byte_offsets`.
 ///
@@ -1095,6 +1394,7 @@ where
     let mut all_matches: Vec<GrepMatch> = Vec::new();
     let mut files_consumed: usize = 0;
     let mut page_filled = false;
+    let mut resume_after_line = 0;
 
     for chunk in files_to_search.chunks(chunk_size) {
         let chunk_offset = files_consumed;
@@ -1137,7 +1437,17 @@ where
                     }
                 }
 
-                let file_matches = search_file(&content, options.max_matches_per_file);
+                let mut file_matches = search_file(&content, options.max_matches_per_file);
+                code_scope::retain_in_scope(
+                    options.scope,
+                    file.path(),
+                    &content,
+                    &mut file_matches,
+                );
+                if let Some(changed) = &options.changed_lines {
+                    file_matches.retain(|m| changed.contains(file.path(), m.line_number));
+                }
+                skip_resumed_lines(options, chunk_offset + local_idx, &mut file_matches);
 
                 if file_matches.is_empty() {
                     return None;
@@ -1151,7 +1461,8 @@ where
         files_consumed = chunk_offset + chunk.len();
 
         // Flatten this chunk's results into the accumulator.
-        for (batch_idx, file, file_matches) in chunk_results {
+        for (batch_idx, file, mut file_matches) in chunk_results {
+            let cut_at = cut_page(options, all_matches.len(), &mut file_matches);
             let file_result_idx = result_files.len();
             result_files.push(file);
 
@@ -1160,6 +1471,13 @@ where
                 all_matches.push(m);
             }
 
+            if let Some(line) = cut_at {
+                // The rest of this file goes to the next page.
+                files_consumed = batch_idx;
+                resume_after_line = line;
+                page_filled = true;
+                break;
+            }
             if all_matches.len() >= page_limit {
                 // Tighten files_consumed to the file that tipped us over so
                 // the next page resumes right after it.
@@ -1192,10 +1510,12 @@ where
         matches: all_matches,
         files_with_matches: result_files.len(),
         files: result_files,
+        file_entries: Vec::new(),
         total_files_searched: files_consumed,
         total_files: ctx.total_files,
         filtered_file_count: ctx.filtered_file_count,
         next_file_offset,
+        next_page: next_page(has_more, next_file_offset, resume_after_line),
         regex_fallback_error: None,
     }
 }
@@ -1226,12 +1546,14 @@ fn collect_grep_results<'a>(
     // files between them that had no matches were still searched and can be
     // safely skipped on the next page.
     let mut files_consumed: usize = 0;
+    let mut resume_after_line = 0;
 
-    for (batch_idx, file, file_matches) in per_file_results {
+    for (batch_idx, file, mut file_matches) in per_file_results {
         // batch_idx is the 0-based position in files_to_search.
         // Advance files_consumed to include this file and all no-match files before it.
         files_consumed = batch_idx + 1;
 
+        let cut_at = cut_page(options, all_matches.len(), &mut file_matches);
         let file_result_idx = result_files.len();
         result_files.push(file);
 
@@ -1240,6 +1562,12 @@ fn collect_grep_results<'a>(
             all_matches.push(m);
         }
 
+        if let Some(line) = cut_at {
+            files_consumed = batch_idx;
+            resume_after_line = line;
+            break;
+        }
+
         // page_limit is a soft cap: we always finish the current file before
         // stopping, so no matches are dropped. A page may return up to
         // page_limit + max_matches_per_file - 1 matches in the worst case.
@@ -1266,25 +1594,165 @@ fn collect_grep_results<'a>(
         matches: all_matches,
         files_with_matches: result_files.len(),
         files: result_files,
+        file_entries: Vec::new(),
         total_files_searched: files_consumed,
         total_files,
         filtered_file_count,
         next_file_offset,
+        next_page: next_page(has_more, next_file_offset, resume_after_line),
         regex_fallback_error: None,
     }
 }
 
-/// Filter files by constraints and size/binary checks, sort by frecency,
-/// and apply file-based pagination.
+/// Drops the lines an earlier page of a resumed search already returned,
+/// they are in the first file searched.
+fn skip_resumed_lines(options: &GrepSearchOptions, position: usize, matches: &mut Vec<GrepMatch>) {
+    if position == 0 && options.resume_after_line > 0 {
+        matches.retain(|m| m.line_number > options.resume_after_line);
+    }
+}
+
+/// With [`GrepSearchOptions::exact_page_limit`], truncates the matches of a
+/// file to what fits on the page after `collected` matches. Returns the last
+/// line kept when the rest of the file did not fit.
+fn cut_page(
+    options: &GrepSearchOptions,
+    collected: usize,
+    matches: &mut Vec<GrepMatch>,
+) -> Option<u64> {
+    let room = options.page_limit.saturating_sub(collected);
+    // an empty page would never advance
+    if !options.exact_page_limit || room == 0 || matches.len() <= room {
+        return None;
+    }
+    matches.truncate(room);
+    matches.last().map(|m| m.line_number)
+}
+
+fn next_page(has_more: bool, file_offset: usize, after_line: u64) -> Option<GrepPageToken> {
+    has_more.then_some(GrepPageToken {
+        file_offset,
+        after_line,
+    })
+}
+
+/// Sort the files to search according to `order`.
+/// Moves the file of the history boost, if it is going to be searched, to
+/// the front. Done before pagination so every page agrees on the order.
+fn promote_history_file(files: &mut [&FileItem], boost: Option<&GrepHistoryBoost>) {
+    if let Some(boost) = boost
+        && let Some(pos) = files.iter().position(|f| f.path() == boost.file_path)
+    {
+        files[..=pos].rotate_right(1);
+    }
+}
+
+/// Lists the remembered line first when its file leads the results.
+fn promote_history_line(result: &mut GrepResult<'_>, boost: Option<&GrepHistoryBoost>) {
+    let Some(boost) = boost else {
+        return;
+    };
+    if result
+        .files
+        .first()
+        .is_none_or(|file| file.path() != boost.file_path)
+    {
+        return;
+    }
+    if let Some(pos) = result
+        .matches
+        .iter()
+        .position(|m| m.file_index == 0 && m.line_number == boost.line_number)
+    {
+        result.matches[..=pos].rotate_right(1);
+    }
+}
+
+/// Folds every match into the first one with the same trimmed line. Files
+/// left without a match are dropped from the result.
+fn dedupe_lines(result: &mut GrepResult<'_>) {
+    let mut first_with_line: HashMap<&str, usize> = HashMap::with_capacity(result.matches.len());
+    let folded_into: Vec<Option<usize>> = result
+        .matches
+        .iter()
+        .enumerate()
+        .map(
+            |(i, m)| match first_with_line.entry(m.line_content.trim()) {
+                Entry::Occupied(first) => Some(*first.get()),
+                Entry::Vacant(slot) => {
+                    slot.insert(i);
+                    None
+                }
+            },
+        )
+        .collect();
+    drop(first_with_line);
+
+    for &first in folded_into.iter().flatten() {
+        result.matches[first].duplicates += 1;
+    }
+    let mut folded = folded_into.iter();
+    result
+        .matches
+        .retain(|_| folded.next().is_some_and(Option::is_none));
+
+    let mut new_index = vec![usize::MAX; result.files.len()];
+    let mut files = Vec::with_capacity(result.files.len());
+    for m in &mut result.matches {
+        if new_index[m.file_index] == usize::MAX {
+            new_index[m.file_index] = files.len();
+            files.push(result.files[m.file_index]);
+        }
+        m.file_index = new_index[m.file_index];
+    }
+    result.files_with_matches = files.len();
+    result.files = files;
+}
+
+fn sort_files_to_search(files: &mut [&FileItem], order: GrepFileOrder) {
+    let by_frecency = |a: &&FileItem, b: &&FileItem| {
+        b.total_frecency_score
+            .cmp(&a.total_frecency_score)
+            .then(b.modified.cmp(&a.modified))
+    };
+
+    match order {
+        GrepFileOrder::Frecency => {
+            // Only sort when there is meaningful frecency or modification data to rank by.
+            // On large repos (500k+ files) with no frecency data (fresh session, benchmark),
+            // skipping the O(n log n) sort saves ~200ms per query.
+            let needs_sort = files
+                .iter()
+                .any(|f| f.total_frecency_score != 0 || f.modified != 0);
+            if needs_sort {
+                sort_with_buffer(files, by_frecency);
+            }
+        }
+        GrepFileOrder::Mtime => sort_with_buffer(files, |a, b| b.modified.cmp(&a.modified)),
+        GrepFileOrder::Path => {
+            sort_with_buffer(files, |a, b| a.relative_path().cmp(b.relative_path()))
+        }
+        GrepFileOrder::GitChangedFirst => sort_with_buffer(files, |a, b| {
+            let is_changed = |f: &FileItem| f.git_status.is_some_and(is_modified_status);
+            is_changed(b)
+                .cmp(&is_changed(a))
+                .then_with(|| by_frecency(a, b))
+        }),
+    }
+}
+
+/// Filter files by constraints and size/binary checks, sort by the requested
+/// file order, and apply file-based pagination.
 ///
 /// Returns `(paginated_files, filtered_file_count)`. The paginated slice
 /// is empty if the offset is past the end of available files.
 fn prepare_files_to_search<'a>(
-    files: &'a [FileItem],
+    files: &'a FileList,
     constraints: &[fff_query_parser::Constraint<'_>],
     options: &GrepSearchOptions,
+    subset: Option<&FileSubset>,
 ) -> (Vec<&'a FileItem>, usize) {
-    let prefiltered: Vec<&FileItem> = if constraints.is_empty() {
+    let mut prefiltered: Vec<&FileItem> = if constraints.is_empty() {
         files
             .iter()
             .filter(|f| !f.is_binary && f.size > 0 && f.size <= options.max_file_size)
@@ -1302,23 +1770,19 @@ fn prepare_files_to_search<'a>(
         }
     };
 
+    if let Some(changed) = &options.changed_lines {
+        prefiltered.retain(|f| changed.contains_file(f.path()));
+    }
+
+    if let Some(subset) = subset {
+        prefiltered.retain(|f| files.index_of(f).is_some_and(|idx| subset.contains(idx)));
+    }
+
     let total_count = prefiltered.len();
     let mut sorted_files = prefiltered;
 
-    // Only sort when there is meaningful frecency or modification data to rank by.
-    // On large repos (500k+ files) with no frecency data (fresh session, benchmark),
-    // skipping the O(n log n) sort saves ~200ms per query.
-    let needs_sort = sorted_files
-        .iter()
-        .any(|f| f.total_frecency_score != 0 || f.modified != 0);
-
-    if needs_sort {
-        sort_with_buffer(&mut sorted_files, |a, b| {
-            b.total_frecency_score
-                .cmp(&a.total_frecency_score)
-                .then(b.modified.cmp(&a.modified))
-        });
-    }
+    sort_files_to_search(&mut sorted_files, options.file_order);
+    promote_history_file(&mut sorted_files, options.history_boost.as_ref());
 
     if options.file_offset > 0 && options.file_offset < total_count {
         let paginated = sorted_files.split_off(options.file_offset);
@@ -1374,6 +1838,7 @@ fn fuzzy_grep_search<'a>(
     case_insensitive: bool,
     budget: &ContentCacheBudget,
     is_cancelled: Option<&AtomicBool>,
+    line_filter: Option<&LineFilter>,
 ) -> GrepResult<'a> {
     // max_typos controls how many *needle* characters can be unmatched.
     // A transposition (e.g. "shcema" → "schema") costs ~1 typo with
@@ -1636,9 +2101,11 @@ fn fuzzy_grep_search<'a>(
                             && is_definition_line(display_line),
                         line_content: display_line.to_string(),
                         match_byte_offsets,
+                        captures: Vec::new(),
                         fuzzy_score: Some(match_indices.score),
                         context_before: Vec::new(),
                         context_after: Vec::new(),
+                        duplicates: 0,
                     });
 
                     if max_matches_per_file != 0 && file_matches.len() >= max_matches_per_file {
@@ -1646,6 +2113,19 @@ fn fuzzy_grep_search<'a>(
                     }
                 }
 
+                if let Some(filter) = line_filter {
+                    filter.retain(&mut file_matches);
+                }
+                code_scope::retain_in_scope(
+                    options.scope,
+                    file.path(),
+                    file_bytes,
+                    &mut file_matches,
+                );
+                if let Some(changed) = &options.changed_lines {
+                    file_matches.retain(|m| changed.contains(file.path(), m.line_number));
+                }
+                skip_resumed_lines(options, idx, &mut file_matches);
                 if file_matches.is_empty() {
                     return None;
                 }
@@ -1668,17 +2148,145 @@ fn fuzzy_grep_search<'a>(
 
 /// Perform a grep search across all indexed files.
 ///
-/// When `query` is empty, returns git-modified/untracked files sorted by
-/// frecency for the "welcome state" UI.
+/// An empty `query` lists the files worth starting from instead: the ones
+/// changed in git or opened before, by frecency, in
+/// [`GrepResult::file_entries`].
 #[tracing::instrument(skip(files, options, budget, bigram_index, bigram_overlay, is_cancelled), fields(file_count = files.len()))]
 pub fn grep_search<'a>(
-    files: &'a [FileItem],
+    files: &'a FileList,
+    query: &FFFQuery<'_>,
+    options: &GrepSearchOptions,
+    budget: &ContentCacheBudget,
+    bigram_index: Option<&BigramFilter>,
+    bigram_overlay: Option<&BigramOverlay>,
+    is_cancelled: Option<&AtomicBool>,
+) -> GrepResult<'a> {
+    grep_search_indexed(
+        files,
+        query,
+        options,
+        budget,
+        ContentIndexes {
+            bigram: bigram_index,
+            bigram_overlay,
+            trigram: None,
+        },
+        is_cancelled,
+        None,
+    )
+}
+
+/// Like [`grep_search`] but only searches the files whose indices are in
+/// `subset`. Sorting, pagination and `filtered_file_count` all apply to the
+/// subset, so paging through the results works the same way.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip_all, fields(file_count = files.len(), subset_len = subset.len()))]
+pub fn grep_search_in_subset<'a>(
+    files: &'a FileList,
     query: &FFFQuery<'_>,
     options: &GrepSearchOptions,
     budget: &ContentCacheBudget,
     bigram_index: Option<&BigramFilter>,
     bigram_overlay: Option<&BigramOverlay>,
     is_cancelled: Option<&AtomicBool>,
+    subset: &FileSubset,
+) -> GrepResult<'a> {
+    grep_search_indexed(
+        files,
+        query,
+        options,
+        budget,
+        ContentIndexes {
+            bigram: bigram_index,
+            bigram_overlay,
+            trigram: None,
+        },
+        is_cancelled,
+        Some(subset),
+    )
+}
+
+/// The content indexes a grep pre-selects its candidate files with.
+#[derive(Clone, Copy)]
+pub(crate) struct ContentIndexes<'i> {
+    pub bigram: Option<&'i BigramFilter>,
+    pub bigram_overlay: Option<&'i BigramOverlay>,
+    pub trigram: Option<&'i TrigramIndex>,
+}
+
+/// The rows of an empty query: files changed in git or with any frecency,
+/// highest frecency first.
+fn welcome_files<'a>(
+    files: &'a FileList,
+    options: &GrepSearchOptions,
+    subset: Option<&FileSubset>,
+) -> GrepResult<'a> {
+    let mut welcome: Vec<&FileItem> = files
+        .iter()
+        .enumerate()
+        .filter(|&(idx, _)| subset.is_none_or(|subset| subset.contains(idx)))
+        .map(|(_, file)| file)
+        .filter(|file| !file.is_deleted && !file.is_binary)
+        .filter(|file| {
+            file.access_frecency_score > 0 || file.git_status.is_some_and(is_modified_status)
+        })
+        .collect();
+    welcome.sort_by(|a, b| {
+        b.total_frecency_score
+            .cmp(&a.total_frecency_score)
+            .then_with(|| a.relative_path().cmp(b.relative_path()))
+    });
+
+    let filtered_file_count = welcome.len();
+    let page: Vec<&FileItem> = welcome
+        .into_iter()
+        .skip(options.file_offset)
+        .take(options.page_limit.max(1))
+        .collect();
+    let end = options.file_offset + page.len();
+    let next_file_offset = if end < filtered_file_count { end } else { 0 };
+
+    GrepResult {
+        file_entries: page,
+        total_files: files.len(),
+        filtered_file_count,
+        next_file_offset,
+        next_page: (next_file_offset > 0).then_some(GrepPageToken {
+            file_offset: next_file_offset,
+            after_line: 0,
+        }),
+        ..Default::default()
+    }
+}
+
+pub(crate) fn grep_search_indexed<'a>(
+    files: &'a FileList,
+    query: &FFFQuery<'_>,
+    options: &GrepSearchOptions,
+    budget: &ContentCacheBudget,
+    indexes: ContentIndexes<'_>,
+    is_cancelled: Option<&AtomicBool>,
+    subset: Option<&FileSubset>,
+) -> GrepResult<'a> {
+    let _crash_guard = crash::enter_search(SearchKind::Grep, query.raw_query, files.len());
+    let started = std::time::Instant::now();
+    let mut result = grep_search_impl(files, query, options, budget, indexes, is_cancelled, subset);
+    metrics::record(SearchKind::Grep, query.raw_query, started.elapsed());
+    promote_history_line(&mut result, options.history_boost.as_ref());
+    if options.dedupe_lines {
+        dedupe_lines(&mut result);
+    }
+    result
+}
+
+fn grep_search_impl<'a>(
+    files: &'a FileList,
+    query: &FFFQuery<'_>,
+    options: &GrepSearchOptions,
+    budget: &ContentCacheBudget,
+    indexes: ContentIndexes<'_>,
+    is_cancelled: Option<&AtomicBool>,
+    subset: Option<&FileSubset>,
 ) -> GrepResult<'a> {
     let total_files = files.len();
 
@@ -1708,14 +2316,7 @@ pub fn grep_search<'a>(
     };
 
     if grep_text.is_empty() {
-        return GrepResult {
-            total_files,
-            filtered_file_count: total_files,
-            next_file_offset: 0,
-            matches: Vec::with_capacity(4),
-            files: Vec::new(),
-            ..Default::default()
-        };
+        return welcome_files(files, options, subset);
     }
 
     let case_insensitive = if options.smart_case {
@@ -1723,6 +2324,7 @@ pub fn grep_search<'a>(
     } else {
         false
     };
+    let line_filter = LineFilter::from_constraints(constraints_from_query, options.smart_case);
 
     let mut regex_fallback_error: Option<String> = None;
     let regex = match options.mode {
@@ -1730,11 +2332,12 @@ pub fn grep_search<'a>(
         GrepMode::Fuzzy => {
             // Fuzzy mode doesn't use bigram — prepare and return early.
             let (mut files_to_search, mut filtered_file_count) =
-                prepare_files_to_search(files, constraints_from_query, options);
+                prepare_files_to_search(files, constraints_from_query, options, subset);
             if files_to_search.is_empty()
                 && let Some(stripped) = strip_file_path_constraints(constraints_from_query)
             {
-                let (retry_files, retry_count) = prepare_files_to_search(files, &stripped, options);
+                let (retry_files, retry_count) =
+                    prepare_files_to_search(files, &stripped, options, subset);
                 files_to_search = retry_files;
                 filtered_file_count = retry_count;
             }
@@ -1755,6 +2358,7 @@ pub fn grep_search<'a>(
                 case_insensitive,
                 budget,
                 is_cancelled,
+                line_filter.as_ref(),
             );
         }
         GrepMode::Regex => build_regex(&grep_text, options.smart_case)
@@ -1786,11 +2390,11 @@ pub fn grep_search<'a>(
 
     // Bigram prefiltering: query the inverted index + merge overlay.
     let bigram_candidates = if regex.is_none()
-        && let Some(idx) = bigram_index
+        && let Some(idx) = indexes.bigram
         && idx.is_ready()
         && let Some(mut candidates) = idx.query(effective_pattern.as_bytes())
     {
-        if let Some(overlay) = bigram_overlay {
+        if let Some(overlay) = indexes.bigram_overlay {
             let pattern_bigrams = extract_bigrams(effective_pattern.as_bytes());
             for (r, t) in candidates.iter_mut().zip(overlay.tombstones().iter()) {
                 *r &= !t;
@@ -1807,8 +2411,34 @@ pub fn grep_search<'a>(
         None
     };
 
+    // Trigrams narrow the bigram candidates further, bigrams saturate on
+    // huge repos.
+    let trigram_candidates = if regex.is_none()
+        && let Some(idx) = indexes.trigram
+    {
+        idx.candidates(effective_pattern.as_bytes(), files)
+    } else {
+        None
+    };
+
+    let content_candidates = match (bigram_candidates, trigram_candidates) {
+        (Some(mut bigram), Some(trigram)) => {
+            for (b, t) in bigram.iter_mut().zip(&trigram) {
+                *b &= t;
+            }
+            Some(bigram)
+        }
+        (bigram, trigram) => bigram.or(trigram),
+    }
+    .map(|mut candidates| {
+        if let Some(subset) = subset {
+            subset.intersect_candidates(&mut candidates);
+        }
+        candidates
+    });
+
     // it is important that this step is coming as early as possible
-    let (files_to_search, filtered_file_count) = match bigram_candidates {
+    let (files_to_search, filtered_file_count) = match content_candidates {
         Some(ref candidates) if constraints_from_query.is_empty() => {
             // this call is essentially free and much more efficient than allowing a recollection
             let cap = BigramFilter::count_candidates(candidates);
@@ -1822,28 +2452,19 @@ pub fn grep_search<'a>(
                 while bits != 0 {
                     let bit = bits.trailing_zeros() as usize;
                     let file_idx = base + bit;
-                    if file_idx < files.len() {
-                        let f = unsafe { files.get_unchecked(file_idx) };
-                        if !f.is_binary && f.size <= options.max_file_size {
-                            result.push(f);
-                        }
+                    if let Some(f) = files.get(file_idx)
+                        && !f.is_binary
+                        && f.size <= options.max_file_size
+                    {
+                        result.push(f);
                     }
                     bits &= bits - 1;
                 }
             }
 
-            let total_searchable = files.len();
-            let needs_sort = result
-                .iter()
-                .any(|f| f.total_frecency_score != 0 || f.modified != 0);
-
-            if needs_sort {
-                sort_with_buffer(&mut result, |a, b| {
-                    b.total_frecency_score
-                        .cmp(&a.total_frecency_score)
-                        .then(b.modified.cmp(&a.modified))
-                });
-            }
+            let total_searchable = subset.map_or(files.len(), FileSubset::len);
+            sort_files_to_search(&mut result, options.file_order);
+            promote_history_file(&mut result, options.history_boost.as_ref());
 
             if options.file_offset > 0 && options.file_offset < result.len() {
                 let paginated = result.split_off(options.file_offset);
@@ -1856,20 +2477,21 @@ pub fn grep_search<'a>(
         }
         _ => {
             // Constraints present or no bigram — full prepare then retain.
-            let (mut fts, mut fc) = prepare_files_to_search(files, constraints_from_query, options);
+            let (mut fts, mut fc) =
+                prepare_files_to_search(files, constraints_from_query, options, subset);
             if fts.is_empty()
                 && let Some(stripped) = strip_file_path_constraints(constraints_from_query)
             {
-                let (retry_files, retry_count) = prepare_files_to_search(files, &stripped, options);
+                let (retry_files, retry_count) =
+                    prepare_files_to_search(files, &stripped, options, subset);
                 fts = retry_files;
                 fc = retry_count;
             }
-            if let Some(ref candidates) = bigram_candidates {
-                let base_ptr = files.as_ptr();
+            if let Some(ref candidates) = content_candidates {
                 fts.retain(|f| {
-                    let file_idx =
-                        unsafe { (*f as *const FileItem).offset_from(base_ptr) as usize };
-                    BigramFilter::is_candidate(candidates, file_idx)
+                    files
+                        .index_of(f)
+                        .is_some_and(|idx| BigramFilter::is_candidate(candidates, idx))
                 });
             }
             (fts, fc)
@@ -1921,7 +2543,7 @@ pub fn grep_search<'a>(
                 classify_definitions: options.classify_definitions,
             };
 
-            match regex {
+            let mut matches = match regex {
                 Some(ref re) => {
                     let regex_matcher = RegexMatcher {
                         regex: re,
@@ -1945,7 +2567,11 @@ pub fn grep_search<'a>(
                     }
                     sink.state.matches
                 }
+            };
+            if let Some(filter) = &line_filter {
+                filter.retain(&mut matches);
             }
+            matches
         },
     );
     result.regex_fallback_error = regex_fallback_error;
@@ -2110,7 +2736,7 @@ mod tests {
         let meta2 = std::fs::metadata(&file2_path).unwrap();
         let meta3 = std::fs::metadata(&file3_path).unwrap();
 
-        let files = vec![
+        let files = FileList::from(vec![
             FileItem::new_raw(
                 file1_path,
                 "grep.rs".to_string(),
@@ -2138,7 +2764,7 @@ mod tests {
                 None,
                 false,
             ),
-        ];
+        ]);
 
         let options = super::GrepSearchOptions {
             max_file_size: 10 * 1024 * 1024,
@@ -2151,6 +2777,13 @@ mod tests {
             before_context: 0,
             after_context: 0,
             classify_definitions: false,
+            file_order: Default::default(),
+            scope: Default::default(),
+            changed_lines: None,
+            resume_after_line: 0,
+            exact_page_limit: false,
+            dedupe_lines: false,
+            history_boost: None,
         };
 
         // Test with 3 patterns
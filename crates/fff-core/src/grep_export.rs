@@ -0,0 +1,277 @@
+//! Grep results written out for other tools.
+//!
+//! [`GrepExportFormat::Quickfix`] is the JSON list `setqflist()` and most
+//! editors' quickfix/location lists accept, [`GrepExportFormat::Sarif`] the
+//! SARIF 2.1.0 log CI systems turn into annotations and
+//! [`GrepExportFormat::Text`] the `file:line:col:text` lines of
+//! `grep -n`/`rg --vimgrep` that `errorformat` and shell pipelines parse.
+
+use crate::error::{Error, Result};
+use crate::grep::GrepResult;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+/// Output format of [`export_grep_matches`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrepExportFormat {
+    /// JSON array of `{ filename, lnum, col, end_col, text }` items.
+    Quickfix,
+    /// SARIF 2.1.0 log with one result per match.
+    Sarif,
+    /// One `relative/path:line:col:text` line per match.
+    Text,
+}
+
+impl GrepExportFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            GrepExportFormat::Quickfix => "quickfix",
+            GrepExportFormat::Sarif => "sarif",
+            GrepExportFormat::Text => "text",
+        }
+    }
+}
+
+impl fmt::Display for GrepExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for GrepExportFormat {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "quickfix" => Ok(GrepExportFormat::Quickfix),
+            "sarif" => Ok(GrepExportFormat::Sarif),
+            "text" => Ok(GrepExportFormat::Text),
+            _ => Err(Error::UnknownExportFormat(name.to_string())),
+        }
+    }
+}
+
+/// One grep match as the exporters see it, so results that already left the
+/// core types (e.g. the C API's result handles) can be exported too.
+#[derive(Debug, Clone)]
+pub struct ExportedMatch<'a> {
+    pub path: Cow<'a, str>,
+    pub relative_path: Cow<'a, str>,
+    /// 1-based line number.
+    pub line_number: u64,
+    /// 0-based byte column of the first match.
+    pub col: usize,
+    /// 0-based byte column where the first match ends, if known.
+    pub end_col: Option<usize>,
+    pub line_content: Cow<'a, str>,
+}
+
+impl GrepResult<'_> {
+    /// The matches of this page with their files.
+    pub fn exported_matches(&self) -> impl Iterator<Item = ExportedMatch<'_>> {
+        self.matches.iter().map(|m| {
+            let file = self.files[m.file_index];
+            ExportedMatch {
+                path: file.path().to_string_lossy(),
+                relative_path: file.relative_path().into(),
+                line_number: m.line_number,
+                col: m.col,
+                end_col: m.match_byte_offsets.first().map(|&(_, end)| end as usize),
+                line_content: m.line_content.as_str().into(),
+            }
+        })
+    }
+
+    /// See [`export_grep_matches`].
+    pub fn export(&self, format: GrepExportFormat) -> String {
+        export_grep_matches(self.exported_matches(), format)
+    }
+}
+
+/// Write `matches` in `format`.
+pub fn export_grep_matches<'a>(
+    matches: impl IntoIterator<Item = ExportedMatch<'a>>,
+    format: GrepExportFormat,
+) -> String {
+    match format {
+        GrepExportFormat::Quickfix => export_quickfix(matches),
+        GrepExportFormat::Sarif => export_sarif(matches),
+        GrepExportFormat::Text => export_text(matches),
+    }
+}
+
+#[derive(Serialize)]
+struct QuickfixItem<'a> {
+    filename: &'a str,
+    lnum: u64,
+    /// 1-based byte column, like quickfix items use by default.
+    col: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_col: Option<usize>,
+    text: &'a str,
+}
+
+fn export_quickfix<'a>(matches: impl IntoIterator<Item = ExportedMatch<'a>>) -> String {
+    let matches: Vec<_> = matches.into_iter().collect();
+    let items: Vec<_> = matches
+        .iter()
+        .map(|m| QuickfixItem {
+            filename: &m.path,
+            lnum: m.line_number,
+            col: m.col + 1,
+            end_col: m.end_col.map(|end| end + 1),
+            text: &m.line_content,
+        })
+        .collect();
+    serde_json::to_string(&items).expect("quickfix items always serialize")
+}
+
+fn export_sarif<'a>(matches: impl IntoIterator<Item = ExportedMatch<'a>>) -> String {
+    // SARIF columns count characters, our columns are bytes of the line
+    let char_col = |line: &str, byte: usize| {
+        let byte = byte.min(line.len());
+        line.char_indices().take_while(|&(i, _)| i < byte).count() + 1
+    };
+
+    let results: Vec<_> = matches
+        .into_iter()
+        .map(|m| {
+            let mut region = serde_json::json!({
+                "startLine": m.line_number,
+                "startColumn": char_col(&m.line_content, m.col),
+                "snippet": { "text": m.line_content },
+            });
+            if let Some(end) = m.end_col {
+                region["endColumn"] = char_col(&m.line_content, end).into();
+            }
+            serde_json::json!({
+                "ruleId": "fff/grep-match",
+                "level": "note",
+                "message": { "text": m.line_content.trim() },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": {
+                            "uri": m.relative_path.replace('\\', "/"),
+                            "uriBaseId": "%SRCROOT%",
+                        },
+                        "region": region,
+                    }
+                }],
+            })
+        })
+        .collect();
+
+    let log = serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "fff",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/dmtrKovalenko/fff.nvim",
+                    "rules": [{
+                        "id": "fff/grep-match",
+                        "shortDescription": { "text": "Line matching the grep query" },
+                    }],
+                }
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }],
+    });
+    serde_json::to_string_pretty(&log).expect("sarif log always serializes")
+}
+
+fn export_text<'a>(matches: impl IntoIterator<Item = ExportedMatch<'a>>) -> String {
+    let mut out = String::new();
+    for m in matches {
+        let _ = writeln!(
+            out,
+            "{}:{}:{}:{}",
+            m.relative_path,
+            m.line_number,
+            m.col + 1,
+            m.line_content
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches() -> Vec<ExportedMatch<'static>> {
+        vec![
+            ExportedMatch {
+                path: "/repo/src/main.rs".into(),
+                relative_path: "src/main.rs".into(),
+                line_number: 3,
+                col: 4,
+                end_col: Some(8),
+                line_content: "fn main() {}".into(),
+            },
+            ExportedMatch {
+                path: "/repo/src/ünï.rs".into(),
+                relative_path: "src/ünï.rs".into(),
+                line_number: 10,
+                col: 9,
+                end_col: Some(12),
+                line_content: "// ünï foo".into(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_text_export() {
+        assert_eq!(
+            export_grep_matches(matches(), GrepExportFormat::Text),
+            "src/main.rs:3:5:fn main() {}\nsrc/ünï.rs:10:10:// ünï foo\n"
+        );
+    }
+
+    #[test]
+    fn test_quickfix_export() {
+        let json = export_grep_matches(matches(), GrepExportFormat::Quickfix);
+        let items: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            items[0],
+            serde_json::json!({
+                "filename": "/repo/src/main.rs",
+                "lnum": 3,
+                "col": 5,
+                "end_col": 9,
+                "text": "fn main() {}",
+            })
+        );
+        assert_eq!(items.as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_sarif_export_counts_characters() {
+        let json = export_grep_matches(matches(), GrepExportFormat::Sarif);
+        let log: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        let results = log["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+
+        let location = &results[1]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/ünï.rs");
+        // "foo" starts at byte 9 but character 7, ü and ï take two bytes
+        assert_eq!(location["region"]["startLine"], 10);
+        assert_eq!(location["region"]["startColumn"], 8);
+        assert_eq!(location["region"]["endColumn"], 11);
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(
+            "sarif".parse::<GrepExportFormat>().unwrap(),
+            GrepExportFormat::Sarif
+        );
+        assert!("csv".parse::<GrepExportFormat>().is_err());
+    }
+}
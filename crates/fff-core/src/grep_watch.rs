@@ -0,0 +1,166 @@
+//! A grep that stays up to date while files are edited.
+//!
+//! [`GrepWatch`] runs a query once over the index and keeps the matches.
+//! Every later [`GrepWatch::refresh`] only greps the files the background
+//! watcher saw change since the previous call and merges their matches into
+//! the cached ones, so re-running a grep while editing costs a handful of
+//! files instead of the whole tree.
+
+use std::time::SystemTime;
+
+use ahash::AHashMap;
+
+use crate::file_picker::PickerSnapshot;
+use crate::grep::{GrepMatch, GrepResult, GrepSearchOptions, parse_grep_query};
+use crate::types::FileItem;
+
+#[derive(Debug)]
+pub struct GrepWatch {
+    query: String,
+    options: GrepSearchOptions,
+    /// Matches of the last refresh grouped by file, in result order.
+    files: Vec<WatchedFile>,
+    /// Unix seconds at the start of the last refresh, `None` before the
+    /// first one. Files modified in or after that second are searched again:
+    /// modification times only have second precision.
+    since: Option<u64>,
+}
+
+#[derive(Debug)]
+struct WatchedFile {
+    relative_path: String,
+    /// Index into `files` of the grep result the matches came from.
+    file_index: usize,
+    matches: Vec<GrepMatch>,
+}
+
+impl GrepWatch {
+    pub fn new(query: impl Into<String>, options: GrepSearchOptions) -> Self {
+        Self {
+            query: query.into(),
+            options,
+            files: Vec::new(),
+            since: None,
+        }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn options(&self) -> &GrepSearchOptions {
+        &self.options
+    }
+
+    /// The first call greps the index like [`PickerSnapshot::grep`]. Later
+    /// calls grep only the files modified since the previous call: their
+    /// matches replace the cached ones, matches of deleted files are dropped
+    /// and files that started matching are appended. `total_files_searched`
+    /// is the number of files searched by this call.
+    pub fn refresh<'a>(&mut self, picker: &'a PickerSnapshot) -> GrepResult<'a> {
+        let started = unix_secs_now();
+        let parsed = parse_grep_query(&self.query);
+        let files = picker.get_files();
+
+        let Some(since) = self.since.replace(started) else {
+            let result = picker.grep(&parsed, &self.options);
+            self.files = group_by_file(&result);
+            return result;
+        };
+
+        // Resolve the cached files and collect the changed ones in one pass
+        // over the index, the file list may have been reordered since.
+        let cached: AHashMap<&str, usize> = self
+            .files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| (file.relative_path.as_str(), i))
+            .collect();
+        let mut resolved: Vec<Option<&'a FileItem>> = vec![None; self.files.len()];
+        let mut changed = Vec::new();
+        for file in files.iter().filter(|file| !file.is_deleted) {
+            if file.modified >= since {
+                changed.push(file.path());
+            } else if let Some(&i) = cached.get(file.relative_path()) {
+                resolved[i] = Some(file);
+            }
+        }
+
+        let options = GrepSearchOptions {
+            file_offset: 0,
+            page_limit: usize::MAX,
+            history_boost: None,
+            ..self.options.clone()
+        };
+        let fresh = picker.grep_in_files(&parsed, &options, changed.iter());
+        let mut fresh_files = group_by_file(&fresh);
+
+        // Changed files keep their place in the results, new matches go last.
+        let mut merged = Vec::with_capacity(self.files.len() + fresh_files.len());
+        let mut result_files = Vec::with_capacity(merged.capacity());
+        for (watched, file) in std::mem::take(&mut self.files).into_iter().zip(resolved) {
+            if let Some(pos) = fresh_files
+                .iter()
+                .position(|f| f.relative_path == watched.relative_path)
+            {
+                let watched = fresh_files.remove(pos);
+                result_files.push(fresh.files[watched.file_index]);
+                merged.push(watched);
+            } else if let Some(file) = file {
+                merged.push(watched);
+                result_files.push(file);
+            }
+        }
+        for watched in fresh_files {
+            result_files.push(fresh.files[watched.file_index]);
+            merged.push(watched);
+        }
+        self.files = merged;
+
+        let mut matches = Vec::new();
+        for (file_index, watched) in self.files.iter_mut().enumerate() {
+            watched.file_index = file_index;
+            matches.extend(watched.matches.iter().map(|m| GrepMatch {
+                file_index,
+                ..m.clone()
+            }));
+        }
+
+        GrepResult {
+            matches,
+            files_with_matches: result_files.len(),
+            files: result_files,
+            file_entries: Vec::new(),
+            total_files_searched: fresh.total_files_searched,
+            total_files: files.len(),
+            filtered_file_count: fresh.filtered_file_count,
+            next_file_offset: 0,
+            next_page: None,
+            regex_fallback_error: fresh.regex_fallback_error,
+        }
+    }
+}
+
+/// Split the matches of `result` per file, files in order of their first match.
+fn group_by_file(result: &GrepResult<'_>) -> Vec<WatchedFile> {
+    let mut groups: Vec<WatchedFile> = Vec::new();
+    let mut group_of_file: AHashMap<usize, usize> = AHashMap::new();
+    for m in &result.matches {
+        let group = *group_of_file.entry(m.file_index).or_insert_with(|| {
+            groups.push(WatchedFile {
+                relative_path: result.files[m.file_index].relative_path().to_string(),
+                file_index: m.file_index,
+                matches: Vec::new(),
+            });
+            groups.len() - 1
+        });
+        groups[group].matches.push(m.clone());
+    }
+    groups
+}
+
+fn unix_secs_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
@@ -1,4 +1,10 @@
+use crate::file_list::FileList;
+use crate::file_picker::ScanLimits;
+use crate::git::open_repository;
+use crate::types::FileItem;
+use git2::Status;
 use std::path::Path;
+use std::sync::OnceLock;
 
 pub(crate) const NON_GIT_IGNORED_DIRS: &[&str] = &[
     "node_modules",
@@ -30,6 +36,15 @@ pub(crate) const PLATFORM_IGNORED_DIRS: &[&str] = &[
 #[cfg(not(any(target_os = "macos", target_os = "windows")))]
 pub(crate) const PLATFORM_IGNORED_DIRS: &[&str] = &[];
 
+/// Configure `.ignore` / `.rgignore` handling on a walker, matching what
+/// ripgrep and fd see in the same tree. `.rgignore` takes precedence.
+pub(crate) fn apply_ignore_files(builder: &mut ignore::WalkBuilder, respect_ignore_files: bool) {
+    builder.ignore(respect_ignore_files);
+    if respect_ignore_files {
+        builder.add_custom_ignore_filename(".rgignore");
+    }
+}
+
 pub(crate) fn non_git_repo_overrides(base_path: &Path) -> Option<ignore::overrides::Override> {
     use ignore::overrides::OverrideBuilder;
 
@@ -59,3 +74,270 @@ pub(crate) fn is_non_code_directory(path: &Path) -> bool {
             path_str.contains(dir)
         })
 }
+
+/// Files the index leaves out because of the ignore rules, `target/` or
+/// `node_modules/` in most repositories. Walked on the first `ignored:`
+/// query instead of with the index and kept until the next full scan.
+#[derive(Debug, Default)]
+pub(crate) struct IgnoredFiles {
+    files: OnceLock<FileList>,
+    same_file_system: bool,
+    limits: ScanLimits,
+}
+
+impl IgnoredFiles {
+    pub(crate) fn new(same_file_system: bool, limits: ScanLimits) -> Self {
+        Self {
+            files: OnceLock::new(),
+            same_file_system,
+            limits,
+        }
+    }
+
+    /// The ignored files sorted by path, walking the tree on the first call.
+    /// `is_indexed` keeps the files of the index out.
+    pub(crate) fn get_or_scan(
+        &self,
+        base_path: &Path,
+        git_workdir: Option<&Path>,
+        is_indexed: impl Fn(&Path) -> bool + Sync,
+    ) -> &FileList {
+        self.files.get_or_init(|| {
+            let start = std::time::Instant::now();
+            let files = scan_ignored_files(
+                base_path,
+                git_workdir,
+                self.same_file_system,
+                self.limits,
+                is_indexed,
+            );
+            tracing::info!(
+                "Scanned {} ignored files in {:?}",
+                files.len(),
+                start.elapsed()
+            );
+            files.into()
+        })
+    }
+}
+
+fn scan_ignored_files(
+    base_path: &Path,
+    git_workdir: Option<&Path>,
+    same_file_system: bool,
+    limits: ScanLimits,
+    is_indexed: impl Fn(&Path) -> bool + Sync,
+) -> Vec<FileItem> {
+    use ignore::{WalkBuilder, WalkState};
+
+    let mut walk_builder = WalkBuilder::new(base_path);
+    walk_builder
+        // same guard as the index for a home directory outside of git
+        .hidden(git_workdir.is_none())
+        .git_ignore(false)
+        .git_exclude(false)
+        .git_global(false)
+        .ignore(false)
+        .follow_links(false)
+        .same_file_system(same_file_system)
+        .max_depth(limits.max_depth);
+
+    let files = parking_lot::Mutex::new(Vec::new());
+    walk_builder.build_parallel().run(|| {
+        let files = &files;
+        let is_indexed = &is_indexed;
+        Box::new(move |result| {
+            let Ok(entry) = result else {
+                return WalkState::Continue;
+            };
+            if entry.file_name() == ".git" {
+                return WalkState::Skip;
+            }
+            if !entry.file_type().is_some_and(|ft| ft.is_file()) || is_indexed(entry.path()) {
+                return WalkState::Continue;
+            }
+
+            let metadata = entry.metadata().ok();
+            let file = FileItem::new_with_metadata(
+                entry.path().to_path_buf(),
+                base_path,
+                None,
+                metadata.as_ref(),
+            );
+            let mut files = files.lock();
+            if limits.max_files.is_some_and(|max| files.len() >= max) {
+                return WalkState::Quit;
+            }
+            files.push(file);
+            WalkState::Continue
+        })
+    });
+
+    let mut files = files.into_inner();
+    files.sort_unstable_by(|a, b| a.path().cmp(b.path()));
+    if let Some(workdir) = git_workdir {
+        apply_ignored_git_status(&mut files, workdir);
+    }
+    files
+}
+
+/// Files in the git index keep their real status, the rest are ignored.
+fn apply_ignored_git_status(files: &mut [FileItem], workdir: &Path) {
+    let Ok(repo) = open_repository(workdir) else {
+        return;
+    };
+    let Ok(index) = repo.index() else {
+        return;
+    };
+
+    for file in files {
+        let Ok(relative) = file.path().strip_prefix(workdir) else {
+            continue;
+        };
+        file.git_status = if index.get_path(relative, 0).is_some() {
+            repo.status_file(relative)
+                .ok()
+                .filter(|status| !status.is_empty())
+        } else {
+            Some(Status::IGNORED)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file_picker::{FilePicker, FilePickerOptions, FuzzySearchOptions};
+    use crate::test_fixtures::{indexed_paths, picker_options, scan, write_files};
+    use crate::types::PaginationArgs;
+    use fff_query_parser::QueryParser;
+    use git2::{Repository, Signature, Status};
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn scanned_paths(tmp: &TempDir, respect_ignore_files: bool) -> Vec<String> {
+        let picker = scan(FilePickerOptions {
+            respect_ignore_files,
+            ..picker_options(tmp.path())
+        });
+        indexed_paths(picker.get_files())
+    }
+
+    fn setup() -> TempDir {
+        let tmp = TempDir::new().unwrap();
+        write_files(
+            tmp.path(),
+            &[
+                ("src/main.rs", "fn main() {}"),
+                ("src/schema.sql", "select 1;"),
+                ("generated/api.rs", "// generated"),
+                ("notes.log", "log"),
+                (".ignore", "generated/\n*.log\n"),
+                ("src/.rgignore", "*.sql\n"),
+            ],
+        );
+        tmp
+    }
+
+    #[test]
+    fn ignore_and_rgignore_files_are_respected() {
+        let tmp = setup();
+        assert_eq!(scanned_paths(&tmp, true), vec!["src/main.rs".to_string()]);
+    }
+
+    #[test]
+    fn rgignore_overrides_ignore() {
+        let tmp = setup();
+        // ripgrep gives .rgignore precedence over .ignore in the same directory.
+        write_files(tmp.path(), &[(".rgignore", "!notes.log\n")]);
+
+        assert_eq!(
+            scanned_paths(&tmp, true),
+            vec!["notes.log".to_string(), "src/main.rs".to_string()]
+        );
+    }
+
+    #[test]
+    fn ignore_files_can_be_disabled() {
+        let tmp = setup();
+        assert_eq!(
+            scanned_paths(&tmp, false),
+            vec![
+                "generated/api.rs".to_string(),
+                "notes.log".to_string(),
+                "src/main.rs".to_string(),
+                "src/schema.sql".to_string(),
+            ]
+        );
+    }
+
+    fn search(picker: &FilePicker, query: &str) -> Vec<(String, Option<Status>)> {
+        let parsed = QueryParser::default().parse(query);
+        let result = FilePicker::fuzzy_search(
+            picker.files_for_query(&parsed),
+            &parsed,
+            None,
+            FuzzySearchOptions {
+                project_path: Some(picker.base_path()),
+                pagination: PaginationArgs {
+                    offset: 0,
+                    limit: 100,
+                },
+                ..Default::default()
+            },
+        );
+        let mut paths: Vec<_> = result
+            .items
+            .iter()
+            .map(|f| (f.relative_path().replace('\\', "/"), f.git_status))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn ignored_token_searches_the_ignored_files() {
+        let tmp = TempDir::new().unwrap();
+        let root = std::fs::canonicalize(tmp.path()).unwrap();
+        write_files(
+            &root,
+            &[
+                ("src/main.rs", "fn main() {}"),
+                ("target/debug/app.d", "deps"),
+                ("vendored.log", "committed anyway"),
+                (".gitignore", "target/\n*.log\n"),
+            ],
+        );
+
+        let repo = Repository::init(&root).unwrap();
+        let mut index = repo.index().unwrap();
+        for path in ["src/main.rs", ".gitignore", "vendored.log"] {
+            index.add_path(Path::new(path)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("fff", "fff@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+
+        let picker = scan(picker_options(&root));
+
+        assert!(
+            search(&picker, "app")
+                .iter()
+                .all(|(path, _)| !path.starts_with("target/"))
+        );
+        assert_eq!(
+            search(&picker, "ignored: app"),
+            vec![("target/debug/app.d".to_string(), Some(Status::IGNORED))]
+        );
+        assert_eq!(
+            search(&picker, "ignored: tracked: log"),
+            vec![("vendored.log".to_string(), None)]
+        );
+        assert!(
+            search(&picker, "ignored: main")
+                .iter()
+                .all(|(path, _)| path != "src/main.rs")
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! Index of the lines matching user-defined named regexes.
+//!
+//! Patterns such as `unwrap\(\)|expect\(` are passed once in
+//! [`FilePickerOptions::keyword_patterns`](crate::FilePickerOptions::keyword_patterns)
+//! and indexed next to the TODO comments, so a search for them is a lookup
+//! instead of a grep over the whole tree.
+
+use regex::bytes::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+
+use crate::error::Error;
+use crate::line_index::{LineCursor, LineEntry, LineIndex, LineMatch};
+
+/// A line returned by [`FilePicker::search_keyword`](crate::FilePicker::search_keyword),
+/// `kind` is the position of its pattern in the configured patterns.
+pub type KeywordMatch<'a> = LineMatch<'a, usize>;
+
+pub(crate) type KeywordIndex = LineIndex<usize>;
+
+/// The compiled keyword patterns. `^` and `$` match at line boundaries.
+#[derive(Debug)]
+pub(crate) struct KeywordMatcher {
+    names: Vec<String>,
+    /// Rejects files matching no pattern in a single pass.
+    any: RegexSet,
+    patterns: Vec<Regex>,
+}
+
+impl KeywordMatcher {
+    /// `None` when no pattern is configured.
+    pub(crate) fn new(patterns: &[(String, String)]) -> Result<Option<Self>, Error> {
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+
+        let compiled = patterns
+            .iter()
+            .map(|(name, pattern)| {
+                RegexBuilder::new(pattern)
+                    .multi_line(true)
+                    .build()
+                    .map_err(|e| Error::InvalidKeywordPattern(name.clone(), e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let any = RegexSetBuilder::new(patterns.iter().map(|(_, pattern)| pattern))
+            .multi_line(true)
+            .build()
+            .map_err(|e| Error::InvalidKeywordPattern(patterns[0].0.clone(), e))?;
+
+        Ok(Some(Self {
+            names: patterns.iter().map(|(name, _)| name.clone()).collect(),
+            any,
+            patterns: compiled,
+        }))
+    }
+
+    pub(crate) fn position(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    /// One entry per line and pattern, ordered by pattern then line.
+    pub(crate) fn scan(&self, content: &[u8]) -> Vec<LineEntry<usize>> {
+        let mut entries = Vec::new();
+        for kind in self.any.matches(content).iter() {
+            let mut cursor = LineCursor::new(content);
+            let mut next_line_start = 0;
+            for hit in self.patterns[kind].find_iter(content) {
+                if hit.start() < next_line_start {
+                    continue;
+                }
+                let line = cursor.line_range(hit.start());
+                next_line_start = line.end + 1;
+                entries.extend(cursor.entry(line, kind));
+            }
+        }
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(patterns: &[(&str, &str)]) -> Vec<(String, String)> {
+        patterns
+            .iter()
+            .map(|(name, pattern)| (name.to_string(), pattern.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn scans_lines_per_pattern() {
+        let matcher = KeywordMatcher::new(&patterns(&[
+            ("error_handling", r"unwrap\(\)|expect\("),
+            ("imports", r"^use "),
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(matcher.position("imports"), Some(1));
+
+        let content =
+            b"use std::fs;\nfn main() {\n    a.unwrap(); b.unwrap();\n    c.expect(\"x\");\n}\n";
+        let entries: Vec<(usize, u64)> = matcher
+            .scan(content)
+            .into_iter()
+            .map(|e| (e.kind, e.line_number))
+            .collect();
+        assert_eq!(entries, vec![(0, 3), (0, 4), (1, 1)]);
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        assert!(KeywordMatcher::new(&[]).unwrap().is_none());
+        let error = KeywordMatcher::new(&patterns(&[("broken", "unwrap(")])).unwrap_err();
+        assert!(error.to_string().contains("broken"));
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! This crate provides the core search engine for [FFF (Fast File Finder)](https://github.com/dmtrKovalenko/fff.nvim).
 //! It includes filesystem indexing with real-time watching, fuzzy matching powered
-//! by [frizbee](https://docs.rs/neo_frizbee), frecency scoring backed by LMDB,
+//! by [frizbee](https://docs.rs/neo_frizbee), frecency scoring backed by LMDB (or redb),
 //! and multi-mode grep search.
 //!
 //! ## Architecture
@@ -10,7 +10,7 @@
 //! - [`file_picker::FilePicker`] — Main entry point. Indexes a directory tree in a
 //!   background thread, maintains a sorted file list, watches the filesystem for
 //!   changes, and performs fuzzy search with frecency-weighted scoring.
-//! - [`frecency::FrecencyTracker`] — LMDB-backed database that tracks file access
+//! - [`frecency::FrecencyTracker`] — LMDB- or redb-backed database that tracks file access
 //!   and modification patterns for intelligent result ranking.
 //! - [`query_tracker::QueryTracker`] — Tracks search query history and provides
 //!   "combo-boost" scoring for repeatedly matched files.
@@ -86,7 +86,7 @@
 //! );
 //!
 //! assert!(results.total_matched > 0);
-//! assert!(results.items.first().unwrap().path.ends_with("lib.rs"));
+//! assert!(results.items.first().unwrap().path().ends_with("lib.rs"));
 //!
 //! let _ = std::fs::remove_dir_all(&tmp);
 //! # Ok::<(), Box<dyn std::error::Error>>(())
@@ -94,11 +94,27 @@
 
 mod background_watcher;
 mod bigram_filter;
+mod code_scope;
+mod completion;
 mod constraints;
 mod db_healthcheck;
+mod duplicates;
 mod error;
+mod keyword_index;
+mod line_index;
+mod power;
+mod preview_hunk;
+mod replace_diff;
 mod score;
+mod search_scratch;
 mod sort_buffer;
+mod suggestion;
+#[cfg(test)]
+mod test_fixtures;
+mod test_index;
+mod todo_index;
+mod trash;
+mod trigram_index;
 // this is pub only for benchmarks
 pub mod case_insensitive_memmem;
 
@@ -107,11 +123,24 @@ pub mod case_insensitive_memmem;
 /// See [`FilePicker`](file_picker::FilePicker) for the main entry point.
 pub mod file_picker;
 
+/// Batch rename / move / delete operations applied atomically to the index.
+pub mod file_ops;
+
+/// The list of indexed files, shared in chunks between index snapshots.
+pub mod file_list;
+
+/// Default frecency and query history database paths shared by the
+/// frontends.
+pub mod default_paths;
+
 /// Frecency (frequency + recency) database for file access scoring.
 ///
 /// Backed by LMDB for persistent, crash-safe storage.
 pub mod frecency;
 
+/// Most opened files and most used queries of a project.
+pub mod frecency_stats;
+
 /// Git status caching and repository detection utilities.
 pub mod git;
 
@@ -121,34 +150,105 @@ pub mod git;
 /// and parallel execution via rayon.
 pub mod grep;
 
+/// Grep results as quickfix JSON, SARIF or `grep -n` style text.
+pub mod grep_export;
+
+/// Grep kept up to date by re-searching only the files modified since the
+/// previous run.
+pub mod grep_watch;
+
+/// Structured crash reports with the search that was running when FFF panicked.
+pub mod crash;
+
 /// Tracing/logging initialization and panic hook setup.
 pub mod log;
 
+/// Wait and hold times of the shared locks, with warnings for slow ones.
+pub mod lock_stats;
+
+/// Latency percentiles and slowest queries of recent searches.
+pub mod metrics;
+
+/// Weekly search latency and index size, persisted when opted in.
+pub mod perf_history;
+
 /// Path manipulation utilities: cross platform canonicalization, tilde expansion, and
 /// directory distance penalties for search scoring.
 pub mod path_utils;
 
+/// Project type detection (Cargo, npm, Python, Go) and the directory
+/// boosts and penalties recommended for each.
+pub mod project_profile;
+
+/// Warnings about constraint typos, invalid globs and regex errors in a
+/// query, with the byte span of the offending token.
+pub mod query_diagnostics;
+
 /// Search query history tracker for combo-boost scoring.
 ///
 /// Records which files a user selects for each query, enabling the scorer
 /// to boost files that were previously chosen for similar searches.
 pub mod query_tracker;
 
+/// Key-value storage backends (LMDB or redb) behind the frecency and query
+/// history databases.
+///
+/// LMDB is the default and lets several processes share a database. redb is a
+/// pure Rust single-file database for home directories where LMDB's lock file
+/// doesn't work, e.g. some network file systems, but only one process can open
+/// it at a time.
+pub mod storage;
+
 /// Core data types shared across the crate.
 pub mod types;
 
+/// Files outside of the local disk, like remote buffers, registered by the
+/// editor and searched next to the index.
+pub mod virtual_files;
+
+/// Read-only directories like `$VIMRUNTIME` searched next to the project
+/// and targeted with `root:`.
+pub mod auxiliary_roots;
+
+/// Cursors that fetch the next page of a fuzzy search without ranking it
+/// again.
+pub mod search_cursor;
+
+/// Files passing the query constraints, cached between keystrokes.
+pub mod working_set;
+
 mod ignore;
 /// Thread-safe shared handles for [`FilePicker`], [`FrecencyTracker`],
 /// and [`QueryTracker`].
 pub mod shared;
 
 pub use bigram_filter::*;
-pub use db_healthcheck::{DbHealth, DbHealthChecker};
+pub use completion::{CompletionKind, ConstraintCompletion};
+pub use db_healthcheck::{DbHealth, DbHealthChecker, DbRepair};
+pub use default_paths::{DefaultDbPaths, default_data_dir, default_db_paths};
+pub use duplicates::DuplicateGroup;
 pub use error::{Error, Result};
 pub use fff_query_parser::*;
+pub use file_list::FileList;
+pub use file_ops::{FileOp, FileOpJournalEntry, FileOpResult};
 pub use file_picker::*;
 pub use frecency::*;
+pub use frecency_stats::{FileUsage, FrecencyStats};
 pub use grep::*;
+pub use grep_export::{ExportedMatch, GrepExportFormat, export_grep_matches};
+pub use keyword_index::KeywordMatch;
+pub use line_index::LineMatch;
+pub use power::on_battery_power;
+pub use preview_hunk::PreviewHunk;
+pub use project_profile::{ProjectKind, ProjectProfile, ProjectProfileOptions};
 pub use query_tracker::*;
+pub use replace_diff::Replacement;
+pub use search_cursor::SearchCursor;
+pub use search_scratch::SearchScratch;
 pub use shared::*;
+pub use storage::{StorageBackend, StorageOptions};
+pub use test_index::{TestKind, TestMatch};
+pub use todo_index::{TodoKind, TodoMatch};
+pub use trigram_index::{TrigramIndex, TrigramIndexHealth};
 pub use types::*;
+pub use working_set::WorkingSetCache;
@@ -0,0 +1,356 @@
+//! Lines of interest collected per file, ranked like file picker results.
+//!
+//! Shared by the TODO index and the user-defined keyword index: a scanner
+//! picks the lines of a file once, the watcher re-scans the files it sees
+//! change, and a search only ranks the collected lines.
+
+use std::path::{Path, PathBuf};
+
+use ahash::AHashMap;
+use rayon::prelude::*;
+
+use crate::file_list::FileList;
+use crate::types::{ContentCacheBudget, FileItem};
+
+/// Longer lines are cut, they are shown on a single picker row.
+const MAX_TEXT_LEN: usize = 256;
+
+/// Content sniffed for NUL bytes before scanning a file the watcher reports.
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LineEntry<K> {
+    pub line_number: u64,
+    pub kind: K,
+    /// The trimmed line, or the name of a test.
+    pub text: String,
+}
+
+/// A line returned by [`FilePicker::search_todos`](crate::FilePicker::search_todos),
+/// [`FilePicker::search_keyword`](crate::FilePicker::search_keyword) or
+/// [`FilePicker::search_tests`](crate::FilePicker::search_tests).
+#[derive(Debug, Clone)]
+pub struct LineMatch<'a, K> {
+    pub file: &'a FileItem,
+    pub line_number: u64,
+    pub kind: K,
+    pub text: String,
+    /// Fuzzy score of the query against the path and the line plus the
+    /// frecency of the file.
+    pub score: i32,
+}
+
+#[derive(Debug)]
+pub(crate) struct LineIndex<K> {
+    by_file: AHashMap<PathBuf, Vec<LineEntry<K>>>,
+}
+
+impl<K> Default for LineIndex<K> {
+    fn default() -> Self {
+        Self {
+            by_file: AHashMap::new(),
+        }
+    }
+}
+
+impl<K: Copy + Send> LineIndex<K> {
+    pub(crate) fn build(
+        files: &FileList,
+        budget: &ContentCacheBudget,
+        scan: impl Fn(&[u8]) -> Vec<LineEntry<K>> + Sync,
+    ) -> Self {
+        let by_file = files
+            .par_iter()
+            .filter(|file| !file.is_deleted && !file.is_binary)
+            .filter_map(|file| {
+                let content = file.get_content_for_search(budget)?;
+                let entries = scan(&content);
+                (!entries.is_empty()).then(|| (file.path().to_path_buf(), entries))
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect();
+        Self { by_file }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.by_file.values().map(Vec::len).sum()
+    }
+
+    /// Re-scan a file the watcher saw change.
+    pub(crate) fn update_file(
+        &mut self,
+        path: &Path,
+        content: &[u8],
+        scan: impl Fn(&[u8]) -> Vec<LineEntry<K>>,
+    ) {
+        let sniff = &content[..content.len().min(BINARY_SNIFF_LEN)];
+        let entries = if memchr::memchr(0, sniff).is_some() {
+            Vec::new()
+        } else {
+            scan(content)
+        };
+
+        if entries.is_empty() {
+            self.by_file.remove(path);
+        } else {
+            self.by_file.insert(path.to_path_buf(), entries);
+        }
+    }
+
+    pub(crate) fn remove_file(&mut self, path: &Path) {
+        self.by_file.remove(path);
+    }
+
+    /// The entries of a file in the order they were scanned.
+    pub(crate) fn file_entries(&self, path: &Path) -> &[LineEntry<K>] {
+        self.by_file.get(path).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn remove_dir(&mut self, dir: &Path) {
+        self.by_file.retain(|path, _| !path.starts_with(dir));
+    }
+
+    /// Ranks the lines of the kinds `keep` accepts against `query` like the
+    /// file picker ranks paths, every whitespace separated part has to match
+    /// the relative path or the line. An empty query lists every line by
+    /// file frecency.
+    pub(crate) fn search<'a>(
+        &self,
+        query: &str,
+        max_results: usize,
+        file_of: impl Fn(&Path) -> Option<&'a FileItem>,
+        keep: impl Fn(K) -> bool,
+    ) -> Vec<LineMatch<'a, K>> {
+        let candidates: Vec<(&'a FileItem, &LineEntry<K>)> = self
+            .by_file
+            .iter()
+            .filter_map(|(path, entries)| Some((file_of(path)?, entries)))
+            .filter(|(file, _)| !file.is_deleted)
+            .flat_map(|(file, entries)| entries.iter().map(move |entry| (file, entry)))
+            .filter(|(_, entry)| keep(entry.kind))
+            .collect();
+
+        let haystacks: Vec<String> = candidates
+            .iter()
+            .map(|(file, entry)| format!("{} {}", file.relative_path(), entry.text))
+            .collect();
+        let haystacks: Vec<&str> = haystacks.iter().map(String::as_str).collect();
+        let fuzzy_scores = fuzzy_scores(query, &haystacks);
+
+        let mut matches: Vec<LineMatch<'a, K>> = candidates
+            .into_iter()
+            .zip(fuzzy_scores)
+            .filter_map(|((file, entry), fuzzy_score)| {
+                Some(LineMatch {
+                    file,
+                    line_number: entry.line_number,
+                    kind: entry.kind,
+                    text: entry.text.clone(),
+                    score: fuzzy_score? as i32 + file.total_frecency_score,
+                })
+            })
+            .collect();
+
+        matches.sort_unstable_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.file.relative_path().cmp(b.file.relative_path()))
+                .then_with(|| a.line_number.cmp(&b.line_number))
+        });
+        matches.truncate(max_results);
+        matches
+    }
+}
+
+/// Resolves byte positions to lines. Positions must be passed in increasing
+/// order, the line count is carried over from the previous call.
+pub(crate) struct LineCursor<'c> {
+    content: &'c [u8],
+    line_number: u64,
+    counted_until: usize,
+}
+
+impl<'c> LineCursor<'c> {
+    pub(crate) fn new(content: &'c [u8]) -> Self {
+        Self {
+            content,
+            line_number: 1,
+            counted_until: 0,
+        }
+    }
+
+    /// Byte range of the line containing `pos`, without the newline.
+    pub(crate) fn line_range(&self, pos: usize) -> std::ops::Range<usize> {
+        let start = memchr::memrchr(b'\n', &self.content[..pos]).map_or(0, |i| i + 1);
+        let end =
+            memchr::memchr(b'\n', &self.content[pos..]).map_or(self.content.len(), |i| pos + i);
+        start..end
+    }
+
+    /// The entry for a range returned by [`Self::line_range`], `None` when
+    /// the line is not UTF-8.
+    pub(crate) fn entry<K>(
+        &mut self,
+        line: std::ops::Range<usize>,
+        kind: K,
+    ) -> Option<LineEntry<K>> {
+        self.line_number +=
+            memchr::memchr_iter(b'\n', &self.content[self.counted_until..line.start]).count()
+                as u64;
+        self.counted_until = line.start;
+
+        let text = std::str::from_utf8(&self.content[line]).ok()?;
+        Some(LineEntry {
+            line_number: self.line_number,
+            kind,
+            text: truncate(text.trim(), MAX_TEXT_LEN).to_string(),
+        })
+    }
+}
+
+/// Score of every haystack against all parts of `query`, `None` where a part
+/// does not match. Every haystack scores 0 for an empty query.
+fn fuzzy_scores(query: &str, haystacks: &[&str]) -> Vec<Option<u16>> {
+    let mut scores = vec![Some(0u16); haystacks.len()];
+    for part in query.split_whitespace() {
+        let options = neo_frizbee::Config {
+            max_typos: Some((part.len() as u16 / 4).min(2)),
+            sort: false,
+            ..Default::default()
+        };
+        let mut part_scores = vec![None; haystacks.len()];
+        for m in neo_frizbee::match_list(part, haystacks, &options) {
+            part_scores[m.index as usize] = Some(m.score);
+        }
+        for (score, part_score) in scores.iter_mut().zip(part_scores) {
+            *score = score.zip(part_score).map(|(a, b)| a.saturating_add(b));
+        }
+    }
+    scores
+}
+
+fn truncate(text: &str, max_len: usize) -> &str {
+    if text.len() <= max_len {
+        return text;
+    }
+    let mut end = max_len;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::file_picker::{FilePicker, FilePickerOptions};
+    use crate::test_fixtures::{picker_options, scan, write_files};
+    use crate::{TestKind, TodoKind};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn picker_for(tmp: &TempDir, keyword_patterns: &[(&str, &str)]) -> FilePicker {
+        scan(FilePickerOptions {
+            keyword_patterns: keyword_patterns
+                .iter()
+                .map(|(name, pattern)| (name.to_string(), pattern.to_string()))
+                .collect(),
+            ..picker_options(tmp.path())
+        })
+    }
+
+    #[test]
+    fn todos_follow_file_changes() {
+        let tmp = TempDir::new().unwrap();
+        write_files(
+            tmp.path(),
+            &[
+                ("a.rs", "// TODO: first\nfn a() {}\n"),
+                ("b.rs", "fn b() {}\n"),
+            ],
+        );
+
+        let mut picker = picker_for(&tmp, &[]);
+        let todos = picker.search_todos("", 10);
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].file.relative_path(), "a.rs");
+        assert_eq!(todos[0].kind, TodoKind::Todo);
+
+        fs::write(tmp.path().join("b.rs"), "fn b() {}\n# FIXME: second\n").unwrap();
+        picker.on_create_or_modify(tmp.path().join("b.rs"));
+        picker.remove_file_by_path(tmp.path().join("a.rs"));
+
+        let todos = picker.search_todos("", 10);
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].file.relative_path(), "b.rs");
+        assert_eq!(todos[0].line_number, 2);
+        assert_eq!(todos[0].kind, TodoKind::Fixme);
+    }
+
+    #[test]
+    fn keyword_patterns_are_searchable_by_name() {
+        let tmp = TempDir::new().unwrap();
+        write_files(
+            tmp.path(),
+            &[(
+                "main.rs",
+                "fn main() {\n    let a = parse().unwrap();\n    let b = load().expect(\"b\");\n}\n",
+            )],
+        );
+
+        let picker = picker_for(&tmp, &[("error_handling", r"unwrap\(\)|expect\(")]);
+        let lines = picker.search_keyword("error_handling", "load", 10).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].line_number, 3);
+        assert_eq!(
+            picker
+                .search_keyword("error_handling", "", 10)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(picker.search_keyword("missing", "", 10).is_none());
+
+        let invalid = FilePicker::new(FilePickerOptions {
+            keyword_patterns: vec![("broken".to_string(), "unwrap(".to_string())],
+            ..picker_options(tmp.path())
+        });
+        assert!(matches!(invalid, Err(Error::InvalidKeywordPattern(name, _)) if name == "broken"));
+    }
+
+    #[test]
+    fn tests_are_searchable_by_name_and_by_line() {
+        let tmp = TempDir::new().unwrap();
+        write_files(
+            tmp.path(),
+            &[
+                (
+                    "parser.rs",
+                    "#[test]\nfn parses_globs() {\n    check();\n}\n\n#[test]\nfn parses_paths() {}\n",
+                ),
+                ("query.spec.ts", "it('keeps the cursor', () => {})\n"),
+            ],
+        );
+
+        let mut picker = picker_for(&tmp, &[]);
+        let tests = picker.search_tests("glob", 10);
+        assert_eq!(tests.len(), 1);
+        assert_eq!(tests[0].text, "parses_globs");
+        assert_eq!(tests[0].line_number, 2);
+        assert_eq!(tests[0].kind, TestKind::Rust);
+        assert_eq!(picker.search_tests("", 10).len(), 3);
+
+        let parser = tmp.path().join("parser.rs");
+        assert_eq!(picker.test_at(&parser, 3).unwrap().text, "parses_globs");
+        assert_eq!(picker.test_at(&parser, 7).unwrap().text, "parses_paths");
+        assert!(picker.test_at(&parser, 1).is_none());
+
+        fs::write(&parser, "fn helper() {}\n").unwrap();
+        picker.on_create_or_modify(&parser);
+        assert!(picker.test_at(&parser, 3).is_none());
+        let tests = picker.search_tests("", 10);
+        assert_eq!(tests.len(), 1);
+        assert_eq!(tests[0].kind, TestKind::Spec);
+    }
+}
@@ -0,0 +1,253 @@
+//! Contention diagnostics for the shared picker, frecency and query tracker locks.
+//!
+//! Every acquisition through [`crate::shared`] is timed. Waits and holds above
+//! [`SLOW_LOCK_THRESHOLD`] are logged with a backtrace and the place the lock
+//! was last taken for writing, which is usually the other half of a freeze.
+
+use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+/// Waits or holds longer than this are logged as warnings.
+pub const SLOW_LOCK_THRESHOLD: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockName {
+    Picker,
+    Frecency,
+    QueryTracker,
+}
+
+impl LockName {
+    pub const ALL: [LockName; 3] = [Self::Picker, Self::Frecency, Self::QueryTracker];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Picker => "picker",
+            Self::Frecency => "frecency",
+            Self::QueryTracker => "query_tracker",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Access {
+    Read,
+    Write,
+}
+
+impl Access {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LockStats {
+    pub acquisitions: u64,
+    pub slow_waits: u64,
+    pub slow_holds: u64,
+    pub total_wait: Duration,
+    pub max_wait: Duration,
+    pub max_hold: Duration,
+    /// Where the lock was last acquired for writing.
+    pub last_writer: Option<&'static Location<'static>>,
+}
+
+struct LockCounters {
+    acquisitions: AtomicU64,
+    slow_waits: AtomicU64,
+    slow_holds: AtomicU64,
+    total_wait_us: AtomicU64,
+    max_wait_us: AtomicU64,
+    max_hold_us: AtomicU64,
+    last_writer: Mutex<Option<&'static Location<'static>>>,
+}
+
+impl LockCounters {
+    const fn new() -> Self {
+        Self {
+            acquisitions: AtomicU64::new(0),
+            slow_waits: AtomicU64::new(0),
+            slow_holds: AtomicU64::new(0),
+            total_wait_us: AtomicU64::new(0),
+            max_wait_us: AtomicU64::new(0),
+            max_hold_us: AtomicU64::new(0),
+            last_writer: Mutex::new(None),
+        }
+    }
+}
+
+static COUNTERS: [LockCounters; 3] = [
+    LockCounters::new(),
+    LockCounters::new(),
+    LockCounters::new(),
+];
+
+fn counters(lock: LockName) -> &'static LockCounters {
+    &COUNTERS[lock as usize]
+}
+
+fn as_micros(duration: Duration) -> u64 {
+    duration.as_micros().min(u64::MAX as u128) as u64
+}
+
+/// A lock guard that records how long it was held when dropped.
+pub struct InstrumentedGuard<G> {
+    guard: G,
+    lock: LockName,
+    access: Access,
+    location: &'static Location<'static>,
+    acquired: Instant,
+}
+
+impl<G: Deref> Deref for InstrumentedGuard<G> {
+    type Target = G::Target;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<G: DerefMut> DerefMut for InstrumentedGuard<G> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+impl<G> Drop for InstrumentedGuard<G> {
+    fn drop(&mut self) {
+        let held = self.acquired.elapsed();
+        let counters = counters(self.lock);
+        counters
+            .max_hold_us
+            .fetch_max(as_micros(held), Ordering::Relaxed);
+
+        if held >= SLOW_LOCK_THRESHOLD {
+            counters.slow_holds.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(
+                lock = self.lock.name(),
+                access = self.access.name(),
+                held = ?held,
+                acquired_at = %self.location,
+                backtrace = %std::backtrace::Backtrace::force_capture(),
+                "Lock held for too long",
+            );
+        }
+    }
+}
+
+/// Acquire a lock through `acquire`, recording the wait and, once the
+/// returned guard is dropped, the hold time.
+pub(crate) fn acquire<G, E>(
+    lock: LockName,
+    access: Access,
+    location: &'static Location<'static>,
+    acquire: impl FnOnce() -> Result<G, E>,
+) -> Result<InstrumentedGuard<G>, E> {
+    let started = Instant::now();
+    let guard = acquire()?;
+    let acquired = Instant::now();
+    let waited = acquired - started;
+
+    let counters = counters(lock);
+    counters.acquisitions.fetch_add(1, Ordering::Relaxed);
+    counters
+        .total_wait_us
+        .fetch_add(as_micros(waited), Ordering::Relaxed);
+    counters
+        .max_wait_us
+        .fetch_max(as_micros(waited), Ordering::Relaxed);
+
+    if waited >= SLOW_LOCK_THRESHOLD {
+        counters.slow_waits.fetch_add(1, Ordering::Relaxed);
+        let last_writer = *counters.last_writer.lock();
+        tracing::warn!(
+            lock = lock.name(),
+            access = access.name(),
+            waited = ?waited,
+            waiter = %location,
+            last_writer = ?last_writer.map(ToString::to_string),
+            backtrace = %std::backtrace::Backtrace::force_capture(),
+            "Waited too long for a lock",
+        );
+    }
+    if access == Access::Write {
+        *counters.last_writer.lock() = Some(location);
+    }
+
+    Ok(InstrumentedGuard {
+        guard,
+        lock,
+        access,
+        location,
+        acquired,
+    })
+}
+
+pub fn stats(lock: LockName) -> LockStats {
+    let counters = counters(lock);
+    LockStats {
+        acquisitions: counters.acquisitions.load(Ordering::Relaxed),
+        slow_waits: counters.slow_waits.load(Ordering::Relaxed),
+        slow_holds: counters.slow_holds.load(Ordering::Relaxed),
+        total_wait: Duration::from_micros(counters.total_wait_us.load(Ordering::Relaxed)),
+        max_wait: Duration::from_micros(counters.max_wait_us.load(Ordering::Relaxed)),
+        max_hold: Duration::from_micros(counters.max_hold_us.load(Ordering::Relaxed)),
+        last_writer: *counters.last_writer.lock(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_holds_and_waits_are_counted() {
+        let lock = std::sync::Arc::new(parking_lot::RwLock::new(0));
+        let before = stats(LockName::QueryTracker);
+
+        let holder = {
+            let lock = lock.clone();
+            let (tx, rx) = std::sync::mpsc::channel();
+            let handle = std::thread::spawn(move || {
+                let mut guard = acquire(
+                    LockName::QueryTracker,
+                    Access::Write,
+                    Location::caller(),
+                    || Ok::<_, ()>(lock.write()),
+                )
+                .unwrap();
+                tx.send(()).unwrap();
+                std::thread::sleep(SLOW_LOCK_THRESHOLD + Duration::from_millis(20));
+                *guard += 1;
+            });
+            rx.recv().unwrap();
+            handle
+        };
+
+        let value = *acquire(
+            LockName::QueryTracker,
+            Access::Read,
+            Location::caller(),
+            || Ok::<_, ()>(lock.read()),
+        )
+        .unwrap();
+        holder.join().unwrap();
+
+        let after = stats(LockName::QueryTracker);
+        assert_eq!(value, 1);
+        // other tests may use the same lock concurrently
+        assert!(after.acquisitions - before.acquisitions >= 2);
+        assert!(after.slow_holds > before.slow_holds);
+        assert!(after.slow_waits > before.slow_waits);
+        assert!(after.max_hold >= SLOW_LOCK_THRESHOLD);
+        assert!(after.last_writer.is_some());
+    }
+}
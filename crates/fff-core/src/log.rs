@@ -1,21 +1,34 @@
 //! Shared logging utilities for FFF crates.
 //!
-//! Provides file-based tracing initialization and a panic hook that writes
-//! to both stderr and a fallback log file.
+//! Provides file-based tracing initialization, a panic hook that writes
+//! to both stderr and a fallback log file, and an on-demand profiler that
+//! records spans in the Chrome trace event format.
 
-use std::io;
-use std::path::Path;
+use parking_lot::Mutex;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Instant;
+use tracing::span;
 use tracing_appender::non_blocking;
 use tracing_subscriber::fmt::format::FmtSpan;
-use tracing_subscriber::{EnvFilter, fmt, prelude::*};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{EnvFilter, Layer, Registry, fmt, prelude::*, reload};
 
 static TRACING_INITIALIZED: std::sync::OnceLock<tracing_appender::non_blocking::WorkerGuard> =
     std::sync::OnceLock::new();
 
+/// Handle for swapping the level filter of the global subscriber at runtime.
+static LOG_FILTER: std::sync::OnceLock<reload::Handle<EnvFilter, Registry>> =
+    std::sync::OnceLock::new();
+
 static PANIC_HOOK_INSTALLED: std::sync::OnceLock<()> = std::sync::OnceLock::new();
 
-/// Install panic hook that writes to both stderr and a fallback file.
-/// This is called separately from init_tracing to ensure panics are always logged.
+/// Install panic hook that writes to both stderr and a fallback file, and
+/// saves a structured [`crate::crash::CrashReport`]. This is called
+/// separately from init_tracing to ensure panics are always logged.
 pub fn install_panic_hook() {
     PANIC_HOOK_INSTALLED.get_or_init(|| {
         let default_panic = std::panic::take_hook();
@@ -54,6 +67,11 @@ pub fn install_panic_hook() {
             eprintln!("Location: {}", location);
             eprintln!("=================");
 
+            let report = crate::crash::build_report(&message, &location);
+            if let Some(path) = crate::crash::write_report(&report) {
+                eprintln!("Crash report written to: {}", path.display());
+            }
+
             // Try to write to fallback panic log file
             if let Some(cache_dir) = dirs::cache_dir() {
                 let panic_log = cache_dir.join("fff_panic.log");
@@ -84,6 +102,125 @@ pub fn install_panic_hook() {
     });
 }
 
+static PROFILING: AtomicBool = AtomicBool::new(false);
+static PROFILE: Mutex<Option<ProfileWriter>> = Mutex::new(None);
+
+struct ProfileWriter {
+    path: PathBuf,
+    out: BufWriter<File>,
+    started: Instant,
+    has_events: bool,
+}
+
+/// Start time and thread of a span, stored in its extensions while profiling.
+struct SpanTiming {
+    start: Instant,
+    tid: u64,
+}
+
+fn current_tid() -> u64 {
+    static NEXT_TID: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static TID: u64 = NEXT_TID.fetch_add(1, Ordering::Relaxed);
+    }
+    TID.with(|tid| *tid)
+}
+
+/// Writes every span closed while profiling is enabled as a Chrome trace
+/// "complete" event. Open the file in `chrome://tracing` or Perfetto.
+struct ChromeTraceLayer;
+
+impl<S> Layer<S> for ChromeTraceLayer
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if !PROFILING.load(Ordering::Relaxed) {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanTiming {
+                start: Instant::now(),
+                tid: current_tid(),
+            });
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        if !PROFILING.load(Ordering::Relaxed) {
+            return;
+        }
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        let Some(timing) = extensions.get::<SpanTiming>() else {
+            return;
+        };
+
+        let mut profile = PROFILE.lock();
+        let Some(writer) = profile.as_mut() else {
+            return;
+        };
+        let event = serde_json::json!({
+            "name": span.name(),
+            "cat": span.metadata().target(),
+            "ph": "X",
+            "ts": timing.start.saturating_duration_since(writer.started).as_micros() as u64,
+            "dur": timing.start.elapsed().as_micros() as u64,
+            "pid": std::process::id(),
+            "tid": timing.tid,
+        });
+        let separator = if writer.has_events { ",\n" } else { "" };
+        writer.has_events = true;
+        let _ = write!(writer.out, "{separator}{event}");
+    }
+}
+
+/// Start or stop recording spans to a Chrome trace file.
+///
+/// Enabling truncates `path` (defaults to `fff_profile.json` in the cache
+/// dir) and returns it. Disabling finishes the file and returns the path of
+/// the recording that was stopped, if any. Spans are only recorded when
+/// tracing was initialized and their level passes the log level filter, so
+/// per-phase spans need the `debug` level.
+pub fn set_profiling(enabled: bool, path: Option<&Path>) -> Result<Option<PathBuf>, io::Error> {
+    let mut profile = PROFILE.lock();
+
+    if let Some(mut finished) = profile.take() {
+        PROFILING.store(false, Ordering::Relaxed);
+        finished.out.write_all(b"\n]\n")?;
+        finished.out.flush()?;
+        if !enabled {
+            return Ok(Some(finished.path));
+        }
+    }
+    if !enabled {
+        return Ok(None);
+    }
+
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => dirs::cache_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("fff_profile.json"),
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut out = BufWriter::new(File::create(&path)?);
+    out.write_all(b"[\n")?;
+
+    *profile = Some(ProfileWriter {
+        path: path.clone(),
+        out,
+        started: Instant::now(),
+        has_events: false,
+    });
+    PROFILING.store(true, Ordering::Relaxed);
+    Ok(Some(path))
+}
+
 /// Parse a log level string into a `tracing::Level`.
 ///
 /// Accepts "trace", "debug", "info", "warn", "error" (case-insensitive).
@@ -130,7 +267,15 @@ pub fn init_tracing(log_file_path: &str, log_level: Option<&str>) -> Result<Stri
     TRACING_INITIALIZED.get_or_init(|| {
         let (non_blocking_appender, guard) = non_blocking(file_appender);
 
+        let (filter, filter_handle) = reload::Layer::new(
+            EnvFilter::builder()
+                .with_default_directive(level.into())
+                .from_env_lossy(),
+        );
+        let _ = LOG_FILTER.set(filter_handle);
+
         let subscriber = tracing_subscriber::registry()
+            .with(filter)
             .with(
                 fmt::layer()
                     .with_writer(non_blocking_appender)
@@ -142,11 +287,7 @@ pub fn init_tracing(log_file_path: &str, log_level: Option<&str>) -> Result<Stri
                     .with_ansi(false)
                     .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE),
             )
-            .with(
-                EnvFilter::builder()
-                    .with_default_directive(level.into())
-                    .from_env_lossy(),
-            );
+            .with(ChromeTraceLayer);
 
         if let Err(e) = tracing::subscriber::set_global_default(subscriber) {
             eprintln!("Failed to set tracing subscriber: {}", e);
@@ -162,3 +303,55 @@ pub fn init_tracing(log_file_path: &str, log_level: Option<&str>) -> Result<Stri
 
     Ok(log_file_path.to_string())
 }
+
+/// Change the log level of the subscriber installed by [`init_tracing`]
+/// without restarting. The new level replaces any `RUST_LOG` directives.
+///
+/// Fails if `level` is not a valid level name or tracing was never initialized.
+pub fn set_log_level(level: &str) -> Result<tracing::Level, io::Error> {
+    let level: tracing::Level = level.trim().parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unknown log level: {level}"),
+        )
+    })?;
+    let handle = LOG_FILTER
+        .get()
+        .ok_or_else(|| io::Error::other("Tracing is not initialized"))?;
+
+    handle
+        .reload(EnvFilter::default().add_directive(level.into()))
+        .map_err(io::Error::other)?;
+    tracing::info!("FFF log level set to {}", level);
+    Ok(level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_file_is_a_valid_trace() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("profile.json");
+        let subscriber = tracing_subscriber::registry().with(ChromeTraceLayer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            assert_eq!(
+                set_profiling(true, Some(&path)).unwrap(),
+                Some(path.clone())
+            );
+            tracing::info_span!("outer").in_scope(|| {
+                tracing::info_span!("inner").in_scope(|| {});
+            });
+            assert_eq!(set_profiling(false, None).unwrap(), Some(path.clone()));
+            tracing::info_span!("after_stop").in_scope(|| {});
+        });
+
+        let events: Vec<serde_json::Value> =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let names: Vec<_> = events.iter().map(|e| e["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["inner", "outer"]);
+        assert!(events.iter().all(|e| e["ph"] == "X"));
+    }
+}
@@ -0,0 +1,178 @@
+//! Latency of recent searches, for "it feels slow" reports.
+//!
+//! Every fuzzy search and grep records how long it took. The last
+//! [`MAX_SAMPLES`] durations of each kind are kept to compute percentiles,
+//! together with the few slowest queries seen since startup, and are served
+//! through the health check. The durations not yet saved to the weekly
+//! history of [`crate::perf_history`] are also counted in a histogram.
+
+use crate::perf_history::LatencyHistogram;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Durations kept per search kind for percentile computation.
+pub const MAX_SAMPLES: usize = 1024;
+/// Slowest queries kept per search kind.
+const MAX_SLOWEST: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchKind {
+    Fuzzy,
+    Grep,
+}
+
+impl SearchKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Fuzzy => "fuzzy",
+            Self::Grep => "grep",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowQuery {
+    pub query: String,
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencySummary {
+    /// Searches recorded since startup, including evicted samples.
+    pub count: u64,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    /// Slowest queries since startup, slowest first.
+    pub slowest: Vec<SlowQuery>,
+}
+
+#[derive(Debug, Default)]
+struct KindMetrics {
+    count: u64,
+    samples: VecDeque<Duration>,
+    slowest: Vec<SlowQuery>,
+    unsaved: LatencyHistogram,
+}
+
+impl KindMetrics {
+    fn record(&mut self, query: &str, duration: Duration) {
+        self.count += 1;
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(duration);
+        self.unsaved.record(duration);
+
+        let is_slow = self.slowest.len() < MAX_SLOWEST
+            || self.slowest.last().is_some_and(|s| duration > s.duration);
+        if is_slow {
+            let pos = self.slowest.partition_point(|s| s.duration >= duration);
+            self.slowest.insert(
+                pos,
+                SlowQuery {
+                    query: query.to_string(),
+                    duration,
+                },
+            );
+            self.slowest.truncate(MAX_SLOWEST);
+        }
+    }
+
+    fn summary(&self) -> LatencySummary {
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let percentile = |p: usize| {
+            if sorted.is_empty() {
+                return Duration::ZERO;
+            }
+            // nearest-rank
+            let rank = (p * sorted.len()).div_ceil(100).max(1);
+            sorted[rank - 1]
+        };
+
+        LatencySummary {
+            count: self.count,
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+            max: sorted.last().copied().unwrap_or_default(),
+            slowest: self.slowest.clone(),
+        }
+    }
+}
+
+static METRICS: Mutex<[KindMetrics; 2]> = Mutex::new([
+    KindMetrics {
+        count: 0,
+        samples: VecDeque::new(),
+        slowest: Vec::new(),
+        unsaved: LatencyHistogram::new(),
+    },
+    KindMetrics {
+        count: 0,
+        samples: VecDeque::new(),
+        slowest: Vec::new(),
+        unsaved: LatencyHistogram::new(),
+    },
+]);
+
+pub fn record(kind: SearchKind, query: &str, duration: Duration) {
+    METRICS.lock()[kind as usize].record(query, duration);
+}
+
+pub fn summary(kind: SearchKind) -> LatencySummary {
+    METRICS.lock()[kind as usize].summary()
+}
+
+/// Durations recorded since the previous call, to be saved to the weekly
+/// history.
+pub fn take_unsaved(kind: SearchKind) -> LatencyHistogram {
+    std::mem::take(&mut METRICS.lock()[kind as usize].unsaved)
+}
+
+pub fn reset() {
+    *METRICS.lock() = Default::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_and_slowest_queries() {
+        let mut metrics = KindMetrics::default();
+        for ms in 1..=100 {
+            metrics.record(&format!("q{ms}"), Duration::from_millis(ms));
+        }
+
+        let summary = metrics.summary();
+        assert_eq!(summary.count, 100);
+        assert_eq!(summary.p50, Duration::from_millis(50));
+        assert_eq!(summary.p95, Duration::from_millis(95));
+        assert_eq!(summary.p99, Duration::from_millis(99));
+        assert_eq!(summary.max, Duration::from_millis(100));
+        assert_eq!(
+            summary
+                .slowest
+                .iter()
+                .map(|s| s.query.as_str())
+                .collect::<Vec<_>>(),
+            ["q100", "q99", "q98", "q97", "q96"]
+        );
+
+        for _ in 0..MAX_SAMPLES {
+            metrics.record("fast", Duration::from_micros(10));
+        }
+        let summary = metrics.summary();
+        assert_eq!(summary.count, 100 + MAX_SAMPLES as u64);
+        assert_eq!(
+            summary.p99,
+            Duration::from_micros(10),
+            "old samples evicted"
+        );
+        assert_eq!(summary.slowest[0].query, "q100");
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 #[cfg(windows)]
 pub fn canonicalize(path: impl AsRef<Path>) -> std::io::Result<PathBuf> {
@@ -26,6 +26,58 @@ pub fn expand_tilde(path: &str) -> PathBuf {
     PathBuf::from(path)
 }
 
+/// The form of `path` the frecency and query history databases are keyed
+/// by, so a file reached through a symlink, `..` or a firmlink like macOS
+/// `/private/tmp` keeps its history. Resolves the path when it exists and
+/// only removes `.` and `..` otherwise, e.g. for a deleted file.
+pub fn normalize_key(path: &Path) -> PathBuf {
+    if let Ok(canonical) = canonicalize(path) {
+        return canonical;
+    }
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Whether `path` is the home directory or one of its ancestors, where a full
+/// scan reaches every project, cache and download of the user at once.
+pub fn is_broad_root(path: &Path) -> bool {
+    let Some(home) = dirs::home_dir() else {
+        return path.parent().is_none();
+    };
+    let home = canonicalize(&home).unwrap_or(home);
+    let path = canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    home.starts_with(path)
+}
+
+/// Id of the mounted filesystem `path` is on, for telling mount points apart
+/// from regular directories. `None` when it can't be read, or on platforms
+/// without a stable device id (Windows, where the `ignore` walker compares
+/// volume serial numbers itself).
+#[cfg(unix)]
+pub fn device_id(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::symlink_metadata(path)
+        .ok()
+        .map(|metadata| metadata.dev())
+}
+
+#[cfg(not(unix))]
+pub fn device_id(_path: &Path) -> Option<u64> {
+    None
+}
+
 /// Calculate distance penalty based on directory proximity
 /// Returns a negative penalty score based on how far the candidate is from the current file
 pub fn calculate_distance_penalty(current_file: Option<&str>, candidate_path: &str) -> i32 {
@@ -79,6 +131,24 @@ pub fn calculate_distance_penalty(current_file: Option<&str>, candidate_path: &s
 mod tests {
     use super::*;
 
+    #[test]
+    #[cfg(unix)]
+    fn test_normalize_key() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = canonicalize(dir.path()).unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "").unwrap();
+        std::os::unix::fs::symlink(root.join("src"), root.join("link")).unwrap();
+
+        let key = root.join("src/main.rs");
+        assert_eq!(normalize_key(&root.join("link/main.rs")), key);
+        assert_eq!(normalize_key(&root.join("src/../src/./main.rs")), key);
+        assert_eq!(
+            normalize_key(&root.join("src/../gone/./deleted.rs")),
+            root.join("gone/deleted.rs")
+        );
+    }
+
     #[test]
     #[cfg(not(target_family = "windows"))]
     fn test_calculate_distance_penalty() {
@@ -158,4 +228,31 @@ mod tests {
             -1
         );
     }
+
+    #[test]
+    fn home_and_its_ancestors_are_broad_roots() {
+        let Some(home) = dirs::home_dir() else { return };
+        assert!(is_broad_root(&home));
+        if let Some(parent) = home.parent() {
+            assert!(is_broad_root(parent));
+        }
+
+        let project = tempfile::tempdir().unwrap();
+        assert!(!is_broad_root(project.path()));
+        assert!(!is_broad_root(
+            &home.join("some-project-that-does-not-exist")
+        ));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn device_id_is_shared_within_a_filesystem() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("file.txt"), "").unwrap();
+
+        let root = device_id(dir.path());
+        assert!(root.is_some());
+        assert_eq!(device_id(&dir.path().join("file.txt")), root);
+        assert_eq!(device_id(&dir.path().join("missing")), None);
+    }
 }
@@ -0,0 +1,140 @@
+//! Search latency and index size kept per week, for trends across sessions.
+//!
+//! [`crate::metrics`] only knows the current session. When the history of a
+//! project is opted into, the durations recorded since the last save are
+//! folded into a [`LatencyHistogram`] of the current week and stored with
+//! the size of the index by [`QueryTracker::record_perf`], so the health
+//! check can tell "median search latency this week: 12ms, last week 9ms".
+//! Only durations and counts are stored, never queries or paths.
+//!
+//! [`QueryTracker::record_perf`]: crate::query_tracker::QueryTracker::record_perf
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Weeks of history kept per project.
+pub const MAX_WEEKS: usize = 8;
+/// Buckets per doubling of the duration, so a bucket spans ~19%.
+const BUCKETS_PER_OCTAVE: f64 = 4.0;
+/// Durations from 1µs to over a minute.
+const MAX_BUCKETS: usize = 104;
+
+pub(crate) const SECONDS_PER_WEEK: u64 = 7 * 24 * 60 * 60;
+
+/// Counts of search durations in logarithmic buckets of microseconds.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    counts: Vec<u32>,
+}
+
+impl LatencyHistogram {
+    pub const fn new() -> Self {
+        Self { counts: Vec::new() }
+    }
+
+    fn bucket(duration: Duration) -> usize {
+        let micros = duration.as_micros() as f64;
+        ((micros + 1.0).log2() * BUCKETS_PER_OCTAVE) as usize
+    }
+
+    pub fn record(&mut self, duration: Duration) {
+        let bucket = Self::bucket(duration).min(MAX_BUCKETS - 1);
+        if self.counts.len() <= bucket {
+            self.counts.resize(bucket + 1, 0);
+        }
+        self.counts[bucket] = self.counts[bucket].saturating_add(1);
+    }
+
+    /// Add the counts of `other`.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        if self.counts.len() < other.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count = count.saturating_add(*other);
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().map(|&c| c as u64).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count() == 0
+    }
+
+    /// The `p`th percentile, accurate to the bucket it falls in.
+    pub fn percentile(&self, p: u8) -> Option<Duration> {
+        let total = self.count();
+        if total == 0 {
+            return None;
+        }
+        // nearest-rank, like the session metrics
+        let rank = (p as u64 * total).div_ceil(100).max(1);
+        let mut seen = 0;
+        let bucket = self.counts.iter().position(|&count| {
+            seen += count as u64;
+            seen >= rank
+        })?;
+
+        // geometric middle of the bucket
+        let micros = ((bucket as f64 + 0.5) / BUCKETS_PER_OCTAVE).exp2() - 1.0;
+        Some(Duration::from_micros(micros.round() as u64))
+    }
+
+    pub fn median(&self) -> Option<Duration> {
+        self.percentile(50)
+    }
+}
+
+/// What was recorded for a project during one week.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PerfWeek {
+    /// Weeks since the unix epoch.
+    pub week: u64,
+    pub fuzzy: LatencyHistogram,
+    pub grep: LatencyHistogram,
+    /// Largest number of indexed files seen during the week.
+    pub max_indexed_files: u64,
+}
+
+impl PerfWeek {
+    /// Start of the week as a unix timestamp.
+    pub fn started_at(&self) -> u64 {
+        self.week * SECONDS_PER_WEEK
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_median_within_a_bucket() {
+        let mut histogram = LatencyHistogram::default();
+        for ms in [8, 9, 12, 12, 13, 40, 900] {
+            histogram.record(Duration::from_millis(ms));
+        }
+
+        let median = histogram.median().unwrap();
+        assert!(
+            (Duration::from_millis(11)..Duration::from_millis(14)).contains(&median),
+            "{median:?}"
+        );
+        assert_eq!(histogram.count(), 7);
+        assert!(LatencyHistogram::default().median().is_none());
+    }
+
+    #[test]
+    fn test_merge_adds_counts() {
+        let mut a = LatencyHistogram::default();
+        a.record(Duration::from_micros(50));
+        let mut b = LatencyHistogram::default();
+        b.record(Duration::from_secs(2));
+        b.record(Duration::from_secs(3600));
+
+        a.merge(&b);
+        assert_eq!(a.count(), 3);
+        assert!(a.percentile(100).unwrap() > Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,65 @@
+use std::path::Path;
+
+/// Whether the machine runs on battery, for
+/// [`BackgroundPriority::skip_warmup`](crate::BackgroundPriority::skip_warmup).
+///
+/// Reads `/sys/class/power_supply` on Linux and `pmset` on macOS. Always
+/// `false` elsewhere or when the power source can't be determined.
+pub fn on_battery_power() -> bool {
+    if cfg!(target_os = "macos") {
+        std::process::Command::new("pmset")
+            .args(["-g", "batt"])
+            .output()
+            .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("'Battery Power'"))
+    } else if cfg!(target_os = "linux") {
+        any_battery_discharging(Path::new("/sys/class/power_supply"))
+    } else {
+        false
+    }
+}
+
+/// A battery only reports `Discharging` while no charger is plugged in.
+fn any_battery_discharging(power_supply: &Path) -> bool {
+    let Ok(entries) = std::fs::read_dir(power_supply) else {
+        return false;
+    };
+    let read = |dir: &Path, name: &str| {
+        std::fs::read_to_string(dir.join(name))
+            .map(|value| value.trim().to_owned())
+            .unwrap_or_default()
+    };
+
+    entries.flatten().any(|entry| {
+        let dir = entry.path();
+        read(&dir, "type") == "Battery" && read(&dir, "status") == "Discharging"
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn supply(root: &Path, name: &str, kind: &str, status: Option<&str>) {
+        let dir = root.join(name);
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("type"), format!("{kind}\n")).unwrap();
+        if let Some(status) = status {
+            fs::write(dir.join("status"), format!("{status}\n")).unwrap();
+        }
+    }
+
+    #[test]
+    fn detects_discharging_battery() {
+        let root = tempfile::tempdir().unwrap();
+        assert!(!any_battery_discharging(root.path()));
+
+        supply(root.path(), "AC", "Mains", None);
+        supply(root.path(), "BAT0", "Battery", Some("Charging"));
+        assert!(!any_battery_discharging(root.path()));
+
+        fs::write(root.path().join("BAT0/status"), "Discharging\n").unwrap();
+        assert!(any_battery_discharging(root.path()));
+        assert!(!any_battery_discharging(&root.path().join("missing")));
+    }
+}
@@ -0,0 +1,94 @@
+//! Preview hunks cut out of the cached file contents.
+//!
+//! A grep match carries the byte offset of its line, so the preview of a
+//! match deep in a large file can be read from the mmap the search already
+//! holds, stepping a few lines around the offset, instead of opening the
+//! file again and splitting it line by line from the top.
+
+use fff_grep::LineTerminator;
+use fff_grep::lines::{self, LineStep};
+
+/// Lines around a byte offset of a file, see
+/// [`FilePicker::read_context_at`](crate::FilePicker::read_context_at).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreviewHunk {
+    /// 1-based line number of the first of `lines`.
+    pub first_line: u64,
+    /// 1-based line number of the line containing the offset.
+    pub target_line: u64,
+    /// The lines without their terminators, invalid UTF-8 replaced.
+    pub lines: Vec<String>,
+}
+
+/// The line containing `byte_offset` with up to `lines_before` lines before
+/// it and `lines_after` after it. `None` when the offset is past the end.
+pub(crate) fn hunk_at(
+    content: &[u8],
+    byte_offset: usize,
+    lines_before: usize,
+    lines_after: usize,
+) -> Option<PreviewHunk> {
+    if byte_offset >= content.len() {
+        return None;
+    }
+
+    let line_start = |end: usize| memchr::memrchr(b'\n', &content[..end]).map_or(0, |i| i + 1);
+    let target_start = line_start(byte_offset);
+    let mut start = target_start;
+    let mut before = 0;
+    while before < lines_before && start > 0 {
+        start = line_start(start - 1);
+        before += 1;
+    }
+
+    // counting newlines is a single SIMD pass, unlike splitting the lines
+    let first_line = lines::count(&content[..start], b'\n') + 1;
+    let mut stepper = LineStep::new(b'\n', start, content.len());
+    let mut hunk_lines = Vec::with_capacity(before + 1 + lines_after);
+    while hunk_lines.len() <= before + lines_after
+        && let Some(line) = stepper.next_match(content)
+    {
+        let line = lines::without_terminator(
+            lines::without_terminator(&content[line], LineTerminator::byte(b'\n')),
+            LineTerminator::byte(b'\r'),
+        );
+        hunk_lines.push(String::from_utf8_lossy(line).into_owned());
+    }
+
+    Some(PreviewHunk {
+        first_line,
+        target_line: first_line + before as u64,
+        lines: hunk_lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &[u8] = b"one\ntwo\r\nthree\nfour\nfive\n";
+
+    #[test]
+    fn test_hunk_around_offset() {
+        let offset = CONTENT.windows(5).position(|w| w == b"three").unwrap() + 2;
+        let hunk = hunk_at(CONTENT, offset, 1, 1).unwrap();
+        assert_eq!(hunk.first_line, 2);
+        assert_eq!(hunk.target_line, 3);
+        assert_eq!(hunk.lines, ["two", "three", "four"]);
+    }
+
+    #[test]
+    fn test_hunk_clamped_to_the_file() {
+        let hunk = hunk_at(CONTENT, 0, 3, 10).unwrap();
+        assert_eq!(hunk.first_line, 1);
+        assert_eq!(hunk.target_line, 1);
+        assert_eq!(hunk.lines.len(), 5);
+
+        let last = CONTENT.len() - 2;
+        let hunk = hunk_at(CONTENT, last, 2, 2).unwrap();
+        assert_eq!((hunk.first_line, hunk.target_line), (3, 5));
+        assert_eq!(hunk.lines, ["three", "four", "five"]);
+
+        assert_eq!(hunk_at(CONTENT, CONTENT.len(), 1, 1), None);
+    }
+}
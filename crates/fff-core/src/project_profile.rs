@@ -0,0 +1,211 @@
+//! Project type detection and the score adjustments that come with it.
+//!
+//! Most build output is already gitignored, but plenty of it is not (checked
+//! in `dist/` bundles, vendored Go modules, generated Python packages) and
+//! with short paths those files tend to outrank the sources they were built
+//! from. When the picker is created we look for the usual manifest files in
+//! the base directory and deprioritize the output directories of every
+//! detected ecosystem while giving source directories a small boost.
+
+use std::path::Path;
+
+/// Score multiplier, in percent, for files under a deprioritized directory.
+const DEPRIORITIZED_PERCENT: i32 = 50;
+/// Score multiplier, in percent, for files under a boosted directory.
+const BOOSTED_PERCENT: i32 = 115;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectKind {
+    Cargo,
+    Npm,
+    Python,
+    Go,
+}
+
+impl ProjectKind {
+    const ALL: [ProjectKind; 4] = [Self::Cargo, Self::Npm, Self::Python, Self::Go];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo",
+            Self::Npm => "npm",
+            Self::Python => "python",
+            Self::Go => "go",
+        }
+    }
+
+    fn markers(self) -> &'static [&'static str] {
+        match self {
+            Self::Cargo => &["Cargo.toml"],
+            Self::Npm => &["package.json"],
+            Self::Python => &[
+                "pyproject.toml",
+                "setup.py",
+                "setup.cfg",
+                "requirements.txt",
+            ],
+            Self::Go => &["go.mod"],
+        }
+    }
+
+    fn deprioritized_dirs(self) -> &'static [&'static str] {
+        match self {
+            Self::Cargo => &["target"],
+            Self::Npm => &["dist", "build", "coverage", ".next", "out"],
+            Self::Python => &["build", "dist", "__pycache__", ".venv", "venv", ".tox"],
+            Self::Go => &["vendor"],
+        }
+    }
+
+    fn boosted_dirs(self) -> &'static [&'static str] {
+        match self {
+            Self::Cargo | Self::Npm | Self::Python => &["src"],
+            Self::Go => &["cmd", "internal"],
+        }
+    }
+}
+
+/// Overrides for the detected profile, see [`crate::FilePickerOptions`].
+#[derive(Debug, Clone)]
+pub struct ProjectProfileOptions {
+    /// When `false` no detection happens and scores are left alone.
+    pub enabled: bool,
+    /// Replaces the recommended deprioritized directories.
+    pub deprioritized_dirs: Option<Vec<String>>,
+    /// Replaces the recommended boosted directories.
+    pub boosted_dirs: Option<Vec<String>>,
+}
+
+impl Default for ProjectProfileOptions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            deprioritized_dirs: None,
+            boosted_dirs: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProjectProfile {
+    pub kinds: Vec<ProjectKind>,
+    pub deprioritized_dirs: Vec<String>,
+    pub boosted_dirs: Vec<String>,
+}
+
+impl ProjectProfile {
+    /// Detects the project types of `base_path` from its manifest files.
+    /// Returns `None` when disabled or when nothing would be adjusted.
+    pub fn detect(base_path: &Path, options: &ProjectProfileOptions) -> Option<Self> {
+        if !options.enabled {
+            return None;
+        }
+
+        let kinds: Vec<_> = ProjectKind::ALL
+            .into_iter()
+            .filter(|kind| {
+                kind.markers()
+                    .iter()
+                    .any(|marker| base_path.join(marker).is_file())
+            })
+            .collect();
+
+        let recommended = |dirs: fn(ProjectKind) -> &'static [&'static str]| {
+            let mut all: Vec<String> = Vec::new();
+            for dir in kinds.iter().flat_map(|&kind| dirs(kind)) {
+                if !all.iter().any(|known| known == dir) {
+                    all.push((*dir).to_string());
+                }
+            }
+            all
+        };
+
+        let profile = Self {
+            deprioritized_dirs: options
+                .deprioritized_dirs
+                .clone()
+                .unwrap_or_else(|| recommended(ProjectKind::deprioritized_dirs)),
+            boosted_dirs: options
+                .boosted_dirs
+                .clone()
+                .unwrap_or_else(|| recommended(ProjectKind::boosted_dirs)),
+            kinds,
+        };
+
+        if profile.deprioritized_dirs.is_empty() && profile.boosted_dirs.is_empty() {
+            return None;
+        }
+        Some(profile)
+    }
+
+    /// Score multiplier in percent for a file, based on the directories of
+    /// its relative path. Deprioritization wins over a boost, so
+    /// `dist/src/index.js` is still pushed down.
+    pub fn multiplier_for(&self, relative_path: &str) -> Option<i32> {
+        let mut boosted = false;
+        for dir in relative_path.split(['/', '\\']).rev().skip(1) {
+            if self.deprioritized_dirs.iter().any(|d| d == dir) {
+                return Some(DEPRIORITIZED_PERCENT);
+            }
+            boosted |= self.boosted_dirs.iter().any(|d| d == dir);
+        }
+        boosted.then_some(BOOSTED_PERCENT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn detects_every_ecosystem_in_the_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("Cargo.toml"), "[package]").unwrap();
+        fs::write(tmp.path().join("package.json"), "{}").unwrap();
+
+        let profile = ProjectProfile::detect(tmp.path(), &Default::default()).unwrap();
+        assert_eq!(profile.kinds, [ProjectKind::Cargo, ProjectKind::Npm]);
+        assert!(profile.deprioritized_dirs.contains(&"target".to_string()));
+        assert!(profile.deprioritized_dirs.contains(&"dist".to_string()));
+        assert_eq!(profile.boosted_dirs, ["src"]);
+
+        assert_eq!(profile.multiplier_for("dist/index.js"), Some(50));
+        assert_eq!(
+            profile.multiplier_for("packages/ui/dist/src/index.js"),
+            Some(50)
+        );
+        assert_eq!(profile.multiplier_for("crates/core/src/lib.rs"), Some(115));
+        assert_eq!(profile.multiplier_for("README.md"), None);
+        assert_eq!(
+            profile.multiplier_for("docs/dist"),
+            None,
+            "file names are not dirs"
+        );
+    }
+
+    #[test]
+    fn options_override_or_disable_the_profile() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("go.mod"), "module x").unwrap();
+
+        let options = ProjectProfileOptions {
+            deprioritized_dirs: Some(vec!["third_party".into()]),
+            boosted_dirs: Some(vec![]),
+            ..Default::default()
+        };
+        let profile = ProjectProfile::detect(tmp.path(), &options).unwrap();
+        assert_eq!(profile.kinds, [ProjectKind::Go]);
+        assert_eq!(profile.multiplier_for("third_party/lib.go"), Some(50));
+        assert_eq!(profile.multiplier_for("vendor/lib.go"), None);
+
+        let disabled = ProjectProfileOptions {
+            enabled: false,
+            ..Default::default()
+        };
+        assert!(ProjectProfile::detect(tmp.path(), &disabled).is_none());
+
+        let empty = tempfile::tempdir().unwrap();
+        assert!(ProjectProfile::detect(empty.path(), &Default::default()).is_none());
+    }
+}
@@ -0,0 +1,242 @@
+//! Warnings about query tokens that don't do what they look like.
+//!
+//! A typo in a constraint usually doesn't fail the search, it quietly turns
+//! the token into fuzzy text or a filter nothing passes, and the user gets
+//! zero results without a hint why. [`validate_query`] finds those tokens so
+//! the prompt can underline them while the user types.
+
+use std::ops::Range;
+
+use fff_query_parser::{
+    Constraint, FFFQuery, FileSearchConfig, FuzzyQuery, GrepConfig, ParserConfig, QueryParser,
+};
+
+use crate::grep::{GrepMode, build_regex};
+
+/// Keys of the `key:value` constraints the file picker understands.
+const KNOWN_KEYS: &[&str] = &["type", "status", "st", "g", "git"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryDiagnosticKind {
+    /// `foo:bar` where `foo` is not a constraint key, searched as text.
+    UnknownKey,
+    /// `status:` with a value that is not a git status, searched as text.
+    UnknownGitStatus,
+    /// Glob constraint that does not compile and matches no file.
+    InvalidGlob,
+    /// Regex grep pattern that does not compile, searched as plain text.
+    InvalidRegex,
+    /// A lone `!` with nothing to negate.
+    EmptyNegation,
+}
+
+impl QueryDiagnosticKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::UnknownKey => "unknown_key",
+            Self::UnknownGitStatus => "unknown_git_status",
+            Self::InvalidGlob => "invalid_glob",
+            Self::InvalidRegex => "invalid_regex",
+            Self::EmptyNegation => "empty_negation",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryDiagnostic {
+    pub kind: QueryDiagnosticKind,
+    pub message: String,
+    /// Byte range of the offending token in the query.
+    pub span: Range<usize>,
+}
+
+/// Validates a file picker query, or a grep query when `grep_mode` is set.
+/// Diagnostics are ordered by their position in the query.
+pub fn validate_query(query: &str, grep_mode: Option<GrepMode>) -> Vec<QueryDiagnostic> {
+    let mut diagnostics = match grep_mode {
+        None => validate_with(query, &QueryParser::new(FileSearchConfig), true),
+        Some(mode) => {
+            let parser = QueryParser::new(GrepConfig);
+            let mut diagnostics = validate_with(query, &parser, false);
+            if mode == GrepMode::Regex {
+                diagnostics.extend(validate_regex(query, &parser.parse(query)));
+            }
+            diagnostics
+        }
+    };
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    diagnostics
+}
+
+fn validate_with<C: ParserConfig>(
+    query: &str,
+    parser: &QueryParser<C>,
+    check_keys: bool,
+) -> Vec<QueryDiagnostic> {
+    let (parsed, spans) = parser.parse_with_spans(query);
+    let mut diagnostics = Vec::new();
+
+    for (constraint, span) in parsed.constraints.iter().zip(&spans) {
+        if let Some(message) = invalid_glob(constraint) {
+            diagnostics.push(QueryDiagnostic {
+                kind: QueryDiagnosticKind::InvalidGlob,
+                message,
+                span: span.clone(),
+            });
+        }
+    }
+
+    // Tokens that did not become a constraint are searched as text.
+    for (token, span) in tokens_with_spans(query) {
+        if spans.contains(&span) {
+            continue;
+        }
+        let diagnostic = |kind, message| QueryDiagnostic {
+            kind,
+            message,
+            span: span.clone(),
+        };
+
+        if token == "!" {
+            diagnostics.push(diagnostic(
+                QueryDiagnosticKind::EmptyNegation,
+                "Nothing to exclude after `!`".to_string(),
+            ));
+            continue;
+        }
+
+        let Some((key, value)) = token.split_once(':').filter(|_| check_keys) else {
+            continue;
+        };
+        if matches!(key, "status" | "st" | "g" | "git") && !value.is_empty() {
+            diagnostics.push(diagnostic(
+                QueryDiagnosticKind::UnknownGitStatus,
+                format!(
+                    "Unknown git status `{value}`, expected modified, untracked, staged or clean"
+                ),
+            ));
+        } else if looks_like_constraint_key(key, value) {
+            diagnostics.push(diagnostic(
+                QueryDiagnosticKind::UnknownKey,
+                format!(
+                    "Unknown filter `{key}:`, expected one of {}",
+                    KNOWN_KEYS.join(", ")
+                ),
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// `foo:bar` reads as a filter, `std::fmt`, `http://`, `file.rs:12` and
+/// `TODO:` don't.
+fn looks_like_constraint_key(key: &str, value: &str) -> bool {
+    !KNOWN_KEYS.contains(&key)
+        && key.len() >= 2
+        && key.bytes().all(|b| b.is_ascii_lowercase())
+        && value
+            .bytes()
+            .next()
+            .is_some_and(|b| b.is_ascii_alphabetic())
+}
+
+fn invalid_glob(constraint: &Constraint<'_>) -> Option<String> {
+    match constraint {
+        Constraint::Glob(pattern) => globset::Glob::new(pattern)
+            .err()
+            .map(|e| format!("Invalid glob: {}", e.kind())),
+        Constraint::Not(inner) => invalid_glob(inner),
+        _ => None,
+    }
+}
+
+fn validate_regex(query: &str, parsed: &FFFQuery<'_>) -> Option<QueryDiagnostic> {
+    if matches!(parsed.fuzzy_query, FuzzyQuery::Empty) {
+        return None;
+    }
+    let error = build_regex(&parsed.grep_text(), false).err()?;
+
+    // The pattern spans from its first to its last text token.
+    let parts = match &parsed.fuzzy_query {
+        FuzzyQuery::Text(text) => std::slice::from_ref(text),
+        FuzzyQuery::Parts(parts) => parts.as_slice(),
+        FuzzyQuery::Empty => &[],
+    };
+    let offset = |part: &str| part.as_ptr() as usize - query.as_ptr() as usize;
+    let start = parts.first().map_or(0, |part| offset(part));
+    let end = parts
+        .last()
+        .map_or(query.len(), |part| offset(part) + part.len());
+
+    Some(QueryDiagnostic {
+        kind: QueryDiagnosticKind::InvalidRegex,
+        // regex errors are multi-line with a caret diagram, keep the summary
+        message: format!(
+            "Invalid regex, searching as plain text: {}",
+            error.lines().last().unwrap_or(&error).trim()
+        ),
+        span: start..end,
+    })
+}
+
+fn tokens_with_spans(query: &str) -> impl Iterator<Item = (&str, Range<usize>)> {
+    query.split_whitespace().map(move |token| {
+        let start = token.as_ptr() as usize - query.as_ptr() as usize;
+        (token, start..start + token.len())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(query: &str, grep_mode: Option<GrepMode>) -> Vec<(QueryDiagnosticKind, &str)> {
+        validate_query(query, grep_mode)
+            .into_iter()
+            .map(|d| (d.kind, &query[d.span]))
+            .collect()
+    }
+
+    #[test]
+    fn valid_queries_have_no_diagnostics() {
+        assert!(kinds("main *.rs !test/ status:modified type:rust", None).is_empty());
+        assert!(kinds("main.rs:12 std::fmt http://x.y TODO:fix", None).is_empty());
+        assert!(kinds("fn search *.rs", Some(GrepMode::Regex)).is_empty());
+    }
+
+    #[test]
+    fn reports_unknown_keys_and_status() {
+        assert_eq!(
+            kinds("main typ:rust status:dirty", None),
+            vec![
+                (QueryDiagnosticKind::UnknownKey, "typ:rust"),
+                (QueryDiagnosticKind::UnknownGitStatus, "status:dirty"),
+            ]
+        );
+        // Grep patterns often contain `word:word`, those are searched as is.
+        assert!(kinds("note:todo", Some(GrepMode::PlainText)).is_empty());
+    }
+
+    #[test]
+    fn reports_empty_negation_and_invalid_glob() {
+        assert_eq!(
+            kinds("main ! src/**/{a,b", None),
+            vec![
+                (QueryDiagnosticKind::EmptyNegation, "!"),
+                (QueryDiagnosticKind::InvalidGlob, "src/**/{a,b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_regex_errors_only_in_regex_mode() {
+        let query = "*.rs fn (search";
+        let diagnostics = validate_query(query, Some(GrepMode::Regex));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind, QueryDiagnosticKind::InvalidRegex);
+        assert_eq!(&query[diagnostics[0].span.clone()], "fn (search");
+
+        assert!(validate_query(query, Some(GrepMode::PlainText)).is_empty());
+    }
+}
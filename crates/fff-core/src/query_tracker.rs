@@ -1,15 +1,31 @@
-use crate::db_healthcheck::DbHealthChecker;
+use crate::db_healthcheck::{self, DbHealth, DbHealthChecker, DbRepair};
 use crate::error::Error;
-use heed::types::Bytes;
-use heed::{Database, Env, EnvOpenOptions};
-use heed::{EnvFlags, types::SerdeBincode};
+use crate::file_ops::FileOpJournalEntry;
+use crate::path_utils::normalize_key;
+use crate::perf_history::{self, LatencyHistogram, PerfWeek};
+use crate::shared::SharedQueryTracker;
+use crate::storage::{self, Schema, Storage, StorageBackend, StorageOptions, StorageWrite, Table};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
-use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 const MAX_HISTORY_ENTRIES: usize = 128;
+const MAX_FILE_OP_JOURNAL_ENTRIES: usize = 64;
+const MAX_GREP_JUMP_ENTRIES: usize = 64;
+const MIN_MAP_SIZE: usize = 10 * 1024 * 1024; // 10 MiB
+const MAX_DBS: u32 = 16;
+/// A combo counts half as many opens for every this long it isn't opened.
+const COMBO_HALF_LIFE_SECS: u64 = 14 * 24 * 60 * 60;
+/// Files remembered per query, so a query leading to a few files boosts all of them.
+pub const MAX_COMBO_CANDIDATES: usize = 3;
+/// Files opened within this long of each other count as opened together.
+const CO_OPEN_WINDOW_SECS: u64 = 10 * 60;
+const MAX_RECENT_OPENS: usize = 16;
+/// Files remembered as opened together with each file.
+const MAX_CO_OPENED: usize = 32;
 
 /// Simplified QueryFileEntry without redundant fields
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -19,6 +35,68 @@ pub struct QueryMatchEntry {
     pub last_opened: u64,   // Unix timestamp
 }
 
+/// Grep result (file and line) last opened for a grep query
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GrepQueryMatchEntry {
+    pub file_path: PathBuf,
+    pub line_number: u64,
+    pub open_count: u32,
+    pub last_opened: u64,
+}
+
+/// Grep result the user jumped to, kept per project most recent last
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct GrepJumpEntry {
+    pub file_path: PathBuf,
+    pub line_number: u64,
+    pub query: String,
+    pub timestamp: u64,
+}
+
+/// Where the picker was left when it was last closed in a project
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct LastSearchState {
+    pub query: String,
+    /// `"files"` or `"grep"`
+    pub mode: String,
+    pub selected_index: u32,
+    pub timestamp: u64,
+}
+
+/// Query saved under a name to rerun it later, e.g. a recurring audit
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+    /// `"files"` or `"grep"`
+    pub mode: String,
+    pub created_at: u64,
+}
+
+/// A file opened together with another one, see [`QueryTracker::track_file_open`]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CoOpenEntry {
+    /// Relative to the project
+    pub relative_path: String,
+    pub count: u32,
+    pub last_opened: u64,
+}
+
+/// File opened in a project, kept for [`CO_OPEN_WINDOW_SECS`]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct RecentOpen {
+    relative_path: String,
+    timestamp: u64,
+}
+
+/// How often a query shows up in the recent history of a project
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct QueryUsage {
+    pub query: String,
+    pub count: usize,
+    pub last_used: u64,
+}
+
 /// Entry for query history tracking
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct HistoryEntry {
@@ -26,81 +104,101 @@ struct HistoryEntry {
     timestamp: u64,
 }
 
+// (project_path, query) -> QueryMatchEntry mappings
+const QUERY_FILES: Table<QueryMatchEntry> = Table::new(Some("query_file_associations"));
+// (project_path, query) -> Vec<QueryMatchEntry> (files opened for the query, each with its own count)
+const QUERY_FILE_CANDIDATES: Table<Vec<QueryMatchEntry>> =
+    Table::new(Some("query_file_candidates"));
+// project_path -> VecDeque<HistoryEntry> mappings (file picker)
+const QUERY_HISTORY: Table<VecDeque<HistoryEntry>> = Table::new(Some("query_history"));
+// project_path -> VecDeque<HistoryEntry> mappings (grep)
+const GREP_QUERY_HISTORY: Table<VecDeque<HistoryEntry>> = Table::new(Some("grep_query_history"));
+// (project_path, grep query) -> GrepQueryMatchEntry mappings
+const GREP_QUERY_FILES: Table<GrepQueryMatchEntry> =
+    Table::new(Some("grep_query_file_associations"));
+// project_path -> VecDeque<GrepJumpEntry> (recently opened grep results)
+const GREP_JUMPS: Table<VecDeque<GrepJumpEntry>> = Table::new(Some("grep_jumps"));
+// project_path -> LastSearchState (picker state on last close)
+const LAST_SEARCH_STATE: Table<LastSearchState> = Table::new(Some("last_search_state"));
+// project_path -> Vec<SavedSearch> (named queries, in the order they were saved)
+const SAVED_SEARCHES: Table<Vec<SavedSearch>> = Table::new(Some("saved_searches"));
+// project_path -> VecDeque<PerfWeek> (opt-in weekly latency and index size, oldest first)
+const PERF_HISTORY: Table<VecDeque<PerfWeek>> = Table::new(Some("perf_history"));
+// project_path -> VecDeque<RecentOpen> (files opened within the co-open window, oldest first)
+const RECENT_OPENS: Table<VecDeque<RecentOpen>> = Table::new(Some("recent_opens"));
+// (project_path, relative path) -> Vec<CoOpenEntry> (files opened together with it)
+const CO_OPENS: Table<Vec<CoOpenEntry>> = Table::new(Some("co_opened_files"));
+// project_path -> VecDeque<FileOpJournalEntry> (undo log for file operations)
+const FILE_OP_JOURNAL: Table<VecDeque<FileOpJournalEntry>> = Table::new(Some("file_op_journal"));
+
+const SCHEMA: Schema = Schema {
+    tables: &[
+        QUERY_FILES.schema(),
+        QUERY_HISTORY.schema(),
+        GREP_QUERY_HISTORY.schema(),
+        GREP_QUERY_FILES.schema(),
+        GREP_JUMPS.schema(),
+        LAST_SEARCH_STATE.schema(),
+        FILE_OP_JOURNAL.schema(),
+        SAVED_SEARCHES.schema(),
+        PERF_HISTORY.schema(),
+        QUERY_FILE_CANDIDATES.schema(),
+        RECENT_OPENS.schema(),
+        CO_OPENS.schema(),
+    ],
+    min_map_size: MIN_MAP_SIZE,
+    max_dbs: MAX_DBS,
+};
+
 #[derive(Debug)]
 pub struct QueryTracker {
-    env: Env,
-    // Database for (project_path, query) -> QueryMatchEntry mappings
-    query_file_db: Database<Bytes, SerdeBincode<QueryMatchEntry>>,
-    // Database for project_path -> VecDeque<HistoryEntry> mappings (file picker)
-    query_history_db: Database<Bytes, SerdeBincode<VecDeque<HistoryEntry>>>,
-    // Database for project_path -> VecDeque<HistoryEntry> mappings (grep)
-    grep_query_history_db: Database<Bytes, SerdeBincode<VecDeque<HistoryEntry>>>,
+    storage: Arc<dyn Storage>,
 }
 
 impl DbHealthChecker for QueryTracker {
-    fn get_env(&self) -> &Env {
-        &self.env
-    }
-
     fn count_entries(&self) -> Result<Vec<(&'static str, u64)>, Error> {
-        let rtxn = self.env.read_txn().map_err(Error::DbStartReadTxn)?;
-
-        let count_queries = self.query_file_db.len(&rtxn).map_err(Error::DbRead)?;
-        let count_histories = self.query_history_db.len(&rtxn).map_err(Error::DbRead)?;
-        let count_grep_histories = self
-            .grep_query_history_db
-            .len(&rtxn)
-            .map_err(Error::DbRead)?;
+        self.storage.read(|rtxn| {
+            Ok(vec![
+                ("query_file_entries", QUERY_FILES.len(rtxn)?),
+                ("query_history_entries", QUERY_HISTORY.len(rtxn)?),
+                ("grep_query_history_entries", GREP_QUERY_HISTORY.len(rtxn)?),
+                ("grep_query_file_entries", GREP_QUERY_FILES.len(rtxn)?),
+                ("grep_jump_entries", GREP_JUMPS.len(rtxn)?),
+                ("last_search_state_entries", LAST_SEARCH_STATE.len(rtxn)?),
+                ("file_op_journal_entries", FILE_OP_JOURNAL.len(rtxn)?),
+                ("saved_search_entries", SAVED_SEARCHES.len(rtxn)?),
+                ("perf_history_entries", PERF_HISTORY.len(rtxn)?),
+                ("query_candidate_entries", QUERY_FILE_CANDIDATES.len(rtxn)?),
+                ("co_open_entries", CO_OPENS.len(rtxn)?),
+            ])
+        })
+    }
 
-        Ok(vec![
-            ("query_file_entries", count_queries),
-            ("query_history_entries", count_histories),
-            ("grep_query_history_entries", count_grep_histories),
-        ])
+    fn get_health(&self) -> Result<DbHealth, Error> {
+        db_healthcheck::storage_health(&*self.storage, self.count_entries()?)
     }
 }
 
 impl QueryTracker {
+    /// Open the LMDB database at `db_path`.
     pub fn new(db_path: impl AsRef<Path>, use_unsafe_no_lock: bool) -> Result<Self, Error> {
-        let db_path = db_path.as_ref();
-        fs::create_dir_all(db_path).map_err(Error::CreateDir)?;
-
-        let env = unsafe {
-            let mut opts = EnvOpenOptions::new();
-            opts.map_size(10 * 1024 * 1024); // 100 MiB
-            opts.max_dbs(16); // Allow up to 16 databases per environment
-            if use_unsafe_no_lock {
-                opts.flags(EnvFlags::NO_LOCK | EnvFlags::NO_SYNC | EnvFlags::NO_META_SYNC);
-            }
-            opts.open(db_path).map_err(Error::EnvOpen)?
-        };
-
-        env.clear_stale_readers()
-            .map_err(Error::DbClearStaleReaders)?;
-
-        let mut wtxn = env.write_txn().map_err(Error::DbStartWriteTxn)?;
-
-        // Create two named databases
-        let query_file_db = env
-            .create_database(&mut wtxn, Some("query_file_associations"))
-            .map_err(Error::DbCreate)?;
-        let query_history_db = env
-            .create_database(&mut wtxn, Some("query_history"))
-            .map_err(Error::DbCreate)?;
-        let grep_query_history_db = env
-            .create_database(&mut wtxn, Some("grep_query_history"))
-            .map_err(Error::DbCreate)?;
-
-        wtxn.commit().map_err(Error::DbCommit)?;
+        Self::with_options(db_path, StorageOptions::lmdb(use_unsafe_no_lock))
+    }
 
+    /// Open the database at `db_path` with the storage backend from `options`.
+    pub fn with_options(db_path: impl AsRef<Path>, options: StorageOptions) -> Result<Self, Error> {
         Ok(QueryTracker {
-            env,
-            query_file_db,
-            query_history_db,
-            grep_query_history_db,
+            storage: storage::open(db_path.as_ref(), options, &SCHEMA)?,
         })
     }
 
+    /// Rebuild the database at `db_path` from the entries that can still be
+    /// read, for when opening it fails with a corruption error
+    /// ([`Error::is_corruption`]). The database must not be open.
+    pub fn repair(db_path: impl AsRef<Path>, backend: StorageBackend) -> Result<DbRepair, Error> {
+        storage::repair(db_path.as_ref(), backend, &SCHEMA)
+    }
+
     fn get_now(&self) -> u64 {
         SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -108,7 +206,13 @@ impl QueryTracker {
             .as_secs()
     }
 
+    /// Key of `query` in the project, under the [`normalize_key`] form of
+    /// `project_path` like every other key of the project.
     fn create_query_key(project_path: &Path, query: &str) -> Result<[u8; 32], Error> {
+        Self::hash_query_key(&normalize_key(project_path), query)
+    }
+
+    fn hash_query_key(project_path: &Path, query: &str) -> Result<[u8; 32], Error> {
         let project_str = project_path
             .to_str()
             .ok_or_else(|| Error::InvalidPath(project_path.to_path_buf()))?;
@@ -122,6 +226,10 @@ impl QueryTracker {
     }
 
     fn create_project_key(project_path: &Path) -> Result<[u8; 32], Error> {
+        Self::hash_project_key(&normalize_key(project_path))
+    }
+
+    fn hash_project_key(project_path: &Path) -> Result<[u8; 32], Error> {
         let project_str = project_path
             .to_str()
             .ok_or_else(|| Error::InvalidPath(project_path.to_path_buf()))?;
@@ -131,16 +239,13 @@ impl QueryTracker {
 
     /// Append a query to a history database within an existing write transaction.
     fn append_to_history(
-        db: &Database<Bytes, SerdeBincode<VecDeque<HistoryEntry>>>,
-        wtxn: &mut heed::RwTxn,
+        table: Table<VecDeque<HistoryEntry>>,
+        wtxn: &mut dyn StorageWrite,
         project_key: &[u8; 32],
         query: &str,
         now: u64,
     ) -> Result<(), Error> {
-        let mut history = db
-            .get(wtxn, project_key)
-            .map_err(Error::DbRead)?
-            .unwrap_or_default();
+        let mut history = table.get(wtxn, project_key)?.unwrap_or_default();
 
         history.push_back(HistoryEntry {
             query: query.to_string(),
@@ -150,24 +255,20 @@ impl QueryTracker {
             history.pop_front();
         }
 
-        db.put(wtxn, project_key, &history)
-            .map_err(Error::DbWrite)?;
-        Ok(())
+        table.put(wtxn, project_key, &history)
     }
 
     /// Read a query from a history database at a specific offset.
     /// offset=0 returns most recent, offset=1 returns 2nd most recent, etc.
     fn read_history_at_offset(
-        db: &Database<Bytes, SerdeBincode<VecDeque<HistoryEntry>>>,
-        env: &Env,
+        &self,
+        table: Table<VecDeque<HistoryEntry>>,
         project_key: &[u8; 32],
         offset: usize,
     ) -> Result<Option<String>, Error> {
-        let rtxn = env.read_txn().map_err(Error::DbStartReadTxn)?;
-
-        let mut history = db
-            .get(&rtxn, project_key)
-            .map_err(Error::DbRead)?
+        let mut history = self
+            .storage
+            .read(|rtxn| table.get(rtxn, project_key))?
             .unwrap_or_default();
 
         // history is FIFO, last element is most recent
@@ -187,73 +288,211 @@ impl QueryTracker {
         file_path: &Path,
     ) -> Result<(), Error> {
         let now = self.get_now();
-        let file_path_buf = file_path.to_path_buf();
+        let file_path_buf = normalize_key(file_path);
 
         let query_key = Self::create_query_key(project_path, query)?;
-        let mut wtxn = self.env.write_txn().map_err(Error::DbStartWriteTxn)?;
-
-        let mut entry = self
-            .query_file_db
-            .get(&wtxn, &query_key)
-            .map_err(Error::DbRead)?
-            .unwrap_or_else(|| QueryMatchEntry {
-                file_path: file_path_buf.clone(),
-                open_count: 0,
-                last_opened: now,
-            });
+        self.storage.write(|wtxn| {
+            let mut entry = QUERY_FILES
+                .get(wtxn, &query_key)?
+                .unwrap_or_else(|| QueryMatchEntry {
+                    file_path: file_path_buf.clone(),
+                    open_count: 0,
+                    last_opened: now,
+                });
 
-        if entry.file_path == file_path_buf {
-            tracing::debug!(
-                ?query,
-                ?file_path,
-                "Query completed for same file as last time"
-            );
+            if entry.file_path == file_path_buf {
+                tracing::debug!(
+                    ?query,
+                    ?file_path,
+                    "Query completed for same file as last time"
+                );
 
-            // Same file - just increment count
-            entry.open_count += 1;
-        } else {
-            tracing::debug!(
-                ?query,
-                ?file_path,
-                "Query completed for different file than last time"
-            );
+                // Same file - just increment count
+                entry.open_count += 1;
+            } else {
+                tracing::debug!(
+                    ?query,
+                    ?file_path,
+                    "Query completed for different file than last time"
+                );
 
-            // Different file - replace and reset count to 1
-            entry.file_path = file_path_buf;
-            entry.open_count = 1;
-        }
+                // Different file - replace and reset count to 1
+                entry.file_path = file_path_buf.clone();
+                entry.open_count = 1;
+            }
 
-        entry.last_opened = now;
+            entry.last_opened = now;
 
-        self.query_file_db
-            .put(&mut wtxn, &query_key, &entry)
-            .map_err(Error::DbWrite)?;
+            QUERY_FILES.put(wtxn, &query_key, &entry)?;
 
-        // Update query history database
-        let project_key = Self::create_project_key(project_path)?;
-        Self::append_to_history(&self.query_history_db, &mut wtxn, &project_key, query, now)?;
+            let mut candidates = QUERY_FILE_CANDIDATES
+                .get(wtxn, &query_key)?
+                .unwrap_or_default();
+            match candidates.iter_mut().find(|c| c.file_path == file_path_buf) {
+                Some(candidate) => {
+                    candidate.open_count =
+                        decayed_open_count(candidate.open_count, candidate.last_opened, now) + 1;
+                    candidate.last_opened = now;
+                }
+                None => {
+                    if candidates.len() >= MAX_COMBO_CANDIDATES
+                        && let Some(weakest) = candidates
+                            .iter()
+                            .enumerate()
+                            .min_by_key(|(_, c)| {
+                                (
+                                    decayed_open_count(c.open_count, c.last_opened, now),
+                                    c.last_opened,
+                                )
+                            })
+                            .map(|(i, _)| i)
+                    {
+                        candidates.swap_remove(weakest);
+                    }
+                    candidates.push(QueryMatchEntry {
+                        file_path: file_path_buf.clone(),
+                        open_count: 1,
+                        last_opened: now,
+                    });
+                }
+            }
+            QUERY_FILE_CANDIDATES.put(wtxn, &query_key, &candidates)?;
 
-        wtxn.commit().map_err(Error::DbCommit)?;
+            // Update query history database
+            let project_key = Self::create_project_key(project_path)?;
+            Self::append_to_history(QUERY_HISTORY, wtxn, &project_key, query, now)?;
+            Ok(())
+        })?;
 
         tracing::debug!(?query, ?file_path, "Tracked query completion");
         Ok(())
     }
 
+    /// The file last opened for `query`, if it still exists and was opened
+    /// at least `min_combo_count` times in a row. The count is decayed by the
+    /// time since it was last opened, see [`decayed_open_count`].
+    ///
+    /// When nothing was opened for `query` itself and it is at least
+    /// `min_prefix_len` characters long, the most recent longer query of the
+    /// project history starting with it is used instead, so the boost shows
+    /// up while a familiar query is still being typed. `0` disables that.
     pub fn get_last_query_entry(
         &self,
         query: &str,
         project_path: &Path,
         min_combo_count: u32,
+        min_prefix_len: usize,
     ) -> Result<Option<QueryMatchEntry>, Error> {
-        let query_key = Self::create_query_key(project_path, query)?;
-        let rtxn = self.env.read_txn().map_err(Error::DbStartReadTxn)?;
+        let last_entry = |query: &str| -> Result<Option<QueryMatchEntry>, Error> {
+            let query_key = Self::create_query_key(project_path, query)?;
+            let last_match = self
+                .storage
+                .read(|rtxn| QUERY_FILES.get(rtxn, &query_key))?;
+            Ok(last_match.and_then(|entry| self.live_combo(entry, project_path, min_combo_count)))
+        };
 
-        let last_match = self
-            .query_file_db
-            .get(&rtxn, &query_key)
-            .map_err(Error::DbRead)?;
+        if let Some(entry) = last_entry(query)? {
+            return Ok(Some(entry));
+        }
+        for longer in self.queries_extending(query, project_path, min_prefix_len)? {
+            if let Some(entry) = last_entry(&longer)? {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Up to [`MAX_COMBO_CANDIDATES`] files opened for `query` that still
+    /// exist, most opened first, with their decayed counts. Unlike
+    /// [`Self::get_last_query_entry`] opening another file doesn't reset
+    /// the others, so a query that leads to a few files keeps boosting all
+    /// of them. Only candidates opened at least `min_combo_count` times are
+    /// returned. Falls back to a longer query like `get_last_query_entry`.
+    pub fn get_query_candidates(
+        &self,
+        query: &str,
+        project_path: &Path,
+        min_combo_count: u32,
+        min_prefix_len: usize,
+    ) -> Result<Vec<QueryMatchEntry>, Error> {
+        let candidates = |query: &str| -> Result<Vec<QueryMatchEntry>, Error> {
+            let query_key = Self::create_query_key(project_path, query)?;
+            let candidates = self.storage.read(|rtxn| {
+                match QUERY_FILE_CANDIDATES.get(rtxn, &query_key)? {
+                    Some(candidates) => Ok(candidates),
+                    // recorded before candidates were kept
+                    None => Ok(QUERY_FILES.get(rtxn, &query_key)?.into_iter().collect()),
+                }
+            })?;
+
+            let mut candidates: Vec<_> = candidates
+                .into_iter()
+                .filter_map(|entry| self.live_combo(entry, project_path, min_combo_count))
+                .collect();
+            candidates.sort_by(|a, b| {
+                b.open_count
+                    .cmp(&a.open_count)
+                    .then_with(|| b.last_opened.cmp(&a.last_opened))
+            });
+            Ok(candidates)
+        };
+
+        let exact = candidates(query)?;
+        if !exact.is_empty() {
+            return Ok(exact);
+        }
+        for longer in self.queries_extending(query, project_path, min_prefix_len)? {
+            let found = candidates(&longer)?;
+            if !found.is_empty() {
+                return Ok(found);
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    /// `entry` with its decayed count, unless its file is gone or the count
+    /// is below `min_combo_count`.
+    fn live_combo(
+        &self,
+        mut entry: QueryMatchEntry,
+        project_path: &Path,
+        min_combo_count: u32,
+    ) -> Option<QueryMatchEntry> {
+        if !project_path.join(&entry.file_path).exists() {
+            return None;
+        }
+        entry.open_count = decayed_open_count(entry.open_count, entry.last_opened, self.get_now());
+        (entry.open_count > 0 && entry.open_count >= min_combo_count).then_some(entry)
+    }
+
+    /// Distinct queries of the file picker history that are longer than
+    /// `query` and start with it, most recent first. Empty when `query` is
+    /// shorter than `min_prefix_len` or `min_prefix_len` is 0.
+    fn queries_extending(
+        &self,
+        query: &str,
+        project_path: &Path,
+        min_prefix_len: usize,
+    ) -> Result<Vec<String>, Error> {
+        if min_prefix_len == 0 || query.chars().count() < min_prefix_len {
+            return Ok(Vec::new());
+        }
+        let project_key = Self::create_project_key(project_path)?;
+        let history = self
+            .storage
+            .read(|rtxn| QUERY_HISTORY.get(rtxn, &project_key))?
+            .unwrap_or_default();
 
-        Ok(last_match.filter(|entry| entry.open_count >= min_combo_count))
+        let mut queries: Vec<String> = Vec::new();
+        for entry in history.into_iter().rev() {
+            if entry.query.len() > query.len()
+                && entry.query.starts_with(query)
+                && !queries.contains(&entry.query)
+            {
+                queries.push(entry.query);
+            }
+        }
+        Ok(queries)
     }
 
     pub fn get_last_query_path(
@@ -265,16 +504,13 @@ impl QueryTracker {
     ) -> Result<i32, Error> {
         let query_key = Self::create_query_key(project_path, query)?;
         tracing::debug!(?query_key, "HASH");
-        let rtxn = self.env.read_txn().map_err(Error::DbStartReadTxn)?;
-
         match self
-            .query_file_db
-            .get(&rtxn, &query_key)
-            .map_err(Error::DbRead)?
+            .storage
+            .read(|rtxn| QUERY_FILES.get(rtxn, &query_key))?
         {
             Some(entry) => {
                 // Check if the file path matches and return boost
-                if entry.file_path == file_path && entry.open_count >= 2 {
+                if entry.file_path == normalize_key(file_path) && entry.open_count >= 2 {
                     Ok(combo_boost)
                 } else {
                     Ok(0)
@@ -292,7 +528,7 @@ impl QueryTracker {
         offset: usize,
     ) -> Result<Option<String>, Error> {
         let project_key = Self::create_project_key(project_path)?;
-        Self::read_history_at_offset(&self.query_history_db, &self.env, &project_key, offset)
+        self.read_history_at_offset(QUERY_HISTORY, &project_key, offset)
     }
 
     /// Track a grep query in the grep-specific history.
@@ -300,22 +536,181 @@ impl QueryTracker {
     pub fn track_grep_query(&mut self, query: &str, project_path: &Path) -> Result<(), Error> {
         let now = self.get_now();
         let project_key = Self::create_project_key(project_path)?;
-        let mut wtxn = self.env.write_txn().map_err(Error::DbStartWriteTxn)?;
+        self.storage.write(|wtxn| {
+            Self::append_to_history(GREP_QUERY_HISTORY, wtxn, &project_key, query, now)?;
+            Ok(())
+        })?;
+
+        tracing::debug!(?query, "Tracked grep query");
+        Ok(())
+    }
+
+    /// Remember the grep result opened for `query`. Opening the same file and
+    /// line again increments its count, anything else replaces it. The result
+    /// is also pushed onto the project's jump list.
+    pub fn track_grep_completion(
+        &mut self,
+        query: &str,
+        project_path: &Path,
+        file_path: &Path,
+        line_number: u64,
+    ) -> Result<(), Error> {
+        let now = self.get_now();
+        let file_path = normalize_key(file_path);
+        let query_key = Self::create_query_key(project_path, query)?;
+        self.storage.write(|wtxn| {
+            let previous = GREP_QUERY_FILES.get(wtxn, &query_key)?;
 
-        Self::append_to_history(
-            &self.grep_query_history_db,
-            &mut wtxn,
-            &project_key,
-            query,
-            now,
-        )?;
+            let open_count = match previous {
+                Some(entry) if entry.file_path == file_path && entry.line_number == line_number => {
+                    entry.open_count + 1
+                }
+                _ => 1,
+            };
 
-        wtxn.commit().map_err(Error::DbCommit)?;
+            let entry = GrepQueryMatchEntry {
+                file_path: file_path.clone(),
+                line_number,
+                open_count,
+                last_opened: now,
+            };
+            GREP_QUERY_FILES.put(wtxn, &query_key, &entry)?;
 
-        tracing::debug!(?query, "Tracked grep query");
+            let project_key = Self::create_project_key(project_path)?;
+            let mut jumps = GREP_JUMPS.get(wtxn, &project_key)?.unwrap_or_default();
+            jumps.retain(|jump| jump.file_path != file_path || jump.line_number != line_number);
+            jumps.push_back(GrepJumpEntry {
+                file_path: file_path.clone(),
+                line_number,
+                query: query.to_string(),
+                timestamp: now,
+            });
+            while jumps.len() > MAX_GREP_JUMP_ENTRIES {
+                jumps.pop_front();
+            }
+            GREP_JUMPS.put(wtxn, &project_key, &jumps)
+        })?;
+
+        tracing::debug!(?query, ?file_path, line_number, "Tracked grep completion");
         Ok(())
     }
 
+    /// The grep result last opened for `query`, if its file still exists and
+    /// it was opened at least `min_combo_count` times in a row, decayed like
+    /// [`Self::get_last_query_entry`].
+    pub fn get_last_grep_entry(
+        &self,
+        query: &str,
+        project_path: &Path,
+        min_combo_count: u32,
+    ) -> Result<Option<GrepQueryMatchEntry>, Error> {
+        let query_key = Self::create_query_key(project_path, query)?;
+        let last_match = self
+            .storage
+            .read(|rtxn| GREP_QUERY_FILES.get(rtxn, &query_key))?;
+
+        let now = self.get_now();
+        Ok(last_match
+            .filter(|entry| project_path.join(&entry.file_path).exists())
+            .map(|mut entry| {
+                entry.open_count = decayed_open_count(entry.open_count, entry.last_opened, now);
+                entry
+            })
+            .filter(|entry| entry.open_count > 0 && entry.open_count >= min_combo_count))
+    }
+
+    /// Delete the combos that decayed to nothing or whose file is gone.
+    /// Returns how many were deleted.
+    pub fn prune_stale_combos(&mut self) -> Result<usize, Error> {
+        let now = self.get_now();
+        let is_stale = |file_path: &Path, open_count: u32, last_opened: u64| {
+            decayed_open_count(open_count, last_opened, now) == 0
+                // relative paths can't be checked without the project
+                || (file_path.is_absolute() && !file_path.exists())
+        };
+
+        self.storage.write(|wtxn| {
+            let mut stale = Vec::new();
+            QUERY_FILES.for_each(wtxn, |key, entry| {
+                if is_stale(&entry.file_path, entry.open_count, entry.last_opened) {
+                    stale.push(key.to_vec());
+                }
+            })?;
+            for key in &stale {
+                QUERY_FILES.delete(wtxn, key)?;
+            }
+
+            let mut candidates = Vec::new();
+            QUERY_FILE_CANDIDATES.for_each(wtxn, |key, entries| {
+                let len = entries.len();
+                let kept: Vec<_> = entries
+                    .into_iter()
+                    .filter(|c| !is_stale(&c.file_path, c.open_count, c.last_opened))
+                    .collect();
+                if kept.len() != len {
+                    candidates.push((key.to_vec(), kept, len));
+                }
+            })?;
+            let mut stale_candidates = 0;
+            for (key, kept, len) in &candidates {
+                stale_candidates += len - kept.len();
+                if kept.is_empty() {
+                    QUERY_FILE_CANDIDATES.delete(wtxn, key)?;
+                } else {
+                    QUERY_FILE_CANDIDATES.put(wtxn, key, kept)?;
+                }
+            }
+
+            let mut stale_grep = Vec::new();
+            GREP_QUERY_FILES.for_each(wtxn, |key, entry| {
+                if is_stale(&entry.file_path, entry.open_count, entry.last_opened) {
+                    stale_grep.push(key.to_vec());
+                }
+            })?;
+            for key in &stale_grep {
+                GREP_QUERY_FILES.delete(wtxn, key)?;
+            }
+
+            Ok(stale.len() + stale_candidates + stale_grep.len())
+        })
+    }
+
+    /// Spawns a background thread running [`Self::prune_stale_combos`], like
+    /// the frecency GC does for old accesses.
+    pub fn spawn_gc(shared: SharedQueryTracker) -> Result<std::thread::JoinHandle<()>, Error> {
+        Ok(std::thread::Builder::new()
+            .name("fff-query-gc".into())
+            .spawn(move || {
+                let Ok(mut guard) = shared.write() else {
+                    return;
+                };
+                let Some(ref mut tracker) = *guard else {
+                    return;
+                };
+                match tracker.prune_stale_combos() {
+                    Ok(0) => {}
+                    Ok(pruned) => tracing::info!(pruned, "Query tracker GC pruned stale combos"),
+                    Err(e) => tracing::debug!("Query tracker GC failed: {e}"),
+                }
+            })?)
+    }
+
+    /// Up to `limit` grep results recently opened in the project, most recent
+    /// first. The same file and line is listed only once.
+    pub fn get_recent_grep_jumps(
+        &self,
+        project_path: &Path,
+        limit: usize,
+    ) -> Result<Vec<GrepJumpEntry>, Error> {
+        let project_key = Self::create_project_key(project_path)?;
+        let jumps = self
+            .storage
+            .read(|rtxn| GREP_JUMPS.get(rtxn, &project_key))?
+            .unwrap_or_default();
+
+        Ok(jumps.into_iter().rev().take(limit).collect())
+    }
+
     /// Get grep query from history at a specific offset.
     /// offset=0 returns most recent grep query, offset=1 returns 2nd most recent, etc.
     pub fn get_historical_grep_query(
@@ -324,7 +719,450 @@ impl QueryTracker {
         offset: usize,
     ) -> Result<Option<String>, Error> {
         let project_key = Self::create_project_key(project_path)?;
-        Self::read_history_at_offset(&self.grep_query_history_db, &self.env, &project_key, offset)
+        self.read_history_at_offset(GREP_QUERY_HISTORY, &project_key, offset)
+    }
+
+    /// The `top_n` queries searched most often among the last
+    /// [`MAX_HISTORY_ENTRIES`] of the project, most used first.
+    pub fn most_used_queries(
+        &self,
+        project_path: &Path,
+        top_n: usize,
+    ) -> Result<Vec<QueryUsage>, Error> {
+        self.most_used_in(QUERY_HISTORY, project_path, top_n)
+    }
+
+    /// Like [`Self::most_used_queries`] for grep queries.
+    pub fn most_used_grep_queries(
+        &self,
+        project_path: &Path,
+        top_n: usize,
+    ) -> Result<Vec<QueryUsage>, Error> {
+        self.most_used_in(GREP_QUERY_HISTORY, project_path, top_n)
+    }
+
+    fn most_used_in(
+        &self,
+        table: Table<VecDeque<HistoryEntry>>,
+        project_path: &Path,
+        top_n: usize,
+    ) -> Result<Vec<QueryUsage>, Error> {
+        let project_key = Self::create_project_key(project_path)?;
+        let history = self
+            .storage
+            .read(|rtxn| table.get(rtxn, &project_key))?
+            .unwrap_or_default();
+
+        let mut usage: Vec<QueryUsage> = Vec::new();
+        for entry in history {
+            match usage.iter_mut().find(|usage| usage.query == entry.query) {
+                Some(usage) => {
+                    usage.count += 1;
+                    usage.last_used = usage.last_used.max(entry.timestamp);
+                }
+                None => usage.push(QueryUsage {
+                    query: entry.query,
+                    count: 1,
+                    last_used: entry.timestamp,
+                }),
+            }
+        }
+
+        usage.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| b.last_used.cmp(&a.last_used))
+        });
+        usage.truncate(top_n);
+        Ok(usage)
+    }
+
+    /// Remember the query, mode and selection the picker was closed with.
+    pub fn store_search_state(
+        &mut self,
+        project_path: &Path,
+        query: &str,
+        mode: &str,
+        selected_index: u32,
+    ) -> Result<(), Error> {
+        let state = LastSearchState {
+            query: query.to_string(),
+            mode: mode.to_string(),
+            selected_index,
+            timestamp: self.get_now(),
+        };
+        let project_key = Self::create_project_key(project_path)?;
+        self.storage
+            .write(|wtxn| LAST_SEARCH_STATE.put(wtxn, &project_key, &state))
+    }
+
+    pub fn get_last_search_state(
+        &self,
+        project_path: &Path,
+    ) -> Result<Option<LastSearchState>, Error> {
+        let project_key = Self::create_project_key(project_path)?;
+        self.storage
+            .read(|rtxn| LAST_SEARCH_STATE.get(rtxn, &project_key))
+    }
+
+    /// Save `query` under `name` in the project, replacing the search saved
+    /// under that name before.
+    pub fn save_search(
+        &mut self,
+        project_path: &Path,
+        name: &str,
+        query: &str,
+        mode: &str,
+    ) -> Result<(), Error> {
+        let search = SavedSearch {
+            name: name.to_string(),
+            query: query.to_string(),
+            mode: mode.to_string(),
+            created_at: self.get_now(),
+        };
+        let project_key = Self::create_project_key(project_path)?;
+        self.storage.write(|wtxn| {
+            let mut searches = SAVED_SEARCHES.get(wtxn, &project_key)?.unwrap_or_default();
+            match searches.iter_mut().find(|saved| saved.name == name) {
+                Some(saved) => *saved = search.clone(),
+                None => searches.push(search.clone()),
+            }
+            SAVED_SEARCHES.put(wtxn, &project_key, &searches)
+        })
+    }
+
+    /// The searches saved in the project, in the order they were first saved.
+    pub fn list_saved_searches(&self, project_path: &Path) -> Result<Vec<SavedSearch>, Error> {
+        let project_key = Self::create_project_key(project_path)?;
+        Ok(self
+            .storage
+            .read(|rtxn| SAVED_SEARCHES.get(rtxn, &project_key))?
+            .unwrap_or_default())
+    }
+
+    /// Forget the search saved under `name`. Returns whether there was one.
+    pub fn delete_saved_search(&mut self, project_path: &Path, name: &str) -> Result<bool, Error> {
+        let project_key = Self::create_project_key(project_path)?;
+        self.storage.write(|wtxn| {
+            let Some(mut searches) = SAVED_SEARCHES.get(wtxn, &project_key)? else {
+                return Ok(false);
+            };
+            let len = searches.len();
+            searches.retain(|saved| saved.name != name);
+            if searches.len() == len {
+                return Ok(false);
+            }
+            SAVED_SEARCHES.put(wtxn, &project_key, &searches)?;
+            Ok(true)
+        })
+    }
+
+    /// Record that `relative_path` was opened in the project. It counts as
+    /// opened together with every other file opened within
+    /// [`CO_OPEN_WINDOW_SECS`], once per window: going back and forth between
+    /// two buffers doesn't count again.
+    pub fn track_file_open(
+        &mut self,
+        project_path: &Path,
+        relative_path: &str,
+    ) -> Result<(), Error> {
+        let now = self.get_now();
+        let project_key = Self::create_project_key(project_path)?;
+
+        self.storage.write(|wtxn| {
+            let mut recent = RECENT_OPENS.get(wtxn, &project_key)?.unwrap_or_default();
+            recent.retain(|open| now.saturating_sub(open.timestamp) <= CO_OPEN_WINDOW_SECS);
+
+            let reopened = recent
+                .iter()
+                .any(|open| open.relative_path == relative_path);
+            if !reopened {
+                for open in &recent {
+                    Self::bump_co_open(
+                        wtxn,
+                        project_path,
+                        relative_path,
+                        &open.relative_path,
+                        now,
+                    )?;
+                    Self::bump_co_open(
+                        wtxn,
+                        project_path,
+                        &open.relative_path,
+                        relative_path,
+                        now,
+                    )?;
+                }
+            }
+
+            recent.retain(|open| open.relative_path != relative_path);
+            recent.push_back(RecentOpen {
+                relative_path: relative_path.to_string(),
+                timestamp: now,
+            });
+            while recent.len() > MAX_RECENT_OPENS {
+                recent.pop_front();
+            }
+            RECENT_OPENS.put(wtxn, &project_key, &recent)
+        })
+    }
+
+    fn bump_co_open(
+        wtxn: &mut dyn StorageWrite,
+        project_path: &Path,
+        file: &str,
+        other: &str,
+        now: u64,
+    ) -> Result<(), Error> {
+        let key = Self::create_query_key(project_path, file)?;
+        let mut co_opened = CO_OPENS.get(wtxn, &key)?.unwrap_or_default();
+        match co_opened
+            .iter_mut()
+            .find(|entry| entry.relative_path == other)
+        {
+            Some(entry) => {
+                entry.count += 1;
+                entry.last_opened = now;
+            }
+            None => {
+                if co_opened.len() >= MAX_CO_OPENED
+                    && let Some(weakest) = co_opened
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, entry)| (entry.count, entry.last_opened))
+                        .map(|(i, _)| i)
+                {
+                    co_opened.swap_remove(weakest);
+                }
+                co_opened.push(CoOpenEntry {
+                    relative_path: other.to_string(),
+                    count: 1,
+                    last_opened: now,
+                });
+            }
+        }
+        CO_OPENS.put(wtxn, &key, &co_opened)
+    }
+
+    /// The files opened together with `relative_path`, most often first.
+    pub fn get_co_opened(
+        &self,
+        project_path: &Path,
+        relative_path: &str,
+    ) -> Result<Vec<CoOpenEntry>, Error> {
+        let key = Self::create_query_key(project_path, relative_path)?;
+        let mut co_opened = self
+            .storage
+            .read(|rtxn| CO_OPENS.get(rtxn, &key))?
+            .unwrap_or_default();
+        co_opened.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| b.last_opened.cmp(&a.last_opened))
+        });
+        Ok(co_opened)
+    }
+
+    /// Add search durations and the current index size to the project's
+    /// history of this week, see [`crate::perf_history`].
+    pub fn record_perf(
+        &mut self,
+        project_path: &Path,
+        fuzzy: &LatencyHistogram,
+        grep: &LatencyHistogram,
+        indexed_files: u64,
+    ) -> Result<(), Error> {
+        let week = self.get_now() / perf_history::SECONDS_PER_WEEK;
+        let project_key = Self::create_project_key(project_path)?;
+        self.storage.write(|wtxn| {
+            let mut history = PERF_HISTORY.get(wtxn, &project_key)?.unwrap_or_default();
+            if history.back().is_none_or(|last| last.week != week) {
+                history.push_back(PerfWeek {
+                    week,
+                    ..Default::default()
+                });
+            }
+            if let Some(current) = history.back_mut() {
+                current.fuzzy.merge(fuzzy);
+                current.grep.merge(grep);
+                current.max_indexed_files = current.max_indexed_files.max(indexed_files);
+            }
+            while history.len() > perf_history::MAX_WEEKS {
+                history.pop_front();
+            }
+            PERF_HISTORY.put(wtxn, &project_key, &history)
+        })
+    }
+
+    /// The weeks recorded by [`Self::record_perf`] for the project, most
+    /// recent first.
+    pub fn perf_history(&self, project_path: &Path) -> Result<Vec<PerfWeek>, Error> {
+        let project_key = Self::create_project_key(project_path)?;
+        let history = self
+            .storage
+            .read(|rtxn| PERF_HISTORY.get(rtxn, &project_key))?
+            .unwrap_or_default();
+        Ok(history.into_iter().rev().collect())
+    }
+
+    /// Move everything recorded for the project under `old_path` to
+    /// `new_path`, for history tracked before project paths were normalized
+    /// with [`crate::path_utils::normalize_key`]. Query histories are merged,
+    /// any other entry already recorded under `new_path` is kept. Returns
+    /// whether there was anything to move.
+    pub fn migrate_project_key(&mut self, old_path: &Path, new_path: &Path) -> Result<bool, Error> {
+        if old_path == new_path {
+            return Ok(false);
+        }
+        let old_key = Self::hash_project_key(old_path)?;
+        let new_key = Self::hash_project_key(new_path)?;
+
+        let moved = self.storage.write(|wtxn| {
+            let mut moved = false;
+            if let Some(queries) = Self::migrate_history(QUERY_HISTORY, wtxn, &old_key, &new_key)? {
+                for query in &queries {
+                    let old_query_key = Self::hash_query_key(old_path, query)?;
+                    let new_query_key = Self::hash_query_key(new_path, query)?;
+                    Self::move_entry(QUERY_FILES, wtxn, &old_query_key, &new_query_key)?;
+                    Self::move_entry(QUERY_FILE_CANDIDATES, wtxn, &old_query_key, &new_query_key)?;
+                }
+                moved = true;
+            }
+            if let Some(queries) =
+                Self::migrate_history(GREP_QUERY_HISTORY, wtxn, &old_key, &new_key)?
+            {
+                for query in &queries {
+                    Self::move_entry(
+                        GREP_QUERY_FILES,
+                        wtxn,
+                        &Self::hash_query_key(old_path, query)?,
+                        &Self::hash_query_key(new_path, query)?,
+                    )?;
+                }
+                moved = true;
+            }
+            moved |= Self::move_entry(GREP_JUMPS, wtxn, &old_key, &new_key)?;
+            moved |= Self::move_entry(LAST_SEARCH_STATE, wtxn, &old_key, &new_key)?;
+            moved |= Self::move_entry(FILE_OP_JOURNAL, wtxn, &old_key, &new_key)?;
+            moved |= Self::move_entry(SAVED_SEARCHES, wtxn, &old_key, &new_key)?;
+            moved |= Self::move_entry(PERF_HISTORY, wtxn, &old_key, &new_key)?;
+            moved |= Self::move_entry(RECENT_OPENS, wtxn, &old_key, &new_key)?;
+            Ok(moved)
+        })?;
+
+        if moved {
+            tracing::info!(
+                ?old_path,
+                ?new_path,
+                "Migrated query history of the project"
+            );
+        }
+        Ok(moved)
+    }
+
+    /// Merge the history under `old_key` into the one under `new_key` by
+    /// time and return the distinct queries it had.
+    fn migrate_history(
+        table: Table<VecDeque<HistoryEntry>>,
+        wtxn: &mut dyn StorageWrite,
+        old_key: &[u8; 32],
+        new_key: &[u8; 32],
+    ) -> Result<Option<Vec<String>>, Error> {
+        let Some(old) = table.get(wtxn, old_key)? else {
+            return Ok(None);
+        };
+
+        let mut queries: Vec<String> = old.iter().map(|entry| entry.query.clone()).collect();
+        queries.sort_unstable();
+        queries.dedup();
+
+        let mut history = table.get(wtxn, new_key)?.unwrap_or_default();
+        history.extend(old);
+        history
+            .make_contiguous()
+            .sort_by_key(|entry| entry.timestamp);
+        while history.len() > MAX_HISTORY_ENTRIES {
+            history.pop_front();
+        }
+
+        table.put(wtxn, new_key, &history)?;
+        table.delete(wtxn, old_key)?;
+        Ok(Some(queries))
+    }
+
+    /// Move the entry under `old_key` to `new_key` unless one is there already.
+    fn move_entry<V: Serialize + DeserializeOwned>(
+        table: Table<V>,
+        wtxn: &mut dyn StorageWrite,
+        old_key: &[u8; 32],
+        new_key: &[u8; 32],
+    ) -> Result<bool, Error> {
+        let Some(entry) = table.get(wtxn, old_key)? else {
+            return Ok(false);
+        };
+        if table.get(wtxn, new_key)?.is_none() {
+            table.put(wtxn, new_key, &entry)?;
+        }
+        table.delete(wtxn, old_key)?;
+        Ok(true)
+    }
+
+    /// Directory where deleted files are stashed so they can be restored by undo.
+    /// Lives next to the database files.
+    pub fn file_op_stash_dir(&self) -> PathBuf {
+        self.storage.path().join("trash")
+    }
+
+    /// Append a file operation to the project's undo journal.
+    ///
+    /// Returns the entries evicted because the journal exceeded its capacity,
+    /// so the caller can clean up whatever they were holding on to.
+    pub fn push_file_op(
+        &mut self,
+        project_path: &Path,
+        entry: FileOpJournalEntry,
+    ) -> Result<Vec<FileOpJournalEntry>, Error> {
+        let project_key = Self::create_project_key(project_path)?;
+        self.storage.write(|wtxn| {
+            let mut journal = FILE_OP_JOURNAL.get(wtxn, &project_key)?.unwrap_or_default();
+
+            journal.push_back(entry.clone());
+            let mut evicted = Vec::new();
+            while journal.len() > MAX_FILE_OP_JOURNAL_ENTRIES {
+                evicted.extend(journal.pop_front());
+            }
+
+            FILE_OP_JOURNAL.put(wtxn, &project_key, &journal)?;
+            Ok(evicted)
+        })
+    }
+
+    /// Remove and return the most recent file operation from the project's undo journal.
+    pub fn pop_file_op(
+        &mut self,
+        project_path: &Path,
+    ) -> Result<Option<FileOpJournalEntry>, Error> {
+        let project_key = Self::create_project_key(project_path)?;
+        self.storage.write(|wtxn| {
+            let Some(mut journal) = FILE_OP_JOURNAL.get(wtxn, &project_key)? else {
+                return Ok(None);
+            };
+
+            let entry = journal.pop_back();
+            FILE_OP_JOURNAL.put(wtxn, &project_key, &journal)?;
+            Ok(entry)
+        })
+    }
+}
+
+/// `open_count` halved for every [`COMBO_HALF_LIFE_SECS`] since
+/// `last_opened`, so a combo that stopped being used stops boosting.
+fn decayed_open_count(open_count: u32, last_opened: u64, now: u64) -> u32 {
+    let half_lives = now.saturating_sub(last_opened) / COMBO_HALF_LIFE_SECS;
+    if half_lives >= u32::BITS as u64 {
+        0
+    } else {
+        open_count >> half_lives
     }
 }
 
@@ -380,6 +1218,408 @@ mod tests {
         let _ = std::fs::remove_dir_all(&temp_dir);
     }
 
+    #[test]
+    fn test_grep_completion_tracking() {
+        let temp_dir = env::temp_dir().join("fff_test_grep_completion_tracking");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut tracker = QueryTracker::new(temp_dir.to_str().unwrap(), true).unwrap();
+        let project_path = temp_dir.join("project");
+        let file_path = project_path.join("src/main.rs");
+        std::fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+        std::fs::write(&file_path, "fn main() {}\n").unwrap();
+
+        for _ in 0..2 {
+            tracker
+                .track_grep_completion("fn main", &project_path, &file_path, 12)
+                .unwrap();
+        }
+        let entry = tracker
+            .get_last_grep_entry("fn main", &project_path, 2)
+            .unwrap()
+            .expect("opened twice");
+        assert_eq!((entry.line_number, entry.open_count), (12, 2));
+
+        // Another line of the same file starts over.
+        tracker
+            .track_grep_completion("fn main", &project_path, &file_path, 40)
+            .unwrap();
+        assert!(
+            tracker
+                .get_last_grep_entry("fn main", &project_path, 2)
+                .unwrap()
+                .is_none()
+        );
+        let jumps = tracker.get_recent_grep_jumps(&project_path, 10).unwrap();
+        assert_eq!(
+            jumps
+                .iter()
+                .map(|jump| jump.line_number)
+                .collect::<Vec<_>>(),
+            [40, 12],
+            "most recent first, repeated jumps listed once"
+        );
+        assert_eq!(
+            tracker
+                .get_recent_grep_jumps(&project_path, 1)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        assert!(
+            tracker
+                .get_last_query_entry("fn main", &project_path, 0, 0)
+                .unwrap()
+                .is_none(),
+            "grep completions are separate from file picker combos"
+        );
+
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_last_search_state_is_per_project() {
+        let temp_dir = env::temp_dir().join("fff_test_last_search_state");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut tracker = QueryTracker::new(temp_dir.to_str().unwrap(), true).unwrap();
+        let project_a = PathBuf::from("/test/a");
+        let project_b = PathBuf::from("/test/b");
+
+        tracker
+            .store_search_state(&project_a, "picker", "files", 3)
+            .unwrap();
+        tracker
+            .store_search_state(&project_a, "fn main", "grep", 1)
+            .unwrap();
+
+        let state = tracker.get_last_search_state(&project_a).unwrap().unwrap();
+        assert_eq!(
+            (
+                state.query.as_str(),
+                state.mode.as_str(),
+                state.selected_index
+            ),
+            ("fn main", "grep", 1)
+        );
+        assert!(tracker.get_last_search_state(&project_b).unwrap().is_none());
+
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_most_used_queries() {
+        let temp_dir = env::temp_dir().join("fff_test_most_used_queries");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut tracker = QueryTracker::new(temp_dir.to_str().unwrap(), true).unwrap();
+        let project = PathBuf::from("/test/project");
+
+        for query in ["main", "lib", "main", "conf", "lib", "main"] {
+            tracker.track_grep_query(query, &project).unwrap();
+        }
+
+        let usage = tracker.most_used_grep_queries(&project, 2).unwrap();
+        let usage: Vec<_> = usage.iter().map(|u| (u.query.as_str(), u.count)).collect();
+        assert_eq!(usage, [("main", 3), ("lib", 2)]);
+        assert!(tracker.most_used_queries(&project, 2).unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_combo_decay_and_deleted_files() {
+        let temp_dir = env::temp_dir().join("fff_test_combo_decay");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut tracker = QueryTracker::new(temp_dir.to_str().unwrap(), true).unwrap();
+        let project = temp_dir.join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        let kept = project.join("kept.rs");
+        let deleted = project.join("deleted.rs");
+        std::fs::write(&kept, "").unwrap();
+        std::fs::write(&deleted, "").unwrap();
+
+        for _ in 0..4 {
+            tracker
+                .track_query_completion("kept", &project, &kept)
+                .unwrap();
+            tracker
+                .track_query_completion("deleted", &project, &deleted)
+                .unwrap();
+        }
+        std::fs::remove_file(&deleted).unwrap();
+
+        assert_eq!(
+            tracker
+                .get_last_query_entry("kept", &project, 3, 0)
+                .unwrap()
+                .map(|entry| entry.open_count),
+            Some(4)
+        );
+        assert!(
+            tracker
+                .get_last_query_entry("deleted", &project, 0, 0)
+                .unwrap()
+                .is_none()
+        );
+
+        // the last file entry and the candidate of the deleted file
+        assert_eq!(tracker.prune_stale_combos().unwrap(), 2);
+        assert_eq!(tracker.prune_stale_combos().unwrap(), 0);
+
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_query_candidates_keep_their_counts() {
+        let temp_dir = env::temp_dir().join("fff_test_query_candidates");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut tracker = QueryTracker::new(temp_dir.to_str().unwrap(), true).unwrap();
+        let project = temp_dir.join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        let files: Vec<_> = ["a/mod.rs", "b/mod.rs", "c/mod.rs", "d/mod.rs"]
+            .iter()
+            .map(|name| {
+                let path = project.join(name);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, "").unwrap();
+                path
+            })
+            .collect();
+
+        // alternating between two files resets the last file combo every time
+        for file in [&files[0], &files[1], &files[0], &files[1], &files[0]] {
+            tracker
+                .track_query_completion("mod", &project, file)
+                .unwrap();
+        }
+        tracker
+            .track_query_completion("mod", &project, &files[2])
+            .unwrap();
+
+        let counts = |tracker: &QueryTracker, min_combo_count| {
+            tracker
+                .get_query_candidates("mod", &project, min_combo_count, 0)
+                .unwrap()
+                .into_iter()
+                .map(|c| (c.file_path, c.open_count))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            counts(&tracker, 0),
+            [
+                (files[0].clone(), 3),
+                (files[1].clone(), 2),
+                (files[2].clone(), 1)
+            ]
+        );
+        assert_eq!(counts(&tracker, 2).len(), 2);
+
+        // a fourth file replaces the least opened one
+        tracker
+            .track_query_completion("mod", &project, &files[3])
+            .unwrap();
+        let candidates = counts(&tracker, 0);
+        assert_eq!(candidates.len(), MAX_COMBO_CANDIDATES);
+        assert!(!candidates.iter().any(|(path, _)| path == &files[2]));
+
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_prefix_of_a_historical_query() {
+        let temp_dir = env::temp_dir().join("fff_test_combo_prefix");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut tracker = QueryTracker::new(temp_dir.to_str().unwrap(), true).unwrap();
+        let project = temp_dir.join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        let file = project.join("file_picker.rs");
+        std::fs::write(&file, "").unwrap();
+
+        for _ in 0..3 {
+            tracker
+                .track_query_completion("file_picker", &project, &file)
+                .unwrap();
+        }
+
+        let last_file = |query, min_prefix_len| {
+            tracker
+                .get_last_query_entry(query, &project, 3, min_prefix_len)
+                .unwrap()
+                .map(|entry| entry.file_path)
+        };
+        assert_eq!(last_file("file_pi", 4), Some(file.clone()));
+        assert_eq!(last_file("fil", 4), None, "shorter than the minimum");
+        assert_eq!(last_file("file_pi", 0), None, "prefix matching disabled");
+        assert_eq!(last_file("picker", 4), None, "not a prefix");
+        assert_eq!(
+            tracker
+                .get_query_candidates("file_", &project, 3, 4)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_files_opened_together() {
+        let temp_dir = env::temp_dir().join("fff_test_co_opened");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut tracker = QueryTracker::new(temp_dir.to_str().unwrap(), true).unwrap();
+        let project = PathBuf::from("/test/project");
+
+        for file in [
+            "router.rs",
+            "handler.rs",
+            "router.rs",
+            "handler.rs",
+            "db.rs",
+        ] {
+            tracker.track_file_open(&project, file).unwrap();
+        }
+
+        let co_opened = |file| {
+            tracker
+                .get_co_opened(&project, file)
+                .unwrap()
+                .into_iter()
+                .map(|entry| (entry.relative_path, entry.count))
+                .collect::<std::collections::BTreeMap<_, _>>()
+        };
+        // switching back and forth within the window counts once
+        assert_eq!(
+            co_opened("router.rs"),
+            [("db.rs".to_string(), 1), ("handler.rs".to_string(), 1)].into()
+        );
+        assert_eq!(co_opened("db.rs").len(), 2);
+        assert!(co_opened("main.rs").is_empty());
+
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_decayed_open_count() {
+        let day = 24 * 60 * 60;
+        assert_eq!(decayed_open_count(8, 1000, 1000 + 13 * day), 8);
+        assert_eq!(decayed_open_count(8, 1000, 1000 + 14 * day), 4);
+        assert_eq!(decayed_open_count(8, 1000, 1000 + 60 * day), 0);
+        assert_eq!(decayed_open_count(u32::MAX, 0, u64::MAX), 0);
+    }
+
+    #[test]
+    fn test_perf_history_merges_the_current_week() {
+        let temp_dir = env::temp_dir().join("fff_test_perf_history");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut tracker = QueryTracker::new(temp_dir.to_str().unwrap(), true).unwrap();
+        let project = PathBuf::from("/test/project");
+
+        let mut fuzzy = LatencyHistogram::default();
+        fuzzy.record(std::time::Duration::from_millis(5));
+        let grep = LatencyHistogram::default();
+        tracker.record_perf(&project, &fuzzy, &grep, 100).unwrap();
+        tracker.record_perf(&project, &fuzzy, &grep, 80).unwrap();
+
+        let history = tracker.perf_history(&project).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].fuzzy.count(), 2);
+        assert!(history[0].grep.is_empty());
+        assert_eq!(history[0].max_indexed_files, 100);
+
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_saved_searches() {
+        let temp_dir = env::temp_dir().join("fff_test_saved_searches");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut tracker = QueryTracker::new(temp_dir.to_str().unwrap(), true).unwrap();
+        let project = PathBuf::from("/test/project");
+
+        tracker
+            .save_search(&project, "unwraps", "unwrap() src/", "grep")
+            .unwrap();
+        tracker
+            .save_search(&project, "todos", "TODO", "grep")
+            .unwrap();
+        tracker
+            .save_search(&project, "unwraps", "unwrap( *.rs", "grep")
+            .unwrap();
+
+        let searches = tracker.list_saved_searches(&project).unwrap();
+        let saved: Vec<_> = searches
+            .iter()
+            .map(|s| (s.name.as_str(), s.query.as_str()))
+            .collect();
+        assert_eq!(saved, [("unwraps", "unwrap( *.rs"), ("todos", "TODO")]);
+        assert!(
+            tracker
+                .list_saved_searches(Path::new("/test/other"))
+                .unwrap()
+                .is_empty()
+        );
+
+        assert!(tracker.delete_saved_search(&project, "todos").unwrap());
+        assert!(!tracker.delete_saved_search(&project, "todos").unwrap());
+        assert_eq!(tracker.list_saved_searches(&project).unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_migrate_project_key() {
+        let temp_dir = env::temp_dir().join("fff_test_migrate_project_key");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut tracker = QueryTracker::new(temp_dir.to_str().unwrap(), true).unwrap();
+        let alias = PathBuf::from("/tmp/project");
+        let project = PathBuf::from("/private/tmp/project");
+        // combos are only returned for files that still exist
+        let file_path = temp_dir.join("main.rs");
+        std::fs::write(&file_path, "").unwrap();
+
+        for _ in 0..2 {
+            tracker
+                .track_query_completion("main", &alias, &file_path)
+                .unwrap();
+        }
+        tracker.track_grep_query("fn main", &project).unwrap();
+
+        assert!(tracker.migrate_project_key(&alias, &project).unwrap());
+        assert!(!tracker.migrate_project_key(&alias, &project).unwrap());
+
+        let entry = tracker
+            .get_last_query_entry("main", &project, 2, 0)
+            .unwrap()
+            .expect("combo moved to the normalized project");
+        assert_eq!(entry.file_path, file_path);
+        assert_eq!(
+            tracker
+                .get_historical_query(&project, 0)
+                .unwrap()
+                .as_deref(),
+            Some("main")
+        );
+        assert_eq!(
+            tracker
+                .get_historical_grep_query(&project, 0)
+                .unwrap()
+                .as_deref(),
+            Some("fn main")
+        );
+        assert!(tracker.get_historical_query(&alias, 0).unwrap().is_none());
+
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
     #[test]
     fn test_hashing_functions() {
         let project_path = PathBuf::from("/test/project");
@@ -0,0 +1,217 @@
+//! Unified diffs previewing replacements in a file.
+//!
+//! A search and replace preview only changes a few lines of each file, so
+//! the diff is cut out of the cached content around the replaced lines
+//! rather than diffing two copies of the file. Replaced lines are known up
+//! front, no line matching is needed.
+
+use fff_grep::LineTerminator;
+use fff_grep::lines;
+
+/// Lines of unchanged context around every change, as `diff -u`.
+const CONTEXT_LINES: usize = 3;
+
+/// Text replacing a byte range of a line, like the match ranges of a grep
+/// match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replacement {
+    /// 1-based line number.
+    pub line_number: u64,
+    /// Byte range within the line, without its terminator.
+    pub start: usize,
+    pub end: usize,
+    /// Can contain newlines, splitting the line.
+    pub text: String,
+}
+
+struct ChangedLine {
+    /// 0-based line index.
+    index: usize,
+    new_lines: Vec<String>,
+}
+
+/// The unified diff of `content` with `replacements` applied, labelled with
+/// `path`. Empty when nothing changes. Replacements outside of their line or
+/// overlapping an earlier one on the same line are ignored.
+pub(crate) fn unified_diff(content: &[u8], path: &str, replacements: &[Replacement]) -> String {
+    let lines = split_lines(content);
+
+    let mut sorted: Vec<&Replacement> = replacements
+        .iter()
+        .filter(|r| r.line_number > 0 && (r.line_number as usize) <= lines.len())
+        .collect();
+    sorted.sort_by_key(|r| (r.line_number, r.start));
+
+    let mut changes: Vec<ChangedLine> = Vec::new();
+    for on_line in sorted.chunk_by(|a, b| a.line_number == b.line_number) {
+        let index = on_line[0].line_number as usize - 1;
+        let old = lines[index];
+        let mut new = Vec::with_capacity(old.len());
+        let mut copied = 0;
+        for replacement in on_line {
+            if replacement.start < copied
+                || replacement.start > replacement.end
+                || replacement.end > old.len()
+            {
+                continue;
+            }
+            new.extend_from_slice(&old[copied..replacement.start]);
+            new.extend_from_slice(replacement.text.as_bytes());
+            copied = replacement.end;
+        }
+        new.extend_from_slice(&old[copied..]);
+
+        if new != old {
+            changes.push(ChangedLine {
+                index,
+                new_lines: String::from_utf8_lossy(&new)
+                    .split('\n')
+                    .map(str::to_string)
+                    .collect(),
+            });
+        }
+    }
+
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut diff = format!("--- a/{path}\n+++ b/{path}\n");
+    // lines added by the earlier hunks, the new side starts that much later
+    let mut shift: isize = 0;
+    for hunk in changes.chunk_by(|a, b| b.index - a.index <= 2 * CONTEXT_LINES + 1) {
+        let first = hunk[0].index.saturating_sub(CONTEXT_LINES);
+        let last = (hunk[hunk.len() - 1].index + CONTEXT_LINES).min(lines.len() - 1);
+        let old_count = last - first + 1;
+        let added: isize = hunk.iter().map(|c| c.new_lines.len() as isize - 1).sum();
+        let new_count = (old_count as isize + added) as usize;
+        let new_first = (first as isize + shift) as usize;
+        diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            first + 1,
+            old_count,
+            new_first + 1,
+            new_count
+        ));
+        shift += added;
+
+        let mut changed = hunk.iter().peekable();
+        let mut index = first;
+        while index <= last {
+            let Some(change) = changed.next_if(|c| c.index == index) else {
+                push_line(&mut diff, ' ', &String::from_utf8_lossy(lines[index]));
+                index += 1;
+                continue;
+            };
+
+            // a run of adjacent changed lines is listed removals first, like git
+            let mut run = vec![change];
+            while let Some(next) = changed.next_if(|c| c.index == index + run.len()) {
+                run.push(next);
+            }
+            for change in &run {
+                push_line(
+                    &mut diff,
+                    '-',
+                    &String::from_utf8_lossy(lines[change.index]),
+                );
+            }
+            for change in &run {
+                for line in &change.new_lines {
+                    push_line(&mut diff, '+', line);
+                }
+            }
+            index += run.len();
+        }
+    }
+
+    diff
+}
+
+fn push_line(diff: &mut String, prefix: char, line: &str) {
+    diff.push(prefix);
+    diff.push_str(line);
+    diff.push('\n');
+}
+
+/// The lines of `content` without their `\n` or `\r\n` terminators.
+fn split_lines(content: &[u8]) -> Vec<&[u8]> {
+    let mut lines = Vec::with_capacity(lines::count(content, b'\n') as usize + 1);
+    let mut start = 0;
+    for end in memchr::memchr_iter(b'\n', content) {
+        lines.push(without_cr(&content[start..end]));
+        start = end + 1;
+    }
+    if start < content.len() {
+        lines.push(without_cr(&content[start..]));
+    }
+    lines
+}
+
+fn without_cr(line: &[u8]) -> &[u8] {
+    lines::without_terminator(line, LineTerminator::byte(b'\r'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replace(line_number: u64, start: usize, end: usize, text: &str) -> Replacement {
+        Replacement {
+            line_number,
+            start,
+            end,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn hunks_keep_three_lines_of_context() {
+        let content: String = (1..=20).map(|i| format!("line {i}\n")).collect();
+        let diff = unified_diff(
+            content.as_bytes(),
+            "a.txt",
+            &[replace(2, 0, 4, "row"), replace(15, 5, 7, "XV\nand more")],
+        );
+        assert_eq!(
+            diff,
+            "--- a/a.txt\n+++ b/a.txt\n\
+             @@ -1,5 +1,5 @@\n line 1\n-line 2\n+row 2\n line 3\n line 4\n line 5\n\
+             @@ -12,7 +12,8 @@\n line 12\n line 13\n line 14\n-line 15\n+line XV\n+and more\n line 16\n line 17\n line 18\n"
+        );
+    }
+
+    #[test]
+    fn close_changes_share_a_hunk() {
+        let content = b"a\nb\nc\nd\ne\r\nf\n";
+        let diff = unified_diff(
+            content,
+            "x",
+            &[
+                replace(5, 0, 1, "E"),
+                replace(1, 0, 1, "A"),
+                replace(2, 0, 1, "B"),
+            ],
+        );
+        assert_eq!(
+            diff,
+            "--- a/x\n+++ b/x\n@@ -1,6 +1,6 @@\n-a\n-b\n+A\n+B\n c\n d\n-e\n+E\n f\n"
+        );
+    }
+
+    #[test]
+    fn invalid_or_noop_replacements_change_nothing() {
+        let content = b"abc\n";
+        assert_eq!(unified_diff(content, "x", &[replace(1, 0, 1, "a")]), "");
+        assert_eq!(unified_diff(content, "x", &[replace(1, 2, 9, "z")]), "");
+        assert_eq!(unified_diff(content, "x", &[replace(3, 0, 1, "z")]), "");
+        assert_eq!(
+            unified_diff(
+                content,
+                "x",
+                &[replace(1, 0, 2, "x"), replace(1, 1, 3, "y")]
+            ),
+            "--- a/x\n+++ b/x\n@@ -1,1 +1,1 @@\n-abc\n+xc\n"
+        );
+    }
+}
@@ -1,26 +1,37 @@
 use crate::{
     constraints::apply_constraints,
+    file_list::FileList,
     git::is_modified_status,
     path_utils::calculate_distance_penalty,
+    search_scratch::{ScratchBuffers, SearchScratch, reuse_strs},
     sort_buffer::{sort_by_key_with_buffer, sort_with_buffer},
-    types::{FileItem, Score, ScoringContext},
+    types::{FileItem, MatchStats, Score, ScoringContext, SearchFacets},
+    working_set::WorkingSetKey,
 };
+use ahash::AHashSet;
 use fff_query_parser::FuzzyQuery;
 use neo_frizbee::Scoring;
 use rayon::prelude::*;
-use std::path::MAIN_SEPARATOR;
+use std::path::{MAIN_SEPARATOR, PathBuf};
+
+/// Queries up to this many chars also rank the files usually opened together
+/// with the current one higher.
+pub(crate) const AFFINITY_MAX_QUERY_LEN: usize = 3;
+/// Co-opens counted towards the affinity boost.
+const MAX_AFFINITY_COUNT: u32 = 10;
+/// Points per co-open when there is no query to score against.
+const AFFINITY_FRECENCY_POINTS: i32 = 3;
 
 // like cow but better
 pub(crate) enum FileItems<'a> {
     /// All files — borrows the original owned slice, zero allocation.
-    All(&'a [FileItem]),
+    All(&'a FileList),
     /// Filtered subset — owns references produced by constraint filtering.
     Filtered(Vec<&'a FileItem>),
 }
 
 impl<'a> FileItems<'a> {
     #[inline]
-    #[allow(dead_code)]
     fn len(&self) -> usize {
         match self {
             FileItems::All(s) => s.len(),
@@ -29,15 +40,38 @@ impl<'a> FileItems<'a> {
     }
 
     #[inline]
-    #[allow(dead_code)]
     fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
-    fn relative_paths(&self) -> Vec<&'a str> {
+    /// Keep only files whose relative path starts with `prefix`.
+    fn under_root(self, prefix: &str) -> Self {
+        let is_under = |file: &&FileItem| file.relative_path().starts_with(prefix);
+        match self {
+            FileItems::All(s) => FileItems::Filtered(s.par_iter().filter(is_under).collect()),
+            FileItems::Filtered(mut v) => {
+                v.retain(is_under);
+                FileItems::Filtered(v)
+            }
+        }
+    }
+
+    /// Keep only the files at `paths`.
+    fn retain_paths(self, paths: &AHashSet<PathBuf>) -> Self {
+        let is_kept = |file: &&FileItem| paths.contains(file.path());
         match self {
-            FileItems::All(s) => s.iter().map(|f| f.relative_path.as_str()).collect(),
-            FileItems::Filtered(v) => v.iter().map(|f| f.relative_path.as_str()).collect(),
+            FileItems::All(s) => FileItems::Filtered(s.par_iter().filter(is_kept).collect()),
+            FileItems::Filtered(mut v) => {
+                v.retain(is_kept);
+                FileItems::Filtered(v)
+            }
+        }
+    }
+
+    fn extend_relative_paths(&self, paths: &mut Vec<&'a str>) {
+        match self {
+            FileItems::All(s) => paths.extend(s.iter().map(|f| f.relative_path())),
+            FileItems::Filtered(v) => paths.extend(v.iter().map(|f| f.relative_path())),
         }
     }
 
@@ -51,6 +85,25 @@ impl<'a> FileItems<'a> {
     }
 }
 
+/// The files passing the query constraints, the search root and the
+/// narrowing of the session.
+fn filter_working_files<'a>(files: &'a FileList, context: &ScoringContext) -> FileItems<'a> {
+    let parsed = context.query;
+    let working_files = match apply_constraints(files, &parsed.constraints) {
+        Some(filtered) => FileItems::Filtered(filtered),
+        None => FileItems::All(files),
+    };
+
+    let working_files = match context.search_root {
+        Some(root) if !working_files.is_empty() => working_files.under_root(root),
+        _ => working_files,
+    };
+    match context.working_set.and_then(|(cache, _)| cache.narrowing()) {
+        Some(paths) if !working_files.is_empty() => working_files.retain_paths(&paths),
+        _ => working_files,
+    }
+}
+
 /// Match files against all fuzzy parts.
 /// Single part: use optimized batch matching.
 /// Multiple parts: each part must match, scores are summed (Nucleo-style).
@@ -58,12 +111,10 @@ impl<'a> FileItems<'a> {
 #[inline]
 fn match_fuzzy_parts(
     fuzzy_parts: &[&str],
-    working_files: &FileItems<'_>,
+    haystack: &[&str],
     options: &neo_frizbee::Config,
     max_threads: usize,
 ) -> Vec<neo_frizbee::Match> {
-    let haystack: Vec<&str> = working_files.relative_paths();
-
     // Filter out parts that are too short (< 2 chars)
     let valid_parts: Vec<&str> = fuzzy_parts
         .iter()
@@ -78,14 +129,14 @@ fn match_fuzzy_parts(
 
     if valid_parts.len() == 1 {
         let matches =
-            neo_frizbee::match_list_parallel(valid_parts[0], &haystack, options, max_threads);
+            neo_frizbee::match_list_parallel(valid_parts[0], haystack, options, max_threads);
         return matches;
     }
 
     // Multiple parts - match first part, then filter by remaining parts
     // TODO figure out if we can move this logic to my frizbee fork at least
     let mut matches =
-        neo_frizbee::match_list_parallel(valid_parts[0], &haystack, options, max_threads);
+        neo_frizbee::match_list_parallel(valid_parts[0], haystack, options, max_threads);
     for part in valid_parts[1..].iter() {
         let mut part_options = *options;
         part_options.max_typos = options.max_typos.map(|t| t.min(part.len() as u16));
@@ -112,32 +163,80 @@ fn match_fuzzy_parts(
     matches
 }
 
+/// Whether every byte of `needle` appears in `haystack` in order (ASCII case
+/// insensitive), i.e. the match did not rely on typo tolerance.
+#[inline]
+fn is_subsequence_ignore_case(needle: &[u8], haystack: &[u8]) -> bool {
+    let mut haystack = haystack.iter();
+    needle
+        .iter()
+        .all(|n| haystack.any(|h| h.eq_ignore_ascii_case(n)))
+}
+
+#[allow(clippy::type_complexity)]
 pub fn match_and_score_files<'a>(
-    files: &'a [FileItem],
+    files: &'a FileList,
     context: &ScoringContext,
-) -> (Vec<&'a FileItem>, Vec<Score>, usize) {
+) -> (
+    Vec<&'a FileItem>,
+    Vec<Score>,
+    usize,
+    MatchStats,
+    Option<SearchFacets>,
+    Vec<(&'a FileItem, Score)>,
+) {
+    let no_matches = || {
+        let facets = context.facets.then(SearchFacets::default);
+        (vec![], vec![], 0, MatchStats::default(), facets, vec![])
+    };
     if files.is_empty() {
-        return (vec![], vec![], 0);
+        return no_matches();
     }
 
     let parsed = context.query;
-    let working_files: FileItems<'a> = if parsed.constraints.is_empty() {
-        FileItems::All(files)
-    } else {
-        match apply_constraints(files, &parsed.constraints) {
-            Some(filtered) if !filtered.is_empty() => FileItems::Filtered(filtered),
-            Some(_) => {
-                return (vec![], vec![], 0);
+    let constraint_span = tracing::debug_span!("constraint_filter").entered();
+    let working_files = match context.working_set {
+        Some((cache, generation))
+            if !parsed.constraints.is_empty()
+                || context.search_root.is_some()
+                || cache.narrowing().is_some() =>
+        {
+            let key =
+                WorkingSetKey::new(files, generation, &parsed.constraints, context.search_root);
+            match cache.get(&key) {
+                Some(Some(indices)) => {
+                    FileItems::Filtered(indices.iter().map(|&i| &files[i as usize]).collect())
+                }
+                Some(None) => FileItems::All(files),
+                None => {
+                    let working_files = filter_working_files(files, context);
+                    let filtered = match &working_files {
+                        FileItems::All(_) => None,
+                        FileItems::Filtered(v) => Some(v.as_slice()),
+                    };
+                    cache.insert(key, files, filtered);
+                    working_files
+                }
             }
-            None => FileItems::All(files),
         }
+        _ => filter_working_files(files, context),
     };
+    if working_files.is_empty() {
+        return no_matches();
+    }
+    drop(constraint_span);
 
     let fuzzy_parts: &[&str] = match &parsed.fuzzy_query {
         FuzzyQuery::Text(t) if t.len() >= 2 => std::slice::from_ref(t),
         FuzzyQuery::Parts(parts) if !parts.is_empty() => parts.as_slice(),
         _ => {
-            return score_filtered_by_frecency(&working_files, context);
+            let (items, scores, total_matched, facets, rest) =
+                score_filtered_by_frecency(&working_files, context);
+            let stats = MatchStats {
+                exact_matched: total_matched,
+                ..Default::default()
+            };
+            return (items, scores, total_matched, stats, facets, rest);
         }
     };
     debug_assert!(!fuzzy_parts.is_empty());
@@ -147,18 +246,49 @@ pub fn match_and_score_files<'a>(
         .any(|p| p.chars().any(|c| c.is_uppercase()));
     let query_contains_path_separator = fuzzy_parts.iter().any(|p| p.contains(MAIN_SEPARATOR));
 
-    let options = neo_frizbee::Config {
+    let weights = context.scoring_weights;
+    let mut options = neo_frizbee::Config {
         max_typos: Some(context.max_typos),
         sort: false,
         scoring: Scoring {
-            capitalization_bonus: if has_uppercase { 8 } else { 0 },
+            prefix_bonus: weights.prefix_bonus,
+            delimiter_bonus: weights.boundary_bonus,
+            capitalization_bonus: weights.camel_case_bonus,
             matching_case_bonus: if has_uppercase { 4 } else { 0 },
             ..Default::default()
         },
     };
 
-    let path_matches =
-        match_fuzzy_parts(fuzzy_parts, &working_files, &options, context.max_threads);
+    // Short queries on big repos match almost everything once typos are
+    // allowed. If enough files match without typos, don't bother with the
+    // typo-tolerant pass for this keystroke.
+    let match_span = tracing::debug_span!("fuzzy_match").entered();
+    let mut scratch = context
+        .scratch
+        .map_or_else(ScratchBuffers::default, SearchScratch::take);
+    scratch.searched = working_files.len();
+    let mut haystack = reuse_strs(std::mem::take(&mut scratch.haystack));
+    haystack.reserve(working_files.len());
+    working_files.extend_relative_paths(&mut haystack);
+    let mut typo_free_matches = None;
+    if context.adaptive_typos_threshold > 0 && context.max_typos > 0 {
+        let exact_options = neo_frizbee::Config {
+            max_typos: Some(0),
+            ..options
+        };
+        let matches =
+            match_fuzzy_parts(fuzzy_parts, &haystack, &exact_options, context.max_threads);
+        if matches.len() >= context.adaptive_typos_threshold {
+            options = exact_options;
+            typo_free_matches = Some(matches);
+        }
+    }
+
+    let path_matches = typo_free_matches.unwrap_or_else(|| {
+        match_fuzzy_parts(fuzzy_parts, &haystack, &options, context.max_threads)
+    });
+    scratch.haystack = reuse_strs(haystack);
+    drop(match_span);
 
     let main_needle = fuzzy_parts[0].as_bytes(); // safe
     let main_needle_len = main_needle.len() as u16;
@@ -166,25 +296,25 @@ pub fn match_and_score_files<'a>(
     // Filename match detection: two tiers, cursor-based (no intermediate bitset/Vec<bool>).
     // 1) Collect filenames only where match_end_col didn't land in the filename region.
     // 2) Batch SIMD on that subset, remap indices, sort for cursor walk in the scoring loop.
-    let mut fallback_indices: Vec<u32> = Vec::new();
+    let fallback_indices = &mut scratch.fallback_indices;
     let filename_fallback_matches = if query_contains_path_separator || path_matches.len() > 15_000
     {
         vec![]
     } else {
-        let mut fallback_filenames: Vec<&str> = Vec::new();
+        let mut fallback_filenames = reuse_strs(std::mem::take(&mut scratch.fallback_filenames));
 
         for (i, path_match) in path_matches.iter().enumerate() {
             let file = working_files.index(path_match.index as usize);
-            let filename_start = (file.relative_path.len() - file.file_name.len()) as u16;
+            let filename_start = (file.relative_path().len() - file.file_name().len()) as u16;
             let match_start_approx = path_match.match_end_col.saturating_sub(main_needle_len - 1);
 
             if match_start_approx < filename_start {
                 fallback_indices.push(i as u32);
-                fallback_filenames.push(file.file_name.as_str());
+                fallback_filenames.push(file.file_name());
             }
         }
 
-        if fallback_filenames.is_empty() {
+        let matches = if fallback_filenames.is_empty() {
             vec![]
         } else {
             let mut matches = neo_frizbee::match_list_parallel(
@@ -200,140 +330,223 @@ pub fn match_and_score_files<'a>(
 
             sort_by_key_with_buffer(&mut matches, |m| fallback_indices[m.index as usize]);
             matches
-        }
+        };
+        scratch.fallback_filenames = reuse_strs(fallback_filenames);
+        matches
     };
 
     let mut next_filename_match_cursor = 0;
-    let results: Vec<_> = path_matches
-        .into_iter()
-        .enumerate()
-        .map(|(match_idx, path_match)| {
-            let file_idx = path_match.index as usize;
-            let file = working_files.index(file_idx);
-
-            let base_score = path_match.score as i32;
-            let frecency_boost = base_score.saturating_mul(file.total_frecency_score) / 100;
-
-            // Give modified/dirty files a 15% boost to make them appear higher in results
-            let git_status_boost = if file.git_status.is_some_and(is_modified_status) {
-                base_score * 15 / 100
-            } else {
-                0
-            };
-
-            let distance_penalty =
-                calculate_distance_penalty(context.current_file, &file.relative_path);
-
-            let filename_start = (file.relative_path.len() - file.file_name.len()) as u16;
-            let match_start_approx = path_match.match_end_col.saturating_sub(main_needle_len - 1);
-
-            let end_col_filename_match = match_start_approx >= filename_start;
-            let simd_filename_match = if !end_col_filename_match {
-                filename_fallback_matches
-                    .get(next_filename_match_cursor)
-                    .and_then(|m| {
-                        if fallback_indices[m.index as usize] == match_idx as u32 {
-                            next_filename_match_cursor += 1;
-                            Some(m)
-                        } else {
-                            None
-                        }
+    let mut typo_matched = 0;
+    let mut results = std::mem::take(&mut scratch.results);
+    results.extend(
+        path_matches
+            .into_iter()
+            .enumerate()
+            .map(|(match_idx, path_match)| {
+                let file_idx = path_match.index as usize;
+                let file = working_files.index(file_idx);
+
+                // parts shorter than 2 chars are not matched at all, see `match_fuzzy_parts`
+                if !fuzzy_parts
+                    .iter()
+                    .filter(|part| part.len() >= 2)
+                    .all(|part| {
+                        is_subsequence_ignore_case(part.as_bytes(), file.relative_path().as_bytes())
                     })
-            } else {
-                None
-            };
-
-            let is_filename_match = end_col_filename_match || simd_filename_match.is_some();
-            let is_exact_filename = simd_filename_match.is_some_and(|m| m.exact)
-                || (end_col_filename_match
-                    && main_needle_len as usize == file.file_name.len()
-                    && main_needle.eq_ignore_ascii_case(file.file_name.as_bytes()));
-
-            let mut has_special_filename_bonus = false;
-            let filename_bonus = if is_exact_filename {
-                base_score / 5 * 2 // 40% bonus for exact filename match
-            } else if is_filename_match {
-                // 16% bonus for fuzzy filename match that landed in the filename region.
-                // For fallback matches (where the path match landed in a directory segment),
-                // scale the bonus by the quality of the filename match — a contiguous match
-                // like "rename" in "rename.ts" gets the full bonus, while a scattered
-                // subsequence like r-e-n-a-m-e in "generateSessionName.ts" gets much less.
-                let max_bonus = (base_score / 6).min(30);
-                if let Some(fm) = simd_filename_match {
-                    let max_possible = main_needle_len as i32 * 16;
-                    let quality = (fm.score as i32).min(max_possible);
-                    max_bonus * quality / max_possible
-                } else {
-                    max_bonus
+                {
+                    typo_matched += 1;
                 }
-            } else if !is_filename_match && is_special_entry_point_file(&file.file_name) {
-                // 5% bonus for special file but not as much as file name to avoid situations
-                // when you have /user_service/server.rs and /user_service/server/mod.rs
-                has_special_filename_bonus = true;
-                base_score * 5 / 100
-            } else {
-                0
-            };
 
-            let current_file_penalty = calculate_current_file_penalty(file, base_score, context);
-            let combo_match_boost = {
-                let last_same_query_match = context
-                    .last_same_query_match
-                    .as_ref()
-                    .filter(|m| m.file_path.as_os_str() == file.path.as_os_str());
-
-                match last_same_query_match {
-                    // if we request a combo match without a boost we have to render it anyway
-                    Some(_) if context.min_combo_count == 0 => 1000,
-                    Some(combo_match) if combo_match.open_count >= context.min_combo_count => {
-                        combo_match.open_count as i32 * context.combo_boost_score_multiplier
-                    }
-                    // until we hit the combo count threshold, we add a smaller boost because it
-                    // makes sense and makes the search more efficient
-                    Some(combo_match) => combo_match.open_count as i32 * 5,
-                    _ => 0,
-                }
-            };
+                let base_score = path_match.score as i32;
+                let frecency_boost = base_score.saturating_mul(file.total_frecency_score) / 100;
 
-            let total = base_score
-                .saturating_add(frecency_boost)
-                .saturating_add(git_status_boost)
-                .saturating_add(distance_penalty)
-                .saturating_add(filename_bonus)
-                .saturating_add(current_file_penalty)
-                .saturating_add(combo_match_boost);
-
-            let score = Score {
-                total,
-                base_score,
-                current_file_penalty,
-                filename_bonus,
-                special_filename_bonus: if has_special_filename_bonus {
-                    filename_bonus
+                // Give modified/dirty files a 15% boost to make them appear higher in results
+                let git_status_boost = if file.git_status.is_some_and(is_modified_status) {
+                    base_score * 15 / 100
                 } else {
                     0
-                },
-                frecency_boost,
-                git_status_boost,
-                distance_penalty,
-                combo_match_boost,
-                exact_match: is_exact_filename || path_match.exact,
-                match_type: if is_exact_filename {
-                    "exact_filename"
+                };
+
+                let distance_penalty =
+                    calculate_distance_penalty(context.current_file, file.relative_path());
+
+                let filename_start = (file.relative_path().len() - file.file_name().len()) as u16;
+                let match_start_approx =
+                    path_match.match_end_col.saturating_sub(main_needle_len - 1);
+
+                let end_col_filename_match = match_start_approx >= filename_start;
+                let simd_filename_match = if !end_col_filename_match {
+                    filename_fallback_matches
+                        .get(next_filename_match_cursor)
+                        .and_then(|m| {
+                            if fallback_indices[m.index as usize] == match_idx as u32 {
+                                next_filename_match_cursor += 1;
+                                Some(m)
+                            } else {
+                                None
+                            }
+                        })
+                } else {
+                    None
+                };
+
+                let is_filename_match = end_col_filename_match || simd_filename_match.is_some();
+                let is_exact_filename = simd_filename_match.is_some_and(|m| m.exact)
+                    || (end_col_filename_match
+                        && main_needle_len as usize == file.file_name().len()
+                        && main_needle.eq_ignore_ascii_case(file.file_name().as_bytes()));
+
+                let mut has_special_filename_bonus = false;
+                let filename_bonus = if is_exact_filename {
+                    base_score / 5 * 2 // 40% bonus for exact filename match
                 } else if is_filename_match {
-                    "fuzzy_filename"
-                } else if path_match.exact {
-                    "exact_path"
+                    // 16% bonus for fuzzy filename match that landed in the filename region.
+                    // For fallback matches (where the path match landed in a directory segment),
+                    // scale the bonus by the quality of the filename match — a contiguous match
+                    // like "rename" in "rename.ts" gets the full bonus, while a scattered
+                    // subsequence like r-e-n-a-m-e in "generateSessionName.ts" gets much less.
+                    let max_bonus = (base_score / 6).min(30);
+                    if let Some(fm) = simd_filename_match {
+                        let max_possible = main_needle_len as i32 * 16;
+                        let quality = (fm.score as i32).min(max_possible);
+                        max_bonus * quality / max_possible
+                    } else {
+                        max_bonus
+                    }
+                } else if !is_filename_match && is_special_entry_point_file(file.file_name()) {
+                    // 5% bonus for special file but not as much as file name to avoid situations
+                    // when you have /user_service/server.rs and /user_service/server/mod.rs
+                    has_special_filename_bonus = true;
+                    base_score * 5 / 100
                 } else {
-                    "fuzzy_path"
-                },
-            };
+                    0
+                };
+
+                let current_file_penalty =
+                    calculate_current_file_penalty(file, base_score, context);
+                let combo_match_boost = {
+                    // every remembered file is boosted by its own count, so a query
+                    // leading to a few files ranks them by how often each was picked
+                    let same_query_match = context
+                        .same_query_matches
+                        .iter()
+                        .find(|m| m.file_path.as_os_str() == file.path().as_os_str());
+
+                    match same_query_match {
+                        // if we request a combo match without a boost we have to render it anyway
+                        Some(_) if context.min_combo_count == 0 => 1000,
+                        Some(combo_match) if combo_match.open_count >= context.min_combo_count => {
+                            combo_match.open_count as i32 * context.combo_boost_score_multiplier
+                        }
+                        // until we hit the combo count threshold, we add a smaller boost because it
+                        // makes sense and makes the search more efficient
+                        Some(combo_match) => combo_match.open_count as i32 * 5,
+                        _ => 0,
+                    }
+                };
+
+                let total = base_score
+                    .saturating_add(frecency_boost)
+                    .saturating_add(git_status_boost)
+                    .saturating_add(distance_penalty)
+                    .saturating_add(filename_bonus)
+                    .saturating_add(current_file_penalty)
+                    .saturating_add(combo_match_boost);
+                // only filled for short queries, where a few typed chars say
+                // little about which of the many matches is wanted
+                let affinity_boost = base_score * co_open_count(file, context) as i32 * 5 / 100;
+                let total = total
+                    .saturating_add(affinity_boost)
+                    .saturating_add(file.external_score);
+
+                // Scaled last so a `.lock` file can't win back its penalty through
+                // a short path or a filename bonus
+                let extension_boost = context
+                    .extension_multipliers
+                    .and_then(|multipliers| multipliers.multiplier_for(file.file_name()))
+                    .map_or(0, |percent| percent_adjustment(total, percent));
+                let project_boost = context
+                    .project_profile
+                    .and_then(|profile| profile.multiplier_for(file.relative_path()))
+                    .map_or(0, |percent| percent_adjustment(total, percent));
+                let total = total
+                    .saturating_add(extension_boost)
+                    .saturating_add(project_boost);
+
+                let score = Score {
+                    total,
+                    base_score,
+                    current_file_penalty,
+                    filename_bonus,
+                    special_filename_bonus: if has_special_filename_bonus {
+                        filename_bonus
+                    } else {
+                        0
+                    },
+                    frecency_boost,
+                    git_status_boost,
+                    distance_penalty,
+                    combo_match_boost,
+                    extension_boost,
+                    project_boost,
+                    affinity_boost,
+                    external_boost: file.external_score,
+                    exact_match: is_exact_filename || path_match.exact,
+                    match_type: if is_exact_filename {
+                        "exact_filename"
+                    } else if is_filename_match {
+                        "fuzzy_filename"
+                    } else if path_match.exact {
+                        "exact_path"
+                    } else {
+                        "fuzzy_path"
+                    },
+                };
+
+                (file_idx as u32, score)
+            }),
+    );
+
+    let stats = MatchStats {
+        exact_matched: results.len() - typo_matched,
+        typo_matched,
+        max_typos: options.max_typos.unwrap_or(0),
+    };
+    drop_weak_matches(&mut results, context);
+    let (items, scores, total_matched, facets, rest) =
+        sort_and_paginate_by(&mut results, |&i| working_files.index(i as usize), context);
+    scratch.results = results;
+    if let Some(search_scratch) = context.scratch {
+        search_scratch.put_back(scratch);
+    }
+    (items, scores, total_matched, stats, facets, rest)
+}
 
-            (file, score)
-        })
-        .collect();
+/// Remove the matches below [`ScoringContext::min_score`] or below
+/// [`ScoringContext::min_score_ratio`] of the best one.
+fn drop_weak_matches<T>(results: &mut Vec<(T, Score)>, context: &ScoringContext) {
+    let best = results.iter().map(|(_, score)| score.total).max();
+    let relative = best
+        .filter(|&best| best > 0 && context.min_score_ratio > 0.0)
+        .map(|best| (best as f64 * context.min_score_ratio as f64).ceil() as i32);
+    let Some(threshold) = context.min_score.max(relative) else {
+        return;
+    };
 
-    sort_and_paginate(results, context)
+    let before = results.len();
+    results.retain(|(_, score)| score.total >= threshold);
+    tracing::debug!(
+        threshold,
+        dropped = before - results.len(),
+        "Dropped weak matches"
+    );
+}
+
+/// Amount to add to `total` to scale it to `percent` of its magnitude. Works
+/// on the absolute value so a penalty also grows for negative totals.
+#[inline]
+fn percent_adjustment(total: i32, percent: i32) -> i32 {
+    total.saturating_abs().saturating_mul(percent - 100) / 100
 }
 
 /// Check if a filename is a special entry point file that deserves bonus scoring
@@ -362,10 +575,17 @@ fn is_special_entry_point_file(filename: &str) -> bool {
 }
 
 /// Score files by frecency when we have a filtered list (prefiltered by constraints)
+#[allow(clippy::type_complexity)]
 pub(crate) fn score_filtered_by_frecency<'a>(
     files: &FileItems<'a>,
     context: &ScoringContext,
-) -> (Vec<&'a FileItem>, Vec<Score>, usize) {
+) -> (
+    Vec<&'a FileItem>,
+    Vec<Score>,
+    usize,
+    Option<SearchFacets>,
+    Vec<(&'a FileItem, Score)>,
+) {
     let score_file = |file: &'a FileItem| {
         let total_frecency_score =
             file.access_frecency_score + file.modification_frecency_score.saturating_mul(4);
@@ -379,9 +599,12 @@ pub(crate) fn score_filtered_by_frecency<'a>(
 
         let current_file_penalty =
             calculate_current_file_penalty(file, total_frecency_score, context);
+        let affinity_boost = co_open_count(file, context) as i32 * AFFINITY_FRECENCY_POINTS;
         let total = total_frecency_score
             .saturating_add(git_status_boost)
-            .saturating_add(current_file_penalty);
+            .saturating_add(current_file_penalty)
+            .saturating_add(affinity_boost)
+            .saturating_add(file.external_score);
 
         let score = Score {
             total,
@@ -390,6 +613,10 @@ pub(crate) fn score_filtered_by_frecency<'a>(
             distance_penalty: 0,
             special_filename_bonus: 0,
             combo_match_boost: 0,
+            extension_boost: 0,
+            project_boost: 0,
+            affinity_boost,
+            external_boost: file.external_score,
             current_file_penalty,
             frecency_boost: total_frecency_score,
             git_status_boost,
@@ -400,12 +627,26 @@ pub(crate) fn score_filtered_by_frecency<'a>(
         (file, score)
     };
 
-    let results: Vec<_> = match files {
+    let mut results: Vec<_> = match files {
         FileItems::All(s) => s.par_iter().map(&score_file).collect(),
         FileItems::Filtered(v) => v.iter().map(|&file| score_file(file)).collect(),
     };
 
-    sort_and_paginate(results, context)
+    sort_and_paginate(&mut results, context)
+}
+
+/// How many times `file` was opened together with the current file, capped so
+/// a pair opened every day doesn't bury everything else.
+#[inline]
+fn co_open_count(file: &FileItem, context: &ScoringContext) -> u32 {
+    if context.co_opened.is_empty() {
+        return 0;
+    }
+    context
+        .co_opened
+        .iter()
+        .find(|entry| entry.relative_path == file.relative_path())
+        .map_or(0, |entry| entry.count.min(MAX_AFFINITY_COUNT))
 }
 
 #[inline]
@@ -417,7 +658,7 @@ fn calculate_current_file_penalty(
     let mut penalty = 0i32;
 
     if let Some(current) = context.current_file
-        && file.relative_path.as_str() == current
+        && file.relative_path() == current
     {
         penalty -= match file.git_status {
             Some(status) if is_modified_status(status) => base_score / 2,
@@ -431,15 +672,45 @@ fn calculate_current_file_penalty(
 /// Sorts elements by total score (descending) and returns the requested page.
 /// Always returns results in descending order (best scores first).
 /// The UI layer handles rendering order based on prompt position.
-#[tracing::instrument(skip_all, level = tracing::Level::DEBUG)]
+/// Facets, when requested, are counted over every match before paginating.
+/// With [`ScoringContext::cursor`] the matches past the page are returned
+/// too, unsorted.
+#[allow(clippy::type_complexity)]
 fn sort_and_paginate<'a>(
-    mut results: Vec<(&'a FileItem, Score)>,
+    results: &mut Vec<(&'a FileItem, Score)>,
+    context: &ScoringContext,
+) -> (
+    Vec<&'a FileItem>,
+    Vec<Score>,
+    usize,
+    Option<SearchFacets>,
+    Vec<(&'a FileItem, Score)>,
+) {
+    sort_and_paginate_by(results, |&file| file, context)
+}
+
+/// [`sort_and_paginate`] for matches referring to their file through `file_of`,
+/// e.g. by index into the searched files.
+#[tracing::instrument(skip_all, level = tracing::Level::DEBUG)]
+#[allow(clippy::type_complexity)]
+fn sort_and_paginate_by<'a, T>(
+    results: &mut Vec<(T, Score)>,
+    file_of: impl Fn(&T) -> &'a FileItem,
     context: &ScoringContext,
-) -> (Vec<&'a FileItem>, Vec<Score>, usize) {
+) -> (
+    Vec<&'a FileItem>,
+    Vec<Score>,
+    usize,
+    Option<SearchFacets>,
+    Vec<(&'a FileItem, Score)>,
+) {
     let total_matched = results.len();
+    let facets = context
+        .facets
+        .then(|| SearchFacets::collect(results.iter().map(|(file, _)| file_of(file))));
 
     if total_matched == 0 {
-        return (vec![], vec![], 0);
+        return (vec![], vec![], 0, facets, vec![]);
     }
 
     let offset = context.pagination.offset;
@@ -457,48 +728,50 @@ fn sort_and_paginate<'a>(
             "Pagination: offset >= total_matched, returning empty"
         );
 
-        return (vec![], vec![], total_matched);
+        return (vec![], vec![], total_matched, facets, vec![]);
     }
 
     let items_needed = offset.saturating_add(limit).min(total_matched);
-    // Use partial sort if we need less than half the results and dataset is large
-    let use_partial_sort = items_needed < total_matched / 2 && total_matched > 100;
+    let mut rest = Vec::new();
     // Always sort in descending order (best scores first)
-    if use_partial_sort {
-        // Partition at position (items_needed - 1) with descending comparator
-        // This puts the highest N needed items at the front
-        results.select_nth_unstable_by(items_needed - 1, |a, b| {
-            b.1.total
-                .cmp(&a.1.total)
-                .then_with(|| b.0.modified.cmp(&a.0.modified))
-        });
-        results.truncate(items_needed);
-    }
-
-    // select nth does not sort the results, we have to sort accordingly anyway
-    sort_with_buffer(&mut results, |a, b| {
+    let by_rank = |a: &(T, Score), b: &(T, Score)| {
         b.1.total
             .cmp(&a.1.total)
-            .then_with(|| b.0.modified.cmp(&a.0.modified))
-    });
+            .then_with(|| file_of(&b.0).modified.cmp(&file_of(&a.0).modified))
+    };
+    // Only what the page needs is sorted: partitioning at (items_needed - 1)
+    // moves the best matches to the front in linear time, which beats
+    // sorting all of them whenever the page ends before the last match.
+    if items_needed < total_matched {
+        results.select_nth_unstable_by(items_needed - 1, by_rank);
+        if context.cursor {
+            rest = results
+                .split_off(items_needed)
+                .into_iter()
+                .map(|(file, score)| (file_of(&file), score))
+                .collect();
+        } else {
+            results.truncate(items_needed);
+        }
+    }
 
-    // in the best scenario truncation happened in the select_nth step
-    if results.len() > limit {
-        let page_end = std::cmp::min(offset + limit, results.len());
-        let page_size = page_end - offset;
+    // select nth does not sort the results, we have to sort accordingly anyway
+    sort_with_buffer(results, by_rank);
 
-        results.drain(0..offset);
-        results.truncate(page_size);
-    }
+    // the partition already cut everything past the page
+    results.drain(..offset);
 
-    let (items, scores): (Vec<&FileItem>, Vec<Score>) = results.into_iter().unzip();
-    (items, scores, total_matched)
+    let (items, scores): (Vec<&FileItem>, Vec<Score>) = results
+        .drain(..)
+        .map(|(file, score)| (file_of(&file), score))
+        .unzip();
+    (items, scores, total_matched, facets, rest)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::PaginationArgs;
+    use crate::types::{PaginationArgs, ScoringWeights};
     use fff_query_parser::QueryParser;
     use std::path::PathBuf;
 
@@ -525,6 +798,10 @@ mod tests {
             exact_match: false,
             match_type: "test",
             combo_match_boost: 0,
+            extension_boost: 0,
+            project_boost: 0,
+            affinity_boost: 0,
+            external_boost: 0,
         };
         (file, score_obj)
     }
@@ -559,7 +836,8 @@ mod tests {
             max_threads: 1,
             max_typos: 2,
             current_file: None,
-            last_same_query_match: None,
+            same_query_matches: Vec::new(),
+            co_opened: Vec::new(),
             project_path: None,
             combo_boost_score_multiplier: 100,
             min_combo_count: 3,
@@ -568,10 +846,21 @@ mod tests {
                 offset: 0,
                 limit: 0,
             },
+            adaptive_typos_threshold: 0,
+            scoring_weights: ScoringWeights::default(),
+            extension_multipliers: None,
+            project_profile: None,
+            search_root: None,
+            facets: false,
+            min_score: None,
+            min_score_ratio: 0.0,
+            cursor: false,
+            working_set: None,
+            scratch: None,
         };
 
         // Test with full sort - returns all results sorted descending
-        let (items, scores, total) = sort_and_paginate(results.clone(), &context);
+        let (items, scores, total, _, _) = sort_and_paginate(&mut results.clone(), &context);
 
         // Should return all 10 items sorted by score descending
         assert_eq!(total, 10);
@@ -581,9 +870,9 @@ mod tests {
         assert_eq!(scores[2].total, 200, "Third should be third highest");
 
         // Verify the files match
-        assert_eq!(items[0].relative_path, "file4.rs");
-        assert_eq!(items[1].relative_path, "file6.rs");
-        assert_eq!(items[2].relative_path, "file2.rs");
+        assert_eq!(items[0].relative_path(), "file4.rs");
+        assert_eq!(items[1].relative_path(), "file6.rs");
+        assert_eq!(items[2].relative_path(), "file2.rs");
     }
 
     #[test]
@@ -597,7 +886,7 @@ mod tests {
             create_test_file("file5.rs", 200, 9000), // Higher score, newest
         ];
 
-        let results: Vec<(&FileItem, Score)> = test_data
+        let mut results: Vec<(&FileItem, Score)> = test_data
             .iter()
             .map(|(file, score)| (file, score.clone()))
             .collect();
@@ -610,7 +899,8 @@ mod tests {
             max_threads: 1,
             max_typos: 2,
             current_file: None,
-            last_same_query_match: None,
+            same_query_matches: Vec::new(),
+            co_opened: Vec::new(),
             project_path: None,
             combo_boost_score_multiplier: 100,
             min_combo_count: 3,
@@ -619,9 +909,20 @@ mod tests {
                 offset: 0,
                 limit: 0,
             },
+            adaptive_typos_threshold: 0,
+            scoring_weights: ScoringWeights::default(),
+            extension_multipliers: None,
+            project_profile: None,
+            search_root: None,
+            facets: false,
+            min_score: None,
+            min_score_ratio: 0.0,
+            cursor: false,
+            working_set: None,
+            scratch: None,
         };
 
-        let (items, scores, _) = sort_and_paginate(results, &context);
+        let (items, scores, _, _, _) = sort_and_paginate(&mut results, &context);
 
         // Should return all 5 items sorted: 200(9000), 200(1000), 100(8000), 100(5000), 100(3000)
         assert_eq!(scores.len(), 5);
@@ -646,7 +947,7 @@ mod tests {
             create_test_file("file3.rs", 50, 3000),
         ];
 
-        let results: Vec<(&FileItem, Score)> = test_data
+        let mut results: Vec<(&FileItem, Score)> = test_data
             .iter()
             .map(|(file, score)| (file, score.clone()))
             .collect();
@@ -659,7 +960,8 @@ mod tests {
             max_threads: 1,
             max_typos: 2,
             current_file: None,
-            last_same_query_match: None,
+            same_query_matches: Vec::new(),
+            co_opened: Vec::new(),
             project_path: None,
             combo_boost_score_multiplier: 100,
             min_combo_count: 3,
@@ -668,25 +970,87 @@ mod tests {
                 offset: 0,
                 limit: 0,
             },
+            adaptive_typos_threshold: 0,
+            scoring_weights: ScoringWeights::default(),
+            extension_multipliers: None,
+            project_profile: None,
+            search_root: None,
+            facets: false,
+            min_score: None,
+            min_score_ratio: 0.0,
+            cursor: false,
+            working_set: None,
+            scratch: None,
         };
 
         // Returns all results sorted descending
-        let (items, scores, _) = sort_and_paginate(results, &context);
+        let (items, scores, _, _, _) = sort_and_paginate(&mut results, &context);
 
         assert_eq!(scores.len(), 3);
         assert_eq!(scores[0].total, 200);
         assert_eq!(scores[1].total, 100);
         assert_eq!(scores[2].total, 50);
-        assert_eq!(items[0].relative_path, "file2.rs");
-        assert_eq!(items[1].relative_path, "file1.rs");
-        assert_eq!(items[2].relative_path, "file3.rs");
+        assert_eq!(items[0].relative_path(), "file2.rs");
+        assert_eq!(items[1].relative_path(), "file1.rs");
+        assert_eq!(items[2].relative_path(), "file3.rs");
+    }
+
+    #[test]
+    fn test_pages_of_small_results() {
+        let test_data = [
+            create_test_file("file1.rs", 100, 1000),
+            create_test_file("file2.rs", 200, 2000),
+            create_test_file("file3.rs", 50, 3000),
+        ];
+        let results: Vec<(&FileItem, Score)> = test_data
+            .iter()
+            .map(|(file, score)| (file, score.clone()))
+            .collect();
+
+        let query = QueryParser::default().parse("test");
+        let page = |offset, limit| {
+            let context = ScoringContext {
+                query: &query,
+                max_threads: 1,
+                max_typos: 2,
+                current_file: None,
+                same_query_matches: Vec::new(),
+                co_opened: Vec::new(),
+                project_path: None,
+                combo_boost_score_multiplier: 100,
+                min_combo_count: 3,
+                pagination: PaginationArgs { offset, limit },
+                adaptive_typos_threshold: 0,
+                scoring_weights: ScoringWeights::default(),
+                extension_multipliers: None,
+                project_profile: None,
+                search_root: None,
+                facets: false,
+                min_score: None,
+                min_score_ratio: 0.0,
+                cursor: false,
+                working_set: None,
+                scratch: None,
+            };
+            let (items, _, total, _, _) = sort_and_paginate(&mut results.clone(), &context);
+            assert_eq!(total, 3);
+            items
+                .iter()
+                .map(|item| item.relative_path())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(page(0, 2), ["file2.rs", "file1.rs"]);
+        assert_eq!(page(1, 1), ["file1.rs"]);
+        // a page running past the last match starts at its offset
+        assert_eq!(page(2, 5), ["file3.rs"]);
     }
 }
 
 #[cfg(test)]
 mod filename_bonus_tests {
     use super::*;
-    use crate::types::PaginationArgs;
+    use crate::types::{ExtensionMultipliers, PaginationArgs, ScoringWeights};
     use fff_query_parser::QueryParser;
     use std::path::PathBuf;
 
@@ -703,7 +1067,15 @@ mod filename_bonus_tests {
         )
     }
 
-    fn search(files: &[FileItem], query: &str) -> Vec<(String, Score)> {
+    fn search(files: &FileList, query: &str) -> Vec<(String, Score)> {
+        search_with_multipliers(files, query, None)
+    }
+
+    fn search_with_multipliers(
+        files: &FileList,
+        query: &str,
+        extension_multipliers: Option<&ExtensionMultipliers>,
+    ) -> Vec<(String, Score)> {
         let parser = QueryParser::default();
         let parsed = parser.parse(query);
         let ctx = ScoringContext {
@@ -711,7 +1083,8 @@ mod filename_bonus_tests {
             max_threads: 1,
             max_typos: 2,
             current_file: None,
-            last_same_query_match: None,
+            same_query_matches: Vec::new(),
+            co_opened: Vec::new(),
             project_path: None,
             combo_boost_score_multiplier: 100,
             min_combo_count: 3,
@@ -719,21 +1092,32 @@ mod filename_bonus_tests {
                 offset: 0,
                 limit: 100,
             },
+            adaptive_typos_threshold: 0,
+            scoring_weights: ScoringWeights::default(),
+            extension_multipliers,
+            project_profile: None,
+            search_root: None,
+            facets: false,
+            min_score: None,
+            min_score_ratio: 0.0,
+            cursor: false,
+            working_set: None,
+            scratch: None,
         };
-        let (items, scores, _) = match_and_score_files(files, &ctx);
+        let (items, scores, _, _, _, _) = match_and_score_files(files, &ctx);
         items
             .iter()
             .zip(scores.iter())
-            .map(|(f, s)| (f.relative_path.clone(), s.clone()))
+            .map(|(f, s)| (f.relative_path().to_string(), s.clone()))
             .collect()
     }
 
     #[test]
     fn test_filename_match_ranks_above_path_only_match() {
-        let files = vec![
+        let files = FileList::from(vec![
             make_file("src/username/handler.rs"),
             make_file("src/username/username.rs"),
-        ];
+        ]);
 
         let results = search(&files, "usrnmea");
 
@@ -760,10 +1144,10 @@ mod filename_bonus_tests {
     fn test_exact_filename_beats_fuzzy_filename() {
         // "username.rs" exactly matches "username.rs" → exact filename
         // "username.rs" is a fuzzy match of "user_name_handler.rs" → fuzzy bonus only
-        let files = vec![
+        let files = FileList::from(vec![
             make_file("src/user_name_handler.rs"),
             make_file("src/username.rs"),
-        ];
+        ]);
 
         let results = search(&files, "username.rs");
 
@@ -780,10 +1164,10 @@ mod filename_bonus_tests {
     fn test_same_length_filename_no_false_exact() {
         // "item.rs" exactly matches "item.rs" → exact_filename
         // "item.rs" should NOT get exact_filename on "file.rs" even though stem lengths match
-        let files = vec![
+        let files = FileList::from(vec![
             make_file("src/item_sync/file.rs"),
             make_file("src/models/item.rs"),
-        ];
+        ]);
 
         let results = search(&files, "item.rs");
 
@@ -798,7 +1182,7 @@ mod filename_bonus_tests {
 
     #[test]
     fn test_path_separator_disables_filename_bonus() {
-        let files = vec![make_file("src/controllers/user.rs")];
+        let files = FileList::from(vec![make_file("src/controllers/user.rs")]);
 
         let results = search(&files, "src/user");
 
@@ -808,6 +1192,314 @@ mod filename_bonus_tests {
             "path-like query should not get filename bonus"
         );
     }
+
+    #[test]
+    fn test_acronym_query_prefers_word_boundaries() {
+        let files = FileList::from(vec![
+            make_file("docs/fastswap.md"),
+            make_file("src/file_system_watcher.rs"),
+            make_file("src/FileSystemWatcher.ts"),
+            make_file("src/offset_swap.rs"),
+        ]);
+
+        let results = search(&files, "fsw");
+        let rank = |path: &str| results.iter().position(|(p, _)| p == path).unwrap();
+
+        assert!(rank("src/file_system_watcher.rs") < rank("docs/fastswap.md"));
+        assert!(rank("src/file_system_watcher.rs") < rank("src/offset_swap.rs"));
+        assert!(rank("src/FileSystemWatcher.ts") < rank("docs/fastswap.md"));
+        assert!(rank("src/FileSystemWatcher.ts") < rank("src/offset_swap.rs"));
+    }
+
+    #[test]
+    fn test_extension_multipliers_reorder_results() {
+        let files = FileList::from(vec![
+            make_file("Cargo.lock"),
+            make_file("src/cargo_config.rs"),
+            make_file("dist/app.min.js"),
+            make_file("src/app/main.js"),
+        ]);
+        let multipliers = ExtensionMultipliers::new([("lock", 10), ("min.js", 10), ("js", 100)]);
+
+        let results = search(&files, "cargo");
+        assert_eq!(results[0].0, "Cargo.lock", "exact filename wins by default");
+
+        let results = search_with_multipliers(&files, "cargo", Some(&multipliers));
+        assert_eq!(results[0].0, "src/cargo_config.rs");
+        let lock = results.iter().find(|(p, _)| p == "Cargo.lock").unwrap();
+        assert!(lock.1.extension_boost < 0);
+
+        let results = search_with_multipliers(&files, "app", Some(&multipliers));
+        let min_js = results
+            .iter()
+            .find(|(p, _)| p == "dist/app.min.js")
+            .unwrap();
+        let main_js = results
+            .iter()
+            .find(|(p, _)| p == "src/app/main.js")
+            .unwrap();
+        assert!(
+            min_js.1.extension_boost < 0,
+            "min.js takes precedence over js"
+        );
+        assert_eq!(main_js.1.extension_boost, 0);
+    }
+
+    #[test]
+    fn test_search_root_limits_results_to_subtree() {
+        use crate::{FilePicker, FuzzySearchOptions};
+        use std::path::Path;
+
+        let files = FileList::from(vec![
+            make_file("lua/fff/main.lua"),
+            make_file("src/main.rs"),
+            make_file("src/nested/main.rs"),
+            make_file("srcs/main.rs"),
+        ]);
+        let parsed = QueryParser::default().parse("main");
+        let search = |search_root: &str| {
+            let result = FilePicker::fuzzy_search(
+                &files,
+                &parsed,
+                None,
+                FuzzySearchOptions {
+                    max_threads: 1,
+                    project_path: Some(Path::new("/repo")),
+                    search_root: Some(Path::new(search_root)),
+                    ..Default::default()
+                },
+            );
+            let mut paths: Vec<_> = result.items.iter().map(|f| f.relative_path()).collect();
+            paths.sort_unstable();
+            paths
+        };
+
+        assert_eq!(search("/repo/src"), ["src/main.rs", "src/nested/main.rs"]);
+        assert_eq!(search("src/nested/"), ["src/nested/main.rs"]);
+        assert_eq!(search("/repo").len(), 4);
+        assert!(search("/elsewhere").is_empty());
+    }
+
+    #[test]
+    fn test_scratch_reused_between_searches_keeps_the_ranking() {
+        use crate::{FilePicker, FuzzySearchOptions, SearchScratch};
+
+        let files = FileList::from(vec![
+            make_file("lua/fff/main.lua"),
+            make_file("src/main.rs"),
+            make_file("src/nested/main.rs"),
+            make_file("src/lib.rs"),
+            make_file("docs/library.md"),
+        ]);
+        let scratch = SearchScratch::new();
+        let search = |query: &str, scratch| {
+            let parsed = QueryParser::default().parse(query);
+            let result = FilePicker::fuzzy_search(
+                &files,
+                &parsed,
+                None,
+                FuzzySearchOptions {
+                    max_threads: 1,
+                    scratch,
+                    ..Default::default()
+                },
+            );
+            result
+                .items
+                .iter()
+                .map(|f| f.relative_path().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        for query in ["main", "lib", "main"] {
+            assert_eq!(search(query, Some(&scratch)), search(query, None));
+        }
+    }
+
+    #[test]
+    fn test_facets_count_every_match() {
+        use crate::{FacetCount, FilePicker, FuzzySearchOptions};
+
+        let files = FileList::from(vec![
+            make_file("lua/fff/main.lua"),
+            make_file("src/main.rs"),
+            make_file("src/nested/main.RS"),
+            make_file("main.md"),
+            make_file("src/lib.rs"),
+        ]);
+        let parsed = QueryParser::default().parse("main");
+        let result = FilePicker::fuzzy_search(
+            &files,
+            &parsed,
+            None,
+            FuzzySearchOptions {
+                max_threads: 1,
+                // facets cover all matches, not only the page
+                pagination: PaginationArgs {
+                    offset: 0,
+                    limit: 1,
+                },
+                facets: true,
+                ..Default::default()
+            },
+        );
+
+        let facet = |value: &str, count| FacetCount {
+            value: value.to_string(),
+            count,
+        };
+        let facets = result.facets.expect("facets were requested");
+        assert_eq!(result.items.len(), 1);
+        assert_eq!(
+            facets.extensions,
+            vec![facet("rs", 2), facet("lua", 1), facet("md", 1)]
+        );
+        assert_eq!(facets.directories, vec![facet("src", 2), facet("lua", 1)]);
+    }
+
+    #[test]
+    fn test_match_stats_count_typo_matches() {
+        let files = FileList::from(vec![
+            make_file("src/handle_lr.rs"),
+            make_file("src/handler.rs"),
+        ]);
+        let parsed = QueryParser::default().parse("handelr");
+        let ctx = ScoringContext {
+            query: &parsed,
+            max_threads: 1,
+            max_typos: 2,
+            current_file: None,
+            same_query_matches: Vec::new(),
+            co_opened: Vec::new(),
+            project_path: None,
+            combo_boost_score_multiplier: 100,
+            min_combo_count: 3,
+            pagination: PaginationArgs {
+                offset: 0,
+                limit: 100,
+            },
+            adaptive_typos_threshold: 0,
+            scoring_weights: ScoringWeights::default(),
+            extension_multipliers: None,
+            project_profile: None,
+            search_root: None,
+            facets: false,
+            min_score: None,
+            min_score_ratio: 0.0,
+            cursor: false,
+            working_set: None,
+            scratch: None,
+        };
+
+        let (_, _, total_matched, stats, _, _) = match_and_score_files(&files, &ctx);
+
+        assert_eq!(stats.max_typos, 2);
+        assert_eq!(total_matched, 2);
+        assert_eq!(
+            stats.exact_matched, 1,
+            "handle_lr.rs contains 'handelr' in order"
+        );
+        assert_eq!(stats.typo_matched, 1, "handler.rs needs a transposition");
+    }
+
+    #[test]
+    fn test_adaptive_typos_skip_typo_matching() {
+        let files = FileList::from(vec![
+            make_file("src/handle_lr.rs"),
+            make_file("src/handler.rs"),
+        ]);
+        let parsed = QueryParser::default().parse("handelr");
+        let search = |adaptive_typos_threshold| {
+            let ctx = ScoringContext {
+                query: &parsed,
+                max_threads: 1,
+                max_typos: 2,
+                current_file: None,
+                same_query_matches: Vec::new(),
+                co_opened: Vec::new(),
+                project_path: None,
+                combo_boost_score_multiplier: 100,
+                min_combo_count: 3,
+                pagination: PaginationArgs {
+                    offset: 0,
+                    limit: 100,
+                },
+                adaptive_typos_threshold,
+                scoring_weights: ScoringWeights::default(),
+                extension_multipliers: None,
+                project_profile: None,
+                search_root: None,
+                facets: false,
+                min_score: None,
+                min_score_ratio: 0.0,
+                cursor: false,
+                working_set: None,
+                scratch: None,
+            };
+            let (_, _, total_matched, stats, _, _) = match_and_score_files(&files, &ctx);
+            (total_matched, stats)
+        };
+
+        let (total_matched, stats) = search(1);
+        assert_eq!(total_matched, 1, "enough typo-free matches, typos skipped");
+        assert_eq!(stats.max_typos, 0);
+
+        let (total_matched, stats) = search(2);
+        assert_eq!(total_matched, 2, "below threshold, typo matching kept");
+        assert_eq!(stats.max_typos, 2);
+    }
+
+    #[test]
+    fn test_min_score_ratio_drops_weak_matches() {
+        let files = FileList::from(vec![
+            make_file("src/config.rs"),
+            make_file("src/core/info.rs"),
+            make_file("scripts/cron/fetch.sh"),
+        ]);
+        let parsed = QueryParser::default().parse("conf");
+        let search = |min_score, min_score_ratio| {
+            let ctx = ScoringContext {
+                query: &parsed,
+                max_threads: 1,
+                max_typos: 0,
+                current_file: None,
+                same_query_matches: Vec::new(),
+                co_opened: Vec::new(),
+                project_path: None,
+                combo_boost_score_multiplier: 100,
+                min_combo_count: 3,
+                pagination: PaginationArgs {
+                    offset: 0,
+                    limit: 100,
+                },
+                adaptive_typos_threshold: 0,
+                scoring_weights: ScoringWeights::default(),
+                extension_multipliers: None,
+                project_profile: None,
+                search_root: None,
+                facets: false,
+                min_score,
+                min_score_ratio,
+                cursor: false,
+                working_set: None,
+                scratch: None,
+            };
+            let (items, scores, total_matched, _, _, _) = match_and_score_files(&files, &ctx);
+            assert_eq!(items.len(), total_matched);
+            (total_matched, scores)
+        };
+
+        let (total_matched, scores) = search(None, 0.0);
+        assert!(total_matched > 1);
+        let best = scores[0].total;
+
+        let (total_matched, scores) = search(None, 0.99);
+        assert_eq!(total_matched, 1);
+        assert_eq!(scores[0].total, best);
+
+        let (total_matched, _) = search(Some(best + 1), 0.0);
+        assert_eq!(total_matched, 0);
+    }
 }
 
 #[cfg(test)]
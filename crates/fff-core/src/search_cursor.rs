@@ -0,0 +1,260 @@
+//! Cursors for paging through a fuzzy search without ranking it again.
+//!
+//! A search run with [`FuzzySearchOptions::with_cursor`] keeps the matches
+//! past the returned page in a max-heap and returns a [`SearchCursor`] to it
+//! in [`SearchResult::cursor`]. Passing the cursor back with the offset it
+//! continues at pops the next page off the heap, which costs the page size
+//! times `log(total)` instead of scoring and sorting every match again.
+//!
+//! [`FuzzySearchOptions::with_cursor`]: crate::FuzzySearchOptions::with_cursor
+
+use crate::file_list::FileList;
+use crate::types::{FileItem, MatchStats, Score, SearchResult};
+use fff_query_parser::Location;
+use parking_lot::Mutex;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+
+/// Where a paginated fuzzy search left off. Cheap to clone, the clones share
+/// the matches left to return. Only valid for the same query over the same
+/// file list, anything else falls back to a regular search.
+#[derive(Debug, Clone)]
+pub struct SearchCursor {
+    query_key: u64,
+    /// [`FileList::id`] and length of the searched file list, the heap
+    /// stores indices into it.
+    files: (u64, usize),
+    total_matched: usize,
+    location: Option<Location>,
+    match_stats: MatchStats,
+    index_generation: u64,
+    state: Arc<Mutex<CursorState>>,
+}
+
+#[derive(Debug)]
+struct CursorState {
+    next_offset: usize,
+    pending: BinaryHeap<Pending>,
+}
+
+/// A match past the returned pages, ordered like the ranking: by score, then
+/// by modification time.
+#[derive(Debug)]
+struct Pending {
+    index: usize,
+    modified: u64,
+    score: Score,
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total
+            .cmp(&other.score.total)
+            .then_with(|| self.modified.cmp(&other.modified))
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Pending {}
+
+fn query_key(query: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    query.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn files_key(files: &FileList) -> (u64, usize) {
+    (files.id(), files.len())
+}
+
+impl SearchCursor {
+    /// Cursor continuing at `next_offset` with the matches of `rest`, which
+    /// must point into `files`. `None` when nothing is left.
+    pub(crate) fn new(
+        files: &FileList,
+        query: &str,
+        next_offset: usize,
+        rest: Vec<(&FileItem, Score)>,
+        result: &SearchResult<'_>,
+    ) -> Option<Self> {
+        if rest.is_empty() {
+            return None;
+        }
+
+        let pending = rest
+            .into_iter()
+            .filter_map(|(file, score)| {
+                Some(Pending {
+                    index: files.index_of(file)?,
+                    modified: file.modified,
+                    score,
+                })
+            })
+            .collect();
+
+        Some(Self {
+            query_key: query_key(query),
+            files: files_key(files),
+            total_matched: result.total_matched,
+            location: result.location,
+            match_stats: result.match_stats,
+            index_generation: result.index_generation,
+            state: Arc::new(Mutex::new(CursorState {
+                next_offset,
+                pending,
+            })),
+        })
+    }
+
+    /// Offset of the first match the next page starts with.
+    pub fn next_offset(&self) -> usize {
+        self.state.lock().next_offset
+    }
+
+    /// Number of matches not returned yet.
+    pub fn remaining(&self) -> usize {
+        self.state.lock().pending.len()
+    }
+
+    /// The `limit` matches (all of them for `0`) starting at `offset`, or
+    /// `None` when the cursor doesn't continue this search at that offset.
+    pub(crate) fn next_page<'a>(
+        &self,
+        files: &'a FileList,
+        query: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Option<SearchResult<'a>> {
+        if self.query_key != query_key(query) || self.files != files_key(files) {
+            return None;
+        }
+
+        let mut state = self.state.lock();
+        if state.next_offset != offset {
+            return None;
+        }
+
+        let count = match limit {
+            0 => state.pending.len(),
+            limit => limit.min(state.pending.len()),
+        };
+        let mut items = Vec::with_capacity(count);
+        let mut scores = Vec::with_capacity(count);
+        while items.len() < count
+            && let Some(pending) = state.pending.pop()
+        {
+            // Files deleted since the search are skipped
+            if let Some(file) = files.get(pending.index).filter(|f| !f.is_deleted) {
+                items.push(file);
+                scores.push(pending.score);
+            }
+        }
+        state.next_offset += items.len();
+        let has_more = !state.pending.is_empty();
+        drop(state);
+
+        Some(SearchResult {
+            items,
+            scores,
+            total_matched: self.total_matched,
+            total_files: files.len(),
+            location: self.location,
+            match_stats: self.match_stats,
+            suggestion: None,
+            facets: None,
+            cursor: has_more.then(|| self.clone()),
+            index_generation: self.index_generation,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_picker::{FilePicker, FuzzySearchOptions};
+    use crate::test_fixtures::{picker_options, scan, write_files};
+    use crate::types::PaginationArgs;
+    use fff_query_parser::QueryParser;
+    use tempfile::TempDir;
+
+    #[test]
+    fn cursor_pages_match_offset_pages() {
+        let tmp = TempDir::new().unwrap();
+        let files: Vec<(String, &str)> = ["src", "src/handlers", "tests", "docs"]
+            .iter()
+            .flat_map(|dir| (0..40).map(move |i| (format!("{dir}/handler_{i}.rs"), "")))
+            .collect();
+        let files: Vec<(&str, &str)> = files.iter().map(|(p, c)| (p.as_str(), *c)).collect();
+        write_files(tmp.path(), &files);
+        let picker = scan(picker_options(tmp.path()));
+
+        let parsed = QueryParser::default().parse("handler");
+        let search = |offset, cursor: Option<&SearchCursor>| {
+            FilePicker::fuzzy_search(
+                picker.get_files(),
+                &parsed,
+                None,
+                FuzzySearchOptions {
+                    max_threads: 1,
+                    pagination: PaginationArgs { offset, limit: 25 },
+                    with_cursor: true,
+                    cursor,
+                    ..Default::default()
+                },
+            )
+        };
+        let ranked = |result: &SearchResult| {
+            result
+                .items
+                .iter()
+                .zip(&result.scores)
+                .map(|(file, score)| (score.total, file.relative_path().to_string()))
+                .collect::<Vec<_>>()
+        };
+        let scores =
+            |result: &SearchResult| result.scores.iter().map(|s| s.total).collect::<Vec<_>>();
+
+        let mut result = search(0, None);
+        assert_eq!(result.total_matched, 160);
+        let mut offset = 0;
+        let mut seen = Vec::new();
+        while let Some(cursor) = result.cursor.take() {
+            offset += result.items.len();
+            assert_eq!(cursor.next_offset(), offset);
+            seen.extend(ranked(&result));
+
+            let next = search(offset, Some(&cursor));
+            let fresh = search(offset, None);
+            assert_eq!(scores(&next), scores(&fresh));
+            result = next;
+        }
+        seen.extend(ranked(&result));
+
+        assert_eq!(seen.len(), 160);
+        assert!(seen.windows(2).all(|pair| pair[0].0 >= pair[1].0));
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), 160, "every match is returned once");
+
+        // a cursor is only followed at the offset it stopped at
+        let first = search(0, None);
+        let cursor = first.cursor.unwrap();
+        assert_eq!(search(50, Some(&cursor)).items.len(), 25);
+        assert_eq!(cursor.next_offset(), 25);
+    }
+}
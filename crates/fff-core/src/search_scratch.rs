@@ -0,0 +1,142 @@
+//! Scratch vectors for fuzzy matching kept between searches
+//!
+//! Every keystroke used to collect the matched paths, the fallback filenames
+//! and the scored results into fresh vectors, which for a big repo is several
+//! megabytes allocated and freed per search. A [`SearchScratch`] kept for the
+//! whole search session holds those vectors between searches: they are
+//! cleared, not freed, so rapid typing reuses the capacity grown by the first
+//! search. The path vectors borrow the searched files, so only their empty
+//! allocation is kept, see [`reuse_strs`].
+
+use crate::types::Score;
+use parking_lot::Mutex;
+
+/// Capacity a vector always keeps, in elements.
+const MIN_KEPT_CAPACITY: usize = 4096;
+/// A vector with more than this many times the capacity the last search
+/// could have used gives the rest back.
+const SHRINK_RATIO: usize = 4;
+
+/// Vectors reused by the fuzzy searches of one session. Keep one per search
+/// session and pass it with [`FuzzySearchOptions::scratch`].
+///
+/// [`FuzzySearchOptions::scratch`]: crate::FuzzySearchOptions::scratch
+#[derive(Debug, Default)]
+pub struct SearchScratch {
+    buffers: Mutex<ScratchBuffers>,
+}
+
+/// The vectors of one search, empty when handed out.
+#[derive(Debug, Default)]
+pub(crate) struct ScratchBuffers {
+    /// Number of files the search went through, the most any vector holds
+    pub searched: usize,
+    /// Relative path of every searched file, the fuzzy matching haystack
+    pub haystack: Vec<&'static str>,
+    /// File names of the path matches whose match ended before the file name
+    pub fallback_filenames: Vec<&'static str>,
+    /// Index into the path matches of every fallback filename
+    pub fallback_indices: Vec<u32>,
+    /// Scored matches before pagination, by index into the searched files
+    pub results: Vec<(u32, Score)>,
+}
+
+impl SearchScratch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The vectors for one search. A search running concurrently with
+    /// another one of the session gets new vectors.
+    pub(crate) fn take(&self) -> ScratchBuffers {
+        std::mem::take(&mut *self.buffers.lock())
+    }
+
+    /// Clear the vectors and keep them for the next search.
+    pub(crate) fn put_back(&self, mut buffers: ScratchBuffers) {
+        let searched = std::mem::take(&mut buffers.searched);
+        recycle(&mut buffers.haystack, searched);
+        recycle(&mut buffers.fallback_filenames, searched);
+        recycle(&mut buffers.fallback_indices, searched);
+        recycle(&mut buffers.results, searched);
+        *self.buffers.lock() = buffers;
+    }
+}
+
+/// Clear `vec` and reuse its allocation for string slices of another
+/// lifetime. The vector is empty by then, so no slice outlives what it
+/// borrows, and mapping in place keeps the allocation.
+pub(crate) fn reuse_strs<'a>(mut vec: Vec<&str>) -> Vec<&'a str> {
+    vec.clear();
+    vec.into_iter().map(|_| "").collect()
+}
+
+/// Clear `vec`, shrinking it when the search just done, over `searched`
+/// files, could use only a small part of what an earlier, bigger one grew it
+/// to. The length isn't the measure: the path vectors are emptied and the
+/// results cut down to a page before they come back.
+fn recycle<T>(vec: &mut Vec<T>, searched: usize) {
+    vec.clear();
+    if vec.capacity() > searched.saturating_mul(SHRINK_RATIO).max(MIN_KEPT_CAPACITY) {
+        vec.shrink_to(searched.max(MIN_KEPT_CAPACITY));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capacity_survives_between_searches() {
+        let scratch = SearchScratch::new();
+        let mut buffers = scratch.take();
+        buffers.searched = 10_000;
+        buffers.fallback_indices.extend(0..10_000);
+        buffers.results.reserve(10_000);
+        let capacity = buffers.fallback_indices.capacity();
+        scratch.put_back(buffers);
+
+        let buffers = scratch.take();
+        assert!(buffers.fallback_indices.is_empty());
+        assert_eq!(buffers.fallback_indices.capacity(), capacity);
+        assert!(buffers.results.capacity() >= 10_000);
+    }
+
+    #[test]
+    fn test_reused_strs_keep_the_allocation() {
+        let owned = String::from("src/main.rs");
+        let mut haystack = reuse_strs(Vec::with_capacity(10_000));
+        haystack.push(owned.as_str());
+        let ptr = haystack.as_ptr() as usize;
+
+        let kept: Vec<&'static str> = reuse_strs(haystack);
+        assert!(kept.is_empty());
+        assert_eq!(kept.as_ptr() as usize, ptr);
+        assert_eq!(kept.capacity(), 10_000);
+    }
+
+    #[test]
+    fn test_small_search_gives_back_the_peak_capacity() {
+        let scratch = SearchScratch::new();
+        let mut buffers = scratch.take();
+        buffers.searched = 10;
+        buffers.fallback_indices.reserve(1_000_000);
+        buffers.fallback_indices.extend(0..10);
+        scratch.put_back(buffers);
+
+        let buffers = scratch.take();
+        assert!(buffers.fallback_indices.capacity() < 1_000_000);
+    }
+
+    #[test]
+    fn test_concurrent_take_gets_new_vectors() {
+        let scratch = SearchScratch::new();
+        let mut outer = scratch.take();
+        outer.fallback_indices.reserve(100);
+
+        let inner = scratch.take();
+        assert_eq!(inner.fallback_indices.capacity(), 0);
+        scratch.put_back(inner);
+        scratch.put_back(outer);
+    }
+}
@@ -1,22 +1,108 @@
+use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::time::Duration;
 
+use arc_swap::ArcSwapOption;
+
 use crate::error::Error;
-use crate::file_picker::FilePicker;
+use crate::file_ops::{FileOp, FileOpResult};
+use crate::file_picker::{FilePicker, PickerSnapshot};
 use crate::frecency::FrecencyTracker;
 use crate::git::GitStatusCache;
+use crate::lock_stats::{self, Access, InstrumentedGuard, LockName};
 use crate::query_tracker::QueryTracker;
 
+pub type PickerReadGuard<'a> =
+    InstrumentedGuard<parking_lot::RwLockReadGuard<'a, Option<FilePicker>>>;
+
+/// Write access to the picker. Publishes a fresh [`PickerSnapshot`] when
+/// dropped after the picker was borrowed mutably, while the write lock is
+/// still held, so snapshots are never older than the last completed write.
+pub struct PickerWriteGuard<'a> {
+    guard: InstrumentedGuard<parking_lot::RwLockWriteGuard<'a, Option<FilePicker>>>,
+    snapshot: &'a ArcSwapOption<PickerSnapshot>,
+    mutated: bool,
+}
+
+impl Deref for PickerWriteGuard<'_> {
+    type Target = Option<FilePicker>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl DerefMut for PickerWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.mutated = true;
+        &mut self.guard
+    }
+}
+
+impl Drop for PickerWriteGuard<'_> {
+    fn drop(&mut self) {
+        if !self.mutated {
+            return;
+        }
+        self.snapshot.store(
+            self.guard
+                .as_ref()
+                .map(|picker| Arc::new(picker.snapshot())),
+        );
+    }
+}
+
+/// A change to the index published by a background job, see
+/// [`SharedPicker::on_index_event`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexEvent {
+    /// A scan or rescan finished, the files are searchable.
+    ScanCompleted,
+    /// The file watcher added, removed or updated files.
+    FilesChanged,
+    /// Git statuses were read for the whole index or refreshed.
+    GitStatusRefreshed,
+}
+
+impl IndexEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            IndexEvent::ScanCompleted => "scan_completed",
+            IndexEvent::FilesChanged => "files_changed",
+            IndexEvent::GitStatusRefreshed => "git_status_refreshed",
+        }
+    }
+}
+
+type IndexEventHook = Arc<dyn Fn(IndexEvent) + Send + Sync>;
+
+/// Clears a scan flag when dropped, so a rescan that returns early or panics
+/// doesn't leave it set and block every later one.
+struct ClearOnDrop(Arc<AtomicBool>);
+
+impl Drop for ClearOnDrop {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
 /// Thread-safe shared handle to the [`FilePicker`] instance.
 ///
 /// Uses `parking_lot::RwLock` which is reader-fair — new readers are not
 /// blocked when a writer is waiting, preventing search query stalls during
-/// background bigram builds or watcher writes.
+/// background bigram builds or watcher writes. Searches don't need the lock
+/// at all: they run on the [`PickerSnapshot`] published after every write.
 ///
 /// `Clone` gives a new handle to the same picker (Arc clone).
 /// `Default` creates an empty handle suitable for `Lazy::new(SharedPicker::default)`.
 #[derive(Clone, Default)]
-pub struct SharedPicker(pub(crate) Arc<parking_lot::RwLock<Option<FilePicker>>>);
+pub struct SharedPicker {
+    picker: Arc<parking_lot::RwLock<Option<FilePicker>>>,
+    snapshot: Arc<ArcSwapOption<PickerSnapshot>>,
+    event_hook: Arc<parking_lot::RwLock<Option<IndexEventHook>>>,
+}
 
 impl std::fmt::Debug for SharedPicker {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -25,19 +111,53 @@ impl std::fmt::Debug for SharedPicker {
 }
 
 impl SharedPicker {
-    pub fn read(&self) -> Result<parking_lot::RwLockReadGuard<'_, Option<FilePicker>>, Error> {
-        Ok(self.0.read())
+    #[track_caller]
+    pub fn read(&self) -> Result<PickerReadGuard<'_>, Error> {
+        lock_stats::acquire(LockName::Picker, Access::Read, Location::caller(), || {
+            Ok(self.picker.read())
+        })
     }
 
-    pub fn write(&self) -> Result<parking_lot::RwLockWriteGuard<'_, Option<FilePicker>>, Error> {
-        Ok(self.0.write())
+    #[track_caller]
+    pub fn write(&self) -> Result<PickerWriteGuard<'_>, Error> {
+        let guard =
+            lock_stats::acquire(LockName::Picker, Access::Write, Location::caller(), || {
+                Ok::<_, Error>(self.picker.write())
+            })?;
+        Ok(PickerWriteGuard {
+            guard,
+            snapshot: &self.snapshot,
+            mutated: false,
+        })
+    }
+
+    /// The index as of the last write, without taking the picker lock.
+    /// `None` until a picker is initialized.
+    pub fn snapshot(&self) -> Option<Arc<PickerSnapshot>> {
+        self.snapshot.load_full()
+    }
+
+    /// Call `hook` from the background thread that changed the index, after
+    /// the change is visible in [`Self::snapshot`]. Replaces the previous
+    /// hook and outlives a picker replaced by a new one. The hook runs on
+    /// the scan and watcher threads, so it should only hand the event over.
+    pub fn on_index_event(&self, hook: impl Fn(IndexEvent) + Send + Sync + 'static) {
+        *self.event_hook.write() = Some(Arc::new(hook));
+    }
+
+    pub(crate) fn emit(&self, event: IndexEvent) {
+        let hook = self.event_hook.read().clone();
+        if let Some(hook) = hook {
+            tracing::debug!(?event, "Index event");
+            hook(event);
+        }
     }
 
     /// Block until the background filesystem scan finishes.
     /// Returns `true` if scan completed, `false` on timeout.
     pub fn wait_for_scan(&self, timeout: Duration) -> bool {
         let signal = {
-            let guard = self.0.read();
+            let guard = self.picker.read();
             match &*guard {
                 Some(picker) => picker.scan_signal(),
                 None => return true,
@@ -58,7 +178,7 @@ impl SharedPicker {
     /// Returns `true` if watcher ready, `false` on timeout.
     pub fn wait_for_watcher(&self, timeout: Duration) -> bool {
         let signal = {
-            let guard = self.0.read();
+            let guard = self.picker.read();
             match &*guard {
                 Some(picker) => picker.watcher_signal(),
                 None => return true,
@@ -75,12 +195,101 @@ impl SharedPicker {
         true
     }
 
+    /// Apply a batch of file operations while holding a single write lock,
+    /// so searches never observe a partially applied batch. Operations are
+    /// recorded in the query tracker's undo journal when it is initialized.
+    pub fn apply_batch(
+        &self,
+        ops: &[FileOp],
+        shared_frecency: &SharedFrecency,
+        shared_query_tracker: &SharedQueryTracker,
+    ) -> Result<Vec<FileOpResult>, Error> {
+        let mut guard = self.write()?;
+        let picker = guard.as_mut().ok_or(Error::FilePickerMissing)?;
+        let frecency = shared_frecency.read()?;
+        let mut query_tracker = shared_query_tracker.write()?;
+
+        Ok(picker.apply_batch(ops, frecency.as_ref(), query_tracker.as_mut()))
+    }
+
+    /// Revert the most recent file operation recorded by [`Self::apply_batch`].
+    /// Returns `None` when there is nothing to undo.
+    pub fn undo_last_file_op(
+        &self,
+        shared_frecency: &SharedFrecency,
+        shared_query_tracker: &SharedQueryTracker,
+    ) -> Result<Option<FileOpResult>, Error> {
+        let mut guard = self.write()?;
+        let picker = guard.as_mut().ok_or(Error::FilePickerMissing)?;
+        let frecency = shared_frecency.read()?;
+        let mut query_tracker = shared_query_tracker.write()?;
+        let Some(journal) = query_tracker.as_mut() else {
+            return Ok(None);
+        };
+
+        picker.undo_last_file_op(frecency.as_ref(), journal)
+    }
+
+    /// Rescan the filesystem like [`FilePicker::trigger_rescan`], but walk
+    /// it without holding the picker lock, which is only taken to swap in
+    /// the new file list.
+    pub fn trigger_rescan(&self, shared_frecency: &SharedFrecency) -> Result<(), Error> {
+        let (job, is_scanning) = {
+            let guard = self.read()?;
+            let picker = guard.as_ref().ok_or(Error::FilePickerMissing)?;
+            (picker.rescan_job(), picker.scan_signal())
+        };
+        if is_scanning.swap(true, Ordering::AcqRel) {
+            tracing::debug!("Scan already in progress, skipping trigger_rescan");
+            return Ok(());
+        }
+        let scan_flag = ClearOnDrop(Arc::clone(&is_scanning));
+
+        let mut trigram_job = None;
+        match job.run(shared_frecency) {
+            Ok(sync) => {
+                let mut guard = self.write()?;
+                // the picker may have been replaced while walking
+                if let Some(picker) = guard.as_mut()
+                    && Arc::ptr_eq(&picker.scan_signal(), &is_scanning)
+                {
+                    picker.install_rescan(sync);
+                    // the rescan invalidated it
+                    if picker.trigram_index().is_some() {
+                        trigram_job = picker.trigram_index_job();
+                    }
+                }
+            }
+            Err(error) => tracing::error!(?error, "Failed to scan file system"),
+        }
+
+        drop(scan_flag);
+        self.emit(IndexEvent::ScanCompleted);
+
+        // Rebuilding can take a while, the watcher must keep going meanwhile.
+        if let Some(job) = trigram_job {
+            let shared_picker = self.clone();
+            std::thread::spawn(move || {
+                let index = job.run();
+                if let Ok(mut guard) = shared_picker.write()
+                    && let Some(picker) = guard.as_mut()
+                {
+                    job.install(picker, index);
+                }
+            });
+        }
+        Ok(())
+    }
+
     /// Refresh git statuses for all indexed files.
     pub fn refresh_git_status(&self, shared_frecency: &SharedFrecency) -> Result<usize, Error> {
-        use git2::StatusOptions;
+        use crate::git::StatusQuery;
         use tracing::debug;
 
-        let git_status = {
+        // the diff against the current statuses is computed under the read
+        // lock, a refresh of a large repository usually changes a handful of
+        // files and the write lock only has to apply those
+        let (statuses_count, git_status) = {
             let guard = self.read()?;
             let Some(ref picker) = *guard else {
                 return Err(Error::FilePickerMissing);
@@ -91,26 +300,35 @@ impl SharedPicker {
                 picker.git_root()
             );
 
-            GitStatusCache::read_git_status(
+            let git_status = GitStatusCache::read_git_status(
                 picker.git_root(),
-                StatusOptions::new()
-                    .include_untracked(true)
-                    .recurse_untracked_dirs(true)
-                    .include_unmodified(true)
-                    .exclude_submodules(true),
-            )
+                StatusQuery {
+                    include_untracked: true,
+                    include_unmodified: true,
+                    exclude_submodules: true,
+                },
+                picker.git_status_provider(),
+            );
+            match git_status {
+                Some(mut git_status) => {
+                    let count = git_status.statuses_len();
+                    picker.retain_changed_git_statuses(&mut git_status);
+                    (count, Some(git_status))
+                }
+                None => (0, None),
+            }
         };
 
-        let mut guard = self.write()?;
-        let picker = guard.as_mut().ok_or(Error::FilePickerMissing)?;
-
-        let statuses_count = if let Some(git_status) = git_status {
-            let count = git_status.statuses_len();
-            picker.update_git_statuses(git_status, shared_frecency)?;
-            count
-        } else {
-            0
-        };
+        if let Some(git_status) = git_status
+            && git_status.statuses_len() > 0
+        {
+            {
+                let mut guard = self.write()?;
+                let picker = guard.as_mut().ok_or(Error::FilePickerMissing)?;
+                picker.update_git_statuses(git_status, shared_frecency)?;
+            }
+            self.emit(IndexEvent::GitStatusRefreshed);
+        }
 
         Ok(statuses_count)
     }
@@ -127,12 +345,25 @@ impl std::fmt::Debug for SharedFrecency {
 }
 
 impl SharedFrecency {
-    pub fn read(&self) -> Result<RwLockReadGuard<'_, Option<FrecencyTracker>>, Error> {
-        self.0.read().map_err(|_| Error::AcquireFrecencyLock)
+    #[track_caller]
+    pub fn read(
+        &self,
+    ) -> Result<InstrumentedGuard<RwLockReadGuard<'_, Option<FrecencyTracker>>>, Error> {
+        lock_stats::acquire(LockName::Frecency, Access::Read, Location::caller(), || {
+            self.0.read().map_err(|_| Error::AcquireFrecencyLock)
+        })
     }
 
-    pub fn write(&self) -> Result<RwLockWriteGuard<'_, Option<FrecencyTracker>>, Error> {
-        self.0.write().map_err(|_| Error::AcquireFrecencyLock)
+    #[track_caller]
+    pub fn write(
+        &self,
+    ) -> Result<InstrumentedGuard<RwLockWriteGuard<'_, Option<FrecencyTracker>>>, Error> {
+        lock_stats::acquire(
+            LockName::Frecency,
+            Access::Write,
+            Location::caller(),
+            || self.0.write().map_err(|_| Error::AcquireFrecencyLock),
+        )
     }
 
     /// Initialize the frecency tracker, replacing any existing one.
@@ -142,13 +373,18 @@ impl SharedFrecency {
         Ok(())
     }
 
+    /// Write queued accesses to the database. Returns how many were written,
+    /// or 0 when no tracker is initialized.
+    pub fn flush(&self) -> Result<usize, Error> {
+        match self.read()?.as_ref() {
+            Some(tracker) => tracker.flush(),
+            None => Ok(0),
+        }
+    }
+
     /// Spawn a background GC thread for this frecency tracker.
-    pub fn spawn_gc(
-        &self,
-        db_path: String,
-        use_unsafe_no_lock: bool,
-    ) -> crate::Result<std::thread::JoinHandle<()>> {
-        FrecencyTracker::spawn_gc(self.clone(), db_path, use_unsafe_no_lock)
+    pub fn spawn_gc(&self, db_path: String) -> crate::Result<std::thread::JoinHandle<()>> {
+        FrecencyTracker::spawn_gc(self.clone(), db_path)
     }
 }
 
@@ -163,12 +399,28 @@ impl std::fmt::Debug for SharedQueryTracker {
 }
 
 impl SharedQueryTracker {
-    pub fn read(&self) -> Result<RwLockReadGuard<'_, Option<QueryTracker>>, Error> {
-        self.0.read().map_err(|_| Error::AcquireFrecencyLock)
+    #[track_caller]
+    pub fn read(
+        &self,
+    ) -> Result<InstrumentedGuard<RwLockReadGuard<'_, Option<QueryTracker>>>, Error> {
+        lock_stats::acquire(
+            LockName::QueryTracker,
+            Access::Read,
+            Location::caller(),
+            || self.0.read().map_err(|_| Error::AcquireFrecencyLock),
+        )
     }
 
-    pub fn write(&self) -> Result<RwLockWriteGuard<'_, Option<QueryTracker>>, Error> {
-        self.0.write().map_err(|_| Error::AcquireFrecencyLock)
+    #[track_caller]
+    pub fn write(
+        &self,
+    ) -> Result<InstrumentedGuard<RwLockWriteGuard<'_, Option<QueryTracker>>>, Error> {
+        lock_stats::acquire(
+            LockName::QueryTracker,
+            Access::Write,
+            Location::caller(),
+            || self.0.write().map_err(|_| Error::AcquireFrecencyLock),
+        )
     }
 
     /// Initialize the query tracker, replacing any existing one.
@@ -177,4 +429,165 @@ impl SharedQueryTracker {
         *guard = Some(tracker);
         Ok(())
     }
+
+    /// Spawn a background GC thread pruning stale combos of this tracker.
+    pub fn spawn_gc(&self) -> crate::Result<std::thread::JoinHandle<()>> {
+        QueryTracker::spawn_gc(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_picker::FilePickerOptions;
+    use crate::test_fixtures::{indexed_paths, picker_options, scan_shared, write_files};
+    use std::fs;
+    use std::sync::mpsc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn snapshots_stay_readable_and_unchanged_during_writes() {
+        let tmp = TempDir::new().unwrap();
+        write_files(tmp.path(), &[("alpha.txt", "alpha"), ("beta.txt", "beta")]);
+
+        let shared_picker = scan_shared(picker_options(tmp.path()));
+        let before = shared_picker.snapshot().expect("published by the write");
+
+        let write_guard = shared_picker.write().unwrap();
+        // no lock needed to read the published snapshot
+        assert_eq!(shared_picker.snapshot().unwrap().get_files().len(), 2);
+        drop(write_guard);
+
+        shared_picker
+            .apply_batch(
+                &[FileOp::Delete {
+                    path: "alpha.txt".into(),
+                }],
+                &SharedFrecency::default(),
+                &SharedQueryTracker::default(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            indexed_paths(before.get_files()).len(),
+            2,
+            "old snapshot is not mutated"
+        );
+        assert_eq!(
+            indexed_paths(shared_picker.snapshot().unwrap().get_files()),
+            vec!["beta.txt".to_string()]
+        );
+    }
+
+    #[test]
+    fn shared_rescan_swaps_in_a_new_file_list() {
+        let tmp = TempDir::new().unwrap();
+        write_files(tmp.path(), &[("alpha.txt", "alpha")]);
+
+        let shared_picker = scan_shared(picker_options(tmp.path()));
+        let before = shared_picker.snapshot().unwrap();
+
+        write_files(tmp.path(), &[("beta.txt", "beta")]);
+        shared_picker
+            .trigger_rescan(&SharedFrecency::default())
+            .unwrap();
+
+        assert_eq!(before.get_files().len(), 1);
+        assert_eq!(
+            indexed_paths(shared_picker.snapshot().unwrap().get_files()),
+            vec!["alpha.txt".to_string(), "beta.txt".to_string()]
+        );
+        assert!(
+            !shared_picker
+                .read()
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .is_scan_active()
+        );
+    }
+
+    #[test]
+    fn watcher_events_copy_one_chunk_of_the_file_list() {
+        let tmp = TempDir::new().unwrap();
+        for i in 0..3000 {
+            std::fs::write(tmp.path().join(format!("file_{i:04}.txt")), "x").unwrap();
+        }
+
+        let shared_picker = scan_shared(picker_options(tmp.path()));
+        let before = shared_picker.snapshot().unwrap().files();
+
+        shared_picker
+            .write()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .on_create_or_modify(tmp.path().join("file_1500.txt"));
+        let modified = shared_picker.snapshot().unwrap().files();
+        assert_eq!(modified.shared_chunks(&before), 2);
+
+        assert!(
+            shared_picker
+                .write()
+                .unwrap()
+                .as_mut()
+                .unwrap()
+                .remove_file_by_path(tmp.path().join("file_0100.txt"))
+        );
+        let removed = shared_picker.snapshot().unwrap().files();
+        assert_eq!(removed.shared_chunks(&modified), 2);
+        assert_eq!(removed.shared_chunks(&before), 1);
+    }
+
+    #[test]
+    fn read_only_write_guards_keep_the_snapshot() {
+        let tmp = TempDir::new().unwrap();
+        write_files(tmp.path(), &[("alpha.txt", "alpha")]);
+
+        let shared_picker = scan_shared(picker_options(tmp.path()));
+        let before = shared_picker.snapshot().unwrap();
+        assert!(shared_picker.write().unwrap().is_some());
+
+        assert!(Arc::ptr_eq(&before, &shared_picker.snapshot().unwrap()));
+    }
+
+    #[test]
+    fn scans_emit_scan_completed() {
+        let tmp = TempDir::new().unwrap();
+        write_files(tmp.path(), &[("main.rs", "fn main() {}\n")]);
+
+        let shared_picker = SharedPicker::default();
+        let (sender, events) = mpsc::channel();
+        let files_seen = shared_picker.clone();
+        shared_picker.on_index_event(move |event| {
+            let files = files_seen
+                .snapshot()
+                .map_or(0, |snapshot| snapshot.get_files().len());
+            let _ = sender.send((event, files));
+        });
+
+        let shared_frecency = SharedFrecency::default();
+        FilePicker::new_with_shared_state(
+            shared_picker.clone(),
+            shared_frecency.clone(),
+            FilePickerOptions {
+                git_enabled: false,
+                ..picker_options(tmp.path())
+            },
+        )
+        .unwrap();
+
+        let timeout = Duration::from_secs(30);
+        assert_eq!(
+            events.recv_timeout(timeout).unwrap(),
+            (IndexEvent::ScanCompleted, 1)
+        );
+
+        fs::write(tmp.path().join("lib.rs"), "").unwrap();
+        shared_picker.trigger_rescan(&shared_frecency).unwrap();
+        assert_eq!(
+            events.recv_timeout(timeout).unwrap(),
+            (IndexEvent::ScanCompleted, 2)
+        );
+    }
 }
@@ -0,0 +1,390 @@
+mod lmdb_backend;
+mod redb_backend;
+
+use std::fmt;
+use std::fs;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::db_healthcheck::DbRepair;
+use crate::error::{Error, Result};
+
+/// Database engine the trackers store their data in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum StorageBackend {
+    /// LMDB environment (`data.mdb` and `lock.mdb`) in the database directory.
+    #[default]
+    Lmdb,
+    /// redb file (`data.redb`) in the database directory. Only one process
+    /// can have it open, the others fail with [`Error::DatabaseInUse`].
+    Redb,
+}
+
+impl StorageBackend {
+    pub fn name(self) -> &'static str {
+        match self {
+            StorageBackend::Lmdb => "lmdb",
+            StorageBackend::Redb => "redb",
+        }
+    }
+}
+
+impl fmt::Display for StorageBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for StorageBackend {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "lmdb" => Ok(StorageBackend::Lmdb),
+            "redb" => Ok(StorageBackend::Redb),
+            _ => Err(Error::UnknownStorageBackend(name.to_string())),
+        }
+    }
+}
+
+/// How a tracker opens its database.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageOptions {
+    pub backend: StorageBackend,
+    /// Open LMDB without its lock file and without syncing to disk. Only safe
+    /// when a single process uses the database. Ignored by redb, which always
+    /// takes an exclusive lock on its file.
+    pub use_unsafe_no_lock: bool,
+}
+
+impl StorageOptions {
+    pub fn lmdb(use_unsafe_no_lock: bool) -> Self {
+        StorageOptions {
+            backend: StorageBackend::Lmdb,
+            use_unsafe_no_lock,
+        }
+    }
+}
+
+/// Name of a table. `None` is the unnamed LMDB database.
+pub(crate) type TableName = Option<&'static str>;
+
+/// Called with the raw key and value of each entry by [`StorageRead::for_each`].
+pub(crate) type VisitEntry<'a> = dyn FnMut(&[u8], &[u8]) -> Result<()> + 'a;
+
+/// Read access to the tables inside a transaction.
+pub(crate) trait StorageRead {
+    fn get(&self, table: TableName, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    fn len(&self, table: TableName) -> Result<u64>;
+
+    /// Visit every entry of `table` in key order.
+    fn for_each(&self, table: TableName, visit: &mut VisitEntry<'_>) -> Result<()>;
+}
+
+/// Write access to the tables inside a transaction.
+pub(crate) trait StorageWrite: StorageRead {
+    fn put(&mut self, table: TableName, key: &[u8], value: &[u8]) -> Result<()>;
+
+    /// Returns whether the key existed.
+    fn delete(&mut self, table: TableName, key: &[u8]) -> Result<bool>;
+}
+
+/// An open database. Use the generic [`read`](Self::read) and
+/// [`write`](Self::write) wrappers rather than the transaction methods.
+pub(crate) trait Storage: Send + Sync + fmt::Debug {
+    fn backend(&self) -> StorageBackend;
+
+    /// Directory the database files live in.
+    fn path(&self) -> &Path;
+
+    fn disk_size(&self) -> Result<u64>;
+
+    fn read_txn(&self, read: &mut dyn FnMut(&dyn StorageRead) -> Result<()>) -> Result<()>;
+
+    /// Run `write` in a write transaction and commit it. `write` can run more
+    /// than once when the backend has to retry the transaction.
+    fn write_txn(&self, write: &mut dyn FnMut(&mut dyn StorageWrite) -> Result<()>) -> Result<()>;
+}
+
+impl dyn Storage + '_ {
+    pub fn read<T>(&self, mut read: impl FnMut(&dyn StorageRead) -> Result<T>) -> Result<T> {
+        let mut value = None;
+        self.read_txn(&mut |txn| {
+            value = Some(read(txn)?);
+            Ok(())
+        })?;
+        Ok(value.expect("read transaction ran"))
+    }
+
+    pub fn write<T>(&self, mut write: impl FnMut(&mut dyn StorageWrite) -> Result<T>) -> Result<T> {
+        let mut value = None;
+        self.write_txn(&mut |txn| {
+            value = Some(write(txn)?);
+            Ok(())
+        })?;
+        Ok(value.expect("write transaction ran"))
+    }
+}
+
+/// Tables of a database and the LMDB limits it is opened with.
+pub(crate) struct Schema {
+    pub tables: &'static [DbTable],
+    pub min_map_size: usize,
+    pub max_dbs: u32,
+}
+
+/// A table of a database and a check that its values still decode.
+pub(crate) struct DbTable {
+    pub name: TableName,
+    pub is_valid: fn(&[u8]) -> bool,
+}
+
+/// Whether `bytes` decode as a `V`, for [`DbTable::is_valid`].
+pub(crate) fn decodes<V: DeserializeOwned>(bytes: &[u8]) -> bool {
+    bincode::deserialize::<V>(bytes).is_ok()
+}
+
+/// A table whose values are `V` encoded with bincode.
+pub(crate) struct Table<V> {
+    name: TableName,
+    _value: PhantomData<fn() -> V>,
+}
+
+impl<V> Clone for Table<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for Table<V> {}
+
+impl<V> fmt::Debug for Table<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Table").field(&self.name).finish()
+    }
+}
+
+impl<V: Serialize + DeserializeOwned> Table<V> {
+    pub const fn new(name: TableName) -> Self {
+        Table {
+            name,
+            _value: PhantomData,
+        }
+    }
+
+    pub const fn schema(self) -> DbTable {
+        DbTable {
+            name: self.name,
+            is_valid: decodes::<V>,
+        }
+    }
+
+    pub fn get(self, txn: &(impl StorageRead + ?Sized), key: &[u8]) -> Result<Option<V>> {
+        txn.get(self.name, key)?
+            .map(|bytes| bincode::deserialize(&bytes).map_err(Error::DbDecode))
+            .transpose()
+    }
+
+    pub fn put(self, txn: &mut (impl StorageWrite + ?Sized), key: &[u8], value: &V) -> Result<()> {
+        let bytes = bincode::serialize(value).map_err(Error::DbEncode)?;
+        txn.put(self.name, key, &bytes)
+    }
+
+    pub fn delete(self, txn: &mut (impl StorageWrite + ?Sized), key: &[u8]) -> Result<bool> {
+        txn.delete(self.name, key)
+    }
+
+    pub fn len(self, txn: &(impl StorageRead + ?Sized)) -> Result<u64> {
+        txn.len(self.name)
+    }
+
+    /// Visit every entry. A value that doesn't decode ends the walk with an error.
+    pub fn for_each(
+        self,
+        txn: &(impl StorageRead + ?Sized),
+        mut visit: impl FnMut(&[u8], V),
+    ) -> Result<()> {
+        txn.for_each(self.name, &mut |key, bytes| {
+            visit(key, bincode::deserialize(bytes).map_err(Error::DbDecode)?);
+            Ok(())
+        })
+    }
+}
+
+/// Open the database in the `db_path` directory with the chosen backend.
+pub(crate) fn open(
+    db_path: &Path,
+    options: StorageOptions,
+    schema: &Schema,
+) -> Result<Arc<dyn Storage>> {
+    fs::create_dir_all(db_path).map_err(Error::CreateDir)?;
+    Ok(match options.backend {
+        StorageBackend::Lmdb => Arc::new(lmdb_backend::LmdbStorage::open(
+            db_path,
+            schema,
+            options.use_unsafe_no_lock,
+        )?),
+        StorageBackend::Redb => Arc::new(redb_backend::RedbStorage::open(db_path, schema)?),
+    })
+}
+
+/// Rebuild the database in `db_path` from every entry that can still be read
+/// and decoded. Nothing else may have the database open.
+pub(crate) fn repair(db_path: &Path, backend: StorageBackend, schema: &Schema) -> Result<DbRepair> {
+    fs::create_dir_all(db_path).map_err(Error::CreateDir)?;
+    let report = match backend {
+        StorageBackend::Lmdb => lmdb_backend::repair(db_path, schema)?,
+        StorageBackend::Redb => redb_backend::repair(db_path, schema)?,
+    };
+
+    tracing::info!(?db_path, %backend, ?report, "Database repaired");
+    Ok(report)
+}
+
+/// Delete the files of a closed database so it can be written again from scratch.
+pub(crate) fn remove(db_path: &Path, backend: StorageBackend) {
+    let files: &[&str] = match backend {
+        StorageBackend::Lmdb => &["data.mdb", "lock.mdb"],
+        StorageBackend::Redb => &["data.redb"],
+    };
+    for file in files {
+        let _ = fs::remove_file(db_path.join(file));
+    }
+}
+
+/// Size of the main data file of a database, 0 when it doesn't exist.
+pub(crate) fn data_file_size(db_path: &Path, backend: StorageBackend) -> u64 {
+    let file = match backend {
+        StorageBackend::Lmdb => "data.mdb",
+        StorageBackend::Redb => "data.redb",
+    };
+    fs::metadata(db_path.join(file)).map_or(0, |meta| meta.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    // LMDB lists named tables in the unnamed one, so the test only uses named ones
+    const ACCESSES: Table<VecDeque<u64>> = Table::new(Some("accesses"));
+    const NAMES: Table<String> = Table::new(Some("names"));
+    const SCHEMA: Schema = Schema {
+        tables: &[ACCESSES.schema(), NAMES.schema()],
+        min_map_size: 1024 * 1024,
+        max_dbs: 4,
+    };
+
+    fn backends() -> [StorageOptions; 2] {
+        [
+            StorageOptions::lmdb(false),
+            StorageOptions {
+                backend: StorageBackend::Redb,
+                use_unsafe_no_lock: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn backends_store_the_same_tables() {
+        for options in backends() {
+            let tmp = tempfile::tempdir().unwrap();
+            {
+                let storage = open(tmp.path(), options, &SCHEMA).unwrap();
+                storage
+                    .write(|txn| {
+                        ACCESSES.put(txn, b"a", &VecDeque::from([1, 2]))?;
+                        ACCESSES.put(txn, b"b", &VecDeque::from([3]))?;
+                        NAMES.put(txn, b"a", &"main.rs".to_string())
+                    })
+                    .unwrap();
+                assert!(storage.write(|txn| ACCESSES.delete(txn, b"b")).unwrap());
+            }
+
+            let storage = open(tmp.path(), options, &SCHEMA).unwrap();
+            assert_eq!(storage.backend(), options.backend);
+            storage
+                .read(|txn| {
+                    assert_eq!(ACCESSES.len(txn)?, 1, "{}", options.backend);
+                    assert_eq!(ACCESSES.get(txn, b"a")?, Some(VecDeque::from([1, 2])));
+                    assert_eq!(ACCESSES.get(txn, b"b")?, None);
+                    assert_eq!(NAMES.get(txn, b"a")?.as_deref(), Some("main.rs"));
+
+                    let mut keys = Vec::new();
+                    NAMES.for_each(txn, |key, _| keys.push(key.to_vec()))?;
+                    assert_eq!(keys, [b"a".to_vec()]);
+                    Ok(())
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn repair_keeps_entries_that_still_decode() {
+        for options in backends() {
+            let tmp = tempfile::tempdir().unwrap();
+            {
+                let storage = open(tmp.path(), options, &SCHEMA).unwrap();
+                storage
+                    .write(|txn| {
+                        ACCESSES.put(txn, b"valid", &VecDeque::from([1, 2]))?;
+                        txn.put(Some("accesses"), b"garbage", &[1, 2, 3])
+                    })
+                    .unwrap();
+            }
+
+            let report = repair(tmp.path(), options.backend, &SCHEMA).unwrap();
+            assert_eq!(
+                report,
+                DbRepair {
+                    kept: 1,
+                    dropped: 1,
+                    reset: false
+                },
+                "{}",
+                options.backend
+            );
+
+            let storage = open(tmp.path(), options, &SCHEMA).unwrap();
+            storage
+                .read(|txn| {
+                    assert_eq!(ACCESSES.len(txn)?, 1);
+                    assert_eq!(ACCESSES.get(txn, b"valid")?, Some(VecDeque::from([1, 2])));
+                    Ok(())
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn unreadable_database_is_moved_aside() {
+        for (options, file) in backends().into_iter().zip(["data.mdb", "data.redb"]) {
+            let tmp = tempfile::tempdir().unwrap();
+            fs::write(tmp.path().join(file), vec![0xAB; 64 * 1024]).unwrap();
+
+            let report = repair(tmp.path(), options.backend, &SCHEMA).unwrap();
+
+            assert!(report.reset, "{}", options.backend);
+            assert_eq!(report.kept, 0);
+            assert!(tmp.path().join(format!("{file}.corrupt")).exists());
+            assert!(open(tmp.path(), options, &SCHEMA).is_ok());
+        }
+    }
+
+    #[test]
+    fn redb_reports_a_database_open_elsewhere() {
+        let tmp = tempfile::tempdir().unwrap();
+        let options = backends()[1];
+        let _open = open(tmp.path(), options, &SCHEMA).unwrap();
+
+        let error = open(tmp.path(), options, &SCHEMA).unwrap_err();
+        assert!(matches!(error, Error::DatabaseInUse(ref path) if path == tmp.path()));
+        assert!(!error.is_corruption());
+    }
+}
@@ -0,0 +1,489 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use heed::types::Bytes;
+use heed::{Database, Env, EnvFlags, EnvOpenOptions, MdbError, RoTxn, RwTxn};
+use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use super::{Schema, Storage, StorageBackend, StorageRead, StorageWrite, TableName, VisitEntry};
+use crate::db_healthcheck::DbRepair;
+use crate::error::{Error, Result};
+
+type RawDb = Database<Bytes, Bytes>;
+
+#[derive(Debug)]
+pub(super) struct LmdbStorage {
+    env: Env,
+    dbs: Vec<(TableName, RawDb)>,
+    txns: TxnGate,
+    /// Stands in for LMDB's own locking when it is turned off.
+    process_lock: Option<ProcessLock>,
+}
+
+impl LmdbStorage {
+    pub fn open(db_path: &Path, schema: &Schema, use_unsafe_no_lock: bool) -> Result<Self> {
+        let env = open_env(
+            db_path,
+            schema.min_map_size,
+            schema.max_dbs,
+            use_unsafe_no_lock,
+        )?;
+
+        let mut wtxn = env.write_txn().map_err(Error::DbStartWriteTxn)?;
+        let mut dbs = Vec::with_capacity(schema.tables.len());
+        for table in schema.tables {
+            let db = env
+                .create_database(&mut wtxn, table.name)
+                .map_err(Error::DbCreate)?;
+            dbs.push((table.name, db));
+        }
+        wtxn.commit().map_err(Error::DbCommit)?;
+
+        let process_lock = if use_unsafe_no_lock {
+            Some(ProcessLock::open(&db_path.join(PROCESS_LOCK_FILE))?)
+        } else {
+            None
+        };
+
+        Ok(LmdbStorage {
+            env,
+            dbs,
+            txns: TxnGate::default(),
+            process_lock,
+        })
+    }
+}
+
+impl Storage for LmdbStorage {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Lmdb
+    }
+
+    fn path(&self) -> &Path {
+        self.env.path()
+    }
+
+    fn disk_size(&self) -> Result<u64> {
+        self.env.real_disk_size().map_err(Error::EnvOpen)
+    }
+
+    fn read_txn(&self, read: &mut dyn FnMut(&dyn StorageRead) -> Result<()>) -> Result<()> {
+        // taken before the gate, so a writer growing the map never waits on us
+        let _process = self
+            .process_lock
+            .as_ref()
+            .map(ProcessLock::read)
+            .transpose()?;
+        let _txn = self.txns.enter();
+        let rtxn = self.env.read_txn().map_err(Error::DbStartReadTxn)?;
+        read(&LmdbRead {
+            txn: &rtxn,
+            dbs: &self.dbs,
+        })
+    }
+
+    fn write_txn(&self, write: &mut dyn FnMut(&mut dyn StorageWrite) -> Result<()>) -> Result<()> {
+        let _process = self
+            .process_lock
+            .as_ref()
+            .map(ProcessLock::write)
+            .transpose()?;
+        write_txn(&self.env, &self.txns, |wtxn| {
+            write(&mut LmdbWrite {
+                txn: wtxn,
+                dbs: &self.dbs,
+            })
+        })
+    }
+}
+
+fn find_db(dbs: &[(TableName, RawDb)], table: TableName) -> RawDb {
+    dbs.iter()
+        .find(|(name, _)| *name == table)
+        .map(|(_, db)| *db)
+        .expect("table is part of the schema")
+}
+
+fn get(
+    dbs: &[(TableName, RawDb)],
+    txn: &RoTxn,
+    table: TableName,
+    key: &[u8],
+) -> Result<Option<Vec<u8>>> {
+    let value = find_db(dbs, table).get(txn, key).map_err(Error::DbRead)?;
+    Ok(value.map(<[u8]>::to_vec))
+}
+
+fn for_each(
+    dbs: &[(TableName, RawDb)],
+    txn: &RoTxn,
+    table: TableName,
+    visit: &mut VisitEntry<'_>,
+) -> Result<()> {
+    for item in find_db(dbs, table).iter(txn).map_err(Error::DbRead)? {
+        let (key, value) = item.map_err(Error::DbRead)?;
+        visit(key, value)?;
+    }
+    Ok(())
+}
+
+struct LmdbRead<'a> {
+    txn: &'a RoTxn<'a>,
+    dbs: &'a [(TableName, RawDb)],
+}
+
+impl StorageRead for LmdbRead<'_> {
+    fn get(&self, table: TableName, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        get(self.dbs, self.txn, table, key)
+    }
+
+    fn len(&self, table: TableName) -> Result<u64> {
+        find_db(self.dbs, table)
+            .len(self.txn)
+            .map_err(Error::DbRead)
+    }
+
+    fn for_each(&self, table: TableName, visit: &mut VisitEntry<'_>) -> Result<()> {
+        for_each(self.dbs, self.txn, table, visit)
+    }
+}
+
+struct LmdbWrite<'a, 'p> {
+    txn: &'a mut RwTxn<'p>,
+    dbs: &'a [(TableName, RawDb)],
+}
+
+impl StorageRead for LmdbWrite<'_, '_> {
+    fn get(&self, table: TableName, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        get(self.dbs, self.txn, table, key)
+    }
+
+    fn len(&self, table: TableName) -> Result<u64> {
+        find_db(self.dbs, table)
+            .len(self.txn)
+            .map_err(Error::DbRead)
+    }
+
+    fn for_each(&self, table: TableName, visit: &mut VisitEntry<'_>) -> Result<()> {
+        for_each(self.dbs, self.txn, table, visit)
+    }
+}
+
+impl StorageWrite for LmdbWrite<'_, '_> {
+    fn put(&mut self, table: TableName, key: &[u8], value: &[u8]) -> Result<()> {
+        find_db(self.dbs, table)
+            .put(self.txn, key, value)
+            .map_err(Error::DbWrite)
+    }
+
+    fn delete(&mut self, table: TableName, key: &[u8]) -> Result<bool> {
+        find_db(self.dbs, table)
+            .delete(self.txn, key)
+            .map_err(Error::DbWrite)
+    }
+}
+
+/// Advisory lock file next to an environment opened without LMDB's locking.
+const PROCESS_LOCK_FILE: &str = "process.lock";
+
+/// Reader-writer lock shared by every process that opens the environment
+/// without LMDB's locking, which then requires that no reader runs while a
+/// transaction is written.
+///
+/// File locks belong to the open file, not to a thread, so the threads of this
+/// process share one shared lock: the first reader takes it and the last one
+/// releases it. Writers first wait for local readers, then lock the file
+/// exclusively.
+#[derive(Debug)]
+struct ProcessLock {
+    file: File,
+    path: PathBuf,
+    local: RwLock<()>,
+    readers: Mutex<usize>,
+}
+
+struct ProcessReadGuard<'a> {
+    lock: &'a ProcessLock,
+    _local: RwLockReadGuard<'a, ()>,
+}
+
+struct ProcessWriteGuard<'a> {
+    lock: &'a ProcessLock,
+    _local: RwLockWriteGuard<'a, ()>,
+}
+
+impl ProcessLock {
+    fn open(path: &Path) -> Result<Self> {
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .map_err(|e| Error::Io(path.to_path_buf(), e))?;
+        Ok(ProcessLock {
+            file,
+            path: path.to_path_buf(),
+            local: RwLock::new(()),
+            readers: Mutex::new(0),
+        })
+    }
+
+    fn read(&self) -> Result<ProcessReadGuard<'_>> {
+        let local = self.local.read();
+        let mut readers = self.readers.lock();
+        if *readers == 0 {
+            self.file
+                .lock_shared()
+                .map_err(|e| Error::Io(self.path.clone(), e))?;
+        }
+        *readers += 1;
+        Ok(ProcessReadGuard {
+            lock: self,
+            _local: local,
+        })
+    }
+
+    fn write(&self) -> Result<ProcessWriteGuard<'_>> {
+        let local = self.local.write();
+        self.file
+            .lock()
+            .map_err(|e| Error::Io(self.path.clone(), e))?;
+        Ok(ProcessWriteGuard {
+            lock: self,
+            _local: local,
+        })
+    }
+}
+
+impl Drop for ProcessReadGuard<'_> {
+    fn drop(&mut self) {
+        let mut readers = self.lock.readers.lock();
+        *readers -= 1;
+        if *readers == 0 {
+            let _ = self.lock.file.unlock();
+        }
+    }
+}
+
+impl Drop for ProcessWriteGuard<'_> {
+    fn drop(&mut self) {
+        let _ = self.lock.file.unlock();
+    }
+}
+
+/// Lets a map resize wait until no transaction is running in this process.
+///
+/// Transactions hold the gate for reading, growing the map takes it for writing.
+#[derive(Debug, Default)]
+struct TxnGate(RwLock<()>);
+
+impl TxnGate {
+    fn enter(&self) -> RwLockReadGuard<'_, ()> {
+        self.0.read()
+    }
+}
+
+/// Open the environment at `db_path`. The map is at least `min_map_size` and
+/// twice the current data file, so a database that filled its map last time
+/// has room again.
+fn open_env(
+    db_path: &Path,
+    min_map_size: usize,
+    max_dbs: u32,
+    use_unsafe_no_lock: bool,
+) -> Result<Env> {
+    fs::create_dir_all(db_path).map_err(Error::CreateDir)?;
+
+    let data_size = fs::metadata(db_path.join("data.mdb")).map_or(0, |meta| meta.len() as usize);
+    let map_size = min_map_size.max(data_size.saturating_mul(2).next_power_of_two());
+
+    let env = unsafe {
+        let mut opts = EnvOpenOptions::new();
+        opts.map_size(map_size);
+        if max_dbs > 0 {
+            opts.max_dbs(max_dbs);
+        }
+        if use_unsafe_no_lock {
+            opts.flags(EnvFlags::NO_LOCK | EnvFlags::NO_SYNC | EnvFlags::NO_META_SYNC);
+        }
+        opts.open(db_path).map_err(Error::EnvOpen)?
+    };
+    env.clear_stale_readers()
+        .map_err(Error::DbClearStaleReaders)?;
+
+    Ok(env)
+}
+
+/// Run `write` in a write transaction and commit it. When the map is full it
+/// is doubled and `write` runs once more. Callers must not hold `gate`.
+fn write_txn<T>(
+    env: &Env,
+    gate: &TxnGate,
+    mut write: impl FnMut(&mut RwTxn<'_>) -> Result<T>,
+) -> Result<T> {
+    let mut attempt = || {
+        let _txn = gate.enter();
+        let mut wtxn = env.write_txn().map_err(Error::DbStartWriteTxn)?;
+        let value = write(&mut wtxn)?;
+        wtxn.commit().map_err(Error::DbCommit)?;
+        Ok(value)
+    };
+
+    match attempt() {
+        Err(e) if is_map_full(&e) => {
+            grow_map(env, gate)?;
+            attempt()
+        }
+        result => result,
+    }
+}
+
+fn is_map_full(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::DbWrite(heed::Error::Mdb(MdbError::MapFull))
+            | Error::DbCommit(heed::Error::Mdb(MdbError::MapFull))
+    )
+}
+
+fn grow_map(env: &Env, gate: &TxnGate) -> Result<()> {
+    let _resize = gate.0.write();
+    let map_size = env.info().map_size * 2;
+    // SAFETY: every transaction on this environment holds the gate, so none
+    // is running while we hold it exclusively.
+    unsafe { env.resize(map_size) }.map_err(Error::DbResize)?;
+
+    tracing::info!(path = ?env.path(), map_size, "Database map was full, grew it");
+    Ok(())
+}
+
+/// Rebuild the environment at `db_path` from the entries that still decode.
+/// An environment that can't be opened at all is moved to `data.mdb.corrupt`.
+pub(super) fn repair(db_path: &Path, schema: &Schema) -> Result<DbRepair> {
+    let data_path = db_path.join("data.mdb");
+    let lock_path = db_path.join("lock.mdb");
+    let mut report = DbRepair::default();
+
+    let salvaged = match salvage(db_path, schema, &mut report) {
+        Ok(salvaged) => {
+            let _ = fs::remove_file(&data_path);
+            salvaged
+        }
+        Err(e) => {
+            tracing::warn!(?db_path, "Database can not be opened, starting over: {e}");
+            fs::rename(&data_path, db_path.join("data.mdb.corrupt"))
+                .map_err(|e| Error::Io(data_path, e))?;
+            report.reset = true;
+            vec![Vec::new(); schema.tables.len()]
+        }
+    };
+    let _ = fs::remove_file(&lock_path);
+
+    let env = open_env(db_path, schema.min_map_size, schema.max_dbs, false)?;
+    let mut wtxn = env.write_txn().map_err(Error::DbStartWriteTxn)?;
+    for (table, entries) in schema.tables.iter().zip(salvaged) {
+        let db: RawDb = env
+            .create_database(&mut wtxn, table.name)
+            .map_err(Error::DbCreate)?;
+        for (key, value) in &entries {
+            db.put(&mut wtxn, key, value).map_err(Error::DbWrite)?;
+        }
+        report.kept += entries.len();
+    }
+    wtxn.commit().map_err(Error::DbCommit)?;
+
+    Ok(report)
+}
+
+type Entries = Vec<(Vec<u8>, Vec<u8>)>;
+
+fn salvage(db_path: &Path, schema: &Schema, report: &mut DbRepair) -> Result<Vec<Entries>> {
+    let env = open_env(db_path, schema.min_map_size, schema.max_dbs, false)?;
+    let rtxn = env.read_txn().map_err(Error::DbStartReadTxn)?;
+
+    let mut salvaged = Vec::with_capacity(schema.tables.len());
+    for table in schema.tables {
+        let mut entries = Vec::new();
+        let db = env
+            .open_database::<Bytes, Bytes>(&rtxn, table.name)
+            .map_err(Error::DbRead)?;
+        if let Some(db) = db {
+            for item in db.iter(&rtxn).map_err(Error::DbRead)? {
+                match item {
+                    Ok((key, value)) if (table.is_valid)(value) => {
+                        entries.push((key.to_vec(), value.to_vec()));
+                    }
+                    Ok(_) => report.dropped += 1,
+                    // the cursor can not move past a broken page
+                    Err(_) => {
+                        report.dropped += 1;
+                        break;
+                    }
+                }
+            }
+        }
+        salvaged.push(entries);
+    }
+
+    Ok(salvaged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::TryLockError;
+
+    // map sizes must be whole OS pages, 16 KiB also covers Apple Silicon
+    const PAGE: usize = 16 * 1024;
+
+    #[test]
+    fn full_map_is_grown_and_the_write_retried() {
+        let tmp = tempfile::tempdir().unwrap();
+        let min_map_size = PAGE * 16;
+        let env = open_env(tmp.path(), min_map_size, 0, true).unwrap();
+        let gate = TxnGate::default();
+        let db = write_txn(&env, &gate, |wtxn| {
+            env.create_database::<Bytes, Bytes>(wtxn, None)
+                .map_err(Error::DbCreate)
+        })
+        .unwrap();
+
+        let value = vec![7u8; 4096];
+        for key in 0..64u32 {
+            write_txn(&env, &gate, |wtxn| {
+                db.put(wtxn, &key.to_be_bytes(), &value)
+                    .map_err(Error::DbWrite)
+            })
+            .unwrap();
+        }
+
+        assert!(env.info().map_size > min_map_size);
+        let rtxn = env.read_txn().unwrap();
+        assert_eq!(db.len(&rtxn).unwrap(), 64);
+    }
+
+    #[test]
+    fn process_lock_is_shared_by_readers_and_excludes_other_processes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join(PROCESS_LOCK_FILE);
+        let lock = ProcessLock::open(&path).unwrap();
+        // a second open file behaves like another process
+        let other = File::options().write(true).open(&path).unwrap();
+
+        let first = lock.read().unwrap();
+        let second = lock.read().unwrap();
+        drop(first);
+        assert!(matches!(other.try_lock(), Err(TryLockError::WouldBlock)));
+        assert!(other.try_lock_shared().is_ok());
+        other.unlock().unwrap();
+        drop(second);
+
+        let write = lock.write().unwrap();
+        assert!(matches!(
+            other.try_lock_shared(),
+            Err(TryLockError::WouldBlock)
+        ));
+        drop(write);
+        assert!(other.try_lock().is_ok());
+    }
+}
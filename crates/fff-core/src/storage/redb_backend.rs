@@ -0,0 +1,203 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use redb::{
+    Database, ReadTransaction, ReadableTable, ReadableTableMetadata, TableDefinition,
+    WriteTransaction,
+};
+
+use super::{Schema, Storage, StorageBackend, StorageRead, StorageWrite, TableName, VisitEntry};
+use crate::db_healthcheck::DbRepair;
+use crate::error::{Error, Result};
+
+const DATA_FILE: &str = "data.redb";
+
+type RawTable = TableDefinition<'static, &'static [u8], &'static [u8]>;
+
+fn definition(table: TableName) -> RawTable {
+    // redb has no unnamed table
+    TableDefinition::new(table.unwrap_or("default"))
+}
+
+fn redb_error(error: impl Into<redb::Error>) -> Error {
+    Error::Redb(Box::new(error.into()))
+}
+
+#[derive(Debug)]
+pub(super) struct RedbStorage {
+    db: Database,
+    path: PathBuf,
+}
+
+impl RedbStorage {
+    pub fn open(db_path: &Path, schema: &Schema) -> Result<Self> {
+        let db = Database::create(db_path.join(DATA_FILE)).map_err(|e| match e {
+            // redb locks its file for as long as it is open
+            redb::DatabaseError::DatabaseAlreadyOpen => Error::DatabaseInUse(db_path.to_path_buf()),
+            e => redb_error(e),
+        })?;
+
+        // create every table up front so readers never find one missing
+        let wtxn = db.begin_write().map_err(redb_error)?;
+        for table in schema.tables {
+            wtxn.open_table(definition(table.name))
+                .map_err(redb_error)?;
+        }
+        wtxn.commit().map_err(redb_error)?;
+
+        Ok(RedbStorage {
+            db,
+            path: db_path.to_path_buf(),
+        })
+    }
+}
+
+impl Storage for RedbStorage {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Redb
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn disk_size(&self) -> Result<u64> {
+        let data_path = self.path.join(DATA_FILE);
+        let meta = fs::metadata(&data_path).map_err(|e| Error::Io(data_path, e))?;
+        Ok(meta.len())
+    }
+
+    fn read_txn(&self, read: &mut dyn FnMut(&dyn StorageRead) -> Result<()>) -> Result<()> {
+        let rtxn = self.db.begin_read().map_err(redb_error)?;
+        read(&RedbRead(rtxn))
+    }
+
+    fn write_txn(&self, write: &mut dyn FnMut(&mut dyn StorageWrite) -> Result<()>) -> Result<()> {
+        let wtxn = self.db.begin_write().map_err(redb_error)?;
+        write(&mut RedbWrite(&wtxn))?;
+        wtxn.commit().map_err(redb_error)
+    }
+}
+
+fn get(
+    table: &impl ReadableTable<&'static [u8], &'static [u8]>,
+    key: &[u8],
+) -> Result<Option<Vec<u8>>> {
+    let value = table.get(key).map_err(redb_error)?;
+    Ok(value.map(|value| value.value().to_vec()))
+}
+
+fn for_each(
+    table: &impl ReadableTable<&'static [u8], &'static [u8]>,
+    visit: &mut VisitEntry<'_>,
+) -> Result<()> {
+    for item in table.iter().map_err(redb_error)? {
+        let (key, value) = item.map_err(redb_error)?;
+        visit(key.value(), value.value())?;
+    }
+    Ok(())
+}
+
+struct RedbRead(ReadTransaction);
+
+impl StorageRead for RedbRead {
+    fn get(&self, table: TableName, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        get(
+            &self.0.open_table(definition(table)).map_err(redb_error)?,
+            key,
+        )
+    }
+
+    fn len(&self, table: TableName) -> Result<u64> {
+        let table = self.0.open_table(definition(table)).map_err(redb_error)?;
+        table.len().map_err(redb_error)
+    }
+
+    fn for_each(&self, table: TableName, visit: &mut VisitEntry<'_>) -> Result<()> {
+        for_each(
+            &self.0.open_table(definition(table)).map_err(redb_error)?,
+            visit,
+        )
+    }
+}
+
+struct RedbWrite<'a>(&'a WriteTransaction);
+
+impl StorageRead for RedbWrite<'_> {
+    fn get(&self, table: TableName, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        get(
+            &self.0.open_table(definition(table)).map_err(redb_error)?,
+            key,
+        )
+    }
+
+    fn len(&self, table: TableName) -> Result<u64> {
+        let table = self.0.open_table(definition(table)).map_err(redb_error)?;
+        table.len().map_err(redb_error)
+    }
+
+    fn for_each(&self, table: TableName, visit: &mut VisitEntry<'_>) -> Result<()> {
+        for_each(
+            &self.0.open_table(definition(table)).map_err(redb_error)?,
+            visit,
+        )
+    }
+}
+
+impl StorageWrite for RedbWrite<'_> {
+    fn put(&mut self, table: TableName, key: &[u8], value: &[u8]) -> Result<()> {
+        let mut table = self.0.open_table(definition(table)).map_err(redb_error)?;
+        table.insert(key, value).map_err(redb_error)?;
+        Ok(())
+    }
+
+    fn delete(&mut self, table: TableName, key: &[u8]) -> Result<bool> {
+        let mut table = self.0.open_table(definition(table)).map_err(redb_error)?;
+        let removed = table.remove(key).map_err(redb_error)?;
+        Ok(removed.is_some())
+    }
+}
+
+/// Delete the entries that no longer decode. redb repairs its own file
+/// structure when it is opened; a file it can't make sense of is moved to
+/// `data.redb.corrupt`.
+pub(super) fn repair(db_path: &Path, schema: &Schema) -> Result<DbRepair> {
+    let mut report = DbRepair::default();
+
+    let storage = match RedbStorage::open(db_path, schema) {
+        Ok(storage) => storage,
+        Err(e) if e.is_corruption() => {
+            tracing::warn!(?db_path, "Database can not be opened, starting over: {e}");
+            let data_path = db_path.join(DATA_FILE);
+            fs::rename(&data_path, db_path.join("data.redb.corrupt"))
+                .map_err(|e| Error::Io(data_path, e))?;
+            report.reset = true;
+            return RedbStorage::open(db_path, schema).map(|_| report);
+        }
+        Err(e) => return Err(e),
+    };
+
+    storage.write_txn(&mut |txn| {
+        let (mut kept, mut dropped) = (0, 0);
+        for table in schema.tables {
+            let mut invalid = Vec::new();
+            txn.for_each(table.name, &mut |key, value| {
+                if (table.is_valid)(value) {
+                    kept += 1;
+                } else {
+                    invalid.push(key.to_vec());
+                }
+                Ok(())
+            })?;
+            for key in &invalid {
+                txn.delete(table.name, key)?;
+            }
+            dropped += invalid.len();
+        }
+        report.kept = kept;
+        report.dropped = dropped;
+        Ok(())
+    })?;
+
+    Ok(report)
+}
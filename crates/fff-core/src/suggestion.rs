@@ -0,0 +1,243 @@
+//! "Did you mean" corrections for queries that match nothing.
+//!
+//! At scan time every indexed path is split into lowercase word tokens
+//! (path segments, `snake_case`/`kebab-case` pieces and camelCase humps) and
+//! the distinct tokens are indexed by their trigrams. A misspelled query word
+//! is corrected to the closest token sharing trigrams with it, preferring the
+//! smaller edit distance and then the token that occurs in more paths.
+
+use crate::file_list::FileList;
+use ahash::AHashMap;
+
+/// Words shorter than this are never corrected: with so few characters
+/// almost any token is within one edit.
+const MIN_WORD_LEN: usize = 3;
+
+#[derive(Debug, Default)]
+pub(crate) struct PathTokenIndex {
+    tokens: Vec<Box<str>>,
+    /// Number of paths each token appears in, parallel to `tokens`.
+    counts: Vec<u32>,
+    by_token: AHashMap<Box<str>, u32>,
+    trigrams: AHashMap<[u8; 3], Vec<u32>>,
+}
+
+impl PathTokenIndex {
+    pub(crate) fn build(files: &FileList) -> Self {
+        let mut index = Self::default();
+        let mut seen_in_path = Vec::new();
+
+        for file in files {
+            seen_in_path.clear();
+            for_each_token(file.relative_path(), |token| {
+                let id = index.intern(token);
+                if !seen_in_path.contains(&id) {
+                    seen_in_path.push(id);
+                    index.counts[id as usize] += 1;
+                }
+            });
+        }
+
+        index
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    fn intern(&mut self, token: &str) -> u32 {
+        if let Some(&id) = self.by_token.get(token) {
+            return id;
+        }
+
+        let id = self.tokens.len() as u32;
+        for trigram in token.as_bytes().windows(3) {
+            let ids = self
+                .trigrams
+                .entry([trigram[0], trigram[1], trigram[2]])
+                .or_default();
+            if ids.last() != Some(&id) {
+                ids.push(id);
+            }
+        }
+        self.tokens.push(token.into());
+        self.counts.push(0);
+        self.by_token.insert(token.into(), id);
+        id
+    }
+
+    /// Closest known token to `word` (already lowercase), if any is close
+    /// enough to be a plausible typo.
+    fn correct_word(&self, word: &str) -> Option<&str> {
+        let mut shared: AHashMap<u32, u16> = AHashMap::new();
+        for trigram in word.as_bytes().windows(3) {
+            if let Some(ids) = self.trigrams.get(&[trigram[0], trigram[1], trigram[2]]) {
+                for &id in ids {
+                    *shared.entry(id).or_default() += 1;
+                }
+            }
+        }
+
+        let max_distance = if word.len() <= 4 { 1 } else { 2 };
+        shared
+            .into_iter()
+            .filter_map(|(id, _)| {
+                let token = &self.tokens[id as usize];
+                if token.len().abs_diff(word.len()) > max_distance {
+                    return None;
+                }
+                let distance = edit_distance(word.as_bytes(), token.as_bytes());
+                (distance <= max_distance).then_some((distance, self.counts[id as usize], id))
+            })
+            .min_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then(a.2.cmp(&b.2)))
+            .map(|(_, _, id)| &*self.tokens[id as usize])
+    }
+
+    /// Rewrites every unknown word of `query` to its closest path token.
+    /// Returns `None` when nothing could be corrected.
+    pub(crate) fn suggest(&self, query: &str) -> Option<String> {
+        let mut suggestion = String::with_capacity(query.len());
+        let mut corrected = false;
+        let mut last = 0;
+
+        for (start, end) in word_ranges(query) {
+            suggestion.push_str(&query[last..start]);
+            last = end;
+
+            let word = query[start..end].to_lowercase();
+            let replacement = (word.len() >= MIN_WORD_LEN && !self.by_token.contains_key(&*word))
+                .then(|| self.correct_word(&word))
+                .flatten();
+
+            match replacement {
+                Some(token) => {
+                    suggestion.push_str(token);
+                    corrected = true;
+                }
+                None => suggestion.push_str(&query[start..end]),
+            }
+        }
+        suggestion.push_str(&query[last..]);
+
+        corrected.then_some(suggestion)
+    }
+}
+
+/// Byte ranges of the alphanumeric runs of `text`.
+fn word_ranges(text: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        let (start, _) = chars.find(|(_, c)| c.is_alphanumeric())?;
+        let mut end = text.len();
+        while let Some(&(i, c)) = chars.peek() {
+            if !c.is_alphanumeric() {
+                end = i;
+                break;
+            }
+            chars.next();
+        }
+        Some((start, end))
+    })
+}
+
+/// Calls `f` with every lowercase token of `path`: each alphanumeric run, and
+/// for camelCase runs additionally each hump.
+fn for_each_token(path: &str, mut f: impl FnMut(&str)) {
+    let mut lowercase = String::new();
+    for (start, end) in word_ranges(path) {
+        let word = &path[start..end];
+        lowercase.clear();
+        lowercase.extend(word.chars().flat_map(char::to_lowercase));
+        if lowercase.len() >= MIN_WORD_LEN {
+            f(&lowercase);
+        }
+
+        let mut hump_start = 0;
+        let bytes = word.as_bytes();
+        for i in 1..bytes.len() {
+            if bytes[i].is_ascii_uppercase() && bytes[i - 1].is_ascii_lowercase() {
+                emit_hump(&word[hump_start..i], &mut f);
+                hump_start = i;
+            }
+        }
+        if hump_start > 0 {
+            emit_hump(&word[hump_start..], &mut f);
+        }
+    }
+}
+
+fn emit_hump(hump: &str, f: &mut impl FnMut(&str)) {
+    if hump.len() >= MIN_WORD_LEN {
+        f(&hump.to_lowercase());
+    }
+}
+
+/// Optimal string alignment distance (Levenshtein plus adjacent transpositions).
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    let width = b.len() + 1;
+    let mut rows = vec![0usize; width * 3];
+    let (mut prev2, mut prev, mut cur) = (0, width, 2 * width);
+
+    for (j, cell) in rows[prev..prev + width].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        rows[cur] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[prev + j] + 1)
+                .min(rows[cur + j - 1] + 1)
+                .min(rows[prev + j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[prev2 + j - 2] + 1);
+            }
+            rows[cur + j] = best;
+        }
+        (prev2, prev, cur) = (prev, cur, prev2);
+    }
+
+    rows[prev + b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FileItem;
+    use std::path::PathBuf;
+
+    fn file(path: &str) -> FileItem {
+        FileItem::new_raw(
+            PathBuf::from(path),
+            path.to_string(),
+            path.rsplit('/').next().unwrap().to_string(),
+            0,
+            0,
+            None,
+            false,
+        )
+    }
+
+    #[test]
+    fn edit_distance_counts_transpositions_once() {
+        assert_eq!(edit_distance(b"watcher", b"watcher"), 0);
+        assert_eq!(edit_distance(b"wacther", b"watcher"), 1);
+        assert_eq!(edit_distance(b"watchr", b"watcher"), 1);
+        assert_eq!(edit_distance(b"", b"abc"), 3);
+    }
+
+    #[test]
+    fn suggests_closest_path_tokens() {
+        let index = PathTokenIndex::build(&FileList::from(vec![
+            file("crates/fff-core/src/background_watcher.rs"),
+            file("lua/fff/picker_ui.lua"),
+            file("src/components/UserProfile.tsx"),
+        ]));
+
+        assert_eq!(index.suggest("wacther").as_deref(), Some("watcher"));
+        assert_eq!(index.suggest("piker ui").as_deref(), Some("picker ui"));
+        assert_eq!(index.suggest("usr Proflie").as_deref(), Some("usr profile"));
+        assert_eq!(index.suggest("picker"), None);
+        assert_eq!(index.suggest("zzzzzz"), None);
+    }
+}
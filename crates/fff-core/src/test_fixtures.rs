@@ -0,0 +1,100 @@
+//! Pickers over temporary directories for the tests of the crate
+
+use crate::file_list::FileList;
+use crate::file_picker::{FFFMode, FilePicker, FilePickerOptions};
+use crate::grep::{GrepMode, GrepSearchOptions};
+use crate::shared::SharedPicker;
+use git2::{IndexAddOption, Repository, Signature};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Options of a picker over `base` that doesn't watch it.
+pub(crate) fn picker_options(base: &Path) -> FilePickerOptions {
+    FilePickerOptions {
+        base_path: base.to_string_lossy().to_string(),
+        mode: FFFMode::Neovim,
+        watch: false,
+        ..Default::default()
+    }
+}
+
+/// Grep options with no limit a small test project would hit.
+pub(crate) fn grep_options(mode: GrepMode) -> GrepSearchOptions {
+    GrepSearchOptions {
+        max_file_size: 10 * 1024 * 1024,
+        max_matches_per_file: 200,
+        smart_case: true,
+        file_offset: 0,
+        page_limit: 200,
+        mode,
+        time_budget_ms: 0,
+        before_context: 0,
+        after_context: 0,
+        classify_definitions: false,
+        file_order: Default::default(),
+        scope: Default::default(),
+        changed_lines: None,
+        resume_after_line: 0,
+        exact_page_limit: false,
+        dedupe_lines: false,
+        history_boost: None,
+    }
+}
+
+/// A picker that scanned its base path before returning.
+pub(crate) fn scan(options: FilePickerOptions) -> FilePicker {
+    let mut picker = FilePicker::new(options).expect("Failed to create FilePicker");
+    picker.collect_files().expect("Failed to scan files");
+    picker
+}
+
+/// [`scan`] published in a [`SharedPicker`].
+pub(crate) fn scan_shared(options: FilePickerOptions) -> SharedPicker {
+    let shared_picker = SharedPicker::default();
+    *shared_picker.write().unwrap() = Some(scan(options));
+    shared_picker
+}
+
+/// Poll `ready` until it holds, failing the test after 30 seconds.
+pub(crate) fn wait_until(what: &str, mut ready: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while !ready() {
+        assert!(Instant::now() < deadline, "Timed out waiting for {what}");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Write every `(relative path, contents)` under `base`.
+pub(crate) fn write_files(base: &Path, files: &[(&str, &str)]) {
+    for (relative, contents) in files {
+        let path = base.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(path, contents).unwrap();
+    }
+}
+
+/// Relative paths of the live `files`, sorted, with `/` separators.
+pub(crate) fn indexed_paths(files: &FileList) -> Vec<String> {
+    let mut paths: Vec<String> = files
+        .iter()
+        .filter(|f| !f.is_deleted)
+        .map(|f| f.relative_path().replace('\\', "/"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Stage every file of the work tree of `repo` and commit them as its first
+/// commit.
+pub(crate) fn initial_commit(repo: &Repository) {
+    let mut index = repo.index().unwrap();
+    index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now("fff", "fff@example.com").unwrap();
+    repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+        .unwrap();
+}
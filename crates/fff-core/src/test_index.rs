@@ -0,0 +1,159 @@
+//! Index of the test functions and spec blocks in the indexed files.
+//!
+//! Built next to the TODO index, so a "run this test" picker looks tests up
+//! by name and finding the test around the cursor is a lookup too. Tests are
+//! found by one regex per declaration style rather than per language: Rust
+//! test attributes, `def test_`, Go `func Test` and `it(…)` / `test(…)`
+//! blocks of the JavaScript and Lua spec runners.
+
+use std::sync::LazyLock;
+
+use regex::bytes::{Captures, Regex, RegexSet};
+
+use crate::line_index::{LineCursor, LineEntry, LineIndex, LineMatch};
+
+const PATTERNS: [(&str, TestKind); 4] = [
+    (
+        r"(?m)^[ \t]*#\[(?:\w+::)*(?:test|rstest|test_case)\b[^\]]*\]\s*(?:#\[[^\]]*\]\s*)*(?:pub(?:\([^)]*\))?\s+)?(?:async\s+)?fn\s+(\w+)",
+        TestKind::Rust,
+    ),
+    (
+        r"(?m)^[ \t]*(?:async[ \t]+)?def[ \t]+(test\w*)",
+        TestKind::Python,
+    ),
+    (r"(?m)^func[ \t]+(Test\w*)[ \t]*\(", TestKind::Go),
+    (
+        r#"(?m)^[ \t]*(?:it|test)(?:\.\w+)?[ \t]*\([ \t]*(?:'([^'\n]*)'|"([^"\n]*)"|`([^`\n]*)`)"#,
+        TestKind::Spec,
+    ),
+];
+
+static MATCHERS: LazyLock<(RegexSet, Vec<Regex>)> = LazyLock::new(|| {
+    let patterns = PATTERNS.map(|(pattern, _)| pattern);
+    let any = RegexSet::new(patterns).expect("valid test patterns");
+    let compiled = patterns
+        .iter()
+        .map(|pattern| Regex::new(pattern).expect("valid test pattern"))
+        .collect();
+    (any, compiled)
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestKind {
+    /// `#[test] fn name`, also `#[tokio::test]`, `#[rstest]`.
+    Rust,
+    /// `def test_name`
+    Python,
+    /// `func TestName(`
+    Go,
+    /// `it("name", …)` or `test("name", …)`
+    Spec,
+}
+
+impl TestKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rust => "rust",
+            Self::Python => "python",
+            Self::Go => "go",
+            Self::Spec => "spec",
+        }
+    }
+}
+
+/// A test returned by [`FilePicker::search_tests`](crate::FilePicker::search_tests),
+/// `text` is the name of the test and `line_number` the line declaring it.
+pub type TestMatch<'a> = LineMatch<'a, TestKind>;
+
+pub(crate) type TestIndex = LineIndex<TestKind>;
+
+/// Finds the tests of a file, in line order.
+pub(crate) fn scan_tests(content: &[u8]) -> Vec<LineEntry<TestKind>> {
+    let (any, patterns) = &*MATCHERS;
+    let mut entries = Vec::new();
+    for pattern_index in any.matches(content).iter() {
+        let kind = PATTERNS[pattern_index].1;
+        let mut cursor = LineCursor::new(content);
+        for captures in patterns[pattern_index].captures_iter(content) {
+            let Some(name) = test_name(&captures) else {
+                continue;
+            };
+            let line = cursor.line_range(name.start());
+            if let Some(mut entry) = cursor.entry(line, kind)
+                && let Ok(name) = std::str::from_utf8(name.as_bytes())
+            {
+                entry.text = name.to_string();
+                entries.push(entry);
+            }
+        }
+    }
+
+    entries.sort_by_key(|entry| entry.line_number);
+    entries
+}
+
+/// The first group that took part in the match, spec names have one per
+/// quote style.
+fn test_name<'c>(captures: &Captures<'c>) -> Option<regex::bytes::Match<'c>> {
+    captures.iter().skip(1).flatten().next()
+}
+
+/// The test declared last at or before `line_number`, the one a cursor on
+/// that line is in when tests are not nested.
+pub(crate) fn enclosing_test(
+    entries: &[LineEntry<TestKind>],
+    line_number: u64,
+) -> Option<&LineEntry<TestKind>> {
+    let after = entries.partition_point(|entry| entry.line_number <= line_number);
+    entries[..after].last()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scanned(content: &str) -> Vec<(u64, TestKind, String)> {
+        scan_tests(content.as_bytes())
+            .into_iter()
+            .map(|e| (e.line_number, e.kind, e.text))
+            .collect()
+    }
+
+    #[test]
+    fn finds_tests_of_every_style() {
+        let rust = "mod tests {\n    #[test]\n    fn parses() {}\n\n    #[tokio::test]\n    #[ignore]\n    async fn fetches() {}\n\n    fn helper() {}\n}\n";
+        assert_eq!(
+            scanned(rust),
+            [
+                (3, TestKind::Rust, "parses".to_string()),
+                (7, TestKind::Rust, "fetches".to_string()),
+            ]
+        );
+
+        let python = "def helper():\n    pass\n\nclass TestQuery:\n    def test_parse(self):\n        pass\n";
+        assert_eq!(
+            scanned(python),
+            [(5, TestKind::Python, "test_parse".to_string())]
+        );
+
+        let go = "func TestParse(t *testing.T) {}\nfunc parse() {}\n";
+        assert_eq!(scanned(go), [(1, TestKind::Go, "TestParse".to_string())]);
+
+        let spec = "describe('query', () => {\n  it(\"parses a glob\", () => {})\n  test.skip(`keeps text`, () => {})\n})\n";
+        assert_eq!(
+            scanned(spec),
+            [
+                (2, TestKind::Spec, "parses a glob".to_string()),
+                (3, TestKind::Spec, "keeps text".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn enclosing_test_is_the_last_one_declared_before_the_line() {
+        let entries = scan_tests(b"#[test]\nfn a() {\n}\n\n#[test]\nfn b() {\n    x();\n}\n");
+        assert!(enclosing_test(&entries, 1).is_none());
+        assert_eq!(enclosing_test(&entries, 3).unwrap().text, "a");
+        assert_eq!(enclosing_test(&entries, 7).unwrap().text, "b");
+    }
+}
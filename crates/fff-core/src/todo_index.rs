@@ -0,0 +1,158 @@
+//! Index of the TODO / FIXME / HACK / XXX comments in the indexed files.
+//!
+//! Built once in the background after the initial scan and kept current by
+//! the watcher, so a TODO picker ranks a few hundred lines instead of
+//! grepping the whole tree on every keystroke.
+
+use std::sync::LazyLock;
+
+use aho_corasick::AhoCorasick;
+
+use crate::line_index::{LineCursor, LineEntry, LineIndex, LineMatch};
+
+const KEYWORDS: [(&str, TodoKind); 4] = [
+    ("TODO", TodoKind::Todo),
+    ("FIXME", TodoKind::Fixme),
+    ("HACK", TodoKind::Hack),
+    ("XXX", TodoKind::Xxx),
+];
+
+static KEYWORD_MATCHER: LazyLock<AhoCorasick> = LazyLock::new(|| {
+    AhoCorasick::new(KEYWORDS.map(|(keyword, _)| keyword)).expect("valid TODO keywords")
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TodoKind {
+    Todo,
+    Fixme,
+    Hack,
+    Xxx,
+}
+
+impl TodoKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Todo => "todo",
+            Self::Fixme => "fixme",
+            Self::Hack => "hack",
+            Self::Xxx => "xxx",
+        }
+    }
+}
+
+/// A TODO comment returned by [`FilePicker::search_todos`](crate::FilePicker::search_todos).
+pub type TodoMatch<'a> = LineMatch<'a, TodoKind>;
+
+pub(crate) type TodoIndex = LineIndex<TodoKind>;
+
+/// Finds the lines with a TODO keyword after a comment marker. Most files
+/// have no keyword at all and are rejected by a single Aho-Corasick pass.
+pub(crate) fn scan_todos(content: &[u8]) -> Vec<LineEntry<TodoKind>> {
+    let mut entries = Vec::new();
+    let mut cursor = LineCursor::new(content);
+    let mut next_line_start = 0;
+
+    for hit in KEYWORD_MATCHER.find_iter(content) {
+        // one entry per line
+        if hit.start() < next_line_start {
+            continue;
+        }
+
+        let line = cursor.line_range(hit.start());
+        let is_word = !content[..hit.start()].last().is_some_and(is_word_byte)
+            && !content.get(hit.end()).is_some_and(is_word_byte);
+        if !is_word || !is_comment(&content[line.start..hit.start()]) {
+            continue;
+        }
+
+        next_line_start = line.end + 1;
+        entries.extend(cursor.entry(line, KEYWORDS[hit.pattern().as_usize()].1));
+    }
+
+    entries
+}
+
+fn is_word_byte(b: &u8) -> bool {
+    b.is_ascii_alphanumeric() || *b == b'_'
+}
+
+/// Whether the text before a keyword opens a comment: `//`, `/*`, `#`, `--`
+/// or `<!--` anywhere, `*` and `;` only at the start of the line.
+fn is_comment(before: &[u8]) -> bool {
+    let trimmed = before.trim_ascii_start();
+    trimmed.starts_with(b"*")
+        || trimmed.starts_with(b";")
+        || before.contains(&b'#')
+        || before
+            .windows(2)
+            .any(|w| w == b"//" || w == b"/*" || w == b"--")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FileItem;
+    use std::path::{Path, PathBuf};
+
+    fn scanned(content: &str) -> Vec<(u64, TodoKind, String)> {
+        scan_todos(content.as_bytes())
+            .into_iter()
+            .map(|e| (e.line_number, e.kind, e.text))
+            .collect()
+    }
+
+    #[test]
+    fn finds_keywords_in_comments_only() {
+        let content = "\
+fn main() {
+    // TODO: handle errors
+    let todo = TODO_LIST; // not a TODOS marker
+    /* FIXME(dmtr) leaks */
+}
+# HACK works around a bug
+-- XXX lua comment XXX
+    * TODO in a doc block
+let XXXX = 1; // XXXX
+";
+        assert_eq!(
+            scanned(content),
+            vec![
+                (2, TodoKind::Todo, "// TODO: handle errors".to_string()),
+                (4, TodoKind::Fixme, "/* FIXME(dmtr) leaks */".to_string()),
+                (6, TodoKind::Hack, "# HACK works around a bug".to_string()),
+                (7, TodoKind::Xxx, "-- XXX lua comment XXX".to_string()),
+                (8, TodoKind::Todo, "* TODO in a doc block".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn ranks_by_query_and_updates_files() {
+        let mut index = TodoIndex::default();
+        let file = FileItem::new_raw(
+            PathBuf::from("/repo/src/lib.rs"),
+            "src/lib.rs".to_string(),
+            "lib.rs".to_string(),
+            0,
+            0,
+            None,
+            false,
+        );
+        index.update_file(
+            file.path(),
+            b"// TODO: cache the parser\n// FIXME: flaky test\n",
+            scan_todos,
+        );
+        assert_eq!(index.len(), 2);
+
+        let file_of = |_: &Path| Some(&file);
+        let found = index.search("flaky", 10, file_of, |_| true);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, TodoKind::Fixme);
+        assert_eq!(found[0].line_number, 2);
+        assert_eq!(index.search("", 10, file_of, |_| true).len(), 2);
+
+        index.update_file(file.path(), b"fn main() {}\n", scan_todos);
+        assert_eq!(index.len(), 0);
+    }
+}
@@ -0,0 +1,240 @@
+//! Moving deleted files to the operating system trash.
+//!
+//! Only compiled with real implementations when the `os-trash` feature is
+//! enabled. Linux and the BSDs follow the freedesktop.org trash specification
+//! (home trash only), macOS uses `~/.Trash`. Every other configuration reports
+//! [`std::io::ErrorKind::Unsupported`] so the caller can surface it.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Move `path` to the OS trash and return its location inside the trash.
+pub(crate) fn move_to_trash(path: &Path) -> io::Result<PathBuf> {
+    imp::move_to_trash(path)
+}
+
+/// Move a file previously trashed by [`move_to_trash`] back to `original`.
+pub(crate) fn restore_from_trash(trashed: &Path, original: &Path) -> io::Result<()> {
+    imp::restore_from_trash(trashed, original)
+}
+
+#[cfg(all(
+    feature = "os-trash",
+    unix,
+    not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+))]
+mod imp {
+    use crate::file_ops::move_file;
+    use std::fs::{self, OpenOptions};
+    use std::io::{self, Write};
+    use std::path::{Path, PathBuf};
+
+    pub(super) fn move_to_trash(path: &Path) -> io::Result<PathBuf> {
+        let data_dir = dirs::data_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no XDG data directory"))?;
+        trash_into(&data_dir.join("Trash"), path)
+    }
+
+    pub(super) fn restore_from_trash(trashed: &Path, original: &Path) -> io::Result<()> {
+        move_file(trashed, original)?;
+
+        // files/<name> has a matching info/<name>.trashinfo next to it.
+        if let (Some(files_dir), Some(name)) = (trashed.parent(), trashed.file_name())
+            && let Some(trash_root) = files_dir.parent()
+        {
+            let mut info_name = name.to_os_string();
+            info_name.push(".trashinfo");
+            let _ = fs::remove_file(trash_root.join("info").join(info_name));
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn trash_into(trash_root: &Path, path: &Path) -> io::Result<PathBuf> {
+        let files_dir = trash_root.join("files");
+        let info_dir = trash_root.join("info");
+        fs::create_dir_all(&files_dir)?;
+        fs::create_dir_all(&info_dir)?;
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?
+            .to_string_lossy()
+            .into_owned();
+
+        // The spec reserves a name by atomically creating its .trashinfo first.
+        for attempt in 0..1000u32 {
+            let name = match attempt {
+                0 => file_name.clone(),
+                n => format!("{file_name}.{n}"),
+            };
+
+            let info_path = info_dir.join(format!("{name}.trashinfo"));
+            let mut info = match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&info_path)
+            {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            };
+
+            let trashed = files_dir.join(&name);
+            let result = write!(
+                info,
+                "[Trash Info]\nPath={}\nDeletionDate={}\n",
+                percent_encode_path(path),
+                chrono::Local::now().format("%Y-%m-%dT%H:%M:%S"),
+            )
+            .and_then(|_| move_file(path, &trashed));
+
+            return match result {
+                Ok(()) => Ok(trashed),
+                Err(e) => {
+                    let _ = fs::remove_file(&info_path);
+                    Err(e)
+                }
+            };
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "too many files with the same name in the trash",
+        ))
+    }
+
+    /// Percent-encode everything except unreserved URI characters and `/`.
+    pub(super) fn percent_encode_path(path: &Path) -> String {
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut encoded = String::new();
+        for &byte in path.as_os_str().as_bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                    encoded.push(byte as char)
+                }
+                _ => encoded.push_str(&format!("%{byte:02X}")),
+            }
+        }
+        encoded
+    }
+}
+
+#[cfg(all(feature = "os-trash", target_os = "macos"))]
+mod imp {
+    use crate::file_ops::move_file;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    pub(super) fn move_to_trash(path: &Path) -> io::Result<PathBuf> {
+        let trash_dir = dirs::home_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?
+            .join(".Trash");
+        std::fs::create_dir_all(&trash_dir)?;
+
+        let stem = path
+            .file_stem()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?
+            .to_string_lossy();
+        let extension = path
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+
+        // Finder names duplicates "name 2.ext", "name 3.ext", ...
+        let trashed = (1..1000u32)
+            .map(|n| match n {
+                1 => trash_dir.join(format!("{stem}{extension}")),
+                n => trash_dir.join(format!("{stem} {n}{extension}")),
+            })
+            .find(|candidate| !candidate.exists())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    "too many files with the same name in the trash",
+                )
+            })?;
+
+        move_file(path, &trashed)?;
+        Ok(trashed)
+    }
+
+    pub(super) fn restore_from_trash(trashed: &Path, original: &Path) -> io::Result<()> {
+        move_file(trashed, original)
+    }
+}
+
+#[cfg(not(all(
+    feature = "os-trash",
+    any(
+        target_os = "macos",
+        all(unix, not(any(target_os = "ios", target_os = "android")))
+    )
+)))]
+mod imp {
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            if cfg!(feature = "os-trash") {
+                "OS trash is not supported on this platform"
+            } else {
+                "built without the `os-trash` feature"
+            },
+        )
+    }
+
+    pub(super) fn move_to_trash(_: &Path) -> io::Result<PathBuf> {
+        Err(unsupported())
+    }
+
+    pub(super) fn restore_from_trash(_: &Path, _: &Path) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "os-trash",
+    unix,
+    not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+))]
+mod tests {
+    use super::imp::{percent_encode_path, restore_from_trash, trash_into};
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn percent_encodes_reserved_bytes() {
+        assert_eq!(
+            percent_encode_path(Path::new("/tmp/a b/ü#1.txt")),
+            "/tmp/a%20b/%C3%BC%231.txt"
+        );
+    }
+
+    #[test]
+    fn trashes_and_restores_with_info_file() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let trash_root = tmp.path().join("Trash");
+        let file = tmp.path().join("notes.txt");
+
+        fs::write(&file, "first").unwrap();
+        let first = trash_into(&trash_root, &file).unwrap();
+        fs::write(&file, "second").unwrap();
+        let second = trash_into(&trash_root, &file).unwrap();
+
+        assert_eq!(first, trash_root.join("files/notes.txt"));
+        assert_eq!(second, trash_root.join("files/notes.txt.1"));
+        let info = fs::read_to_string(trash_root.join("info/notes.txt.1.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath=/"));
+        assert!(info.contains("DeletionDate="));
+
+        restore_from_trash(&second, &file).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "second");
+        assert!(!trash_root.join("info/notes.txt.1.trashinfo").exists());
+        assert!(trash_root.join("info/notes.txt.trashinfo").exists());
+    }
+}
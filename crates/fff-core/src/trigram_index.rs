@@ -0,0 +1,546 @@
+//! Persisted trigram index of the file contents.
+//!
+//! The bigram filter is rebuilt on every start and saturates on huge repos:
+//! almost every file contains almost every bigram. Trigrams are selective
+//! enough to narrow a plain text grep to a handful of files, but are too
+//! expensive to compute on every start, so the index is written next to the
+//! frecency database and reloaded. Files changed since they were indexed are
+//! recognised by their size and mtime and always searched, files the watcher
+//! reports are re-indexed into an in-memory overlay.
+
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use ahash::AHashMap;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::error::Error;
+use crate::file_list::FileList;
+use crate::types::{ContentCacheBudget, FileItem};
+
+/// Bumped whenever the layout or the trigram extraction changes.
+const FORMAT_VERSION: u32 = 1;
+
+/// Files read per parallel batch while building, bounds the memory held by
+/// the not yet merged trigram sets.
+const BUILD_BATCH: usize = 1024;
+
+/// A loaded index is rebuilt when fewer of the current files are up to date.
+const MIN_FRESH_RATIO: f64 = 0.9;
+
+/// Changed files whose trigrams are kept in the overlay. Changes beyond it
+/// are only recorded, those files are searched until the next rebuild.
+const MAX_OVERLAY_FILES: usize = 10_000;
+
+/// Larger changed files are searched instead of re-indexed.
+const MAX_OVERLAY_FILE_SIZE: usize = 4 * 1024 * 1024;
+
+/// Content sniffed for NUL bytes before re-indexing a changed file.
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexedFile {
+    path: PathBuf,
+    size: u64,
+    modified: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TrigramIndex {
+    version: u32,
+    /// Sorted by path, posting lists refer to positions in this list.
+    files: Vec<IndexedFile>,
+    /// Sorted trigram keys, `postings[i]` belongs to `keys[i]`.
+    keys: Vec<u32>,
+    /// Delta encoded varint file ids.
+    postings: Vec<Vec<u8>>,
+    /// Trigrams of the files the watcher reported since the index was built.
+    /// Their mtime has a one second resolution, so an edit does not always
+    /// show in the stamps.
+    #[serde(skip)]
+    overlay: parking_lot::RwLock<AHashMap<PathBuf, ChangedFile>>,
+    /// Set when the file list was replaced by a rescan, the index is ignored
+    /// until it is rebuilt.
+    #[serde(skip)]
+    invalidated: AtomicBool,
+}
+
+#[derive(Debug)]
+enum ChangedFile {
+    /// Sorted unique trigrams of the current content.
+    Indexed(Vec<u32>),
+    /// Too large, binary, unreadable or over the overlay limit.
+    Unindexed,
+}
+
+/// State of the trigram index, see [`FilePicker::trigram_index_health`](crate::FilePicker::trigram_index_health).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrigramIndexHealth {
+    pub indexed_files: usize,
+    /// Files re-indexed from watcher events since the index was built.
+    pub changed_files: usize,
+    /// Changed files that could not be re-indexed and are always searched.
+    pub unindexed_changes: usize,
+    /// Share of the searchable files the index can rule out, the others are
+    /// always searched.
+    pub coverage: f64,
+    /// The file list was rescanned and the index is not rebuilt yet.
+    pub invalidated: bool,
+}
+
+impl TrigramIndex {
+    /// Index the content of every readable text file.
+    pub fn build(files: &FileList, budget: &ContentCacheBudget) -> Self {
+        let mut sorted: Vec<&FileItem> = files
+            .iter()
+            .filter(|f| !f.is_deleted && !f.is_binary && f.size > 0)
+            .collect();
+        sorted.sort_unstable_by(|a, b| a.path().as_os_str().cmp(b.path().as_os_str()));
+
+        let mut indexed = Vec::with_capacity(sorted.len());
+        let mut postings: AHashMap<u32, PostingList> = AHashMap::new();
+        for batch in sorted.chunks(BUILD_BATCH) {
+            let trigrams: Vec<Option<Vec<u32>>> = batch
+                .par_iter()
+                .map(|file| {
+                    let content = file.get_content_for_search(budget)?;
+                    Some(extract_trigrams(&content))
+                })
+                .collect();
+
+            // Unreadable files stay out of the index and are always searched.
+            for (file, trigrams) in batch.iter().zip(trigrams) {
+                let Some(trigrams) = trigrams else { continue };
+                let id = indexed.len() as u32;
+                indexed.push(IndexedFile {
+                    path: file.path().to_path_buf(),
+                    size: file.size,
+                    modified: file.modified,
+                });
+                for key in trigrams {
+                    postings.entry(key).or_default().push(id);
+                }
+            }
+        }
+
+        let mut postings: Vec<(u32, PostingList)> = postings.into_iter().collect();
+        postings.sort_unstable_by_key(|(key, _)| *key);
+        let (keys, postings) = postings
+            .into_iter()
+            .map(|(key, list)| (key, list.bytes))
+            .unzip();
+
+        Self {
+            version: FORMAT_VERSION,
+            files: indexed,
+            keys,
+            postings,
+            overlay: Default::default(),
+            invalidated: AtomicBool::new(false),
+        }
+    }
+
+    /// Path of the index of `base_path` inside `dir`, one file per project.
+    pub fn path_for(dir: &Path, base_path: &Path) -> PathBuf {
+        let hash = xxh3_64(base_path.as_os_str().as_encoded_bytes());
+        dir.join(format!("trigrams-{hash:016x}.bin"))
+    }
+
+    /// Number of indexed files.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Read an index written by [`Self::save`]. `None` when the file is
+    /// missing, unreadable or written by another version.
+    pub fn load(path: &Path) -> Option<Self> {
+        let file = File::open(path).ok()?;
+        let index: Self = bincode::deserialize_from(BufReader::new(file))
+            .inspect_err(|e| warn!("Ignoring unreadable trigram index {}: {e}", path.display()))
+            .ok()?;
+        (index.version == FORMAT_VERSION
+            && index.keys.len() == index.postings.len()
+            && index
+                .files
+                .is_sorted_by(|a, b| a.path.as_os_str() < b.path.as_os_str()))
+        .then_some(index)
+    }
+
+    /// Write the index atomically, a crash never leaves a truncated file.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        bincode::serialize_into(&mut writer, self)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Share of the searchable `files` whose content is indexed as is.
+    fn fresh_ratio(&self, files: &FileList) -> f64 {
+        let overlay = self.overlay.read();
+        let mut searchable = 0usize;
+        let mut fresh = 0usize;
+        for file in files.iter().filter(|f| !f.is_deleted && !f.is_binary) {
+            searchable += 1;
+            let is_fresh = match overlay.get(file.path()) {
+                Some(changed) => matches!(changed, ChangedFile::Indexed(_)),
+                None => self
+                    .position(file.path())
+                    .is_some_and(|id| self.is_fresh(id, file)),
+            };
+            if is_fresh {
+                fresh += 1;
+            }
+        }
+        if searchable == 0 {
+            return 1.0;
+        }
+        fresh as f64 / searchable as f64
+    }
+
+    fn position(&self, path: &Path) -> Option<usize> {
+        self.files
+            .binary_search_by(|f| f.path.as_os_str().cmp(path.as_os_str()))
+            .ok()
+    }
+
+    fn is_fresh(&self, id: usize, file: &FileItem) -> bool {
+        let indexed = &self.files[id];
+        indexed.size == file.size && indexed.modified == file.modified
+    }
+
+    /// Re-index a file the watcher saw created or modified. `None` content
+    /// means it could not be read, it is searched on every grep then.
+    pub fn update_file(&self, path: &Path, content: Option<&[u8]>) {
+        let mut overlay = self.overlay.write();
+        let has_room = overlay.len() < MAX_OVERLAY_FILES || overlay.contains_key(path);
+        let changed = match content {
+            Some(content)
+                if has_room
+                    && content.len() <= MAX_OVERLAY_FILE_SIZE
+                    && memchr::memchr(0, &content[..content.len().min(BINARY_SNIFF_LEN)])
+                        .is_none() =>
+            {
+                ChangedFile::Indexed(extract_trigrams(content))
+            }
+            _ => ChangedFile::Unindexed,
+        };
+        overlay.insert(path.to_path_buf(), changed);
+    }
+
+    /// Forget the changes of a deleted file. Deleted files are never
+    /// searched, and a re-created one is reported again.
+    pub fn remove_file(&self, path: &Path) {
+        self.overlay.write().remove(path);
+    }
+
+    pub fn remove_dir(&self, dir: &Path) {
+        self.overlay
+            .write()
+            .retain(|path, _| !path.starts_with(dir));
+    }
+
+    /// Stop using the index, after a bulk change replaced the file list. A
+    /// rebuilt index takes its place.
+    pub fn invalidate(&self) {
+        self.invalidated.store(true, Ordering::Release);
+    }
+
+    pub fn is_invalidated(&self) -> bool {
+        self.invalidated.load(Ordering::Acquire)
+    }
+
+    pub fn health(&self, files: &FileList) -> TrigramIndexHealth {
+        let (changed_files, unindexed_changes) = {
+            let overlay = self.overlay.read();
+            let unindexed = overlay
+                .values()
+                .filter(|c| matches!(c, ChangedFile::Unindexed))
+                .count();
+            (overlay.len(), unindexed)
+        };
+        TrigramIndexHealth {
+            indexed_files: self.files.len(),
+            changed_files,
+            unindexed_changes,
+            coverage: self.fresh_ratio(files),
+            invalidated: self.is_invalidated(),
+        }
+    }
+
+    /// Bitset over `files` of those that may contain `pattern`, compared
+    /// case-insensitively. Files the index does not know, or that changed
+    /// since they were indexed and were not re-indexed, are always
+    /// candidates. `None` when the pattern has no trigram to look up or the
+    /// index is invalidated.
+    pub fn candidates(&self, pattern: &[u8], files: &FileList) -> Option<Vec<u64>> {
+        let mut keys = pattern_trigrams(pattern);
+        if keys.is_empty() || self.is_invalidated() {
+            return None;
+        }
+        keys.sort_unstable();
+        keys.dedup();
+
+        // Ids of the indexed files containing every trigram of the pattern.
+        let mut matching = vec![u64::MAX; self.files.len().div_ceil(64)];
+        for &key in &keys {
+            let mut ids = vec![0u64; matching.len()];
+            if let Ok(pos) = self.keys.binary_search(&key) {
+                for id in decode_postings(&self.postings[pos]) {
+                    if let Some(word) = ids.get_mut(id as usize / 64) {
+                        *word |= 1 << (id % 64);
+                    }
+                }
+            }
+            for (m, i) in matching.iter_mut().zip(&ids) {
+                *m &= i;
+            }
+        }
+
+        // The base files are sorted by path bytes like the index, so they are
+        // resolved by a merge. Unsorted overflow files fall back to a binary
+        // search.
+        let overlay = self.overlay.read();
+        let mut candidates = vec![0u64; files.len().div_ceil(64)];
+        let mut cursor = 0;
+        let mut previous: Option<&OsStr> = None;
+        for (file_idx, file) in files.iter().enumerate() {
+            let path = file.path().as_os_str();
+            let id = if previous.is_some_and(|p| path < p) {
+                self.position(file.path())
+            } else {
+                while cursor < self.files.len() && self.files[cursor].path.as_os_str() < path {
+                    cursor += 1;
+                }
+                (cursor < self.files.len() && self.files[cursor].path.as_os_str() == path)
+                    .then_some(cursor)
+            };
+            previous = Some(path);
+
+            let changed = (!overlay.is_empty())
+                .then(|| overlay.get(file.path()))
+                .flatten();
+            let is_candidate = match (changed, id) {
+                (Some(ChangedFile::Indexed(trigrams)), _) => {
+                    keys.iter().all(|key| trigrams.binary_search(key).is_ok())
+                }
+                (Some(ChangedFile::Unindexed), _) => true,
+                (None, Some(id)) if self.is_fresh(id, file) => {
+                    matching[id / 64] & (1 << (id % 64)) != 0
+                }
+                (None, _) => true,
+            };
+            if is_candidate {
+                candidates[file_idx / 64] |= 1 << (file_idx % 64);
+            }
+        }
+        Some(candidates)
+    }
+}
+
+/// Reuse the index saved in `dir` when it is still mostly up to date,
+/// otherwise build and save a new one.
+pub(crate) fn load_or_build(
+    dir: &Path,
+    base_path: &Path,
+    files: &FileList,
+    budget: &ContentCacheBudget,
+) -> TrigramIndex {
+    let path = TrigramIndex::path_for(dir, base_path);
+    if let Some(index) = TrigramIndex::load(&path) {
+        let fresh_ratio = index.fresh_ratio(files);
+        if fresh_ratio >= MIN_FRESH_RATIO {
+            info!(
+                files = index.len(),
+                "Loaded trigram index from {} ({:.0}% fresh)",
+                path.display(),
+                fresh_ratio * 100.0,
+            );
+            return index;
+        }
+    }
+
+    let index = TrigramIndex::build(files, budget);
+    if let Err(e) = index.save(&path) {
+        warn!("Failed to save trigram index to {}: {e}", path.display());
+    }
+    index
+}
+
+#[derive(Default)]
+struct PostingList {
+    bytes: Vec<u8>,
+    last: Option<u32>,
+}
+
+impl PostingList {
+    /// Ids must be pushed in increasing order.
+    fn push(&mut self, id: u32) {
+        let mut delta = id - self.last.map_or(0, |last| last + 1);
+        self.last = Some(id);
+        while delta >= 0x80 {
+            self.bytes.push((delta as u8) | 0x80);
+            delta >>= 7;
+        }
+        self.bytes.push(delta as u8);
+    }
+}
+
+fn decode_postings(bytes: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    let mut pos = 0;
+    let mut next_id = 0u32;
+    std::iter::from_fn(move || {
+        let mut delta = 0u32;
+        let mut shift = 0;
+        loop {
+            let byte = *bytes.get(pos)?;
+            pos += 1;
+            delta |= u32::from(byte & 0x7f) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        let id = next_id + delta;
+        next_id = id + 1;
+        Some(id)
+    })
+}
+
+/// Lowercased trigrams of printable ASCII, the same alphabet as the bigram
+/// filter. Trigrams spanning other bytes are never looked up.
+fn trigrams(content: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    content
+        .windows(3)
+        .filter(|w| w.iter().all(|b| (b' '..=b'~').contains(b)))
+        .map(|w| {
+            u32::from(w[0].to_ascii_lowercase()) << 16
+                | u32::from(w[1].to_ascii_lowercase()) << 8
+                | u32::from(w[2].to_ascii_lowercase())
+        })
+}
+
+fn extract_trigrams(content: &[u8]) -> Vec<u32> {
+    let mut unique: Vec<u32> = trigrams(content).collect();
+    unique.sort_unstable();
+    unique.dedup();
+    unique
+}
+
+fn pattern_trigrams(pattern: &[u8]) -> Vec<u32> {
+    trigrams(pattern).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write(tmp: &TempDir, name: &str, contents: &str) -> FileItem {
+        let path = tmp.path().join(name);
+        fs::write(&path, contents).unwrap();
+        FileItem::new(path, tmp.path(), None)
+    }
+
+    fn candidate_names(index: &TrigramIndex, pattern: &str, files: &FileList) -> Vec<String> {
+        let candidates = index.candidates(pattern.as_bytes(), files).unwrap();
+        files
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| candidates[i / 64] & (1 << (i % 64)) != 0)
+            .map(|(_, f)| f.relative_path().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn postings_roundtrip() {
+        let mut list = PostingList::default();
+        let ids = [0, 1, 127, 128, 300, 70_000];
+        for id in ids {
+            list.push(id);
+        }
+        assert_eq!(decode_postings(&list.bytes).collect::<Vec<_>>(), ids);
+    }
+
+    #[test]
+    fn narrows_candidates_and_keeps_changed_files() {
+        let tmp = TempDir::new().unwrap();
+        let mut files = FileList::from(vec![
+            write(&tmp, "a.rs", "fn ParseConfig() {}"),
+            write(&tmp, "b.rs", "fn load() {}"),
+            write(&tmp, "c.rs", "let parse = 1;"),
+        ]);
+        let index = TrigramIndex::build(&files, &ContentCacheBudget::default());
+        assert_eq!(index.len(), 3);
+
+        assert_eq!(candidate_names(&index, "parseconfig", &files), vec!["a.rs"]);
+        assert_eq!(
+            candidate_names(&index, "parse", &files),
+            vec!["a.rs", "c.rs"]
+        );
+        assert!(candidate_names(&index, "missing", &files).is_empty());
+        assert!(index.candidates(b"ab", &files).is_none());
+
+        // an edit the index has not seen makes the file a candidate again
+        files[1].modified += 1;
+        files.push(write(&tmp, "d.rs", "unrelated"));
+        assert_eq!(
+            candidate_names(&index, "missing", &files),
+            vec!["b.rs", "d.rs"]
+        );
+
+        // the watcher re-indexes changed and new files
+        index.update_file(files[1].path(), Some(b"fn missing() {}"));
+        index.update_file(files[3].path(), Some(b"unrelated"));
+        assert_eq!(candidate_names(&index, "missing", &files), vec!["b.rs"]);
+
+        index.update_file(files[2].path(), None);
+        assert_eq!(
+            candidate_names(&index, "missing", &files),
+            vec!["b.rs", "c.rs"]
+        );
+
+        let health = index.health(&files);
+        assert_eq!(health.changed_files, 3);
+        assert_eq!(health.unindexed_changes, 1);
+        assert_eq!(health.coverage, 0.75);
+
+        index.invalidate();
+        assert!(index.candidates(b"missing", &files).is_none());
+    }
+
+    #[test]
+    fn saved_index_is_reused_while_fresh() {
+        let tmp = TempDir::new().unwrap();
+        let files = FileList::from(vec![write(&tmp, "a.rs", "fn needle() {}")]);
+        let dir = tmp.path().join("cache");
+        let budget = ContentCacheBudget::default();
+
+        let built = load_or_build(&dir, tmp.path(), &files, &budget);
+        let path = TrigramIndex::path_for(&dir, tmp.path());
+        assert!(path.exists());
+
+        let loaded = TrigramIndex::load(&path).unwrap();
+        assert_eq!(loaded.len(), built.len());
+        assert_eq!(candidate_names(&loaded, "needle", &files), vec!["a.rs"]);
+    }
+}
@@ -1,9 +1,13 @@
 use std::path::{Path, PathBuf};
-use std::sync::OnceLock;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 
 use crate::constraints::Constrainable;
-use crate::query_tracker::QueryMatchEntry;
+use crate::project_profile::ProjectProfile;
+use crate::query_tracker::{CoOpenEntry, QueryMatchEntry};
+use crate::search_cursor::SearchCursor;
+use crate::search_scratch::SearchScratch;
+use crate::working_set::WorkingSetCache;
 use fff_query_parser::{FFFQuery, FuzzyQuery, Location};
 
 /// Cached file contents — mmap on Unix, heap buffer on Windows.
@@ -41,16 +45,23 @@ impl std::ops::Deref for FileContent {
 ///
 /// Thread-safety: `OnceLock` provides lock-free reads after initialization.
 /// Each file is only searched by one rayon worker at a time via `par_iter`.
-#[derive(Debug)]
+///
+/// Clones share the content cache, so copying a chunk of the file list for a
+/// new index snapshot keeps the already loaded contents.
+///
+/// The relative path and file name are suffixes of the absolute path, so for
+/// UTF-8 paths they are kept as offsets into it rather than as separate strings.
+#[derive(Debug, Clone)]
 pub struct FileItem {
-    pub path: PathBuf,
-    pub relative_path: String,
-    pub file_name: String,
+    path: PathBuf,
+    names: PathNames,
     pub size: u64,
     pub modified: u64,
     pub access_frecency_score: i32,
     pub modification_frecency_score: i32,
     pub total_frecency_score: i32,
+    /// Added to the score as is, see [`crate::FilePicker::set_external_scores`].
+    pub external_score: i32,
     pub git_status: Option<git2::Status>,
     pub is_binary: bool,
     /// Tombstone flag — file was deleted but index slot is preserved so
@@ -58,26 +69,39 @@ pub struct FileItem {
     pub is_deleted: bool,
     /// Lazily-initialized file contents for grep.
     /// Initialized on first grep access via `OnceLock`; lock-free on subsequent reads.
-    content: OnceLock<FileContent>,
+    content: Arc<OnceLock<FileContent>>,
 }
 
-impl Clone for FileItem {
-    fn clone(&self) -> Self {
-        Self {
-            path: self.path.clone(),
-            relative_path: self.relative_path.clone(),
-            file_name: self.file_name.clone(),
-            size: self.size,
-            modified: self.modified,
-            access_frecency_score: self.access_frecency_score,
-            modification_frecency_score: self.modification_frecency_score,
-            total_frecency_score: self.total_frecency_score,
-            git_status: self.git_status,
-            is_binary: self.is_binary,
-            is_deleted: self.is_deleted,
-            // Don't clone the content — the clone lazily re-creates it on demand
-            content: OnceLock::new(),
+/// Location of the relative path and file name of a [`FileItem`].
+#[derive(Debug, Clone)]
+enum PathNames {
+    /// Byte offsets into the item's path, which is valid UTF-8.
+    Suffix {
+        relative_start: u32,
+        file_name_start: u32,
+    },
+    /// Owned copies for non UTF-8 paths (converted lossily) and for names
+    /// that are not suffixes of the path.
+    Owned(Box<(Box<str>, Box<str>)>),
+}
+
+impl PathNames {
+    fn new(path: &Path, relative_path: String, file_name: String) -> Self {
+        if let Some(path_str) = path.to_str()
+            && path_str.ends_with(relative_path.as_str())
+            && relative_path.ends_with(file_name.as_str())
+            && let Ok(file_name_start) = u32::try_from(path_str.len() - file_name.len())
+        {
+            return Self::Suffix {
+                relative_start: (path_str.len() - relative_path.len()) as u32,
+                file_name_start,
+            };
         }
+
+        Self::Owned(Box::new((
+            relative_path.into_boxed_str(),
+            file_name.into_boxed_str(),
+        )))
     }
 }
 
@@ -119,21 +143,53 @@ impl FileItem {
         is_binary: bool,
     ) -> Self {
         Self {
+            names: PathNames::new(&path, relative_path, file_name),
             path,
-            relative_path,
-            file_name,
             size,
             modified,
             access_frecency_score: 0,
             modification_frecency_score: 0,
             total_frecency_score: 0,
+            external_score: 0,
             git_status,
             is_binary,
             is_deleted: false,
-            content: OnceLock::new(),
+            content: Arc::default(),
         }
     }
 
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path relative to the indexed base directory.
+    #[inline]
+    pub fn relative_path(&self) -> &str {
+        match &self.names {
+            PathNames::Suffix { relative_start, .. } => self.path_suffix(*relative_start),
+            PathNames::Owned(names) => &names.0,
+        }
+    }
+
+    #[inline]
+    pub fn file_name(&self) -> &str {
+        match &self.names {
+            PathNames::Suffix {
+                file_name_start, ..
+            } => self.path_suffix(*file_name_start),
+            PathNames::Owned(names) => &names.1,
+        }
+    }
+
+    #[inline]
+    fn path_suffix(&self, start: u32) -> &str {
+        let bytes = &self.path.as_os_str().as_encoded_bytes()[start as usize..];
+        // SAFETY: `Suffix` offsets are only created for paths that are valid
+        // UTF-8 and point at a char boundary, and `path` is never modified.
+        unsafe { std::str::from_utf8_unchecked(bytes) }
+    }
+
     /// Invalidate the cached content so the next `get_content()` call creates a fresh one.
     ///
     /// Call this when the background watcher detects that the file has been modified.
@@ -146,7 +202,18 @@ impl FileItem {
             budget.cached_bytes.fetch_sub(self.size, Ordering::Relaxed);
         }
 
-        self.content = OnceLock::new();
+        self.content = Arc::default();
+    }
+
+    pub(crate) fn has_content(&self) -> bool {
+        self.content.get().is_some()
+    }
+
+    /// Contents that don't come from the disk, e.g. from a
+    /// [`crate::virtual_files::VirtualFileProvider`]. They are not counted
+    /// against the [`ContentCacheBudget`].
+    pub(crate) fn set_content(&self, content: Vec<u8>) {
+        let _ = self.content.set(FileContent::Buffer(content));
     }
 
     /// Get the cached file contents or lazily load and cache them.
@@ -224,7 +291,7 @@ const MMAP_THRESHOLD: u64 = 4 * 1024;
 /// Load file contents: small files are read into a heap buffer to avoid
 /// mmap page alignment waste; large files use mmap for zero-copy access.
 /// On Windows, always uses heap buffer (mmap holds the file handle open).
-fn load_file_content(path: &Path, size: u64) -> Option<FileContent> {
+pub(crate) fn load_file_content(path: &Path, size: u64) -> Option<FileContent> {
     #[cfg(not(target_os = "windows"))]
     {
         if size < MMAP_THRESHOLD {
@@ -251,12 +318,12 @@ fn load_file_content(path: &Path, size: u64) -> Option<FileContent> {
 impl Constrainable for FileItem {
     #[inline]
     fn relative_path(&self) -> &str {
-        &self.relative_path
+        FileItem::relative_path(self)
     }
 
     #[inline]
     fn file_name(&self) -> &str {
-        &self.file_name
+        FileItem::file_name(self)
     }
 
     #[inline]
@@ -276,6 +343,15 @@ pub struct Score {
     pub distance_penalty: i32,
     pub current_file_penalty: i32,
     pub combo_match_boost: i32,
+    /// Adjustment from [`ExtensionMultipliers`], negative when deprioritized.
+    pub extension_boost: i32,
+    /// Adjustment from the detected [`ProjectProfile`] directories.
+    pub project_boost: i32,
+    /// Boost of a file often opened together with the current one, see
+    /// [`ScoringContext::co_opened`].
+    pub affinity_boost: i32,
+    /// [`FileItem::external_score`] of the file.
+    pub external_boost: i32,
     pub exact_match: bool,
     pub match_type: &'static str,
 }
@@ -295,6 +371,77 @@ impl Default for PaginationArgs {
     }
 }
 
+/// Matcher bonuses for query characters that land on the start of a word.
+///
+/// These are what make acronym queries work: `fsw` should prefer
+/// `file_system_watcher.rs`, where every character starts a word, over a path
+/// that merely contains the letters in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScoringWeights {
+    /// Matching the first character of the path.
+    pub prefix_bonus: u16,
+    /// Matching right after a `_`, `-`, `.`, `/` or space.
+    pub boundary_bonus: u16,
+    /// Matching an uppercase letter that follows a lowercase one (the `W` in
+    /// `FileWatcher`). Applied regardless of the query's case.
+    pub camel_case_bonus: u16,
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        Self {
+            prefix_bonus: 12,
+            boundary_bonus: 10,
+            camel_case_bonus: 8,
+        }
+    }
+}
+
+/// Per-extension score multipliers in percent: `50` halves the score of a
+/// matching file, `150` raises it by half.
+///
+/// Keys are file name suffixes without the leading dot and may span several
+/// dots (`min.js`), in which case the longest one wins: with both `min.js` and
+/// `js` configured, `app.min.js` uses the `min.js` multiplier.
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionMultipliers {
+    by_suffix: ahash::AHashMap<String, i32>,
+}
+
+impl ExtensionMultipliers {
+    pub fn new<S: AsRef<str>>(entries: impl IntoIterator<Item = (S, i32)>) -> Self {
+        let by_suffix = entries
+            .into_iter()
+            .map(|(suffix, percent)| {
+                let suffix = suffix.as_ref().trim_start_matches('.').to_lowercase();
+                (suffix, percent.max(0))
+            })
+            .collect();
+        Self { by_suffix }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_suffix.is_empty()
+    }
+
+    /// Multiplier for `file_name`, or `None` when no suffix is configured.
+    pub fn multiplier_for(&self, file_name: &str) -> Option<i32> {
+        if self.by_suffix.is_empty() {
+            return None;
+        }
+
+        let lowercase = file_name.to_lowercase();
+        let mut rest = lowercase.as_str();
+        while let Some(dot) = rest.find('.') {
+            rest = &rest[dot + 1..];
+            if let Some(&percent) = self.by_suffix.get(rest) {
+                return Some(percent);
+            }
+        }
+        None
+    }
+}
+
 /// Context for scoring files during search.
 ///
 /// The `query` field contains the pre-parsed query with constraints,
@@ -308,10 +455,36 @@ pub struct ScoringContext<'a> {
     pub current_file: Option<&'a str>,
     pub max_typos: u16,
     pub max_threads: usize,
-    pub last_same_query_match: Option<QueryMatchEntry>,
+    /// Files opened for this query before, see
+    /// [`crate::query_tracker::QueryTracker::get_query_candidates`].
+    pub same_query_matches: Vec<QueryMatchEntry>,
+    /// Files opened together with the current file, only looked up for the
+    /// empty and short queries, see
+    /// [`crate::query_tracker::QueryTracker::get_co_opened`].
+    pub co_opened: Vec<CoOpenEntry>,
     pub combo_boost_score_multiplier: i32,
     pub min_combo_count: u32,
     pub pagination: PaginationArgs,
+    /// See [`crate::FuzzySearchOptions::adaptive_typos_threshold`].
+    pub adaptive_typos_threshold: usize,
+    pub scoring_weights: ScoringWeights,
+    pub extension_multipliers: Option<&'a ExtensionMultipliers>,
+    pub project_profile: Option<&'a ProjectProfile>,
+    /// Relative path prefix (ending with a separator) results are limited to.
+    pub search_root: Option<&'a str>,
+    /// See [`crate::FuzzySearchOptions::facets`].
+    pub facets: bool,
+    /// See [`crate::FuzzySearchOptions::min_score`].
+    pub min_score: Option<i32>,
+    /// See [`crate::FuzzySearchOptions::min_score_ratio`].
+    pub min_score_ratio: f32,
+    /// Keep the matches past the page, see
+    /// [`crate::FuzzySearchOptions::with_cursor`].
+    pub cursor: bool,
+    /// See [`crate::FuzzySearchOptions::working_set`].
+    pub working_set: Option<(&'a WorkingSetCache, u64)>,
+    /// See [`crate::FuzzySearchOptions::scratch`].
+    pub scratch: Option<&'a SearchScratch>,
 }
 
 impl ScoringContext<'_> {
@@ -326,6 +499,83 @@ impl ScoringContext<'_> {
     }
 }
 
+/// Aggregate view of how the matches of a fuzzy search were found, so a UI
+/// can tell the user when it is only showing typo-tolerant guesses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchStats {
+    /// Matches containing every query character in order.
+    pub exact_matched: usize,
+    /// Matches that needed typo tolerance to be found.
+    pub typo_matched: usize,
+    /// Typo budget the matcher ran with, `0` when no fuzzy matching happened.
+    pub max_typos: u16,
+}
+
+/// Extensions and top-level directories among all matches of a search, with
+/// the number of matches of each, for filter chips like `ts 420 · rs 37`.
+/// Only computed when [`crate::FuzzySearchOptions::facets`] is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchFacets {
+    /// Lowercase extensions without the dot, `*.{value}` as a constraint.
+    pub extensions: Vec<FacetCount>,
+    /// First directory of the relative path, `{value}/` as a constraint.
+    /// Files in the root directory are not counted.
+    pub directories: Vec<FacetCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: usize,
+}
+
+/// Values kept per facet, the rest are too small to be worth a chip.
+const MAX_FACET_VALUES: usize = 20;
+
+impl SearchFacets {
+    pub(crate) fn collect<'a>(files: impl Iterator<Item = &'a FileItem>) -> Self {
+        let mut extensions: ahash::AHashMap<String, usize> = Default::default();
+        let mut directories: ahash::AHashMap<&str, usize> = Default::default();
+
+        for file in files {
+            if let Some((_, ext)) = file.file_name().rsplit_once('.')
+                && !ext.is_empty()
+            {
+                // `get_mut` first to only allocate for new extensions
+                match extensions.get_mut(ext) {
+                    Some(count) => *count += 1,
+                    None => *extensions.entry(ext.to_ascii_lowercase()).or_default() += 1,
+                }
+            }
+            if let Some((dir, _)) = file.relative_path().split_once(['/', '\\']) {
+                *directories.entry(dir).or_default() += 1;
+            }
+        }
+
+        Self {
+            extensions: top_facet_values(extensions),
+            directories: top_facet_values(directories),
+        }
+    }
+}
+
+fn top_facet_values<K: AsRef<str>>(counts: ahash::AHashMap<K, usize>) -> Vec<FacetCount> {
+    let mut counts: Vec<(K, usize)> = counts.into_iter().collect();
+    counts.sort_unstable_by(|(a, a_count), (b, b_count)| {
+        b_count
+            .cmp(a_count)
+            .then_with(|| a.as_ref().cmp(b.as_ref()))
+    });
+    counts
+        .into_iter()
+        .take(MAX_FACET_VALUES)
+        .map(|(value, count)| FacetCount {
+            value: value.as_ref().to_string(),
+            count,
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SearchResult<'a> {
     pub items: Vec<&'a FileItem>,
@@ -333,6 +583,18 @@ pub struct SearchResult<'a> {
     pub total_matched: usize,
     pub total_files: usize,
     pub location: Option<Location>,
+    pub match_stats: MatchStats,
+    /// "Did you mean" query for searches that matched nothing, see
+    /// [`crate::FilePicker::suggest_query`].
+    pub suggestion: Option<String>,
+    /// Match counts per extension and directory, see [`SearchFacets`].
+    pub facets: Option<SearchFacets>,
+    /// Continues the search past this page, see [`SearchCursor`].
+    pub cursor: Option<SearchCursor>,
+    /// Generation of the index the results come from, see
+    /// [`crate::FilePicker::index_generation`]. A UI showing these results
+    /// can search again once the picker reports another generation.
+    pub index_generation: u64,
 }
 
 const MAX_MMAP_FILE_SIZE: u64 = 10 * 1024 * 1024;
@@ -422,3 +684,37 @@ impl Default for ContentCacheBudget {
         Self::new_for_repo(30_000)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_read_from_the_path_or_owned_copies() {
+        let item = FileItem::new_raw(
+            PathBuf::from("/repo/src/main.rs"),
+            "src/main.rs".to_string(),
+            "main.rs".to_string(),
+            0,
+            0,
+            None,
+            false,
+        );
+        assert!(matches!(item.names, PathNames::Suffix { .. }));
+        assert_eq!(item.relative_path(), "src/main.rs");
+        assert_eq!(item.file_name(), "main.rs");
+
+        let item = FileItem::new_raw(
+            PathBuf::from("/repo/src/main.rs"),
+            "renamed.rs".to_string(),
+            "renamed.rs".to_string(),
+            0,
+            0,
+            None,
+            false,
+        );
+        assert!(matches!(item.names, PathNames::Owned(_)));
+        assert_eq!(item.relative_path(), "renamed.rs");
+        assert_eq!(item.file_name(), "renamed.rs");
+    }
+}
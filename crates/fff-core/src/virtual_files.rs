@@ -0,0 +1,279 @@
+//! Files that don't exist on the local disk, like `scp://` buffers or paths
+//! inside a container, registered by the embedding editor through a
+//! [`VirtualFileProvider`]. They are ranked next to the index by name and
+//! grepped with the contents their provider returns.
+
+use crate::file_list::FileList;
+use crate::grep::{GrepResult, GrepSearchOptions, grep_search};
+use crate::types::{ContentCacheBudget, FileItem, SearchResult};
+use fff_query_parser::FFFQuery;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A file listed by a [`VirtualFileProvider`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualFile {
+    /// Shown and searched as is, e.g. `scp://host//etc/nginx/nginx.conf`.
+    pub path: String,
+    /// Size of the contents in bytes, grep skips empty files.
+    pub size: u64,
+    /// Modification time in seconds since the epoch, breaks score ties.
+    pub modified: u64,
+}
+
+pub trait VirtualFileProvider: Send + Sync {
+    /// Registering another provider with the same name replaces this one.
+    fn name(&self) -> &str;
+
+    fn files(&self) -> Vec<VirtualFile>;
+
+    /// Contents of one of [`Self::files`]. Called from the grep threads the
+    /// first time the file is searched, the result is kept afterwards.
+    fn read(&self, path: &str) -> std::io::Result<Vec<u8>>;
+}
+
+/// The registered providers and the files they list, sorted by path.
+#[derive(Clone, Default)]
+pub struct VirtualFiles {
+    providers: Vec<Arc<dyn VirtualFileProvider>>,
+    files: FileList,
+    /// Index of the provider of each file.
+    owners: Arc<Vec<usize>>,
+}
+
+impl std::fmt::Debug for VirtualFiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VirtualFiles")
+            .field("providers", &self.provider_names().collect::<Vec<_>>())
+            .field("files", &self.files.len())
+            .finish()
+    }
+}
+
+impl VirtualFiles {
+    pub fn register(&mut self, provider: Arc<dyn VirtualFileProvider>) {
+        self.providers.retain(|p| p.name() != provider.name());
+        self.providers.push(provider);
+        self.rebuild();
+    }
+
+    /// Whether a provider with this name was registered.
+    pub fn unregister(&mut self, name: &str) -> bool {
+        let before = self.providers.len();
+        self.providers.retain(|p| p.name() != name);
+        let removed = self.providers.len() != before;
+        if removed {
+            self.rebuild();
+        }
+        removed
+    }
+
+    /// List the files of every provider again, e.g. after a remote listing
+    /// changed. Contents are read again on the next grep.
+    pub fn reload(&mut self) {
+        self.rebuild();
+    }
+
+    pub fn provider_names(&self) -> impl Iterator<Item = &str> {
+        self.providers.iter().map(|p| p.name())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// All virtual files sorted by path, to rank with
+    /// [`crate::FilePicker::fuzzy_search`].
+    pub fn files(&self) -> &FileList {
+        &self.files
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&FileItem> {
+        self.files
+            .binary_search_by(self.files.len(), |f| f.path().cmp(path))
+            .ok()
+            .map(|idx| &self.files[idx])
+    }
+
+    /// Contents of a virtual file straight from its provider, e.g. for a
+    /// preview.
+    pub fn read(&self, path: &Path) -> Option<std::io::Result<Vec<u8>>> {
+        let idx = self
+            .files
+            .binary_search_by(self.files.len(), |f| f.path().cmp(path))
+            .ok()?;
+        let provider = &self.providers[self.owners[idx]];
+        Some(provider.read(&path.to_string_lossy()))
+    }
+
+    /// Grep the virtual files, reading the contents not read yet through
+    /// their providers. A file whose provider fails is skipped.
+    pub fn grep(
+        &self,
+        query: &FFFQuery<'_>,
+        options: &GrepSearchOptions,
+        budget: &ContentCacheBudget,
+    ) -> GrepResult<'_> {
+        self.files
+            .par_iter()
+            .zip(self.owners.par_iter())
+            .filter(|(file, _)| file.size > 0 && !file.has_content())
+            .for_each(|(file, provider)| {
+                let path = file.path().to_string_lossy();
+                match self.providers[*provider].read(&path) {
+                    Ok(content) => file.set_content(content),
+                    Err(e) => tracing::debug!(?e, %path, "Failed to read a virtual file"),
+                }
+            });
+
+        grep_search(&self.files, query, options, budget, None, None, None)
+    }
+
+    fn rebuild(&mut self) {
+        let mut items: Vec<(FileItem, usize)> = self
+            .providers
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, provider)| {
+                provider
+                    .files()
+                    .into_iter()
+                    .map(move |file| (virtual_file_item(file), idx))
+            })
+            .collect();
+        items.sort_by(|(a, _), (b, _)| a.path().cmp(b.path()));
+        items.dedup_by(|(a, _), (b, _)| a.path() == b.path());
+
+        let (files, owners) = items.into_iter().unzip();
+        self.files = files;
+        self.owners = Arc::new(owners);
+    }
+}
+
+fn virtual_file_item(file: VirtualFile) -> FileItem {
+    let file_name = file
+        .path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(&file.path)
+        .to_string();
+    FileItem::new_raw(
+        PathBuf::from(&file.path),
+        file.path,
+        file_name,
+        file.size,
+        file.modified,
+        None,
+        false,
+    )
+}
+
+/// Merge two rankings from offset 0 into one, ordered like a single search:
+/// by score, then by the most recently modified file.
+pub fn merge_rankings<'a>(
+    ranking: SearchResult<'a>,
+    other: SearchResult<'a>,
+    limit: usize,
+) -> SearchResult<'a> {
+    if other.items.is_empty() {
+        return ranking;
+    }
+
+    let mut merged: Vec<_> = ranking
+        .items
+        .into_iter()
+        .zip(ranking.scores)
+        .chain(other.items.into_iter().zip(other.scores))
+        .collect();
+    merged.sort_by(|(a_item, a), (b_item, b)| {
+        b.total
+            .cmp(&a.total)
+            .then_with(|| b_item.modified.cmp(&a_item.modified))
+    });
+    if limit > 0 {
+        merged.truncate(limit);
+    }
+    let (items, scores) = merged.into_iter().unzip();
+
+    SearchResult {
+        items,
+        scores,
+        total_matched: ranking.total_matched + other.total_matched,
+        total_files: ranking.total_files + other.total_files,
+        // the cursor of either ranking doesn't know the matches of the other
+        cursor: None,
+        ..ranking
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    struct Remote;
+
+    impl VirtualFileProvider for Remote {
+        fn name(&self) -> &str {
+            "remote"
+        }
+
+        fn files(&self) -> Vec<VirtualFile> {
+            ["scp://host//etc/hosts", "scp://host//etc/nginx.conf"]
+                .into_iter()
+                .map(|path| VirtualFile {
+                    path: path.to_string(),
+                    size: 16,
+                    modified: 0,
+                })
+                .collect()
+        }
+
+        fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+            if path.ends_with("nginx.conf") {
+                Ok(b"worker_processes 4;\n".to_vec())
+            } else {
+                Err(io::Error::other("connection refused"))
+            }
+        }
+    }
+
+    #[test]
+    fn greps_through_the_provider() {
+        let mut virtual_files = VirtualFiles::default();
+        virtual_files.register(Arc::new(Remote));
+        assert_eq!(virtual_files.files()[1].file_name(), "nginx.conf");
+
+        let query = crate::grep::parse_grep_query("worker");
+        let options = GrepSearchOptions {
+            max_file_size: 10 * 1024 * 1024,
+            max_matches_per_file: 0,
+            smart_case: true,
+            file_offset: 0,
+            page_limit: 100,
+            mode: crate::GrepMode::PlainText,
+            time_budget_ms: 0,
+            before_context: 0,
+            after_context: 0,
+            classify_definitions: false,
+            file_order: Default::default(),
+            scope: Default::default(),
+            changed_lines: None,
+            resume_after_line: 0,
+            exact_page_limit: false,
+            dedupe_lines: false,
+            history_boost: None,
+        };
+        let result = virtual_files.grep(&query, &options, &ContentCacheBudget::default());
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!(
+            result.files[0].path(),
+            Path::new("scp://host//etc/nginx.conf")
+        );
+
+        assert!(virtual_files.unregister("remote"));
+        assert!(virtual_files.is_empty());
+    }
+}
@@ -0,0 +1,234 @@
+//! Constraint filtering cached between keystrokes.
+//!
+//! Typing after `*.rs src/` only changes the fuzzy part of the query, yet
+//! every keystroke ran the constraints and the search root over the whole
+//! index again before matching. A [`WorkingSetCache`] remembers the files
+//! that passed them, keyed by the constraint set and the generation of the
+//! file list, so only the fuzzy matching reruns while the constraints stay
+//! the same. Every write to the index bumps its generation (see
+//! [`FilePicker::index_generation`]), so a file added, removed or updated by
+//! the background watcher invalidates the cached set.
+//!
+//! The session can also be narrowed to a set of files, e.g. the ones selected
+//! so far, so the following queries only search within them.
+//!
+//! [`FilePicker::index_generation`]: crate::FilePicker::index_generation
+
+use crate::file_list::FileList;
+use crate::types::FileItem;
+use ahash::AHashSet;
+use fff_query_parser::Constraint;
+use parking_lot::Mutex;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// The files of the last search that passed its constraints. Keep one per
+/// search session and pass it with [`FuzzySearchOptions::working_set`].
+///
+/// [`FuzzySearchOptions::working_set`]: crate::FuzzySearchOptions::working_set
+#[derive(Debug, Default)]
+pub struct WorkingSetCache {
+    entry: Mutex<Option<Entry>>,
+    narrowing: Mutex<Option<Arc<AHashSet<PathBuf>>>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    key: WorkingSetKey,
+    /// Indices into the file list, `None` when the constraints kept every file.
+    indices: Option<Arc<[u32]>>,
+}
+
+/// What the working set of a search depends on.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct WorkingSetKey {
+    generation: u64,
+    /// [`FileList::id`] and length of the file list.
+    files: (u64, usize),
+    constraints: String,
+    search_root: Option<String>,
+}
+
+impl WorkingSetKey {
+    pub fn new(
+        files: &FileList,
+        generation: u64,
+        constraints: &[Constraint<'_>],
+        search_root: Option<&str>,
+    ) -> Self {
+        Self {
+            generation,
+            files: (files.id(), files.len()),
+            constraints: format!("{constraints:?}"),
+            search_root: search_root.map(str::to_owned),
+        }
+    }
+}
+
+impl WorkingSetCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the cached working set.
+    pub fn clear(&self) {
+        self.entry.lock().take();
+    }
+
+    /// Search only the files at `paths` (absolute) until
+    /// [`Self::clear_narrowing`]. Narrowing again keeps the
+    /// files that are in both sets.
+    pub fn narrow_to<P: Into<PathBuf>>(&self, paths: impl IntoIterator<Item = P>) {
+        let mut paths: AHashSet<PathBuf> = paths.into_iter().map(Into::into).collect();
+        let mut narrowing = self.narrowing.lock();
+        if let Some(previous) = narrowing.as_ref() {
+            paths.retain(|path| previous.contains(path));
+        }
+        *narrowing = Some(Arc::new(paths));
+        self.clear();
+    }
+
+    /// Search every file again.
+    pub fn clear_narrowing(&self) {
+        if self.narrowing.lock().take().is_some() {
+            self.clear();
+        }
+    }
+
+    /// The files searched since [`Self::narrow_to`], `None` when not narrowed.
+    pub fn narrowing(&self) -> Option<Arc<AHashSet<PathBuf>>> {
+        self.narrowing.lock().clone()
+    }
+
+    /// The cached working set for `key`: `Some(None)` when every file passed.
+    pub(crate) fn get(&self, key: &WorkingSetKey) -> Option<Option<Arc<[u32]>>> {
+        let entry = self.entry.lock();
+        let entry = entry.as_ref().filter(|entry| entry.key == *key)?;
+        Some(entry.indices.clone())
+    }
+
+    /// Remember the working set of `key`, `None` for every file of `files`.
+    pub(crate) fn insert(
+        &self,
+        key: WorkingSetKey,
+        files: &FileList,
+        working_set: Option<&[&FileItem]>,
+    ) {
+        let indices = working_set.map(|working_set| {
+            working_set
+                .iter()
+                .filter_map(|file| files.index_of(file))
+                .map(|index| index as u32)
+                .collect()
+        });
+        *self.entry.lock() = Some(Entry { key, indices });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_picker::{FilePicker, FuzzySearchOptions};
+    use crate::test_fixtures::{picker_options, scan, write_files};
+    use fff_query_parser::QueryParser;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn picker_with_sources(tmp: &TempDir) -> FilePicker {
+        write_files(
+            tmp.path(),
+            &[
+                ("src/handler.rs", ""),
+                ("src/handler.ts", ""),
+                ("src/router.rs", ""),
+                ("src/main.rs", ""),
+            ],
+        );
+        scan(picker_options(tmp.path()))
+    }
+
+    fn search(picker: &FilePicker, cache: &WorkingSetCache, query: &str) -> Vec<String> {
+        let parsed = QueryParser::default().parse(query);
+        let result = FilePicker::fuzzy_search(
+            picker.get_files(),
+            &parsed,
+            None,
+            FuzzySearchOptions {
+                max_threads: 1,
+                max_typos: Some(0),
+                index_generation: picker.index_generation(),
+                working_set: Some(cache),
+                ..Default::default()
+            },
+        );
+        assert_eq!(result.index_generation, picker.index_generation());
+        let mut paths: Vec<_> = result
+            .items
+            .iter()
+            .map(|file| file.relative_path().to_string())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn working_set_follows_the_index_generation() {
+        let tmp = TempDir::new().unwrap();
+        let mut picker = picker_with_sources(&tmp);
+        let cache = WorkingSetCache::new();
+
+        assert_eq!(search(&picker, &cache, "*.rs ha"), ["src/handler.rs"]);
+        // same constraints, only the fuzzy part changed
+        assert_eq!(search(&picker, &cache, "*.rs rout"), ["src/router.rs"]);
+        assert_eq!(search(&picker, &cache, "*.ts ha"), ["src/handler.ts"]);
+
+        let generation = picker.index_generation();
+        let added = tmp.path().join("src/handler_test.rs");
+        fs::write(&added, "").unwrap();
+        picker.on_create_or_modify(&added).unwrap();
+        assert_ne!(picker.index_generation(), generation);
+
+        assert_eq!(
+            search(&picker, &cache, "*.rs handler"),
+            ["src/handler.rs", "src/handler_test.rs"]
+        );
+    }
+
+    #[test]
+    fn narrowing_searches_only_the_pinned_files() {
+        let tmp = TempDir::new().unwrap();
+        let picker = picker_with_sources(&tmp);
+        let cache = WorkingSetCache::new();
+        let path = |name: &str| picker.base_path().join("src").join(name);
+
+        // cache the working set of the constraints before narrowing
+        assert_eq!(
+            search(&picker, &cache, "*.rs"),
+            ["src/handler.rs", "src/main.rs", "src/router.rs"]
+        );
+
+        cache.narrow_to([path("handler.rs"), path("handler.ts"), path("router.rs")]);
+        assert_eq!(
+            search(&picker, &cache, ""),
+            ["src/handler.rs", "src/handler.ts", "src/router.rs"]
+        );
+        assert_eq!(
+            search(&picker, &cache, "*.rs"),
+            ["src/handler.rs", "src/router.rs"]
+        );
+        assert_eq!(
+            search(&picker, &cache, "handler"),
+            ["src/handler.rs", "src/handler.ts"]
+        );
+
+        // narrowing again stays within the previous set
+        cache.narrow_to([path("handler.ts"), path("main.rs")]);
+        assert_eq!(search(&picker, &cache, ""), ["src/handler.ts"]);
+
+        cache.clear_narrowing();
+        assert_eq!(
+            search(&picker, &cache, "*.rs"),
+            ["src/handler.rs", "src/main.rs", "src/router.rs"]
+        );
+    }
+}
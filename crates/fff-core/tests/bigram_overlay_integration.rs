@@ -273,10 +273,10 @@ fn new_file_findable_after_add() {
     {
         let guard = shared_picker.read().unwrap();
         let picker = guard.as_ref().unwrap();
-        let overflow = picker.get_overflow_files();
+        let overflow: Vec<_> = picker.get_overflow_files().collect();
         assert_eq!(overflow.len(), 1, "Should have 1 overflow file");
         assert!(
-            overflow[0].path.ends_with("newcomer.txt"),
+            overflow[0].path().ends_with("newcomer.txt"),
             "Overflow file should be newcomer.txt"
         );
     }
@@ -302,6 +302,13 @@ fn grep_opts() -> GrepSearchOptions {
         before_context: 0,
         after_context: 0,
         classify_definitions: false,
+        file_order: Default::default(),
+        scope: Default::default(),
+        changed_lines: None,
+        resume_after_line: 0,
+        exact_page_limit: false,
+        dedupe_lines: false,
+        history_boost: None,
     }
 }
 
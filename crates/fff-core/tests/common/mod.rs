@@ -0,0 +1,37 @@
+//! Fixtures shared by the integration tests. Every test binary compiles its
+//! own copy and uses only some of them.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::Path;
+
+use fff_search::FilePickerOptions;
+use fff_search::file_picker::{FFFMode, FilePicker};
+
+/// Options of a picker over `base` that doesn't watch it.
+pub fn picker_options(base: &Path) -> FilePickerOptions {
+    FilePickerOptions {
+        base_path: base.to_string_lossy().to_string(),
+        mode: FFFMode::Neovim,
+        watch: false,
+        ..Default::default()
+    }
+}
+
+/// A picker that scanned its base path before returning.
+pub fn scan(options: FilePickerOptions) -> FilePicker {
+    let mut picker = FilePicker::new(options).expect("Failed to create FilePicker");
+    picker.collect_files().expect("Failed to scan files");
+    picker
+}
+
+/// Write every `(relative path, contents)` under `base`.
+pub fn write_files(base: &Path, files: &[(&str, &str)]) {
+    for (relative, contents) in files {
+        let path = base.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(path, contents).unwrap();
+    }
+}
@@ -0,0 +1,74 @@
+//! Integration test: bare "dotfiles" repositories configured through
+//! `GIT_DIR` / `GIT_WORK_TREE`.
+//!
+//! Kept in its own test binary because it mutates process environment.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+use common::{picker_options, scan, write_files};
+use fff_search::git::GitRepoLocation;
+use git2::{Repository, Signature, Status};
+
+#[test]
+fn git_dir_and_work_tree_env_are_honored() {
+    let tmp = TempDir::new().unwrap();
+    let root = fs::canonicalize(tmp.path()).unwrap();
+    let git_dir = root.join("dotfiles.git");
+    let home = root.join("home");
+    let outside = root.join("outside");
+    fs::create_dir_all(&outside).unwrap();
+    write_files(
+        &home,
+        &[
+            (".config/nvim/init.lua", "-- init"),
+            (".config/nvim/notes.txt", "notes"),
+        ],
+    );
+
+    let repo = Repository::init_bare(&git_dir).unwrap();
+    repo.set_workdir(&home, false).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(".config/nvim/init.lua")).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now("fff", "fff@example.com").unwrap();
+    repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+        .unwrap();
+    repo.config()
+        .unwrap()
+        .set_str("status.showUntrackedFiles", "no")
+        .unwrap();
+
+    fs::write(home.join(".config/nvim/init.lua"), "-- changed").unwrap();
+
+    // SAFETY: this is the only test in this binary, nothing reads the
+    // environment concurrently.
+    unsafe {
+        std::env::set_var("GIT_DIR", &git_dir);
+        std::env::set_var("GIT_WORK_TREE", &home);
+    }
+
+    let base_path = home.join(".config");
+    let location = GitRepoLocation::discover(&base_path).unwrap();
+    assert_eq!(location.workdir, home.join(""));
+    assert!(!location.is_worktree);
+    assert!(GitRepoLocation::discover(&outside).is_none());
+
+    let picker = scan(picker_options(&base_path));
+
+    let status_of = |name: &str| {
+        picker
+            .get_files()
+            .iter()
+            .find(|f| f.path() == home.join(".config/nvim").join(name))
+            .unwrap()
+            .git_status
+    };
+    assert!(status_of("init.lua").is_some_and(|s| s.contains(Status::WT_MODIFIED)));
+    // showUntrackedFiles=no keeps untracked files out of the status list.
+    assert_eq!(status_of("notes.txt"), None);
+}
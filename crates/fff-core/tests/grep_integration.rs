@@ -1,10 +1,19 @@
-use std::fs;
+mod common;
+
+use std::fs::{self, File};
 use std::path::Path;
+use std::time::{Duration, SystemTime};
 use tempfile::TempDir;
 
-use fff_search::ContentCacheBudget;
-use fff_search::grep::{GrepMode, GrepSearchOptions, grep_search, parse_grep_query};
+use common::{picker_options, scan, write_files};
+use fff_search::file_picker::FilePicker;
+use fff_search::grep::{
+    CaptureSpan, FileSubset, GrepFileOrder, GrepHistoryBoost, GrepMode, GrepResult, GrepRow,
+    GrepScope, GrepSearchOptions, grep_search, grep_search_in_subset, parse_grep_query,
+};
+use fff_search::grep_watch::GrepWatch;
 use fff_search::types::FileItem;
+use fff_search::{ContentCacheBudget, FileList, FilePickerOptions, TrigramIndex};
 
 /// Create a file inside a temp dir and return its `FileItem`.
 fn create_file(base: &Path, relative: &str, contents: &str) -> FileItem {
@@ -29,6 +38,13 @@ fn plain_opts() -> GrepSearchOptions {
         before_context: 0,
         after_context: 0,
         classify_definitions: false,
+        file_order: Default::default(),
+        scope: Default::default(),
+        changed_lines: None,
+        resume_after_line: 0,
+        exact_page_limit: false,
+        dedupe_lines: false,
+        history_boost: None,
     }
 }
 
@@ -45,6 +61,13 @@ fn regex_opts() -> GrepSearchOptions {
         before_context: 0,
         after_context: 0,
         classify_definitions: false,
+        file_order: Default::default(),
+        scope: Default::default(),
+        changed_lines: None,
+        resume_after_line: 0,
+        exact_page_limit: false,
+        dedupe_lines: false,
+        history_boost: None,
     }
 }
 
@@ -61,17 +84,24 @@ fn fuzzy_opts() -> GrepSearchOptions {
         before_context: 0,
         after_context: 0,
         classify_definitions: false,
+        file_order: Default::default(),
+        scope: Default::default(),
+        changed_lines: None,
+        resume_after_line: 0,
+        exact_page_limit: false,
+        dedupe_lines: false,
+        history_boost: None,
     }
 }
 
 #[test]
 fn plain_text_finds_exact_literal() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "hello.txt",
         "Hello, World!\nGoodbye, World!\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("Hello");
     let result = grep_search(
@@ -89,14 +119,132 @@ fn plain_text_finds_exact_literal() {
     assert!(result.matches[0].line_content.contains("Hello"));
 }
 
+#[test]
+fn file_order_controls_which_files_are_searched_first() {
+    let tmp = TempDir::new().unwrap();
+    let mut files = FileList::from(vec![
+        create_file(tmp.path(), "a.txt", "needle\n"),
+        create_file(tmp.path(), "b.txt", "needle\n"),
+        create_file(tmp.path(), "c.txt", "needle\n"),
+    ]);
+    files[0].total_frecency_score = 10;
+    files[2].git_status = Some(git2::Status::WT_MODIFIED);
+
+    let parsed = parse_grep_query("needle");
+    let order_of = |file_order| {
+        let result = grep_search(
+            &files,
+            &parsed,
+            &GrepSearchOptions {
+                file_order,
+                ..plain_opts()
+            },
+            &ContentCacheBudget::unlimited(),
+            None,
+            None,
+            None,
+        );
+        result
+            .matches
+            .iter()
+            .map(|m| result.files[m.file_index].relative_path().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(order_of(GrepFileOrder::Frecency)[0], "a.txt");
+    assert_eq!(
+        order_of(GrepFileOrder::GitChangedFirst),
+        ["c.txt", "a.txt", "b.txt"]
+    );
+    assert_eq!(order_of(GrepFileOrder::Path), ["a.txt", "b.txt", "c.txt"]);
+}
+
+#[test]
+fn history_boost_pins_the_remembered_match_first() {
+    let tmp = TempDir::new().unwrap();
+    let files = FileList::from(vec![
+        create_file(tmp.path(), "a.txt", "needle\n"),
+        create_file(tmp.path(), "b.txt", "needle one\nneedle two\n"),
+    ]);
+
+    let result = grep_search(
+        &files,
+        &parse_grep_query("needle"),
+        &GrepSearchOptions {
+            file_order: GrepFileOrder::Path,
+            history_boost: Some(GrepHistoryBoost {
+                file_path: files[1].path().to_path_buf(),
+                line_number: 2,
+            }),
+            ..plain_opts()
+        },
+        &ContentCacheBudget::unlimited(),
+        None,
+        None,
+        None,
+    );
+
+    let order: Vec<_> = result
+        .matches
+        .iter()
+        .map(|m| (result.files[m.file_index].relative_path(), m.line_number))
+        .collect();
+    assert_eq!(order, [("b.txt", 2), ("b.txt", 1), ("a.txt", 1)]);
+}
+
+#[test]
+fn grep_in_subset_only_searches_the_given_files() {
+    let tmp = TempDir::new().unwrap();
+    let files = FileList::from(vec![
+        create_file(tmp.path(), "src/a.rs", "needle\n"),
+        create_file(tmp.path(), "src/b.rs", "needle\n"),
+        create_file(tmp.path(), "tests/c.rs", "needle\n"),
+    ]);
+
+    for mode in [GrepMode::PlainText, GrepMode::Regex, GrepMode::Fuzzy] {
+        let result = grep_search_in_subset(
+            &files,
+            &parse_grep_query("needle"),
+            &GrepSearchOptions {
+                mode,
+                ..plain_opts()
+            },
+            &ContentCacheBudget::unlimited(),
+            None,
+            None,
+            None,
+            &FileSubset::from_indices([0, 2]),
+        );
+
+        let mut paths: Vec<_> = result.files.iter().map(|f| f.relative_path()).collect();
+        paths.sort();
+        assert_eq!(paths, ["src/a.rs", "tests/c.rs"], "{mode:?}");
+        assert_eq!(result.filtered_file_count, 2, "{mode:?}");
+    }
+
+    // Constraints still narrow the subset further.
+    let result = grep_search_in_subset(
+        &files,
+        &parse_grep_query("src/ needle"),
+        &plain_opts(),
+        &ContentCacheBudget::unlimited(),
+        None,
+        None,
+        None,
+        &FileSubset::from_indices([0, 2]),
+    );
+    assert_eq!(result.files.len(), 1);
+    assert_eq!(result.files[0].relative_path(), "src/a.rs");
+}
+
 #[test]
 fn plain_text_smart_case_insensitive() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "Hello World\nhello world\nHELLO WORLD\n",
-    )];
+    )]);
 
     // All lowercase query → smart case → case-insensitive
     let parsed = parse_grep_query("hello");
@@ -120,11 +268,11 @@ fn plain_text_smart_case_insensitive() {
 #[test]
 fn plain_text_smart_case_sensitive_with_uppercase() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "Hello World\nhello world\nHELLO WORLD\n",
-    )];
+    )]);
 
     // Query has uppercase → smart case → case-sensitive
     let parsed = parse_grep_query("Hello");
@@ -149,11 +297,11 @@ fn plain_text_smart_case_sensitive_with_uppercase() {
 #[test]
 fn plain_text_regex_metacharacters_are_literal() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "code.rs",
         "fn main() {\n    println!(\"test\");\n}\nfn foo() {}\n",
-    )];
+    )]);
 
     // In plain text mode, these regex metacharacters should be literal
     let parsed = parse_grep_query("fn main()");
@@ -188,11 +336,11 @@ fn plain_text_regex_metacharacters_are_literal() {
 #[test]
 fn plain_text_dot_is_literal() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "config.toml",
         "version = \"1.0\"\nname = \"foo\"\nversion_major = 1\n",
-    )];
+    )]);
 
     // In plain text mode, dot should be literal, not "any char"
     let parsed = parse_grep_query("1.0");
@@ -217,11 +365,11 @@ fn plain_text_dot_is_literal() {
 #[test]
 fn plain_text_asterisk_is_literal() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "doc.md",
         "Use **bold** text\nUse *italic* text\nUse normal text\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("**bold**");
     let result = grep_search(
@@ -240,11 +388,11 @@ fn plain_text_asterisk_is_literal() {
 #[test]
 fn plain_text_backslash_is_literal() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "paths.txt",
         "C:\\Users\\foo\\bar\n/home/user/bin\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("C:\\Users");
     let result = grep_search(
@@ -262,11 +410,11 @@ fn plain_text_backslash_is_literal() {
 #[test]
 fn plain_text_across_multiple_files() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![
+    let files = FileList::from(vec![
         create_file(tmp.path(), "a.txt", "use std::io;\nuse std::fs;\n"),
         create_file(tmp.path(), "b.txt", "use std::path;\nuse serde;\n"),
         create_file(tmp.path(), "c.txt", "no match here\n"),
-    ];
+    ]);
 
     let parsed = parse_grep_query("use std");
     let result = grep_search(
@@ -287,7 +435,11 @@ fn plain_text_across_multiple_files() {
 #[test]
 fn plain_text_highlight_offsets_are_correct() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(tmp.path(), "a.txt", "foo bar foo baz foo\n")];
+    let files = FileList::from(vec![create_file(
+        tmp.path(),
+        "a.txt",
+        "foo bar foo baz foo\n",
+    )]);
 
     let parsed = parse_grep_query("foo");
     let result = grep_search(
@@ -313,7 +465,7 @@ fn plain_text_highlight_offsets_are_correct() {
 #[test]
 fn plain_text_empty_query_returns_no_content_matches() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(tmp.path(), "a.txt", "some content\n")];
+    let files = FileList::from(vec![create_file(tmp.path(), "a.txt", "some content\n")]);
 
     let parsed = parse_grep_query("");
     let result = grep_search(
@@ -326,9 +478,44 @@ fn plain_text_empty_query_returns_no_content_matches() {
         None,
     );
 
-    // Empty query in grep returns git-modified welcome state (no content matches)
-    // Since our test files have no git status, we expect 0 matches
     assert_eq!(result.matches.len(), 0);
+    assert!(result.files.is_empty());
+}
+
+#[test]
+fn empty_query_lists_changed_and_frecent_files() {
+    let tmp = TempDir::new().unwrap();
+    let mut opened = create_file(tmp.path(), "opened.rs", "fn opened() {}\n");
+    opened.access_frecency_score = 3;
+    opened.total_frecency_score = 3;
+    let mut changed = create_file(tmp.path(), "changed.rs", "fn changed() {}\n");
+    changed.git_status = Some(git2::Status::WT_MODIFIED);
+    changed.modification_frecency_score = 8;
+    changed.total_frecency_score = 8;
+    let untouched = create_file(tmp.path(), "untouched.rs", "fn untouched() {}\n");
+    let files = FileList::from(vec![changed, opened, untouched]);
+
+    let first_page = GrepSearchOptions {
+        page_limit: 1,
+        ..plain_opts()
+    };
+    let budget = ContentCacheBudget::unlimited();
+    let parsed = parse_grep_query("");
+    let result = grep_search(&files, &parsed, &first_page, &budget, None, None, None);
+
+    assert!(result.matches.is_empty());
+    let rows: Vec<_> = result.rows().collect();
+    assert!(matches!(rows[..], [GrepRow::FileEntry(file)] if file.relative_path() == "changed.rs"));
+    assert_eq!(result.filtered_file_count, 2);
+    assert_eq!(result.next_file_offset, 1);
+
+    let second_page = GrepSearchOptions {
+        file_offset: 1,
+        ..first_page
+    };
+    let result = grep_search(&files, &parsed, &second_page, &budget, None, None, None);
+    assert_eq!(result.file_entries[0].relative_path(), "opened.rs");
+    assert_eq!(result.next_file_offset, 0);
 }
 
 #[test]
@@ -354,7 +541,7 @@ fn plain_text_binary_files_are_skipped() {
 
     let text_file = create_file(tmp.path(), "text.txt", "match this text\n");
 
-    let files = vec![binary_file, text_file];
+    let files = FileList::from(vec![binary_file, text_file]);
 
     let parsed = parse_grep_query("match this text");
     let result = grep_search(
@@ -369,7 +556,7 @@ fn plain_text_binary_files_are_skipped() {
 
     // Only the text file should be searched, not the binary one
     assert_eq!(result.files.len(), 1);
-    assert!(result.files[0].relative_path.contains("text.txt"));
+    assert!(result.files[0].relative_path().contains("text.txt"));
 }
 
 #[test]
@@ -379,7 +566,7 @@ fn plain_text_max_matches_per_file() {
     for i in 0..50 {
         content.push_str(&format!("line {} match_target\n", i));
     }
-    let files = vec![create_file(tmp.path(), "many.txt", &content)];
+    let files = FileList::from(vec![create_file(tmp.path(), "many.txt", &content)]);
 
     let mut opts = plain_opts();
     opts.max_matches_per_file = 5;
@@ -409,7 +596,7 @@ fn plain_text_page_limit() {
     for i in 0..100 {
         content.push_str(&format!("line {} target\n", i));
     }
-    let files = vec![create_file(tmp.path(), "big.txt", &content)];
+    let files = FileList::from(vec![create_file(tmp.path(), "big.txt", &content)]);
 
     let mut opts = plain_opts();
     opts.page_limit = 10;
@@ -442,12 +629,112 @@ fn plain_text_page_limit() {
     assert_eq!(result.matches.len(), 100, "all 100 lines must be returned");
 }
 
+#[test]
+fn line_constraints_filter_matched_lines_before_the_page_fills() {
+    let tmp = TempDir::new().unwrap();
+    let files = FileList::from(vec![
+        create_file(
+            tmp.path(),
+            "a.rs",
+            "// parse the header\nfn parse() {}\n// parse test input\n",
+        ),
+        create_file(tmp.path(), "b.rs", "parse();\n  // parse later\n"),
+    ]);
+
+    let mut opts = plain_opts();
+    opts.page_limit = 2;
+    let lines = |query: &str, opts: &GrepSearchOptions| {
+        let parsed = parse_grep_query(query);
+        let result = grep_search(
+            &files,
+            &parsed,
+            opts,
+            &ContentCacheBudget::unlimited(),
+            None,
+            None,
+            None,
+        );
+        result
+            .matches
+            .iter()
+            .map(|m| m.line_content.clone())
+            .collect::<Vec<_>>()
+    };
+
+    // only comments without "test", and the dropped lines leave room for b.rs
+    let query = r"parse line:^\s*// line:!test";
+    assert_eq!(
+        lines(query, &opts),
+        ["// parse the header", "  // parse later"]
+    );
+    assert_eq!(lines(query, &fuzzy_opts()).len(), 2);
+    // an invalid regex is matched literally
+    assert_eq!(
+        lines("parse line:parse(", &opts),
+        ["fn parse() {}", "parse();"]
+    );
+}
+
+#[test]
+fn scope_keeps_matches_in_code_comments_or_strings() {
+    let tmp = TempDir::new().unwrap();
+    let files = FileList::from(vec![
+        create_file(
+            tmp.path(),
+            "lib.rs",
+            "/// Builds a Parser.\nfn new() -> Parser { Parser::default() } // Parser\nlet name = \"Parser\";\n",
+        ),
+        create_file(tmp.path(), "notes.txt", "Parser notes\n"),
+    ]);
+
+    let lines = |scope: GrepScope, mode: GrepMode| {
+        let mut opts = plain_opts();
+        opts.scope = scope;
+        opts.mode = mode;
+        let result = grep_search(
+            &files,
+            &parse_grep_query("Parser"),
+            &opts,
+            &ContentCacheBudget::unlimited(),
+            None,
+            None,
+            None,
+        );
+        let mut lines: Vec<_> = result
+            .matches
+            .iter()
+            .map(|m| (m.line_number, m.col, m.match_byte_offsets.len()))
+            .collect();
+        lines.sort();
+        lines
+    };
+
+    // files of unknown languages are not filtered
+    assert_eq!(
+        lines(GrepScope::Code, GrepMode::PlainText),
+        [(1, 0, 1), (2, 12, 2)]
+    );
+    assert_eq!(
+        lines(GrepScope::Comments, GrepMode::PlainText),
+        [(1, 0, 1), (1, 13, 1), (2, 44, 1)]
+    );
+    assert_eq!(
+        lines(GrepScope::Strings, GrepMode::PlainText),
+        [(1, 0, 1), (3, 12, 1)]
+    );
+    assert_eq!(
+        lines(GrepScope::Code, GrepMode::Regex),
+        [(1, 0, 1), (2, 12, 2)]
+    );
+    assert_eq!(lines(GrepScope::All, GrepMode::PlainText).len(), 4);
+}
+
 #[test]
 fn plain_text_file_offset_pagination() {
     let tmp = TempDir::new().unwrap();
     // Create many files (1 match per file) so file-based pagination exercises
     // offset tracking across files with and without matches.
-    let mut files = Vec::new();
+    let mut files = FileList::new();
     for i in 0..20 {
         files.push(create_file(
             tmp.path(),
@@ -515,14 +802,111 @@ fn plain_text_file_offset_pagination() {
     );
 }
 
+#[test]
+fn exact_page_limit_resumes_inside_a_file() {
+    let tmp = TempDir::new().unwrap();
+    let big: String = (1..=12).map(|i| format!("token big {i}\n")).collect();
+    let files = FileList::from(vec![
+        create_file(tmp.path(), "a.txt", "token a\n"),
+        create_file(tmp.path(), "big.txt", &big),
+        create_file(tmp.path(), "c.txt", "token c 1\ntoken c 2\n"),
+    ]);
+
+    for mut opts in [plain_opts(), fuzzy_opts()] {
+        opts.page_limit = 5;
+        opts.exact_page_limit = true;
+        let parsed = parse_grep_query("token");
+
+        let mut seen: Vec<String> = Vec::new();
+        let mut page_sizes = Vec::new();
+        loop {
+            let result = grep_search(
+                &files,
+                &parsed,
+                &opts,
+                &ContentCacheBudget::unlimited(),
+                None,
+                None,
+                None,
+            );
+            page_sizes.push(result.matches.len());
+            for m in &result.matches {
+                let line = format!(
+                    "{}:{}",
+                    result.files[m.file_index].relative_path(),
+                    m.line_number
+                );
+                assert!(!seen.contains(&line), "{line} reported twice");
+                seen.push(line);
+            }
+            let Some(token) = result.next_page else {
+                break;
+            };
+            assert_eq!(token.to_string().parse(), Ok(token));
+            opts.resume_from(token);
+            assert!(page_sizes.len() < 10, "pagination did not terminate");
+        }
+
+        assert_eq!(seen.len(), 15, "{:?}", opts.mode);
+        assert_eq!(page_sizes, [5, 5, 5], "{:?}", opts.mode);
+    }
+}
+
+#[test]
+fn dedupe_lines_folds_identical_lines_into_the_first() {
+    let tmp = TempDir::new().unwrap();
+    let header = "// SPDX-License-Identifier: MIT\n";
+    let files = FileList::from(vec![
+        create_file(tmp.path(), "a.rs", &format!("{header}fn a() {{}}\n")),
+        create_file(tmp.path(), "b.rs", &format!("  {header}")),
+        create_file(
+            tmp.path(),
+            "c.rs",
+            &format!("{header}// MIT licensed parts\n"),
+        ),
+    ]);
+    let parsed = parse_grep_query("MIT");
+
+    let mut opts = plain_opts();
+    opts.file_order = GrepFileOrder::Path;
+    let search = |opts: &GrepSearchOptions| {
+        grep_search(
+            &files,
+            &parsed,
+            opts,
+            &ContentCacheBudget::unlimited(),
+            None,
+            None,
+            None,
+        )
+    };
+    assert_eq!(search(&opts).matches.len(), 4);
+
+    opts.dedupe_lines = true;
+    let result = search(&opts);
+    let found: Vec<(&str, u64, u32)> = result
+        .matches
+        .iter()
+        .map(|m| {
+            (
+                result.files[m.file_index].relative_path(),
+                m.line_number,
+                m.duplicates,
+            )
+        })
+        .collect();
+    assert_eq!(found, [("a.rs", 1, 2), ("c.rs", 2, 0)]);
+    assert_eq!(result.files_with_matches, 2);
+}
+
 #[test]
 fn plain_text_line_numbers_are_correct() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "line one\nline two\nline three\nline four\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("line");
     let result = grep_search(
@@ -547,7 +931,7 @@ fn plain_text_max_file_size_filter() {
     let tmp = TempDir::new().unwrap();
     // Create a file larger than 100 bytes
     let big_content = "a".repeat(200) + "\nmatch_me\n";
-    let files = vec![create_file(tmp.path(), "big.txt", &big_content)];
+    let files = FileList::from(vec![create_file(tmp.path(), "big.txt", &big_content)]);
 
     let mut opts = plain_opts();
     opts.max_file_size = 100; // Only allow files up to 100 bytes
@@ -572,11 +956,11 @@ fn plain_text_max_file_size_filter() {
 #[test]
 fn regex_basic_pattern() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "foo123\nbar456\nbaz789\nfoo_bar\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("foo\\d+");
     let result = grep_search(
@@ -597,7 +981,11 @@ fn regex_basic_pattern() {
 #[test]
 fn regex_capture_group_matching() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(tmp.path(), "a.txt", "foobar\nfoobaz\nfoo123\n")];
+    let files = FileList::from(vec![create_file(
+        tmp.path(),
+        "a.txt",
+        "foobar\nfoobaz\nfoo123\n",
+    )]);
 
     // Use a capturing group (not lookahead, which regex crate doesn't support)
     let parsed = parse_grep_query("foo(bar|baz)");
@@ -621,14 +1009,70 @@ fn regex_capture_group_matching() {
     assert!(contents.contains(&"foobaz"));
 }
 
+#[test]
+fn regex_capture_groups_are_reported_per_match() {
+    let tmp = TempDir::new().unwrap();
+    let files = FileList::from(vec![create_file(
+        tmp.path(),
+        "a.rs",
+        "fn parse(x) fn load(y)\nlet fn_ = 1;\n",
+    )]);
+
+    let parsed = parse_grep_query(r"fn (\w+)\((\w)?");
+    let search = |opts: &GrepSearchOptions| {
+        grep_search(
+            &files,
+            &parsed,
+            opts,
+            &ContentCacheBudget::unlimited(),
+            None,
+            None,
+            None,
+        )
+    };
+    let result = search(&regex_opts());
+
+    assert_eq!(result.matches.len(), 1);
+    let m = &result.matches[0];
+    assert_eq!(m.match_byte_offsets.as_slice(), [(0, 10), (12, 21)]);
+    assert_eq!(
+        m.captures,
+        [
+            CaptureSpan {
+                group: 1,
+                start: 3,
+                end: 8
+            },
+            CaptureSpan {
+                group: 2,
+                start: 9,
+                end: 10
+            },
+            CaptureSpan {
+                group: 1,
+                start: 15,
+                end: 19
+            },
+            CaptureSpan {
+                group: 2,
+                start: 20,
+                end: 21
+            },
+        ]
+    );
+
+    let result = search(&plain_opts());
+    assert!(result.matches.iter().all(|m| m.captures.is_empty()));
+}
+
 #[test]
 fn regex_dot_matches_any_char() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "v1.0\nv1x0\nv1-0\nv100\nv2.0\n",
-    )];
+    )]);
 
     // In regex mode, . matches any character, so v1.0 matches v1.0, v1x0, v1-0, and v100
     let parsed = parse_grep_query("v1.0");
@@ -652,11 +1096,11 @@ fn regex_dot_matches_any_char() {
 #[test]
 fn regex_alternation() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "apple\nbanana\ncherry\napricot\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("apple|cherry");
     let result = grep_search(
@@ -678,11 +1122,11 @@ fn regex_alternation() {
 #[test]
 fn regex_character_class() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "cat\ncut\ncot\ncit\ncxt\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("c[aou]t");
     let result = grep_search(
@@ -709,11 +1153,11 @@ fn regex_character_class() {
 #[test]
 fn regex_quantifiers() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "fo\nfoo\nfooo\nfoooo\nbar\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("fo{2,3}");
     let result = grep_search(
@@ -732,11 +1176,11 @@ fn regex_quantifiers() {
 #[test]
 fn regex_anchors() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "start of line\nmiddle start end\nend of line\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("^start");
     let result = grep_search(
@@ -756,11 +1200,11 @@ fn regex_anchors() {
 #[test]
 fn regex_anchors_multiword() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "test.c",
         "int ff_function(void);\nstatic int ff_other(void);\nint main(void);\nint ff_another(void);\n",
-    )];
+    )]);
 
     // ^int ff_ should match lines starting with "int ff_"
     let parsed = parse_grep_query("^int ff_");
@@ -786,7 +1230,7 @@ fn regex_anchors_multiword() {
 #[test]
 fn regex_highlight_offsets_variable_length() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(tmp.path(), "a.txt", "aab aaab aaaab\n")];
+    let files = FileList::from(vec![create_file(tmp.path(), "a.txt", "aab aaab aaaab\n")]);
 
     let parsed = parse_grep_query("a+b");
     let result = grep_search(
@@ -812,11 +1256,11 @@ fn regex_highlight_offsets_variable_length() {
 #[test]
 fn regex_invalid_pattern_falls_back_to_literal() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "call name(arg)\nother line\n",
-    )];
+    )]);
 
     // Invalid regex: unmatched group — should fall back to literal search
     let parsed = parse_grep_query("name(");
@@ -860,11 +1304,11 @@ fn regex_invalid_pattern_falls_back_to_literal() {
 #[test]
 fn regex_smart_case() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "Foo bar\nfoo bar\nFOO BAR\n",
-    )];
+    )]);
 
     // Lowercase query → case-insensitive
     let parsed_lower = parse_grep_query("foo");
@@ -896,7 +1340,7 @@ fn regex_smart_case() {
 #[test]
 fn regex_across_multiple_files() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![
+    let files = FileList::from(vec![
         create_file(
             tmp.path(),
             "lib.rs",
@@ -908,7 +1352,7 @@ fn regex_across_multiple_files() {
             "fn test_one() {}\nfn test_two() {}\nmod tests;\n",
         ),
         create_file(tmp.path(), "readme.md", "# Title\nSome text\n"),
-    ];
+    ]);
 
     let parsed = parse_grep_query("fn \\w+\\(\\)");
     let result = grep_search(
@@ -931,11 +1375,11 @@ fn regex_across_multiple_files() {
 #[test]
 fn plain_text_and_regex_agree_on_simple_literal() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "hello world\ngoodbye world\nhello again\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("hello");
     let plain_result = grep_search(
@@ -967,11 +1411,11 @@ fn plain_text_and_regex_agree_on_simple_literal() {
 #[test]
 fn plain_text_escapes_what_regex_does_not() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "price is $100\nprice is 100\nprice is $200\n",
-    )];
+    )]);
 
     // "$100" — in plain text, $ is literal; in regex, $ is anchor
     let parsed_plain = parse_grep_query("$100");
@@ -1008,11 +1452,11 @@ fn plain_text_escapes_what_regex_does_not() {
 #[test]
 fn grep_with_extension_constraint() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![
+    let files = FileList::from(vec![
         create_file(tmp.path(), "a.rs", "use std::io;\nfn main() {}\n"),
         create_file(tmp.path(), "b.txt", "use std::io;\nsome text\n"),
         create_file(tmp.path(), "c.rs", "use std::fs;\n"),
-    ];
+    ]);
 
     let parsed = parse_grep_query("use std *.rs");
     let result = grep_search(
@@ -1028,9 +1472,9 @@ fn grep_with_extension_constraint() {
     // Should only search .rs files
     for file in &result.files {
         assert!(
-            file.relative_path.ends_with(".rs"),
+            file.relative_path().ends_with(".rs"),
             "should only match .rs files, got: {}",
-            file.relative_path
+            file.relative_path()
         );
     }
     assert!(
@@ -1044,11 +1488,11 @@ fn grep_with_extension_constraint() {
 #[test]
 fn plain_text_bracket_is_literal() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "code.rs",
         "let x = arr[0];\nlet y = arr[1];\nlet z = something;\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("arr[0]");
     let result = grep_search(
@@ -1074,10 +1518,10 @@ fn plain_text_bracket_is_literal() {
 #[test]
 fn grep_backslash_escapes_extension_filter() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![
+    let files = FileList::from(vec![
         create_file(tmp.path(), "a.rs", "contains *.rs pattern\n"),
         create_file(tmp.path(), "b.txt", "also has *.rs here\n"),
-    ];
+    ]);
 
     // Without escape: "*.rs" is an extension filter, so only .rs files are searched
     let parsed = parse_grep_query("pattern *.rs");
@@ -1117,10 +1561,10 @@ fn grep_backslash_escapes_extension_filter() {
 #[test]
 fn grep_backslash_escapes_path_segment() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![
+    let files = FileList::from(vec![
         create_file(tmp.path(), "src/main.rs", "search for /src/ path\n"),
         create_file(tmp.path(), "lib/utils.rs", "also /src/ mentioned\n"),
-    ];
+    ]);
 
     // With escape: "\\/src/" is literal text, not a path constraint
     let parsed = parse_grep_query("\\/src/");
@@ -1143,11 +1587,11 @@ fn grep_backslash_escapes_path_segment() {
 #[test]
 fn grep_backslash_escapes_negation() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "the !test macro\nother stuff\n",
-    )];
+    )]);
 
     // With escape: "\\!test" is literal text "!test"
     let parsed = parse_grep_query("\\!test");
@@ -1167,11 +1611,11 @@ fn grep_backslash_escapes_negation() {
 #[test]
 fn grep_with_path_constraint() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![
+    let files = FileList::from(vec![
         create_file(tmp.path(), "src/lib.rs", "target_text\n"),
         create_file(tmp.path(), "tests/test.rs", "target_text\n"),
         create_file(tmp.path(), "src/main.rs", "other content\n"),
-    ];
+    ]);
 
     let parsed = parse_grep_query("target_text /src/");
     let result = grep_search(
@@ -1185,7 +1629,7 @@ fn grep_with_path_constraint() {
     );
 
     assert_eq!(result.matches.len(), 1);
-    assert!(result.files[0].relative_path.starts_with("src/"));
+    assert!(result.files[0].relative_path().starts_with("src/"));
 }
 
 // ── Negated constraint tests ───────────────────────────────────────────
@@ -1193,11 +1637,11 @@ fn grep_with_path_constraint() {
 #[test]
 fn grep_with_negated_extension_constraint() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![
+    let files = FileList::from(vec![
         create_file(tmp.path(), "src/lib.rs", "target_text\n"),
         create_file(tmp.path(), "src/app.ts", "target_text\n"),
         create_file(tmp.path(), "src/main.rs", "target_text\n"),
-    ];
+    ]);
 
     let query = "target_text !*.rs";
     let parsed = parse_grep_query(query);
@@ -1218,20 +1662,20 @@ fn grep_with_negated_extension_constraint() {
         result.matches.len()
     );
     assert!(
-        result.files[0].relative_path.ends_with(".ts"),
+        result.files[0].relative_path().ends_with(".ts"),
         "should only match .ts file, got: {}",
-        result.files[0].relative_path
+        result.files[0].relative_path()
     );
 }
 
 #[test]
 fn grep_with_negated_path_constraint() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![
+    let files = FileList::from(vec![
         create_file(tmp.path(), "src/lib.rs", "target_text\n"),
         create_file(tmp.path(), "tests/test.rs", "target_text\n"),
         create_file(tmp.path(), "src/main.rs", "other content\n"),
-    ];
+    ]);
 
     let query = "target_text !/src/";
     let parsed = parse_grep_query(query);
@@ -1252,20 +1696,20 @@ fn grep_with_negated_path_constraint() {
         result.matches.len()
     );
     assert!(
-        result.files[0].relative_path.starts_with("tests/"),
+        result.files[0].relative_path().starts_with("tests/"),
         "should only match tests/ file, got: {}",
-        result.files[0].relative_path
+        result.files[0].relative_path()
     );
 }
 
 #[test]
 fn grep_with_negated_text_constraint() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![
+    let files = FileList::from(vec![
         create_file(tmp.path(), "src/lib.rs", "target_text\n"),
         create_file(tmp.path(), "tests/helper.rs", "target_text\n"),
         create_file(tmp.path(), "docs/readme.md", "target_text\n"),
-    ];
+    ]);
 
     let query = "target_text !test";
     let parsed = parse_grep_query(query);
@@ -1288,9 +1732,9 @@ fn grep_with_negated_text_constraint() {
     );
     for file in &result.files {
         assert!(
-            !file.relative_path.contains("test"),
+            !file.relative_path().contains("test"),
             "should not match files with 'test' in path, got: {}",
-            file.relative_path
+            file.relative_path()
         );
     }
 }
@@ -1306,7 +1750,7 @@ fn grep_empty_file_is_skipped() {
 
     let text_file = create_file(tmp.path(), "text.txt", "findme\n");
 
-    let files = vec![empty_file, text_file];
+    let files = FileList::from(vec![empty_file, text_file]);
     let parsed = parse_grep_query("findme");
     let result = grep_search(
         &files,
@@ -1324,7 +1768,7 @@ fn grep_empty_file_is_skipped() {
 #[test]
 fn grep_single_line_no_trailing_newline() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(tmp.path(), "a.txt", "no newline at end")];
+    let files = FileList::from(vec![create_file(tmp.path(), "a.txt", "no newline at end")]);
 
     let parsed = parse_grep_query("no newline");
     let result = grep_search(
@@ -1344,11 +1788,11 @@ fn grep_single_line_no_trailing_newline() {
 #[test]
 fn grep_unicode_content() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "utf8.txt",
         "日本語テスト\nrégulière\nñoño\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("régulière");
     let result = grep_search(
@@ -1381,7 +1825,7 @@ fn grep_unicode_content() {
 fn grep_long_line_is_truncated() {
     let tmp = TempDir::new().unwrap();
     let long_line = "x".repeat(1000) + "NEEDLE" + &"y".repeat(1000);
-    let files = vec![create_file(tmp.path(), "long.txt", &long_line)];
+    let files = FileList::from(vec![create_file(tmp.path(), "long.txt", &long_line)]);
 
     let parsed = parse_grep_query("NEEDLE");
     let result = grep_search(
@@ -1406,11 +1850,11 @@ fn grep_long_line_is_truncated() {
 #[test]
 fn regex_word_boundary() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "foo\nfoobar\nbarfoo\nfoo_baz\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("\\bfoo\\b");
     let result = grep_search(
@@ -1434,11 +1878,11 @@ fn regex_word_boundary() {
 #[test]
 fn plain_text_question_mark_is_literal() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "what is this?\nhow does it work?\nno question here\nwhat?\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("?");
     let result = grep_search(
@@ -1461,11 +1905,11 @@ fn plain_text_question_mark_is_literal() {
 #[test]
 fn plain_text_query_with_question_mark_in_word() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "code.rs",
         "let x = foo?;\nlet y = bar.baz();\nfoo?.unwrap()\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("foo?");
     let result = grep_search(
@@ -1488,7 +1932,11 @@ fn plain_text_query_with_question_mark_in_word() {
 #[test]
 fn regex_question_mark_is_quantifier() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(tmp.path(), "a.txt", "color\ncolour\ncolouur\n")];
+    let files = FileList::from(vec![create_file(
+        tmp.path(),
+        "a.txt",
+        "color\ncolour\ncolouur\n",
+    )]);
 
     // In regex mode, ? means "zero or one of preceding"
     let parsed = parse_grep_query("colou?r");
@@ -1514,11 +1962,11 @@ fn regex_question_mark_is_quantifier() {
 #[test]
 fn fuzzy_finds_exact_substring() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "a.txt",
         "hello world\ngoodbye world\nhello again\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("hello");
     let result = grep_search(
@@ -1543,11 +1991,11 @@ fn fuzzy_finds_exact_substring() {
 #[test]
 fn fuzzy_finds_scattered_characters() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "code.rs",
         "fn mutex_lock() {}\nfn main() {}\nfn mutex_unlock() {}\n",
-    )];
+    )]);
 
     // "mutex" should fuzzy match "mutex_lock" (contiguous prefix)
     let parsed = parse_grep_query("mutex");
@@ -1571,7 +2019,7 @@ fn fuzzy_finds_scattered_characters() {
 #[test]
 fn fuzzy_highlight_offsets_correct() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(tmp.path(), "a.txt", "hello world\n")];
+    let files = FileList::from(vec![create_file(tmp.path(), "a.txt", "hello world\n")]);
 
     let parsed = parse_grep_query("hell");
     let result = grep_search(
@@ -1598,11 +2046,11 @@ fn fuzzy_highlight_offsets_correct() {
 #[test]
 fn fuzzy_unicode_char_indices() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "utf8.txt",
         "日本語テスト\nrégulière\nñoño\n",
-    )];
+    )]);
 
     // Use "guli" which is a contiguous ASCII substring within "régulière"
     // (the chars g-u-l-i appear contiguously between the two accented chars)
@@ -1626,7 +2074,7 @@ fn fuzzy_unicode_char_indices() {
 #[test]
 fn fuzzy_empty_query_returns_empty() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(tmp.path(), "a.txt", "some content\n")];
+    let files = FileList::from(vec![create_file(tmp.path(), "a.txt", "some content\n")]);
 
     let parsed = parse_grep_query("");
     let result = grep_search(
@@ -1646,11 +2094,11 @@ fn fuzzy_empty_query_returns_empty() {
 #[test]
 fn fuzzy_with_extension_constraint() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![
+    let files = FileList::from(vec![
         create_file(tmp.path(), "a.rs", "use std::io;\nfn main() {}\n"),
         create_file(tmp.path(), "b.txt", "use std::io;\nsome text\n"),
         create_file(tmp.path(), "c.rs", "use std::fs;\n"),
-    ];
+    ]);
 
     let parsed = parse_grep_query("use std *.rs");
     let result = grep_search(
@@ -1666,9 +2114,9 @@ fn fuzzy_with_extension_constraint() {
     // Should only search .rs files
     for file in &result.files {
         assert!(
-            file.relative_path.ends_with(".rs"),
+            file.relative_path().ends_with(".rs"),
             "should only match .rs files, got: {}",
-            file.relative_path
+            file.relative_path()
         );
     }
 }
@@ -1680,7 +2128,7 @@ fn fuzzy_respects_page_limit() {
     for i in 0..100 {
         content.push_str(&format!("line {} target\n", i));
     }
-    let files = vec![create_file(tmp.path(), "big.txt", &content)];
+    let files = FileList::from(vec![create_file(tmp.path(), "big.txt", &content)]);
 
     let mut opts = fuzzy_opts();
     opts.page_limit = 10;
@@ -1725,7 +2173,7 @@ fn fuzzy_respects_max_matches_per_file() {
     for i in 0..50 {
         content.push_str(&format!("line {} match_target\n", i));
     }
-    let files = vec![create_file(tmp.path(), "many.txt", &content)];
+    let files = FileList::from(vec![create_file(tmp.path(), "many.txt", &content)]);
 
     let mut opts = fuzzy_opts();
     opts.max_matches_per_file = 5;
@@ -1751,11 +2199,11 @@ fn fuzzy_respects_max_matches_per_file() {
 #[test]
 fn fuzzy_filters_low_quality_matches() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "code.rs",
         "fn mutex_lock() {}\nfn xyz() {}\nfn abc_def_ghi() {}\nfn abcdefghij() {}\n",
-    )];
+    )]);
 
     // Search for "abc" - should match "abc_def_ghi" and "abcdefghij" with high scores,
     // but NOT "xyz" (no relation) or "mutex_lock" (only weak letter overlap)
@@ -1790,11 +2238,11 @@ fn fuzzy_filters_low_quality_matches() {
 #[test]
 fn fuzzy_exact_match_always_passes() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "test.txt",
         "exact match line\nno match here\n",
-    )];
+    )]);
 
     // Exact matches should always pass regardless of score threshold
     let parsed = parse_grep_query("exact");
@@ -1819,11 +2267,11 @@ fn fuzzy_exact_match_always_passes() {
 #[test]
 fn fuzzy_score_is_captured() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "test.txt",
         "hello world\ngoodbye world\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("hello");
     let result = grep_search(
@@ -1853,7 +2301,7 @@ fn fuzzy_score_is_captured() {
 #[test]
 fn fuzzy_score_is_none_in_plain_mode() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(tmp.path(), "test.txt", "hello world\n")];
+    let files = FileList::from(vec![create_file(tmp.path(), "test.txt", "hello world\n")]);
 
     let parsed = parse_grep_query("hello");
     let result = grep_search(
@@ -1882,11 +2330,11 @@ fn fuzzy_score_is_none_in_plain_mode() {
 #[test]
 fn plain_text_smart_case_finds_uppercase_content_with_lowercase_query() {
     let tmp = TempDir::new().unwrap();
-    let files = vec![create_file(
+    let files = FileList::from(vec![create_file(
         tmp.path(),
         "driver.c",
         "// VFIO-KVM integration\nstatic int init(void) {}\n",
-    )];
+    )]);
 
     let parsed = parse_grep_query("vfio-kvm");
     let result = grep_search(
@@ -1905,3 +2353,181 @@ fn plain_text_smart_case_finds_uppercase_content_with_lowercase_query() {
         "lowercase query should case-insensitively match 'VFIO-KVM'"
     );
 }
+
+// ── Watched grep tests ─────────────────────────────────────────────────
+
+fn matched_lines(result: &GrepResult<'_>) -> Vec<(String, String)> {
+    result
+        .matches
+        .iter()
+        .map(|m| {
+            (
+                result.files[m.file_index]
+                    .relative_path()
+                    .replace('\\', "/"),
+                m.line_content.clone(),
+            )
+        })
+        .collect()
+}
+
+/// A watched grep only searches the files modified since its previous
+/// refresh and keeps the matches of the others.
+#[test]
+fn watch_refresh_searches_only_modified_files() {
+    let tmp = TempDir::new().unwrap();
+    let base = tmp.path();
+    let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+    for (name, contents) in [
+        ("alpha.txt", "needle one\n"),
+        ("beta.txt", "needle two\n"),
+        ("gamma.txt", "nothing here\n"),
+    ] {
+        fs::write(base.join(name), contents).unwrap();
+        File::options()
+            .write(true)
+            .open(base.join(name))
+            .unwrap()
+            .set_modified(an_hour_ago)
+            .unwrap();
+    }
+
+    let mut picker = scan(picker_options(base));
+
+    let mut watch = GrepWatch::new("needle", plain_opts());
+    let snapshot = picker.snapshot();
+    let first = watch.refresh(&snapshot);
+    assert_eq!(
+        matched_lines(&first),
+        vec![
+            ("alpha.txt".to_string(), "needle one".to_string()),
+            ("beta.txt".to_string(), "needle two".to_string()),
+        ]
+    );
+
+    // alpha stops matching, gamma starts, beta is untouched.
+    fs::write(base.join("alpha.txt"), "haystack\n").unwrap();
+    fs::write(base.join("gamma.txt"), "needle three\n").unwrap();
+    picker.on_create_or_modify(base.join("alpha.txt"));
+    picker.on_create_or_modify(base.join("gamma.txt"));
+
+    let snapshot = picker.snapshot();
+    let second = watch.refresh(&snapshot);
+    assert_eq!(second.total_files_searched, 2);
+    assert_eq!(
+        matched_lines(&second),
+        vec![
+            ("beta.txt".to_string(), "needle two".to_string()),
+            ("gamma.txt".to_string(), "needle three".to_string()),
+        ]
+    );
+    assert_eq!(second.files_with_matches, 2);
+}
+
+// ── Trigram index tests ────────────────────────────────────────────────
+
+/// Relative paths of the files with a match for `query`, sorted.
+fn grep_paths(picker: &FilePicker, query: &str) -> Vec<String> {
+    let result = picker.grep(&parse_grep_query(query), &plain_opts());
+    let mut paths: Vec<String> = result
+        .matches
+        .iter()
+        .map(|m| {
+            result.files[m.file_index]
+                .relative_path()
+                .replace('\\', "/")
+        })
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+fn picker_with_trigram_index(base: &Path, index_dir: &Path) -> FilePicker {
+    let mut picker = scan(FilePickerOptions {
+        trigram_index_dir: Some(index_dir.to_path_buf()),
+        ..picker_options(base)
+    });
+    picker.load_or_build_trigram_index();
+    picker
+}
+
+/// Grep over a persisted trigram index finds the same matches as a full
+/// scan, including in files edited after indexing.
+#[test]
+fn grep_uses_persisted_trigram_index() {
+    let project = TempDir::new().unwrap();
+    let cache = TempDir::new().unwrap();
+    let base = project.path();
+    write_files(
+        base,
+        &[
+            ("src/config.rs", "fn parse_config() {}\n"),
+            ("src/loader.rs", "fn load() { parse_config(); }\n"),
+            ("notes.txt", "nothing to see\n"),
+        ],
+    );
+    File::options()
+        .write(true)
+        .open(base.join("notes.txt"))
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(3600))
+        .unwrap();
+
+    let mut picker = picker_with_trigram_index(base, cache.path());
+    assert_eq!(picker.trigram_index().map(TrigramIndex::len), Some(3));
+    assert!(TrigramIndex::path_for(cache.path(), base).exists());
+    assert_eq!(
+        grep_paths(&picker, "parse_config"),
+        vec!["src/config.rs", "src/loader.rs"]
+    );
+
+    fs::write(base.join("notes.txt"), "parse_config!!\n").unwrap();
+    picker.on_create_or_modify(base.join("notes.txt"));
+    fs::write(base.join("added.rs"), "parse_config\n").unwrap();
+    picker.on_create_or_modify(base.join("added.rs"));
+    assert_eq!(
+        grep_paths(&picker, "parse_config"),
+        vec!["added.rs", "notes.txt", "src/config.rs", "src/loader.rs"]
+    );
+
+    // a new picker reloads the saved index
+    let reloaded = picker_with_trigram_index(base, cache.path());
+    assert!(reloaded.trigram_index().is_some());
+    assert_eq!(grep_paths(&reloaded, "fn load"), vec!["src/loader.rs"]);
+}
+
+#[test]
+fn watcher_events_keep_trigram_index_current() {
+    let project = TempDir::new().unwrap();
+    let cache = TempDir::new().unwrap();
+    let base = project.path();
+    write_files(
+        base,
+        &[
+            ("old_name.rs", "fn renamed_needle() {}\n"),
+            ("doomed.rs", "fn doomed_needle() {}\n"),
+            ("other.rs", "fn other() {}\n"),
+        ],
+    );
+
+    let mut picker = picker_with_trigram_index(base, cache.path());
+    let health = picker.trigram_index_health().unwrap();
+    assert_eq!((health.indexed_files, health.changed_files), (3, 0));
+    assert_eq!(health.coverage, 1.0);
+    assert!(!health.invalidated);
+
+    fs::rename(base.join("old_name.rs"), base.join("new_name.rs")).unwrap();
+    picker.remove_file_by_path(base.join("old_name.rs"));
+    picker.on_create_or_modify(base.join("new_name.rs"));
+    fs::remove_file(base.join("doomed.rs")).unwrap();
+    picker.remove_file_by_path(base.join("doomed.rs"));
+
+    assert_eq!(grep_paths(&picker, "renamed_needle"), vec!["new_name.rs"]);
+    assert!(grep_paths(&picker, "doomed_needle").is_empty());
+
+    let health = picker.trigram_index_health().unwrap();
+    assert_eq!(health.changed_files, 1);
+    assert_eq!(health.unindexed_changes, 0);
+    assert_eq!(health.coverage, 1.0);
+}
@@ -0,0 +1,83 @@
+//! Integration test: a fuzzy search with a warmed up [`SearchScratch`]
+//! allocates less than one without it. The test binary counts every byte the
+//! global allocator hands out, so it holds a single test.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tempfile::TempDir;
+
+use fff_search::file_picker::FilePicker;
+use fff_search::{FuzzySearchOptions, QueryParser, SearchScratch};
+
+mod common;
+use common::{picker_options, scan};
+
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const FILES: usize = 5_000;
+
+#[test]
+fn warmed_scratch_allocates_less_per_search() {
+    let tmp = TempDir::new().unwrap();
+    for i in 0..FILES {
+        let dir = tmp.path().join(format!("module_{i}"));
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join(format!("item_{i}.rs")), "").unwrap();
+    }
+    let picker = scan(picker_options(tmp.path()));
+    assert_eq!(picker.get_files().len(), FILES);
+
+    // "module" matches in the directory of every path, so each search also
+    // fills the fallback filenames.
+    let parsed = QueryParser::default().parse("module");
+    let scratch = SearchScratch::new();
+    let allocated_by_search = |scratch| {
+        let before = ALLOCATED.load(Ordering::Relaxed);
+        let result = FilePicker::fuzzy_search(
+            picker.get_files(),
+            &parsed,
+            None,
+            FuzzySearchOptions {
+                max_threads: 1,
+                scratch,
+                ..Default::default()
+            },
+        );
+        assert_eq!(result.total_matched, FILES);
+        drop(result);
+        ALLOCATED.load(Ordering::Relaxed) - before
+    };
+
+    allocated_by_search(Some(&scratch));
+    let with_scratch = allocated_by_search(Some(&scratch));
+    let without_scratch = allocated_by_search(None);
+
+    // At the very least the haystack and the fallback filenames, one string
+    // slice per file each, are no longer allocated.
+    let reused = 2 * FILES * size_of::<&str>();
+    assert!(
+        with_scratch + reused <= without_scratch,
+        "{with_scratch} bytes with the scratch, {without_scratch} without"
+    );
+}
@@ -1,8 +1,10 @@
-//! Cursor store for grep pagination.
+//! Cursor store for grep and file search pagination.
 //!
-//! Maintains an in-memory map of opaque cursor IDs to file offsets.
+//! Maintains an in-memory map of opaque cursor IDs to file offsets, and for
+//! file searches the [`SearchCursor`] that serves the next page.
 //! Cursors are evicted LRU-style when the store exceeds capacity.
 
+use fff::SearchCursor;
 use std::collections::{HashMap, VecDeque};
 
 const MAX_CURSORS: usize = 20;
@@ -11,7 +13,7 @@ const MAX_CURSORS: usize = 20;
 pub struct CursorStore {
     counter: u64,
     /// Map from cursor ID string → file offset for next page.
-    cursors: HashMap<String, usize>,
+    cursors: HashMap<String, (usize, Option<SearchCursor>)>,
     /// Insertion order for LRU eviction.
     insertion_order: VecDeque<String>,
 }
@@ -27,10 +29,16 @@ impl CursorStore {
 
     /// Store a cursor and return its opaque ID string.
     pub fn store(&mut self, file_offset: usize) -> String {
+        self.store_search(file_offset, None)
+    }
+
+    /// Store a cursor of a file search, with the search cursor that continues
+    /// it at `offset`.
+    pub fn store_search(&mut self, offset: usize, search: Option<SearchCursor>) -> String {
         self.counter = self.counter.wrapping_add(1);
         let id = self.counter.to_string();
 
-        self.cursors.insert(id.clone(), file_offset);
+        self.cursors.insert(id.clone(), (offset, search));
         self.insertion_order.push_back(id.clone());
 
         // Evict oldest cursors
@@ -47,6 +55,11 @@ impl CursorStore {
 
     /// Retrieve the file offset for a cursor ID.
     pub fn get(&self, id: &str) -> Option<usize> {
-        self.cursors.get(id).copied()
+        self.cursors.get(id).map(|(offset, _)| *offset)
+    }
+
+    /// Retrieve the search cursor stored with [`Self::store_search`].
+    pub fn get_search(&self, id: &str) -> Option<SearchCursor> {
+        self.cursors.get(id).and_then(|(_, search)| search.clone())
     }
 }
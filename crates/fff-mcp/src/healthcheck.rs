@@ -1,5 +1,4 @@
 use crate::Args;
-use git2::Repository;
 
 fn check(label: &str, ok: bool, detail: &str) -> bool {
     let marker = if ok { "+" } else { "x" };
@@ -37,10 +36,15 @@ pub fn run_healthcheck(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
     );
 
     // 2. Git repository
-    match Repository::discover(&base_path) {
+    match fff::git::open_repository(std::path::Path::new(&base_path)) {
         Ok(repo) => {
             if let Some(workdir) = repo.workdir() {
-                all_ok &= check("Git repository", true, &format!("{}", workdir.display()));
+                let workdir = if repo.is_worktree() {
+                    format!("{} (worktree)", workdir.display())
+                } else {
+                    format!("{}", workdir.display())
+                };
+                all_ok &= check("Git repository", true, &workdir);
             } else {
                 all_ok &= check("Git repository", true, "bare repository");
             }
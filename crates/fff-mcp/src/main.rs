@@ -136,6 +136,16 @@ pub(crate) struct Args {
     #[arg(long = "no-warmup")]
     no_warmup: bool,
 
+    /// Ignore per-directory `.ignore` and `.rgignore` files. Git ignore rules
+    /// still apply.
+    #[arg(long = "no-ignore-files")]
+    no_ignore_files: bool,
+
+    /// Don't descend into other mounted filesystems, like fd's
+    /// `--one-file-system`.
+    #[arg(long = "one-file-system")]
+    one_file_system: bool,
+
     /// Maximum number of files whose content is kept persistently in memory.
     /// Files beyond this limit are still searchable via temporary mmaps that
     /// are released after each grep. Defaults to 30 000.
@@ -149,39 +159,21 @@ pub(crate) struct Args {
 }
 
 /// Resolve default paths for frecency db, history db, and log file.
-/// Shares Neovim's standard data locations when they exist so the MCP
-/// server and fff.nvim plugin use the same databases.
+/// The databases default to the ones fff.nvim uses, see
+/// [`fff::default_db_paths`].
 fn resolve_defaults(args: &mut Args) {
     let home = dirs_home();
-    let is_windows = cfg!(target_os = "windows");
 
-    let nvim_cache_dir = if is_windows {
-        format!("{}\\AppData\\Local\\nvim-data", home)
-    } else {
-        format!("{}/.cache/nvim", home)
-    };
-    let nvim_data_dir = if is_windows {
-        format!("{}\\AppData\\Local\\nvim-data", home)
-    } else {
-        format!("{}/.local/share/nvim", home)
-    };
-
-    let use_nvim_paths = std::path::Path::new(&nvim_cache_dir).exists()
-        || std::path::Path::new(&nvim_data_dir).exists();
-
-    if args.frecency_db_path.is_none() {
-        args.frecency_db_path = Some(if use_nvim_paths {
-            format!("{}/fff_nvim", nvim_cache_dir)
-        } else {
-            format!("{}/.fff/frecency.mdb", home)
-        });
-    }
-    if args.history_db_path.is_none() {
-        args.history_db_path = Some(if use_nvim_paths {
-            format!("{}/fff_queries", nvim_data_dir)
-        } else {
-            format!("{}/.fff/history.mdb", home)
-        });
+    if args.frecency_db_path.is_none() || args.history_db_path.is_none() {
+        match fff::default_db_paths() {
+            Ok(defaults) => {
+                args.frecency_db_path
+                    .get_or_insert_with(|| defaults.frecency.to_string_lossy().to_string());
+                args.history_db_path
+                    .get_or_insert_with(|| defaults.history.to_string_lossy().to_string());
+            }
+            Err(e) => eprintln!("Warning: Failed to resolve default database paths: {}", e),
+        }
     }
 
     // Ensure parent directories exist for database paths
@@ -195,7 +187,7 @@ fn resolve_defaults(args: &mut Args) {
     }
 
     if args.log_file.is_none() {
-        args.log_file = Some(if is_windows {
+        args.log_file = Some(if cfg!(target_os = "windows") {
             format!("{}\\AppData\\Local\\fff_mcp.log", home)
         } else {
             format!("{}/.cache/fff_mcp.log", home)
@@ -257,7 +249,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     match FrecencyTracker::new(&frecency_db_path, false) {
         Ok(tracker) => {
             let _ = shared_frecency.init(tracker);
-            let _ = shared_frecency.spawn_gc(frecency_db_path, false);
+            let _ = shared_frecency.spawn_gc(frecency_db_path);
         }
         Err(e) => {
             eprintln!("Warning: Failed to init frecency db: {}", e);
@@ -275,6 +267,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             cache_budget: args
                 .max_cached_files
                 .map(fff::ContentCacheBudget::new_for_repo),
+            respect_ignore_files: !args.no_ignore_files,
+            same_file_system: args.one_file_system,
             ..Default::default()
         },
     )
@@ -312,6 +306,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .map_err(|e| format!("Failed to start MCP server: {}", e))?;
 
     let picker_for_shutdown = shared_picker.clone();
+    let frecency_for_shutdown = shared_frecency.clone();
     tokio::spawn(async move {
         tokio::signal::ctrl_c().await.ok();
         if let Ok(mut guard) = picker_for_shutdown.write()
@@ -319,6 +314,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         {
             picker.stop_background_monitor();
         }
+        let _ = frecency_for_shutdown.flush();
         std::process::exit(0);
     });
 
@@ -329,6 +325,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     {
         picker.stop_background_monitor();
     }
+    let _ = shared_frecency.flush();
 
     Ok(())
 }
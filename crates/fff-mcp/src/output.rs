@@ -252,10 +252,10 @@ impl GrepFormatter<'_> {
         let mut content_first_file = "";
         for fm in &file_preview {
             if content_first_file.is_empty() {
-                content_first_file = &fm.file.relative_path;
+                content_first_file = fm.file.relative_path();
             }
             if content_def_file.is_empty() && fm.is_definition {
-                content_def_file = &fm.file.relative_path;
+                content_def_file = fm.file.relative_path();
             }
         }
 
@@ -310,8 +310,8 @@ impl GrepFormatter<'_> {
             let file = files[m.file_index];
             let mut match_lines: Vec<String> = Vec::new();
 
-            if file.relative_path.as_str() != current_file {
-                current_file = &file.relative_path;
+            if file.relative_path() != current_file {
+                current_file = file.relative_path();
                 match_lines.push(current_file.to_string());
             }
 
@@ -362,14 +362,14 @@ impl GrepFormatter<'_> {
                 && !show_context
                 && m.is_definition
                 && !m.context_after.is_empty()
-                && !def_expanded_files.contains(file.relative_path.as_str())
+                && !def_expanded_files.contains(file.relative_path())
             {
                 let expand_limit = if def_expanded_files.is_empty() {
                     MAX_DEF_EXPAND_FIRST
                 } else {
                     MAX_DEF_EXPAND
                 };
-                def_expanded_files.insert(file.relative_path.as_str());
+                def_expanded_files.insert(file.relative_path());
                 let start_line = m.line_number + 1;
                 for (i, ctx) in m.context_after.iter().take(expand_limit).enumerate() {
                     if ctx.trim().is_empty() {
@@ -419,10 +419,10 @@ fn format_files_with_matches(
     let mut first_file = "";
     for fm in &file_map {
         if first_file.is_empty() {
-            first_file = &fm.file.relative_path;
+            first_file = fm.file.relative_path();
         }
         if first_def_file.is_empty() && fm.is_definition {
-            first_def_file = &fm.file.relative_path;
+            first_def_file = fm.file.relative_path();
         }
     }
     let suggest_path = if !first_def_file.is_empty() {
@@ -456,7 +456,7 @@ fn format_files_with_matches(
         let def_tag = if is_def { " [def]" } else { "" };
         lines.push(format!(
             "{}{}{}",
-            fm.file.relative_path,
+            fm.file.relative_path(),
             def_tag,
             size_tag(fm.file.size)
         ));
@@ -526,7 +526,7 @@ fn format_count(
     let mut counts: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
     let mut order: Vec<&str> = Vec::new();
     for m in items {
-        let path = files[m.file_index].relative_path.as_str();
+        let path = files[m.file_index].relative_path();
         let count = counts.entry(path).or_insert_with(|| {
             order.push(path);
             0
@@ -550,7 +550,7 @@ fn collect_file_preview<'a>(items: &[GrepMatch], files: &[&'a FileItem]) -> Vec<
     let mut seen = std::collections::HashSet::new();
     for m in items {
         let file = files[m.file_index];
-        if seen.insert(&file.relative_path) {
+        if seen.insert(file.relative_path()) {
             file_preview.push(FileMeta {
                 file,
                 line_number: m.line_number,
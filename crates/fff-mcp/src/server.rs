@@ -64,6 +64,13 @@ fn make_grep_options(
             before_context: ctx_lines,
             after_context: after_ctx,
             classify_definitions: true,
+            file_order: Default::default(),
+            scope: Default::default(),
+            changed_lines: None,
+            resume_after_line: 0,
+            exact_page_limit: false,
+            dedupe_lines: false,
+            history_boost: None,
         },
         auto_expand,
     )
@@ -313,8 +320,8 @@ impl FffServer {
                 let mut current_file = "";
                 for m in fuzzy_result.matches.iter().take(3) {
                     let file = fuzzy_result.files[m.file_index];
-                    if file.relative_path.as_str() != current_file {
-                        current_file = &file.relative_path;
+                    if file.relative_path() != current_file {
+                        current_file = file.relative_path();
                         lines.push(current_file.to_string());
                     }
                     lines.push(format!(" {}: {}", m.line_number, m.line_content));
@@ -334,10 +341,25 @@ impl FffServer {
                     project_path: Some(picker.base_path()),
                     combo_boost_score_multiplier: 100,
                     min_combo_count: 3,
+                    min_combo_prefix_len: 0,
                     pagination: PaginationArgs {
                         offset: 0,
                         limit: 1,
                     },
+                    max_typos: None,
+                    adaptive_typos_threshold: 0,
+                    scoring_weights: Default::default(),
+                    extension_multipliers: None,
+                    project_profile: picker.project_profile(),
+                    search_root: None,
+                    facets: false,
+                    min_score: None,
+                    min_score_ratio: 0.0,
+                    with_cursor: false,
+                    cursor: None,
+                    index_generation: picker.index_generation(),
+                    working_set: None,
+                    scratch: None,
                 };
                 let file_result =
                     FilePicker::fuzzy_search(picker.get_files(), &file_query, None, file_opts);
@@ -349,7 +371,7 @@ impl FffServer {
                     if score.base_score > query_len * 10 {
                         return Ok(CallToolResult::success(vec![Content::text(format!(
                             "0 content matches. But there is a relevant file path: {}",
-                            top.relative_path
+                            top.relative_path()
                         ))]));
                     }
                 }
@@ -402,11 +424,14 @@ impl FffServer {
         let max_results = params.max_results.unwrap_or(20.0).round() as usize; // safe
         let query = &params.query;
 
-        let page_offset = params
+        let (page_offset, search_cursor) = params
             .cursor
             .as_deref()
-            .and_then(|id| self.cursor_store.lock().ok()?.get(id))
-            .unwrap_or(0);
+            .and_then(|id| {
+                let store = self.cursor_store.lock().ok()?;
+                Some((store.get(id)?, store.get_search(id)))
+            })
+            .unwrap_or((0, None));
 
         let guard = self.picker.read().map_err(|e| {
             ErrorData::internal_error(format!("Failed to acquire picker lock: {e}"), None)
@@ -417,28 +442,48 @@ impl FffServer {
 
         let files = picker.get_files();
         let base_path = picker.base_path();
-        let make_opts = |offset: usize| FuzzySearchOptions {
+        let make_opts = |offset: usize, cursor| FuzzySearchOptions {
             max_threads: 0,
             current_file: None,
             project_path: Some(base_path),
             combo_boost_score_multiplier: 100,
             min_combo_count: 3,
+            min_combo_prefix_len: 0,
             pagination: PaginationArgs {
                 offset,
                 limit: max_results,
             },
+            max_typos: None,
+            adaptive_typos_threshold: 0,
+            scoring_weights: Default::default(),
+            extension_multipliers: None,
+            project_profile: picker.project_profile(),
+            search_root: None,
+            facets: false,
+            min_score: None,
+            min_score_ratio: 0.0,
+            with_cursor: true,
+            cursor,
+            index_generation: picker.index_generation(),
+            working_set: None,
+            scratch: None,
         };
 
         let parser = QueryParser::default();
         let fff_query = parser.parse(query);
-        let result = FilePicker::fuzzy_search(files, &fff_query, None, make_opts(page_offset));
+        let result = FilePicker::fuzzy_search(
+            files,
+            &fff_query,
+            None,
+            make_opts(page_offset, search_cursor.as_ref()),
+        );
         let total_files = result.total_files;
 
         // Auto-retry with fewer terms if 3+ words return 0 results
         let words: Vec<&str> = query.split_whitespace().collect();
         let shorter = words.get(..2).map(|w| w.join(" "));
 
-        let (items, scores, total_matched) =
+        let (items, scores, total_matched, next_cursor) =
             if result.items.is_empty() && words.len() >= 3 && page_offset == 0 {
                 if let Some(shorter) = &shorter {
                     let shorter_query = parser.parse(shorter);
@@ -446,21 +491,35 @@ impl FffServer {
                         files,
                         &shorter_query,
                         /*query_tracker=*/ None,
-                        make_opts(0),
+                        make_opts(0, None),
                     );
 
-                    (retry.items, retry.scores, retry.total_matched)
+                    (retry.items, retry.scores, retry.total_matched, retry.cursor)
                 } else {
-                    (result.items, result.scores, result.total_matched)
+                    (
+                        result.items,
+                        result.scores,
+                        result.total_matched,
+                        result.cursor,
+                    )
                 }
             } else {
-                (result.items, result.scores, result.total_matched)
+                (
+                    result.items,
+                    result.scores,
+                    result.total_matched,
+                    result.cursor,
+                )
             };
 
         if items.is_empty() {
+            let hint = picker
+                .suggest_query(query)
+                .map(|suggestion| format!(". Did you mean: {suggestion}"))
+                .unwrap_or_default();
             return Ok(CallToolResult::success(vec![Content::text(format!(
-                "0 results ({} indexed)",
-                total_files
+                "0 results ({} indexed){}",
+                total_files, hint
             ))]));
         }
 
@@ -470,11 +529,14 @@ impl FffServer {
 
         if page_offset == 0 {
             if is_exact_match {
-                lines.push(format!("→ Read {} (exact match!)", top_item.relative_path));
+                lines.push(format!(
+                    "→ Read {} (exact match!)",
+                    top_item.relative_path()
+                ));
             } else if scores.len() < 2 || scores[0].total > scores[1].total.saturating_mul(2) {
                 lines.push(format!(
                     "→ Read {} (best match — Read this file directly)",
-                    top_item.relative_path
+                    top_item.relative_path()
                 ));
             }
         }
@@ -489,14 +551,14 @@ impl FffServer {
         for item in &items {
             lines.push(format!(
                 "{}{}",
-                item.relative_path,
+                item.relative_path(),
                 file_suffix(item.git_status, item.total_frecency_score)
             ));
         }
 
         if has_more {
             let mut cs = self.lock_cursors()?;
-            let cursor_id = cs.store(next_offset);
+            let cursor_id = cs.store_search(next_offset, next_cursor);
             lines.push(format!("cursor: {}", cursor_id));
         }
 
@@ -515,6 +577,13 @@ impl FffServer {
         &self,
         Parameters(params): Parameters<GrepParams>,
     ) -> Result<CallToolResult, ErrorData> {
+        // an empty query lists files for a welcome screen, it matches nothing
+        if params.query.trim().is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(
+                "0 matches.".to_string(),
+            )]));
+        }
+
         let max_results = params.max_results.unwrap_or(20.0) as usize;
         let output_mode = OutputMode::new(params.output_mode.as_deref());
 
@@ -0,0 +1,486 @@
+//! Node-API bindings for fff-core.
+//!
+//! Exposes the same instance API as `fff-c` as a native addon for Node.js,
+//! Bun and Electron, without the C FFI and JSON layer in between. Searches
+//! run on the libuv thread pool and return Promises, so a large grep never
+//! blocks the event loop.
+//!
+//! ```js
+//! const { FileFinder } = require('@ff-labs/fff-napi');
+//! const finder = FileFinder.create({ basePath: process.cwd() });
+//! await finder.waitForScan(5000);
+//! const { items } = await finder.search('main.rs');
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use fff::file_picker::FilePicker;
+use fff::frecency::FrecencyTracker;
+use fff::query_tracker::QueryTracker;
+use fff::{
+    FFFMode, FuzzySearchOptions, PaginationArgs, QueryParser, SearchScratch, SharedFrecency,
+    SharedPicker, SharedQueryTracker,
+};
+use mimalloc::MiMalloc;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+mod types;
+
+use types::{
+    GrepOptions, InitOptions, JsGrepResult, JsScanProgress, JsSearchResult, MultiGrepOptions,
+    SearchOptions,
+};
+
+#[global_allocator]
+static GLOBAL: MiMalloc = MiMalloc;
+
+fn error(message: impl std::fmt::Display) -> Error {
+    Error::from_reason(message.to_string())
+}
+
+/// Per-instance state. Every handle is shared with the background scanner
+/// and watcher, so cloning is cheap and tasks take their own copy.
+#[derive(Clone)]
+struct Instance {
+    picker: SharedPicker,
+    frecency: SharedFrecency,
+    query_tracker: SharedQueryTracker,
+    /// Vectors reused by the fuzzy searches of this instance
+    scratch: Arc<SearchScratch>,
+}
+
+/// A file index over one directory.
+#[napi]
+pub struct FileFinder {
+    instance: Instance,
+}
+
+#[napi]
+impl FileFinder {
+    /// Index `basePath` in the background and open the databases that were
+    /// given a path.
+    #[napi(factory)]
+    pub fn create(options: InitOptions) -> Result<Self> {
+        if options.base_path.is_empty() {
+            return Err(error("basePath is empty"));
+        }
+        let no_lock = options.use_unsafe_no_lock.unwrap_or(false);
+        let read_only = options.read_only.unwrap_or(false);
+
+        let instance = Instance {
+            picker: SharedPicker::default(),
+            frecency: SharedFrecency::default(),
+            query_tracker: SharedQueryTracker::default(),
+            scratch: Arc::default(),
+        };
+
+        if let Some(path) = options
+            .frecency_db_path
+            .filter(|path| !read_only && !path.is_empty())
+        {
+            if let Some(parent) = PathBuf::from(&path).parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            let tracker = FrecencyTracker::new(&path, no_lock)
+                .map_err(|e| error(format!("Failed to init frecency db: {e}")))?;
+            instance.frecency.init(tracker).map_err(error)?;
+            let _ = instance.frecency.spawn_gc(path);
+        }
+
+        if let Some(path) = options
+            .history_db_path
+            .filter(|path| !read_only && !path.is_empty())
+        {
+            if let Some(parent) = PathBuf::from(&path).parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            let tracker = QueryTracker::new(&path, no_lock)
+                .map_err(|e| error(format!("Failed to init query tracker db: {e}")))?;
+            instance.query_tracker.init(tracker).map_err(error)?;
+        }
+
+        let mode = if options.ai_mode.unwrap_or(false) {
+            FFFMode::Ai
+        } else {
+            FFFMode::Neovim
+        };
+
+        FilePicker::new_with_shared_state(
+            instance.picker.clone(),
+            instance.frecency.clone(),
+            fff::FilePickerOptions {
+                base_path: options.base_path,
+                warmup_mmap_cache: options.warmup_mmap_cache.unwrap_or(false),
+                mode,
+                cache_budget: None,
+                thread_pool_size: options.thread_pool_size.map(|size| size as usize),
+                read_only,
+                ..Default::default()
+            },
+        )
+        .map_err(|e| error(format!("Failed to init file picker: {e}")))?;
+
+        Ok(Self { instance })
+    }
+
+    /// Stop the background watcher and close the databases. The finder can't
+    /// be used afterwards.
+    #[napi]
+    pub fn destroy(&self) {
+        if let Ok(mut guard) = self.instance.picker.write()
+            && let Some(mut picker) = guard.take()
+        {
+            picker.stop_background_monitor();
+        }
+        if let Ok(mut guard) = self.instance.frecency.write() {
+            *guard = None;
+        }
+        if let Ok(mut guard) = self.instance.query_tracker.write() {
+            *guard = None;
+        }
+    }
+
+    /// Fuzzy search the indexed files.
+    #[napi(ts_return_type = "Promise<SearchResult>")]
+    pub fn search(&self, query: String, options: Option<SearchOptions>) -> AsyncTask<SearchTask> {
+        AsyncTask::new(SearchTask {
+            instance: self.instance.clone(),
+            query,
+            options: options.unwrap_or_default(),
+        })
+    }
+
+    /// Search the content of the indexed files.
+    #[napi(ts_return_type = "Promise<GrepResult>")]
+    pub fn grep(&self, query: String, options: Option<GrepOptions>) -> AsyncTask<GrepTask> {
+        AsyncTask::new(GrepTask {
+            instance: self.instance.clone(),
+            query,
+            options: options.unwrap_or_default(),
+        })
+    }
+
+    /// Search for lines matching any of several literal patterns.
+    #[napi(ts_return_type = "Promise<GrepResult>")]
+    pub fn multi_grep(&self, options: MultiGrepOptions) -> Result<AsyncTask<MultiGrepTask>> {
+        if options.patterns.iter().all(|pattern| pattern.is_empty()) {
+            return Err(error("patterns must not be empty"));
+        }
+        Ok(AsyncTask::new(MultiGrepTask {
+            instance: self.instance.clone(),
+            options,
+        }))
+    }
+
+    /// Rescan the indexed directory.
+    #[napi]
+    pub fn scan_files(&self) -> Result<()> {
+        self.instance
+            .picker
+            .trigger_rescan(&self.instance.frecency)
+            .map_err(|e| error(format!("Failed to trigger rescan: {e}")))
+    }
+
+    #[napi]
+    pub fn is_scanning(&self) -> bool {
+        self.instance
+            .picker
+            .read()
+            .ok()
+            .and_then(|guard| guard.as_ref().map(|picker| picker.is_scan_active()))
+            .unwrap_or(false)
+    }
+
+    #[napi]
+    pub fn get_scan_progress(&self) -> Result<JsScanProgress> {
+        let guard = self.instance.picker.read().map_err(error)?;
+        let picker = guard
+            .as_ref()
+            .ok_or_else(|| error("File picker not initialized"))?;
+        Ok(picker.get_scan_progress().into())
+    }
+
+    /// Resolve with `true` once the scan finished, `false` on timeout.
+    #[napi(ts_return_type = "Promise<boolean>")]
+    pub fn wait_for_scan(&self, timeout_ms: u32) -> AsyncTask<WaitForScanTask> {
+        AsyncTask::new(WaitForScanTask {
+            picker: self.instance.picker.clone(),
+            timeout: Duration::from_millis(timeout_ms.into()),
+        })
+    }
+
+    /// Re-read the git status of every indexed file, returns the number of
+    /// files whose status changed.
+    #[napi]
+    pub fn refresh_git_status(&self) -> Result<u32> {
+        self.instance
+            .picker
+            .refresh_git_status(&self.instance.frecency)
+            .map(|count| count as u32)
+            .map_err(|e| error(format!("Failed to refresh git status: {e}")))
+    }
+
+    /// Remember that `filePath` was picked for `query`, so repeating the
+    /// query ranks it higher. Returns `false` when history is disabled.
+    #[napi]
+    pub fn track_query(&self, query: String, file_path: String) -> Result<bool> {
+        let file_path = fff::path_utils::canonicalize(&file_path)
+            .map_err(|e| error(format!("Failed to canonicalize path: {e}")))?;
+        let project_path = match self.instance.picker.read().map_err(error)?.as_ref() {
+            Some(picker) => picker.base_path().to_path_buf(),
+            None => return Ok(false),
+        };
+
+        let mut guard = self.instance.query_tracker.write().map_err(error)?;
+        let Some(tracker) = guard.as_mut() else {
+            return Ok(false);
+        };
+        tracker
+            .track_query_completion(&query, &project_path, &file_path)
+            .map_err(|e| error(format!("Failed to track query: {e}")))?;
+        Ok(true)
+    }
+}
+
+pub struct SearchTask {
+    instance: Instance,
+    query: String,
+    options: SearchOptions,
+}
+
+#[napi]
+impl Task for SearchTask {
+    type Output = JsSearchResult;
+    type JsValue = JsSearchResult;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let options = &self.options;
+        let picker = self
+            .instance
+            .picker
+            .snapshot()
+            .ok_or_else(|| error("File picker not initialized"))?;
+        let query_tracker = self.instance.query_tracker.read().map_err(error)?;
+
+        let parsed = QueryParser::default().parse(&self.query);
+        let mut results = picker.install(|| {
+            FilePicker::fuzzy_search(
+                picker.get_files(),
+                &parsed,
+                query_tracker.as_ref(),
+                FuzzySearchOptions {
+                    max_threads: options.max_threads.unwrap_or(0) as usize,
+                    current_file: options.current_file.as_deref(),
+                    project_path: Some(picker.base_path()),
+                    combo_boost_score_multiplier: options.combo_boost_multiplier.unwrap_or(100),
+                    min_combo_count: options.min_combo_count.unwrap_or(3),
+                    min_combo_prefix_len: 0,
+                    pagination: PaginationArgs {
+                        offset: options.page_index.unwrap_or(0) as usize,
+                        limit: options.page_size.unwrap_or(100) as usize,
+                    },
+                    max_typos: options.max_typos,
+                    adaptive_typos_threshold: options.adaptive_typos_threshold.unwrap_or(0)
+                        as usize,
+                    scoring_weights: Default::default(),
+                    extension_multipliers: None,
+                    project_profile: picker.project_profile(),
+                    search_root: options.search_root.as_deref().map(Path::new),
+                    facets: false,
+                    min_score: None,
+                    min_score_ratio: 0.0,
+                    with_cursor: false,
+                    cursor: None,
+                    index_generation: picker.index_generation(),
+                    working_set: None,
+                    scratch: Some(self.instance.scratch.as_ref()),
+                },
+            )
+        });
+        if results.total_matched == 0 {
+            results.suggestion = picker.suggest_query(&self.query);
+        }
+
+        Ok(JsSearchResult::from(&results))
+    }
+
+    fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+pub struct GrepTask {
+    instance: Instance,
+    query: String,
+    options: GrepOptions,
+}
+
+#[napi]
+impl Task for GrepTask {
+    type Output = JsGrepResult;
+    type JsValue = JsGrepResult;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let options = &self.options;
+        let picker = self
+            .instance
+            .picker
+            .snapshot()
+            .ok_or_else(|| error("File picker not initialized"))?;
+
+        let parsed = if picker.mode().is_ai() {
+            QueryParser::new(fff_query_parser::AiGrepConfig).parse(&self.query)
+        } else {
+            fff::grep::parse_grep_query(&self.query)
+        };
+        let mode = match options.mode.as_deref() {
+            Some("regex") => fff::GrepMode::Regex,
+            Some("fuzzy") => fff::GrepMode::Fuzzy,
+            _ => fff::GrepMode::PlainText,
+        };
+        let changed_lines = if options.changed_only.unwrap_or(false) {
+            picker
+                .changed_lines()
+                .map_err(|e| error(format!("Failed to diff the work tree: {e}")))?
+                .map(Arc::new)
+        } else {
+            None
+        };
+        let page_token: Option<fff::GrepPageToken> = options
+            .page_token
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .map_err(error)?;
+
+        let mut grep_options = fff::GrepSearchOptions {
+            max_file_size: options.max_file_size.unwrap_or(10 * 1024 * 1024) as u64,
+            max_matches_per_file: options.max_matches_per_file.unwrap_or(0) as usize,
+            smart_case: options.smart_case.unwrap_or(true),
+            file_offset: options.file_offset.unwrap_or(0) as usize,
+            page_limit: options.page_limit.unwrap_or(50) as usize,
+            mode,
+            time_budget_ms: options.time_budget_ms.unwrap_or(0) as u64,
+            before_context: options.before_context.unwrap_or(0) as usize,
+            after_context: options.after_context.unwrap_or(0) as usize,
+            classify_definitions: options.classify_definitions.unwrap_or(false),
+            file_order: options
+                .file_order
+                .as_deref()
+                .and_then(fff::GrepFileOrder::from_name)
+                .unwrap_or_default(),
+            scope: options
+                .scope
+                .as_deref()
+                .and_then(fff::GrepScope::from_name)
+                .unwrap_or_default(),
+            changed_lines,
+            resume_after_line: 0,
+            exact_page_limit: options.exact_page_limit.unwrap_or(false),
+            dedupe_lines: options.dedupe_lines.unwrap_or(false),
+            history_boost: None,
+        };
+        if let Some(token) = page_token {
+            grep_options.resume_from(token);
+        }
+
+        let result = picker.grep(&parsed, &grep_options);
+        Ok(JsGrepResult::from(&result))
+    }
+
+    fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+pub struct MultiGrepTask {
+    instance: Instance,
+    options: MultiGrepOptions,
+}
+
+#[napi]
+impl Task for MultiGrepTask {
+    type Output = JsGrepResult;
+    type JsValue = JsGrepResult;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let options = &self.options;
+        let picker = self
+            .instance
+            .picker
+            .snapshot()
+            .ok_or_else(|| error("File picker not initialized"))?;
+
+        let constraints = options
+            .constraints
+            .as_deref()
+            .filter(|constraints| !constraints.is_empty())
+            .map(|constraints| {
+                if picker.mode().is_ai() {
+                    QueryParser::new(fff_query_parser::AiGrepConfig).parse(constraints)
+                } else {
+                    fff::grep::parse_grep_query(constraints)
+                }
+            });
+        let constraint_refs: &[fff::Constraint<'_>] = match &constraints {
+            Some(parsed) => &parsed.constraints,
+            None => &[],
+        };
+        let patterns: Vec<&str> = options.patterns.iter().map(String::as_str).collect();
+
+        let result = fff::multi_grep_search(
+            picker.get_files(),
+            &patterns,
+            constraint_refs,
+            &fff::GrepSearchOptions {
+                max_file_size: options.max_file_size.unwrap_or(10 * 1024 * 1024) as u64,
+                max_matches_per_file: options.max_matches_per_file.unwrap_or(0) as usize,
+                smart_case: options.smart_case.unwrap_or(true),
+                file_offset: options.file_offset.unwrap_or(0) as usize,
+                page_limit: options.page_limit.unwrap_or(50) as usize,
+                mode: fff::GrepMode::PlainText, // ignored by multi_grep_search
+                time_budget_ms: options.time_budget_ms.unwrap_or(0) as u64,
+                before_context: options.before_context.unwrap_or(0) as usize,
+                after_context: options.after_context.unwrap_or(0) as usize,
+                classify_definitions: options.classify_definitions.unwrap_or(false),
+                file_order: Default::default(),
+                scope: Default::default(),
+                changed_lines: None,
+                resume_after_line: 0,
+                exact_page_limit: false,
+                dedupe_lines: false,
+                history_boost: None,
+            },
+            picker.cache_budget(),
+            None,
+        );
+
+        Ok(JsGrepResult::from(&result))
+    }
+
+    fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+pub struct WaitForScanTask {
+    picker: SharedPicker,
+    timeout: Duration,
+}
+
+#[napi]
+impl Task for WaitForScanTask {
+    type Output = bool;
+    type JsValue = bool;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        Ok(self.picker.wait_for_scan(self.timeout))
+    }
+
+    fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
@@ -0,0 +1,351 @@
+//! JavaScript facing option and result objects.
+//!
+//! Field names are converted to camelCase by napi. Numbers that can exceed
+//! 32 bits (sizes, timestamps, byte offsets) are exposed as `i64`, which
+//! arrives in JavaScript as a plain `number`.
+
+use fff::git::{format_git_status, format_git_status_short};
+use fff::{FileItem, GrepMatch, Location, Score};
+use napi_derive::napi;
+
+#[napi(object)]
+pub struct InitOptions {
+    /// Directory to index.
+    pub base_path: String,
+    /// Frecency database directory, omit to disable frecency.
+    pub frecency_db_path: Option<String>,
+    /// Query history database directory, omit to disable history.
+    pub history_db_path: Option<String>,
+    pub use_unsafe_no_lock: Option<bool>,
+    /// Pre-populate mmap caches after the initial scan so the first grep is
+    /// as fast as later ones.
+    pub warmup_mmap_cache: Option<bool>,
+    /// Enable the AI agent optimizations (frecency tracked on modifications).
+    pub ai_mode: Option<bool>,
+    /// Search and index on a thread pool of this size owned by the instance
+    /// (0 for one thread per CPU) instead of the process wide rayon pool.
+    pub thread_pool_size: Option<u32>,
+    /// Never write anything: the databases are not opened and the file
+    /// operations fail.
+    pub read_only: Option<bool>,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SearchOptions {
+    /// Currently open file, ranked lower.
+    pub current_file: Option<String>,
+    /// Worker threads, 0 or omitted to auto-detect.
+    pub max_threads: Option<u32>,
+    pub page_index: Option<u32>,
+    /// Results per page, 100 by default.
+    pub page_size: Option<u32>,
+    pub combo_boost_multiplier: Option<i32>,
+    pub min_combo_count: Option<u32>,
+    pub max_typos: Option<u16>,
+    pub adaptive_typos_threshold: Option<u32>,
+    /// Only return files under this directory, absolute or relative to the
+    /// indexed directory.
+    pub search_root: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct GrepOptions {
+    /// `"plain"` (default), `"regex"` or `"fuzzy"`.
+    pub mode: Option<String>,
+    pub max_file_size: Option<i64>,
+    pub max_matches_per_file: Option<u32>,
+    pub smart_case: Option<bool>,
+    pub file_offset: Option<u32>,
+    pub page_limit: Option<u32>,
+    pub time_budget_ms: Option<i64>,
+    pub before_context: Option<u32>,
+    pub after_context: Option<u32>,
+    pub classify_definitions: Option<bool>,
+    /// `"frecency"` (default), `"mtime"`, `"path"` or `"git_changed_first"`.
+    pub file_order: Option<String>,
+    /// `"all"` (default), `"code"`, `"comments"` or `"strings"`.
+    pub scope: Option<String>,
+    /// Only grep the lines of uncommitted git hunks.
+    pub changed_only: Option<bool>,
+    /// `nextPage` of the previous page, replaces `fileOffset`.
+    pub page_token: Option<String>,
+    /// End the page at `pageLimit` matches, even inside a file.
+    pub exact_page_limit: Option<bool>,
+    /// Keep one match of the lines that read the same, within a page.
+    pub dedupe_lines: Option<bool>,
+}
+
+#[napi(object)]
+pub struct MultiGrepOptions {
+    /// Lines matching any of these patterns are returned.
+    pub patterns: Vec<String>,
+    /// File filter such as `"*.rs"` or `"/src/"`.
+    pub constraints: Option<String>,
+    pub max_file_size: Option<i64>,
+    pub max_matches_per_file: Option<u32>,
+    pub smart_case: Option<bool>,
+    pub file_offset: Option<u32>,
+    pub page_limit: Option<u32>,
+    pub time_budget_ms: Option<i64>,
+    pub before_context: Option<u32>,
+    pub after_context: Option<u32>,
+    pub classify_definitions: Option<bool>,
+}
+
+#[napi(object, js_name = "FileItem")]
+pub struct JsFileItem {
+    pub path: String,
+    pub relative_path: String,
+    pub file_name: String,
+    pub git_status: String,
+    /// `git status --short` style indicator, empty when clean.
+    pub git_status_short: String,
+    pub size: i64,
+    pub modified: i64,
+    pub access_frecency_score: i32,
+    pub modification_frecency_score: i32,
+    pub total_frecency_score: i32,
+    pub is_binary: bool,
+}
+
+impl From<&FileItem> for JsFileItem {
+    fn from(item: &FileItem) -> Self {
+        Self {
+            path: item.path().to_string_lossy().into_owned(),
+            relative_path: item.relative_path().to_string(),
+            file_name: item.file_name().to_string(),
+            git_status: format_git_status(item.git_status).to_string(),
+            git_status_short: format_git_status_short(item.git_status).to_string(),
+            size: item.size as i64,
+            modified: item.modified as i64,
+            access_frecency_score: item.access_frecency_score,
+            modification_frecency_score: item.modification_frecency_score,
+            total_frecency_score: item.total_frecency_score,
+            is_binary: item.is_binary,
+        }
+    }
+}
+
+#[napi(object, js_name = "Score")]
+pub struct JsScore {
+    pub total: i32,
+    pub base_score: i32,
+    pub filename_bonus: i32,
+    pub special_filename_bonus: i32,
+    pub frecency_boost: i32,
+    pub distance_penalty: i32,
+    pub current_file_penalty: i32,
+    pub combo_match_boost: i32,
+    pub exact_match: bool,
+    pub match_type: String,
+}
+
+impl From<&Score> for JsScore {
+    fn from(score: &Score) -> Self {
+        Self {
+            total: score.total,
+            base_score: score.base_score,
+            filename_bonus: score.filename_bonus,
+            special_filename_bonus: score.special_filename_bonus,
+            frecency_boost: score.frecency_boost,
+            distance_penalty: score.distance_penalty,
+            current_file_penalty: score.current_file_penalty,
+            combo_match_boost: score.combo_match_boost,
+            exact_match: score.exact_match,
+            match_type: score.match_type.to_string(),
+        }
+    }
+}
+
+/// Location parsed from the query, e.g. `file.ts:42:10`. `endLine` and
+/// `endCol` are only set for ranges.
+#[napi(object, js_name = "Location")]
+pub struct JsLocation {
+    pub line: i32,
+    pub col: Option<i32>,
+    pub end_line: Option<i32>,
+    pub end_col: Option<i32>,
+}
+
+impl From<&Location> for JsLocation {
+    fn from(location: &Location) -> Self {
+        match *location {
+            Location::Line(line) => Self {
+                line,
+                col: None,
+                end_line: None,
+                end_col: None,
+            },
+            Location::Position { line, col } => Self {
+                line,
+                col: Some(col),
+                end_line: None,
+                end_col: None,
+            },
+            Location::Range { start, end } => Self {
+                line: start.0,
+                col: Some(start.1),
+                end_line: Some(end.0),
+                end_col: Some(end.1),
+            },
+        }
+    }
+}
+
+#[napi(object, js_name = "SearchResult")]
+pub struct JsSearchResult {
+    pub items: Vec<JsFileItem>,
+    pub scores: Vec<JsScore>,
+    pub total_matched: u32,
+    pub total_files: u32,
+    pub location: Option<JsLocation>,
+    pub exact_matched: u32,
+    pub typo_matched: u32,
+    pub max_typos: u32,
+    /// "Did you mean" query when nothing matched.
+    pub suggestion: Option<String>,
+}
+
+impl From<&fff::SearchResult<'_>> for JsSearchResult {
+    fn from(result: &fff::SearchResult<'_>) -> Self {
+        Self {
+            items: result
+                .items
+                .iter()
+                .map(|item| JsFileItem::from(*item))
+                .collect(),
+            scores: result.scores.iter().map(JsScore::from).collect(),
+            total_matched: result.total_matched as u32,
+            total_files: result.total_files as u32,
+            location: result.location.as_ref().map(JsLocation::from),
+            exact_matched: result.match_stats.exact_matched as u32,
+            typo_matched: result.match_stats.typo_matched as u32,
+            max_typos: result.match_stats.max_typos as u32,
+            suggestion: result.suggestion.clone(),
+        }
+    }
+}
+
+#[napi(object, js_name = "GrepMatch")]
+pub struct JsGrepMatch {
+    pub path: String,
+    pub relative_path: String,
+    pub file_name: String,
+    pub git_status: String,
+    pub size: i64,
+    pub modified: i64,
+    pub total_frecency_score: i32,
+    pub is_binary: bool,
+    /// 1-based line number.
+    pub line_number: i64,
+    /// 0-based byte column of the first match.
+    pub col: u32,
+    pub byte_offset: i64,
+    pub line_content: String,
+    /// `[start, end)` byte ranges of the matches within `lineContent`.
+    pub match_ranges: Vec<Vec<u32>>,
+    /// `[group, start, end]` spans of the regex capture groups.
+    pub capture_ranges: Vec<Vec<u32>>,
+    pub fuzzy_score: Option<u32>,
+    pub is_definition: bool,
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
+    /// Matches with the same line folded into this one by `dedupeLines`.
+    pub duplicates: u32,
+}
+
+impl JsGrepMatch {
+    fn new(m: &GrepMatch, file: &FileItem) -> Self {
+        Self {
+            path: file.path().to_string_lossy().into_owned(),
+            relative_path: file.relative_path().to_string(),
+            file_name: file.file_name().to_string(),
+            git_status: format_git_status(file.git_status).to_string(),
+            size: file.size as i64,
+            modified: file.modified as i64,
+            total_frecency_score: file.total_frecency_score,
+            is_binary: file.is_binary,
+            line_number: m.line_number as i64,
+            col: m.col as u32,
+            byte_offset: m.byte_offset as i64,
+            line_content: m.line_content.clone(),
+            match_ranges: m
+                .match_byte_offsets
+                .iter()
+                .map(|&(start, end)| vec![start, end])
+                .collect(),
+            capture_ranges: m
+                .captures
+                .iter()
+                .map(|c| vec![c.group, c.start, c.end])
+                .collect(),
+            fuzzy_score: m.fuzzy_score.map(u32::from),
+            is_definition: m.is_definition,
+            context_before: m.context_before.clone(),
+            context_after: m.context_after.clone(),
+            duplicates: m.duplicates,
+        }
+    }
+}
+
+#[napi(object, js_name = "GrepResult")]
+pub struct JsGrepResult {
+    pub items: Vec<JsGrepMatch>,
+    /// Files an empty query lists, which has no `items`.
+    pub file_entries: Vec<JsFileItem>,
+    pub total_matched: u32,
+    pub total_files_searched: u32,
+    pub total_files: u32,
+    pub filtered_file_count: u32,
+    /// File offset of the next page, 0 when every file was searched.
+    pub next_file_offset: u32,
+    /// Token to pass as `pageToken` for the next page.
+    pub next_page: Option<String>,
+    /// Why the regex fell back to a literal search.
+    pub regex_fallback_error: Option<String>,
+}
+
+impl From<&fff::GrepResult<'_>> for JsGrepResult {
+    fn from(result: &fff::GrepResult<'_>) -> Self {
+        Self {
+            items: result
+                .matches
+                .iter()
+                .map(|m| JsGrepMatch::new(m, result.files[m.file_index]))
+                .collect(),
+            file_entries: result
+                .file_entries
+                .iter()
+                .map(|&file| JsFileItem::from(file))
+                .collect(),
+            total_matched: result.matches.len() as u32,
+            total_files_searched: result.total_files_searched as u32,
+            total_files: result.total_files as u32,
+            filtered_file_count: result.filtered_file_count as u32,
+            next_file_offset: result.next_file_offset as u32,
+            next_page: result.next_page.map(|token| token.to_string()),
+            regex_fallback_error: result.regex_fallback_error.clone(),
+        }
+    }
+}
+
+#[napi(object, js_name = "ScanProgress")]
+pub struct JsScanProgress {
+    pub scanned_files_count: u32,
+    pub is_scanning: bool,
+    pub is_watcher_ready: bool,
+    pub is_warmup_complete: bool,
+}
+
+impl From<fff::file_picker::ScanProgress> for JsScanProgress {
+    fn from(progress: fff::file_picker::ScanProgress) -> Self {
+        Self {
+            scanned_files_count: progress.scanned_files_count as u32,
+            is_scanning: progress.is_scanning,
+            is_watcher_ready: progress.is_watcher_ready,
+            is_warmup_complete: progress.is_warmup_complete,
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
 use fff::file_picker::{FFFMode, FilePicker};
-use fff::types::{ContentCacheBudget, FileItem, PaginationArgs};
+use fff::types::{ContentCacheBudget, PaginationArgs};
 use fff::{
-    FilePickerOptions, FuzzySearchOptions, GrepMode, GrepSearchOptions, QueryParser,
+    FileList, FilePickerOptions, FuzzySearchOptions, GrepMode, GrepSearchOptions, QueryParser,
     SharedFrecency, SharedPicker, build_bigram_index, grep,
 };
 use std::path::PathBuf;
@@ -105,12 +105,12 @@ fn wait_for_scan_completion(
 }
 
 /// Get files from the shared picker
-fn get_files_snapshot(shared_picker: &SharedPicker) -> Result<Vec<FileItem>, String> {
+fn get_files_snapshot(shared_picker: &SharedPicker) -> Result<FileList, String> {
     let picker_guard = shared_picker
         .read()
         .map_err(|_| "Failed to acquire read lock")?;
     if let Some(ref picker) = *picker_guard {
-        Ok(picker.get_files().to_vec())
+        Ok(picker.get_files().clone())
     } else {
         Err("FilePicker not initialized".to_string())
     }
@@ -126,7 +126,7 @@ fn cleanup_shared_state(shared_picker: &SharedPicker) {
 }
 
 /// Initialize FilePicker once and return files snapshot
-fn setup_once() -> Result<(Vec<FileItem>, SharedPicker, SharedFrecency), String> {
+fn setup_once() -> Result<(FileList, SharedPicker, SharedFrecency), String> {
     init_tracing();
 
     let big_repo_path = PathBuf::from("./big-repo");
@@ -248,10 +248,25 @@ fn bench_search_queries(c: &mut Criterion) {
 
                         combo_boost_score_multiplier: 100,
                         min_combo_count: 3,
+                        min_combo_prefix_len: 0,
                         pagination: PaginationArgs {
                             offset: 0,
                             limit: 100,
                         },
+                        max_typos: None,
+                        adaptive_typos_threshold: 0,
+                        scoring_weights: Default::default(),
+                        extension_multipliers: None,
+                        project_profile: None,
+                        search_root: None,
+                        facets: false,
+                        min_score: None,
+                        min_score_ratio: 0.0,
+                        with_cursor: false,
+                        cursor: None,
+                        index_generation: 0,
+                        working_set: None,
+                        scratch: None,
                     },
                 );
                 results.total_matched
@@ -297,10 +312,25 @@ fn bench_search_thread_scaling(c: &mut Criterion) {
 
                             combo_boost_score_multiplier: 100,
                             min_combo_count: 3,
+                            min_combo_prefix_len: 0,
                             pagination: PaginationArgs {
                                 offset: 0,
                                 limit: 100,
                             },
+                            max_typos: None,
+                            adaptive_typos_threshold: 0,
+                            scoring_weights: Default::default(),
+                            extension_multipliers: None,
+                            project_profile: None,
+                            search_root: None,
+                            facets: false,
+                            min_score: None,
+                            min_score_ratio: 0.0,
+                            with_cursor: false,
+                            cursor: None,
+                            index_generation: 0,
+                            working_set: None,
+                            scratch: None,
                         },
                     );
                     results.total_matched
@@ -344,10 +374,25 @@ fn bench_search_result_limits(c: &mut Criterion) {
 
                         combo_boost_score_multiplier: 100,
                         min_combo_count: 3,
+                        min_combo_prefix_len: 0,
                         pagination: PaginationArgs {
                             offset: 0,
                             limit: limit,
                         },
+                        max_typos: None,
+                        adaptive_typos_threshold: 0,
+                        scoring_weights: Default::default(),
+                        extension_multipliers: None,
+                        project_profile: None,
+                        search_root: None,
+                        facets: false,
+                        min_score: None,
+                        min_score_ratio: 0.0,
+                        with_cursor: false,
+                        cursor: None,
+                        index_generation: 0,
+                        working_set: None,
+                        scratch: None,
                     },
                 );
                 results.total_matched
@@ -389,11 +434,11 @@ fn bench_search_scalability(c: &mut Criterion) {
             continue;
         }
 
-        let subset = &all_files[..count];
+        let subset: FileList = all_files.iter_range(0..count).cloned().collect();
         group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
             b.iter(|| {
                 let results = FilePicker::fuzzy_search(
-                    black_box(subset),
+                    black_box(&subset),
                     black_box(&parsed),
                     None,
                     FuzzySearchOptions {
@@ -403,10 +448,25 @@ fn bench_search_scalability(c: &mut Criterion) {
 
                         combo_boost_score_multiplier: 100,
                         min_combo_count: 3,
+                        min_combo_prefix_len: 0,
                         pagination: PaginationArgs {
                             offset: 0,
                             limit: 100,
                         },
+                        max_typos: None,
+                        adaptive_typos_threshold: 0,
+                        scoring_weights: Default::default(),
+                        extension_multipliers: None,
+                        project_profile: None,
+                        search_root: None,
+                        facets: false,
+                        min_score: None,
+                        min_score_ratio: 0.0,
+                        with_cursor: false,
+                        cursor: None,
+                        index_generation: 0,
+                        working_set: None,
+                        scratch: None,
                     },
                 );
                 results.total_matched
@@ -448,10 +508,25 @@ fn bench_search_ordering(c: &mut Criterion) {
 
                     combo_boost_score_multiplier: 100,
                     min_combo_count: 3,
+                    min_combo_prefix_len: 0,
                     pagination: PaginationArgs {
                         offset: 0,
                         limit: 100,
                     },
+                    max_typos: None,
+                    adaptive_typos_threshold: 0,
+                    scoring_weights: Default::default(),
+                    extension_multipliers: None,
+                    project_profile: None,
+                    search_root: None,
+                    facets: false,
+                    min_score: None,
+                    min_score_ratio: 0.0,
+                    with_cursor: false,
+                    cursor: None,
+                    index_generation: 0,
+                    working_set: None,
+                    scratch: None,
                 },
             );
             results.total_matched
@@ -472,10 +547,25 @@ fn bench_search_ordering(c: &mut Criterion) {
 
                     combo_boost_score_multiplier: 100,
                     min_combo_count: 3,
+                    min_combo_prefix_len: 0,
                     pagination: PaginationArgs {
                         offset: 0,
                         limit: 100,
                     },
+                    max_typos: None,
+                    adaptive_typos_threshold: 0,
+                    scoring_weights: Default::default(),
+                    extension_multipliers: None,
+                    project_profile: None,
+                    search_root: None,
+                    facets: false,
+                    min_score: None,
+                    min_score_ratio: 0.0,
+                    with_cursor: false,
+                    cursor: None,
+                    index_generation: 0,
+                    working_set: None,
+                    scratch: None,
                 },
             );
             results.total_matched
@@ -496,10 +586,25 @@ fn bench_search_ordering(c: &mut Criterion) {
 
                     combo_boost_score_multiplier: 100,
                     min_combo_count: 3,
+                    min_combo_prefix_len: 0,
                     pagination: PaginationArgs {
                         offset: 0,
                         limit: 500,
                     },
+                    max_typos: None,
+                    adaptive_typos_threshold: 0,
+                    scoring_weights: Default::default(),
+                    extension_multipliers: None,
+                    project_profile: None,
+                    search_root: None,
+                    facets: false,
+                    min_score: None,
+                    min_score_ratio: 0.0,
+                    with_cursor: false,
+                    cursor: None,
+                    index_generation: 0,
+                    working_set: None,
+                    scratch: None,
                 },
             );
             results.total_matched
@@ -519,10 +624,25 @@ fn bench_search_ordering(c: &mut Criterion) {
 
                     combo_boost_score_multiplier: 100,
                     min_combo_count: 3,
+                    min_combo_prefix_len: 0,
                     pagination: PaginationArgs {
                         offset: 0,
                         limit: 500,
                     },
+                    max_typos: None,
+                    adaptive_typos_threshold: 0,
+                    scoring_weights: Default::default(),
+                    extension_multipliers: None,
+                    project_profile: None,
+                    search_root: None,
+                    facets: false,
+                    min_score: None,
+                    min_score_ratio: 0.0,
+                    with_cursor: false,
+                    cursor: None,
+                    index_generation: 0,
+                    working_set: None,
+                    scratch: None,
                 },
             );
             results.total_matched
@@ -543,10 +663,25 @@ fn bench_search_ordering(c: &mut Criterion) {
 
                     combo_boost_score_multiplier: 100,
                     min_combo_count: 3,
+                    min_combo_prefix_len: 0,
                     pagination: PaginationArgs {
                         offset: 0,
                         limit: 10,
                     },
+                    max_typos: None,
+                    adaptive_typos_threshold: 0,
+                    scoring_weights: Default::default(),
+                    extension_multipliers: None,
+                    project_profile: None,
+                    search_root: None,
+                    facets: false,
+                    min_score: None,
+                    min_score_ratio: 0.0,
+                    with_cursor: false,
+                    cursor: None,
+                    index_generation: 0,
+                    working_set: None,
+                    scratch: None,
                 },
             );
             results.total_matched
@@ -566,10 +701,25 @@ fn bench_search_ordering(c: &mut Criterion) {
 
                     combo_boost_score_multiplier: 100,
                     min_combo_count: 3,
+                    min_combo_prefix_len: 0,
                     pagination: PaginationArgs {
                         offset: 0,
                         limit: 10,
                     },
+                    max_typos: None,
+                    adaptive_typos_threshold: 0,
+                    scoring_weights: Default::default(),
+                    extension_multipliers: None,
+                    project_profile: None,
+                    search_root: None,
+                    facets: false,
+                    min_score: None,
+                    min_score_ratio: 0.0,
+                    with_cursor: false,
+                    cursor: None,
+                    index_generation: 0,
+                    working_set: None,
+                    scratch: None,
                 },
             );
             results.total_matched
@@ -611,10 +761,25 @@ fn bench_pagination_performance(c: &mut Criterion) {
 
                     combo_boost_score_multiplier: 100,
                     min_combo_count: 3,
+                    min_combo_prefix_len: 0,
                     pagination: PaginationArgs {
                         offset: 0,
                         limit: page_size,
                     },
+                    max_typos: None,
+                    adaptive_typos_threshold: 0,
+                    scoring_weights: Default::default(),
+                    extension_multipliers: None,
+                    project_profile: None,
+                    search_root: None,
+                    facets: false,
+                    min_score: None,
+                    min_score_ratio: 0.0,
+                    with_cursor: false,
+                    cursor: None,
+                    index_generation: 0,
+                    working_set: None,
+                    scratch: None,
                 },
             );
             results.total_matched
@@ -635,10 +800,25 @@ fn bench_pagination_performance(c: &mut Criterion) {
 
                     combo_boost_score_multiplier: 100,
                     min_combo_count: 3,
+                    min_combo_prefix_len: 0,
                     pagination: PaginationArgs {
                         offset: 10,
                         limit: page_size,
                     },
+                    max_typos: None,
+                    adaptive_typos_threshold: 0,
+                    scoring_weights: Default::default(),
+                    extension_multipliers: None,
+                    project_profile: None,
+                    search_root: None,
+                    facets: false,
+                    min_score: None,
+                    min_score_ratio: 0.0,
+                    with_cursor: false,
+                    cursor: None,
+                    index_generation: 0,
+                    working_set: None,
+                    scratch: None,
                 },
             );
             results.total_matched
@@ -659,10 +839,25 @@ fn bench_pagination_performance(c: &mut Criterion) {
 
                     combo_boost_score_multiplier: 100,
                     min_combo_count: 3,
+                    min_combo_prefix_len: 0,
                     pagination: PaginationArgs {
                         offset: 50,
                         limit: page_size,
                     },
+                    max_typos: None,
+                    adaptive_typos_threshold: 0,
+                    scoring_weights: Default::default(),
+                    extension_multipliers: None,
+                    project_profile: None,
+                    search_root: None,
+                    facets: false,
+                    min_score: None,
+                    min_score_ratio: 0.0,
+                    with_cursor: false,
+                    cursor: None,
+                    index_generation: 0,
+                    working_set: None,
+                    scratch: None,
                 },
             );
             results.total_matched
@@ -707,6 +902,13 @@ fn bench_grep_search(c: &mut Criterion) {
         before_context: 0,
         after_context: 0,
         classify_definitions: false,
+        file_order: Default::default(),
+        scope: Default::default(),
+        changed_lines: None,
+        resume_after_line: 0,
+        exact_page_limit: false,
+        dedupe_lines: false,
+        history_boost: None,
     };
 
     let test_queries = vec![
@@ -175,6 +175,7 @@ fn bench_realistic_workload(c: &mut Criterion) {
                                     black_box(&entry.query),
                                     black_box(&entry.project_path),
                                     3,
+                                    0,
                                 )
                                 .unwrap(),
                         );
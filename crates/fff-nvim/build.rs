@@ -0,0 +1,83 @@
+fn main() {
+    // Embed the toolchain and target for `:checkhealth fff`, most "module
+    // not found" reports come from a binary built for another platform or
+    // with an old compiler.
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = std::process::Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=FFF_RUSTC_VERSION={}", rustc_version);
+    println!(
+        "cargo:rustc-env=FFF_BUILD_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+    println!(
+        "cargo:rustc-env=FFF_BUILD_PROFILE={}",
+        std::env::var("PROFILE").unwrap_or_default()
+    );
+
+    let lib = std::fs::read_to_string("src/lib.rs").expect("failed to read src/lib.rs");
+    println!("cargo:rustc-env=FFF_ABI_HASH={}", abi_hash(&lib));
+
+    let hash = std::process::Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=FFF_GIT_HASH={}", hash);
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=../../.git/HEAD");
+    // HEAD only changes on checkout, a commit moves the branch it points to
+    if let Some(branch) = std::fs::read_to_string("../../.git/HEAD")
+        .ok()
+        .and_then(|head| Some(head.strip_prefix("ref: ")?.trim().to_string()))
+    {
+        let loose = format!("../../.git/{branch}");
+        if std::path::Path::new(&loose).exists() {
+            println!("cargo:rerun-if-changed={loose}");
+        } else {
+            println!("cargo:rerun-if-changed=../../.git/packed-refs");
+        }
+    }
+    println!("cargo:rerun-if-env-changed=RUSTC");
+}
+
+/// FNV-1a of `LUA_ABI_VERSION` and the sorted names passed to
+/// `exports.set(` in `lib.rs`, the functions the Lua side can call. The
+/// version covers their arguments and results, which the names don't.
+/// `lua/fff/utils/abi.lua` computes the same hash from the checked-out
+/// sources, keep both in sync.
+fn abi_hash(lib: &str) -> String {
+    let version = lib
+        .split("const LUA_ABI_VERSION: u32 = ")
+        .nth(1)
+        .and_then(|rest| rest.split(';').next())
+        .expect("LUA_ABI_VERSION is missing from src/lib.rs");
+    let mut names: Vec<&str> = lib
+        .split("exports.set(")
+        .skip(1)
+        .filter_map(|rest| {
+            let rest = rest.trim_start().strip_prefix('"')?;
+            rest.split('"').next()
+        })
+        .collect();
+    names.sort_unstable();
+
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in format!("{version}\n{}", names.join("\n")).bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    format!("{hash:08x}")
+}
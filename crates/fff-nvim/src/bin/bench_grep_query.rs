@@ -18,7 +18,7 @@ fn fmt_dur(us: u128) -> String {
     }
 }
 
-fn run_grep(files: &[fff::FileItem], index: Option<&fff::BigramFilter>, query: &str, iters: usize) {
+fn run_grep(files: &fff::FileList, index: Option<&fff::BigramFilter>, query: &str, iters: usize) {
     let options = GrepSearchOptions {
         max_file_size: 10 * 1024 * 1024,
         max_matches_per_file: 200,
@@ -30,6 +30,13 @@ fn run_grep(files: &[fff::FileItem], index: Option<&fff::BigramFilter>, query: &
         before_context: 0,
         after_context: 0,
         classify_definitions: false,
+        file_order: Default::default(),
+        scope: Default::default(),
+        changed_lines: None,
+        resume_after_line: 0,
+        exact_page_limit: false,
+        dedupe_lines: false,
+        history_boost: None,
     };
 
     let parsed = parse_grep_query(query);
@@ -70,7 +77,7 @@ fn run_grep(files: &[fff::FileItem], index: Option<&fff::BigramFilter>, query: &
     }
 }
 
-fn build_bigram(files: &mut [fff::FileItem]) -> fff::BigramFilter {
+fn build_bigram(files: &mut fff::FileList) -> fff::BigramFilter {
     let budget = ContentCacheBudget::default();
     let (index, binary_indices) = fff::build_bigram_index(files, &budget);
 
@@ -124,7 +131,7 @@ fn main() {
     // ── 1. Scan files ──────────────────────────────────────────────────
     eprint!("[1/3] Scanning files... ");
     let t = Instant::now();
-    let mut files = fff::scan_files(&canonical);
+    let mut files: fff::FileList = fff::scan_files(&canonical).into();
     let non_binary = files.iter().filter(|f| !f.is_binary).count();
     eprintln!(
         "{} files in {:.2}s ({} non-binary)",
@@ -1,6 +1,6 @@
 /// Simple search profiler that directly uses scan_filesystem without background thread overhead
 use fff::file_picker::FilePicker;
-use fff::{FileItem, FuzzySearchOptions, PaginationArgs, QueryParser};
+use fff::{FileItem, FileList, FuzzySearchOptions, PaginationArgs, QueryParser};
 use std::time::Instant;
 
 fn main() {
@@ -22,7 +22,7 @@ fn main() {
     let start = Instant::now();
     let files = {
         use ignore::WalkBuilder;
-        let mut files = Vec::new();
+        let mut files = FileList::new();
 
         WalkBuilder::new(&canonical_path)
             .hidden(false)
@@ -95,10 +95,25 @@ fn main() {
                     project_path: None,
                     combo_boost_score_multiplier: 100,
                     min_combo_count: 3,
+                    min_combo_prefix_len: 0,
                     pagination: PaginationArgs {
                         offset: 0,
                         limit: 100,
                     },
+                    max_typos: None,
+                    adaptive_typos_threshold: 0,
+                    scoring_weights: Default::default(),
+                    extension_multipliers: None,
+                    project_profile: None,
+                    search_root: None,
+                    facets: false,
+                    min_score: None,
+                    min_score_ratio: 0.0,
+                    with_cursor: false,
+                    cursor: None,
+                    index_generation: 0,
+                    working_set: None,
+                    scratch: None,
                 },
             );
             match_count += results.total_matched;
@@ -1,4 +1,3 @@
-use fff::FileItem;
 /// Fuzzy grep quality test against ~/dev/lightsource
 ///
 /// Runs queries through the fuzzy grep pipeline and prints results
@@ -8,14 +7,15 @@ use fff::FileItem;
 ///   cargo run --release --bin fuzzy_grep_test              # runs default test queries
 ///   cargo run --release --bin fuzzy_grep_test -- "query"   # runs a single user query
 use fff::grep::{GrepMode, GrepSearchOptions, grep_search, parse_grep_query};
+use fff::{FileItem, FileList};
 use std::io::Read;
 use std::path::Path;
 use std::time::Instant;
 
-fn load_files(base_path: &Path) -> Vec<FileItem> {
+fn load_files(base_path: &Path) -> FileList {
     use ignore::WalkBuilder;
 
-    let mut files = Vec::new();
+    let mut files = FileList::new();
 
     WalkBuilder::new(base_path)
         .hidden(false)
@@ -62,7 +62,7 @@ fn detect_binary(path: &Path, size: u64) -> bool {
     buf[..n].contains(&0)
 }
 
-fn run_fuzzy_query(files: &[FileItem], query: &str, label: &str) {
+fn run_fuzzy_query(files: &FileList, query: &str, label: &str) {
     let options = GrepSearchOptions {
         max_file_size: 10 * 1024 * 1024,
         max_matches_per_file: 200,
@@ -74,6 +74,13 @@ fn run_fuzzy_query(files: &[FileItem], query: &str, label: &str) {
         before_context: 0,
         after_context: 0,
         classify_definitions: false,
+        file_order: Default::default(),
+        scope: Default::default(),
+        changed_lines: None,
+        resume_after_line: 0,
+        exact_page_limit: false,
+        dedupe_lines: false,
+        history_boost: None,
     };
 
     let parsed = parse_grep_query(query);
@@ -110,7 +117,7 @@ fn run_fuzzy_query(files: &[FileItem], query: &str, label: &str) {
         if m.file_index != current_file_idx {
             current_file_idx = m.file_index;
             let file = &result.files[m.file_index];
-            eprintln!("\n  ┌─ {}", file.relative_path);
+            eprintln!("\n  ┌─ {}", file.relative_path());
         }
 
         // Truncate long lines for display
@@ -10,7 +10,7 @@
 ///   cargo build --release --bin grep_profiler
 ///   ./target/release/grep_profiler [--path /path/to/repo]
 use fff::{
-    BigramFilter, FileItem,
+    BigramFilter, FileItem, FileList,
     grep::{GrepMode, GrepSearchOptions, grep_search, parse_grep_query},
     types::ContentCacheBudget,
 };
@@ -18,10 +18,10 @@ use std::io::Read;
 use std::path::Path;
 use std::time::{Duration, Instant};
 
-fn load_files(base_path: &Path) -> Vec<FileItem> {
+fn load_files(base_path: &Path) -> FileList {
     use ignore::WalkBuilder;
 
-    let mut files = Vec::new();
+    let mut files = FileList::new();
 
     WalkBuilder::new(base_path)
         .hidden(false)
@@ -116,17 +116,17 @@ impl BenchStats {
 }
 
 struct GrepBench<'a> {
-    files: &'a [FileItem],
+    files: &'a FileList,
     options: GrepSearchOptions,
     bigram_index: Option<&'a BigramFilter>,
 }
 
 impl<'a> GrepBench<'a> {
-    fn new(files: &'a [FileItem]) -> Self {
+    fn new(files: &'a FileList) -> Self {
         Self::with_mode(files, GrepMode::PlainText)
     }
 
-    fn with_mode(files: &'a [FileItem], mode: GrepMode) -> Self {
+    fn with_mode(files: &'a FileList, mode: GrepMode) -> Self {
         Self {
             files,
             bigram_index: None,
@@ -141,6 +141,13 @@ impl<'a> GrepBench<'a> {
                 before_context: 0,
                 after_context: 0,
                 classify_definitions: false,
+                file_order: Default::default(),
+                scope: Default::default(),
+                changed_lines: None,
+                resume_after_line: 0,
+                exact_page_limit: false,
+                dedupe_lines: false,
+                history_boost: None,
             },
         }
     }
@@ -184,7 +191,7 @@ impl<'a> GrepBench<'a> {
     }
 }
 
-fn build_bigram(files: &mut [FileItem]) -> BigramFilter {
+fn build_bigram(files: &mut FileList) -> BigramFilter {
     let budget = ContentCacheBudget::default();
     let (index, binary_indices) = fff::build_bigram_index(files, &budget);
 
@@ -500,6 +507,13 @@ fn main() {
             before_context: 0,
             after_context: 0,
             classify_definitions: false,
+            file_order: Default::default(),
+            scope: Default::default(),
+            changed_lines: None,
+            resume_after_line: 0,
+            exact_page_limit: false,
+            dedupe_lines: false,
+            history_boost: None,
         };
         let start = Instant::now();
         let result = grep_search(
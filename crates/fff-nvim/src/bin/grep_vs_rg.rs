@@ -1,5 +1,4 @@
 use fff::FFFQuery;
-use fff::FileItem;
 /// FFF vs ripgrep comparison benchmark
 ///
 /// Demonstrates why a persistent in-process search engine (fff) is fundamentally
@@ -22,6 +21,7 @@ use fff::FileItem;
 ///   cargo build --release --bin grep_vs_rg
 ///   ./target/release/grep_vs_rg [--path /path/to/repo] [--iters 5]
 use fff::grep::{GrepSearchOptions, grep_search, parse_grep_query};
+use fff::{FileItem, FileList};
 use std::io::Read;
 use std::path::Path;
 use std::process::Command;
@@ -30,10 +30,10 @@ use std::time::{Duration, Instant};
 /// Number of times each query is repeated (overridable with --iters).
 const DEFAULT_ITERS: usize = 5;
 
-fn load_files(base_path: &Path) -> Vec<FileItem> {
+fn load_files(base_path: &Path) -> FileList {
     use ignore::WalkBuilder;
 
-    let mut files = Vec::new();
+    let mut files = FileList::new();
     WalkBuilder::new(base_path)
         .hidden(false)
         .git_ignore(true)
@@ -195,7 +195,7 @@ fn bytecount(bytes: &[u8], needle: u8) -> usize {
 }
 
 /// fff full: collects all GrepMatch structs (what the UI uses).
-fn run_fff_full(files: &[FileItem], query: &str) -> (usize, Duration) {
+fn run_fff_full(files: &FileList, query: &str) -> (usize, Duration) {
     let parsed = parse_grep_query(query);
     let options = GrepSearchOptions {
         max_file_size: 10 * 1024 * 1024,
@@ -208,6 +208,13 @@ fn run_fff_full(files: &[FileItem], query: &str) -> (usize, Duration) {
         before_context: 0,
         after_context: 0,
         classify_definitions: false,
+        file_order: Default::default(),
+        scope: Default::default(),
+        changed_lines: None,
+        resume_after_line: 0,
+        exact_page_limit: false,
+        dedupe_lines: false,
+        history_boost: None,
     };
     let start = Instant::now();
     let result = grep_search(
@@ -224,7 +231,7 @@ fn run_fff_full(files: &[FileItem], query: &str) -> (usize, Duration) {
 }
 
 #[allow(dead_code)]
-fn benchmark_fff_smart_case(files: &[FileItem], parsed: &FFFQuery<'_>) -> (usize, Duration) {
+fn benchmark_fff_smart_case(files: &FileList, parsed: &FFFQuery<'_>) -> (usize, Duration) {
     let options = GrepSearchOptions {
         max_file_size: 10 * 1024 * 1024,
         max_matches_per_file: usize::MAX,
@@ -236,6 +243,13 @@ fn benchmark_fff_smart_case(files: &[FileItem], parsed: &FFFQuery<'_>) -> (usize
         before_context: 0,
         after_context: 0,
         classify_definitions: false,
+        file_order: Default::default(),
+        scope: Default::default(),
+        changed_lines: None,
+        resume_after_line: 0,
+        exact_page_limit: false,
+        dedupe_lines: false,
+        history_boost: None,
     };
     let start = Instant::now();
     let result = grep_search(
@@ -252,7 +266,7 @@ fn benchmark_fff_smart_case(files: &[FileItem], parsed: &FFFQuery<'_>) -> (usize
 }
 
 /// fff paginated: first 50 results only (real UI scenario).
-fn run_fff_page(files: &[FileItem], query: &str) -> (usize, Duration) {
+fn run_fff_page(files: &FileList, query: &str) -> (usize, Duration) {
     let parsed = parse_grep_query(query);
     let options = GrepSearchOptions {
         max_file_size: 10 * 1024 * 1024,
@@ -265,6 +279,13 @@ fn run_fff_page(files: &[FileItem], query: &str) -> (usize, Duration) {
         before_context: 0,
         after_context: 0,
         classify_definitions: false,
+        file_order: Default::default(),
+        scope: Default::default(),
+        changed_lines: None,
+        resume_after_line: 0,
+        exact_page_limit: false,
+        dedupe_lines: false,
+        history_boost: None,
     };
     let start = Instant::now();
     let result = grep_search(
@@ -97,10 +97,25 @@ fn test_search_memory_pattern(
                         project_path: None,
                         combo_boost_score_multiplier: 100,
                         min_combo_count: 3,
+                        min_combo_prefix_len: 0,
                         pagination: PaginationArgs {
                             offset: 0,
                             limit: 50 + (i % 50),
                         },
+                        max_typos: None,
+                        adaptive_typos_threshold: 0,
+                        scoring_weights: Default::default(),
+                        extension_multipliers: None,
+                        project_profile: None,
+                        search_root: None,
+                        facets: false,
+                        min_score: None,
+                        min_score_ratio: 0.0,
+                        with_cursor: false,
+                        cursor: None,
+                        index_generation: 0,
+                        working_set: None,
+                        scratch: None,
                     },
                 );
                 (search_result.items.len(), search_result.total_matched)
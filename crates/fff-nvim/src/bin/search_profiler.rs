@@ -1,6 +1,6 @@
 use fff::file_picker::{FFFMode, FilePicker};
 use fff::{
-    FileItem, FuzzySearchOptions, PaginationArgs, QueryParser, SharedFrecency, SharedPicker,
+    FileList, FuzzySearchOptions, PaginationArgs, QueryParser, SharedFrecency, SharedPicker,
 };
 use std::time::{Duration, Instant};
 
@@ -22,12 +22,12 @@ fn wait_for_scan(shared_picker: &SharedPicker, timeout_secs: u64) -> Result<usiz
 }
 
 /// Get files snapshot from shared state
-fn get_files(shared_picker: &SharedPicker) -> Result<Vec<FileItem>, String> {
+fn get_files(shared_picker: &SharedPicker) -> Result<FileList, String> {
     let picker_guard = shared_picker
         .read()
         .map_err(|e| format!("Failed to acquire read lock: {}", e))?;
     if let Some(ref picker) = *picker_guard {
-        Ok(picker.get_files().to_vec())
+        Ok(picker.get_files().clone())
     } else {
         Err("FilePicker not initialized".to_string())
     }
@@ -110,10 +110,25 @@ fn main() {
                     project_path: None,
                     combo_boost_score_multiplier: 100,
                     min_combo_count: 3,
+                    min_combo_prefix_len: 0,
                     pagination: PaginationArgs {
                         offset: 0,
                         limit: 100,
                     },
+                    max_typos: None,
+                    adaptive_typos_threshold: 0,
+                    scoring_weights: Default::default(),
+                    extension_multipliers: None,
+                    project_profile: None,
+                    search_root: None,
+                    facets: false,
+                    min_score: None,
+                    min_score_ratio: 0.0,
+                    with_cursor: false,
+                    cursor: None,
+                    index_generation: 0,
+                    working_set: None,
+                    scratch: None,
                 },
             );
 
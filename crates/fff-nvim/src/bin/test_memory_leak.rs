@@ -144,7 +144,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             if !files.is_empty() {
                 println!("Sample files:");
                 for (i, file) in files.iter().take(5).enumerate() {
-                    println!("  {}. {}", i + 1, file.relative_path);
+                    println!("  {}. {}", i + 1, file.relative_path());
                 }
             }
             files.len()
@@ -210,10 +210,25 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         project_path: None,
                         combo_boost_score_multiplier: 100,
                         min_combo_count: 3,
+                        min_combo_prefix_len: 0,
                         pagination: PaginationArgs {
                             offset: 0,
                             limit: max_results,
                         },
+                        max_typos: None,
+                        adaptive_typos_threshold: 0,
+                        scoring_weights: Default::default(),
+                        extension_multipliers: None,
+                        project_profile: None,
+                        search_root: None,
+                        facets: false,
+                        min_score: None,
+                        min_score_ratio: 0.0,
+                        with_cursor: false,
+                        cursor: None,
+                        index_generation: 0,
+                        working_set: None,
+                        scratch: None,
                     },
                 );
                 let duration = search_start.elapsed();
@@ -68,7 +68,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 println!(
                     "  {}. {} ({})",
                     i + 1,
-                    file.relative_path,
+                    file.relative_path(),
                     format_git_status(file.git_status)
                 );
             }
@@ -110,7 +110,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 let files = guard.as_ref().unwrap().get_files();
                 let newest_files = files.iter().rev().take(added.min(3));
                 for file in newest_files {
-                    println!("   ➕ {}", file.relative_path);
+                    println!("   ➕ {}", file.relative_path());
                 }
             } else {
                 let removed = last_count - current_count;
@@ -164,10 +164,25 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     project_path: None,
                     combo_boost_score_multiplier: 100,
                     min_combo_count: 3,
+                    min_combo_prefix_len: 0,
                     pagination: PaginationArgs {
                         offset: 0,
                         limit: 5,
                     },
+                    max_typos: None,
+                    adaptive_typos_threshold: 0,
+                    scoring_weights: Default::default(),
+                    extension_multipliers: None,
+                    project_profile: None,
+                    search_root: None,
+                    facets: false,
+                    min_score: None,
+                    min_score_ratio: 0.0,
+                    with_cursor: false,
+                    cursor: None,
+                    index_generation: 0,
+                    working_set: None,
+                    scratch: None,
                 },
             );
 
@@ -186,7 +201,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 println!(
                     "   {}. {} (score: {})",
                     i + 1,
-                    file.relative_path,
+                    file.relative_path(),
                     score.total
                 );
             }
@@ -0,0 +1,172 @@
+//! Pre-rendered display columns for search results.
+//!
+//! When the picker asks for them, every result row is shipped with the
+//! already-shortened directory, the file name, the full parent directory and
+//! the frecency badge. The columns are computed on the rayon pool before the
+//! Lua tables are built, so rendering a large page only concatenates strings.
+
+use crate::icons::IconMapping;
+use crate::path_shortening::{PathShortenStrategy, shorten_path_with_cache};
+use fff::FileItem;
+use mlua::prelude::*;
+use rayon::prelude::*;
+use std::path::Path;
+
+/// The picker never shrinks the path column below this width, even when the
+/// window is narrower.
+const MIN_AVAILABLE_WIDTH: usize = 40;
+
+pub struct DisplayOptions {
+    /// Width of the list text area, icon included.
+    pub width: usize,
+    pub strategy: PathShortenStrategy,
+}
+
+impl FromLua for DisplayOptions {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let table = LuaTable::from_lua(value, lua)?;
+        let strategy = table
+            .get::<Option<String>>("path_shorten_strategy")?
+            .map(|name| PathShortenStrategy::from_name(&name))
+            .unwrap_or_default();
+
+        Ok(Self {
+            width: table.get("width")?,
+            strategy,
+        })
+    }
+}
+
+pub struct DisplayColumns {
+    /// Width the columns were fitted into, after the icon was accounted for.
+    pub width: usize,
+    pub filename: String,
+    /// Parent directory shortened to fit next to the file name.
+    pub dir: String,
+    pub parent_dir: String,
+    /// Frecency indicator such as ` 🔥12`, empty when the file has no score.
+    pub score_badge: String,
+}
+
+impl DisplayColumns {
+    fn render(item: &FileItem, icons: Option<&IconMapping>, options: &DisplayOptions) -> Self {
+        let icon_width = icons.map_or(0, |icons| icons.icon_width(item.file_name()));
+        let available_width = options
+            .width
+            .saturating_sub(icon_width)
+            .max(MIN_AVAILABLE_WIDTH);
+
+        let parent_dir = Path::new(item.relative_path())
+            .parent()
+            .map(|parent| parent.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        // filename + " "
+        let path_max_width = available_width.saturating_sub(item.file_name().chars().count() + 1);
+        let dir = if parent_dir.is_empty() || path_max_width == 0 {
+            String::new()
+        } else {
+            shorten_path_with_cache(options.strategy, path_max_width, Path::new(&parent_dir))
+                .unwrap_or_else(|_| {
+                    options
+                        .strategy
+                        .shorten_path(Path::new(&parent_dir), path_max_width)
+                })
+        };
+
+        Self {
+            width: available_width,
+            filename: item.file_name().to_string(),
+            dir,
+            parent_dir,
+            score_badge: score_badge(item),
+        }
+    }
+
+    pub fn into_lua_table(self, lua: &Lua) -> LuaResult<LuaTable> {
+        let table = lua.create_table()?;
+        table.set("width", self.width)?;
+        table.set("filename", self.filename)?;
+        table.set("dir", self.dir)?;
+        table.set("parent_dir", self.parent_dir)?;
+        table.set("score_badge", self.score_badge)?;
+        Ok(table)
+    }
+}
+
+fn score_badge(item: &FileItem) -> String {
+    let total = item.total_frecency_score;
+    if total <= 0 {
+        return String::new();
+    }
+
+    let indicator = if item.modification_frecency_score >= 6 {
+        "🔥"
+    } else if item.access_frecency_score >= 4 {
+        "⭐️"
+    } else if total >= 3 {
+        "✨"
+    } else if total >= 1 {
+        "•"
+    } else {
+        ""
+    };
+    format!(" {indicator}{total}")
+}
+
+pub fn render_columns(
+    items: &[&FileItem],
+    icons: Option<&IconMapping>,
+    options: &DisplayOptions,
+) -> Vec<DisplayColumns> {
+    items
+        .par_iter()
+        .map(|item| DisplayColumns::render(item, icons, options))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn item(relative_path: &str, total_frecency_score: i32) -> FileItem {
+        let mut item = FileItem::new_raw(
+            PathBuf::from("/repo").join(relative_path),
+            relative_path.to_string(),
+            Path::new(relative_path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned(),
+            0,
+            0,
+            None,
+            false,
+        );
+        item.total_frecency_score = total_frecency_score;
+        item
+    }
+
+    #[test]
+    fn renders_columns_for_nested_and_root_files() {
+        let nested = item("crates/fff-core/src/lib.rs", 2);
+        let root = item("README.md", 0);
+        let options = DisplayOptions {
+            width: 80,
+            strategy: PathShortenStrategy::MiddleNumber,
+        };
+
+        let columns = render_columns(&[&nested, &root], None, &options);
+
+        assert_eq!(columns[0].width, 80);
+        assert_eq!(columns[0].filename, "lib.rs");
+        assert_eq!(columns[0].dir, "crates/fff-core/src");
+        assert_eq!(columns[0].parent_dir, "crates/fff-core/src");
+        assert_eq!(columns[0].score_badge, " •2");
+
+        assert_eq!(columns[1].filename, "README.md");
+        assert_eq!(columns[1].dir, "");
+        assert_eq!(columns[1].score_badge, "");
+    }
+}
@@ -0,0 +1,132 @@
+//! Static icon mapping registered from Lua.
+//!
+//! Lua hands over the icon provider's extension/filename tables once, and
+//! search results are serialized with `icon` and `hl_group` already attached,
+//! so the render loop does not have to query the provider for every row.
+
+use mlua::prelude::*;
+use once_cell::sync::Lazy;
+use std::sync::RwLock;
+
+pub struct IconEntry {
+    pub icon: String,
+    pub hl_group: String,
+}
+
+impl FromLua for IconEntry {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let table = LuaTable::from_lua(value, lua)?;
+        Ok(Self {
+            icon: table.get("icon")?,
+            hl_group: table.get("hl_group")?,
+        })
+    }
+}
+
+pub struct IconMapping {
+    by_filename: ahash::AHashMap<String, IconEntry>,
+    by_extension: ahash::AHashMap<String, IconEntry>,
+    /// Only sizes the icon column, files without an entry get their icon
+    /// from the provider in Lua.
+    default: Option<IconEntry>,
+}
+
+impl IconMapping {
+    fn from_lua_table(table: &LuaTable) -> LuaResult<Self> {
+        let read_map = |key: &str| -> LuaResult<ahash::AHashMap<String, IconEntry>> {
+            match table.get::<Option<LuaTable>>(key)? {
+                Some(entries) => entries.pairs::<String, IconEntry>().collect(),
+                None => Ok(Default::default()),
+            }
+        };
+
+        Ok(Self {
+            by_filename: read_map("filenames")?,
+            by_extension: read_map("extensions")?,
+            default: table.get("default")?,
+        })
+    }
+
+    /// Exact file name first, then the longest extension (`spec.ts` before
+    /// `ts`). `None` leaves the icon to the Lua fallback, which also knows
+    /// filetypes detected from the content.
+    pub fn lookup(&self, file_name: &str) -> Option<&IconEntry> {
+        if let Some(entry) = self.by_filename.get(file_name) {
+            return Some(entry);
+        }
+
+        let lowercase = file_name.to_lowercase();
+        if let Some(entry) = self.by_filename.get(&lowercase) {
+            return Some(entry);
+        }
+
+        let mut rest = lowercase.as_str();
+        while let Some(dot) = rest.find('.') {
+            rest = &rest[dot + 1..];
+            if let Some(entry) = self.by_extension.get(rest) {
+                return Some(entry);
+            }
+        }
+
+        None
+    }
+
+    /// Display width of the icon and its separator, assuming the Lua
+    /// fallback is as wide as the default icon.
+    pub fn icon_width(&self, file_name: &str) -> usize {
+        self.lookup(file_name)
+            .or(self.default.as_ref())
+            .map_or(0, |entry| entry.icon.chars().count() + 1)
+    }
+}
+
+pub static ICON_MAPPING: Lazy<RwLock<Option<IconMapping>>> = Lazy::new(|| RwLock::new(None));
+
+/// `set_icon_mapping({ extensions = {...}, filenames = {...}, default = {...} })`
+/// where every entry is `{ icon = string, hl_group = string }`. Pass `nil` to
+/// stop attaching icons.
+pub fn set_icon_mapping(_: &Lua, mapping: Option<LuaTable>) -> LuaResult<()> {
+    let mapping = mapping
+        .map(|table| IconMapping::from_lua_table(&table))
+        .transpose()?;
+
+    let mut guard = ICON_MAPPING
+        .write()
+        .map_err(|_| LuaError::RuntimeError("Icon mapping lock poisoned".into()))?;
+    *guard = mapping;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(icon: &str) -> IconEntry {
+        IconEntry {
+            icon: icon.into(),
+            hl_group: format!("Hl{icon}"),
+        }
+    }
+
+    #[test]
+    fn lookup_prefers_filename_then_longest_extension() {
+        let mapping = IconMapping {
+            by_filename: [("makefile".to_string(), entry("make"))]
+                .into_iter()
+                .collect(),
+            by_extension: [
+                ("ts".to_string(), entry("ts")),
+                ("spec.ts".to_string(), entry("spec")),
+            ]
+            .into_iter()
+            .collect(),
+            default: Some(entry("default")),
+        };
+
+        assert_eq!(mapping.lookup("Makefile").unwrap().icon, "make");
+        assert_eq!(mapping.lookup("app.spec.ts").unwrap().icon, "spec");
+        assert_eq!(mapping.lookup("App.TS").unwrap().icon, "ts");
+        assert!(mapping.lookup("README").is_none());
+        assert_eq!(mapping.icon_width("README"), "default".len() + 1);
+    }
+}
@@ -0,0 +1,74 @@
+//! Index changes pushed to Lua instead of polled.
+//!
+//! `subscribe_index_events()` returns the read end of a pipe that Lua opens
+//! with `vim.uv.new_pipe():open(fd)`. The scan, watcher and git status
+//! threads of the picker add their event to [`PENDING`] and only write a
+//! byte when nothing was pending, so the pipe never holds more than one
+//! unread byte and the background threads never block on a full pipe.
+//! Lua wakes up on the byte and collects the events with
+//! `take_index_events()`.
+
+use crate::FILE_PICKER;
+use fff::IndexEvent;
+use mlua::prelude::*;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static PENDING: AtomicU8 = AtomicU8::new(0);
+
+const EVENTS: [IndexEvent; 3] = [
+    IndexEvent::ScanCompleted,
+    IndexEvent::FilesChanged,
+    IndexEvent::GitStatusRefreshed,
+];
+
+fn bit(event: IndexEvent) -> u8 {
+    match event {
+        IndexEvent::ScanCompleted => 1,
+        IndexEvent::FilesChanged => 2,
+        IndexEvent::GitStatusRefreshed => 4,
+    }
+}
+
+/// `subscribe_index_events()` returns the file descriptor to read the
+/// event notifications from, the same one on every call, or `nil` where
+/// pipes aren't supported and the UI has to poll.
+#[cfg(unix)]
+pub fn subscribe_index_events(_: &Lua, _: ()) -> LuaResult<Option<i32>> {
+    use once_cell::sync::OnceCell;
+    use std::io::Write;
+    use std::os::fd::IntoRawFd;
+
+    static READ_FD: OnceCell<i32> = OnceCell::new();
+
+    READ_FD
+        .get_or_try_init(|| {
+            let (reader, writer) = std::io::pipe()?;
+            FILE_PICKER.on_index_event(move |event| {
+                if PENDING.fetch_or(bit(event), Ordering::AcqRel) == 0
+                    && let Err(error) = (&writer).write_all(&[1])
+                {
+                    tracing::warn!(?error, "Failed to notify index event");
+                }
+            });
+            Ok::<_, std::io::Error>(reader.into_raw_fd())
+        })
+        .map(|fd| Some(*fd))
+        .into_lua_err()
+}
+
+#[cfg(not(unix))]
+pub fn subscribe_index_events(_: &Lua, _: ()) -> LuaResult<Option<i32>> {
+    Ok(None)
+}
+
+/// `take_index_events()` returns the names of the events since the last
+/// call and forgets them.
+pub fn take_index_events(lua: &Lua, _: ()) -> LuaResult<LuaTable> {
+    let pending = PENDING.swap(0, Ordering::AcqRel);
+    lua.create_sequence_from(
+        EVENTS
+            .into_iter()
+            .filter(|&event| pending & bit(event) != 0)
+            .map(IndexEvent::name),
+    )
+}
@@ -2,24 +2,38 @@ use crate::path_shortening::shorten_path_with_cache;
 use error::IntoLuaResult;
 use fff::file_picker::FilePicker;
 use fff::frecency::FrecencyTracker;
+use fff::git::GitStatusProvider;
+use fff::grep_watch::GrepWatch;
 use fff::path_utils::expand_tilde;
 use fff::query_tracker::QueryTracker;
 use fff::{
-    DbHealthChecker, Error, FFFMode, FileSearchConfig, FuzzySearchOptions, PaginationArgs,
-    QueryParser, Score, SearchResult, SharedFrecency, SharedPicker, SharedQueryTracker,
+    DbHealthChecker, DbRepair, Error, ExportedMatch, ExtensionMultipliers, FFFMode, FileOp,
+    FileOpResult, FileSearchConfig, FrecencyStats, FuzzySearchOptions, GrepExportFormat,
+    MatchStats, PaginationArgs, ProjectProfileOptions, QueryParser, Score, SearchResult,
+    SearchScratch, SharedFrecency, SharedPicker, SharedQueryTracker, StorageBackend,
+    StorageOptions, WorkingSetCache, export_grep_matches,
 };
 use mimalloc::MiMalloc;
 use mlua::prelude::*;
 use once_cell::sync::Lazy;
 use path_shortening::PathShortenStrategy;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 
+mod display;
 mod error;
 mod hex_dump;
+mod icons;
+mod index_events;
+mod loader;
 mod log;
 mod lua_types;
 mod path_shortening;
+mod query;
+mod result_window;
+mod runtime_config;
+mod saved_searches;
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
@@ -29,33 +43,124 @@ static GLOBAL: MiMalloc = MiMalloc;
 pub static FILE_PICKER: Lazy<SharedPicker> = Lazy::new(SharedPicker::default);
 pub static FRECENCY: Lazy<SharedFrecency> = Lazy::new(SharedFrecency::default);
 pub static QUERY_TRACKER: Lazy<SharedQueryTracker> = Lazy::new(SharedQueryTracker::default);
+static EXTENSION_MULTIPLIERS: Lazy<RwLock<ExtensionMultipliers>> = Lazy::new(Default::default);
+static FRECENCY_DB_STATUS: Lazy<RwLock<DbOpenStatus>> = Lazy::new(Default::default);
+static QUERY_DB_STATUS: Lazy<RwLock<DbOpenStatus>> = Lazy::new(Default::default);
+/// Files passing the constraints of the last query, reused while typing.
+static WORKING_SET: Lazy<WorkingSetCache> = Lazy::new(WorkingSetCache::new);
+/// Matching vectors reused between the searches of the picker.
+static SEARCH_SCRATCH: Lazy<SearchScratch> = Lazy::new(SearchScratch::new);
+
+/// How opening a database went, reported by the health check.
+#[derive(Debug, Default)]
+struct DbOpenStatus {
+    error: Option<String>,
+    repair: Option<DbRepair>,
+}
+
+/// Open a database, rebuilding it from its readable entries once if it is
+/// corrupted. A database that still can't be opened stays disabled instead of
+/// failing the whole setup.
+fn open_or_repair<T>(
+    name: &str,
+    open: impl Fn() -> fff::Result<T>,
+    repair: impl FnOnce() -> fff::Result<DbRepair>,
+) -> (Option<T>, DbOpenStatus) {
+    let mut status = DbOpenStatus::default();
+    let open_error = match open() {
+        Ok(db) => return (Some(db), status),
+        Err(e) => e,
+    };
+    // e.g. another process holds the redb file, rebuilding would not help
+    if !open_error.is_corruption() {
+        tracing::error!("{name} database is disabled: {open_error}");
+        status.error = Some(open_error.to_string());
+        return (None, status);
+    }
+
+    tracing::warn!("Failed to open {name} database, repairing it: {open_error}");
+    let result = repair().and_then(|report| {
+        status.repair = Some(report);
+        open()
+    });
+    match result {
+        Ok(db) => (Some(db), status),
+        Err(e) => {
+            tracing::error!("{name} database is disabled: {e}");
+            status.error = Some(format!("{open_error}; repair failed: {e}"));
+            (None, status)
+        }
+    }
+}
 
 pub fn init_db(
     _: &Lua,
-    (frecency_db_path, history_db_path, use_unsafe_no_lock): (String, String, bool),
+    (frecency_db_path, history_db_path, use_unsafe_no_lock, storage_backend): (
+        String,
+        String,
+        bool,
+        Option<String>,
+    ),
 ) -> LuaResult<bool> {
+    let backend = match storage_backend {
+        Some(name) => name.parse::<StorageBackend>().into_lua_result()?,
+        None => StorageBackend::default(),
+    };
+    let options = StorageOptions {
+        backend,
+        use_unsafe_no_lock,
+    };
+
     let mut frecency = FRECENCY.write().into_lua_result()?;
-    if frecency.is_some() {
-        *frecency = None;
-    }
-    *frecency =
-        Some(FrecencyTracker::new(&frecency_db_path, use_unsafe_no_lock).into_lua_result()?);
-    tracing::info!("Frecency database initialized at {}", frecency_db_path);
+    *frecency = None;
+    let (tracker, status) = open_or_repair(
+        "frecency",
+        || FrecencyTracker::with_options(&frecency_db_path, options),
+        || FrecencyTracker::repair(&frecency_db_path, backend),
+    );
+    let frecency_ready = tracker.is_some();
+    *frecency = tracker;
+    *FRECENCY_DB_STATUS
+        .write()
+        .map_err(|_| LuaError::RuntimeError("Database status lock poisoned".into()))? = status;
     drop(frecency);
 
-    // Spawn background GC to purge stale entries without blocking startup
-    let _ = FRECENCY.spawn_gc(frecency_db_path, use_unsafe_no_lock);
+    if frecency_ready {
+        tracing::info!("Frecency database initialized at {}", frecency_db_path);
+        // Spawn background GC to purge stale entries without blocking startup
+        let _ = FRECENCY.spawn_gc(frecency_db_path);
+    }
 
     let mut query_tracker = QUERY_TRACKER.write().into_lua_result()?;
-    if query_tracker.is_some() {
-        *query_tracker = None;
-    }
+    *query_tracker = None;
+    let (tracker, status) = open_or_repair(
+        "query history",
+        || QueryTracker::with_options(&history_db_path, options),
+        || QueryTracker::repair(&history_db_path, backend),
+    );
+    let query_tracker_ready = tracker.is_some();
+    *query_tracker = tracker;
+    *QUERY_DB_STATUS
+        .write()
+        .map_err(|_| LuaError::RuntimeError("Database status lock poisoned".into()))? = status;
 
-    *query_tracker =
-        Some(QueryTracker::new(&history_db_path, use_unsafe_no_lock).into_lua_result()?);
+    drop(query_tracker);
 
-    tracing::info!("Query tracker database initialized at {}", history_db_path);
-    Ok(true)
+    if query_tracker_ready {
+        tracing::info!("Query tracker database initialized at {}", history_db_path);
+        let _ = QUERY_TRACKER.spawn_gc();
+    }
+    Ok(frecency_ready && query_tracker_ready)
+}
+
+/// `{ frecency, history }` database paths used when the config has none,
+/// shared with the other frontends.
+pub fn default_db_paths(lua: &Lua, _: ()) -> LuaResult<LuaTable> {
+    let paths = fff::default_db_paths().into_lua_result()?;
+    let table = lua.create_table()?;
+    table.set("frecency", paths.frecency.to_string_lossy().to_string())?;
+    table.set("history", paths.history.to_string_lossy().to_string())?;
+    Ok(table)
 }
 
 pub fn destroy_frecency_db(_: &Lua, _: ()) -> LuaResult<bool> {
@@ -70,14 +175,99 @@ pub fn destroy_query_db(_: &Lua, _: ()) -> LuaResult<bool> {
     Ok(true)
 }
 
-pub fn init_file_picker(_: &Lua, base_path: String) -> LuaResult<bool> {
+/// Scan of a home directory until the user confirms indexing all of it, see
+/// [`broad_root_limits`].
+const BROAD_ROOT_SCAN_LIMITS: fff::ScanLimits = fff::ScanLimits {
+    max_depth: Some(3),
+    max_files: Some(100_000),
+};
+
+/// Limits for a picker on `path`: the restricted scan when it is the home
+/// directory or above and the user hasn't allowed it, none otherwise.
+fn broad_root_limits(path: &Path, allow_broad_root: bool) -> Option<fff::ScanLimits> {
+    (!allow_broad_root && fff::path_utils::is_broad_root(path)).then_some(BROAD_ROOT_SCAN_LIMITS)
+}
+
+/// Returns whether a picker was started, and whether it was restricted to
+/// the top of a home directory and Lua should ask to index all of it.
+pub fn init_file_picker(
+    _: &Lua,
+    (base_path, opts): (String, Option<LuaTable>),
+) -> LuaResult<(bool, bool)> {
     {
         let guard = FILE_PICKER.read().into_lua_result()?;
         if guard.is_some() {
-            return Ok(false);
+            return Ok((false, false));
         }
     }
 
+    let defaults = fff::FilePickerOptions::default();
+    let allow_broad_root = match &opts {
+        Some(opts) => opts
+            .get::<Option<bool>>("allow_broad_root")?
+            .unwrap_or(false),
+        None => false,
+    };
+    let restricted = broad_root_limits(Path::new(&base_path), allow_broad_root);
+    let read_only = match &opts {
+        Some(opts) => opts
+            .get::<Option<bool>>("read_only")?
+            .unwrap_or(defaults.read_only),
+        None => defaults.read_only,
+    };
+    let git_enabled = match &opts {
+        Some(opts) => opts
+            .get::<Option<bool>>("git_enabled")?
+            .unwrap_or(defaults.git_enabled),
+        None => defaults.git_enabled,
+    };
+    let git_status_provider = match &opts {
+        Some(opts) => match opts.get::<Option<String>>("git_status_provider")? {
+            Some(name) => name.parse::<GitStatusProvider>().into_lua_result()?,
+            None => defaults.git_status_provider,
+        },
+        None => defaults.git_status_provider,
+    };
+    let keyword_patterns = match &opts {
+        Some(opts) => keyword_patterns(opts.get("keyword_patterns")?)?,
+        None => defaults.keyword_patterns,
+    };
+    let trigram_index_dir = match &opts {
+        Some(opts) => opts
+            .get::<Option<String>>("trigram_index_dir")?
+            .map(PathBuf::from),
+        None => defaults.trigram_index_dir,
+    };
+    let background_priority = match &opts {
+        Some(opts) => background_priority(opts.get("background_io_priority")?)?,
+        None => defaults.background_priority,
+    };
+    let (use_os_trash, respect_ignore_files, same_file_system, project_profile) = match opts {
+        Some(opts) => (
+            opts.get::<Option<bool>>("use_os_trash")?
+                .unwrap_or(defaults.use_os_trash),
+            opts.get::<Option<bool>>("respect_ignore_files")?
+                .unwrap_or(defaults.respect_ignore_files),
+            opts.get::<Option<bool>>("same_file_system")?
+                .unwrap_or(defaults.same_file_system),
+            match opts.get::<Option<LuaTable>>("project_profile")? {
+                Some(profile) => ProjectProfileOptions {
+                    enabled: profile.get::<Option<bool>>("enabled")?.unwrap_or(true),
+                    deprioritized_dirs: profile.get("deprioritized_dirs")?,
+                    boosted_dirs: profile.get("boosted_dirs")?,
+                },
+                None => defaults.project_profile,
+            },
+        ),
+        None => (
+            defaults.use_os_trash,
+            defaults.respect_ignore_files,
+            defaults.same_file_system,
+            defaults.project_profile,
+        ),
+    };
+
+    let given_path = PathBuf::from(&base_path);
     FilePicker::new_with_shared_state(
         FILE_PICKER.clone(),
         FRECENCY.clone(),
@@ -85,19 +275,101 @@ pub fn init_file_picker(_: &Lua, base_path: String) -> LuaResult<bool> {
             base_path,
             warmup_mmap_cache: true,
             mode: FFFMode::Neovim,
+            use_os_trash,
+            respect_ignore_files,
+            same_file_system,
+            git_enabled,
+            git_status_provider,
+            project_profile,
+            keyword_patterns,
+            trigram_index_dir,
+            background_priority,
+            read_only,
+            // watching every directory of a home is as costly as scanning it
+            watch: restricted.is_none(),
+            scan_limits: restricted.unwrap_or_default(),
             ..Default::default()
         },
     )
     .into_lua_result()?;
+    migrate_query_history(given_path);
 
-    Ok(true)
+    Ok((true, restricted.is_some()))
+}
+
+/// Move the query history recorded under the project path as given to the
+/// normalized path the picker uses, on a background thread like the other
+/// query tracker writes.
+fn migrate_query_history(given_path: PathBuf) {
+    let project_path = match FILE_PICKER.read().as_deref() {
+        Ok(Some(picker)) if picker.base_path() != given_path => picker.base_path().to_path_buf(),
+        _ => return,
+    };
+
+    let query_tracker = QUERY_TRACKER.clone();
+    std::thread::spawn(move || {
+        if let Ok(mut guard) = query_tracker.write()
+            && let Some(tracker) = guard.as_mut()
+            && let Err(e) = tracker.migrate_project_key(&given_path, &project_path)
+        {
+            tracing::error!(error = ?e, "Failed to migrate query history");
+        }
+    });
+}
+
+/// `{ name = pattern }` into `(name, pattern)` pairs sorted by name, Lua
+/// tables have no order.
+fn keyword_patterns(table: Option<LuaTable>) -> LuaResult<Vec<(String, String)>> {
+    let Some(table) = table else {
+        return Ok(Vec::new());
+    };
+    let mut patterns = table
+        .pairs::<String, String>()
+        .collect::<LuaResult<Vec<_>>>()?;
+    patterns.sort();
+    Ok(patterns)
+}
+
+/// `{ max_threads, batch_pause_ms, skip_warmup_on_battery }` into the
+/// picker's [`fff::BackgroundPriority`].
+fn background_priority(table: Option<LuaTable>) -> LuaResult<fff::BackgroundPriority> {
+    let Some(table) = table else {
+        return Ok(Default::default());
+    };
+    let skip_on_battery = table
+        .get::<Option<bool>>("skip_warmup_on_battery")?
+        .unwrap_or(false);
+    Ok(fff::BackgroundPriority {
+        max_parallelism: table.get("max_threads")?,
+        batch_pause: Duration::from_millis(
+            table.get::<Option<u64>>("batch_pause_ms")?.unwrap_or(0),
+        ),
+        skip_warmup: skip_on_battery
+            .then(|| Arc::new(fff::on_battery_power) as Arc<dyn Fn() -> bool + Send + Sync>),
+    })
 }
 
-fn reinit_file_picker_internal(path: &Path) -> Result<(), Error> {
+fn reinit_file_picker_internal(
+    path: &Path,
+    scan_limits: Option<fff::ScanLimits>,
+) -> Result<(), Error> {
     // Cancel and stop the old picker under a single write lock to avoid
     // a window where FILE_PICKER is None (which causes FilePickerMissing
     // errors if the UI is searching concurrently).
-    {
+    let (
+        use_os_trash,
+        respect_ignore_files,
+        same_file_system,
+        git_enabled,
+        git_status_provider,
+        project_profile,
+        keyword_patterns,
+        trigram_index_dir,
+        background_priority,
+        virtual_files,
+        auxiliary_roots,
+        read_only,
+    ) = {
         let mut guard = FILE_PICKER.write()?;
         if let Some(ref mut picker) = *guard {
             // Signal cancellation BEFORE stopping — this tells any orphaned
@@ -107,7 +379,39 @@ fn reinit_file_picker_internal(path: &Path) -> Result<(), Error> {
         }
         // Don't take() here — leave the old picker in place so searches
         // still work until new_with_shared_state replaces it atomically.
-    }
+        guard.as_ref().map_or(
+            (
+                false,
+                true,
+                false,
+                true,
+                Default::default(),
+                Default::default(),
+                Vec::new(),
+                None,
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                false,
+            ),
+            |picker| {
+                (
+                    picker.use_os_trash(),
+                    picker.respect_ignore_files(),
+                    picker.same_file_system(),
+                    picker.git_enabled(),
+                    picker.git_status_provider(),
+                    picker.project_profile_options().clone(),
+                    picker.keyword_patterns().to_vec(),
+                    picker.trigram_index_dir().map(Path::to_path_buf),
+                    picker.background_priority().clone(),
+                    picker.virtual_files().clone(),
+                    picker.auxiliary_roots().clone(),
+                    picker.read_only(),
+                )
+            },
+        )
+    };
 
     // Create new picker — this atomically replaces the old one via write lock
     FilePicker::new_with_shared_state(
@@ -117,6 +421,20 @@ fn reinit_file_picker_internal(path: &Path) -> Result<(), Error> {
             base_path: path.to_string_lossy().to_string(),
             warmup_mmap_cache: true,
             mode: FFFMode::Neovim,
+            use_os_trash,
+            respect_ignore_files,
+            same_file_system,
+            git_enabled,
+            git_status_provider,
+            project_profile,
+            keyword_patterns,
+            trigram_index_dir,
+            background_priority,
+            virtual_files,
+            auxiliary_roots,
+            read_only,
+            watch: scan_limits.is_none(),
+            scan_limits: scan_limits.unwrap_or_default(),
             ..Default::default()
         },
     )?;
@@ -124,7 +442,12 @@ fn reinit_file_picker_internal(path: &Path) -> Result<(), Error> {
     Ok(())
 }
 
-pub fn restart_index_in_path(_: &Lua, new_path: String) -> LuaResult<()> {
+/// Returns whether the new picker is restricted to the top of a home
+/// directory, like [`init_file_picker`].
+pub fn restart_index_in_path(
+    _: &Lua,
+    (new_path, allow_broad_root): (String, Option<bool>),
+) -> LuaResult<bool> {
     let path = std::path::PathBuf::from(&new_path);
     if !path.exists() {
         return Err(LuaError::RuntimeError(format!(
@@ -137,15 +460,17 @@ pub fn restart_index_in_path(_: &Lua, new_path: String) -> LuaResult<()> {
         LuaError::RuntimeError(format!("Failed to canonicalize path '{}': {}", new_path, e))
     })?;
 
+    let scan_limits = broad_root_limits(&canonical_path, allow_broad_root.unwrap_or(false));
     if let Ok(Some(picker)) = FILE_PICKER.read().as_deref()
         && picker.base_path() == canonical_path
+        && picker.scan_limits() == scan_limits.unwrap_or_default()
     {
-        return Ok(()); // same dir
+        return Ok(scan_limits.is_some()); // same dir
     }
 
     // Spawn a background thread to avoid blocking Lua/UI thread
     std::thread::spawn(move || {
-        if let Err(e) = reinit_file_picker_internal(&canonical_path) {
+        if let Err(e) = reinit_file_picker_internal(&canonical_path, scan_limits) {
             ::tracing::error!(
                 ?e,
                 ?canonical_path,
@@ -156,49 +481,57 @@ pub fn restart_index_in_path(_: &Lua, new_path: String) -> LuaResult<()> {
         }
     });
 
-    Ok(())
+    Ok(scan_limits.is_some())
 }
 
 pub fn scan_files(_: &Lua, _: ()) -> LuaResult<()> {
-    let mut file_picker = FILE_PICKER.write().into_lua_result()?;
-    let picker = file_picker
-        .as_mut()
-        .ok_or(Error::FilePickerMissing)
-        .into_lua_result()?;
-
-    picker.trigger_rescan(&FRECENCY).into_lua_result()?;
+    FILE_PICKER.trigger_rescan(&FRECENCY).into_lua_result()?;
     ::tracing::info!("scan_files trigger_rescan completed");
     Ok(())
 }
 
-#[allow(clippy::type_complexity)]
+/// `set_extension_multipliers({ lock = 50, ['min.js'] = 30 })` scales the
+/// fuzzy score of files by suffix, in percent. `nil` clears the table.
+pub fn set_extension_multipliers(
+    _: &Lua,
+    multipliers: Option<std::collections::HashMap<String, i32>>,
+) -> LuaResult<()> {
+    let mut guard = EXTENSION_MULTIPLIERS
+        .write()
+        .map_err(|_| LuaError::RuntimeError("Extension multipliers lock poisoned".into()))?;
+    *guard = ExtensionMultipliers::new(multipliers.unwrap_or_default());
+    Ok(())
+}
+
+/// `fuzzy_search_files(query, offset?, page_size?, opts?)` ranks the indexed
+/// files. Scoring settings come from `set_config`; `opts` carries
+/// `current_file`, `display`, `search_root`, `facets` and per-call
+/// `max_threads` or `min_combo_count` overrides.
 pub fn fuzzy_search_files(
     lua: &Lua,
-    (
-        query,
-        max_threads,
-        current_file,
-        combo_boost_score_multiplier,
-        min_combo_count,
-        page_index,
-        page_size,
-    ): (
+    (query, page_index, page_size, opts): (
         String,
-        usize,
-        Option<String>,
-        i32,
-        Option<u32>,
         Option<usize>,
         Option<usize>,
+        Option<runtime_config::SearchOptions>,
     ),
 ) -> LuaResult<LuaValue> {
-    let file_picker_guard = FILE_PICKER.read().into_lua_result()?;
-    let Some(ref picker) = *file_picker_guard else {
+    let config = runtime_config::current()?;
+    let runtime_config::SearchOptions {
+        current_file,
+        display,
+        search_root,
+        max_threads,
+        min_combo_count,
+        facets,
+    } = opts.unwrap_or_default();
+
+    let Some(picker) = FILE_PICKER.snapshot() else {
         return Err(error::to_lua_error(Error::FilePickerMissing));
     };
 
     let base_path = picker.base_path();
-    let min_combo_count = min_combo_count.unwrap_or(3);
+    let min_combo_count = min_combo_count.unwrap_or(config.min_combo_count);
 
     let query_tracker_guard = QUERY_TRACKER.read().into_lua_result()?;
 
@@ -216,25 +549,95 @@ pub fn fuzzy_search_files(
     );
 
     let parser = QueryParser::new(FileSearchConfig);
-    let parsed = parser.parse(&query);
-
-    let files = picker.get_files();
-    let results = FilePicker::fuzzy_search(
-        files,
-        &parsed,
-        query_tracker_guard.as_ref(),
-        FuzzySearchOptions {
-            max_threads,
-            current_file: current_file.as_deref(),
-            project_path: Some(picker.base_path()),
-            combo_boost_score_multiplier,
-            min_combo_count,
-            pagination: PaginationArgs {
-                offset: page_index.unwrap_or(0),
-                limit: page_size.unwrap_or(0),
+    let mut parsed = parser.parse(&query);
+    picker.exclude_runtime_globs(&mut parsed);
+    if !picker.git_enabled() {
+        // no file has a git status, `status:` would filter out everything
+        parsed
+            .constraints
+            .retain(|constraint| !constraint.is_git_status());
+    }
+
+    let offset = page_index.unwrap_or(0);
+    let limit = page_size.unwrap_or(0);
+    // Rank past the requested page so later windows can be served from the
+    // cache by `fetch_results_window` without rescoring.
+    let ranking_limit = match limit {
+        0 => 0,
+        limit => offset
+            .saturating_add(limit)
+            .max(result_window::MAX_CACHED_RANKING),
+    };
+
+    let extension_multipliers = EXTENSION_MULTIPLIERS
+        .read()
+        .map_err(|_| LuaError::RuntimeError("Extension multipliers lock poisoned".into()))?;
+
+    let files = picker.files_for_query(&parsed);
+    let rank = |files, working_set| {
+        FilePicker::fuzzy_search(
+            files,
+            &parsed,
+            query_tracker_guard.as_ref(),
+            FuzzySearchOptions {
+                max_threads: max_threads.unwrap_or(config.max_threads),
+                current_file: current_file.as_deref(),
+                project_path: Some(picker.base_path()),
+                combo_boost_score_multiplier: config.combo_boost_score_multiplier,
+                min_combo_count,
+                min_combo_prefix_len: config.min_combo_prefix_len,
+                pagination: PaginationArgs {
+                    offset: 0,
+                    limit: ranking_limit,
+                },
+                max_typos: config.max_typos,
+                adaptive_typos_threshold: config.adaptive_typos_threshold,
+                scoring_weights: Default::default(),
+                extension_multipliers: Some(&*extension_multipliers).filter(|m| !m.is_empty()),
+                project_profile: picker.project_profile(),
+                search_root: search_root.as_deref().map(Path::new),
+                facets,
+                min_score: config.min_score,
+                min_score_ratio: config.min_score_ratio,
+                with_cursor: false,
+                cursor: None,
+                index_generation: picker.index_generation(),
+                working_set,
+                scratch: Some(&*SEARCH_SCRATCH),
             },
-        },
-    );
+        )
+    };
+    let ranked = match parsed.constraints.iter().find_map(|c| c.root()) {
+        Some(root) => result_window::RankedFiles::Root(root.to_string()),
+        None if parsed.constraints.iter().any(|c| c.is_ignored()) => {
+            result_window::RankedFiles::Ignored
+        }
+        None => result_window::RankedFiles::Index,
+    };
+    // auxiliary roots are rescanned on their own, only the index and the
+    // ignored files change with the picker generation
+    let working_set = match ranked {
+        result_window::RankedFiles::Root(_) => None,
+        _ => Some(&*WORKING_SET),
+    };
+    let mut ranking = rank(files, working_set);
+    // a narrowed session only searches the pinned files of the index
+    if matches!(ranked, result_window::RankedFiles::Index) && WORKING_SET.narrowing().is_none() {
+        if !picker.virtual_files().is_empty() {
+            let virtual_ranking = rank(picker.virtual_files().files(), None);
+            ranking = fff::virtual_files::merge_rankings(ranking, virtual_ranking, ranking_limit);
+        }
+        for root in picker.auxiliary_roots().weighted() {
+            let mut root_ranking = rank(root.files(), None);
+            root.weigh(&mut root_ranking);
+            ranking = fff::virtual_files::merge_rankings(ranking, root_ranking, ranking_limit);
+        }
+    }
+    if ranking.total_matched == 0 {
+        ranking.suggestion = picker.suggest_query(&query);
+    }
+    let query_id = result_window::store_ranking(&ranking, ranked, &query);
+    let results = result_window::page_of(&ranking, offset, limit);
 
     if results.items.is_empty() && query.contains(std::path::MAIN_SEPARATOR) {
         let pure_query = match &parsed.fuzzy_query {
@@ -244,7 +647,7 @@ pub fn fuzzy_search_files(
 
         let path = expand_tilde(pure_query);
         if path.is_absolute() && path.is_file() {
-            if let Ok(idx) = files.binary_search_by(|f| f.path.as_path().cmp(&path)) {
+            if let Ok(idx) = files.binary_search_by(files.len(), |f| f.path().cmp(&path)) {
                 let found = SearchResult {
                     items: vec![&files[idx]],
                     scores: vec![Score {
@@ -255,68 +658,200 @@ pub fn fuzzy_search_files(
                     total_matched: 1,
                     total_files: results.total_files,
                     location: parsed.location,
+                    match_stats: MatchStats {
+                        exact_matched: 1,
+                        ..Default::default()
+                    },
+                    suggestion: None,
+                    facets: None,
+                    cursor: None,
+                    index_generation: results.index_generation,
                 };
 
-                return lua_types::SearchResultLua::from(found).into_lua(lua);
+                return lua_types::SearchResultLua::from(found)
+                    .with_display(display)
+                    .into_lua(lua);
             }
 
             return build_file_path_fallback(lua, &path, results.total_files);
         }
     }
 
-    lua_types::SearchResultLua::from(results).into_lua(lua)
+    lua_types::SearchResultLua::from(results)
+        .with_query_id(query_id)
+        .with_display(display)
+        .with_roots(picker.auxiliary_roots())
+        .into_lua(lua)
 }
 
-#[allow(clippy::type_complexity)]
+/// `live_grep(query, file_offset?, page_size?, opts?)` greps the indexed
+/// files. `opts.mode` picks plain, regex or fuzzy matching and any key of the
+/// `grep` config section overrides the value given to `set_config`.
 pub fn live_grep(
     lua: &Lua,
-    (
-        query,
-        file_offset,
-        page_size,
-        max_file_size,
-        max_matches_per_file,
-        smart_case,
-        grep_mode,
-        time_budget_ms,
-    ): (
-        String,
-        Option<usize>,
-        Option<usize>,
-        Option<u64>,
-        Option<usize>,
-        Option<bool>,
-        Option<String>,
-        Option<u64>,
-    ),
+    (query, file_offset, page_size, opts): (String, Option<usize>, Option<usize>, Option<LuaTable>),
 ) -> LuaResult<LuaValue> {
-    let file_picker_guard = FILE_PICKER.read().into_lua_result()?;
-    let Some(ref picker) = *file_picker_guard else {
+    let Some(picker) = FILE_PICKER.snapshot() else {
         return Err(error::to_lua_error(Error::FilePickerMissing));
     };
 
-    let parsed = fff::grep::parse_grep_query(&query);
-    let mode = match grep_mode.as_deref() {
-        Some("regex") => fff::GrepMode::Regex,
-        Some("fuzzy") => fff::GrepMode::Fuzzy,
-        _ => fff::GrepMode::PlainText, // "plain" or nil or unknown
+    let config = runtime_config::current()?;
+    let mut parsed = fff::grep::parse_grep_query(&query);
+    picker.exclude_runtime_globs(&mut parsed);
+    let mut options = runtime_config::grep_options(file_offset, page_size, opts)?;
+
+    if config.history_enabled {
+        let query_tracker_guard = QUERY_TRACKER.read().into_lua_result()?;
+        options.history_boost = query_tracker_guard
+            .as_ref()
+            .and_then(|tracker| {
+                tracker
+                    .get_last_grep_entry(&query, picker.base_path(), config.min_combo_count)
+                    .ok()
+                    .flatten()
+            })
+            .map(|entry| fff::GrepHistoryBoost {
+                file_path: entry.file_path,
+                line_number: entry.line_number,
+            });
+    }
+
+    if let Some(name) = parsed.constraints.iter().find_map(|c| c.root()) {
+        // an unknown root matches nothing, like in the file search
+        let result = picker
+            .auxiliary_roots()
+            .get(name)
+            .map(|root| root.grep(&parsed, &options, picker.cache_budget()))
+            .unwrap_or_default();
+        return lua_types::GrepResultLua::from(result)
+            .with_roots(picker.auxiliary_roots())
+            .into_lua(lua);
+    }
+
+    let narrowing = WORKING_SET.narrowing();
+    let mut result = match narrowing {
+        Some(ref paths) => picker.grep_in_files(&parsed, &options, paths.iter()),
+        None => picker.grep(&parsed, &options),
     };
+    // Virtual files come after the last page of the index
+    if result.next_file_offset == 0 && narrowing.is_none() && !picker.virtual_files().is_empty() {
+        options.file_offset = 0;
+        let virtual_result = picker
+            .virtual_files()
+            .grep(&parsed, &options, picker.cache_budget());
+        let file_offset = result.files.len();
+        result.files.extend(virtual_result.files);
+        result
+            .matches
+            .extend(virtual_result.matches.into_iter().map(|mut m| {
+                m.file_index += file_offset;
+                m
+            }));
+        result.total_files += virtual_result.total_files;
+        result.total_files_searched += virtual_result.total_files_searched;
+        result.filtered_file_count += virtual_result.filtered_file_count;
+        result.files_with_matches += virtual_result.files_with_matches;
+    }
+    lua_types::GrepResultLua::from(result).into_lua(lua)
+}
 
-    let options = fff::GrepSearchOptions {
-        max_file_size: max_file_size.unwrap_or(10 * 1024 * 1024),
-        max_matches_per_file: max_matches_per_file.unwrap_or(200),
-        smart_case: smart_case.unwrap_or(true),
-        file_offset: file_offset.unwrap_or(0),
-        page_limit: page_size.unwrap_or(50),
-        mode,
-        time_budget_ms: time_budget_ms.unwrap_or(0),
-        before_context: 0,
-        after_context: 0,
-        classify_definitions: false,
+/// `read_context_at(path, byte_offset, lines_before, lines_after)` returns
+/// `{ first_line, target_line, lines }` around the `byte_offset` of a grep
+/// match, read from the indexed content, or `nil` when the file can't be
+/// read that way and the preview has to open it.
+pub fn read_context_at(
+    lua: &Lua,
+    (path, byte_offset, lines_before, lines_after): (String, u64, usize, usize),
+) -> LuaResult<LuaValue> {
+    let Some(picker) = FILE_PICKER.snapshot() else {
+        return Err(error::to_lua_error(Error::FilePickerMissing));
+    };
+    let Some(hunk) = picker.read_context_at(&path, byte_offset, lines_before, lines_after) else {
+        return Ok(LuaValue::Nil);
     };
 
-    let result = picker.grep(&parsed, &options);
-    lua_types::GrepResultLua::from(result).into_lua(lua)
+    let table = lua.create_table()?;
+    table.set("first_line", hunk.first_line)?;
+    table.set("target_line", hunk.target_line)?;
+    table.set("lines", lua.create_sequence_from(hunk.lines)?)?;
+    Ok(LuaValue::Table(table))
+}
+
+/// `generate_diff(path, replacements)` returns the unified diff of a search
+/// and replace in the file, each replacement a `{ line_number, start, end,
+/// text }` table with the byte range of the line like a grep item's
+/// `match_ranges`. `nil` when the file can't be read from the index.
+pub fn generate_diff(
+    _: &Lua,
+    (path, replacements): (String, LuaTable),
+) -> LuaResult<Option<String>> {
+    let Some(picker) = FILE_PICKER.snapshot() else {
+        return Err(error::to_lua_error(Error::FilePickerMissing));
+    };
+
+    let mut parsed = Vec::new();
+    for replacement in replacements.sequence_values::<LuaTable>() {
+        let replacement = replacement?;
+        parsed.push(fff::Replacement {
+            line_number: replacement.get("line_number")?,
+            start: replacement.get("start")?,
+            end: replacement.get("end")?,
+            text: replacement.get("text")?,
+        });
+    }
+    Ok(picker.generate_diff(&path, &parsed))
+}
+
+/// `export_grep_results(result, format)` writes the items of a grep result,
+/// or a list of grep items, as `"quickfix"` JSON, a `"sarif"` log or
+/// `"text"` lines of `path:line:col:content`.
+pub fn export_grep_results(_: &Lua, (result, format): (LuaTable, String)) -> LuaResult<String> {
+    let format = format.parse::<GrepExportFormat>().into_lua_result()?;
+    let items = result.get::<Option<LuaTable>>("items")?.unwrap_or(result);
+
+    let mut matches = Vec::new();
+    for item in items.sequence_values::<LuaTable>() {
+        let item = item?;
+        let end_col = item
+            .get::<Option<LuaTable>>("match_ranges")?
+            .map(|ranges| ranges.get::<Option<LuaTable>>(1))
+            .transpose()?
+            .flatten()
+            .map(|range| range.get::<usize>(2))
+            .transpose()?;
+        matches.push(ExportedMatch {
+            path: item.get::<String>("path")?.into(),
+            relative_path: item.get::<String>("relative_path")?.into(),
+            line_number: item.get("line_number")?,
+            col: item.get("col")?,
+            end_col,
+            line_content: item.get::<String>("line_content")?.into(),
+        });
+    }
+    Ok(export_grep_matches(matches, format))
+}
+
+static GREP_WATCH: Lazy<Mutex<Option<GrepWatch>>> = Lazy::new(|| Mutex::new(None));
+
+/// `grep_watch(query, opts?)` greps like `live_grep` on the first call and,
+/// while the query and mode stay the same, only re-greps the files modified
+/// since the previous call. Results are not paginated after the first call.
+pub fn grep_watch(lua: &Lua, (query, opts): (String, Option<LuaTable>)) -> LuaResult<LuaValue> {
+    let Some(picker) = FILE_PICKER.snapshot() else {
+        return Err(error::to_lua_error(Error::FilePickerMissing));
+    };
+
+    let options = runtime_config::grep_options(None, None, opts)?;
+    let mut guard = GREP_WATCH
+        .lock()
+        .map_err(|_| LuaError::RuntimeError("Grep watch lock poisoned".into()))?;
+    let watch = match guard.take() {
+        Some(watch) if watch.query() == query && watch.options().mode == options.mode => watch,
+        _ => GrepWatch::new(query, options),
+    };
+    let watch = guard.insert(watch);
+
+    lua_types::GrepResultLua::from(watch.refresh(&picker)).into_lua(lua)
 }
 
 /// Build a file-picker result for an absolute path that exists on disk but
@@ -340,6 +875,7 @@ fn build_file_path_fallback(lua: &Lua, path: &Path, total_files: usize) -> LuaRe
     item.set("modification_frecency_score", 0i32)?;
     item.set("total_frecency_score", 0i32)?;
     item.set("git_status", "")?;
+    item.set("git_status_short", "")?;
     item.set("is_binary", false)?;
 
     let items_table = lua.create_table()?;
@@ -356,6 +892,9 @@ fn build_file_path_fallback(lua: &Lua, path: &Path, total_files: usize) -> LuaRe
     score.set("distance_penalty", 0)?;
     score.set("current_file_penalty", 0)?;
     score.set("combo_match_boost", 0)?;
+    score.set("extension_boost", 0)?;
+    score.set("project_boost", 0)?;
+    score.set("affinity_boost", 0)?;
     score.set("exact_match", true)?;
     score.set("match_type", "path")?;
 
@@ -370,10 +909,9 @@ fn build_file_path_fallback(lua: &Lua, path: &Path, total_files: usize) -> LuaRe
 }
 
 pub fn track_access(_: &Lua, file_path: String) -> LuaResult<bool> {
-    let file_path = PathBuf::from(&file_path);
+    let file_path = PathBuf::from(file_path);
 
-    // Track access in frecency DB (expensive LMDB write, ~100-200ms)
-    // Do this WITHOUT holding FILE_PICKER lock to avoid blocking searches
+    // Queued for the frecency flusher thread, so this never waits on an LMDB write
     let frecency_guard = FRECENCY.read().into_lua_result()?;
     let Some(ref frecency) = *frecency_guard else {
         return Ok(false);
@@ -383,53 +921,522 @@ pub fn track_access(_: &Lua, file_path: String) -> LuaResult<bool> {
         .into_lua_result()?;
     drop(frecency_guard);
 
-    // Quick lock to update single file's frecency score in picker
+    // Quick lock to update single file's frecency score in picker
+    let mut file_picker = FILE_PICKER.write().into_lua_result()?;
+    let Some(ref mut picker) = *file_picker else {
+        return Err(error::to_lua_error(Error::FilePickerMissing));
+    };
+
+    let frecency_guard = FRECENCY.read().into_lua_result()?;
+    let Some(ref frecency) = *frecency_guard else {
+        return Ok(false);
+    };
+    picker
+        .update_single_file_frecency(&file_path, frecency)
+        .into_lua_result()?;
+    let project_path = picker.base_path().to_path_buf();
+    drop(frecency_guard);
+    drop(file_picker);
+
+    if runtime_config::current()?.history_enabled
+        && let Ok(relative_path) = file_path.strip_prefix(&project_path)
+    {
+        let relative_path = relative_path.to_string_lossy().into_owned();
+        let query_tracker = QUERY_TRACKER.clone();
+        std::thread::spawn(move || {
+            if let Ok(mut guard) = query_tracker.write()
+                && let Some(tracker) = guard.as_mut()
+                && let Err(e) = tracker.track_file_open(&project_path, &relative_path)
+            {
+                tracing::error!(error = ?e, "Failed to track file open");
+            }
+        });
+    }
+
+    Ok(true)
+}
+
+pub fn flush_frecency(_: &Lua, _: ()) -> LuaResult<usize> {
+    FRECENCY.flush().into_lua_result()
+}
+
+/// `set_frecency_context({ time_of_day?, branch?, branch_scoped? })` scores
+/// the files opened around the current hour of the day, or on the checked out
+/// branch of the project, higher. `branch_scoped` scores by the accesses made
+/// on the branch alone once it has any. Called when the picker opens, so the
+/// scores follow the clock and branch switches; they are only read again when
+/// that changed. Returns whether it did.
+pub fn set_frecency_context(_: &Lua, opts: Option<LuaTable>) -> LuaResult<bool> {
+    let get = |key: &str| -> LuaResult<bool> {
+        Ok(match opts {
+            Some(ref opts) => opts.get::<Option<bool>>(key)?.unwrap_or(false),
+            None => false,
+        })
+    };
+    let (time_of_day, branch_scoped) = (get("time_of_day")?, get("branch_scoped")?);
+    let branch = branch_scoped || get("branch")?;
+
+    let git_root = match branch {
+        true => FILE_PICKER
+            .read()
+            .into_lua_result()?
+            .as_ref()
+            .and_then(|picker| picker.git_root().map(Path::to_path_buf)),
+        false => None,
+    };
+    let branch = git_root.as_deref().and_then(fff::git::current_branch);
+    let context = fff::FrecencyContext {
+        time_of_day: time_of_day.then(fff::TimeOfDay::now),
+        repository: git_root.filter(|_| branch.is_some()),
+        branch,
+        branch_scoped,
+    };
+
+    let changed = match *FRECENCY.read().into_lua_result()? {
+        Some(ref frecency) => frecency.set_context(context),
+        None => return Ok(false),
+    };
+    if changed {
+        std::thread::spawn(|| {
+            // picker first, then frecency, like the background watcher
+            if let Ok(mut picker_guard) = FILE_PICKER.write()
+                && let Some(ref mut picker) = *picker_guard
+                && let Ok(frecency_guard) = FRECENCY.read()
+                && let Some(ref frecency) = *frecency_guard
+            {
+                picker.refresh_frecency_scores(frecency);
+            }
+        });
+    }
+    Ok(changed)
+}
+
+/// Parse a single `{ op = "rename" | "move" | "delete", ... }` table.
+fn file_op_from_lua(op: &LuaTable) -> LuaResult<FileOp> {
+    let kind: String = op.get("op")?;
+    match kind.as_str() {
+        "rename" => Ok(FileOp::Rename {
+            from: op.get::<String>("from")?.into(),
+            to: op.get::<String>("to")?.into(),
+        }),
+        "move" => Ok(FileOp::Move {
+            from: op.get::<String>("from")?.into(),
+            to_dir: op.get::<String>("to_dir")?.into(),
+        }),
+        "delete" => Ok(FileOp::Delete {
+            path: op.get::<String>("path")?.into(),
+        }),
+        other => Err(LuaError::RuntimeError(format!(
+            "Unknown file operation: {other}"
+        ))),
+    }
+}
+
+fn file_op_result_to_lua(lua: &Lua, result: FileOpResult) -> LuaResult<LuaTable> {
+    let item = lua.create_table()?;
+    item.set("success", result.success)?;
+    item.set("path", result.path.to_string_lossy().to_string())?;
+    if let Some(error) = result.error {
+        item.set("error", error)?;
+    }
+    if let Some(trash_location) = result.trash_location {
+        item.set(
+            "trash_location",
+            trash_location.to_string_lossy().to_string(),
+        )?;
+    }
+    Ok(item)
+}
+
+pub fn apply_batch(lua: &Lua, ops: LuaTable) -> LuaResult<LuaValue> {
+    let ops = ops
+        .sequence_values::<LuaTable>()
+        .map(|op| file_op_from_lua(&op?))
+        .collect::<LuaResult<Vec<_>>>()?;
+
+    let results = FILE_PICKER
+        .apply_batch(&ops, &FRECENCY, &QUERY_TRACKER)
+        .into_lua_result()?;
+
+    let table = lua.create_table()?;
+    for (i, result) in results.into_iter().enumerate() {
+        table.set(i + 1, file_op_result_to_lua(lua, result)?)?;
+    }
+
+    Ok(LuaValue::Table(table))
+}
+
+pub fn undo_last_file_op(lua: &Lua, _: ()) -> LuaResult<LuaValue> {
+    match FILE_PICKER
+        .undo_last_file_op(&FRECENCY, &QUERY_TRACKER)
+        .into_lua_result()?
+    {
+        Some(result) => Ok(LuaValue::Table(file_op_result_to_lua(lua, result)?)),
+        None => Ok(LuaValue::Nil),
+    }
+}
+
+pub fn get_scan_progress(lua: &Lua, _: ()) -> LuaResult<LuaValue> {
+    let file_picker = FILE_PICKER.read().into_lua_result()?;
+    let picker = file_picker
+        .as_ref()
+        .ok_or(Error::FilePickerMissing)
+        .into_lua_result()?;
+    let progress = picker.get_scan_progress();
+
+    let table = lua.create_table()?;
+    table.set("scanned_files_count", progress.scanned_files_count)?;
+    table.set("is_scanning", progress.is_scanning)?;
+    table.set("is_truncated", progress.is_truncated)?;
+    Ok(LuaValue::Table(table))
+}
+
+/// `get_index_generation()` changes whenever a scan or the watcher changed
+/// the indexed files, compare it with the `index_generation` of a search
+/// result to know whether the displayed list is stale.
+pub fn get_index_generation(_: &Lua, _: ()) -> LuaResult<Option<u64>> {
+    Ok(FILE_PICKER
+        .snapshot()
+        .map(|picker| picker.index_generation()))
+}
+
+pub fn is_scanning(_: &Lua, _: ()) -> LuaResult<bool> {
+    let file_picker = FILE_PICKER.read().into_lua_result()?;
+    let picker = file_picker
+        .as_ref()
+        .ok_or(Error::FilePickerMissing)
+        .into_lua_result()?;
+    Ok(picker.is_scan_active())
+}
+
+pub fn get_git_root(_: &Lua, _: ()) -> LuaResult<Option<String>> {
+    let file_picker = FILE_PICKER.read().into_lua_result()?;
+    let Some(ref picker) = *file_picker else {
+        return Ok(None);
+    };
+
+    Ok(picker.git_root().map(|p| p.to_string_lossy().into_owned()))
+}
+
+/// `complete_constraint(prefix)` returns `{ text, kind, count }` completions
+/// of a constraint token, `kind` being `key`, `extension`, `git_status` or
+/// `path_segment`.
+pub fn complete_constraint(lua: &Lua, prefix: String) -> LuaResult<LuaTable> {
+    let file_picker = FILE_PICKER.read().into_lua_result()?;
+    let picker = file_picker
+        .as_ref()
+        .ok_or(Error::FilePickerMissing)
+        .into_lua_result()?;
+
+    let completions = lua.create_table()?;
+    for completion in picker.complete_constraint(&prefix) {
+        let table = lua.create_table()?;
+        table.set("text", completion.text)?;
+        table.set("kind", completion.kind.name())?;
+        table.set("count", completion.count)?;
+        completions.push(table)?;
+    }
+    Ok(completions)
+}
+
+/// `find_duplicates(min_size?)` returns `{ size, hash, wasted_bytes, files }`
+/// groups of indexed files with identical content, `files` being
+/// `{ path, relative_path }` lists. `hash` is a hex string, Lua numbers can't
+/// hold every u64.
+pub fn find_duplicates(lua: &Lua, min_size: Option<u64>) -> LuaResult<LuaTable> {
+    let file_picker = FILE_PICKER.read().into_lua_result()?;
+    let picker = file_picker
+        .as_ref()
+        .ok_or(Error::FilePickerMissing)
+        .into_lua_result()?;
+
+    let groups = lua.create_table()?;
+    for group in picker.find_duplicates(min_size.unwrap_or(0)) {
+        let files = lua.create_table()?;
+        for file in &group.files {
+            let table = lua.create_table()?;
+            table.set("path", file.path().to_string_lossy())?;
+            table.set("relative_path", file.relative_path())?;
+            files.push(table)?;
+        }
+
+        let table = lua.create_table()?;
+        table.set("size", group.size)?;
+        table.set("hash", format!("{:016x}", group.hash))?;
+        table.set("wasted_bytes", group.wasted_bytes())?;
+        table.set("files", files)?;
+        groups.push(table)?;
+    }
+    Ok(groups)
+}
+
+/// `search_todos(query, max_results?)` returns the TODO, FIXME, HACK and XXX
+/// comments matching `query` as `{ path, relative_path, line_number, kind,
+/// text, score }`, best first.
+pub fn search_todos(
+    lua: &Lua,
+    (query, max_results): (String, Option<usize>),
+) -> LuaResult<LuaTable> {
+    let file_picker = FILE_PICKER.read().into_lua_result()?;
+    let picker = file_picker
+        .as_ref()
+        .ok_or(Error::FilePickerMissing)
+        .into_lua_result()?;
+
+    let todos = lua.create_table()?;
+    for todo in picker.search_todos(&query, max_results.unwrap_or(100)) {
+        let table = lua.create_table()?;
+        table.set("path", todo.file.path().to_string_lossy())?;
+        table.set("relative_path", todo.file.relative_path())?;
+        table.set("line_number", todo.line_number)?;
+        table.set("kind", todo.kind.name())?;
+        table.set("text", todo.text)?;
+        table.set("score", todo.score)?;
+        todos.push(table)?;
+    }
+    Ok(todos)
+}
+
+/// `search_keyword(name, query, max_results?)` returns the lines matching the
+/// `keyword_patterns[name]` regex given to `init_file_picker`, shaped like
+/// the `search_todos` results minus `kind`. Errors on an unknown name.
+pub fn search_keyword(
+    lua: &Lua,
+    (name, query, max_results): (String, String, Option<usize>),
+) -> LuaResult<LuaTable> {
+    let file_picker = FILE_PICKER.read().into_lua_result()?;
+    let picker = file_picker
+        .as_ref()
+        .ok_or(Error::FilePickerMissing)
+        .into_lua_result()?;
+
+    let Some(matches) = picker.search_keyword(&name, &query, max_results.unwrap_or(100)) else {
+        return Err(LuaError::RuntimeError(format!(
+            "Unknown keyword pattern: {name}"
+        )));
+    };
+
+    let lines = lua.create_table()?;
+    for line in matches {
+        let table = lua.create_table()?;
+        table.set("path", line.file.path().to_string_lossy())?;
+        table.set("relative_path", line.file.relative_path())?;
+        table.set("line_number", line.line_number)?;
+        table.set("text", line.text)?;
+        table.set("score", line.score)?;
+        lines.push(table)?;
+    }
+    Ok(lines)
+}
+
+fn test_table(lua: &Lua, test: fff::TestMatch<'_>) -> LuaResult<LuaTable> {
+    let table = lua.create_table()?;
+    table.set("path", test.file.path().to_string_lossy())?;
+    table.set("relative_path", test.file.relative_path())?;
+    table.set("line_number", test.line_number)?;
+    table.set("kind", test.kind.name())?;
+    table.set("name", test.text)?;
+    table.set("score", test.score)?;
+    Ok(table)
+}
+
+/// `search_tests(query, max_results?)` returns the tests whose path and name
+/// match `query` as `{ path, relative_path, line_number, kind, name, score }`,
+/// best first. `kind` is `rust`, `python`, `go` or `spec`.
+pub fn search_tests(
+    lua: &Lua,
+    (query, max_results): (String, Option<usize>),
+) -> LuaResult<LuaTable> {
+    let file_picker = FILE_PICKER.read().into_lua_result()?;
+    let picker = file_picker
+        .as_ref()
+        .ok_or(Error::FilePickerMissing)
+        .into_lua_result()?;
+
+    let tests = lua.create_table()?;
+    for test in picker.search_tests(&query, max_results.unwrap_or(100)) {
+        tests.push(test_table(lua, test)?)?;
+    }
+    Ok(tests)
+}
+
+/// `test_at(path, line)` returns the test the 1-based `line` of `path` is in,
+/// shaped like the `search_tests` results, or `nil`.
+pub fn test_at(lua: &Lua, (path, line): (String, u64)) -> LuaResult<LuaValue> {
+    let file_picker = FILE_PICKER.read().into_lua_result()?;
+    let picker = file_picker
+        .as_ref()
+        .ok_or(Error::FilePickerMissing)
+        .into_lua_result()?;
+
+    let path = fff::path_utils::normalize_key(Path::new(&path));
+    match picker.test_at(&path, line) {
+        Some(test) => test_table(lua, test).map(LuaValue::Table),
+        None => Ok(LuaValue::Nil),
+    }
+}
+
+/// Virtual files registered from Lua with their contents. Lua can't be
+/// called from the grep threads, so contents are handed over up front.
+struct LuaVirtualFiles {
+    name: String,
+    files: Vec<(String, Option<Vec<u8>>)>,
+}
+
+impl fff::virtual_files::VirtualFileProvider for LuaVirtualFiles {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn files(&self) -> Vec<fff::virtual_files::VirtualFile> {
+        self.files
+            .iter()
+            .map(|(path, content)| fff::virtual_files::VirtualFile {
+                path: path.clone(),
+                size: content.as_ref().map_or(0, |c| c.len() as u64),
+                modified: 0,
+            })
+            .collect()
+    }
+
+    fn read(&self, path: &str) -> std::io::Result<Vec<u8>> {
+        self.files
+            .iter()
+            .find(|(file, _)| file == path)
+            .and_then(|(_, content)| content.clone())
+            .ok_or_else(|| std::io::ErrorKind::NotFound.into())
+    }
+}
+
+/// `register_virtual_files(name, files)` makes `{ path, content? }` files
+/// that don't exist on disk, e.g. `scp://` buffers, searchable by name and,
+/// when `content` is given, by grep. Replaces the previous files of `name`.
+pub fn register_virtual_files(_: &Lua, (name, files): (String, Vec<LuaTable>)) -> LuaResult<usize> {
+    let files = files
+        .into_iter()
+        .map(|file| {
+            let path: String = file.get("path")?;
+            let content: Option<LuaString> = file.get("content")?;
+            Ok((path, content.map(|c| c.as_bytes().to_vec())))
+        })
+        .collect::<LuaResult<Vec<_>>>()?;
+    let count = files.len();
+
+    let mut file_picker = FILE_PICKER.write().into_lua_result()?;
+    let Some(ref mut picker) = *file_picker else {
+        return Err(error::to_lua_error(Error::FilePickerMissing));
+    };
+    picker.register_virtual_provider(Arc::new(LuaVirtualFiles { name, files }));
+    Ok(count)
+}
+
+/// `set_external_scores({ [path] = score })` adds `score` to the files at
+/// `path`, absolute or relative to the indexed directory. Replaces the scores
+/// of the previous call, `nil` clears them. Returns how many files matched.
+pub fn set_external_scores(
+    _: &Lua,
+    scores: Option<std::collections::HashMap<String, i32>>,
+) -> LuaResult<usize> {
+    let mut file_picker = FILE_PICKER.write().into_lua_result()?;
+    let Some(ref mut picker) = *file_picker else {
+        return Err(error::to_lua_error(Error::FilePickerMissing));
+    };
+    Ok(picker.set_external_scores(scores.unwrap_or_default()))
+}
+
+/// `narrow_to(paths)` makes the following file searches and greps of the
+/// session search only `paths`, e.g. the selected files. Narrowing again keeps
+/// the files that are in both sets.
+pub fn narrow_to(_: &Lua, paths: Vec<String>) -> LuaResult<()> {
+    WORKING_SET.narrow_to(
+        paths
+            .iter()
+            .map(|path| fff::path_utils::normalize_key(Path::new(path))),
+    );
+    Ok(())
+}
+
+/// `clear_narrowing()` searches every file again after `narrow_to`.
+pub fn clear_narrowing(_: &Lua, _: ()) -> LuaResult<()> {
+    WORKING_SET.clear_narrowing();
+    Ok(())
+}
+
+/// `set_runtime_excludes(globs)` hides the files matching any of `globs` from
+/// the picker's file search and grep until called again, without a rescan.
+/// `nil` or `{}` shows everything again.
+pub fn set_runtime_excludes(_: &Lua, globs: Option<Vec<String>>) -> LuaResult<()> {
+    let mut file_picker = FILE_PICKER.write().into_lua_result()?;
+    let Some(ref mut picker) = *file_picker else {
+        return Err(error::to_lua_error(Error::FilePickerMissing));
+    };
+    picker.set_runtime_excludes(globs.unwrap_or_default());
+    Ok(())
+}
+
+pub fn unregister_virtual_files(_: &Lua, name: String) -> LuaResult<bool> {
     let mut file_picker = FILE_PICKER.write().into_lua_result()?;
     let Some(ref mut picker) = *file_picker else {
         return Err(error::to_lua_error(Error::FilePickerMissing));
     };
+    Ok(picker.unregister_virtual_provider(&name))
+}
 
-    let frecency_guard = FRECENCY.read().into_lua_result()?;
-    let Some(ref frecency) = *frecency_guard else {
-        return Ok(false);
+/// Contents of a registered virtual file for the preview, `nil` for any
+/// other path.
+pub fn read_virtual_file(lua: &Lua, path: String) -> LuaResult<Option<LuaString>> {
+    let Some(picker) = FILE_PICKER.snapshot() else {
+        return Ok(None);
     };
-    picker
-        .update_single_file_frecency(&file_path, frecency)
-        .into_lua_result()?;
-
-    Ok(true)
+    match picker.virtual_files().read(Path::new(&path)) {
+        Some(Ok(content)) => lua.create_string(content).map(Some),
+        Some(Err(e)) => Err(LuaError::RuntimeError(format!(
+            "Failed to read {path}: {e}"
+        ))),
+        None => Ok(None),
+    }
 }
 
-pub fn get_scan_progress(lua: &Lua, _: ()) -> LuaResult<LuaValue> {
-    let file_picker = FILE_PICKER.read().into_lua_result()?;
-    let picker = file_picker
-        .as_ref()
-        .ok_or(Error::FilePickerMissing)
-        .into_lua_result()?;
-    let progress = picker.get_scan_progress();
-
-    let table = lua.create_table()?;
-    table.set("scanned_files_count", progress.scanned_files_count)?;
-    table.set("is_scanning", progress.is_scanning)?;
-    Ok(LuaValue::Table(table))
-}
+/// `register_root(name, path, opts?)` searches the files under `path` next
+/// to the project. `opts.weight` is the percentage of their score they keep
+/// (50 by default, 0 for `root:name` queries only) and `opts.label` is set
+/// as `item.root_label` on their results instead of the name. Replaces the
+/// root of `name`.
+pub fn register_root(
+    _: &Lua,
+    (name, path, opts): (String, String, Option<LuaTable>),
+) -> LuaResult<bool> {
+    let (weight, label) = match opts {
+        Some(opts) => (
+            opts.get::<Option<u32>>("weight")?,
+            opts.get::<Option<String>>("label")?,
+        ),
+        None => (None, None),
+    };
+    let path = expand_tilde(&path);
+    if !path.is_dir() {
+        return Err(error::to_lua_error(Error::InvalidPath(path)));
+    }
 
-pub fn is_scanning(_: &Lua, _: ()) -> LuaResult<bool> {
-    let file_picker = FILE_PICKER.read().into_lua_result()?;
-    let picker = file_picker
-        .as_ref()
-        .ok_or(Error::FilePickerMissing)
-        .into_lua_result()?;
-    Ok(picker.is_scan_active())
+    let mut file_picker = FILE_PICKER.write().into_lua_result()?;
+    let Some(ref mut picker) = *file_picker else {
+        return Err(error::to_lua_error(Error::FilePickerMissing));
+    };
+    picker.register_auxiliary_root(
+        name,
+        path,
+        weight.unwrap_or(fff::auxiliary_roots::DEFAULT_ROOT_WEIGHT),
+        label,
+    );
+    Ok(true)
 }
 
-pub fn get_git_root(_: &Lua, _: ()) -> LuaResult<Option<String>> {
-    let file_picker = FILE_PICKER.read().into_lua_result()?;
-    let Some(ref picker) = *file_picker else {
-        return Ok(None);
+pub fn unregister_root(_: &Lua, name: String) -> LuaResult<bool> {
+    let mut file_picker = FILE_PICKER.write().into_lua_result()?;
+    let Some(ref mut picker) = *file_picker else {
+        return Err(error::to_lua_error(Error::FilePickerMissing));
     };
-
-    Ok(picker.git_root().map(|p| p.to_string_lossy().into_owned()))
+    Ok(picker.unregister_auxiliary_root(&name))
 }
 
 pub fn refresh_git_status(_: &Lua, _: ()) -> LuaResult<usize> {
@@ -490,14 +1497,7 @@ pub fn track_query_completion(_: &Lua, (query, file_path): (String, String)) ->
         picker.base_path().to_path_buf()
     };
 
-    // Canonicalize the file path before spawning thread
-    let file_path = match fff::path_utils::canonicalize(&file_path) {
-        Ok(path) => path,
-        Err(e) => {
-            tracing::warn!(?file_path, error = ?e, "Failed to canonicalize file path for tracking");
-            return Ok(false);
-        }
-    };
+    let file_path = PathBuf::from(file_path);
 
     // Spawn background thread to do the actual tracking (expensive DB write)
     let query_tracker = QUERY_TRACKER.clone();
@@ -563,6 +1563,39 @@ pub fn track_grep_query(_: &Lua, query: String) -> LuaResult<bool> {
     Ok(true)
 }
 
+pub fn track_grep_completion(
+    _: &Lua,
+    (query, file_path, line_number): (String, String, u64),
+) -> LuaResult<bool> {
+    let project_path = {
+        let file_picker = FILE_PICKER.read().into_lua_result()?;
+        let Some(ref picker) = *file_picker else {
+            return Ok(false);
+        };
+        picker.base_path().to_path_buf()
+    };
+
+    let file_path = PathBuf::from(file_path);
+
+    let query_tracker = QUERY_TRACKER.clone();
+    std::thread::spawn(move || {
+        if let Ok(mut guard) = query_tracker.write()
+            && let Some(ref mut tracker) = *guard
+            && let Err(e) =
+                tracker.track_grep_completion(&query, &project_path, &file_path, line_number)
+        {
+            tracing::error!(
+                query = %query,
+                file = %file_path.display(),
+                error = ?e,
+                "Failed to track grep completion"
+            );
+        }
+    });
+
+    Ok(true)
+}
+
 pub fn get_historical_grep_query(_: &Lua, offset: usize) -> LuaResult<Option<String>> {
     let project_path = {
         let file_picker = FILE_PICKER.read().into_lua_result()?;
@@ -582,6 +1615,164 @@ pub fn get_historical_grep_query(_: &Lua, offset: usize) -> LuaResult<Option<Str
         .into_lua_result()
 }
 
+/// Recently opened grep results of the current project, most recent first,
+/// `get_frecency_stats(top_n?)` returns the most opened files and most used
+/// queries of the current project as `{ files, queries, grep_queries }`,
+/// `top_n` (default 20) of each, most used first.
+pub fn get_frecency_stats(lua: &Lua, top_n: Option<usize>) -> LuaResult<LuaTable> {
+    let Some(picker) = FILE_PICKER.snapshot() else {
+        return Err(error::to_lua_error(Error::FilePickerMissing));
+    };
+    let frecency = FRECENCY.read().into_lua_result()?;
+    let query_tracker = QUERY_TRACKER.read().into_lua_result()?;
+    let stats = FrecencyStats::collect(
+        picker.get_files(),
+        frecency.as_ref(),
+        query_tracker.as_ref(),
+        picker.base_path(),
+        top_n.unwrap_or(20),
+    )
+    .into_lua_result()?;
+
+    let files = lua.create_table()?;
+    for (i, file) in stats.files.into_iter().enumerate() {
+        let item = lua.create_table()?;
+        item.set("path", file.path.to_string_lossy().to_string())?;
+        item.set("relative_path", file.relative_path)?;
+        item.set("open_count", file.open_count)?;
+        item.set("last_opened", file.last_opened)?;
+        files.set(i + 1, item)?;
+    }
+    let queries_table = |queries: Vec<fff::QueryUsage>| -> LuaResult<LuaTable> {
+        let table = lua.create_table()?;
+        for (i, usage) in queries.into_iter().enumerate() {
+            let item = lua.create_table()?;
+            item.set("query", usage.query)?;
+            item.set("count", usage.count)?;
+            item.set("last_used", usage.last_used)?;
+            table.set(i + 1, item)?;
+        }
+        Ok(table)
+    };
+
+    let table = lua.create_table()?;
+    table.set("files", files)?;
+    table.set("queries", queries_table(stats.queries)?)?;
+    table.set("grep_queries", queries_table(stats.grep_queries)?)?;
+    Ok(table)
+}
+
+/// Remember the picker query, mode and selected index for `:FFFResume`.
+/// With `history.performance_counters` on, the search durations since the
+/// last call are saved to the weekly history as well.
+pub fn store_search_state(
+    _: &Lua,
+    (query, mode, selected_index): (String, String, u32),
+) -> LuaResult<bool> {
+    let (project_path, indexed_files) = {
+        let file_picker = FILE_PICKER.read().into_lua_result()?;
+        let Some(ref picker) = *file_picker else {
+            return Ok(false);
+        };
+        (picker.base_path().to_path_buf(), picker.get_files().len())
+    };
+    let perf = runtime_config::current()?.perf_counters_enabled.then(|| {
+        (
+            fff::metrics::take_unsaved(fff::metrics::SearchKind::Fuzzy),
+            fff::metrics::take_unsaved(fff::metrics::SearchKind::Grep),
+        )
+    });
+
+    let query_tracker = QUERY_TRACKER.clone();
+    std::thread::spawn(move || {
+        let Ok(mut guard) = query_tracker.write() else {
+            return;
+        };
+        let Some(ref mut tracker) = *guard else {
+            return;
+        };
+        if let Err(e) = tracker.store_search_state(&project_path, &query, &mode, selected_index) {
+            tracing::error!(error = ?e, "Failed to store search state");
+        }
+        if let Some((fuzzy, grep)) = perf
+            && let Err(e) = tracker.record_perf(&project_path, &fuzzy, &grep, indexed_files as u64)
+        {
+            tracing::error!(error = ?e, "Failed to record performance counters");
+        }
+    });
+
+    Ok(true)
+}
+
+/// The state stored by `store_search_state` for the current project as
+/// `{ query, mode, selected_index }`, or `nil`.
+pub fn get_last_search_state(lua: &Lua, _: ()) -> LuaResult<LuaValue> {
+    let project_path = {
+        let file_picker = FILE_PICKER.read().into_lua_result()?;
+        let Some(ref picker) = *file_picker else {
+            return Ok(LuaValue::Nil);
+        };
+        picker.base_path().to_path_buf()
+    };
+
+    let query_tracker = QUERY_TRACKER.read().into_lua_result()?;
+    let Some(ref tracker) = *query_tracker else {
+        return Ok(LuaValue::Nil);
+    };
+
+    let Some(state) = tracker
+        .get_last_search_state(&project_path)
+        .into_lua_result()?
+    else {
+        return Ok(LuaValue::Nil);
+    };
+
+    let table = lua.create_table()?;
+    table.set("query", state.query)?;
+    table.set("mode", state.mode)?;
+    table.set("selected_index", state.selected_index)?;
+    Ok(LuaValue::Table(table))
+}
+
+/// as `{ path, relative_path, line, query, timestamp }` tables.
+pub fn get_recent_grep_jumps(lua: &Lua, limit: Option<usize>) -> LuaResult<LuaTable> {
+    let jumps_table = lua.create_table()?;
+    let project_path = {
+        let file_picker = FILE_PICKER.read().into_lua_result()?;
+        let Some(ref picker) = *file_picker else {
+            return Ok(jumps_table);
+        };
+        picker.base_path().to_path_buf()
+    };
+
+    let query_tracker = QUERY_TRACKER.read().into_lua_result()?;
+    let Some(ref tracker) = *query_tracker else {
+        return Ok(jumps_table);
+    };
+
+    let jumps = tracker
+        .get_recent_grep_jumps(&project_path, limit.unwrap_or(20))
+        .into_lua_result()?;
+    for (i, jump) in jumps.into_iter().enumerate() {
+        let relative_path = jump
+            .file_path
+            .strip_prefix(&project_path)
+            .unwrap_or(&jump.file_path)
+            .to_string_lossy()
+            .into_owned();
+
+        let item = lua.create_table()?;
+        item.set("path", jump.file_path.to_string_lossy().as_ref())?;
+        item.set("relative_path", relative_path)?;
+        item.set("line", jump.line_number)?;
+        item.set("query", jump.query)?;
+        item.set("timestamp", jump.timestamp)?;
+        jumps_table.set(i + 1, item)?;
+    }
+
+    Ok(jumps_table)
+}
+
 pub fn wait_for_initial_scan(_: &Lua, timeout_ms: Option<u64>) -> LuaResult<bool> {
     // Extract the scan signal Arc WITHOUT holding the read lock, so the
     // scan thread can acquire the write lock to store its results.
@@ -626,10 +1817,116 @@ pub fn init_tracing(
         .map_err(|e| LuaError::RuntimeError(format!("Failed to initialize tracing: {}", e)))
 }
 
+/// Returns the report written by the last panic, or nil if there never was one.
+pub fn get_last_crash_report(lua: &Lua, _: ()) -> LuaResult<LuaValue> {
+    let Some(report) = fff::crash::last_crash_report() else {
+        return Ok(LuaValue::Nil);
+    };
+
+    let table = lua.create_table()?;
+    table.set("timestamp", report.timestamp)?;
+    table.set("fff_version", report.fff_version)?;
+    table.set("plugin_version", env!("CARGO_PKG_VERSION"))?;
+    table.set("os", report.os)?;
+    table.set("arch", report.arch)?;
+    table.set("thread", report.thread)?;
+    table.set("message", report.message)?;
+    table.set("location", report.location)?;
+    table.set("indexed_files", report.indexed_files)?;
+    table.set("backtrace", report.backtrace)?;
+    if let Some(last_query) = report.last_query {
+        let query = lua.create_table()?;
+        query.set("kind", last_query.kind)?;
+        query.set("query", last_query.query)?;
+        query.set("in_progress", last_query.in_progress)?;
+        table.set("last_query", query)?;
+    }
+    if let Some(path) = fff::crash::crash_report_path() {
+        table.set("path", path.to_string_lossy().into_owned())?;
+    }
+
+    Ok(LuaValue::Table(table))
+}
+
+/// Changes the log level (trace, debug, info, warn, error) without restarting.
+pub fn set_log_level(_: &Lua, level: String) -> LuaResult<()> {
+    crate::log::set_log_level(&level)
+        .map(|_| ())
+        .map_err(|e| LuaError::RuntimeError(format!("Failed to set log level: {}", e)))
+}
+
+/// Starts (`true`) or stops (`false`) writing spans to a Chrome trace file.
+/// Returns the path of the trace file that was started or finished.
+pub fn set_profiling(
+    _: &Lua,
+    (enabled, path): (bool, Option<String>),
+) -> LuaResult<Option<String>> {
+    crate::log::set_profiling(enabled, path.as_deref().map(Path::new))
+        .map(|path| path.map(|p| p.to_string_lossy().into_owned()))
+        .map_err(|e| LuaError::RuntimeError(format!("Failed to toggle profiling: {}", e)))
+}
+
 /// Returns health check information including version, git2 status, and repository detection
+fn set_db_open_status(lua: &Lua, info: &LuaTable, status: &RwLock<DbOpenStatus>) -> LuaResult<()> {
+    let Ok(status) = status.read() else {
+        return Ok(());
+    };
+    if let Some(ref error) = status.error {
+        info.set("error", error.as_str())?;
+    }
+    if let Some(ref repair) = status.repair {
+        let repair_table = lua.create_table()?;
+        repair_table.set("kept", repair.kept)?;
+        repair_table.set("dropped", repair.dropped)?;
+        repair_table.set("reset", repair.reset)?;
+        info.set("repaired", repair_table)?;
+    }
+    Ok(())
+}
+
+/// How this binary was built, so `:checkhealth` can tell a binary for the
+/// wrong platform or linked in an unexpected way from a plugin bug.
+fn build_info(lua: &Lua) -> LuaResult<LuaTable> {
+    let build = lua.create_table()?;
+    build.set("rustc", env!("FFF_RUSTC_VERSION"))?;
+    build.set("target", env!("FFF_BUILD_TARGET"))?;
+    build.set("profile", env!("FFF_BUILD_PROFILE"))?;
+    build.set("arch", std::env::consts::ARCH)?;
+    build.set("os", std::env::consts::OS)?;
+    // mlua is built with `module`, Lua symbols resolve against the host
+    // Neovim when the library is loaded instead of being linked in.
+    build.set("lua", "luajit (module, resolved from the host)")?;
+    build.set("entry_point", "luaopen_fff_nvim")?;
+
+    let libgit2 = git2::Version::get();
+    build.set(
+        "libgit2",
+        if libgit2.vendored() {
+            "vendored (static)"
+        } else {
+            "system (dynamic)"
+        },
+    )?;
+    build.set("libgit2_threads", libgit2.threads())?;
+
+    let mut features = Vec::new();
+    if cfg!(feature = "os-trash") {
+        features.push("os-trash");
+    }
+    if cfg!(feature = "zlob") {
+        features.push("zlob");
+    }
+    if cfg!(feature = "gix") {
+        features.push("gix");
+    }
+    build.set("features", features)?;
+    Ok(build)
+}
+
 pub fn health_check(lua: &Lua, test_path: Option<String>) -> LuaResult<LuaValue> {
     let table = lua.create_table()?;
     table.set("version", env!("CARGO_PKG_VERSION"))?;
+    table.set("build", build_info(lua)?)?;
 
     let test_path = test_path
         .map(PathBuf::from)
@@ -639,22 +1936,45 @@ pub fn health_check(lua: &Lua, test_path: Option<String>) -> LuaResult<LuaValue>
     let git_version = git2::Version::get();
     let (major, minor, rev) = git_version.libgit2_version();
     let libgit2_version_str = format!("{}.{}.{}", major, minor, rev);
+    git_info.set("backend", fff::git::backend().name())?;
+
+    // a picker with git disabled skips discovery, so does the health check
+    let git_enabled = FILE_PICKER
+        .snapshot()
+        .is_none_or(|picker| picker.git_enabled());
+    git_info.set("enabled", git_enabled)?;
+    if let Ok(guard) = FILE_PICKER.read()
+        && let Some(ref picker) = *guard
+    {
+        let provider = picker.git_status_provider();
+        git_info.set("status_provider", provider.name())?;
+        if let Some(root) = picker.git_root() {
+            git_info.set("status_uses_cli", provider.uses_cli(root))?;
+        }
+    }
 
-    match git2::Repository::discover(&test_path) {
-        Ok(repo) => {
-            git_info.set("available", true)?;
-            git_info.set("repository_found", true)?;
-            if let Some(workdir) = repo.workdir() {
-                git_info.set("workdir", workdir.to_string_lossy().to_string())?;
+    if !git_enabled {
+        git_info.set("available", true)?;
+        git_info.set("repository_found", false)?;
+        git_info.set("libgit2_version", libgit2_version_str)?;
+    } else {
+        match fff::git::open_repository(&test_path) {
+            Ok(repo) => {
+                git_info.set("available", true)?;
+                git_info.set("repository_found", true)?;
+                if let Some(workdir) = repo.workdir() {
+                    git_info.set("workdir", workdir.to_string_lossy().to_string())?;
+                }
+                git_info.set("is_worktree", repo.is_worktree())?;
+                // Get git2 version info
+                git_info.set("libgit2_version", libgit2_version_str.clone())?;
+            }
+            Err(e) => {
+                git_info.set("available", true)?;
+                git_info.set("repository_found", false)?;
+                git_info.set("error", e.message().to_string())?;
+                git_info.set("libgit2_version", libgit2_version_str)?;
             }
-            // Get git2 version info
-            git_info.set("libgit2_version", libgit2_version_str.clone())?;
-        }
-        Err(e) => {
-            git_info.set("available", true)?;
-            git_info.set("repository_found", false)?;
-            git_info.set("error", e.message().to_string())?;
-            git_info.set("libgit2_version", libgit2_version_str)?;
         }
     }
     table.set("git", git_info)?;
@@ -672,6 +1992,25 @@ pub fn health_check(lua: &Lua, test_path: Option<String>) -> LuaResult<LuaValue>
                 picker_info.set("is_scanning", picker.is_scan_active())?;
                 let progress = picker.get_scan_progress();
                 picker_info.set("indexed_files", progress.scanned_files_count)?;
+                if let Some(profile) = picker.project_profile() {
+                    let profile_info = lua.create_table()?;
+                    profile_info.set(
+                        "kinds",
+                        profile.kinds.iter().map(|k| k.name()).collect::<Vec<_>>(),
+                    )?;
+                    profile_info.set("deprioritized_dirs", profile.deprioritized_dirs.clone())?;
+                    profile_info.set("boosted_dirs", profile.boosted_dirs.clone())?;
+                    picker_info.set("project_profile", profile_info)?;
+                }
+                if let Some(health) = picker.trigram_index_health() {
+                    let trigram_info = lua.create_table()?;
+                    trigram_info.set("indexed_files", health.indexed_files)?;
+                    trigram_info.set("changed_files", health.changed_files)?;
+                    trigram_info.set("unindexed_changes", health.unindexed_changes)?;
+                    trigram_info.set("coverage", health.coverage)?;
+                    trigram_info.set("invalidated", health.invalidated)?;
+                    picker_info.set("trigram_index", trigram_info)?;
+                }
             } else {
                 picker_info.set("initialized", false)?;
             }
@@ -693,6 +2032,7 @@ pub fn health_check(lua: &Lua, test_path: Option<String>) -> LuaResult<LuaValue>
                     Ok(health) => {
                         let healthcheck_table = lua.create_table()?;
                         healthcheck_table.set("path", health.path)?;
+                        healthcheck_table.set("backend", health.backend.name())?;
                         healthcheck_table.set("disk_size", health.disk_size)?;
                         for (name, count) in health.entry_counts {
                             healthcheck_table.set(name, count)?;
@@ -710,6 +2050,7 @@ pub fn health_check(lua: &Lua, test_path: Option<String>) -> LuaResult<LuaValue>
             frecency_info.set("error", "Failed to acquire frecency lock")?;
         }
     }
+    set_db_open_status(lua, &frecency_info, &FRECENCY_DB_STATUS)?;
     table.set("frecency", frecency_info)?;
 
     let query_tracker_info = lua.create_table()?;
@@ -721,6 +2062,7 @@ pub fn health_check(lua: &Lua, test_path: Option<String>) -> LuaResult<LuaValue>
                     Ok(health) => {
                         let healthcheck_table = lua.create_table()?;
                         healthcheck_table.set("path", health.path)?;
+                        healthcheck_table.set("backend", health.backend.name())?;
                         healthcheck_table.set("disk_size", health.disk_size)?;
                         for (name, count) in health.entry_counts {
                             healthcheck_table.set(name, count)?;
@@ -731,6 +2073,28 @@ pub fn health_check(lua: &Lua, test_path: Option<String>) -> LuaResult<LuaValue>
                         query_tracker_info.set("db_healthcheck_error", e.to_string())?;
                     }
                 }
+
+                let project_path = FILE_PICKER
+                    .read()
+                    .ok()
+                    .and_then(|guard| guard.as_ref().map(|p| p.base_path().to_path_buf()));
+                if let Some(project_path) = project_path
+                    && let Ok(weeks) = query_history.perf_history(&project_path)
+                {
+                    let as_ms = |d: Option<Duration>| d.map(|d| d.as_secs_f64() * 1000.0);
+                    let history_table = lua.create_table()?;
+                    for (i, week) in weeks.into_iter().enumerate() {
+                        let week_table = lua.create_table()?;
+                        week_table.set("started_at", week.started_at())?;
+                        week_table.set("fuzzy_count", week.fuzzy.count())?;
+                        week_table.set("fuzzy_median_ms", as_ms(week.fuzzy.median()))?;
+                        week_table.set("grep_count", week.grep.count())?;
+                        week_table.set("grep_median_ms", as_ms(week.grep.median()))?;
+                        week_table.set("max_indexed_files", week.max_indexed_files)?;
+                        history_table.set(i + 1, week_table)?;
+                    }
+                    query_tracker_info.set("latency_history", history_table)?;
+                }
             }
         }
         Err(_) => {
@@ -738,8 +2102,55 @@ pub fn health_check(lua: &Lua, test_path: Option<String>) -> LuaResult<LuaValue>
             query_tracker_info.set("error", "Failed to acquire query tracker lock")?;
         }
     }
+    set_db_open_status(lua, &query_tracker_info, &QUERY_DB_STATUS)?;
     table.set("query_tracker", query_tracker_info)?;
 
+    let latency_info = lua.create_table()?;
+    for kind in [
+        fff::metrics::SearchKind::Fuzzy,
+        fff::metrics::SearchKind::Grep,
+    ] {
+        let summary = fff::metrics::summary(kind);
+        let as_ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+
+        let kind_info = lua.create_table()?;
+        kind_info.set("count", summary.count)?;
+        kind_info.set("p50_ms", as_ms(summary.p50))?;
+        kind_info.set("p95_ms", as_ms(summary.p95))?;
+        kind_info.set("p99_ms", as_ms(summary.p99))?;
+        kind_info.set("max_ms", as_ms(summary.max))?;
+
+        let slowest = lua.create_table()?;
+        for (i, slow) in summary.slowest.into_iter().enumerate() {
+            let entry = lua.create_table()?;
+            entry.set("query", slow.query)?;
+            entry.set("ms", as_ms(slow.duration))?;
+            slowest.set(i + 1, entry)?;
+        }
+        kind_info.set("slowest", slowest)?;
+        latency_info.set(kind.name(), kind_info)?;
+    }
+    table.set("latency", latency_info)?;
+
+    let locks_info = lua.create_table()?;
+    for lock in fff::lock_stats::LockName::ALL {
+        let stats = fff::lock_stats::stats(lock);
+        let as_ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+
+        let lock_info = lua.create_table()?;
+        lock_info.set("acquisitions", stats.acquisitions)?;
+        lock_info.set("slow_waits", stats.slow_waits)?;
+        lock_info.set("slow_holds", stats.slow_holds)?;
+        lock_info.set("total_wait_ms", as_ms(stats.total_wait))?;
+        lock_info.set("max_wait_ms", as_ms(stats.max_wait))?;
+        lock_info.set("max_hold_ms", as_ms(stats.max_hold))?;
+        if let Some(last_writer) = stats.last_writer {
+            lock_info.set("last_writer", last_writer.to_string())?;
+        }
+        locks_info.set(lock.name(), lock_info)?;
+    }
+    table.set("locks", locks_info)?;
+
     Ok(LuaValue::Table(table))
 }
 
@@ -766,14 +2177,24 @@ pub fn shorten_path(
     shorten_path_with_cache(strategy, max_size, Path::new(&path)).map_err(LuaError::RuntimeError)
 }
 
+/// Bump whenever an exported function takes or returns something different
+/// under the same name. `build.rs` hashes it with the export names into
+/// [`loader::ABI_HASH`], so a prebuilt binary with the old shapes is rejected.
+pub(crate) const LUA_ABI_VERSION: u32 = 1;
+
 fn create_exports(lua: &Lua) -> LuaResult<LuaTable> {
     let exports = lua.create_table()?;
     exports.set("init_db", lua.create_function(init_db)?)?;
+    exports.set("default_db_paths", lua.create_function(default_db_paths)?)?;
     exports.set(
         "destroy_frecency_db",
         lua.create_function(destroy_frecency_db)?,
     )?;
     exports.set("init_file_picker", lua.create_function(init_file_picker)?)?;
+    exports.set(
+        "set_config",
+        lua.create_function(runtime_config::set_config)?,
+    )?;
     exports.set(
         "restart_index_in_path",
         lua.create_function(restart_index_in_path)?,
@@ -783,20 +2204,102 @@ fn create_exports(lua: &Lua) -> LuaResult<LuaTable> {
         "fuzzy_search_files",
         lua.create_function(fuzzy_search_files)?,
     )?;
+    exports.set(
+        "fetch_results_window",
+        lua.create_function(result_window::fetch_results_window)?,
+    )?;
+    exports.set(
+        "restore_query_snapshot",
+        lua.create_function(result_window::restore_query_snapshot)?,
+    )?;
     exports.set("live_grep", lua.create_function(live_grep)?)?;
+    exports.set("grep_watch", lua.create_function(grep_watch)?)?;
+    exports.set(
+        "export_grep_results",
+        lua.create_function(export_grep_results)?,
+    )?;
+    exports.set("read_context_at", lua.create_function(read_context_at)?)?;
+    exports.set("generate_diff", lua.create_function(generate_diff)?)?;
+    exports.set(
+        "grep_in_results",
+        lua.create_function(result_window::grep_in_results)?,
+    )?;
     exports.set("track_access", lua.create_function(track_access)?)?;
+    exports.set("flush_frecency", lua.create_function(flush_frecency)?)?;
+    exports.set(
+        "set_frecency_context",
+        lua.create_function(set_frecency_context)?,
+    )?;
+    exports.set("apply_batch", lua.create_function(apply_batch)?)?;
+    exports.set("narrow_to", lua.create_function(narrow_to)?)?;
+    exports.set("clear_narrowing", lua.create_function(clear_narrowing)?)?;
+    exports.set(
+        "set_runtime_excludes",
+        lua.create_function(set_runtime_excludes)?,
+    )?;
+    exports.set(
+        "set_external_scores",
+        lua.create_function(set_external_scores)?,
+    )?;
+    exports.set(
+        "set_icon_mapping",
+        lua.create_function(icons::set_icon_mapping)?,
+    )?;
+    exports.set(
+        "set_extension_multipliers",
+        lua.create_function(set_extension_multipliers)?,
+    )?;
+    exports.set("undo_last_file_op", lua.create_function(undo_last_file_op)?)?;
     exports.set("cancel_scan", lua.create_function(cancel_scan)?)?;
     exports.set("get_scan_progress", lua.create_function(get_scan_progress)?)?;
+    exports.set(
+        "get_index_generation",
+        lua.create_function(get_index_generation)?,
+    )?;
+    exports.set(
+        "subscribe_index_events",
+        lua.create_function(index_events::subscribe_index_events)?,
+    )?;
+    exports.set(
+        "take_index_events",
+        lua.create_function(index_events::take_index_events)?,
+    )?;
     exports.set(
         "refresh_git_status",
         lua.create_function(refresh_git_status)?,
     )?;
+    exports.set(
+        "register_virtual_files",
+        lua.create_function(register_virtual_files)?,
+    )?;
+    exports.set(
+        "unregister_virtual_files",
+        lua.create_function(unregister_virtual_files)?,
+    )?;
+    exports.set("read_virtual_file", lua.create_function(read_virtual_file)?)?;
+    exports.set("register_root", lua.create_function(register_root)?)?;
+    exports.set("unregister_root", lua.create_function(unregister_root)?)?;
     exports.set("get_git_root", lua.create_function(get_git_root)?)?;
+    exports.set(
+        "complete_constraint",
+        lua.create_function(complete_constraint)?,
+    )?;
+    exports.set("find_duplicates", lua.create_function(find_duplicates)?)?;
+    exports.set("search_todos", lua.create_function(search_todos)?)?;
+    exports.set("search_keyword", lua.create_function(search_keyword)?)?;
+    exports.set("search_tests", lua.create_function(search_tests)?)?;
+    exports.set("test_at", lua.create_function(test_at)?)?;
     exports.set(
         "stop_background_monitor",
         lua.create_function(stop_background_monitor)?,
     )?;
     exports.set("init_tracing", lua.create_function(init_tracing)?)?;
+    exports.set(
+        "get_last_crash_report",
+        lua.create_function(get_last_crash_report)?,
+    )?;
+    exports.set("set_log_level", lua.create_function(set_log_level)?)?;
+    exports.set("set_profiling", lua.create_function(set_profiling)?)?;
     exports.set(
         "wait_for_initial_scan",
         lua.create_function(wait_for_initial_scan)?,
@@ -815,13 +2318,59 @@ fn create_exports(lua: &Lua) -> LuaResult<LuaTable> {
         lua.create_function(get_historical_query)?,
     )?;
     exports.set("track_grep_query", lua.create_function(track_grep_query)?)?;
+    exports.set(
+        "store_search_state",
+        lua.create_function(store_search_state)?,
+    )?;
+    exports.set(
+        "get_last_search_state",
+        lua.create_function(get_last_search_state)?,
+    )?;
+    exports.set(
+        "get_frecency_stats",
+        lua.create_function(get_frecency_stats)?,
+    )?;
+    exports.set(
+        "save_search",
+        lua.create_function(saved_searches::save_search)?,
+    )?;
+    exports.set(
+        "list_saved_searches",
+        lua.create_function(saved_searches::list_saved_searches)?,
+    )?;
+    exports.set(
+        "delete_saved_search",
+        lua.create_function(saved_searches::delete_saved_search)?,
+    )?;
+    exports.set(
+        "count_saved_search",
+        lua.create_function(saved_searches::count_saved_search)?,
+    )?;
+    exports.set(
+        "get_recent_grep_jumps",
+        lua.create_function(get_recent_grep_jumps)?,
+    )?;
+    exports.set(
+        "track_grep_completion",
+        lua.create_function(track_grep_completion)?,
+    )?;
     exports.set(
         "get_historical_grep_query",
         lua.create_function(get_historical_grep_query)?,
     )?;
     exports.set("health_check", lua.create_function(health_check)?)?;
     exports.set("shorten_path", lua.create_function(shorten_path)?)?;
+    exports.set("parse_query", lua.create_function(query::parse_query)?)?;
+    exports.set(
+        "validate_query",
+        lua.create_function(query::validate_query)?,
+    )?;
     exports.set("hex_dump", lua.create_function(hex_dump::hex_dump)?)?;
+    exports.set("fff_version", lua.create_function(loader::version)?)?;
+    exports.set(
+        "verify_binary_compat",
+        lua.create_function(loader::verify_binary_compat)?,
+    )?;
 
     Ok(exports)
 }
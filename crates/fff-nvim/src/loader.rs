@@ -0,0 +1,60 @@
+//! Version markers of the binary, so prebuilt libraries can be checked
+//! against the checked-out plugin before Lua loads them.
+//!
+//! The `extern "C"` symbols can be read with LuaJIT's FFI without running
+//! `luaopen_fff_nvim`, the Lua exports serve an already loaded binary.
+
+use mlua::prelude::*;
+use std::ffi::c_char;
+
+/// Hash of [`LUA_ABI_VERSION`](crate::LUA_ABI_VERSION) and the names of the
+/// functions exported to Lua, computed by `build.rs`. A plugin checkout
+/// whose `lib.rs` hashes to the same value calls only functions this binary
+/// has, with the arguments it takes.
+pub const ABI_HASH: &str = env!("FFF_ABI_HASH");
+
+const VERSION_C: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+const ABI_HASH_C: &str = concat!(env!("FFF_ABI_HASH"), "\0");
+
+/// Crate version, as a NUL terminated static string.
+#[unsafe(no_mangle)]
+pub extern "C" fn fff_version() -> *const c_char {
+    VERSION_C.as_ptr().cast()
+}
+
+/// [`ABI_HASH`], as a NUL terminated static string.
+#[unsafe(no_mangle)]
+pub extern "C" fn fff_abi_hash() -> *const c_char {
+    ABI_HASH_C.as_ptr().cast()
+}
+
+pub fn version(lua: &Lua, _: ()) -> LuaResult<LuaTable> {
+    let table = lua.create_table()?;
+    table.set("version", env!("CARGO_PKG_VERSION"))?;
+    table.set("commit", env!("FFF_GIT_HASH"))?;
+    table.set("abi_hash", ABI_HASH)?;
+    table.set("abi_version", crate::LUA_ABI_VERSION)?;
+    Ok(table)
+}
+
+/// Whether the binary serves a plugin checkout whose exports hash to
+/// `expected_hash`.
+pub fn verify_binary_compat(_: &Lua, expected_hash: String) -> LuaResult<bool> {
+    Ok(expected_hash.trim() == ABI_HASH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn c_symbols_match_the_build() {
+        let version = unsafe { CStr::from_ptr(fff_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+
+        let hash = unsafe { CStr::from_ptr(fff_abi_hash()) };
+        assert_eq!(hash.to_str().unwrap(), ABI_HASH);
+        assert_eq!(ABI_HASH.len(), 8);
+    }
+}
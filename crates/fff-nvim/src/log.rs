@@ -1,3 +1,3 @@
 //! Logging setup for fff-nvim — delegates to the shared fff-core::log utilities.
 
-pub use fff::log::{init_tracing, install_panic_hook};
+pub use fff::log::{init_tracing, install_panic_hook, set_log_level, set_profiling};
@@ -1,24 +1,99 @@
-use fff::git::format_git_status;
-use fff::{FileItem, GrepResult, Location, Score, SearchResult};
+use crate::display::{self, DisplayOptions};
+use crate::icons::{ICON_MAPPING, IconMapping};
+use fff::auxiliary_roots::AuxiliaryRoots;
+use fff::git::{format_git_status, format_git_status_short};
+use fff::{FacetCount, FileItem, GrepMatch, GrepResult, GrepRow, Location, Score, SearchResult};
 use mlua::prelude::*;
 
 pub struct SearchResultLua<'a> {
     inner: SearchResult<'a>,
+    query_id: Option<u64>,
+    display: Option<DisplayOptions>,
+    roots: Option<&'a AuxiliaryRoots>,
 }
 
 impl<'a> From<SearchResult<'a>> for SearchResultLua<'a> {
     fn from(inner: SearchResult<'a>) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            query_id: None,
+            display: None,
+            roots: None,
+        }
+    }
+}
+
+impl<'a> SearchResultLua<'a> {
+    /// Id of the cached ranking this result belongs to, see `fetch_results_window`.
+    pub fn with_query_id(mut self, query_id: u64) -> Self {
+        self.query_id = Some(query_id);
+        self
+    }
+
+    /// Attach pre-rendered display columns (`item.display`) to every item.
+    pub fn with_display(mut self, display: Option<DisplayOptions>) -> Self {
+        self.display = display;
+        self
+    }
+
+    /// Set `item.root` and `item.root_label` on the files of these roots.
+    pub fn with_roots(mut self, roots: &'a AuxiliaryRoots) -> Self {
+        self.roots = Some(roots);
+        self
     }
 }
 
 pub struct GrepResultLua<'a> {
     inner: GrepResult<'a>,
+    roots: Option<&'a AuxiliaryRoots>,
 }
 
 impl<'a> From<GrepResult<'a>> for GrepResultLua<'a> {
     fn from(inner: GrepResult<'a>) -> Self {
-        Self { inner }
+        Self { inner, roots: None }
+    }
+}
+
+impl<'a> GrepResultLua<'a> {
+    /// See [`SearchResultLua::with_roots`].
+    pub fn with_roots(mut self, roots: &'a AuxiliaryRoots) -> Self {
+        self.roots = Some(roots);
+        self
+    }
+}
+
+/// `{ { value = "rs", count = 37 }, ... }`, most matches first.
+fn facet_counts(lua: &Lua, counts: &[FacetCount]) -> LuaResult<LuaTable> {
+    let table = lua.create_table()?;
+    for facet in counts {
+        let entry = lua.create_table()?;
+        entry.set("value", facet.value.as_str())?;
+        entry.set("count", facet.count)?;
+        table.push(entry)?;
+    }
+    Ok(table)
+}
+
+/// `{ line }`, `{ line, col }` or `{ start = { line, col }, end = { line, col } }`.
+pub struct LuaLocation(pub Location);
+
+impl IntoLua for LuaLocation {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        let table = lua.create_table()?;
+        match self.0 {
+            Location::Line(line) => {
+                table.set("line", line)?;
+            }
+            Location::Position { line, col } => {
+                table.set("line", line)?;
+                table.set("col", col)?;
+            }
+            Location::Range { start, end } => {
+                table.set("start", LuaPosition(start))?;
+                table.set("end", LuaPosition(end))?;
+            }
+        }
+        Ok(LuaValue::Table(table))
     }
 }
 
@@ -33,11 +108,34 @@ impl IntoLua for LuaPosition {
     }
 }
 
-fn file_item_into_lua(item: &FileItem, lua: &Lua) -> LuaResult<LuaValue> {
+/// `root` and `root_label` for a file of an auxiliary root, nothing for the
+/// files of the project.
+fn set_root(table: &LuaTable, file: &FileItem, roots: Option<&AuxiliaryRoots>) -> LuaResult<()> {
+    if let Some(root) = roots.and_then(|roots| roots.root_of(file)) {
+        table.set("root", root.name())?;
+        table.set("root_label", root.label())?;
+    }
+    Ok(())
+}
+
+fn set_icon(table: &LuaTable, file_name: &str, icons: Option<&IconMapping>) -> LuaResult<()> {
+    if let Some(entry) = icons.and_then(|icons| icons.lookup(file_name)) {
+        table.set("icon", entry.icon.as_str())?;
+        table.set("hl_group", entry.hl_group.as_str())?;
+    }
+    Ok(())
+}
+
+fn file_item_into_lua(
+    item: &FileItem,
+    icons: Option<&IconMapping>,
+    roots: Option<&AuxiliaryRoots>,
+    lua: &Lua,
+) -> LuaResult<LuaValue> {
     let table = lua.create_table()?;
-    table.set("path", item.path.to_string_lossy().to_string())?;
-    table.set("relative_path", item.relative_path.clone())?;
-    table.set("name", item.file_name.clone())?;
+    table.set("path", item.path().to_string_lossy().to_string())?;
+    table.set("relative_path", item.relative_path())?;
+    table.set("name", item.file_name())?;
     table.set("size", item.size)?;
     table.set("modified", item.modified)?;
     table.set("access_frecency_score", item.access_frecency_score)?;
@@ -47,7 +145,10 @@ fn file_item_into_lua(item: &FileItem, lua: &Lua) -> LuaResult<LuaValue> {
     )?;
     table.set("total_frecency_score", item.total_frecency_score)?;
     table.set("git_status", format_git_status(item.git_status))?;
+    table.set("git_status_short", format_git_status_short(item.git_status))?;
     table.set("is_binary", item.is_binary)?;
+    set_icon(&table, item.file_name(), icons)?;
+    set_root(&table, item, roots)?;
     Ok(LuaValue::Table(table))
 }
 
@@ -61,6 +162,9 @@ fn score_into_lua(score: &Score, lua: &Lua) -> LuaResult<LuaValue> {
     table.set("distance_penalty", score.distance_penalty)?;
     table.set("current_file_penalty", score.current_file_penalty)?;
     table.set("combo_match_boost", score.combo_match_boost)?;
+    table.set("extension_boost", score.extension_boost)?;
+    table.set("project_boost", score.project_boost)?;
+    table.set("affinity_boost", score.affinity_boost)?;
     table.set("match_type", score.match_type)?;
     table.set("exact_match", score.exact_match)?;
     Ok(LuaValue::Table(table))
@@ -71,9 +175,22 @@ impl IntoLua for SearchResultLua<'_> {
         let table = lua.create_table()?;
 
         // Convert items
+        let icons = ICON_MAPPING.read().ok();
+        let icons = icons.as_ref().and_then(|guard| guard.as_ref());
+        let mut columns = self
+            .display
+            .as_ref()
+            .map(|options| display::render_columns(&self.inner.items, icons, options))
+            .unwrap_or_default()
+            .into_iter();
+
         let items_table = lua.create_table()?;
         for (i, item) in self.inner.items.iter().enumerate() {
-            items_table.set(i + 1, file_item_into_lua(item, lua)?)?;
+            let item_value = file_item_into_lua(item, icons, self.roots, lua)?;
+            if let (LuaValue::Table(item_table), Some(columns)) = (&item_value, columns.next()) {
+                item_table.set("display", columns.into_lua_table(lua)?)?;
+            }
+            items_table.set(i + 1, item_value)?;
         }
         table.set("items", items_table)?;
 
@@ -86,25 +203,20 @@ impl IntoLua for SearchResultLua<'_> {
 
         table.set("total_matched", self.inner.total_matched)?;
         table.set("total_files", self.inner.total_files)?;
+        table.set("index_generation", self.inner.index_generation)?;
+        table.set("query_id", self.query_id)?;
+        table.set("exact_matched", self.inner.match_stats.exact_matched)?;
+        table.set("typo_matched", self.inner.match_stats.typo_matched)?;
+        table.set("max_typos", self.inner.match_stats.max_typos)?;
+        table.set("suggestion", self.inner.suggestion.as_deref())?;
 
-        if let Some(location) = &self.inner.location {
-            let location_table = lua.create_table()?;
-
-            match location {
-                Location::Line(line) => {
-                    location_table.set("line", *line)?;
-                }
-                Location::Position { line, col } => {
-                    location_table.set("line", *line)?;
-                    location_table.set("col", *col)?;
-                }
-                Location::Range { start, end } => {
-                    location_table.set("start", LuaPosition(*start))?;
-                    location_table.set("end", LuaPosition(*end))?;
-                }
-            }
+        table.set("location", self.inner.location.map(LuaLocation))?;
 
-            table.set("location", location_table)?;
+        if let Some(facets) = &self.inner.facets {
+            let facets_table = lua.create_table()?;
+            facets_table.set("extensions", facet_counts(lua, &facets.extensions)?)?;
+            facets_table.set("directories", facet_counts(lua, &facets.directories)?)?;
+            table.set("facets", facets_table)?;
         }
 
         Ok(LuaValue::Table(table))
@@ -116,17 +228,24 @@ impl IntoLua for GrepResultLua<'_> {
         let table = lua.create_table()?;
 
         // Convert grep match items — each includes file metadata + match metadata
+        let icons = ICON_MAPPING.read().ok();
+        let icons = icons.as_ref().and_then(|guard| guard.as_ref());
         let items_table = lua.create_table()?;
-        for (i, m) in self.inner.matches.iter().enumerate() {
+        for (i, row) in self.inner.rows().enumerate() {
             let item = lua.create_table()?;
+            let file = match row {
+                GrepRow::FileEntry(file) | GrepRow::LineMatch(_, file) => file,
+            };
 
-            // File metadata from the deduplicated files vec
-            let file = self.inner.files[m.file_index];
-            item.set("path", file.path.to_string_lossy().to_string())?;
-            item.set("relative_path", file.relative_path.as_str())?;
-            item.set("name", file.file_name.as_str())?;
+            // File metadata
+            item.set("path", file.path().to_string_lossy().to_string())?;
+            item.set("relative_path", file.relative_path())?;
+            item.set("name", file.file_name())?;
             item.set("is_binary", file.is_binary)?;
             item.set("git_status", format_git_status(file.git_status))?;
+            item.set("git_status_short", format_git_status_short(file.git_status))?;
+            set_icon(&item, file.file_name(), icons)?;
+            set_root(&item, file, self.roots)?;
             item.set("size", file.size)?;
             item.set("modified", file.modified)?;
             item.set("total_frecency_score", file.total_frecency_score)?;
@@ -136,36 +255,26 @@ impl IntoLua for GrepResultLua<'_> {
                 file.modification_frecency_score,
             )?;
 
-            // Match metadata
-            item.set("line_number", m.line_number)?;
-            item.set("col", m.col)?;
-            item.set("byte_offset", m.byte_offset)?;
-            item.set("line_content", m.line_content.as_str())?;
-
-            // Match byte ranges within line_content
-            let ranges = lua.create_table()?;
-            for (j, &(start, end)) in m.match_byte_offsets.iter().enumerate() {
-                let range = lua.create_table()?;
-                range.set(1, start)?;
-                range.set(2, end)?;
-                ranges.set(j + 1, range)?;
-            }
-            item.set("match_ranges", ranges)?;
-
-            // Fuzzy match score (only set in fuzzy grep mode, nil otherwise)
-            if let Some(score) = m.fuzzy_score {
-                item.set("fuzzy_score", score)?;
+            item.set("kind", row.name())?;
+            if let GrepRow::LineMatch(m, _) = row {
+                set_line_match(lua, &item, m)?;
             }
 
             items_table.set(i + 1, item)?;
         }
         table.set("items", items_table)?;
 
-        table.set("total_matched", self.inner.matches.len())?;
+        table.set(
+            "total_matched",
+            self.inner.matches.len() + self.inner.file_entries.len(),
+        )?;
         table.set("total_files_searched", self.inner.total_files_searched)?;
         table.set("total_files", self.inner.total_files)?;
         table.set("filtered_file_count", self.inner.filtered_file_count)?;
         table.set("next_file_offset", self.inner.next_file_offset)?;
+        if let Some(next_page) = self.inner.next_page {
+            table.set("next_page", next_page.to_string())?;
+        }
 
         // Pass regex fallback error to Lua (nil if no error)
         if let Some(ref err) = self.inner.regex_fallback_error {
@@ -175,3 +284,43 @@ impl IntoLua for GrepResultLua<'_> {
         Ok(LuaValue::Table(table))
     }
 }
+
+/// Match metadata of a line match row.
+fn set_line_match(lua: &Lua, item: &LuaTable, m: &GrepMatch) -> LuaResult<()> {
+    item.set("line_number", m.line_number)?;
+    item.set("col", m.col)?;
+    item.set("byte_offset", m.byte_offset)?;
+    item.set("line_content", m.line_content.as_str())?;
+
+    // Match byte ranges within line_content
+    let ranges = lua.create_table()?;
+    for (j, &(start, end)) in m.match_byte_offsets.iter().enumerate() {
+        let range = lua.create_table()?;
+        range.set(1, start)?;
+        range.set(2, end)?;
+        ranges.set(j + 1, range)?;
+    }
+    item.set("match_ranges", ranges)?;
+
+    // `{ group, start, end }` spans of the regex capture groups
+    if !m.captures.is_empty() {
+        let captures = lua.create_table()?;
+        for (j, capture) in m.captures.iter().enumerate() {
+            let span = lua.create_table()?;
+            span.set(1, capture.group)?;
+            span.set(2, capture.start)?;
+            span.set(3, capture.end)?;
+            captures.set(j + 1, span)?;
+        }
+        item.set("capture_ranges", captures)?;
+    }
+
+    // Fuzzy match score (only set in fuzzy grep mode, nil otherwise)
+    if let Some(score) = m.fuzzy_score {
+        item.set("fuzzy_score", score)?;
+    }
+    if m.duplicates > 0 {
+        item.set("duplicates", m.duplicates)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! Query parsing for the Lua side.
+//!
+//! The prompt and the preview both need to know which parts of a query are
+//! constraints and which text is actually searched for. `parse_query` hands
+//! them the result of the same parser the searches use instead of letting
+//! Lua guess from the token shape. `validate_query` points at the tokens
+//! that will silently not filter the way they look like they do.
+
+use fff::GrepMode;
+use fff::query_diagnostics::validate_query as validate;
+use fff_query_parser::{Constraint, FileSearchConfig, FuzzyQuery, GrepConfig, QueryParser};
+use mlua::prelude::*;
+use std::ops::Range;
+
+use crate::lua_types::LuaLocation;
+
+/// `parse_query(query, grep?)` parses a file picker query, or a grep query
+/// when `grep` is true, into
+///
+/// ```lua
+/// {
+///   grep_text = "fn search",   -- text searched for, constraints removed
+///   fuzzy = { "fn", "search" },
+///   constraints = {
+///     -- `start`/`end` are the 0-based, end-exclusive byte span of the token
+///     { kind = "extension", value = "rs", start = 7, ["end"] = 11 },
+///     { kind = "not", inner = { kind = "path_segment", value = "test" }, start = 12, ["end"] = 18 },
+///   },
+///   location = { line = 12 } | nil,
+/// }
+/// ```
+pub fn parse_query(lua: &Lua, (query, grep): (String, Option<bool>)) -> LuaResult<LuaTable> {
+    let (parsed, spans) = if grep.unwrap_or(false) {
+        QueryParser::new(GrepConfig).parse_with_spans(&query)
+    } else {
+        QueryParser::new(FileSearchConfig).parse_with_spans(&query)
+    };
+
+    let table = lua.create_table()?;
+    table.set("grep_text", parsed.grep_text())?;
+
+    let fuzzy = match &parsed.fuzzy_query {
+        FuzzyQuery::Text(text) => vec![*text],
+        FuzzyQuery::Parts(parts) => parts.clone(),
+        FuzzyQuery::Empty => Vec::new(),
+    };
+    table.set("fuzzy", fuzzy)?;
+
+    let constraints = lua.create_table()?;
+    for (constraint, span) in parsed.constraints.iter().zip(spans) {
+        constraints.push(constraint_table(lua, constraint, Some(span))?)?;
+    }
+    table.set("constraints", constraints)?;
+    table.set("location", parsed.location.map(LuaLocation))?;
+
+    Ok(table)
+}
+
+/// `validate_query(query, grep_mode?)` checks a file picker query, or a grep
+/// query when `grep_mode` (`"plain"`, `"regex"` or `"fuzzy"`) is given, and
+/// returns a list of `{ kind, message, start, end }` warnings. `kind` is one
+/// of `unknown_key`, `unknown_git_status`, `invalid_glob`, `invalid_regex`
+/// or `empty_negation`; the span is the same as in `parse_query`.
+pub fn validate_query(
+    lua: &Lua,
+    (query, grep_mode): (String, Option<String>),
+) -> LuaResult<LuaTable> {
+    let grep_mode = grep_mode.map(|mode| match mode.as_str() {
+        "regex" => GrepMode::Regex,
+        "fuzzy" => GrepMode::Fuzzy,
+        _ => GrepMode::PlainText,
+    });
+
+    let diagnostics = lua.create_table()?;
+    for diagnostic in validate(&query, grep_mode) {
+        let table = lua.create_table()?;
+        table.set("kind", diagnostic.kind.name())?;
+        table.set("message", diagnostic.message)?;
+        table.set("start", diagnostic.span.start)?;
+        table.set("end", diagnostic.span.end)?;
+        diagnostics.push(table)?;
+    }
+    Ok(diagnostics)
+}
+
+fn constraint_table(
+    lua: &Lua,
+    constraint: &Constraint<'_>,
+    span: Option<Range<usize>>,
+) -> LuaResult<LuaTable> {
+    let table = lua.create_table()?;
+    let kind = match constraint {
+        Constraint::Extension(value) => {
+            table.set("value", *value)?;
+            "extension"
+        }
+        Constraint::Glob(value) => {
+            table.set("value", *value)?;
+            "glob"
+        }
+        Constraint::Parts(values) => {
+            table.set("values", values.to_vec())?;
+            "parts"
+        }
+        Constraint::Text(value) => {
+            table.set("value", *value)?;
+            "text"
+        }
+        Constraint::Exclude(values) => {
+            table.set("values", values.to_vec())?;
+            "exclude"
+        }
+        Constraint::PathSegment(value) => {
+            table.set("value", *value)?;
+            "path_segment"
+        }
+        Constraint::FilePath(value) => {
+            table.set("value", *value)?;
+            "file_path"
+        }
+        Constraint::FileType(value) => {
+            table.set("value", *value)?;
+            "file_type"
+        }
+        Constraint::GitStatus(status) => {
+            table.set("value", status.to_string())?;
+            "git_status"
+        }
+        Constraint::Ignored => "ignored",
+        Constraint::Root(name) => {
+            table.set("value", *name)?;
+            "root"
+        }
+        Constraint::Line(pattern) => {
+            table.set("value", *pattern)?;
+            "line"
+        }
+        Constraint::Not(inner) => {
+            table.set("inner", constraint_table(lua, inner, None)?)?;
+            "not"
+        }
+    };
+    table.set("kind", kind)?;
+
+    if let Some(span) = span {
+        table.set("start", span.start)?;
+        table.set("end", span.end)?;
+    }
+    Ok(table)
+}
@@ -0,0 +1,380 @@
+//! Cached rankings of the last fuzzy searches for virtual scrolling.
+//!
+//! Every search scores the whole index anyway, so instead of throwing away
+//! everything past the requested page we keep the top of the ranking around
+//! and serve later windows of it by absolute index. Scrolling a large result
+//! set then costs a slice and a few binary searches instead of a full rescore.
+//!
+//! The rankings of the last few queries are kept, so undoing a query edit in
+//! the prompt restores its results from here instead of searching again.
+
+use crate::FILE_PICKER;
+use crate::display::DisplayOptions;
+use crate::error;
+use crate::lua_types::{GrepResultLua, SearchResultLua};
+use crate::runtime_config;
+use fff::{Error, FileList, Location, MatchStats, Score, SearchResult};
+use mlua::prelude::*;
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Upper bound of ranked items kept after a search. Results past this index
+/// are only reachable through a regular paginated search.
+pub const MAX_CACHED_RANKING: usize = 20_000;
+/// Queries whose rankings are kept for [`restore_query_snapshot`].
+const MAX_QUERY_SNAPSHOTS: usize = 8;
+
+struct CachedRanking {
+    query_id: u64,
+    query: String,
+    /// Paths rather than indices: the file list can be reshuffled by the
+    /// watcher between two fetches, but it always stays sorted by path.
+    paths: Vec<PathBuf>,
+    ranked: RankedFiles,
+    scores: Vec<Score>,
+    total_matched: usize,
+    location: Option<Location>,
+    match_stats: MatchStats,
+    /// The windows report the generation the ranking was made at, so the UI
+    /// still sees it is stale when the files changed since.
+    index_generation: u64,
+}
+
+/// The files a search ranked, the virtual files and weighted roots are
+/// merged into the index ranking.
+pub enum RankedFiles {
+    Index,
+    /// The ignored files of an `ignored:` query.
+    Ignored,
+    /// The files of the root of a `root:` query.
+    Root(String),
+}
+
+static NEXT_QUERY_ID: AtomicU64 = AtomicU64::new(1);
+/// Latest first, one per query.
+static RANKINGS: Lazy<Mutex<VecDeque<CachedRanking>>> = Lazy::new(Default::default);
+
+/// Remember the ranking of a search that was run from offset 0 and return
+/// the id under which windows of it can be fetched.
+pub fn store_ranking(result: &SearchResult, ranked: RankedFiles, query: &str) -> u64 {
+    let query_id = NEXT_QUERY_ID.fetch_add(1, Ordering::Relaxed);
+    let ranking = CachedRanking {
+        query_id,
+        query: query.to_string(),
+        paths: result
+            .items
+            .iter()
+            .map(|item| item.path().to_path_buf())
+            .collect(),
+        ranked,
+        scores: result.scores.clone(),
+        total_matched: result.total_matched,
+        location: result.location,
+        match_stats: result.match_stats,
+        index_generation: result.index_generation,
+    };
+
+    if let Ok(mut rankings) = RANKINGS.lock() {
+        push_ranking(&mut rankings, ranking);
+    }
+    query_id
+}
+
+/// Put `ranking` first, replacing the previous ranking of the same query so
+/// the snapshots step through distinct queries.
+fn push_ranking(rankings: &mut VecDeque<CachedRanking>, ranking: CachedRanking) {
+    if rankings
+        .front()
+        .is_some_and(|latest| latest.query == ranking.query)
+    {
+        rankings.pop_front();
+    }
+    rankings.push_front(ranking);
+    rankings.truncate(MAX_QUERY_SNAPSHOTS);
+}
+
+/// Cut the requested page out of a ranking that starts at offset 0.
+pub fn page_of<'a>(ranking: &SearchResult<'a>, offset: usize, limit: usize) -> SearchResult<'a> {
+    let start = offset.min(ranking.items.len());
+    let end = match limit {
+        0 => ranking.items.len(),
+        limit => start.saturating_add(limit).min(ranking.items.len()),
+    };
+
+    SearchResult {
+        items: ranking.items[start..end].to_vec(),
+        scores: ranking.scores[start..end].to_vec(),
+        total_matched: ranking.total_matched,
+        total_files: ranking.total_files,
+        location: ranking.location,
+        match_stats: ranking.match_stats,
+        suggestion: ranking.suggestion.clone(),
+        facets: ranking.facets.clone(),
+        cursor: None,
+        index_generation: ranking.index_generation,
+    }
+}
+
+fn window_of<'a>(
+    ranking: &CachedRanking,
+    files: &'a FileList,
+    merged: &[&'a FileList],
+    start: usize,
+    count: usize,
+) -> SearchResult<'a> {
+    let start = start.min(ranking.paths.len());
+    let end = start.saturating_add(count).min(ranking.paths.len());
+
+    let mut items = Vec::with_capacity(end - start);
+    let mut scores = Vec::with_capacity(end - start);
+    for (path, score) in ranking.paths[start..end]
+        .iter()
+        .zip(&ranking.scores[start..end])
+    {
+        // Files deleted since the search are dropped from the window
+        let lookup = |files: &'a FileList| {
+            files
+                .binary_search_by(files.len(), |f| f.path().cmp(path))
+                .ok()
+                .map(|index| &files[index])
+        };
+        if let Some(file) = lookup(files).or_else(|| merged.iter().find_map(|m| lookup(m))) {
+            items.push(file);
+            scores.push(score.clone());
+        }
+    }
+
+    SearchResult {
+        items,
+        scores,
+        total_matched: ranking.total_matched,
+        total_files: files.len(),
+        location: ranking.location,
+        match_stats: ranking.match_stats,
+        suggestion: None,
+        facets: None,
+        cursor: None,
+        index_generation: ranking.index_generation,
+    }
+}
+
+fn lock_rankings() -> LuaResult<std::sync::MutexGuard<'static, VecDeque<CachedRanking>>> {
+    RANKINGS
+        .lock()
+        .map_err(|_| LuaError::RuntimeError("Result cache lock poisoned".into()))
+}
+
+/// `fetch_results_window(query_id, start, count, display?)` returns the same
+/// shape as `fuzzy_search_files` for items `start..start + count` (0-based) of
+/// the cached ranking, or `nil` when the ranking of `query_id` was dropped.
+pub fn fetch_results_window(
+    lua: &Lua,
+    (query_id, start, count, display): (u64, usize, usize, Option<DisplayOptions>),
+) -> LuaResult<LuaValue> {
+    let rankings = lock_rankings()?;
+    match rankings.iter().find(|ranking| ranking.query_id == query_id) {
+        Some(ranking) => ranking_window(lua, ranking, start, count, display),
+        None => Ok(LuaValue::Nil),
+    }
+}
+
+/// `restore_query_snapshot(n, count, display?)` returns `{ query, results }`
+/// for the `n`th last distinct query of the session (0 is the latest), with
+/// the first `count` items of its cached ranking in `results`, so the prompt
+/// can undo an edit without searching again. `nil` when it is not kept.
+/// The `index_generation` of `results` tells when the files changed since.
+pub fn restore_query_snapshot(
+    lua: &Lua,
+    (n, count, display): (usize, usize, Option<DisplayOptions>),
+) -> LuaResult<LuaValue> {
+    let rankings = lock_rankings()?;
+    let Some(ranking) = rankings.get(n) else {
+        return Ok(LuaValue::Nil);
+    };
+
+    let snapshot = lua.create_table()?;
+    snapshot.set("query", ranking.query.as_str())?;
+    snapshot.set("results", ranking_window(lua, ranking, 0, count, display)?)?;
+    Ok(LuaValue::Table(snapshot))
+}
+
+fn ranking_window(
+    lua: &Lua,
+    ranking: &CachedRanking,
+    start: usize,
+    count: usize,
+    display: Option<DisplayOptions>,
+) -> LuaResult<LuaValue> {
+    let Some(picker) = FILE_PICKER.snapshot() else {
+        return Err(error::to_lua_error(Error::FilePickerMissing));
+    };
+
+    let roots = picker.auxiliary_roots();
+    let no_files = FileList::new();
+    let (files, merged) = match &ranking.ranked {
+        RankedFiles::Index => {
+            let merged: Vec<_> = std::iter::once(picker.virtual_files().files())
+                .chain(roots.weighted().map(|root| root.files()))
+                .collect();
+            (picker.get_files(), merged)
+        }
+        RankedFiles::Ignored => (picker.ignored_files(), Vec::new()),
+        RankedFiles::Root(name) => (
+            roots.get(name).map_or(&no_files, |root| root.files()),
+            Vec::new(),
+        ),
+    };
+    let window = window_of(ranking, files, &merged, start, count);
+    SearchResultLua::from(window)
+        .with_query_id(ranking.query_id)
+        .with_display(display)
+        .with_roots(roots)
+        .into_lua(lua)
+}
+
+/// `grep_in_results(query_id, query, file_offset?, page_size?, opts?)` greps
+/// only the files ranked by the fuzzy search `query_id`, taking the same
+/// options as `live_grep`. Returns `nil` when its ranking was dropped. Only the first [`MAX_CACHED_RANKING`] matches of the search
+/// are kept, so files ranked below that are not searched.
+pub fn grep_in_results(
+    lua: &Lua,
+    (query_id, query, file_offset, page_size, opts): (
+        u64,
+        String,
+        Option<usize>,
+        Option<usize>,
+        Option<LuaTable>,
+    ),
+) -> LuaResult<LuaValue> {
+    let paths = match lock_rankings()?
+        .iter()
+        .find(|ranking| ranking.query_id == query_id)
+    {
+        Some(ranking) => ranking.paths.clone(),
+        None => return Ok(LuaValue::Nil),
+    };
+
+    let Some(picker) = FILE_PICKER.snapshot() else {
+        return Err(error::to_lua_error(Error::FilePickerMissing));
+    };
+
+    let parsed = fff::grep::parse_grep_query(&query);
+    let options = runtime_config::grep_options(file_offset, page_size, opts)?;
+
+    let result = picker.grep_in_files(&parsed, &options, &paths);
+    GrepResultLua::from(result).into_lua(lua)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fff::FileItem;
+
+    fn file(path: &str) -> FileItem {
+        FileItem::new_raw(
+            PathBuf::from(path),
+            path.trim_start_matches('/').to_string(),
+            path.rsplit('/').next().unwrap().to_string(),
+            0,
+            0,
+            None,
+            false,
+        )
+    }
+
+    fn score(total: i32) -> Score {
+        Score {
+            total,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn window_follows_ranking_into_virtual_files_and_skips_deleted_ones() {
+        let files = FileList::from(vec![file("/a.rs"), file("/b.rs"), file("/c.rs")]);
+        let ranking = CachedRanking {
+            query_id: 1,
+            query: String::new(),
+            paths: ["/c.rs", "/gone.rs", "scp://host//x.rs", "/a.rs", "/b.rs"]
+                .iter()
+                .map(PathBuf::from)
+                .collect(),
+            ranked: RankedFiles::Index,
+            scores: vec![score(50), score(40), score(30), score(20), score(10)],
+            total_matched: 5,
+            location: None,
+            match_stats: MatchStats::default(),
+            index_generation: 0,
+        };
+
+        let virtual_files = FileList::from(vec![file("scp://host//x.rs")]);
+        let window = window_of(&ranking, &files, &[&virtual_files], 0, 4);
+        let names: Vec<_> = window.items.iter().map(|f| f.file_name()).collect();
+        assert_eq!(names, ["c.rs", "x.rs", "a.rs"]);
+        assert_eq!(window.scores[2].total, 20);
+        assert_eq!(window.total_matched, 5);
+
+        assert!(
+            window_of(&ranking, &files, &[&virtual_files], 10, 3)
+                .items
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn snapshots_keep_the_latest_ranking_of_each_query() {
+        let ranking = |query_id: u64, query: &str| CachedRanking {
+            query_id,
+            query: query.to_string(),
+            paths: Vec::new(),
+            ranked: RankedFiles::Index,
+            scores: Vec::new(),
+            total_matched: 0,
+            location: None,
+            match_stats: MatchStats::default(),
+            index_generation: 0,
+        };
+
+        let mut rankings = VecDeque::new();
+        for (id, query) in ["m", "ma", "ma", "mai"].into_iter().enumerate() {
+            push_ranking(&mut rankings, ranking(id as u64, query));
+        }
+        let kept: Vec<_> = rankings
+            .iter()
+            .map(|r| (r.query_id, r.query.as_str()))
+            .collect();
+        assert_eq!(kept, [(3, "mai"), (2, "ma"), (0, "m")]);
+
+        for id in 4..20 {
+            push_ranking(&mut rankings, ranking(id, &id.to_string()));
+        }
+        assert_eq!(rankings.len(), MAX_QUERY_SNAPSHOTS);
+        assert_eq!(rankings[0].query_id, 19);
+    }
+
+    #[test]
+    fn page_of_slices_full_ranking() {
+        let files = FileList::from(vec![file("/a.rs"), file("/b.rs"), file("/c.rs")]);
+        let ranking = SearchResult {
+            items: files.iter().collect(),
+            scores: vec![score(3), score(2), score(1)],
+            total_matched: 3,
+            total_files: 3,
+            location: None,
+            match_stats: MatchStats::default(),
+            suggestion: None,
+            facets: None,
+            cursor: None,
+            index_generation: 0,
+        };
+
+        let page = page_of(&ranking, 1, 1);
+        assert_eq!(page.items[0].file_name(), "b.rs");
+        assert_eq!(page.scores[0].total, 2);
+        assert!(page_of(&ranking, 5, 1).items.is_empty());
+        assert_eq!(page_of(&ranking, 0, 0).items.len(), 3);
+    }
+}
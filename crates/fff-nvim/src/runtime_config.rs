@@ -0,0 +1,257 @@
+//! Search settings that are set once per instance.
+//!
+//! `set_config` takes the plugin configuration table and keeps the parts the
+//! searches need, so `fuzzy_search_files` and `live_grep` only receive the
+//! query, the page and the few options that really change between calls.
+//! Keys missing from the table keep their defaults.
+
+use fff::{GrepFileOrder, GrepMode, GrepScope, GrepSearchOptions};
+use mlua::prelude::*;
+use once_cell::sync::Lazy;
+use std::sync::{Arc, RwLock};
+
+use crate::FILE_PICKER;
+use crate::display::DisplayOptions;
+use crate::error::IntoLuaResult;
+
+static RUNTIME_CONFIG: Lazy<RwLock<RuntimeConfig>> = Lazy::new(Default::default);
+
+#[derive(Debug, Clone)]
+pub struct RuntimeConfig {
+    pub max_threads: usize,
+    /// `false` turns off the grep history boost, the fuzzy combo boost is
+    /// controlled by `min_combo_count` alone.
+    pub history_enabled: bool,
+    /// Save weekly search latency and index size to the history database.
+    pub perf_counters_enabled: bool,
+    pub min_combo_count: u32,
+    /// See [`fff::FuzzySearchOptions::min_combo_prefix_len`].
+    pub min_combo_prefix_len: usize,
+    pub combo_boost_score_multiplier: i32,
+    pub max_typos: Option<u16>,
+    pub adaptive_typos_threshold: usize,
+    pub min_score: Option<i32>,
+    pub min_score_ratio: f32,
+    pub grep: GrepConfig,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            max_threads: 4,
+            history_enabled: true,
+            perf_counters_enabled: false,
+            min_combo_count: 3,
+            min_combo_prefix_len: 0,
+            combo_boost_score_multiplier: 100,
+            max_typos: None,
+            adaptive_typos_threshold: 0,
+            min_score: None,
+            min_score_ratio: 0.0,
+            grep: GrepConfig::default(),
+        }
+    }
+}
+
+impl FromLua for RuntimeConfig {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let table = LuaTable::from_lua(value, lua)?;
+        let mut config = Self::default();
+
+        if let Some(max_threads) = table.get("max_threads")? {
+            config.max_threads = max_threads;
+        }
+        if let Some(history) = table.get::<Option<LuaTable>>("history")? {
+            if let Some(enabled) = history.get("enabled")? {
+                config.history_enabled = enabled;
+            }
+            if let Some(enabled) = history.get("performance_counters")? {
+                config.perf_counters_enabled = enabled;
+            }
+            if let Some(min_combo_count) = history.get("min_combo_count")? {
+                config.min_combo_count = min_combo_count;
+            }
+            if let Some(min_prefix_length) = history.get("min_combo_prefix_length")? {
+                config.min_combo_prefix_len = min_prefix_length;
+            }
+            if let Some(multiplier) = history.get("combo_boost_score_multiplier")? {
+                config.combo_boost_score_multiplier = multiplier;
+            }
+        }
+        if let Some(file_picker) = table.get::<Option<LuaTable>>("file_picker")? {
+            config.max_typos = file_picker.get("max_typos")?;
+            if let Some(threshold) = file_picker.get("adaptive_typos_threshold")? {
+                config.adaptive_typos_threshold = threshold;
+            }
+            config.min_score = file_picker.get("min_score")?;
+            if let Some(ratio) = file_picker.get("min_score_ratio")? {
+                config.min_score_ratio = ratio;
+            }
+        }
+        if let Some(grep) = table.get::<Option<LuaTable>>("grep")? {
+            config.grep = config.grep.with_overrides(&grep)?;
+        }
+
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GrepConfig {
+    pub max_file_size: u64,
+    pub max_matches_per_file: usize,
+    pub smart_case: bool,
+    pub time_budget_ms: u64,
+    pub file_order: GrepFileOrder,
+    pub scope: GrepScope,
+    /// Only grep the lines of uncommitted git hunks.
+    pub changed_only: bool,
+    pub dedupe_lines: bool,
+}
+
+impl Default for GrepConfig {
+    fn default() -> Self {
+        Self {
+            max_file_size: 10 * 1024 * 1024,
+            max_matches_per_file: 200,
+            smart_case: true,
+            time_budget_ms: 0,
+            file_order: GrepFileOrder::default(),
+            scope: GrepScope::default(),
+            changed_only: false,
+            dedupe_lines: false,
+        }
+    }
+}
+
+impl GrepConfig {
+    /// Copy of this config with the keys present in `table` replaced.
+    fn with_overrides(&self, table: &LuaTable) -> LuaResult<Self> {
+        let mut config = self.clone();
+        if let Some(max_file_size) = table.get("max_file_size")? {
+            config.max_file_size = max_file_size;
+        }
+        if let Some(max_matches_per_file) = table.get("max_matches_per_file")? {
+            config.max_matches_per_file = max_matches_per_file;
+        }
+        if let Some(smart_case) = table.get("smart_case")? {
+            config.smart_case = smart_case;
+        }
+        if let Some(time_budget_ms) = table.get("time_budget_ms")? {
+            config.time_budget_ms = time_budget_ms;
+        }
+        if let Some(file_order) = table.get::<Option<String>>("file_order")? {
+            config.file_order = GrepFileOrder::from_name(&file_order).unwrap_or_default();
+        }
+        if let Some(scope) = table.get::<Option<String>>("scope")? {
+            config.scope = GrepScope::from_name(&scope).unwrap_or_default();
+        }
+        if let Some(changed_only) = table.get("changed_only")? {
+            config.changed_only = changed_only;
+        }
+        if let Some(dedupe_lines) = table.get("dedupe_lines")? {
+            config.dedupe_lines = dedupe_lines;
+        }
+        Ok(config)
+    }
+}
+
+/// Per-call options of `fuzzy_search_files`.
+#[derive(Default)]
+pub struct SearchOptions {
+    pub current_file: Option<String>,
+    pub display: Option<DisplayOptions>,
+    /// Only return files under this directory.
+    pub search_root: Option<String>,
+    pub max_threads: Option<usize>,
+    pub min_combo_count: Option<u32>,
+    /// Return match counts per extension and top-level directory.
+    pub facets: bool,
+}
+
+impl FromLua for SearchOptions {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let table = LuaTable::from_lua(value, lua)?;
+        Ok(Self {
+            current_file: table.get("current_file")?,
+            display: table.get("display")?,
+            search_root: table.get("search_root")?,
+            max_threads: table.get("max_threads")?,
+            min_combo_count: table.get("min_combo_count")?,
+            facets: table.get::<Option<bool>>("facets")?.unwrap_or(false),
+        })
+    }
+}
+
+/// Grep options for one call: `mode`, `page_token` and `exact_page` plus any
+/// key of the `grep` config section, overriding the configured value.
+pub fn grep_options(
+    file_offset: Option<usize>,
+    page_size: Option<usize>,
+    overrides: Option<LuaTable>,
+) -> LuaResult<GrepSearchOptions> {
+    let config = current()?.grep;
+    let (config, mode, page_token, exact_page) = match overrides {
+        Some(table) => (
+            config.with_overrides(&table)?,
+            table.get::<Option<String>>("mode")?,
+            table.get::<Option<String>>("page_token")?,
+            table.get::<Option<bool>>("exact_page")?.unwrap_or(false),
+        ),
+        None => (config, None, None, false),
+    };
+
+    // diffed on every call, the hunks change while typing in another window
+    let changed_lines = match FILE_PICKER.snapshot() {
+        Some(picker) if config.changed_only => picker.changed_lines().into_lua_result()?,
+        _ => None,
+    };
+
+    let mode = match mode.as_deref() {
+        Some("regex") => GrepMode::Regex,
+        Some("fuzzy") => GrepMode::Fuzzy,
+        _ => GrepMode::PlainText, // "plain" or nil or unknown
+    };
+
+    let mut options = GrepSearchOptions {
+        max_file_size: config.max_file_size,
+        max_matches_per_file: config.max_matches_per_file,
+        smart_case: config.smart_case,
+        file_offset: file_offset.unwrap_or(0),
+        page_limit: page_size.unwrap_or(50),
+        mode,
+        time_budget_ms: config.time_budget_ms,
+        before_context: 0,
+        after_context: 0,
+        classify_definitions: false,
+        file_order: config.file_order,
+        scope: config.scope,
+        changed_lines: changed_lines.map(Arc::new),
+        resume_after_line: 0,
+        exact_page_limit: exact_page,
+        dedupe_lines: config.dedupe_lines,
+        history_boost: None,
+    };
+    if let Some(token) = page_token {
+        // the token carries the file offset of the page
+        options.resume_from(token.parse().map_err(LuaError::RuntimeError)?);
+    }
+    Ok(options)
+}
+
+pub fn current() -> LuaResult<RuntimeConfig> {
+    RUNTIME_CONFIG
+        .read()
+        .map(|config| config.clone())
+        .map_err(|_| LuaError::RuntimeError("Runtime config lock poisoned".into()))
+}
+
+/// `set_config(config)` replaces the search settings with the ones found in
+/// the plugin configuration table.
+pub fn set_config(_: &Lua, config: RuntimeConfig) -> LuaResult<()> {
+    let mut guard = RUNTIME_CONFIG
+        .write()
+        .map_err(|_| LuaError::RuntimeError("Runtime config lock poisoned".into()))?;
+    *guard = config;
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//! Named searches saved per project in the query tracker database.
+//!
+//! The match count shown next to a saved search is only computed when
+//! `count_saved_search` asks for it, and kept until the index generation
+//! changes, so listing the searches never runs them.
+
+use crate::error::IntoLuaResult;
+use crate::{FILE_PICKER, QUERY_TRACKER, runtime_config};
+use fff::file_picker::FilePicker;
+use fff::{Error, FileSearchConfig, FuzzySearchOptions, PaginationArgs, QueryParser};
+use mlua::prelude::*;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Matches collected per grep call while counting.
+const GREP_COUNT_PAGE: usize = 10_000;
+
+/// `(mode, query)` of a saved search.
+type SearchKey = (String, String);
+
+/// Match count of every counted search with the index generation it was
+/// counted at.
+static COUNTS: Lazy<Mutex<HashMap<SearchKey, (u64, usize)>>> = Lazy::new(Default::default);
+
+fn project_path() -> LuaResult<Option<PathBuf>> {
+    let file_picker = FILE_PICKER.read().into_lua_result()?;
+    Ok(file_picker
+        .as_ref()
+        .map(|picker| picker.base_path().to_path_buf()))
+}
+
+/// `save_search(name, query, mode)` saves `query` as the `"files"` or
+/// `"grep"` search `name` of the current project, replacing the one saved
+/// under that name before.
+pub fn save_search(_: &Lua, (name, query, mode): (String, String, String)) -> LuaResult<bool> {
+    if mode != "files" && mode != "grep" {
+        return Err(LuaError::RuntimeError(format!(
+            "Unknown search mode {mode:?}, expected \"files\" or \"grep\""
+        )));
+    }
+    let Some(project_path) = project_path()? else {
+        return Ok(false);
+    };
+
+    let mut query_tracker = QUERY_TRACKER.write().into_lua_result()?;
+    let Some(ref mut tracker) = *query_tracker else {
+        return Ok(false);
+    };
+    tracker
+        .save_search(&project_path, &name, &query, &mode)
+        .into_lua_result()?;
+    Ok(true)
+}
+
+/// `list_saved_searches()` returns the `{ name, query, mode }` searches of the
+/// current project in the order they were saved.
+pub fn list_saved_searches(lua: &Lua, _: ()) -> LuaResult<LuaTable> {
+    let table = lua.create_table()?;
+    let Some(project_path) = project_path()? else {
+        return Ok(table);
+    };
+
+    let query_tracker = QUERY_TRACKER.read().into_lua_result()?;
+    let Some(ref tracker) = *query_tracker else {
+        return Ok(table);
+    };
+
+    for (i, search) in tracker
+        .list_saved_searches(&project_path)
+        .into_lua_result()?
+        .into_iter()
+        .enumerate()
+    {
+        let item = lua.create_table()?;
+        item.set("name", search.name)?;
+        item.set("query", search.query)?;
+        item.set("mode", search.mode)?;
+        table.set(i + 1, item)?;
+    }
+    Ok(table)
+}
+
+/// `delete_saved_search(name)` forgets the search, returns whether it existed.
+pub fn delete_saved_search(_: &Lua, name: String) -> LuaResult<bool> {
+    let Some(project_path) = project_path()? else {
+        return Ok(false);
+    };
+
+    let mut query_tracker = QUERY_TRACKER.write().into_lua_result()?;
+    let Some(ref mut tracker) = *query_tracker else {
+        return Ok(false);
+    };
+    tracker
+        .delete_saved_search(&project_path, &name)
+        .into_lua_result()
+}
+
+/// `count_saved_search(query, mode)` returns the number of files, or grep
+/// matches, the search finds in the current index.
+pub fn count_saved_search(_: &Lua, (query, mode): (String, String)) -> LuaResult<usize> {
+    let Some(picker) = FILE_PICKER.snapshot() else {
+        return Err(crate::error::to_lua_error(Error::FilePickerMissing));
+    };
+
+    let generation = picker.index_generation();
+    let key = (mode, query);
+    let mut counts = COUNTS
+        .lock()
+        .map_err(|_| LuaError::RuntimeError("Saved search counts lock poisoned".into()))?;
+    if let Some(&(cached_generation, count)) = counts.get(&key)
+        && cached_generation == generation
+    {
+        return Ok(count);
+    }
+
+    let (mode, query) = &key;
+    let count = if mode == "grep" {
+        let parsed = fff::grep::parse_grep_query(query);
+        let mut options = runtime_config::grep_options(None, Some(GREP_COUNT_PAGE), None)?;
+        let mut count = 0;
+        loop {
+            let result = picker.grep(&parsed, &options);
+            count += result.matches.len();
+            if result.next_file_offset == 0 {
+                break count;
+            }
+            options.file_offset = result.next_file_offset;
+        }
+    } else {
+        let config = runtime_config::current()?;
+        let parsed = QueryParser::new(FileSearchConfig).parse(query);
+        FilePicker::fuzzy_search(
+            picker.files_for_query(&parsed),
+            &parsed,
+            None,
+            FuzzySearchOptions {
+                max_threads: config.max_threads,
+                project_path: Some(picker.base_path()),
+                pagination: PaginationArgs {
+                    offset: 0,
+                    limit: 1,
+                },
+                max_typos: config.max_typos,
+                adaptive_typos_threshold: config.adaptive_typos_threshold,
+                project_profile: picker.project_profile(),
+                min_score: config.min_score,
+                min_score_ratio: config.min_score_ratio,
+                index_generation: generation,
+                ..Default::default()
+            },
+        )
+        .total_matched
+    };
+
+    counts.insert(key, (generation, count));
+    Ok(count)
+}
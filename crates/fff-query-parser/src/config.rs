@@ -73,6 +73,16 @@ pub trait ParserConfig {
         true
     }
 
+    /// Should parse auxiliary root filters (e.g., root:runtime)
+    fn enable_roots(&self) -> bool {
+        true
+    }
+
+    /// Should parse matched line filters (e.g., line:!test)
+    fn enable_line_filter(&self) -> bool {
+        false
+    }
+
     /// Should parse location suffixes (e.g., file:12, file:12:4)
     /// Disabled for grep modes where colon-number patterns like localhost:8080
     /// are search text, not file locations.
@@ -80,6 +90,12 @@ pub trait ParserConfig {
         true
     }
 
+    /// Should split `files >> content` queries, turning the part before `>>`
+    /// into file constraints and parsing the rest as the search text.
+    fn enable_two_stage(&self) -> bool {
+        false
+    }
+
     /// Determine whether a token should be treated as a glob constraint.
     ///
     /// The default implementation delegates to `zlob::has_wildcards` with
@@ -133,10 +149,18 @@ impl ParserConfig for GrepConfig {
         false
     }
 
+    fn enable_line_filter(&self) -> bool {
+        true
+    }
+
     fn enable_location(&self) -> bool {
         false
     }
 
+    fn enable_two_stage(&self) -> bool {
+        true
+    }
+
     /// Only recognise globs that are clearly directory/path oriented.
     ///
     /// Characters like `?`, `[`, and bare `*` (without `/`) are extremely
@@ -196,10 +220,23 @@ impl ParserConfig for AiGrepConfig {
         false
     }
 
+    /// Agents search the project only, `root:` stays search text
+    fn enable_roots(&self) -> bool {
+        false
+    }
+
+    fn enable_line_filter(&self) -> bool {
+        true
+    }
+
     fn enable_location(&self) -> bool {
         false
     }
 
+    fn enable_two_stage(&self) -> bool {
+        true
+    }
+
     fn is_glob_pattern(&self, token: &str) -> bool {
         // First check GrepConfig's strict rules (path globs, brace expansion)
         if GrepConfig.is_glob_pattern(token) {
@@ -30,6 +30,18 @@ pub enum Constraint<'a> {
     /// Git status constraint: status:modified -> GitStatus(Modified)
     GitStatus(GitStatusFilter),
 
+    /// Search the files hidden by the ignore rules instead of the index:
+    /// ignored: -> Ignored
+    Ignored,
+
+    /// Search the files of an auxiliary root registered next to the
+    /// project instead of the index: root:runtime -> Root("runtime")
+    Root(&'a str),
+
+    /// Grep only: keep the matched lines containing this regex, checked
+    /// after the files are searched: line:^\s*// -> Line("^\s*//")
+    Line(&'a str),
+
     /// Negation constraint: !extension:rs -> Not(Extension("rs"))
     /// Negates the inner constraint
     Not(Box<Constraint<'a>>),
@@ -41,6 +53,76 @@ pub enum GitStatusFilter {
     Untracked,
     Staged,
     Unmodified,
+    /// In the git index: tracked: or status:tracked
+    Tracked,
+}
+
+impl<'a> Constraint<'a> {
+    /// Whether this is a `status:` constraint, possibly negated.
+    pub fn is_git_status(&self) -> bool {
+        match self {
+            Constraint::GitStatus(_) => true,
+            Constraint::Not(inner) => inner.is_git_status(),
+            _ => false,
+        }
+    }
+
+    /// Whether this is `ignored:`, which swaps the searched files rather
+    /// than filtering them.
+    pub fn is_ignored(&self) -> bool {
+        matches!(self, Constraint::Ignored)
+    }
+
+    /// The name of a `root:` constraint, which like `ignored:` swaps the
+    /// searched files.
+    pub fn root(&self) -> Option<&'a str> {
+        match self {
+            Constraint::Root(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for GitStatusFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            GitStatusFilter::Modified => "modified",
+            GitStatusFilter::Untracked => "untracked",
+            GitStatusFilter::Staged => "staged",
+            GitStatusFilter::Unmodified => "clean",
+            GitStatusFilter::Tracked => "tracked",
+        })
+    }
+}
+
+/// Formats the constraint back into the token that produces it, e.g.
+/// `Extension("rs")` as `*.rs` and `Not(FileType("rust"))` as `!type:rust`.
+impl std::fmt::Display for Constraint<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Constraint::Extension(ext) => write!(f, "*.{ext}"),
+            Constraint::Glob(pattern) => f.write_str(pattern),
+            Constraint::Parts(parts) => f.write_str(&parts.join(" ")),
+            Constraint::Text(text) => f.write_str(text),
+            Constraint::Exclude(parts) => {
+                for (i, part) in parts.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "!{part}")?;
+                }
+                Ok(())
+            }
+            Constraint::PathSegment(segment) => write!(f, "/{segment}/"),
+            Constraint::FilePath(path) => f.write_str(path),
+            Constraint::FileType(file_type) => write!(f, "type:{file_type}"),
+            Constraint::GitStatus(status) => write!(f, "status:{status}"),
+            Constraint::Ignored => f.write_str("ignored:"),
+            Constraint::Root(name) => write!(f, "root:{name}"),
+            Constraint::Line(pattern) => write!(f, "line:{pattern}"),
+            Constraint::Not(inner) => write!(f, "!{inner}"),
+        }
+    }
 }
 
 /// Buffer for text parts during query parsing.
@@ -38,6 +38,26 @@
 //!     _ => panic!("Expected Not constraint"),
 //! }
 //! ```
+//!
+//! # Round-trip
+//!
+//! [`FFFQuery`], [`Constraint`], [`FuzzyQuery`] and [`Location`] implement
+//! `Display`, writing the query syntax back out. Parsing the output with the
+//! same config gives the same constraints, fuzzy text and location:
+//!
+//! ```
+//! use fff_query_parser::QueryParser;
+//!
+//! let parser = QueryParser::default();
+//! let query = parser.parse("main   !*.rs  src/ status:modified");
+//! assert_eq!(query.to_string(), "!*.rs /src/ status:modified main");
+//! ```
+//!
+//! # Stability
+//!
+//! The types re-exported from the crate root follow semver. Constraint enums
+//! are matched exhaustively by users, so adding a variant is a breaking change
+//! and bumps the minor version while the crate is below 1.0.
 
 mod config;
 mod constraints;
@@ -154,6 +174,18 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_is_git_status() {
+        let parser = QueryParser::default();
+        let result = parser.parse("!status:clean status:staged *.rs foo");
+        let git: Vec<bool> = result
+            .constraints
+            .iter()
+            .map(|c| c.is_git_status())
+            .collect();
+        assert_eq!(git, vec![true, true, false]);
+    }
+
     #[test]
     fn test_file_type() {
         let parser = QueryParser::default();
@@ -228,4 +260,77 @@ mod tests {
             _ => panic!("Expected Parts fuzzy query"),
         }
     }
+
+    fn assert_round_trip<C: ParserConfig>(parser: &QueryParser<C>, query: &str) {
+        let parsed = parser.parse(query);
+        let printed = parsed.to_string();
+        let reparsed = parser.parse(&printed);
+        assert_eq!(
+            (
+                &reparsed.constraints,
+                &reparsed.fuzzy_query,
+                reparsed.location
+            ),
+            (&parsed.constraints, &parsed.fuzzy_query, parsed.location),
+            "{query:?} printed as {printed:?}"
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let parser = QueryParser::default();
+        for query in [
+            "",
+            "hello",
+            "main *.rs",
+            "one two three",
+            "!*.rs foo",
+            "**/*.rs foo",
+            "src/ lib",
+            "/src/ lib",
+            "type:rust !type:lua parser",
+            "status:modified status:clean !status:untracked",
+            "!test foo",
+            "main.rs:12",
+            "main.rs:12:4",
+            "main.rs:12-14",
+            "main.rs:12:4-20",
+            "main.rs:12:4-14:2",
+            "*.rs foo bar:3",
+        ] {
+            assert_round_trip(&parser, query);
+        }
+
+        let grep = QueryParser::new(GrepConfig);
+        for query in [
+            "fn main *.rs",
+            "TODO !test/ src/",
+            "*.rs !vendor/ impl Display",
+        ] {
+            assert_round_trip(&grep, query);
+        }
+    }
+
+    #[test]
+    fn test_display_normalizes_query() {
+        let parser = QueryParser::default();
+        assert_eq!(
+            parser.parse("  foo   *.rs  bar ").to_string(),
+            "*.rs foo bar"
+        );
+        assert_eq!(parser.parse("main.rs:12:4").to_string(), "main.rs:12:4");
+        assert_eq!(
+            Constraint::Not(Box::new(Constraint::GitStatus(GitStatusFilter::Unmodified)))
+                .to_string(),
+            "!status:clean"
+        );
+        assert_eq!(
+            Location::Range {
+                start: (3, 0),
+                end: (9, 0)
+            }
+            .to_string(),
+            ":3-9"
+        );
+    }
 }
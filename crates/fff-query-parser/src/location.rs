@@ -16,6 +16,26 @@ pub enum Location {
     Position { line: i32, col: i32 },
 }
 
+/// Formats the location as the suffix [`parse_location`] reads back:
+/// `:12`, `:12:4`, `:12-14`, `:12:4-20` or `:12:4-14:20`.
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Location::Line(line) => write!(f, ":{line}"),
+            Location::Position { line, col } => write!(f, ":{line}:{col}"),
+            Location::Range { start, end } if start.1 == 0 && end.1 == 0 => {
+                write!(f, ":{}-{}", start.0, end.0)
+            }
+            Location::Range { start, end } if start.0 == end.0 => {
+                write!(f, ":{}:{}-{}", start.0, start.1, end.1)
+            }
+            Location::Range { start, end } => {
+                write!(f, ":{}:{}-{}:{}", start.0, start.1, end.0, end.1)
+            }
+        }
+    }
+}
+
 fn parse_number_pair(location: &str, split_char: char) -> Option<(i32, i32)> {
     let mut iter = location.split(split_char);
 
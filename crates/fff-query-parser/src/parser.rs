@@ -3,6 +3,7 @@ use crate::config::ParserConfig;
 use crate::constraints::{Constraint, GitStatusFilter, TextPartsBuffer};
 use crate::glob_detect::has_wildcards;
 use crate::location::{Location, parse_location};
+use std::ops::Range;
 
 #[derive(Debug, Clone, PartialEq)]
 #[allow(clippy::large_enum_variant)]
@@ -14,7 +15,8 @@ pub enum FuzzyQuery<'a> {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FFFQuery<'a> {
-    /// The original raw query string before parsing
+    /// The original raw query string before parsing. For a two-stage
+    /// `files >> content` query this is only the content part.
     pub raw_query: &'a str,
     /// Parsed constraints (stack-allocated for ≤8 constraints)
     pub constraints: ConstraintVec<'a>,
@@ -35,6 +37,63 @@ impl<C: ParserConfig> QueryParser<C> {
     }
 
     pub fn parse<'a>(&self, query: &'a str) -> FFFQuery<'a> {
+        if self.config.enable_two_stage()
+            && let Some((file_part, content_part)) = split_two_stage(query)
+        {
+            let mut constraints: ConstraintVec<'a> = file_part
+                .split_whitespace()
+                .map(|token| parse_file_part_token(token, &self.config))
+                .collect();
+            let mut content = self.parse_stage(content_part);
+            constraints.append(&mut content.constraints);
+            content.constraints = constraints;
+            return content;
+        }
+
+        self.parse_stage(query)
+    }
+
+    /// Parses the query and returns the byte range of the token each
+    /// constraint came from, in the same order as `constraints`. Used for
+    /// highlighting constraint tokens in a prompt.
+    pub fn parse_with_spans<'a>(&self, query: &'a str) -> (FFFQuery<'a>, Vec<Range<usize>>) {
+        let parsed = self.parse(query);
+        let span_of = |token: &str| {
+            let start = token.as_ptr() as usize - query.as_ptr() as usize;
+            start..start + token.len()
+        };
+
+        let mut spans = Vec::with_capacity(parsed.constraints.len());
+        let mut remaining = parsed.constraints.iter().peekable();
+        let mut content_part = query;
+        if self.config.enable_two_stage()
+            && let Some((file_part, rest)) = split_two_stage(query)
+        {
+            // Every token of the file part is a constraint.
+            for token in file_part.split_whitespace() {
+                remaining.next();
+                spans.push(span_of(token));
+            }
+            content_part = rest;
+        }
+
+        // Constraints are pushed in token order, but a token that parses as a
+        // constraint can still end up as text (a second file path, a single
+        // file name token), so only take the span when the constraint matches.
+        for token in content_part.split_whitespace() {
+            let Some(next) = remaining.peek() else {
+                break;
+            };
+            if parse_token(token, &self.config).as_ref() == Some(*next) {
+                remaining.next();
+                spans.push(span_of(token));
+            }
+        }
+
+        (parsed, spans)
+    }
+
+    fn parse_stage<'a>(&self, query: &'a str) -> FFFQuery<'a> {
         let raw_query = query;
         let config: &C = &self.config;
         let mut constraints = ConstraintVec::new();
@@ -167,6 +226,29 @@ impl<C: ParserConfig> QueryParser<C> {
     }
 }
 
+/// Splits `files >> content` at the first standalone `>>` token. Escape it
+/// as `\>>` to search for a literal `>>`.
+fn split_two_stage(query: &str) -> Option<(&str, &str)> {
+    query.match_indices(">>").find_map(|(idx, _)| {
+        let (before, after) = (&query[..idx], &query[idx + 2..]);
+        let standalone = before.chars().next_back().is_none_or(char::is_whitespace)
+            && after.chars().next().is_none_or(char::is_whitespace);
+        standalone.then_some((before, after))
+    })
+}
+
+/// Every token of the file part of a two-stage query filters files: plain
+/// words match anywhere in the path and any wildcard token is a glob.
+fn parse_file_part_token<'a, C: ParserConfig>(token: &'a str, config: &C) -> Constraint<'a> {
+    parse_token(token, config).unwrap_or_else(|| {
+        if has_wildcards(token) {
+            Constraint::Glob(token)
+        } else {
+            Constraint::Text(token)
+        }
+    })
+}
+
 impl<'a> FFFQuery<'a> {
     /// Returns the grep search text by joining all non-constraint text tokens.
     ///
@@ -190,6 +272,48 @@ impl<'a> FFFQuery<'a> {
     }
 }
 
+impl std::fmt::Display for FuzzyQuery<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FuzzyQuery::Parts(parts) => f.write_str(&parts.join(" ")),
+            FuzzyQuery::Text(text) => f.write_str(text),
+            FuzzyQuery::Empty => Ok(()),
+        }
+    }
+}
+
+/// Formats the query so that parsing the output with the same config gives
+/// back the same constraints, fuzzy text and location. Constraints come
+/// first, followed by the fuzzy text with the location appended. Plain text
+/// constraints only exist in the file part of a two-stage query, so a query
+/// holding any is written as `files >> content`.
+impl std::fmt::Display for FFFQuery<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let two_stage = self
+            .constraints
+            .iter()
+            .any(|c| matches!(c, Constraint::Text(_) | Constraint::Parts(_)));
+
+        let mut separator = "";
+        for constraint in &self.constraints {
+            write!(f, "{separator}{constraint}")?;
+            separator = " ";
+        }
+        if two_stage {
+            f.write_str(" >>")?;
+        }
+
+        let fuzzy = self.fuzzy_query.to_string();
+        if !fuzzy.is_empty() || self.location.is_some() {
+            write!(f, "{separator}{fuzzy}")?;
+        }
+        if let Some(location) = &self.location {
+            write!(f, "{location}")?;
+        }
+        Ok(())
+    }
+}
+
 /// Strip the leading `\` from a backslash-escaped constraint token only.
 ///
 /// We strip the backslash when the next character is a constraint trigger
@@ -201,7 +325,7 @@ fn strip_leading_backslash(token: &str) -> &str {
     if token.len() > 1 && token.starts_with('\\') {
         let next = token.as_bytes()[1];
         // Only strip if the backslash is escaping a constraint trigger character
-        if next == b'*' || next == b'/' || next == b'!' {
+        if next == b'*' || next == b'/' || next == b'!' || token == "\\>>" {
             return &token[1..];
         }
     }
@@ -222,6 +346,13 @@ fn parse_token<'a, C: ParserConfig>(token: &'a str, config: &C) -> Option<Constr
         return None;
     }
 
+    // before the glob check, the pattern is a regex: line:^\s*//
+    if config.enable_line_filter()
+        && let Some(pattern) = token.strip_prefix("line:")
+    {
+        return parse_line_filter(pattern);
+    }
+
     let first_byte = token.as_bytes().first()?;
 
     match first_byte {
@@ -270,6 +401,15 @@ fn parse_token<'a, C: ParserConfig>(token: &'a str, config: &C) -> Option<Constr
                     "status" | "st" | "g" | "git" if config.enable_git_status() => {
                         return parse_git_status(value);
                     }
+                    "tracked" if value.is_empty() && config.enable_git_status() => {
+                        return Some(Constraint::GitStatus(GitStatusFilter::Tracked));
+                    }
+                    "ignored" if value.is_empty() && config.enable_git_status() => {
+                        return Some(Constraint::Ignored);
+                    }
+                    "root" if !value.is_empty() && config.enable_roots() => {
+                        return Some(Constraint::Root(value));
+                    }
                     _ => {}
                 }
             }
@@ -328,6 +468,13 @@ fn parse_token_without_negation<'a, C: ParserConfig>(
         return None;
     }
 
+    // before the glob check, the pattern is a regex: line:^\s*//
+    if config.enable_line_filter()
+        && let Some(pattern) = token.strip_prefix("line:")
+    {
+        return parse_line_filter(pattern);
+    }
+
     let first_byte = token.as_bytes().first()?;
 
     match first_byte {
@@ -368,6 +515,9 @@ fn parse_token_without_negation<'a, C: ParserConfig>(
                     "status" | "gi" | "g" | "st" if config.enable_git_status() => {
                         return parse_git_status(value);
                     }
+                    "tracked" if value.is_empty() && config.enable_git_status() => {
+                        return Some(Constraint::GitStatus(GitStatusFilter::Tracked));
+                    }
                     _ => {}
                 }
             }
@@ -377,6 +527,19 @@ fn parse_token_without_negation<'a, C: ParserConfig>(
     }
 }
 
+/// Parse matched line filter value: ^use -> Line("^use"), !test -> Not(Line("test"))
+#[inline]
+fn parse_line_filter(pattern: &str) -> Option<Constraint<'_>> {
+    match pattern.strip_prefix('!') {
+        Some(negated) if !negated.is_empty() => {
+            Some(Constraint::Not(Box::new(Constraint::Line(negated))))
+        }
+        Some(_) => None,
+        None if pattern.is_empty() => None,
+        None => Some(Constraint::Line(pattern)),
+    }
+}
+
 /// Parse path segment: /src/ -> PathSegment("src")
 #[inline]
 fn parse_path_segment(token: &str) -> Option<Constraint<'_>> {
@@ -408,7 +571,7 @@ fn parse_path_segment_trailing(token: &str) -> Option<Constraint<'_>> {
     }
 }
 
-/// Parse git status filter: modified|m|untracked|u|staged|s
+/// Parse git status filter: modified|m|untracked|u|staged|s|clean|c|tracked|t
 #[inline]
 fn parse_git_status(value: &str) -> Option<Constraint<'_>> {
     if value == "*" {
@@ -431,6 +594,10 @@ fn parse_git_status(value: &str) -> Option<Constraint<'_>> {
         return Some(Constraint::GitStatus(GitStatusFilter::Unmodified));
     }
 
+    if "tracked".starts_with(value) {
+        return Some(Constraint::GitStatus(GitStatusFilter::Tracked));
+    }
+
     None
 }
 
@@ -586,6 +753,77 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tracked_and_ignored_tokens() {
+        let parser = QueryParser::new(FileSearchConfig);
+        let result = parser.parse("ignored: tracked: foo");
+        assert_eq!(
+            result.constraints.as_slice(),
+            &[
+                Constraint::Ignored,
+                Constraint::GitStatus(GitStatusFilter::Tracked)
+            ]
+        );
+        assert_eq!(result.fuzzy_query, FuzzyQuery::Text("foo"));
+
+        let result = parser.parse("!tracked: status:t foo");
+        assert_eq!(
+            result.constraints.as_slice(),
+            &[
+                Constraint::Not(Box::new(Constraint::GitStatus(GitStatusFilter::Tracked))),
+                Constraint::GitStatus(GitStatusFilter::Tracked)
+            ]
+        );
+
+        // a value makes it a plain word
+        let result = parser.parse("ignored:foo bar");
+        assert!(result.constraints.is_empty());
+    }
+
+    #[test]
+    fn test_root_token() {
+        let result = QueryParser::new(FileSearchConfig).parse("root:runtime options");
+        assert_eq!(
+            result.constraints.as_slice(),
+            &[Constraint::Root("runtime")]
+        );
+        assert_eq!(result.constraints[0].root(), Some("runtime"));
+
+        let result = QueryParser::new(GrepConfig).parse("root:runtime nvim_buf_");
+        assert_eq!(
+            result.constraints.as_slice(),
+            &[Constraint::Root("runtime")]
+        );
+
+        // a root needs a name
+        let result = QueryParser::new(FileSearchConfig).parse("root: foo");
+        assert!(result.constraints.is_empty());
+    }
+
+    #[test]
+    fn test_line_filter_token() {
+        let parser = QueryParser::new(GrepConfig);
+        let result = parser.parse("todo line:!test line:^\\s*//");
+        assert_eq!(
+            result.constraints.as_slice(),
+            &[
+                Constraint::Not(Box::new(Constraint::Line("test"))),
+                Constraint::Line("^\\s*//"),
+            ]
+        );
+        assert_eq!(result.grep_text(), "todo");
+        assert_eq!(result.to_string(), "!line:test line:^\\s*// todo");
+        assert_eq!(
+            parser.parse("todo !line:test").constraints.as_slice(),
+            &[Constraint::Not(Box::new(Constraint::Line("test")))]
+        );
+
+        // an empty filter and the file picker keep it as text
+        assert!(parser.parse("todo line:!").constraints.is_empty());
+        let result = QueryParser::new(FileSearchConfig).parse("todo line:test");
+        assert!(result.constraints.is_empty());
+    }
+
     #[test]
     fn test_backslash_escape_extension() {
         let parser = QueryParser::new(FileSearchConfig);
@@ -624,6 +862,38 @@ mod tests {
         assert_eq!(result.constraints.len(), 0);
     }
 
+    #[test]
+    fn test_parse_with_spans() {
+        let query = "foo *.rs  !test/ bar status:modified";
+        let (parsed, spans) = QueryParser::default().parse_with_spans(query);
+        assert_eq!(parsed.constraints.len(), 3);
+        let tokens: Vec<&str> = spans.iter().map(|span| &query[span.clone()]).collect();
+        assert_eq!(tokens, vec!["*.rs", "!test/", "status:modified"]);
+    }
+
+    #[test]
+    fn test_parse_with_spans_skips_demoted_tokens() {
+        // Only the first file path is a constraint, the second one is text.
+        let query = "src/a.rs src/b.rs *.rs";
+        let (parsed, spans) = QueryParser::default().parse_with_spans(query);
+        assert_eq!(parsed.constraints.len(), 2);
+        assert_eq!(spans, vec![0..8, 18..22]);
+
+        // A lone file name is fuzzy text, not a constraint.
+        let (parsed, spans) = QueryParser::default().parse_with_spans("main.rs");
+        assert!(parsed.constraints.is_empty());
+        assert!(spans.is_empty());
+    }
+
+    #[test]
+    fn test_parse_with_spans_two_stage() {
+        let query = "picker *.rs >> fn search !test/";
+        let (parsed, spans) = QueryParser::new(GrepConfig).parse_with_spans(query);
+        assert_eq!(parsed.constraints.len(), 3);
+        let tokens: Vec<&str> = spans.iter().map(|span| &query[span.clone()]).collect();
+        assert_eq!(tokens, vec!["picker", "*.rs", "!test/"]);
+    }
+
     #[test]
     fn test_grep_text_plain_text() {
         // Multi-token plain text — no constraints
@@ -674,6 +944,48 @@ mod tests {
         assert_eq!(q.grep_text(), "!test foo");
     }
 
+    #[test]
+    fn test_two_stage_query_splits_files_and_content() {
+        let q = QueryParser::new(GrepConfig).parse("picker *.rs >> fn search");
+        assert_eq!(
+            q.constraints,
+            [Constraint::Text("picker"), Constraint::Extension("rs")]
+        );
+        assert_eq!(q.grep_text(), "fn search");
+
+        // Only the first standalone `>>` splits, content constraints still apply.
+        let q = QueryParser::new(GrepConfig).parse("src/ *test* >> a >> b !*.md");
+        assert_eq!(
+            q.constraints,
+            [
+                Constraint::PathSegment("src"),
+                Constraint::Glob("*test*"),
+                Constraint::Not(Box::new(Constraint::Extension("md"))),
+            ]
+        );
+        assert_eq!(q.grep_text(), "a >> b");
+
+        let q = QueryParser::new(GrepConfig).parse("lib.rs >>");
+        assert_eq!(q.constraints, [Constraint::Text("lib.rs")]);
+        assert_eq!(q.grep_text(), "");
+        assert_eq!(q.raw_query, "");
+    }
+
+    #[test]
+    fn test_two_stage_separator_must_be_standalone_or_escaped() {
+        let q = QueryParser::new(GrepConfig).parse("x>>2");
+        assert!(q.constraints.is_empty());
+        assert_eq!(q.grep_text(), "x>>2");
+
+        let q = QueryParser::new(GrepConfig).parse("x \\>> 2");
+        assert!(q.constraints.is_empty());
+        assert_eq!(q.grep_text(), "x >> 2");
+
+        // File search keeps `>>` as plain text.
+        let q = QueryParser::new(FileSearchConfig).parse("a >> b");
+        assert!(q.constraints.is_empty());
+    }
+
     #[test]
     fn test_grep_text_question_mark_is_text() {
         let q = QueryParser::new(GrepConfig).parse("foo? bar");
@@ -0,0 +1,314 @@
+//! WebAssembly build of the fff query parser and fuzzy matcher.
+//!
+//! The file picker itself needs a file system, LMDB and libgit2, none of which
+//! exist in a browser. Query parsing and path matching don't, so this crate
+//! exposes just those two layers to JavaScript for the docs site and web
+//! playgrounds:
+//!
+//! ```js
+//! import init, { parse, match_list } from 'fff-wasm';
+//! await init();
+//! parse('main *.rs !test src/:12');
+//! match_list('mainrs', ['src/main.rs', 'src/lib.rs']);
+//! ```
+//!
+//! Build it with `wasm-pack build crates/fff-wasm --target web`.
+//! `fff-query-parser` is used without the `zlob` feature, so glob detection
+//! falls back to its pure Rust implementation.
+
+use fff_query_parser::{
+    Constraint, FFFQuery, FileSearchConfig, FuzzyQuery, GitStatusFilter, GrepConfig, Location,
+    QueryParser,
+};
+use neo_frizbee::Scoring;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Owned, serializable form of [`FFFQuery`].
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ParsedQuery {
+    pub raw_query: String,
+    /// Text left for fuzzy matching, one entry per whitespace separated part.
+    pub fuzzy: Vec<String>,
+    pub constraints: Vec<ParsedConstraint>,
+    pub location: Option<ParsedLocation>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ParsedConstraint {
+    Extension { value: String },
+    Glob { value: String },
+    Parts { values: Vec<String> },
+    Text { value: String },
+    Exclude { values: Vec<String> },
+    PathSegment { value: String },
+    FilePath { value: String },
+    FileType { value: String },
+    GitStatus { value: &'static str },
+    Ignored,
+    Root { value: String },
+    Line { value: String },
+    Not { inner: Box<ParsedConstraint> },
+}
+
+impl From<&Constraint<'_>> for ParsedConstraint {
+    fn from(constraint: &Constraint<'_>) -> Self {
+        let owned = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+        match constraint {
+            Constraint::Extension(v) => Self::Extension {
+                value: v.to_string(),
+            },
+            Constraint::Glob(v) => Self::Glob {
+                value: v.to_string(),
+            },
+            Constraint::Parts(v) => Self::Parts { values: owned(v) },
+            Constraint::Text(v) => Self::Text {
+                value: v.to_string(),
+            },
+            Constraint::Exclude(v) => Self::Exclude { values: owned(v) },
+            Constraint::PathSegment(v) => Self::PathSegment {
+                value: v.to_string(),
+            },
+            Constraint::FilePath(v) => Self::FilePath {
+                value: v.to_string(),
+            },
+            Constraint::FileType(v) => Self::FileType {
+                value: v.to_string(),
+            },
+            Constraint::GitStatus(status) => Self::GitStatus {
+                value: match status {
+                    GitStatusFilter::Modified => "modified",
+                    GitStatusFilter::Untracked => "untracked",
+                    GitStatusFilter::Staged => "staged",
+                    GitStatusFilter::Unmodified => "unmodified",
+                    GitStatusFilter::Tracked => "tracked",
+                },
+            },
+            Constraint::Ignored => Self::Ignored,
+            Constraint::Root(v) => Self::Root {
+                value: v.to_string(),
+            },
+            Constraint::Line(v) => Self::Line {
+                value: v.to_string(),
+            },
+            Constraint::Not(inner) => Self::Not {
+                inner: Box::new(Self::from(inner.as_ref())),
+            },
+        }
+    }
+}
+
+/// `file:12`, `file:12:4` or `file:12:4-14:2`. Columns are only set when
+/// the query had them.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ParsedLocation {
+    pub line: i32,
+    pub col: Option<i32>,
+    pub end_line: Option<i32>,
+    pub end_col: Option<i32>,
+}
+
+impl From<&Location> for ParsedLocation {
+    fn from(location: &Location) -> Self {
+        match *location {
+            Location::Line(line) => Self {
+                line,
+                col: None,
+                end_line: None,
+                end_col: None,
+            },
+            Location::Position { line, col } => Self {
+                line,
+                col: Some(col),
+                end_line: None,
+                end_col: None,
+            },
+            Location::Range { start, end } => Self {
+                line: start.0,
+                col: Some(start.1),
+                end_line: Some(end.0),
+                end_col: Some(end.1),
+            },
+        }
+    }
+}
+
+impl From<&FFFQuery<'_>> for ParsedQuery {
+    fn from(query: &FFFQuery<'_>) -> Self {
+        Self {
+            raw_query: query.raw_query.to_string(),
+            fuzzy: fuzzy_parts(query).iter().map(|p| p.to_string()).collect(),
+            constraints: query.constraints.iter().map(Into::into).collect(),
+            location: query.location.as_ref().map(Into::into),
+        }
+    }
+}
+
+fn fuzzy_parts<'a>(query: &FFFQuery<'a>) -> Vec<&'a str> {
+    match &query.fuzzy_query {
+        FuzzyQuery::Text(text) => vec![*text],
+        FuzzyQuery::Parts(parts) => parts.clone(),
+        FuzzyQuery::Empty => Vec::new(),
+    }
+}
+
+/// Parse a file picker query, or a grep query when `grep` is true.
+pub fn parse_query(query: &str, grep: bool) -> ParsedQuery {
+    if grep {
+        ParsedQuery::from(&QueryParser::new(GrepConfig).parse(query))
+    } else {
+        ParsedQuery::from(&QueryParser::new(FileSearchConfig).parse(query))
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PathMatch {
+    /// Index into the haystack list.
+    pub index: u32,
+    pub score: u16,
+    /// Every query character appears in order, no typo was needed.
+    pub exact: bool,
+}
+
+/// Fuzzy match `haystacks` against the text of a file picker query the way
+/// the picker matches relative paths, best match first. Constraints and
+/// location suffixes are parsed off and ignored: they need file metadata.
+///
+/// Mirrors `match_and_score_files` in fff-core without the frecency,
+/// file name and distance adjustments that are applied on top.
+pub fn match_paths(query: &str, haystacks: &[String], max_typos: Option<u16>) -> Vec<PathMatch> {
+    let parsed = QueryParser::new(FileSearchConfig).parse(query);
+    let parts: Vec<&str> = fuzzy_parts(&parsed)
+        .into_iter()
+        .filter(|part| part.len() >= 2)
+        .collect();
+    let Some(first) = parts.first() else {
+        return Vec::new();
+    };
+
+    let max_typos = max_typos.unwrap_or((first.len() as u16 / 4).clamp(2, 6));
+    let has_uppercase = parts.iter().any(|p| p.chars().any(char::is_uppercase));
+    // Same weights as the default `ScoringWeights` of the picker.
+    let config = neo_frizbee::Config {
+        max_typos: Some(max_typos),
+        sort: false,
+        scoring: Scoring {
+            prefix_bonus: 12,
+            delimiter_bonus: 10,
+            capitalization_bonus: 8,
+            matching_case_bonus: if has_uppercase { 4 } else { 0 },
+            ..Default::default()
+        },
+    };
+
+    let mut matches = neo_frizbee::match_list(first, haystacks, &config);
+    // Every further part has to match as well, the scores add up.
+    for part in &parts[1..] {
+        let part_config = neo_frizbee::Config {
+            max_typos: Some(max_typos.min(part.len() as u16)),
+            ..config
+        };
+        matches.retain_mut(|m| {
+            let haystack = &haystacks[m.index as usize];
+            match neo_frizbee::match_list(part, &[haystack], &part_config).first() {
+                Some(part_match) => {
+                    m.score = m.score.saturating_add(part_match.score);
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+
+    let mut matches: Vec<PathMatch> = matches
+        .into_iter()
+        .map(|m| PathMatch {
+            index: m.index,
+            score: m.score,
+            exact: parts
+                .iter()
+                .all(|part| is_subsequence_ignore_case(part, &haystacks[m.index as usize])),
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.cmp(&a.score).then(a.index.cmp(&b.index)));
+    matches
+}
+
+fn is_subsequence_ignore_case(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.bytes();
+    needle
+        .bytes()
+        .all(|n| haystack.any(|h| h.eq_ignore_ascii_case(&n)))
+}
+
+fn to_js(value: &impl Serialize) -> Result<JsValue, JsError> {
+    serde_wasm_bindgen::to_value(value).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// `parse(query, grep?)` returns `{ raw_query, fuzzy, constraints, location }`.
+#[wasm_bindgen]
+pub fn parse(query: &str, grep: Option<bool>) -> Result<JsValue, JsError> {
+    to_js(&parse_query(query, grep.unwrap_or(false)))
+}
+
+/// `match_list(query, haystacks, max_typos?)` returns `{ index, score, exact }`
+/// for every matching haystack, best match first.
+#[wasm_bindgen]
+pub fn match_list(
+    query: &str,
+    haystacks: Vec<String>,
+    max_typos: Option<u16>,
+) -> Result<JsValue, JsError> {
+    to_js(&match_paths(query, &haystacks, max_typos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_keeps_constraints_and_location() {
+        let parsed = parse_query("main *.rs !test status:modified", false);
+        assert_eq!(parsed.fuzzy, vec!["main"]);
+        assert_eq!(
+            parsed.constraints,
+            vec![
+                ParsedConstraint::Extension { value: "rs".into() },
+                ParsedConstraint::Not {
+                    inner: Box::new(ParsedConstraint::Text {
+                        value: "test".into()
+                    })
+                },
+                ParsedConstraint::GitStatus { value: "modified" },
+            ]
+        );
+
+        let parsed = parse_query("main.rs:12:4", false);
+        assert_eq!(
+            parsed.location,
+            Some(ParsedLocation {
+                line: 12,
+                col: Some(4),
+                end_line: None,
+                end_col: None,
+            })
+        );
+    }
+
+    #[test]
+    fn match_list_requires_every_part() {
+        let haystacks = vec![
+            "src/main.rs".to_string(),
+            "src/lib.rs".to_string(),
+            "docs/main.md".to_string(),
+        ];
+
+        let matches = match_paths("main src", &haystacks, Some(0));
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].index, 0);
+        assert!(matches[0].exact);
+
+        assert!(match_paths("", &haystacks, None).is_empty());
+    }
+}
@@ -8,6 +8,7 @@ local M = {}
 --- @field preview_size number
 --- @field show_scrollbar boolean
 --- @field path_shorten_strategy string
+--- @field prerender_display boolean
 
 --- @class FffPreviewConfig
 --- @field enabled boolean
@@ -40,13 +41,19 @@ local M = {}
 
 --- @class FffFrecencyConfig
 --- @field enabled boolean
---- @field db_path string
+--- @field db_path string|nil
+--- @field context { time_of_day: boolean, branch: boolean, branch_scoped: boolean }
+
+--- @class FffStorageConfig
+--- @field backend 'lmdb'|'redb'
 
 --- @class FffHistoryConfig
 --- @field enabled boolean
---- @field db_path string
+--- @field db_path string|nil
 --- @field min_combo_count number
 --- @field combo_boost_score_multiplier number
+--- @field min_combo_prefix_length number
+--- @field performance_counters boolean
 
 --- @class FffGrepConfig
 --- @field max_file_size number
@@ -62,12 +69,14 @@ local M = {}
 --- @field max_results number
 --- @field max_threads number
 --- @field lazy_sync boolean
+--- @field read_only boolean
 --- @field layout FffLayoutConfig
 --- @field preview FffPreviewConfig
 --- @field keymaps FffKeymapsConfig
 --- @field hl table<string, string>
 --- @field frecency FffFrecencyConfig
 --- @field history FffHistoryConfig
+--- @field storage FffStorageConfig
 --- @field git table
 --- @field debug table
 --- @field logging table
@@ -195,6 +204,7 @@ local function init()
     max_results = 100,
     max_threads = 4,
     lazy_sync = true, -- set to false if you want file indexing to start on open
+    read_only = false, -- Never create the frecency/history databases or trigram index and refuse file operations (shared checkouts, CI)
     layout = {
       height = 0.8,
       width = 0.8,
@@ -212,6 +222,9 @@ local function init()
       -- 'middle': always uses dots (a/./b, a/../b, a/.../b)
       -- 'end': truncates from the end (home/user/projects)
       path_shorten_strategy = 'middle_number',
+      -- Let Rust return shortened path, filename and score badge columns with
+      -- every result so large pages render without per-row Lua work
+      prerender_display = false,
     },
     preview = {
       enabled = true,
@@ -247,6 +260,9 @@ local function init()
       -- multi-select keymaps for quickfix
       toggle_select = '<Tab>',
       send_to_quickfix = '<C-q>',
+      -- hide the files matching file_picker.runtime_excludes until the picker closes
+      toggle_excludes = '<C-x>',
+      undo_query = '<C-z>',
       -- this are specific for the normal mode (you can exit it using any other keybind like jj)
       focus_list = '<leader>l',
       focus_preview = '<leader>p',
@@ -289,6 +305,7 @@ local function init()
       git_sign_ignored_selected = 'FFFGitSignIgnoredSelected',
       -- Grep highlights
       grep_match = 'IncSearch', -- Highlight for matched text in grep results
+      grep_capture = 'CurSearch', -- Highlight for the capture groups of a regex match
       grep_line_number = 'LineNr', -- Highlight for :line:col location
       grep_regex_active = 'DiagnosticInfo', -- Highlight for keybind + label when regex is on
       grep_plain_active = 'Comment', -- Highlight for keybind + label when regex is off
@@ -299,17 +316,31 @@ local function init()
     -- Store file open frecency
     frecency = {
       enabled = true,
-      db_path = vim.fn.stdpath('cache') .. '/fff_nvim',
+      db_path = nil, -- Defaults to stdpath('cache') .. '/fff_nvim', the database the other fff frontends open too
+      -- Rank files opened around this hour of the day or on the checked out git branch higher
+      -- branch_scoped ranks by what was opened on the current branch alone (project-wide until it has any)
+      context = { time_of_day = false, branch = false, branch_scoped = false },
     },
     -- Store successfully opened queries with respective matches
     history = {
       enabled = true,
-      db_path = vim.fn.stdpath('data') .. '/fff_queries',
-      min_combo_count = 3, -- Minimum selections before combo boost applies (3 = boost starts on 3rd selection)
+      db_path = nil, -- Defaults to stdpath('data') .. '/fff_queries'
+      min_combo_count = 3, -- Minimum selections before combo boost applies (3 = boost starts on 3rd selection), also pins the grep match opened that often for the same query
       combo_boost_score_multiplier = 100, -- Score multiplier for combo matches (files repeatedly opened with same query)
+      min_combo_prefix_length = 0, -- Boost the file of a longer past query while typing its first N characters (0 = exact queries only)
+      performance_counters = false, -- Keep weekly search latency and index size in the history database (never leaves the machine), shown by :checkhealth
+    },
+    -- Database engine for frecency and history: 'lmdb' or 'redb'. Switch to 'redb' when
+    -- LMDB can't lock its files, e.g. on a network home directory. redb keeps its data in a
+    -- separate file and lets only one Neovim instance open it, the others run without frecency
+    -- and history; switching starts from empty data.
+    storage = {
+      backend = 'lmdb',
     },
     -- Git integration
     git = {
+      enabled = true, -- Set to false to skip repository discovery, git status and `status:` filters (huge non-git trees)
+      status_provider = 'auto', -- 'auto' reads status with `git status` in repos enabling fsmonitor/untracked cache, 'cli' always, 'library' never
       status_text_color = false, -- Apply git status colors to filename text (default: false, only sign column)
     },
     debug = {
@@ -324,6 +355,28 @@ local function init()
     -- find_files settings
     file_picker = {
       current_file_label = '(current)',
+      use_os_trash = false, -- Send files deleted from the picker to the OS trash instead of unlinking them
+      respect_ignore_files = true, -- Honor .ignore and .rgignore files like ripgrep and fd
+      same_file_system = false, -- Don't descend into other mounted filesystems (network drives, snapshots) like fd --one-file-system
+      allow_broad_root = false, -- Index all of a home directory right away instead of its top levels until confirmed
+      max_typos = nil, -- Typo budget for fuzzy matching (nil = derived from the query length)
+      adaptive_typos_threshold = 100, -- Skip typo-tolerant matching when this many files match without typos (0 = never)
+      min_score = nil, -- Drop matches scoring below this (nil = keep all)
+      min_score_ratio = 0, -- Drop matches scoring below this fraction of the best one, e.g. 0.3 (0 = keep all)
+      extension_multipliers = { lock = 50, snap = 50, ['min.js'] = 50, map = 50 }, -- Score multipliers in percent by file suffix
+      runtime_excludes = { '**/tests/**', '**/*_test.*', '**/*.test.*', '**/*.spec.*' }, -- Globs hidden while toggle_excludes is on, without a rescan
+      project_profile = {
+        enabled = true, -- Detect Cargo/npm/Python/Go projects and adjust scores of their build output and source dirs
+        deprioritized_dirs = nil, -- Directories to push down (nil = recommended for the detected project types)
+        boosted_dirs = nil, -- Directories to push up (nil = recommended for the detected project types)
+      },
+      keyword_patterns = {}, -- Named regexes indexed at scan time for search_keyword, e.g. { error_handling = 'unwrap\\(\\)|expect\\(' }
+      trigram_index = false, -- Persist a trigram index of file contents next to the frecency db to speed up grep on huge repos
+      background_io_priority = {
+        max_threads = nil, -- Threads for scanning, warmup and git status (nil = all but two cores)
+        batch_pause_ms = 0, -- Rest between batches of warmed up files to keep the CPU and fan quiet
+        skip_warmup_on_battery = false, -- Skip the content warmup while the machine runs on battery
+      },
     },
     -- grep settings
     grep = {
@@ -332,6 +385,10 @@ local function init()
       smart_case = true, -- Case-insensitive unless query has uppercase
       time_budget_ms = 150, -- Max search time in ms per call (prevents UI freeze, 0 = no limit)
       modes = { 'plain', 'regex', 'fuzzy' }, -- Available grep modes and their cycling order
+      file_order = 'frecency', -- Order files are searched in: 'frecency', 'mtime', 'path' or 'git_changed_first'
+      scope = 'all', -- Keep only the matches in 'code', 'comments' or 'strings' of known languages, or 'all'
+      changed_only = false, -- Only grep the lines of uncommitted git hunks, e.g. to find a debug print you just added
+      dedupe_lines = false, -- Show one match of the lines that read the same (license headers, generated code) with a count, per page of results
     },
   }
 
@@ -15,7 +15,7 @@ local state = {
 local function setup_global_autocmds(config)
   local group = vim.api.nvim_create_augroup('fff_file_tracking', { clear = true })
 
-  if config.frecency.enabled then
+  if config.frecency.enabled and not config.read_only then
     vim.api.nvim_create_autocmd({ 'BufEnter' }, {
       group = group,
       desc = 'Track file access for FFF frecency',
@@ -39,6 +39,12 @@ local function setup_global_autocmds(config)
         end)
       end,
     })
+
+    vim.api.nvim_create_autocmd('VimLeavePre', {
+      group = group,
+      desc = 'Write pending FFF frecency accesses',
+      callback = function() pcall(fuzzy.flush_frecency) end,
+    })
   end
 
   -- make sure that this won't work correctly if autochdir plugins are enabled
@@ -70,6 +76,22 @@ end
 --- @return boolean
 M.is_file_picker_initialized = function() return state.file_picker_initialized end
 
+--- Ask whether to index all of a home directory that was opened with the
+--- restricted scan, which only indexes its top few levels.
+--- @param path string
+M.confirm_broad_root = function(path)
+  vim.schedule(function()
+    local choice = vim.fn.confirm(
+      'FFF: ' .. path .. ' is a home directory, only its top levels were indexed. Index everything?',
+      '&Yes\n&No',
+      2
+    )
+    if choice ~= 1 then return end
+    local ok, err = pcall(fuzzy.restart_index_in_path, path, true)
+    if not ok then vim.notify('FFF: Failed to index ' .. path .. ': ' .. tostring(err), vim.log.levels.ERROR) end
+  end)
+end
+
 M.ensure_initialized = function()
   if state.initialized then return fuzzy end
   state.initialized = true
@@ -84,19 +106,55 @@ M.ensure_initialized = function()
     end
   end
 
-  local frecency_db_path = config.frecency.db_path or (vim.fn.stdpath('cache') .. '/fff_frecency')
-  local history_db_path = config.history.db_path or (vim.fn.stdpath('data') .. '/fff_history')
+  local ok, result
+  local trigram_index_dir = nil
+  if not config.read_only then
+    local paths_ok, default_paths = pcall(fuzzy.default_db_paths)
+    if not paths_ok then
+      default_paths = {
+        frecency = vim.fn.stdpath('cache') .. '/fff_nvim',
+        history = vim.fn.stdpath('data') .. '/fff_queries',
+      }
+    end
+    local frecency_db_path = config.frecency.db_path or default_paths.frecency
+    local history_db_path = config.history.db_path or default_paths.history
 
-  local ok, result = pcall(fuzzy.init_db, frecency_db_path, history_db_path, true)
-  if not ok then vim.notify('Failed to databases: ' .. tostring(result), vim.log.levels.WARN) end
+    ok, result = pcall(fuzzy.init_db, frecency_db_path, history_db_path, true, config.storage.backend)
+    if not ok then vim.notify('Failed to databases: ' .. tostring(result), vim.log.levels.WARN) end
 
-  ok, result = pcall(fuzzy.init_file_picker, config.base_path)
+    if config.file_picker.trigram_index then
+      trigram_index_dir = vim.fn.fnamemodify(frecency_db_path, ':h') .. '/fff_trigrams'
+    end
+  end
+
+  ok, result = pcall(fuzzy.set_config, config)
+  if not ok then vim.notify('Failed to apply search config: ' .. tostring(result), vim.log.levels.WARN) end
+
+  local restricted
+  ok, result, restricted = pcall(fuzzy.init_file_picker, config.base_path, {
+    use_os_trash = config.file_picker.use_os_trash,
+    respect_ignore_files = config.file_picker.respect_ignore_files,
+    same_file_system = config.file_picker.same_file_system,
+    git_enabled = config.git and config.git.enabled,
+    git_status_provider = config.git and config.git.status_provider,
+    project_profile = config.file_picker.project_profile,
+    keyword_patterns = config.file_picker.keyword_patterns,
+    trigram_index_dir = trigram_index_dir,
+    background_io_priority = config.file_picker.background_io_priority,
+    allow_broad_root = config.file_picker.allow_broad_root,
+    read_only = config.read_only,
+  })
   if not ok then
     vim.notify('Failed to initialize file picker: ' .. tostring(result), vim.log.levels.ERROR)
     return fuzzy
   end
 
   state.file_picker_initialized = true
+  if restricted then M.confirm_broad_root(config.base_path) end
+
+  local icon_mapping = require('fff.file_picker.icons').build_static_mapping()
+  if icon_mapping then pcall(fuzzy.set_icon_mapping, icon_mapping) end
+  pcall(fuzzy.set_extension_multipliers, config.file_picker.extension_multipliers)
   setup_global_autocmds(config)
 
   local git_utils = require('fff.git_utils')
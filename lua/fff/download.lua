@@ -2,6 +2,7 @@ local M = {}
 local system = require('fff.utils.system')
 local fs_utils = require('fff.utils.fs')
 local fff_version = require('fff.utils.version')
+local abi = require('fff.utils.abi')
 
 local GITHUB_REPO = 'dmtrKovalenko/fff.nvim'
 
@@ -13,10 +14,17 @@ local function get_binary_path(plugin_dir)
   return binary_dir .. '/libfff_nvim.' .. extension
 end
 
+--- ABI hash a binary for the checkout containing `plugin_dir` must have
+local function expected_abi_hash(plugin_dir) return abi.expected_hash(vim.fn.fnamemodify(plugin_dir, ':h')) end
+
 local function binary_exists(plugin_dir)
   local binary_path = get_binary_path(plugin_dir)
   local stat = vim.uv.fs_stat(binary_path)
-  if stat and stat.type == 'file' then return true end
+  if stat and stat.type == 'file' then
+    -- a binary left from before a plugin update may lack functions the Lua side calls
+    local expected = expected_abi_hash(plugin_dir)
+    return not expected or abi.binary_hash(binary_path) == expected
+  end
 
   -- On Windows the rename over a loaded DLL fails, so a verified binary may be
   -- left at binary_path .. '.tmp'. Promote it now that the old session is gone.
@@ -123,6 +131,20 @@ local function download_from_github(version, binary_path, opts, callback)
         return
       end
 
+      local actual_abi = opts.expected_abi_hash and abi.binary_hash(tmp_path)
+      if opts.expected_abi_hash and actual_abi ~= opts.expected_abi_hash then
+        vim.uv.fs_unlink(tmp_path)
+        callback(
+          false,
+          string.format(
+            'Downloaded binary does not match this checkout of fff.nvim (abi %s, expected %s)',
+            actual_abi or 'unknown',
+            opts.expected_abi_hash
+          )
+        )
+        return
+      end
+
       -- Atomically replace the live binary only after successful validation.
       -- On Windows the old .dll may be locked by the current process, so rename can
       -- fail if fff is already loaded. In that case, leave the verified .tmp on disk
@@ -166,7 +188,12 @@ function M.ensure_downloaded(opts, callback)
     end
 
     local binary_path = get_binary_path(plugin_dir)
-    download_from_github(release_tag, binary_path, opts, callback)
+    download_from_github(release_tag, binary_path, {
+      proxy = opts.proxy,
+      extra_curl_args = opts.extra_curl_args,
+      -- a pinned version is the user's choice, only derived tags must match the checkout
+      expected_abi_hash = not opts.version and expected_abi_hash(plugin_dir) or nil,
+    }, callback)
   end
 
   if opts.version then
@@ -274,6 +301,13 @@ function M.download_or_build_binary()
   if fatal_error then error(fatal_error) end
 end
 
+--- ABI hash of this checkout, compare with `verify_binary_compat` of a loaded binary
+--- @return string|nil
+function M.get_expected_abi_hash()
+  local plugin_dir = vim.fn.fnamemodify(debug.getinfo(1, 'S').source:sub(2), ':h:h')
+  return expected_abi_hash(plugin_dir)
+end
+
 function M.get_binary_path()
   local plugin_dir = vim.fn.fnamemodify(debug.getinfo(1, 'S').source:sub(2), ':h:h')
   return get_binary_path(plugin_dir)
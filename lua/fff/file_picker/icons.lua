@@ -73,6 +73,62 @@ function M.get_icon(filename, extension, is_directory)
   return nil, nil
 end
 
+--- Build a static extension/filename -> icon table for the Rust side, so search
+--- results arrive with `icon` and `hl_group` already attached.
+--- @return table|nil Mapping `{ extensions = {}, filenames = {}, default = {} }`, nil if unsupported
+function M.build_static_mapping()
+  if not M.setup() then return nil end
+
+  local mapping = { extensions = {}, filenames = {} }
+
+  if M.provider_name == 'nvim-web-devicons' then
+    local devicons = M.provider
+    if not (devicons.get_icons_by_extension and devicons.get_icons_by_filename) then return nil end
+
+    local function convert(icons, target)
+      for key, data in pairs(icons) do
+        if data.icon and data.name then target[key] = { icon = data.icon, hl_group = 'DevIcon' .. data.name } end
+      end
+    end
+
+    convert(devicons.get_icons_by_extension(), mapping.extensions)
+    convert(devicons.get_icons_by_filename(), mapping.filenames)
+
+    local default = devicons.get_default_icon and devicons.get_default_icon()
+    if default and default.icon and default.name then
+      mapping.default = { icon = default.icon, hl_group = 'DevIcon' .. default.name }
+    end
+  elseif M.provider_name == 'mini.icons' then
+    local mini = M.provider
+    if not mini.list then return nil end
+
+    local function convert(category, target)
+      for _, name in ipairs(mini.list(category)) do
+        local icon, hl = mini.get(category, name)
+        if icon and icon ~= '' and hl then target[name] = { icon = icon, hl_group = hl } end
+      end
+    end
+
+    convert('extension', mapping.extensions)
+    convert('file', mapping.filenames)
+
+    local icon, hl = mini.get('default', 'file')
+    if icon and hl then mapping.default = { icon = icon, hl_group = hl } end
+  else
+    return nil
+  end
+
+  return mapping
+end
+
+--- Icon for a search result item, preferring the one attached by Rust
+--- @param item table File item from Rust
+--- @return string|nil, string|nil Icon and highlight group
+function M.get_item_icon(item)
+  if item.icon then return item.icon, item.hl_group end
+  return M.get_icon(item.name, item.extension, false)
+end
+
 --- Check if directories are supported by current provider
 --- @return boolean True if directory icons are supported
 function M.supports_directories() return M.setup() and M.provider_name ~= nil end
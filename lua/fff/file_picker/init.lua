@@ -52,33 +52,34 @@ end
 --- @param min_combo_count_override number|nil Optional override for min_combo_count (nil uses config)
 --- @param page_index number Page index (0-based: 0, 1, 2, ...)
 --- @param page_size number|nil Items per page (nil uses config default)
+--- @param display table|nil `{ width, path_shorten_strategy }` to get pre-rendered `item.display` columns
+--- @param search_root string|nil Only return files under this directory (absolute or relative to the base path)
 --- @return table List of matching files
-function M.search_files_paginated(query, current_file, max_threads, min_combo_count_override, page_index, page_size)
-  local config = require('fff.conf').get()
+function M.search_files_paginated(
+  query,
+  current_file,
+  max_threads,
+  min_combo_count_override,
+  page_index,
+  page_size,
+  display,
+  search_root
+)
   if not M.state.initialized then return {} end
 
-  max_threads = max_threads or config.max_threads or 4
   page_index = page_index or 0
   page_size = page_size or 0
 
-  local min_combo_count = min_combo_count_override
-  if min_combo_count == nil then min_combo_count = config.history and config.history.min_combo_count or 3 end
-
-  local combo_boost_score_multiplier = config.history and config.history.combo_boost_score_multiplier or 100
-
   -- Convert page_index to offset (Rust expects offset in items, not page number)
   local offset = page_index * page_size
 
-  local ok, search_result = pcall(
-    fuzzy.fuzzy_search_files,
-    query,
-    max_threads,
-    current_file,
-    combo_boost_score_multiplier,
-    min_combo_count,
-    offset,
-    page_size
-  )
+  local ok, search_result = pcall(fuzzy.fuzzy_search_files, query, offset, page_size, {
+    current_file = current_file,
+    max_threads = max_threads,
+    min_combo_count = min_combo_count_override,
+    display = display,
+    search_root = search_root,
+  })
 
   if not ok then
     vim.notify('Failed to search files: ' .. tostring(search_result), vim.log.levels.ERROR)
@@ -89,13 +90,51 @@ function M.search_files_paginated(query, current_file, max_threads, min_combo_co
   return search_result.items
 end
 
+--- Fetch a window of the last search ranking by absolute index, without rescoring
+--- @param start number 0-based index of the first item
+--- @param count number Number of items
+--- @param display table|nil Same as in `search_files_paginated`
+--- @return table|nil List of files, nil when the ranking is no longer cached
+function M.fetch_results_window(start, count, display)
+  local last = M.state.last_search_result
+  if not M.state.initialized or not last or not last.query_id then return nil end
+
+  local ok, window = pcall(fuzzy.fetch_results_window, last.query_id, start, count, display)
+  if not ok or not window or #window.items == 0 then return nil end
+
+  M.state.last_search_result = window
+  return window.items
+end
+
+--- Restore an earlier query of the session together with its cached results
+--- @param n number How many distinct queries to go back, 0 is the latest
+--- @param count number Number of items of the restored page
+--- @param display table|nil Same as in `search_files_paginated`
+--- @return string|nil query, table|nil items; nil when the query is no longer cached
+function M.restore_query_snapshot(n, count, display)
+  if not M.state.initialized then return nil end
+
+  local ok, snapshot = pcall(fuzzy.restore_query_snapshot, n, count, display)
+  if not ok or not snapshot then return nil end
+
+  M.state.last_search_result = snapshot.results
+  return snapshot.query, snapshot.results.items
+end
+
 --- Get the last search result metadata
---- @return table Search metadata with total_matched and total_files
+--- @return table Search metadata with total_matched, total_files, exact/typo match counts and suggestion
 function M.get_search_metadata()
-  if not M.state.last_search_result then return { total_matched = 0, total_files = 0 } end
+  if not M.state.last_search_result then
+    return { total_matched = 0, total_files = 0, exact_matched = 0, typo_matched = 0, max_typos = 0 }
+  end
   return {
     total_matched = M.state.last_search_result.total_matched,
     total_files = M.state.last_search_result.total_files,
+    exact_matched = M.state.last_search_result.exact_matched or 0,
+    typo_matched = M.state.last_search_result.typo_matched or 0,
+    max_typos = M.state.last_search_result.max_typos or 0,
+    suggestion = M.state.last_search_result.suggestion,
+    index_generation = M.state.last_search_result.index_generation,
   }
 end
 
@@ -163,6 +202,17 @@ function M.get_scan_progress()
   return result
 end
 
+--- Get the generation of the index, which changes whenever a scan or the file
+--- watcher changed the indexed files
+--- @return number|nil
+function M.get_index_generation()
+  if not M.state.initialized then return nil end
+
+  local ok, result = pcall(fuzzy.get_index_generation)
+  if not ok then return nil end
+  return result
+end
+
 --- Refresh git status on cached files (call after git status loading completes)
 --- @return table List of files with updated git status
 function M.refresh_git_status()
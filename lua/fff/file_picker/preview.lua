@@ -414,10 +414,13 @@ function M.create_file_info_content(file, info, file_index)
     table.insert(
       lines,
       string.format(
-        'Score Modifiers: frec_boost=%d, dist_penalty=%d, current_penalty=%d',
+        'Score Modifiers: frec_boost=%d, dist_penalty=%d, current_penalty=%d, ext_boost=%d, project_boost=%d, affinity_boost=%d',
         score.frecency_boost,
         score.distance_penalty,
-        score.current_file_penalty or 0
+        score.current_file_penalty or 0,
+        score.extension_boost or 0,
+        score.project_boost or 0,
+        score.affinity_boost or 0
       )
     )
   else
@@ -579,6 +582,29 @@ function M.preview_file(file_path, bufnr)
   return true
 end
 
+--- Preview a file registered with `register_virtual_files`, its contents
+--- come from the registry instead of the disk
+function M.preview_virtual_file(file_path, bufnr, content)
+  local lines = vim.split(content, '\n', { plain = true })
+  M.clear_preview_visual_state(bufnr)
+  set_buffer_lines(bufnr, lines)
+
+  attach_preview_highlighter(bufnr, utils.detect_filetype(file_path) or 'text')
+  vim.api.nvim_set_option_value('modifiable', false, { buf = bufnr })
+  vim.api.nvim_set_option_value('readonly', true, { buf = bufnr })
+  vim.api.nvim_set_option_value('buftype', 'nofile', { buf = bufnr })
+
+  M.state.has_more_content = false
+  M.state.content_height = #lines
+  M.state.scroll_offset = 0
+
+  local generation = M.state.preview_generation
+  vim.schedule(function()
+    if M.state.preview_generation == generation then M.apply_location_highlighting(bufnr) end
+  end)
+  return true
+end
+
 -- Hex preview highlight support: dynamically create hl groups from "#rrggbb"
 local hex_ns = nil
 local hex_hl_cache = {}
@@ -727,6 +753,9 @@ function M.preview(file_path, bufnr, location, is_binary)
   M.state.bufnr = bufnr
   M.state.location = location
 
+  local virtual_ok, virtual_content = pcall(rust.read_virtual_file, file_path)
+  if virtual_ok and virtual_content then return M.preview_virtual_file(file_path, bufnr, virtual_content) end
+
   if image.is_image(file_path) then
     M.clear_buffer(bufnr)
 
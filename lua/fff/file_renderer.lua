@@ -14,9 +14,22 @@ local M = {}
 --- @field access_frecency_score number Access-based frecency score
 --- @field modification_frecency_score number Modification-based frecency score
 --- @field git_status string|nil Git status string (e.g. 'modified', 'untracked') if file is in git repo
+--- @field icon string|nil Icon attached by Rust when a static icon mapping is registered
+--- @field hl_group string|nil Highlight group for `icon`
+--- @field git_status_short string|nil Compact git status indicator ('M', 'A', '??', 'R', ...), empty when clean
+--- @field display FileItemDisplay|nil Pre-rendered columns when `layout.prerender_display` is enabled
+--- @field root string|nil Name of the root registered with `register_root` the file comes from, nil for project files
+--- @field root_label string|nil Label of that root, shown as a `[label]` badge
 --- internal:
 --- @field _has_group_header boolean Internal flag for render_line to indicate if this item has a combo header line (not from Rust)
 
+--- @class FileItemDisplay
+--- @field width number Width the columns were fitted into (icon excluded)
+--- @field filename string
+--- @field dir string Parent directory shortened to fit next to the filename
+--- @field parent_dir string Full parent directory relative to the base path
+--- @field score_badge string Frecency indicator, empty when the file has no score
+
 --- Render a file item line
 --- @param item FileItem File item from Rust
 --- @param ctx ListRenderContext Render context with all state
@@ -29,11 +42,13 @@ function M.render_line(item, ctx, item_idx)
   local has_combo = item_idx == 1 and ctx.has_combo and ctx.combo_header_line
   if has_combo then table.insert(lines, ctx.combo_header_line) end
 
-  local icon, _ = icons.get_icon(item.name, item.extension, false)
+  local icon, _ = icons.get_item_icon(item)
 
   -- Build frecency indicator (debug mode only)
   local frecency = ''
-  if ctx.debug_enabled then
+  if ctx.debug_enabled and item.display then
+    frecency = item.display.score_badge
+  elseif ctx.debug_enabled then
     local total = item.total_frecency_score or 0
     local access = item.access_frecency_score or 0
     local mod = item.modification_frecency_score or 0
@@ -87,7 +102,7 @@ function M.apply_highlights(item, ctx, item_idx, buf, ns_id, line_idx, line_cont
   local is_current_file = score and score.current_file_penalty and score.current_file_penalty < 0
 
   -- Get icon and paths
-  local icon, icon_hl_group = icons.get_icon(item.name, item.extension, false)
+  local icon, icon_hl_group = icons.get_item_icon(item)
   local icon_width = icon and (vim.fn.strdisplaywidth(icon) + 1) or 0
   local available_width = math.max(ctx.max_path_width - icon_width, 40)
   local filename, dir_path = ctx.format_file_display(item, available_width)
@@ -172,6 +187,12 @@ function M.apply_highlights(item, ctx, item_idx, buf, ns_id, line_idx, line_cont
       virt_text = { { ' ' .. ctx.config.file_picker.current_file_label, hl } },
       virt_text_pos = 'right_align',
     })
+  elseif item.root_label then
+    -- Files of an auxiliary root
+    vim.api.nvim_buf_set_extmark(buf, ns_id, line_idx - 1, 0, {
+      virt_text = { { ' [' .. item.root_label .. ']', is_cursor and ctx.config.hl.cursor or 'Comment' } },
+      virt_text_pos = 'right_align',
+    })
   end
 
   -- 7. Git sign
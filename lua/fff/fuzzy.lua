@@ -7,6 +7,7 @@ if not ok then error('Failed to load fff.rust module: ' .. rust_module) end
 
 -- export all functions from the Rust module
 M.init_db = rust_module.init_db
+M.default_db_paths = rust_module.default_db_paths
 M.destroy_db = rust_module.destroy_db
 M.access = rust_module.access
 M.set_provider_items = rust_module.set_provider_items
@@ -19,19 +20,45 @@ M.init_file_picker = rust_module.init_file_picker
 M.restart_index_in_path = rust_module.restart_index_in_path
 M.scan_files = rust_module.scan_files
 M.get_cached_files = rust_module.get_cached_files
+M.set_config = rust_module.set_config
 M.fuzzy_search_files = rust_module.fuzzy_search_files
+M.fetch_results_window = rust_module.fetch_results_window
+M.restore_query_snapshot = rust_module.restore_query_snapshot
 M.track_access = rust_module.track_access
+M.flush_frecency = rust_module.flush_frecency
+M.set_frecency_context = rust_module.set_frecency_context
+M.set_external_scores = rust_module.set_external_scores
+M.set_runtime_excludes = rust_module.set_runtime_excludes
+M.narrow_to = rust_module.narrow_to
+M.clear_narrowing = rust_module.clear_narrowing
 M.add_file = rust_module.add_file
 M.remove_file = rust_module.remove_file
 M.cancel_scan = rust_module.cancel_scan
 M.get_scan_progress = rust_module.get_scan_progress
+M.get_index_generation = rust_module.get_index_generation
+M.subscribe_index_events = rust_module.subscribe_index_events
+M.take_index_events = rust_module.take_index_events
 M.is_scanning = rust_module.is_scanning
 M.refresh_git_status = rust_module.refresh_git_status
+M.register_virtual_files = rust_module.register_virtual_files
+M.unregister_virtual_files = rust_module.unregister_virtual_files
+M.read_virtual_file = rust_module.read_virtual_file
+M.register_root = rust_module.register_root
+M.unregister_root = rust_module.unregister_root
 M.update_single_file_frecency = rust_module.update_single_file_frecency
 M.stop_background_monitor = rust_module.stop_background_monitor
 M.cleanup_file_picker = rust_module.cleanup_file_picker
 M.init_tracing = rust_module.init_tracing
+M.set_log_level = rust_module.set_log_level
+M.get_last_crash_report = rust_module.get_last_crash_report
+M.set_profiling = rust_module.set_profiling
 M.wait_for_initial_scan = rust_module.wait_for_initial_scan
+M.set_icon_mapping = rust_module.set_icon_mapping
+M.set_extension_multipliers = rust_module.set_extension_multipliers
+
+-- File operations
+M.apply_batch = rust_module.apply_batch
+M.undo_last_file_op = rust_module.undo_last_file_op
 
 -- Query tracking functions
 M.init_query_db = rust_module.init_query_db
@@ -39,6 +66,15 @@ M.destroy_query_db = rust_module.destroy_query_db
 M.track_query_completion = rust_module.track_query_completion
 M.get_historical_query = rust_module.get_historical_query
 M.track_grep_query = rust_module.track_grep_query
+M.track_grep_completion = rust_module.track_grep_completion
+M.get_recent_grep_jumps = rust_module.get_recent_grep_jumps
+M.store_search_state = rust_module.store_search_state
+M.get_last_search_state = rust_module.get_last_search_state
+M.get_frecency_stats = rust_module.get_frecency_stats
+M.save_search = rust_module.save_search
+M.list_saved_searches = rust_module.list_saved_searches
+M.delete_saved_search = rust_module.delete_saved_search
+M.count_saved_search = rust_module.count_saved_search
 M.get_historical_grep_query = rust_module.get_historical_grep_query
 
 -- Git functions
@@ -46,9 +82,24 @@ M.get_git_root = rust_module.get_git_root
 
 -- Grep functions
 M.live_grep = rust_module.live_grep
+M.read_context_at = rust_module.read_context_at
+M.generate_diff = rust_module.generate_diff
+M.grep_watch = rust_module.grep_watch
+M.export_grep_results = rust_module.export_grep_results
+M.grep_in_results = rust_module.grep_in_results
 
 -- Utility functions
 M.health_check = rust_module.health_check
+M.fff_version = rust_module.fff_version
+M.verify_binary_compat = rust_module.verify_binary_compat
 M.shorten_path = rust_module.shorten_path
+M.parse_query = rust_module.parse_query
+M.validate_query = rust_module.validate_query
+M.complete_constraint = rust_module.complete_constraint
+M.find_duplicates = rust_module.find_duplicates
+M.search_todos = rust_module.search_todos
+M.search_keyword = rust_module.search_keyword
+M.search_tests = rust_module.search_tests
+M.test_at = rust_module.test_at
 
 return M
@@ -182,7 +182,30 @@ local function apply_match_highlights(item, ctx, item_idx, buf, ns_id, row, line
     end
   end
 
-  -- 6. Selection marker (per-occurrence in grep mode)
+  -- Capture groups of a regex match drawn over the match itself
+  if item.capture_ranges then
+    for _, capture in ipairs(item.capture_ranges) do
+      local hl_start = content_start + capture[2]
+      local hl_end = content_start + capture[3]
+      if hl_end > hl_start and hl_end <= #line_content then
+        pcall(vim.api.nvim_buf_set_extmark, buf, ns_id, row, hl_start, {
+          end_col = hl_end,
+          hl_group = config.hl.grep_capture or 'CurSearch',
+          priority = 201,
+        })
+      end
+    end
+  end
+
+  -- 6. Count of the identical lines folded into this one by `dedupe_lines`
+  if item.duplicates and item.duplicates > 0 then
+    vim.api.nvim_buf_set_extmark(buf, ns_id, row, 0, {
+      virt_text = { { string.format(' +%d identical', item.duplicates), 'Comment' } },
+      virt_text_pos = 'right_align',
+    })
+  end
+
+  -- 7. Selection marker (per-occurrence in grep mode)
   if ctx.selected_items then
     local key = string.format('%s:%d:%d', item.path, item.line_number or 0, item.col or 0)
     if ctx.selected_items[key] then
@@ -197,11 +220,18 @@ end
 
 --- Render a single item's lines (called by list_renderer's generate_item_lines).
 --- Returns 2 lines [header, match] for the first match of a file group,
---- or 1 line [match] for subsequent matches in the same file.
+--- or 1 line [match] for subsequent matches in the same file, or 1 file line for a file row.
 ---@param item FileItem Grep match item
 ---@param ctx table Render context
 ---@return string[]
 function M.render_line(item, ctx)
+  -- A whole file row (listed by an empty query) is just the file line
+  if item.kind == 'file' then
+    ctx._grep_last_file = nil
+    item._has_group_header = false
+    return { build_group_header(item, ctx) }
+  end
+
   -- Track file grouping across the render pass via ctx
   -- ctx._grep_last_file is reset each render (ctx is fresh per render_list call)
   local is_new_group = (item.path ~= ctx._grep_last_file)
@@ -232,6 +262,11 @@ end
 function M.apply_highlights(item, ctx, item_idx, buf, ns_id, line_idx, line_content)
   local row = line_idx - 1 -- 0-based for nvim API
 
+  if item.kind == 'file' then
+    file_renderer.apply_highlights(item, ctx, item_idx, buf, ns_id, line_idx, line_content)
+    return
+  end
+
   -- Apply match line highlights
   apply_match_highlights(item, ctx, item_idx, buf, ns_id, row, line_content)
 
@@ -12,10 +12,30 @@ local fuzzy = require('fff.fuzzy')
 ---@field total_files number Total indexed files
 ---@field filtered_file_count number Total searchable files after filtering
 ---@field next_file_offset number File offset to pass for the next page (0 = no more results)
+---@field next_page string|nil Token to pass as the `page_token` option for the next page, can resume inside a file
 ---@field regex_fallback_error string|nil Error message if regex compilation failed and search fell back to literal
 
 local last_result = nil
 
+--- Per-call options for the Rust grep functions, settings not given here come from `set_config`.
+---@param config? table Grep configuration overrides
+---@param grep_mode? string
+---@return table
+local function grep_opts(config, grep_mode)
+  local conf = config or {}
+  return {
+    mode = grep_mode or 'plain',
+    max_file_size = conf.max_file_size,
+    max_matches_per_file = conf.max_matches_per_file,
+    smart_case = conf.smart_case,
+    time_budget_ms = conf.time_budget_ms,
+    file_order = conf.file_order,
+    scope = conf.scope,
+    changed_only = conf.changed_only,
+    dedupe_lines = conf.dedupe_lines,
+  }
+end
+
 --- Perform a grep search.
 ---@param query string The search query (may contain file constraints like *.rs)
 ---@param file_offset? number Index into sorted file list to start from (default 0)
@@ -24,20 +44,25 @@ local last_result = nil
 ---@param grep_mode? string Search mode: "plain" (default), "regex", or "fuzzy"
 ---@return fff.grep.SearchResult
 function M.search(query, file_offset, page_size, config, grep_mode)
-  local conf = config or {}
-  last_result = fuzzy.live_grep(
-    query or '',
-    file_offset or 0,
-    page_size or 50,
-    conf.max_file_size,
-    conf.max_matches_per_file,
-    conf.smart_case,
-    grep_mode or 'plain',
-    conf.time_budget_ms
-  )
+  last_result = fuzzy.live_grep(query or '', file_offset or 0, page_size or 50, grep_opts(config, grep_mode))
   return last_result
 end
 
+--- Grep only the files matched by a previous fuzzy search.
+---@param query_id number The `query_id` returned by `fuzzy_search_files`
+---@param query string The grep query
+---@param file_offset? number Index into the sorted matched files to start from (default 0)
+---@param page_size? number Max matches to collect (default 50)
+---@param config? table Grep configuration overrides
+---@param grep_mode? string Search mode: "plain" (default), "regex", or "fuzzy"
+---@return fff.grep.SearchResult|nil nil when `query_id` is no longer the latest fuzzy search
+function M.search_in_results(query_id, query, file_offset, page_size, config, grep_mode)
+  local result =
+    fuzzy.grep_in_results(query_id, query or '', file_offset or 0, page_size or 50, grep_opts(config, grep_mode))
+  if result then last_result = result end
+  return result
+end
+
 --- Get metadata from the last search result.
 ---@return { total_matched: number, total_files_searched: number, total_files: number, next_file_offset: number }
 function M.get_search_metadata()
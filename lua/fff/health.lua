@@ -13,6 +13,99 @@ local function fetch_rust_checkhealth(rust_module, test_path)
   return result, nil
 end
 
+--- Warn about a database that had to be repaired or could not be opened
+--- @param messages table
+--- @param label string
+--- @param info table
+local function add_db_open_messages(messages, label, info)
+  local repaired = info.repaired
+  if repaired then
+    local msg = repaired.reset and string.format('%s could not be read and was recreated empty', label)
+      or string.format('%s was repaired (%d entries kept, %d dropped)', label, repaired.kept, repaired.dropped)
+    table.insert(messages, { level = 'warn', msg = msg })
+  end
+
+  if not info.initialized and info.error then
+    table.insert(messages, { level = 'warn', msg = label .. ' is disabled: ' .. info.error })
+  end
+end
+
+--- Whether the library at `path` can be linked and exports the Lua entry point
+--- @param path string|nil
+--- @return table library_info
+local function check_library(path)
+  if not path or not vim.uv.fs_stat(path) then return { exists = false } end
+
+  local linked, link_err = package.loadlib(path, '*')
+  if not linked then return { exists = true, loads = false, error = link_err } end
+
+  local entry, entry_err = package.loadlib(path, 'luaopen_fff_nvim')
+  return { exists = true, loads = true, entry_point_exported = entry ~= nil, error = entry_err }
+end
+
+--- Explain why the library at `path` could not be loaded
+--- @param messages table
+--- @param path string
+--- @param library table result of check_library
+local function add_library_messages(messages, path, library)
+  local rebuild = 'run `:lua require("fff.download").download_or_build_binary()` or `cargo build --release`'
+  if not library.exists then
+    table.insert(messages, { level = 'error', msg = 'No binary at ' .. path .. ', ' .. rebuild })
+  elseif not library.loads then
+    table.insert(messages, {
+      level = 'error',
+      msg = string.format(
+        'Binary at %s cannot be linked, it may be built for another platform (%s %s): %s',
+        path,
+        jit.os,
+        jit.arch,
+        tostring(library.error)
+      ),
+    })
+  elseif not library.entry_point_exported then
+    table.insert(messages, {
+      level = 'error',
+      msg = 'Binary at ' .. path .. ' does not export luaopen_fff_nvim, it is not a build of fff-nvim; ' .. rebuild,
+    })
+  end
+end
+
+--- LuaJIT's name for a Rust target architecture
+local jit_arch = { x86_64 = 'x64', x86 = 'x86', aarch64 = 'arm64', arm = 'arm' }
+
+--- Report the toolchain and link mode the binary was built with
+--- @param messages table
+--- @param build table
+local function add_build_messages(messages, build)
+  table.insert(messages, {
+    level = 'info',
+    msg = string.format(
+      'Built with %s for %s (%s profile, libgit2 %s, %s, features: %s)',
+      build.rustc,
+      build.target,
+      build.profile,
+      build.libgit2,
+      build.lua,
+      #build.features > 0 and table.concat(build.features, ', ') or 'none'
+    ),
+  })
+
+  if build.profile ~= 'release' then
+    table.insert(messages, {
+      level = 'warn',
+      msg = 'Binary is a ' .. build.profile .. ' build, searches will be slow; rebuild with `cargo build --release`',
+    })
+  end
+
+  local expected_arch = jit_arch[build.arch] or build.arch
+  if expected_arch ~= jit.arch then
+    table.insert(messages, {
+      level = 'warn',
+      msg = string.format('Binary is built for %s but Neovim runs on %s', build.arch, jit.arch),
+    })
+  end
+end
+
 --- Check snacks.nvim image preview availability
 --- @return table image_preview_info
 local function check_image_preview()
@@ -54,15 +147,20 @@ function M.run(opts)
     binary = {
       available = false,
       path = nil,
+      library = nil,
       error = nil,
     },
     rust = {
       version = nil,
+      build = nil,
       git = {
         available = false,
         repository_found = false,
         workdir = nil,
+        is_worktree = false,
         libgit2_version = nil,
+        backend = nil,
+        enabled = true,
         error = nil,
       },
       file_picker = {
@@ -116,10 +214,14 @@ function M.run(opts)
       level = 'error',
       msg = 'Binary not available: ' .. tostring(rust_module),
     })
+    health.binary.library = check_library(health.binary.path)
+    add_library_messages(health.messages, health.binary.path, health.binary.library)
     return health
   end
 
   health.binary.available = true
+  health.binary.path = rust_module.library_path or health.binary.path
+  health.binary.library = check_library(health.binary.path)
   table.insert(health.messages, {
     level = 'ok',
     msg = 'Binary loaded successfully from: ' .. health.binary.path,
@@ -133,24 +235,70 @@ function M.run(opts)
       msg = 'fff.nvim version: ' .. (rust_health.version or 'unknown'),
     })
 
+    health.rust.build = rust_health.build
+    if rust_health.build then add_build_messages(health.messages, rust_health.build) end
+
+    local expected_abi = download.get_expected_abi_hash()
+    if expected_abi and rust_module.verify_binary_compat then
+      local version = rust_module.fff_version()
+      health.binary.abi_hash = version.abi_hash
+      if not rust_module.verify_binary_compat(expected_abi) then
+        table.insert(health.messages, {
+          level = 'error',
+          msg = string.format(
+            'Binary (abi %s, commit %s) does not match this checkout of fff.nvim (abi %s); '
+              .. 'run `:lua require("fff.download").download_or_build_binary()`',
+            version.abi_hash,
+            version.commit:sub(1, 12),
+            expected_abi
+          ),
+        })
+      end
+    end
+
     if rust_health.git then
       health.rust.git.available = rust_health.git.available
       health.rust.git.repository_found = rust_health.git.repository_found
       health.rust.git.workdir = rust_health.git.workdir
+      health.rust.git.is_worktree = rust_health.git.is_worktree or false
       health.rust.git.libgit2_version = rust_health.git.libgit2_version
+      health.rust.git.backend = rust_health.git.backend
+      health.rust.git.enabled = rust_health.git.enabled ~= false
+      health.rust.git.status_provider = rust_health.git.status_provider
       health.rust.git.error = rust_health.git.error
 
-      if rust_health.git.available then
+      if not health.rust.git.enabled then
+        table.insert(health.messages, {
+          level = 'info',
+          msg = 'Git integration disabled (git.enabled = false), files have no git status',
+        })
+      elseif rust_health.git.available then
         table.insert(health.messages, {
           level = 'ok',
-          msg = 'libgit2 available (version: ' .. (rust_health.git.libgit2_version or 'unknown') .. ')',
+          msg = 'libgit2 available (version: '
+            .. (rust_health.git.libgit2_version or 'unknown')
+            .. ', status backend: '
+            .. (rust_health.git.backend or 'libgit2')
+            .. ')',
         })
 
         if rust_health.git.repository_found then
           table.insert(health.messages, {
             level = 'ok',
-            msg = 'Git repository found: ' .. (rust_health.git.workdir or 'unknown'),
+            msg = 'Git repository found: '
+              .. (rust_health.git.workdir or 'unknown')
+              .. (rust_health.git.is_worktree and ' (worktree)' or ''),
           })
+          if rust_health.git.status_provider then
+            table.insert(health.messages, {
+              level = 'info',
+              msg = string.format(
+                'Git status provider: %s (%s)',
+                rust_health.git.status_provider,
+                rust_health.git.status_uses_cli and 'git CLI' or (rust_health.git.backend or 'libgit2')
+              ),
+            })
+          end
         else
           table.insert(health.messages, {
             level = 'info',
@@ -172,6 +320,8 @@ function M.run(opts)
       health.rust.file_picker.is_scanning = rust_health.file_picker.is_scanning
       health.rust.file_picker.indexed_files = rust_health.file_picker.indexed_files
       health.rust.file_picker.error = rust_health.file_picker.error
+      health.rust.file_picker.project_profile = rust_health.file_picker.project_profile
+      health.rust.file_picker.trigram_index = rust_health.file_picker.trigram_index
 
       if rust_health.file_picker.initialized then
         local status = rust_health.file_picker.is_scanning and 'scanning' or 'ready'
@@ -184,6 +334,33 @@ function M.run(opts)
             rust_health.file_picker.base_path or 'unknown'
           ),
         })
+        local profile = rust_health.file_picker.project_profile
+        if profile then
+          table.insert(health.messages, {
+            level = 'info',
+            msg = string.format(
+              'Project profile: %s (deprioritized: %s, boosted: %s)',
+              #profile.kinds > 0 and table.concat(profile.kinds, ', ') or 'custom',
+              #profile.deprioritized_dirs > 0 and table.concat(profile.deprioritized_dirs, ', ') or 'none',
+              #profile.boosted_dirs > 0 and table.concat(profile.boosted_dirs, ', ') or 'none'
+            ),
+          })
+        end
+        local trigrams = rust_health.file_picker.trigram_index
+        if trigrams then
+          local stale = trigrams.invalidated or trigrams.coverage < 0.9
+          table.insert(health.messages, {
+            level = stale and 'warn' or 'ok',
+            msg = string.format(
+              'Trigram index: %d files, %d changed since build (%d not re-indexed), %.0f%% coverage%s',
+              trigrams.indexed_files,
+              trigrams.changed_files,
+              trigrams.unindexed_changes,
+              trigrams.coverage * 100,
+              trigrams.invalidated and ', rebuilding after a rescan' or ''
+            ),
+          })
+        end
       else
         table.insert(health.messages, {
           level = 'info',
@@ -207,9 +384,10 @@ function M.run(opts)
           table.insert(health.messages, {
             level = 'ok',
             msg = string.format(
-              'Frecency database initialized (%d entries, %s, path: %s)',
+              'Frecency database initialized (%d entries, %s, %s, path: %s)',
               db_info.absolute_frecency_entries or 0,
               utils.format_file_size(db_info.disk_size or 0),
+              db_info.backend or 'lmdb',
               db_info.path or 'unknown'
             ),
           })
@@ -225,12 +403,13 @@ function M.run(opts)
             msg = 'Frecency database initialized',
           })
         end
-      else
+      elseif not rust_health.frecency.error then
         table.insert(health.messages, {
           level = 'info',
           msg = 'Frecency database not initialized (will initialize on first use)',
         })
       end
+      add_db_open_messages(health.messages, 'Frecency database', rust_health.frecency)
     end
 
     if rust_health.query_tracker then
@@ -248,10 +427,11 @@ function M.run(opts)
           table.insert(health.messages, {
             level = 'ok',
             msg = string.format(
-              'Query tracker initialized (%d query-file mappings, %d history entries, %s, path: %s)',
+              'Query tracker initialized (%d query-file mappings, %d history entries, %s, %s, path: %s)',
               db_info.query_file_entries or 0,
               db_info.query_history_entries or 0,
               utils.format_file_size(db_info.disk_size or 0),
+              db_info.backend or 'lmdb',
               db_info.path or 'unknown'
             ),
           })
@@ -267,12 +447,97 @@ function M.run(opts)
             msg = 'Query tracker initialized',
           })
         end
-      else
+      elseif not rust_health.query_tracker.error then
         table.insert(health.messages, {
           level = 'info',
           msg = 'Query tracker not initialized (will initialize on first use)',
         })
       end
+      add_db_open_messages(health.messages, 'Query tracker database', rust_health.query_tracker)
+    end
+
+    health.rust.latency = rust_health.latency
+    for _, kind in ipairs({ 'fuzzy', 'grep' }) do
+      local latency = rust_health.latency and rust_health.latency[kind]
+      if latency and latency.count > 0 then
+        local slowest = {}
+        for _, slow in ipairs(latency.slowest) do
+          table.insert(slowest, string.format('%q %.1fms', slow.query, slow.ms))
+        end
+        table.insert(health.messages, {
+          level = 'info',
+          msg = string.format(
+            '%s search latency (%d searches): p50 %.1fms, p95 %.1fms, p99 %.1fms (slowest: %s)',
+            kind == 'fuzzy' and 'File' or 'Grep',
+            latency.count,
+            latency.p50_ms,
+            latency.p95_ms,
+            latency.p99_ms,
+            table.concat(slowest, ', ')
+          ),
+        })
+      end
+    end
+
+    local weeks = rust_health.query_tracker and rust_health.query_tracker.latency_history or {}
+    health.rust.latency_history = weeks
+    for _, kind in ipairs({ 'fuzzy', 'grep' }) do
+      local this_week, last_week = weeks[1], weeks[2]
+      local median = this_week and this_week[kind .. '_median_ms']
+      if median then
+        local msg = string.format(
+          'Median %s search latency this week: %.1fms',
+          kind == 'fuzzy' and 'file' or 'grep',
+          median
+        )
+        local last_median = last_week and last_week[kind .. '_median_ms']
+        if last_median then
+          msg = msg
+            .. string.format(
+              ', last week: %.1fms (%d -> %d files)',
+              last_median,
+              last_week.max_indexed_files,
+              this_week.max_indexed_files
+            )
+        end
+        table.insert(health.messages, { level = 'info', msg = msg })
+      end
+    end
+
+    health.rust.locks = rust_health.locks
+    for name, lock in pairs(rust_health.locks or {}) do
+      if lock.slow_waits > 0 or lock.slow_holds > 0 then
+        table.insert(health.messages, {
+          level = 'warn',
+          msg = string.format(
+            '%s lock was contended: %d slow waits (max %.1fms), %d slow holds (max %.1fms), last written at %s',
+            name,
+            lock.slow_waits,
+            lock.max_wait_ms,
+            lock.slow_holds,
+            lock.max_hold_ms,
+            lock.last_writer or 'unknown'
+          ),
+        })
+      end
+    end
+
+    local crash = rust_module.get_last_crash_report and rust_module.get_last_crash_report()
+    if crash then
+      local query = ''
+      if crash.last_query then
+        query = string.format(' while running %s query %q', crash.last_query.kind, crash.last_query.query)
+      end
+      table.insert(health.messages, {
+        level = 'warn',
+        msg = string.format(
+          'Last crash at %s%s: %s (attach %s to bug reports)',
+          os.date('%Y-%m-%d %H:%M', crash.timestamp),
+          query,
+          crash.message,
+          crash.path or 'the crash report'
+        ),
+      })
     end
   else
     health.ok = false
@@ -0,0 +1,59 @@
+--- Index changes pushed by the Rust side: scan completion, file watcher
+--- batches and git status refreshes. The Rust side writes a byte to a pipe
+--- when events are pending, which wakes up the libuv loop.
+
+local M = {
+  listeners = {},
+  next_id = 1,
+  pipe = nil,
+}
+
+local function dispatch()
+  local fuzzy = require('fff.core').ensure_initialized()
+  local ok, events = pcall(fuzzy.take_index_events)
+  if not ok or #events == 0 then return end
+
+  for _, listener in pairs(M.listeners) do
+    local listener_ok, err = pcall(listener, events)
+    if not listener_ok then vim.notify('fff: index event listener failed: ' .. tostring(err), vim.log.levels.WARN) end
+  end
+end
+
+--- Open the event pipe once per session
+--- @return boolean true when events are pushed, false when the UI has to poll
+local function start()
+  if M.pipe then return true end
+
+  local fuzzy = require('fff.core').ensure_initialized()
+  local ok, fd = pcall(fuzzy.subscribe_index_events)
+  if not ok or not fd then return false end
+
+  local pipe = vim.uv.new_pipe(false)
+  if not pipe or not pipe:open(fd) then return false end
+
+  pipe:read_start(function(err, data)
+    if err or not data then return end
+    vim.schedule(dispatch)
+  end)
+  M.pipe = pipe
+  return true
+end
+
+--- Call `listener` with the list of event names whenever the index changes
+--- @param listener fun(events: string[])
+--- @return integer|nil id for `unsubscribe`, nil when events are not supported
+function M.subscribe(listener)
+  if not start() then return nil end
+
+  local id = M.next_id
+  M.next_id = id + 1
+  M.listeners[id] = listener
+  return id
+end
+
+--- @param id integer|nil
+function M.unsubscribe(id)
+  if id then M.listeners[id] = nil end
+end
+
+return M
@@ -1,3 +1,5 @@
+local fuzzy = require('fff.fuzzy')
+
 local M = {}
 
 --- Jump to a location in the current buffer
@@ -178,16 +180,9 @@ function M.highlight_grep_matches(bufnr, location, namespace)
 
   local query = location.grep_query
 
-  -- Extract the actual search text from the grep query (strip file constraints like *.rs /src/)
-  -- The query parser uses space-separated tokens; the first non-constraint token is the pattern.
-  -- Simple heuristic: strip tokens that look like constraints (start with *, /, or !)
-  local search_text = query
-  local parts = vim.split(query, '%s+')
-  local text_parts = {}
-  for _, part in ipairs(parts) do
-    if part ~= '' and not part:match('^[%*!/]') and not part:match('^%.') then table.insert(text_parts, part) end
-  end
-  if #text_parts > 0 then search_text = text_parts[1] end
+  -- Strip file constraints like *.rs /src/ the same way the grep does
+  local ok, parsed = pcall(fuzzy.parse_query, query, true)
+  local search_text = ok and parsed.grep_text or query
 
   if not search_text or search_text == '' then return nil end
 
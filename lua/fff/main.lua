@@ -73,6 +73,73 @@ function M.refresh_git_status()
   end
 end
 
+--- Make files that don't exist on disk, e.g. `scp://` buffers or files in a
+--- container, searchable next to the project. Calling it again with the same
+--- name replaces the previous files.
+--- @param name string Name of the source
+--- @param files table List of `{ path = string, content = string? }`, files without content are only matched by name
+--- @return number|nil Number of registered files
+function M.register_virtual_files(name, files)
+  local fuzzy = require('fff.core').ensure_initialized()
+  local ok, result = pcall(fuzzy.register_virtual_files, name, files)
+  if not ok then
+    vim.notify('Failed to register virtual files: ' .. tostring(result), vim.log.levels.ERROR)
+    return nil
+  end
+  return result
+end
+
+--- Remove the files registered with `register_virtual_files`
+--- @param name string Name of the source
+--- @return boolean Whether the source was registered
+function M.unregister_virtual_files(name)
+  local fuzzy = require('fff.core').ensure_initialized()
+  local ok, removed = pcall(fuzzy.unregister_virtual_files, name)
+  return ok and removed
+end
+
+--- Rank files higher or lower by a score from another source, e.g. the files
+--- referencing the symbol under the cursor or the ones with failing tests.
+--- The score is added to the file's score as is. Replaces the scores of the
+--- previous call, `nil` clears them; a rescan clears them too.
+--- @param scores table<string, number>|nil Score by path, absolute or relative to the indexed directory
+--- @return number|nil Number of indexed files that got a score
+function M.set_external_scores(scores)
+  local fuzzy = require('fff.core').ensure_initialized()
+  local ok, result = pcall(fuzzy.set_external_scores, scores)
+  if not ok then
+    vim.notify('Failed to set external scores: ' .. tostring(result), vim.log.levels.ERROR)
+    return nil
+  end
+  return result
+end
+
+--- Search a read-only directory like `$VIMRUNTIME` or a plugin next to the
+--- project without reindexing it. Its files rank below the project's and
+--- `root:<name>` in a query searches only them.
+--- @param name string Name used by `root:<name>`
+--- @param path string Directory to search
+--- @param opts {weight: number?, label: string?}|nil `weight` is the percentage of the score its files keep next to the project, 50 by default, 0 to only search it with `root:<name>`. `label` is shown as a `[label]` badge on its files instead of the name
+--- @return boolean Whether the root was registered
+function M.register_root(name, path, opts)
+  local fuzzy = require('fff.core').ensure_initialized()
+  local ok, result = pcall(fuzzy.register_root, name, path, opts)
+  if not ok then
+    vim.notify('Failed to register root ' .. name .. ': ' .. tostring(result), vim.log.levels.ERROR)
+    return false
+  end
+  return result
+end
+
+--- Stop searching the root registered with `register_root`
+--- @param name string Name of the root
+--- @return boolean Whether the root was registered
+function M.unregister_root(name)
+  local fuzzy = require('fff.core').ensure_initialized()
+  local ok, removed = pcall(fuzzy.unregister_root, name)
+  return ok and removed
+end
+
 --- Search files programmatically
 --- @param query string Search query
 --- @param max_results number Maximum number of results
@@ -81,20 +148,7 @@ function M.search(query, max_results)
   local fuzzy = require('fff.core').ensure_initialized()
   local config = require('fff.conf').get()
   max_results = max_results or config.max_results
-  local max_threads = config.max_threads or 4
-  local combo_boost_score_multiplier = config.history and config.history.combo_boost_score_multiplier or 100
-  local min_combo_count = config.history and config.history.min_combo_count or 3
-  -- Args: query, max_threads, current_file, combo_boost_score_multiplier, min_combo_count, offset, page_size
-  local ok, search_result = pcall(
-    fuzzy.fuzzy_search_files,
-    query,
-    max_threads,
-    nil,
-    combo_boost_score_multiplier,
-    min_combo_count,
-    0,
-    max_results
-  )
+  local ok, search_result = pcall(fuzzy.fuzzy_search_files, query, 0, max_results)
   if ok and search_result.items then return search_result.items end
   return {}
 end
@@ -175,6 +229,141 @@ function M.find_files_in_dir(directory)
   end
 end
 
+--- Find files under the directory of the current buffer, without changing the
+--- indexed directory
+--- @param opts? table Same as `find_files`
+function M.find_files_in_current_file_dir(opts)
+  local directory = vim.fn.expand('%:p:h')
+  if directory == '' or vim.fn.isdirectory(directory) == 0 then directory = vim.uv.cwd() end
+
+  M.find_files(vim.tbl_extend('force', {
+    title = 'Files in ' .. vim.fn.fnamemodify(directory, ':~:.'),
+    search_root = directory,
+  }, opts or {}))
+end
+
+--- Reopen the picker with the query, mode and selection it was last closed with
+--- in the current project
+--- @param opts? table Same as `find_files` / `live_grep`
+--- @return boolean `true` if there was a search to resume
+function M.resume(opts)
+  local fff = require('fff.core').ensure_initialized()
+  local ok, state = pcall(fff.get_last_search_state)
+  if not ok or not state then
+    vim.notify('No search to resume', vim.log.levels.INFO)
+    return false
+  end
+
+  local resume_opts = vim.tbl_extend('force', { query = state.query, cursor = state.selected_index }, opts or {})
+  if state.mode == 'grep' then
+    M.live_grep(resume_opts)
+  else
+    M.find_files(resume_opts)
+  end
+  return true
+end
+
+--- Save a query under `name` in the current project to rerun it with `open_saved_search`
+--- @param name string
+--- @param query? string Defaults to the query of the open picker
+--- @param mode? 'files'|'grep' Defaults to the mode of the open picker, or 'files'
+--- @return boolean `true` if the search was saved
+function M.save_search(name, query, mode)
+  local picker_ui = require('fff.picker_ui')
+  if query == nil and picker_ui.state.active then
+    query = picker_ui.state.query
+    mode = mode or (picker_ui.state.mode == 'grep' and 'grep' or 'files')
+  end
+  if not name or name == '' or not query or query == '' then
+    vim.notify('A saved search needs a name and a query', vim.log.levels.WARN)
+    return false
+  end
+
+  local fff = require('fff.core').ensure_initialized()
+  local ok, saved = pcall(fff.save_search, name, query, mode or 'files')
+  if not ok or not saved then
+    vim.notify('Failed to save search: ' .. tostring(saved), vim.log.levels.ERROR)
+    return false
+  end
+  vim.notify(string.format('Saved search "%s"', name), vim.log.levels.INFO)
+  return true
+end
+
+--- Open the picker with the search saved under `name`
+--- @param name string
+--- @param opts? table Same as `find_files` / `live_grep`
+--- @return boolean `true` if there was a search saved under the name
+function M.open_saved_search(name, opts)
+  local fff = require('fff.core').ensure_initialized()
+  local ok, searches = pcall(fff.list_saved_searches)
+  for _, search in ipairs(ok and searches or {}) do
+    if search.name == name then
+      local search_opts = vim.tbl_extend('force', { query = search.query }, opts or {})
+      if search.mode == 'grep' then
+        M.live_grep(search_opts)
+      else
+        M.find_files(search_opts)
+      end
+      return true
+    end
+  end
+
+  vim.notify(string.format('No saved search "%s"', name), vim.log.levels.WARN)
+  return false
+end
+
+--- Pick one of the searches saved in the current project and open it
+--- @param opts? { counts?: boolean } `counts` shows how many files or matches every search finds
+function M.saved_searches(opts)
+  local fff = require('fff.core').ensure_initialized()
+  local ok, searches = pcall(fff.list_saved_searches)
+  if not ok or #searches == 0 then
+    vim.notify('No saved searches', vim.log.levels.INFO)
+    return
+  end
+
+  local show_counts = opts and opts.counts
+  vim.ui.select(searches, {
+    prompt = 'Saved searches',
+    format_item = function(search)
+      local label = string.format('%s  [%s] %s', search.name, search.mode, search.query)
+      if not show_counts then return label end
+      -- counted only when shown, cached by the index generation
+      local count_ok, count = pcall(fff.count_saved_search, search.query, search.mode)
+      return count_ok and string.format('%s (%d)', label, count) or label
+    end,
+  }, function(search)
+    if search then M.open_saved_search(search.name) end
+  end)
+end
+
+--- Forget the search saved under `name`
+--- @param name string
+--- @return boolean `true` if there was a search saved under the name
+function M.delete_saved_search(name)
+  local fff = require('fff.core').ensure_initialized()
+  local ok, deleted = pcall(fff.delete_saved_search, name)
+  return ok and deleted == true
+end
+
+--- Jump back to a grep result opened before, also across sessions
+--- @param index? number 1 for the most recent jump (default), 2 for the one before, etc.
+--- @return boolean `true` if a jump was found and opened
+function M.resume_last_grep_jump(index)
+  index = index or 1
+  local fff = require('fff.core').ensure_initialized()
+  local ok, jumps = pcall(fff.get_recent_grep_jumps, index)
+  local jump = ok and jumps[index]
+  if not jump then
+    vim.notify('No recent grep jumps', vim.log.levels.INFO)
+    return false
+  end
+
+  vim.cmd('edit ' .. vim.fn.fnameescape(jump.path))
+  require('fff.location_utils').jump_to_location({ line = jump.line })
+  return true
+end
+
 --- Change the base directory for the file picker
 --- @param new_path string New directory path to use as base
 --- @return boolean `true` if successful, `false` otherwise
@@ -8,6 +8,7 @@ local location_utils = require('fff.location_utils')
 local combo_renderer = require('fff.combo_renderer')
 local list_renderer = require('fff.list_renderer')
 local scrollbar = require('fff.scrollbar')
+local index_events = require('fff.index_events')
 local rust = require('fff.rust')
 
 local BORDER_PRESETS = {
@@ -40,6 +41,22 @@ local function get_border_chars()
   return BORDER_PRESETS.single, T_JUNCTION_PRESETS.single
 end
 
+--- Options asking Rust for pre-rendered display columns, nil when disabled
+--- @return table|nil
+local function get_display_options()
+  local config = M.state.config
+  local layout = config and config.layout
+  if not (layout and layout.prerender_display) then return nil end
+  if not M.state.list_win or not vim.api.nvim_win_is_valid(M.state.list_win) then return nil end
+
+  local win_info = vim.fn.getwininfo(M.state.list_win)[1]
+  local text_offset = win_info and win_info.textoff or 2
+  return {
+    width = vim.api.nvim_win_get_width(M.state.list_win) - text_offset,
+    path_shorten_strategy = layout.path_shorten_strategy,
+  }
+end
+
 local function get_prompt_position()
   local config = M.state.config
 
@@ -511,6 +528,14 @@ M.state = {
   mode = nil,
   -- Grep-specific config overrides (max_file_size, smart_case, etc.)
   grep_config = nil,
+  -- Whether the files matching file_picker.runtime_excludes are hidden
+  hide_excluded = false,
+  -- Whether the searches only look at the files pinned by a `+` query
+  narrowed = false,
+  -- How many queries back the prompt was undone, and the restored query that
+  -- must not be searched again when the prompt change comes in
+  query_undo_depth = 0,
+  restored_query = nil,
   -- Grep search mode: 'plain', 'regex', or 'fuzzy'
   grep_mode = 'plain',
   -- Regex fallback error: set when regex compilation fails and search fell back to literal
@@ -840,6 +865,8 @@ function M.setup_keymaps()
   set_keymap({ 'i', 'n' }, keymaps.toggle_select, M.toggle_select, input_opts)
   set_keymap({ 'i', 'n' }, keymaps.send_to_quickfix, M.send_to_quickfix, input_opts)
   set_keymap({ 'i', 'n' }, keymaps.cycle_grep_modes, M.cycle_grep_modes, input_opts)
+  set_keymap({ 'i', 'n' }, keymaps.toggle_excludes, M.toggle_excludes, input_opts)
+  set_keymap({ 'i', 'n' }, keymaps.undo_query, M.undo_query, input_opts)
 
   -- List buffer
   set_keymap('n', keymaps.close, M.close, list_opts)
@@ -857,6 +884,7 @@ function M.setup_keymaps()
   set_keymap('n', keymaps.toggle_debug, M.toggle_debug, list_opts)
   set_keymap('n', keymaps.toggle_select, M.toggle_select, list_opts)
   set_keymap('n', keymaps.send_to_quickfix, M.send_to_quickfix, list_opts)
+  set_keymap('n', keymaps.toggle_excludes, M.toggle_excludes, list_opts)
 
   -- Preview buffer
   if M.state.preview_buf then
@@ -905,12 +933,14 @@ function M.toggle_debug()
     local current_filtered_items = M.state.filtered_items
     local current_selected_files = M.state.selected_files
     local current_selected_items = M.state.selected_items
+    local current_hide_excluded = M.state.hide_excluded
 
     M.close()
     M.open({
       mode = current_mode,
       renderer = current_renderer,
       grep_config = current_grep_config,
+      hide_excluded = current_hide_excluded,
     })
 
     M.state.query = current_query
@@ -935,6 +965,24 @@ function M.toggle_debug()
   end
 end
 
+--- Pass the globs of `file_picker.runtime_excludes` to the search while
+--- `hide_excluded` is set. The index is untouched, so this is instant.
+local function apply_runtime_excludes(config, hide_excluded)
+  local globs = hide_excluded and config.file_picker and config.file_picker.runtime_excludes or nil
+  local fuzzy = require('fff.core').ensure_initialized()
+  pcall(fuzzy.set_runtime_excludes, globs)
+end
+
+--- Hide or show the files matching `file_picker.runtime_excludes`, e.g. tests
+--- or generated code, until the picker is closed.
+function M.toggle_excludes()
+  if not M.state.active or not M.state.config then return end
+
+  M.state.hide_excluded = not M.state.hide_excluded
+  apply_runtime_excludes(M.state.config, M.state.hide_excluded)
+  M.update_results()
+end
+
 --- Cycle through grep search modes based on configured modes list.
 --- Only works when the picker is in grep mode. Triggers a re-search
 --- with the current query using the new mode.
@@ -971,6 +1019,51 @@ function M.cycle_grep_modes()
   if M.state.query ~= '' then M.update_results_sync() end
 end
 
+--- A query of just `+` pins the selected files, or the files of the selected
+--- grep matches, and clears the prompt; the following queries only search
+--- them. `+` without a selection searches everything again.
+--- @return boolean Whether the query was the narrowing token
+local function apply_narrowing_token(query)
+  if query ~= '+' then return false end
+
+  local paths = {}
+  if M.state.mode == 'grep' then
+    local seen = {}
+    for _, item in pairs(M.state.selected_items) do
+      if not seen[item.path] then
+        seen[item.path] = true
+        table.insert(paths, item.path)
+      end
+    end
+  else
+    for path in pairs(M.state.selected_files) do
+      table.insert(paths, path)
+    end
+  end
+
+  local fuzzy = require('fff.core').ensure_initialized()
+  if #paths > 0 then
+    pcall(fuzzy.narrow_to, paths)
+    M.state.narrowed = true
+    M.state.selected_files = {}
+    M.state.selected_items = {}
+  elseif M.state.narrowed then
+    pcall(fuzzy.clear_narrowing)
+    M.state.narrowed = false
+  else
+    return false
+  end
+
+  local prompt = M.state.config.prompt
+  vim.api.nvim_buf_set_lines(M.state.input_buf, 0, -1, false, { prompt })
+  vim.schedule(function()
+    if M.state.active and M.state.input_win and vim.api.nvim_win_is_valid(M.state.input_win) then
+      vim.api.nvim_win_set_cursor(M.state.input_win, { 1, #prompt })
+    end
+  end)
+  return true
+end
+
 function M.on_input_change()
   if not M.state.active then return end
 
@@ -1003,11 +1096,57 @@ function M.on_input_change()
     if full_line:sub(1, prompt_len) == M.state.config.prompt then query = full_line:sub(prompt_len + 1) end
   end
 
+  -- clearing the prompt runs this again with the empty query
+  if apply_narrowing_token(query) then return end
+
+  if M.state.restored_query ~= nil and query == M.state.restored_query then
+    M.state.restored_query = nil
+    return
+  end
+  M.state.restored_query = nil
+  M.state.query_undo_depth = 0
+
   M.state.query = query
 
   M.update_results_sync()
 end
 
+--- Go back to the previous query of the session, showing the results it had
+--- from the cache instead of searching again. Repeat to go further back.
+function M.undo_query()
+  if not M.state.active or M.state.mode == 'grep' then return end
+
+  local page_size = M.state.pagination.page_size
+  if page_size == 0 then page_size = M.state.config.max_results or 100 end
+
+  local depth = M.state.query_undo_depth + 1
+  local query, items = file_picker.restore_query_snapshot(depth, page_size, get_display_options())
+  if not query then return end
+
+  M.state.query_undo_depth = depth
+  M.state.restored_query = query
+  M.state.query = query
+
+  local prompt = M.state.config.prompt
+  vim.api.nvim_buf_set_lines(M.state.input_buf, 0, -1, false, { prompt .. query })
+  vim.schedule(function()
+    if M.state.active and M.state.input_win and vim.api.nvim_win_is_valid(M.state.input_win) then
+      vim.api.nvim_win_set_cursor(M.state.input_win, { 1, #prompt + #query })
+    end
+  end)
+
+  M.state.items = items
+  M.state.filtered_items = items
+  M.state.suggestion_items = nil
+  M.state.suggestion_source = nil
+  M.state.location = file_picker.get_search_location()
+  M.state.pagination.page_index = 0
+  M.state.pagination.total_matched = file_picker.get_search_metadata().total_matched
+  M.state.cursor = 1
+
+  M.render_debounced()
+end
+
 function M.update_results() M.update_results_sync() end
 
 function M.update_results_sync()
@@ -1045,25 +1184,18 @@ function M.update_results_sync()
   local results
   if M.state.mode == 'grep' then
     M.state.grep_regex_fallback_error = nil
-    if M.state.query == '' then
-      -- Empty query: show empty state (no search needed)
-      results = {}
-      M.state.pagination.total_matched = 0
-      M.state.pagination.grep_file_offsets = {}
-      M.state.pagination.grep_next_file_offset = 0
-    else
-      -- Grep mode: use live_grep search (file_offset=0 for first page)
-      local grep = require('fff.grep')
-      local grep_result = grep.search(M.state.query, 0, page_size, M.state.grep_config, M.state.grep_mode)
-      results = grep_result.items or {}
-      M.state.pagination.total_matched = grep_result.total_matched or 0
-      M.state.pagination.grep_file_offsets = { 0 } -- Page 0 starts at file 0
-      M.state.pagination.grep_next_file_offset = grep_result.next_file_offset or 0
-      M.state.grep_regex_fallback_error = grep_result.regex_fallback_error or nil
-      -- Record offset for page 1 so forward navigation works immediately
-      if grep_result.next_file_offset and grep_result.next_file_offset > 0 then
-        M.state.pagination.grep_file_offsets[2] = grep_result.next_file_offset
-      end
+    -- Grep mode: use live_grep search (file_offset=0 for first page). An empty
+    -- query lists the changed and recently opened files as whole file rows.
+    local grep = require('fff.grep')
+    local grep_result = grep.search(M.state.query, 0, page_size, M.state.grep_config, M.state.grep_mode)
+    results = grep_result.items or {}
+    M.state.pagination.total_matched = grep_result.total_matched or 0
+    M.state.pagination.grep_file_offsets = { 0 } -- Page 0 starts at file 0
+    M.state.pagination.grep_next_file_offset = grep_result.next_file_offset or 0
+    M.state.grep_regex_fallback_error = grep_result.regex_fallback_error or nil
+    -- Record offset for page 1 so forward navigation works immediately
+    if grep_result.next_file_offset and grep_result.next_file_offset > 0 then
+      M.state.pagination.grep_file_offsets[2] = grep_result.next_file_offset
     end
     M.state.location = nil -- Location comes from selected item, not query
   else
@@ -1074,7 +1206,9 @@ function M.update_results_sync()
       M.state.config.max_threads,
       min_combo_override,
       0,
-      page_size
+      page_size,
+      get_display_options(),
+      M.state.search_root
     )
 
     -- Get location from search results
@@ -1100,7 +1234,9 @@ function M.update_results_sync()
         M.state.config.max_threads,
         nil,
         0,
-        page_size
+        page_size,
+        get_display_options(),
+        M.state.search_root
       )
       if suggestion_results and #suggestion_results > 0 then
         M.state.suggestion_items = suggestion_results
@@ -1172,6 +1308,12 @@ function M.load_page_at_index(new_page_index, adjust_cursor_fn)
       end
     end
   else
+    -- Serve the page from the cached ranking, rescoring only when it is gone
+    results = file_picker.fetch_results_window(new_page_index * page_size, page_size, get_display_options())
+    ok = results ~= nil
+  end
+
+  if not ok and M.state.mode ~= 'grep' then
     ok, results = pcall(
       file_picker.search_files_paginated,
       M.state.query,
@@ -1179,7 +1321,9 @@ function M.load_page_at_index(new_page_index, adjust_cursor_fn)
       M.state.config.max_threads,
       nil, -- No combo boost override for page navigation
       new_page_index,
-      page_size
+      page_size,
+      get_display_options(),
+      M.state.search_root
     )
   end
 
@@ -1338,6 +1482,10 @@ local function shrink_path(path, max_width)
 end
 
 local function format_file_display(item, max_width)
+  -- Columns pre-rendered by Rust are reused as long as the width still matches
+  local display = item.display
+  if display and display.width == max_width then return display.filename, display.dir end
+
   local filename = item.name
   local dir_path = item.directory or ''
 
@@ -1387,8 +1535,8 @@ local function scroll_to_bottom()
   end)
 end
 
---- Render the grep empty state: tips + bordered section of recent files.
---- Called when grep mode has an empty query and no items.
+--- Render the grep empty state: the query syntax tips.
+--- Called when grep mode has an empty query and no changed or recent files to list.
 local function render_grep_empty_state(ctx)
   local config = ctx.config
   local win_width = ctx.win_width
@@ -1719,7 +1867,7 @@ function M.update_preview()
 
   -- In grep mode (or when previewing grep suggestions), location comes from the match item
   local is_grep_item = M.state.mode == 'grep' or M.state.suggestion_source == 'grep'
-  if is_grep_item and item.line_number and item.line_number > 0 then
+  if is_grep_item and item.kind == 'line' then
     effective_location = { line = item.line_number }
     if item.col and item.col > 0 then
       effective_location.col = item.col + 1 -- Convert 0-based byte col to 1-based for highlight_location
@@ -1881,6 +2029,14 @@ function M.update_status(progress)
     local search_metadata = file_picker.get_search_metadata()
     if #M.state.query < 2 then
       status_info = string.format('%d', search_metadata.total_files)
+    elseif search_metadata.total_matched == 0 and search_metadata.suggestion then
+      status_info = string.format('did you mean: %s', search_metadata.suggestion)
+    elseif search_metadata.exact_matched == 0 and search_metadata.typo_matched > 0 then
+      status_info = string.format(
+        'no exact matches, fuzzy guesses %d/%d',
+        search_metadata.total_matched,
+        search_metadata.total_files
+      )
     else
       status_info = string.format('%d/%d', search_metadata.total_matched, search_metadata.total_files)
     end
@@ -2188,6 +2344,27 @@ function M.toggle_select()
   end
 end
 
+--- Grep matches the picker acts on: the selected ones (surviving page changes),
+--- or without selections every match, collected by re-running the search with a large limit.
+local function collect_grep_items()
+  if next(M.state.selected_items) ~= nil then
+    local items = {}
+    for _, item in pairs(M.state.selected_items) do
+      table.insert(items, item)
+    end
+    return items
+  end
+
+  local grep = require('fff.grep')
+  local exhaustive_config = vim.tbl_extend('force', M.state.grep_config or {}, { max_matches_per_file = 0 })
+  local exhaustive = grep.search(M.state.query, 0, 10000, exhaustive_config, M.state.grep_mode)
+  local items = {}
+  for _, item in ipairs(exhaustive and exhaustive.items or {}) do
+    if item and item.path then table.insert(items, item) end
+  end
+  return items
+end
+
 --- Send selected files/matches to quickfix list and close picker.
 --- Normal file mode: entries at line 1, col 1.
 --- Grep mode with selections: selected occurrences with exact line/col.
@@ -2199,40 +2376,19 @@ function M.send_to_quickfix()
 
   if M.state.mode == 'grep' then
     -- Grep mode: per-occurrence entries with exact locations
-    local has_selections = next(M.state.selected_items) ~= nil
-
-    if has_selections then
-      -- Use explicitly selected items (survives page changes)
-      for _, item in pairs(M.state.selected_items) do
-        table.insert(qf_list, {
-          filename = item.path,
-          lnum = item.line_number or 1,
-          col = (item.col or 0) + 1,
-          text = item.line_content or vim.fn.fnamemodify(item.path, ':.'),
-        })
-      end
-    else
-      -- No selections: run an exhaustive search to get all matches
-      local grep = require('fff.grep')
-      local exhaustive_config = vim.tbl_extend('force', M.state.grep_config or {}, { max_matches_per_file = 0 })
-      local exhaustive = grep.search(M.state.query, 0, 10000, exhaustive_config, M.state.grep_mode)
-      local all_items = exhaustive and exhaustive.items or {}
-
-      if #all_items == 0 then
-        vim.notify('No matches to send to quickfix', vim.log.levels.WARN)
-        return
-      end
+    local items = collect_grep_items()
+    if #items == 0 then
+      vim.notify('No matches to send to quickfix', vim.log.levels.WARN)
+      return
+    end
 
-      for _, item in ipairs(all_items) do
-        if item and item.path then
-          table.insert(qf_list, {
-            filename = item.path,
-            lnum = item.line_number or 1,
-            col = (item.col or 0) + 1,
-            text = item.line_content or vim.fn.fnamemodify(item.path, ':.'),
-          })
-        end
-      end
+    for _, item in ipairs(items) do
+      table.insert(qf_list, {
+        filename = item.path,
+        lnum = item.line_number or 1,
+        col = (item.col or 0) + 1,
+        text = item.line_content or vim.fn.fnamemodify(item.path, ':.'),
+      })
     end
   else
     -- Normal file mode: per-file entries at line 1
@@ -2276,6 +2432,32 @@ function M.send_to_quickfix()
   vim.notify(string.format('Added %d %s to quickfix list', count, unit), vim.log.levels.INFO)
 end
 
+--- Export the selected grep matches, or all of them, for other tools and copy
+--- the output into `register` (the unnamed register by default).
+--- @param format 'quickfix'|'sarif'|'text' quickfix JSON, a SARIF log or `path:line:col:text` lines
+--- @param register string|nil
+--- @return string|nil output the exported matches, nil when there was nothing to export
+function M.export_grep_results(format, register)
+  if not M.state.active or M.state.mode ~= 'grep' then return nil end
+
+  local items = collect_grep_items()
+  if #items == 0 then
+    vim.notify('No matches to export', vim.log.levels.WARN)
+    return nil
+  end
+
+  local fuzzy = require('fff.core').ensure_initialized()
+  local ok, output = pcall(fuzzy.export_grep_results, items, format)
+  if not ok then
+    vim.notify('Failed to export matches: ' .. tostring(output), vim.log.levels.ERROR)
+    return nil
+  end
+
+  vim.fn.setreg(register or '"', output)
+  vim.notify(string.format('Exported %d matches as %s', #items, format), vim.log.levels.INFO)
+  return output
+end
+
 function M.select(action)
   if not M.state.active then return end
 
@@ -2303,7 +2485,7 @@ function M.select(action)
 
   -- In grep mode (or when selecting a grep suggestion), derive location from the match item
   local is_grep_item = mode == 'grep' or suggestion_source == 'grep'
-  if is_grep_item and item.line_number and item.line_number > 0 then
+  if is_grep_item and item.kind == 'line' then
     location = { line = item.line_number }
     if item.col and item.col > 0 then
       location.col = item.col + 1 -- Convert 0-based byte col to 1-based
@@ -2361,6 +2543,7 @@ function M.select(action)
         -- Track in background thread (non-blocking, handled by Rust)
         if mode == 'grep' then
           pcall(fff.track_grep_query, query)
+          if location then pcall(fff.track_grep_completion, query, item.path, location.line) end
         else
           pcall(fff.track_query_completion, query, item.path)
         end
@@ -2408,6 +2591,14 @@ function M.close()
   vim.cmd('stopinsert')
   M.state.active = false
 
+  local history = M.state.config and M.state.config.history
+  if history and history.enabled then
+    local fff = require('fff.core').ensure_initialized()
+    pcall(fff.store_search_state, M.state.query, M.state.mode == 'grep' and 'grep' or 'files', M.state.cursor)
+  end
+
+  index_events.unsubscribe(M.state.index_events_id)
+  M.state.index_events_id = nil
   combo_renderer.cleanup()
   scrollbar.cleanup()
 
@@ -2471,6 +2662,19 @@ function M.close()
   M.state.selected_items = {}
   M.state.mode = nil
   M.state.grep_config = nil
+  M.state.search_root = nil
+  if M.state.hide_excluded then
+    M.state.hide_excluded = false
+    local fuzzy = require('fff.core').ensure_initialized()
+    pcall(fuzzy.set_runtime_excludes, nil)
+  end
+  if M.state.narrowed then
+    M.state.narrowed = false
+    local fuzzy = require('fff.core').ensure_initialized()
+    pcall(fuzzy.clear_narrowing)
+  end
+  M.state.query_undo_depth = 0
+  M.state.restored_query = nil
   M.state.grep_mode = 'plain'
   M.state.grep_regex_fallback_error = nil
   M.state.suggestion_items = nil
@@ -2584,6 +2788,7 @@ local function open_ui_with_state(query, results, location, merged_config, curre
     vim.cmd('startinsert!')
   end
 
+  M.state.index_events_id = index_events.subscribe(M.on_index_events)
   M.monitor_scan_progress(0)
   return true
 end
@@ -2622,7 +2827,7 @@ function M.open_with_callback(query, callback, opts)
 end
 
 --- Open the file picker UI
---- @param opts? {cwd?: string, title?: string, prompt?: string, max_results?: number, max_threads?: number, layout?: {width?: number|function, height?: number|function, prompt_position?: string|function, preview_position?: string|function, preview_size?: number|function}, renderer?: table, mode?: string, grep_config?: table, query?: string} Optional configuration to override defaults
+--- @param opts? {cwd?: string, title?: string, prompt?: string, max_results?: number, max_threads?: number, layout?: {width?: number|function, height?: number|function, prompt_position?: string|function, preview_position?: string|function, preview_size?: number|function}, renderer?: table, mode?: string, grep_config?: table, query?: string, search_root?: string, hide_excluded?: boolean} Optional configuration to override defaults
 function M.open(opts)
   if M.state.active then return end
 
@@ -2631,12 +2836,25 @@ function M.open(opts)
   M.state.renderer = opts and opts.renderer or nil
   M.state.mode = opts and opts.mode or nil
   M.state.grep_config = opts and opts.grep_config or nil
+  M.state.search_root = opts and opts.search_root or nil
 
   local merged_config, base_path = initialize_picker(opts)
   if not merged_config then return end
 
   if base_path then M.change_indexing_directory(base_path) end
 
+  M.state.hide_excluded = opts and opts.hide_excluded or false
+  if M.state.hide_excluded then apply_runtime_excludes(merged_config, true) end
+
+  local frecency_context = merged_config.frecency and merged_config.frecency.context
+  if
+    frecency_context
+    and (frecency_context.time_of_day or frecency_context.branch or frecency_context.branch_scoped)
+  then
+    local fuzzy = require('fff.core').ensure_initialized()
+    pcall(fuzzy.set_frecency_context, frecency_context)
+  end
+
   -- Initialize grep_mode to first configured mode when opening in grep mode
   if M.state.mode == 'grep' then
     -- Use grep_config.modes if provided, otherwise fall back to global config
@@ -2649,7 +2867,19 @@ function M.open(opts)
 
   local current_file_cache = get_current_file_cache(base_path)
   local query = opts and opts.query or nil ---@type string|nil
-  return open_ui_with_state(query, nil, nil, merged_config, current_file_cache)
+  local opened = open_ui_with_state(query, nil, nil, merged_config, current_file_cache)
+
+  if opened and opts and opts.cursor and #M.state.filtered_items > 0 then
+    M.state.cursor = math.max(1, math.min(opts.cursor, #M.state.filtered_items))
+    M.render_list()
+    if M.state.mode == 'grep' then
+      M.update_preview_smart()
+    else
+      M.update_preview()
+    end
+  end
+
+  return opened
 end
 
 --- Change the base directory for the file picker
@@ -2668,15 +2898,17 @@ function M.change_indexing_directory(new_path)
     return false
   end
 
-  local fuzzy = require('fff.core').ensure_initialized()
-  local ok, result = pcall(fuzzy.restart_index_in_path, expanded_path)
+  local core = require('fff.core')
+  local fuzzy = core.ensure_initialized()
+  local config = require('fff.conf').get()
+  local ok, result = pcall(fuzzy.restart_index_in_path, expanded_path, config.file_picker.allow_broad_root)
   if not ok then
     vim.notify('Failed to change directory: ' .. result, vim.log.levels.ERROR)
     return false
   end
 
-  local config = require('fff.conf').get()
   config.base_path = expanded_path
+  if result then core.confirm_broad_root(expanded_path) end
   return true
 end
 
@@ -2687,6 +2919,8 @@ function M.monitor_scan_progress(iteration)
 
   if progress.is_scanning then
     M.update_status(progress)
+    -- the scan_completed event refreshes the results
+    if M.state.index_events_id then return end
 
     local timeout
     if iteration < 10 then
@@ -2700,9 +2934,38 @@ function M.monitor_scan_progress(iteration)
     vim.defer_fn(function() M.monitor_scan_progress(iteration + 1) end, timeout)
   else
     M.update_results()
+    if not M.state.index_events_id then M.monitor_index_changes() end
   end
 end
 
+--- Whether the index changed since the displayed file results were ranked
+local function results_are_stale()
+  if M.state.mode == 'grep' then return false end
+
+  local shown = file_picker.get_search_metadata().index_generation
+  local current = file_picker.get_index_generation()
+  return shown ~= nil and current ~= nil and shown ~= current
+end
+
+--- Search again when the file watcher changed the index since the displayed
+--- results were ranked, polling while the picker stays open. Only used when
+--- the index events can't be pushed, see `fff.index_events`.
+function M.monitor_index_changes()
+  if not M.state.active then return end
+
+  if results_are_stale() then M.update_results() end
+
+  vim.defer_fn(M.monitor_index_changes, 1000)
+end
+
+--- Refresh the results when a scan finished or the index changed under them
+--- @param events string[]
+function M.on_index_events(events)
+  if not M.state.active then return end
+
+  if vim.tbl_contains(events, 'scan_completed') or results_are_stale() then M.update_results() end
+end
+
 M.enabled_preview = function()
   local preview_state = nil
 
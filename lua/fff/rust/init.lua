@@ -51,13 +51,13 @@ local function try_load_library()
     if stat and stat.type == 'file' then
       local loader, err = package.loadlib(actual_path, 'luaopen_fff_nvim')
       if err then return nil, string.format('Error loading library from %s: %s', actual_path, err) end
-      if loader then return loader() end
+      if loader then return loader(), nil, actual_path end
     end
   end
   return nil, 'No valid library found in any search path'
 end
 
-local backend, load_err = try_load_library()
+local backend, load_err, library_path = try_load_library()
 if not backend or load_err then
   local resolved = {}
   for _, p in ipairs(paths) do
@@ -73,4 +73,7 @@ if not backend or load_err then
   error(err_msg)
 end
 
+-- reported by :checkhealth, the library can come from any of the paths above
+backend.library_path = library_path
+
 return backend
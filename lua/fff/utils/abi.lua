@@ -0,0 +1,79 @@
+local bit = require('bit')
+
+local M = {}
+
+--- Byte-wise string comparison, `<` on strings depends on the locale
+---@param a string
+---@param b string
+---@return boolean
+local function byte_less(a, b)
+  for i = 1, math.min(#a, #b) do
+    local x, y = a:byte(i), b:byte(i)
+    if x ~= y then return x < y end
+  end
+  return #a < #b
+end
+
+--- Names of the functions the Rust module exports to Lua, sorted
+---@param source string contents of crates/fff-nvim/src/lib.rs
+---@return string[]
+function M.export_names(source)
+  local names = {}
+  for name in source:gmatch('exports%.set%(%s*"([^"]*)"') do
+    table.insert(names, name)
+  end
+  table.sort(names, byte_less)
+  return names
+end
+
+--- Version of the arguments and results of the exports, bumped by hand
+---@param source string contents of crates/fff-nvim/src/lib.rs
+---@return string|nil
+function M.abi_version(source) return source:match('const LUA_ABI_VERSION: u32 = (%d+);') end
+
+--- FNV-1a of the version and the newline-joined names, the same hash
+--- crates/fff-nvim/build.rs embeds into the binary
+---@param version string
+---@param names string[]
+---@return string hash 8 lowercase hex digits
+function M.hash(version, names)
+  local data = version .. '\n' .. table.concat(names, '\n')
+  local h = bit.tobit(0x811c9dc5)
+  for i = 1, #data do
+    h = bit.bxor(h, data:byte(i))
+    -- h * 16777619 as 2^24 + 403 so the product stays exact in a double
+    h = bit.tobit(bit.lshift(h, 24) + h * 403)
+  end
+  return bit.tohex(h)
+end
+
+--- ABI hash a binary built from the checkout at `repo_root` has
+---@param repo_root string
+---@return string|nil hash nil when the Rust sources are not checked out
+function M.expected_hash(repo_root)
+  local f = io.open(repo_root .. '/crates/fff-nvim/src/lib.rs', 'r')
+  if not f then return nil end
+  local source = f:read('*a')
+  f:close()
+  local version = M.abi_version(source)
+  if not version then return nil end
+  return M.hash(version, M.export_names(source))
+end
+
+--- ABI hash embedded in the library at `path`, read through the FFI without
+--- running luaopen_fff_nvim
+---@param path string
+---@return string|nil hash nil when the library can't be loaded or predates the hash
+function M.binary_hash(path)
+  local has_ffi, ffi = pcall(require, 'ffi')
+  if not has_ffi then return nil end
+  -- cdef fails when it was declared by an earlier call
+  pcall(ffi.cdef, 'const char *fff_abi_hash(void);')
+
+  local loaded, lib = pcall(ffi.load, path)
+  if not loaded then return nil end
+  local ok, hash = pcall(function() return ffi.string(lib.fff_abi_hash()) end)
+  return ok and hash or nil
+end
+
+return M
@@ -48,6 +48,7 @@ const ffiDefinition = {
       FFIType.bool, // use_unsafe_no_lock
       FFIType.bool, // warmup_mmap_cache
       FFIType.bool, // ai_mode
+      FFIType.bool, // read_only
     ],
     returns: FFIType.ptr,
   },
@@ -67,6 +68,7 @@ const ffiDefinition = {
       FFIType.u32, // page_size
       FFIType.i32, // combo_boost_multiplier
       FFIType.u32, // min_combo_count
+      FFIType.cstring, // search_root
     ],
     returns: FFIType.ptr,
   },
@@ -357,6 +359,7 @@ export function ffiCreate(
   useUnsafeNoLock: boolean,
   warmupMmapCache: boolean,
   aiMode: boolean,
+  readOnly: boolean,
 ): Result<NativeHandle> {
   const library = loadLibrary();
   const resultPtr = library.symbols.fff_create_instance(
@@ -366,6 +369,7 @@ export function ffiCreate(
     useUnsafeNoLock,
     warmupMmapCache,
     aiMode,
+    readOnly,
   );
 
   if (resultPtr === null) {
@@ -404,7 +408,8 @@ export function ffiDestroy(handle: NativeHandle): void {
 // Struct byte offsets (must match #[repr(C)] layout on 64-bit)
 // ---------------------------------------------------------------------------
 
-// FffSearchResult { items: *mut, scores: *mut, count: u32, total_matched: u32, total_files: u32, location: FffLocation }
+// FffSearchResult { items: *mut, scores: *mut, count: u32, total_matched: u32, total_files: u32, location: FffLocation,
+//                   exact_matched: u32, typo_matched: u32, max_typos: u32, suggestion: *mut c_char }
 const SR_ITEMS = 0; // *mut FffFileItem (8)
 const SR_SCORES = 8; // *mut FffScore    (8)
 const SR_COUNT = 16; // u32              (4)
@@ -416,6 +421,10 @@ const SR_LOC_LINE = 32; // i32              (4)
 const SR_LOC_COL = 36; // i32              (4)
 const SR_LOC_END_LINE = 40; // i32           (4)
 const SR_LOC_END_COL = 44; // i32           (4)
+const SR_EXACT_MATCHED = 48; // u32         (4)
+const SR_TYPO_MATCHED = 52; // u32          (4)
+const SR_MAX_TYPOS = 56; // u32             (4 + 4 padding)
+const SR_SUGGESTION = 64; // *mut c_char    (8)
 
 // FffFileItem (80 bytes)
 const FI_PATH = 0; // *mut c_char (8)
@@ -428,7 +437,8 @@ const FI_ACCESS = 48; // i64         (8)
 const FI_MODFR = 56; // i64         (8)
 const FI_TOTAL_FR = 64; // i64         (8)
 const _FI_BINARY = 72; // bool        (1 + 7 pad)
-const FI_SIZE_OF = 80;
+const FI_GIT_SHORT = 80; // *mut c_char (8)
+const FI_SIZE_OF = 88;
 
 // FffScore (48 bytes)
 const SC_TOTAL = 0; // i32         (4)
@@ -458,6 +468,7 @@ function readFileItemStruct(p: number): FileItem {
     relativePath: readCString(read.ptr(pp, FI_RELPATH)) ?? "",
     fileName: readCString(read.ptr(pp, FI_FNAME)) ?? "",
     gitStatus: readCString(read.ptr(pp, FI_GIT)) ?? "",
+    gitStatusShort: readCString(read.ptr(pp, FI_GIT_SHORT)) ?? "",
     size: Number(read.u64(pp, FI_SIZE)),
     modified: Number(read.u64(pp, FI_MODIFIED)),
     accessFrecencyScore: Number(read.i64(pp, FI_ACCESS)),
@@ -504,6 +515,10 @@ function parseSearchResult(resultPtr: Pointer | null): Result<SearchResult> {
   const count = read.u32(hp, SR_COUNT);
   const totalMatched = read.u32(hp, SR_MATCHED);
   const totalFiles = read.u32(hp, SR_TOTAL);
+  const exactMatched = read.u32(hp, SR_EXACT_MATCHED);
+  const typoMatched = read.u32(hp, SR_TYPO_MATCHED);
+  const maxTypos = read.u32(hp, SR_MAX_TYPOS);
+  const suggestion = readCString(read.ptr(hp, SR_SUGGESTION));
   const itemsBase = read.ptr(hp, SR_ITEMS);
   const scoresBase = read.ptr(hp, SR_SCORES);
 
@@ -541,10 +556,21 @@ function parseSearchResult(resultPtr: Pointer | null): Result<SearchResult> {
   // Free native search result
   loadLibrary().symbols.fff_free_search_result(hp);
 
-  const result: SearchResult = { items, scores, totalMatched, totalFiles };
+  const result: SearchResult = {
+    items,
+    scores,
+    totalMatched,
+    totalFiles,
+    exactMatched,
+    typoMatched,
+    maxTypos,
+  };
   if (location) {
     result.location = location;
   }
+  if (suggestion) {
+    result.suggestion = suggestion;
+  }
   return { ok: true, value: result };
 }
 
@@ -559,33 +585,36 @@ const GM_FNAME = 16;
 const GM_GIT = 24;
 const GM_LINE_CONTENT = 32;
 const GM_MATCH_RANGES = 40;
-const GM_CTX_BEFORE = 48;
-const GM_CTX_AFTER = 56;
+const _GM_CAPTURE_RANGES = 48;
+const GM_CTX_BEFORE = 56;
+const GM_CTX_AFTER = 64;
 
 // 8-byte numeric fields
-const GM_SIZE = 64;
-const GM_MODIFIED = 72;
-const GM_TOTAL_FR = 80;
-const GM_ACCESS_FR = 88;
-const GM_MOD_FR = 96;
-const GM_LINE_NUM = 104;
-const GM_BYTE_OFF = 112;
+const GM_SIZE = 72;
+const GM_MODIFIED = 80;
+const GM_TOTAL_FR = 88;
+const GM_ACCESS_FR = 96;
+const GM_MOD_FR = 104;
+const GM_LINE_NUM = 112;
+const GM_BYTE_OFF = 120;
 
 // 4-byte fields
-const GM_COL = 120;
-const GM_MR_COUNT = 124;
-const GM_CTX_B_COUNT = 128;
-const GM_CTX_A_COUNT = 132;
+const GM_COL = 128;
+const GM_MR_COUNT = 132;
+const _GM_CR_COUNT = 136;
+const GM_CTX_B_COUNT = 140;
+const GM_CTX_A_COUNT = 144;
+const _GM_DUPLICATES = 148;
 
 // 2-byte
-const GM_FUZZY_SCORE = 136;
+const GM_FUZZY_SCORE = 152;
 // 1-byte
-const GM_HAS_FUZZY = 138;
-const GM_IS_BINARY = 139;
-const _GM_IS_DEF = 140;
+const GM_HAS_FUZZY = 154;
+const GM_IS_BINARY = 155;
+const _GM_IS_DEF = 156;
 
-// struct size: pad to 8-byte alignment → 144
-const GM_SIZE_OF = 144;
+// struct size: pad to 8-byte alignment → 160
+const GM_SIZE_OF = 160;
 
 // FffGrepResult
 const GR_ITEMS = 0; // *mut FffGrepMatch (8)
@@ -596,6 +625,9 @@ const GR_TOTAL_FILES = 20; // u32 (4)
 const GR_FILTERED = 24; // u32 (4)
 const GR_NEXT_OFFSET = 28; // u32 (4)
 const GR_REGEX_ERR = 32; // *mut c_char (8)
+const _GR_NEXT_PAGE = 40; // *mut c_char (8)
+const GR_FILE_ENTRIES = 48; // *mut FffFileItem (8)
+const GR_FILE_ENTRIES_COUNT = 56; // u32 (4)
 
 // FffMatchRange (8 bytes)
 const MR_START = 0;
@@ -691,11 +723,18 @@ function parseGrepResult(resultPtr: Pointer | null): Result<GrepResult> {
   for (let i = 0; i < count; i++) {
     items.push(readGrepMatchStruct(itemsBase + i * GM_SIZE_OF));
   }
+  const fileEntriesCount = read.u32(hp, GR_FILE_ENTRIES_COUNT);
+  const fileEntriesBase = read.ptr(hp, GR_FILE_ENTRIES);
+  const fileEntries: FileItem[] = [];
+  for (let i = 0; i < fileEntriesCount; i++) {
+    fileEntries.push(readFileItemStruct(fileEntriesBase + i * FI_SIZE_OF));
+  }
 
   loadLibrary().symbols.fff_free_grep_result(hp);
 
   const grepResult: GrepResult = {
     items,
+    fileEntries,
     totalMatched,
     totalFilesSearched,
     totalFiles,
@@ -720,6 +759,7 @@ export function ffiSearch(
   pageSize: number,
   comboBoostMultiplier: number,
   minComboCount: number,
+  searchRoot: string,
 ): Result<SearchResult> {
   const library = loadLibrary();
   const resultPtr = library.symbols.fff_search(
@@ -731,6 +771,7 @@ export function ffiSearch(
     pageSize,
     comboBoostMultiplier,
     minComboCount,
+    ptr(encodeString(searchRoot)),
   );
   return parseSearchResult(resultPtr);
 }
@@ -109,6 +109,7 @@ export class FileFinder {
       options.useUnsafeNoLock ?? false,
       options.warmupMmapCache ?? false,
       options.aiMode ?? false,
+      options.readOnly ?? false,
     );
 
     if (!result.ok) {
@@ -186,6 +187,7 @@ export class FileFinder {
       options?.pageSize ?? 0,
       options?.comboBoostMultiplier ?? 0,
       options?.minComboCount ?? 0,
+      options?.searchRoot ?? "",
     );
   }
 
@@ -38,6 +38,11 @@ export interface InitOptions {
   warmupMmapCache?: boolean;
   /** enables optimizations for AI agent assistants. Provide as true if running via mcp/agent */
   aiMode?: boolean;
+  /**
+   * Never write anything: the databases are not opened or created and file
+   * operations fail. For shared checkouts and CI. (default: false)
+   */
+  readOnly?: boolean;
 }
 
 /**
@@ -56,6 +61,8 @@ export interface SearchOptions {
   pageIndex?: number;
   /** Page size for pagination (default: 100) */
   pageSize?: number;
+  /** Only return files under this directory (absolute or relative to the indexed directory) */
+  searchRoot?: string;
 }
 
 /**
@@ -80,6 +87,8 @@ export interface FileItem {
   totalFrecencyScore: number;
   /** Git status: 'clean', 'modified', 'untracked', 'staged_new', etc. */
   gitStatus: string;
+  /** Compact `git status --short` indicator: 'M', 'A', '??', 'R', ... ('' when clean) */
+  gitStatusShort: string;
 }
 
 /**
@@ -132,8 +141,15 @@ export interface SearchResult {
   totalMatched: number;
   /** Total number of indexed files */
   totalFiles: number;
+  /** Matches containing every query character in order */
+  exactMatched: number;
+  /** Matches that were only found thanks to typo tolerance */
+  typoMatched: number;
+  /** Typo budget used for this query (0 when no fuzzy matching happened) */
+  maxTypos: number;
   /** Location parsed from query (e.g., "file.ts:42:10") */
-  location?: Location;
+  location?: Location;  /** "Did you mean" query when nothing matched */
+  suggestion?: string;
 }
 
 /**
@@ -315,6 +331,8 @@ export interface GrepMatch {
 export interface GrepResult {
   /** Matched items with file and line information. At most `max_matches_per_file`. */
   items: GrepMatch[];
+  /** Files an empty query lists, which has no `items` */
+  fileEntries: FileItem[];
   /** Total number of matches collected (always equal to items.length). */
   totalMatched: number;
   /** Number of files actually opened and searched in this call */
@@ -328,6 +328,7 @@ export function ffiCreate(
   useUnsafeNoLock: boolean,
   warmupMmapCache: boolean,
   aiMode: boolean,
+  readOnly: boolean,
 ): Result<NativeHandle> {
   loadLibrary();
 
@@ -340,8 +341,9 @@ export function ffiCreate(
       DataType.Boolean, // use_unsafe_no_lock
       DataType.Boolean, // warmup_mmap_cache
       DataType.Boolean, // ai_mode
+      DataType.Boolean, // read_only
     ],
-    [basePath, frecencyDbPath, historyDbPath, useUnsafeNoLock, warmupMmapCache, aiMode],
+    [basePath, frecencyDbPath, historyDbPath, useUnsafeNoLock, warmupMmapCache, aiMode, readOnly],
   );
 
   const success = structData.success !== 0;
@@ -391,6 +393,7 @@ const FFF_FILE_ITEM_STRUCT = {
   modification_frecency_score: DataType.I64,
   total_frecency_score: DataType.I64,
   is_binary: DataType.U8,
+  git_status_short: DataType.External,
 };
 
 interface FffFileItemRaw {
@@ -404,6 +407,7 @@ interface FffFileItemRaw {
   modification_frecency_score: number;
   total_frecency_score: number;
   is_binary: number;
+  git_status_short: JsExternal;
 }
 
 const FFF_SCORE_STRUCT = {
@@ -444,6 +448,10 @@ const FFF_SEARCH_RESULT_STRUCT = {
   location_col: DataType.I32,
   location_end_line: DataType.I32,
   location_end_col: DataType.I32,
+  exact_matched: DataType.U32,
+  typo_matched: DataType.U32,
+  max_typos: DataType.U32,
+  suggestion: DataType.External,
 };
 
 interface FffSearchResultRaw {
@@ -457,9 +465,13 @@ interface FffSearchResultRaw {
   location_col: number;
   location_end_line: number;
   location_end_col: number;
+  exact_matched: number;
+  typo_matched: number;
+  max_typos: number;
+  suggestion: JsExternal;
 }
 
-// FffGrepMatch (144 bytes) — ordered by alignment: ptrs, u64s, u32s, u16, bools
+// FffGrepMatch (160 bytes) — ordered by alignment: ptrs, u64s, u32s, u16, bools
 const FFF_GREP_MATCH_STRUCT = {
   path: DataType.External,
   relative_path: DataType.External,
@@ -467,6 +479,7 @@ const FFF_GREP_MATCH_STRUCT = {
   git_status: DataType.External,
   line_content: DataType.External,
   match_ranges: DataType.External,
+  capture_ranges: DataType.External,
   context_before: DataType.External,
   context_after: DataType.External,
   size: DataType.U64,
@@ -478,8 +491,10 @@ const FFF_GREP_MATCH_STRUCT = {
   byte_offset: DataType.U64,
   col: DataType.U32,
   match_ranges_count: DataType.U32,
+  capture_ranges_count: DataType.U32,
   context_before_count: DataType.U32,
   context_after_count: DataType.U32,
+  duplicates: DataType.U32,
   fuzzy_score: DataType.U32, // actually u16 in C, but ffi-rs doesn't have U16 — reads as u32 with padding
   has_fuzzy_score: DataType.U8,
   is_binary: DataType.U8,
@@ -493,6 +508,7 @@ interface FffGrepMatchRaw {
   git_status: JsExternal;
   line_content: JsExternal;
   match_ranges: JsExternal;
+  capture_ranges: JsExternal;
   context_before: JsExternal;
   context_after: JsExternal;
   size: number;
@@ -504,8 +520,10 @@ interface FffGrepMatchRaw {
   byte_offset: number;
   col: number;
   match_ranges_count: number;
+  capture_ranges_count: number;
   context_before_count: number;
   context_after_count: number;
+  duplicates: number;
   fuzzy_score: number;
   has_fuzzy_score: number;
   is_binary: number;
@@ -521,6 +539,9 @@ const FFF_GREP_RESULT_STRUCT = {
   filtered_file_count: DataType.U32,
   next_file_offset: DataType.U32,
   regex_fallback_error: DataType.External,
+  next_page: DataType.External,
+  file_entries: DataType.External,
+  file_entries_count: DataType.U32,
 };
 
 interface FffGrepResultRaw {
@@ -532,6 +553,9 @@ interface FffGrepResultRaw {
   filtered_file_count: number;
   next_file_offset: number;
   regex_fallback_error: JsExternal;
+  next_page: JsExternal;
+  file_entries: JsExternal;
+  file_entries_count: number;
 }
 
 const FFF_MATCH_RANGE_STRUCT = {
@@ -554,6 +578,7 @@ function readFileItemFromRaw(raw: FffFileItemRaw): FileItem {
     relativePath: readCString(raw.relative_path) ?? "",
     fileName: readCString(raw.file_name) ?? "",
     gitStatus: readCString(raw.git_status) ?? "",
+    gitStatusShort: readCString(raw.git_status_short) ?? "",
     size: Number(raw.size),
     modified: Number(raw.modified),
     accessFrecencyScore: Number(raw.access_frecency_score),
@@ -721,6 +746,16 @@ function parseGrepResult(rawPtr: JsExternal): Result<GrepResult> {
     );
     items.push(readGrepMatchFromRaw(rawMatch));
   }
+  const fileEntries: FileItem[] = [];
+  for (let i = 0; i < gr.file_entries_count; i++) {
+    const rawItem = callAccessor<FffFileItemRaw>(
+      "fff_grep_result_get_file_entry",
+      handlePtr,
+      i,
+      FFF_FILE_ITEM_STRUCT,
+    );
+    fileEntries.push(readFileItemFromRaw(rawItem));
+  }
 
   // Free native grep result
   load({
@@ -733,6 +768,7 @@ function parseGrepResult(rawPtr: JsExternal): Result<GrepResult> {
 
   const grepResult: GrepResult = {
     items,
+    fileEntries,
     totalMatched: gr.total_matched,
     totalFilesSearched: gr.total_files_searched,
     totalFiles: gr.total_files,
@@ -831,10 +867,17 @@ function parseSearchResult(rawPtr: JsExternal): Result<SearchResult> {
     scores,
     totalMatched: sr.total_matched,
     totalFiles: sr.total_files,
+    exactMatched: sr.exact_matched,
+    typoMatched: sr.typo_matched,
+    maxTypos: sr.max_typos,
   };
   if (location) {
     result.location = location;
   }
+  const suggestion = readCString(sr.suggestion);
+  if (suggestion) {
+    result.suggestion = suggestion;
+  }
   return { ok: true, value: result };
 }
 
@@ -850,6 +893,7 @@ export function ffiSearch(
   pageSize: number,
   comboBoostMultiplier: number,
   minComboCount: number,
+  searchRoot: string,
 ): Result<SearchResult> {
   loadLibrary();
 
@@ -866,6 +910,7 @@ export function ffiSearch(
       DataType.U32, // page_size
       DataType.I32, // combo_boost_multiplier
       DataType.U32, // min_combo_count
+      DataType.String, // search_root
     ],
     paramsValue: [
       handle,
@@ -876,6 +921,7 @@ export function ffiSearch(
       pageSize,
       comboBoostMultiplier,
       minComboCount,
+      searchRoot,
     ],
     freeResultMemory: false,
   }) as JsExternal;
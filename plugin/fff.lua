@@ -49,6 +49,34 @@ end, {
   desc = 'Find files with FFF (use directory path or search query)',
 })
 
+vim.api.nvim_create_user_command('FFFResume', function() require('fff').resume() end, {
+  desc = 'Reopen FFF with the last query, mode and selection',
+})
+
+vim.api.nvim_create_user_command('FFFSaved', function(opts)
+  if opts.args ~= '' then
+    require('fff').open_saved_search(opts.args)
+  else
+    require('fff').saved_searches({ counts = opts.bang })
+  end
+end, {
+  nargs = '?',
+  bang = true,
+  complete = function()
+    local ok, searches = pcall(function() return require('fff.core').ensure_initialized().list_saved_searches() end)
+    return vim.tbl_map(function(search) return search.name end, ok and searches or {})
+  end,
+  desc = 'Open a saved FFF search by name, or pick one (with ! to show match counts)',
+})
+
+vim.api.nvim_create_user_command('FFFSaveSearch', function(opts)
+  local name, query = opts.args:match('^(%S+)%s*(.*)$')
+  require('fff').save_search(name, query ~= '' and query or nil)
+end, {
+  nargs = '+',
+  desc = 'Save the query of the open picker, or the given query, under a name: FFFSaveSearch {name} [query]',
+})
+
 vim.api.nvim_create_user_command('FFFScan', function() require('fff').scan_files() end, {
   desc = 'Scan files for FFF',
 })
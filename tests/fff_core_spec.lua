@@ -52,8 +52,8 @@ describe('fff.nvim core', function()
       wait_for_scan(10000)
 
       -- Search for "main" which should match main.lua and possibly other files
-      -- Args: query, max_threads, current_file, combo_boost_score_multiplier, min_combo_count, offset, page_size
-      local result = fff_rust.fuzzy_search_files('main', 2, nil, 100, 3, 0, 10)
+      -- Args: query, page_index, page_size, opts
+      local result = fff_rust.fuzzy_search_files('main', 0, 10)
       assert.is_not_nil(result)
       assert.is_not_nil(result.items)
       assert.is_true(#result.items > 0, 'expected search results for "main"')
@@ -69,7 +69,7 @@ describe('fff.nvim core', function()
       assert.is_true(ok)
       wait_for_scan(10000)
 
-      local result = fff_rust.fuzzy_search_files('zzzxxxqqq_no_match_ever', 2, nil, 100, 3, 0, 10)
+      local result = fff_rust.fuzzy_search_files('zzzxxxqqq_no_match_ever', 0, 10)
       assert.is_not_nil(result)
       assert.is_not_nil(result.items)
       assert.are.equal(0, #result.items)
@@ -126,4 +126,140 @@ describe('fff.nvim core', function()
       assert.is_string(health.file_picker.base_path)
     end)
   end)
+
+  describe('query parsing', function()
+    it('should return grep text and constraint spans', function()
+      local query = 'fn search *.rs !test/'
+      local parsed = fff_rust.parse_query(query, true)
+      assert.are.equal('fn search', parsed.grep_text)
+      assert.are.equal(2, #parsed.constraints)
+
+      local extension = parsed.constraints[1]
+      assert.are.equal('extension', extension.kind)
+      assert.are.equal('rs', extension.value)
+      assert.are.equal('*.rs', query:sub(extension.start + 1, extension['end']))
+
+      local negation = parsed.constraints[2]
+      assert.are.equal('not', negation.kind)
+      assert.are.equal('path_segment', negation.inner.kind)
+      assert.are.equal('!test/', query:sub(negation.start + 1, negation['end']))
+    end)
+
+    it('should parse the location of a file query', function()
+      local parsed = fff_rust.parse_query('main.rs:12:4')
+      assert.are.same({ 'main.rs' }, parsed.fuzzy)
+      assert.are.same({ line = 12, col = 4 }, parsed.location)
+    end)
+
+    it('should point at invalid constraint tokens', function()
+      local query = 'main typ:rust !'
+      local diagnostics = fff_rust.validate_query(query)
+      assert.are.equal(2, #diagnostics)
+      assert.are.equal('unknown_key', diagnostics[1].kind)
+      assert.are.equal('typ:rust', query:sub(diagnostics[1].start + 1, diagnostics[1]['end']))
+      assert.are.equal('empty_negation', diagnostics[2].kind)
+
+      assert.are.same({}, fff_rust.validate_query('fn search *.rs', 'regex'))
+      assert.are.equal('invalid_regex', fff_rust.validate_query('fn (search', 'regex')[1].kind)
+    end)
+  end)
+
+  describe('constraint completion', function()
+    it('should complete extensions present in the index', function()
+      local ok = fff_rust.init_file_picker(test_dir)
+      assert.is_true(ok)
+      wait_for_scan(10000)
+
+      local completions = fff_rust.complete_constraint('*.lu')
+      assert.are.equal('*.lua', completions[1].text)
+      assert.are.equal('extension', completions[1].kind)
+      assert.is_true(completions[1].count > 0)
+    end)
+  end)
+
+  describe('grep watch', function()
+    it('should keep matches across refreshes of the same query', function()
+      local ok = fff_rust.init_file_picker(test_dir)
+      assert.is_true(ok)
+      wait_for_scan(10000)
+
+      local first = fff_rust.grep_watch('local M', { mode = 'plain' })
+      assert.is_true(#first.items > 0)
+
+      local second = fff_rust.grep_watch('local M', { mode = 'plain' })
+      assert.are.equal(#first.items, #second.items)
+      assert.is_true(second.total_files_searched < first.total_files_searched)
+    end)
+  end)
+
+  describe('duplicate detection', function()
+    it('should group files with identical content', function()
+      local tmp_dir = vim.fn.tempname()
+      vim.fn.mkdir(tmp_dir .. '/vendor', 'p')
+      vim.fn.writefile({ 'return { copied = true }' }, tmp_dir .. '/config.lua')
+      vim.fn.writefile({ 'return { copied = true }' }, tmp_dir .. '/vendor/config.lua')
+      vim.fn.writefile({ 'return { copied = false }' }, tmp_dir .. '/other.lua')
+
+      local ok = fff_rust.init_file_picker(tmp_dir)
+      assert.is_true(ok)
+      wait_for_scan(10000)
+
+      local groups = fff_rust.find_duplicates(1)
+      vim.fn.delete(tmp_dir, 'rf')
+
+      assert.are.equal(1, #groups)
+      local group = groups[1]
+      local paths = vim.tbl_map(function(file) return file.relative_path end, group.files)
+      table.sort(paths)
+      assert.are.same({ 'config.lua', 'vendor/config.lua' }, paths)
+      assert.are.equal(group.size, group.wasted_bytes)
+      assert.are.equal(16, #group.hash)
+    end)
+  end)
+
+  describe('todo index', function()
+    it('should return todo comments with their location', function()
+      local tmp_dir = vim.fn.tempname()
+      vim.fn.mkdir(tmp_dir, 'p')
+      vim.fn.writefile({ 'local M = {}', '', '-- TODO: cache the parsed config', 'return M' }, tmp_dir .. '/config.lua')
+
+      local ok = fff_rust.init_file_picker(tmp_dir)
+      assert.is_true(ok)
+      wait_for_scan(10000)
+
+      local todos = fff_rust.search_todos('', 20)
+      vim.fn.delete(tmp_dir, 'rf')
+
+      assert.are.equal(1, #todos)
+      assert.are.equal('config.lua', todos[1].relative_path)
+      assert.are.equal(3, todos[1].line_number)
+      assert.are.equal('todo', todos[1].kind)
+      assert.is_truthy(todos[1].text:find('cache the parsed config', 1, true))
+    end)
+  end)
+
+  describe('keyword index', function()
+    it('should search lines of a configured pattern', function()
+      local ok = fff_rust.init_file_picker(test_dir, { keyword_patterns = { requires = "require\\('fff" } })
+      assert.is_true(ok)
+      wait_for_scan(10000)
+
+      local lines = fff_rust.search_keyword('requires', '', 20)
+      assert.is_true(#lines > 0)
+      assert.is_truthy(lines[1].text:find('require', 1, true))
+      assert.has_error(function() fff_rust.search_keyword('missing', '') end)
+    end)
+  end)
+
+  describe('trigram index', function()
+    it('should grep the same files with the index enabled', function()
+      local index_dir = vim.fn.tempname()
+      local ok = fff_rust.init_file_picker(test_dir, { trigram_index_dir = index_dir })
+      assert.is_true(ok)
+      wait_for_scan(10000)
+
+      local result = fff_rust.live_grep('local M', 0, 50, { mode = 'plain' })
+      assert.is_true(#result.items > 0)
+    end)
+  end)
 end)